@@ -311,6 +311,7 @@ pub enum JsTerm {
     BlankNode(JsBlankNode),
     Literal(JsLiteral),
     DefaultGraph(JsDefaultGraph),
+    Quad(Box<JsQuad>),
 }
 
 impl From<JsTerm> for JsValue {
@@ -320,6 +321,7 @@ impl From<JsTerm> for JsValue {
             JsTerm::BlankNode(v) => v.into(),
             JsTerm::Literal(v) => v.into(),
             JsTerm::DefaultGraph(v) => v.into(),
+            JsTerm::Quad(v) => (*v).into(),
         }
     }
 }
@@ -351,12 +353,34 @@ impl From<NamedOrBlankNode> for JsTerm {
     }
 }
 
+impl From<Triple> for JsTerm {
+    fn from(triple: Triple) -> Self {
+        JsTerm::Quad(Box::new(JsQuad {
+            subject: triple.subject.into(),
+            predicate: triple.predicate.into(),
+            object: triple.object.into(),
+            graph_name: JsTerm::DefaultGraph(JsDefaultGraph {}),
+        }))
+    }
+}
+
+impl From<Subject> for JsTerm {
+    fn from(node: Subject) -> Self {
+        match node {
+            Subject::NamedNode(node) => node.into(),
+            Subject::BlankNode(node) => node.into(),
+            Subject::Triple(triple) => (*triple).into(),
+        }
+    }
+}
+
 impl From<Term> for JsTerm {
     fn from(term: Term) -> Self {
         match term {
             Term::NamedNode(node) => node.into(),
             Term::BlankNode(node) => node.into(),
             Term::Literal(literal) => literal.into(),
+            Term::Triple(triple) => (*triple).into(),
         }
     }
 }
@@ -386,6 +410,7 @@ impl TryFrom<JsTerm> for NamedNode {
                 literal.inner
             )),
             JsTerm::DefaultGraph(_) => Err(format_err!("The default graph is not a named node")),
+            JsTerm::Quad(_) => Err(format_err!("A quoted triple is not a named node")),
         }
     }
 }
@@ -404,6 +429,28 @@ impl TryFrom<JsTerm> for NamedOrBlankNode {
             JsTerm::DefaultGraph(_) => {
                 Err(format_err!("The default graph is not a possible RDF term"))
             }
+            JsTerm::Quad(_) => Err(format_err!(
+                "A quoted triple is not a possible named or blank node term"
+            )),
+        }
+    }
+}
+
+impl TryFrom<JsTerm> for Subject {
+    type Error = JsValue;
+
+    fn try_from(value: JsTerm) -> Result<Self, JsValue> {
+        match value {
+            JsTerm::NamedNode(node) => Ok(node.into()),
+            JsTerm::BlankNode(node) => Ok(node.into()),
+            JsTerm::Literal(literal) => Err(format_err!(
+                "The literal {} is not a possible subject",
+                literal.inner
+            )),
+            JsTerm::DefaultGraph(_) => {
+                Err(format_err!("The default graph is not a possible RDF term"))
+            }
+            JsTerm::Quad(quad) => Ok(Triple::try_from(*quad)?.into()),
         }
     }
 }
@@ -419,6 +466,7 @@ impl TryFrom<JsTerm> for Term {
             JsTerm::DefaultGraph(_) => {
                 Err(format_err!("The default graph is not a possible RDF term"))
             }
+            JsTerm::Quad(quad) => Ok(Triple::try_from(*quad)?.into()),
         }
     }
 }
@@ -435,6 +483,7 @@ impl TryFrom<JsTerm> for GraphName {
                 literal.inner
             )),
             JsTerm::DefaultGraph(_) => Ok(GraphName::DefaultGraph),
+            JsTerm::Quad(_) => Err(format_err!("A quoted triple is not a possible graph name")),
         }
     }
 }
@@ -450,6 +499,16 @@ pub struct JsQuad {
 
 #[wasm_bindgen(js_class = Quad)]
 impl JsQuad {
+    #[wasm_bindgen(getter = termType)]
+    pub fn term_type(&self) -> String {
+        "Quad".to_owned()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> String {
+        "".to_owned()
+    }
+
     #[wasm_bindgen(getter = subject)]
     pub fn subject(&self) -> JsValue {
         self.subject.clone().into()
@@ -488,12 +547,29 @@ impl From<Quad> for JsQuad {
     }
 }
 
+impl TryFrom<JsQuad> for Triple {
+    type Error = JsValue;
+
+    fn try_from(quad: JsQuad) -> Result<Self, JsValue> {
+        if quad.graph_name != JsTerm::DefaultGraph(JsDefaultGraph {}) {
+            return Err(format_err!(
+                "A quoted triple should be in the default graph"
+            ));
+        }
+        Ok(Triple::new(
+            Subject::try_from(quad.subject)?,
+            NamedNode::try_from(quad.predicate)?,
+            Term::try_from(quad.object)?,
+        ))
+    }
+}
+
 impl TryFrom<JsQuad> for Quad {
     type Error = JsValue;
 
     fn try_from(quad: JsQuad) -> Result<Self, JsValue> {
         Ok(Quad {
-            subject: NamedOrBlankNode::try_from(quad.subject)?,
+            subject: Subject::try_from(quad.subject)?,
             predicate: NamedNode::try_from(quad.predicate)?,
             object: Term::try_from(quad.object)?,
             graph_name: GraphName::try_from(quad.graph_name)?,
@@ -568,6 +644,7 @@ impl FromJsConverter {
                     }
                 }
                 "DefaultGraph" => Ok(JsTerm::DefaultGraph(JsDefaultGraph {})),
+                "Quad" => Ok(JsTerm::Quad(Box::new(self.to_quad(value)?))),
                 _ => Err(format_err!(
                     "The termType {} is not supported by Oxigraph",
                     term_type
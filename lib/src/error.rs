@@ -1,5 +1,6 @@
 use crate::model::{BlankNodeIdParseError, IriParseError, LanguageTagParseError};
 use crate::sparql::SparqlParseError;
+use crate::store::StorageError;
 use rio_turtle::TurtleError;
 use rio_xml::RdfXmlError;
 use std::error;
@@ -135,6 +136,12 @@ impl From<SparqlParseError> for Error {
     }
 }
 
+impl From<StorageError> for Error {
+    fn from(error: StorageError) -> Self {
+        Self::wrap(error)
+    }
+}
+
 #[cfg(feature = "rocksdb")]
 impl From<rocksdb::Error> for Error {
     fn from(error: rocksdb::Error) -> Self {
@@ -2,8 +2,10 @@
 //!
 //! Its goal is to provide a compliant, safe and fast graph database.
 //!
-//! It currently provides three `Store` implementation providing [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) capability:
+//! It currently provides four `Store` implementation providing [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) capability:
 //! * `MemoryStore`: a simple in memory implementation.
+//! * `HybridStore`: an in memory implementation spilling its strings and its least recently written quads to disk,
+//!   for the datasets slightly larger than the available memory.
 //! * `RocksDbStore`: a file system implementation based on the [RocksDB](https://rocksdb.org/) key-value store.
 //!   It requires the `"rocksdb"` feature to be activated.
 //!   It also requires the [clang](https://clang.llvm.org/) compiler to be installed.
@@ -12,6 +14,22 @@
 //!   Sled is much faster to build than RockDB and does not require a C++ compiler.
 //!   However, Sled is still in developpment, less tested and data load seems much slower than RocksDB.
 //!
+//! The SPARQL queries could do full-text searches on string literals with the `<http://oxigraph.org/ns#matches>` predicate:
+//! `?literal ox:matches "some words"` matches the string literals containing all the given words, ignoring case,
+//! that are the object of a quad of the queried graph, like a triple pattern would do.
+//! By default the search scans the full store.
+//! The `"full-text"` feature makes the `MemoryStore`, `RocksDbStore` and `SledStore` maintain an index of the literals words to avoid these scans.
+//! The on-disk stores build their index when they are opened for the first time with this feature.
+//!
+//! The [GeoSPARQL](https://www.ogc.org/standards/geosparql) `geof:distance`, `geof:sfWithin` and `geof:sfContains` functions
+//! are supported on `geo:wktLiteral` points, line strings and polygons in the default CRS84 reference system.
+//! By default these functions filter the geometries returned by the other patterns of the query.
+//! The `"spatial-index"` feature makes the `MemoryStore` maintain an R-tree of the WKT literals bounding boxes:
+//! the `geof:sfWithin` and `geof:distance` filters against a constant geometry then only read the candidate literals.
+//!
+//! The `"collation"` feature allows to order string literals following the [Unicode Collation Algorithm](https://unicode.org/reports/tr10/)
+//! tailored for a locale using `QueryOptions::with_collation`.
+//!
 //! Usage example with the `MemoryStore`:
 //!
 //! ```
@@ -114,6 +132,7 @@ mod syntax;
 
 pub use error::Error;
 pub type Result<T> = ::std::result::Result<T, Error>;
+pub use crate::store::hybrid::HybridStore;
 pub use crate::store::memory::MemoryStore;
 #[cfg(feature = "rocksdb")]
 pub use crate::store::rocksdb::RocksDbStore;
@@ -4,6 +4,7 @@ use std::error::Error;
 use std::fmt;
 use std::io::Write;
 use std::str;
+use std::sync::Arc;
 
 /// An RDF [blank node](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node).
 ///
@@ -20,7 +21,7 @@ pub struct BlankNode(BlankNodeContent);
 
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 enum BlankNodeContent {
-    Named(String),
+    Named(Arc<str>),
     Anonymous { id: u128, str: [u8; 32] },
 }
 
@@ -50,13 +51,18 @@ impl BlankNode {
             if result.as_str() == id {
                 result
             } else {
-                Self(BlankNodeContent::Named(id))
+                Self(BlankNodeContent::Named(id.into()))
             }
         } else {
-            Self(BlankNodeContent::Named(id))
+            Self(BlankNodeContent::Named(id.into()))
         }
     }
 
+    /// Creates a blank node sharing the buffer of an identifier that is not the representation of a unique numerical id
+    pub(crate) fn new_from_shared(id: Arc<str>) -> Self {
+        Self(BlankNodeContent::Named(id))
+    }
+
     /// Creates a blank node from a unique numerical id
     ///
     /// In most cases, it is much more convenient to create a blank node using `BlankNode::default()`.
@@ -81,7 +87,7 @@ impl BlankNode {
     /// Returns the underlying ID of this blank node
     pub fn into_string(self) -> String {
         match self.0 {
-            BlankNodeContent::Named(id) => id,
+            BlankNodeContent::Named(id) => id.to_string(),
             BlankNodeContent::Anonymous { str, .. } => {
                 let len = str.iter().position(|x| x == &0).unwrap_or(32);
                 str::from_utf8(&str[..len]).unwrap().to_owned()
@@ -0,0 +1,510 @@
+//! Implements the [RDF Dataset Canonicalization](https://www.w3.org/TR/rdf-canon/) algorithm (RDFC-1.0, formerly known as URDNA2015).
+//!
+//! It assigns to the blank nodes of a dataset labels that only depend on the dataset structure.
+//! Two isomorphic datasets get the same canonical form so they could be hashed, signed or diffed deterministically.
+//!
+//! Usage example:
+//! ```
+//! use oxigraph::model::*;
+//! use oxigraph::model::canonicalization::to_canonical_n_quads;
+//!
+//! let ex = NamedNode::new("http://example.com")?;
+//! let a: Dataset = vec![Quad::new(BlankNode::new("a")?, ex.clone(), ex.clone(), None)].into_iter().collect();
+//! let b: Dataset = vec![Quad::new(BlankNode::new("b")?, ex.clone(), ex.clone(), None)].into_iter().collect();
+//! assert_eq!(to_canonical_n_quads(&a)?, "_:c14n0 <http://example.com> <http://example.com> .\n");
+//! assert_eq!(to_canonical_n_quads(&a)?, to_canonical_n_quads(&b)?);
+//! # oxigraph::Result::Ok(())
+//! ```
+//!
+//! The algorithm has an exponential complexity on some datasets with many indistinguishable blank nodes.
+//! To not hang on such [poisoned datasets](https://www.w3.org/TR/rdf-canon/#dataset-poisoning),
+//! the canonicalization fails after `MAX_DEEP_ITERATIONS` iterations of the N-degree hash algorithm.
+//!
+//! The quoted triples are not covered by the specification.
+//! Their blank nodes are canonicalized like the ones of the quad they are in
+//! and they are serialized using the [N-Triples-star](https://w3c.github.io/rdf-star/cg-spec/#n-triples-star) syntax.
+
+use crate::model::*;
+use crate::{Error, Result};
+use digest::Digest;
+use sha2::Sha256;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+
+/// The maximal number of evaluations of the N-degree hash algorithm and of the permutations it explores
+pub const MAX_DEEP_ITERATIONS: usize = 100_000;
+
+/// Returns the canonical label (`c14n0`, `c14n1`...) of each blank node of the dataset
+///
+/// Fails if the canonicalization needs more than `MAX_DEEP_ITERATIONS` deep iterations.
+pub fn canonical_blank_node_labels(dataset: &Dataset) -> Result<HashMap<BlankNode, BlankNode>> {
+    let canonicalizer = Canonicalizer::new(dataset);
+    Ok(canonicalizer
+        .issue_canonical_identifiers()?
+        .issued
+        .into_iter()
+        .map(|(bnode, id)| (bnode.clone(), BlankNode::new_unchecked(&id[2..])))
+        .collect())
+}
+
+/// Returns a copy of the dataset with its blank nodes replaced by their canonical labels
+///
+/// Fails if the canonicalization needs more than `MAX_DEEP_ITERATIONS` deep iterations.
+pub fn canonicalize(dataset: &Dataset) -> Result<Dataset> {
+    let labels = canonical_blank_node_labels(dataset)?;
+    Ok(dataset
+        .iter()
+        .map(|quad| map_quad_blank_nodes(quad, &|bnode| labels[bnode].clone()))
+        .collect())
+}
+
+/// Returns the canonical [N-Quads](https://www.w3.org/TR/n-quads/) serialization of the dataset:
+/// the quads of the canonicalized dataset, one per line, sorted in code point order
+///
+/// Fails if the canonicalization needs more than `MAX_DEEP_ITERATIONS` deep iterations.
+pub fn to_canonical_n_quads(dataset: &Dataset) -> Result<String> {
+    let mut lines: Vec<_> = canonicalize(dataset)?
+        .iter()
+        .map(|quad| format!("{} .\n", quad))
+        .collect();
+    lines.sort();
+    Ok(lines.concat())
+}
+
+struct Canonicalizer<'a> {
+    blank_node_to_quads: HashMap<&'a BlankNode, Vec<(&'a Quad, Vec<(char, &'a BlankNode)>)>>,
+    deep_iterations: Cell<usize>,
+}
+
+impl<'a> Canonicalizer<'a> {
+    fn new(dataset: &'a Dataset) -> Self {
+        let mut blank_node_to_quads: HashMap<&'a BlankNode, Vec<(&'a Quad, _)>> = HashMap::new();
+        for quad in dataset {
+            let mut components = Vec::new();
+            add_subject_blank_nodes(&quad.subject, 's', &mut components);
+            add_term_blank_nodes(&quad.object, 'o', &mut components);
+            if let GraphName::BlankNode(bnode) = &quad.graph_name {
+                components.push(('g', bnode));
+            }
+            for (_, bnode) in &components {
+                let quads = blank_node_to_quads.entry(*bnode).or_default();
+                if quads.last().map_or(true, |(q, _)| !std::ptr::eq(*q, quad)) {
+                    quads.push((quad, components.clone()));
+                }
+            }
+        }
+        Self {
+            blank_node_to_quads,
+            deep_iterations: Cell::new(0),
+        }
+    }
+
+    /// Counts a deep iteration and fails if there have been too many of them
+    fn add_deep_iteration(&self) -> Result<()> {
+        let deep_iterations = self.deep_iterations.get() + 1;
+        if deep_iterations > MAX_DEEP_ITERATIONS {
+            return Err(Error::msg(format!(
+                "The dataset canonicalization has been stopped after {} deep iterations",
+                MAX_DEEP_ITERATIONS
+            )));
+        }
+        self.deep_iterations.set(deep_iterations);
+        Ok(())
+    }
+
+    fn issue_canonical_identifiers(&self) -> Result<IdentifierIssuer<'a>> {
+        let mut canonical_issuer = IdentifierIssuer::new("_:c14n");
+
+        let mut hash_to_blank_nodes: BTreeMap<String, Vec<&'a BlankNode>> = BTreeMap::new();
+        for bnode in self.blank_node_to_quads.keys() {
+            hash_to_blank_nodes
+                .entry(self.hash_first_degree_quads(bnode))
+                .or_default()
+                .push(*bnode);
+        }
+
+        // The blank nodes with a unique first degree hash are labeled in the hash order
+        let mut non_unique = Vec::new();
+        for (_, bnodes) in hash_to_blank_nodes {
+            if bnodes.len() == 1 {
+                canonical_issuer.issue(bnodes[0]);
+            } else {
+                non_unique.push(bnodes);
+            }
+        }
+
+        // The other ones are labeled using the N-degree hashes
+        for bnodes in non_unique {
+            let mut hash_path_list = Vec::new();
+            for bnode in bnodes {
+                if canonical_issuer.get(bnode).is_some() {
+                    continue;
+                }
+                let mut temporary_issuer = IdentifierIssuer::new("_:b");
+                temporary_issuer.issue(bnode);
+                hash_path_list.push(self.hash_n_degree_quads(
+                    bnode,
+                    temporary_issuer,
+                    &canonical_issuer,
+                )?);
+            }
+            hash_path_list.sort_by(|(h1, _), (h2, _)| h1.cmp(h2));
+            for (_, issuer) in hash_path_list {
+                for bnode in issuer.order {
+                    canonical_issuer.issue(bnode);
+                }
+            }
+        }
+        Ok(canonical_issuer)
+    }
+
+    fn hash_first_degree_quads(&self, reference: &BlankNode) -> String {
+        let mut nquads: Vec<_> = self.blank_node_to_quads[reference]
+            .iter()
+            .map(|(quad, _)| {
+                let quad = map_quad_blank_nodes(quad, &|bnode| {
+                    BlankNode::new_unchecked(if bnode == reference { "a" } else { "z" })
+                });
+                format!("{} .\n", quad)
+            })
+            .collect();
+        nquads.sort();
+        hex::encode(Sha256::digest(nquads.concat().as_bytes()))
+    }
+
+    fn hash_related_blank_node(
+        &self,
+        related: &BlankNode,
+        quad: &Quad,
+        issuer: &IdentifierIssuer<'_>,
+        canonical_issuer: &IdentifierIssuer<'_>,
+        position: char,
+    ) -> String {
+        let mut input = position.to_string();
+        if position != 'g' {
+            input.push_str(&quad.predicate.to_string());
+        }
+        if let Some(id) = canonical_issuer
+            .get(related)
+            .or_else(|| issuer.get(related))
+        {
+            input.push_str(id);
+        } else {
+            input.push_str(&self.hash_first_degree_quads(related));
+        }
+        hex::encode(Sha256::digest(input.as_bytes()))
+    }
+
+    fn hash_n_degree_quads(
+        &self,
+        identifier: &'a BlankNode,
+        mut issuer: IdentifierIssuer<'a>,
+        canonical_issuer: &IdentifierIssuer<'a>,
+    ) -> Result<(String, IdentifierIssuer<'a>)> {
+        self.add_deep_iteration()?;
+        let mut hash_to_related_blank_nodes: BTreeMap<String, Vec<&'a BlankNode>> = BTreeMap::new();
+        for (quad, components) in &self.blank_node_to_quads[identifier] {
+            for (position, related) in components {
+                if *related != identifier {
+                    hash_to_related_blank_nodes
+                        .entry(self.hash_related_blank_node(
+                            related,
+                            quad,
+                            &issuer,
+                            canonical_issuer,
+                            *position,
+                        ))
+                        .or_default()
+                        .push(*related);
+                }
+            }
+        }
+
+        let mut data_to_hash = String::new();
+        for (related_hash, blank_node_list) in hash_to_related_blank_nodes {
+            data_to_hash.push_str(&related_hash);
+            let mut chosen_path = String::new();
+            let mut chosen_issuer = None;
+            'permutations: for permutation in Permutations::new(blank_node_list) {
+                self.add_deep_iteration()?;
+                let mut issuer_copy = issuer.clone();
+                let mut path = String::new();
+                let mut recursion_list = Vec::new();
+                for related in permutation {
+                    if let Some(id) = canonical_issuer.get(related) {
+                        path.push_str(id);
+                    } else {
+                        if issuer_copy.get(related).is_none() {
+                            recursion_list.push(related);
+                        }
+                        path.push_str(issuer_copy.issue(related));
+                    }
+                    if !chosen_path.is_empty()
+                        && path.len() >= chosen_path.len()
+                        && path > chosen_path
+                    {
+                        continue 'permutations;
+                    }
+                }
+                for related in recursion_list {
+                    let (result_hash, result_issuer) =
+                        self.hash_n_degree_quads(related, issuer_copy, canonical_issuer)?;
+                    issuer_copy = result_issuer;
+                    path.push_str(issuer_copy.issue(related));
+                    path.push('<');
+                    path.push_str(&result_hash);
+                    path.push('>');
+                    if !chosen_path.is_empty()
+                        && path.len() >= chosen_path.len()
+                        && path > chosen_path
+                    {
+                        continue 'permutations;
+                    }
+                }
+                if chosen_path.is_empty() || path < chosen_path {
+                    chosen_path = path;
+                    chosen_issuer = Some(issuer_copy);
+                }
+            }
+            data_to_hash.push_str(&chosen_path);
+            if let Some(chosen_issuer) = chosen_issuer {
+                issuer = chosen_issuer;
+            }
+        }
+        Ok((hex::encode(Sha256::digest(data_to_hash.as_bytes())), issuer))
+    }
+}
+
+/// Issues identifiers with a given prefix and a counter, remembering the order in which they are issued
+#[derive(Clone)]
+struct IdentifierIssuer<'a> {
+    prefix: &'static str,
+    issued: HashMap<&'a BlankNode, String>,
+    order: Vec<&'a BlankNode>,
+}
+
+impl<'a> IdentifierIssuer<'a> {
+    fn new(prefix: &'static str) -> Self {
+        Self {
+            prefix,
+            issued: HashMap::new(),
+            order: Vec::new(),
+        }
+    }
+
+    fn get(&self, bnode: &BlankNode) -> Option<&str> {
+        self.issued.get(bnode).map(|id| id.as_str())
+    }
+
+    fn issue(&mut self, bnode: &'a BlankNode) -> &str {
+        let prefix = self.prefix;
+        let order = &mut self.order;
+        self.issued
+            .entry(bnode)
+            .or_insert_with(|| {
+                order.push(bnode);
+                format!("{}{}", prefix, order.len() - 1)
+            })
+            .as_str()
+    }
+}
+
+/// Lazily enumerates the permutations of a list in the lexicographic order of the element positions
+struct Permutations<'a> {
+    elements: Vec<&'a BlankNode>,
+    positions: Option<Vec<usize>>,
+}
+
+impl<'a> Permutations<'a> {
+    fn new(elements: Vec<&'a BlankNode>) -> Self {
+        Self {
+            positions: Some((0..elements.len()).collect()),
+            elements,
+        }
+    }
+}
+
+impl<'a> Iterator for Permutations<'a> {
+    type Item = Vec<&'a BlankNode>;
+
+    fn next(&mut self) -> Option<Vec<&'a BlankNode>> {
+        let elements = &self.elements;
+        let positions = self.positions.as_mut()?;
+        let permutation = positions.iter().map(|i| elements[*i]).collect();
+        // We compute the next positions permutation
+        if let Some(pivot) = (1..positions.len())
+            .rev()
+            .find(|i| positions[*i - 1] < positions[*i])
+        {
+            let swap = (pivot..positions.len())
+                .rev()
+                .find(|i| positions[pivot - 1] < positions[*i])
+                .unwrap_or(pivot);
+            positions.swap(pivot - 1, swap);
+            positions[pivot..].reverse();
+        } else {
+            self.positions = None;
+        }
+        Some(permutation)
+    }
+}
+
+fn add_subject_blank_nodes<'a>(
+    subject: &'a Subject,
+    position: char,
+    components: &mut Vec<(char, &'a BlankNode)>,
+) {
+    match subject {
+        Subject::NamedNode(_) => (),
+        Subject::BlankNode(bnode) => components.push((position, bnode)),
+        Subject::Triple(triple) => add_triple_blank_nodes(triple, position, components),
+    }
+}
+
+fn add_term_blank_nodes<'a>(
+    term: &'a Term,
+    position: char,
+    components: &mut Vec<(char, &'a BlankNode)>,
+) {
+    match term {
+        Term::NamedNode(_) | Term::Literal(_) => (),
+        Term::BlankNode(bnode) => components.push((position, bnode)),
+        Term::Triple(triple) => add_triple_blank_nodes(triple, position, components),
+    }
+}
+
+fn add_triple_blank_nodes<'a>(
+    triple: &'a Triple,
+    position: char,
+    components: &mut Vec<(char, &'a BlankNode)>,
+) {
+    add_subject_blank_nodes(&triple.subject, position, components);
+    add_term_blank_nodes(&triple.object, position, components);
+}
+
+fn map_quad_blank_nodes(quad: &Quad, map: &impl Fn(&BlankNode) -> BlankNode) -> Quad {
+    Quad {
+        subject: map_subject_blank_nodes(&quad.subject, map),
+        predicate: quad.predicate.clone(),
+        object: map_term_blank_nodes(&quad.object, map),
+        graph_name: match &quad.graph_name {
+            GraphName::BlankNode(bnode) => map(bnode).into(),
+            graph_name => graph_name.clone(),
+        },
+    }
+}
+
+fn map_triple_blank_nodes(triple: &Triple, map: &impl Fn(&BlankNode) -> BlankNode) -> Triple {
+    Triple {
+        subject: map_subject_blank_nodes(&triple.subject, map),
+        predicate: triple.predicate.clone(),
+        object: map_term_blank_nodes(&triple.object, map),
+    }
+}
+
+fn map_subject_blank_nodes(subject: &Subject, map: &impl Fn(&BlankNode) -> BlankNode) -> Subject {
+    match subject {
+        Subject::BlankNode(bnode) => map(bnode).into(),
+        Subject::Triple(triple) => map_triple_blank_nodes(triple, map).into(),
+        subject => subject.clone(),
+    }
+}
+
+fn map_term_blank_nodes(term: &Term, map: &impl Fn(&BlankNode) -> BlankNode) -> Term {
+    match term {
+        Term::BlankNode(bnode) => map(bnode).into(),
+        Term::Triple(triple) => map_triple_blank_nodes(triple, map).into(),
+        term => term.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonicalization() {
+        let p = NamedNode::new_unchecked("http://example.com/p");
+        // A cycle of blank nodes that are not distinguished by the first degree hashes
+        let cycle = |ids: &[&str]| -> Dataset {
+            (0..ids.len())
+                .map(|i| {
+                    Quad::new(
+                        BlankNode::new_unchecked(ids[i]),
+                        p.clone(),
+                        BlankNode::new_unchecked(ids[(i + 1) % ids.len()]),
+                        None,
+                    )
+                })
+                .collect()
+        };
+        let canonical = to_canonical_n_quads(&cycle(&["a", "b", "c"])).unwrap();
+        assert_eq!(
+            canonical,
+            to_canonical_n_quads(&cycle(&["x", "y", "z"])).unwrap()
+        );
+        assert_eq!(
+            canonical,
+            to_canonical_n_quads(&cycle(&["c", "b", "a"])).unwrap()
+        );
+        assert_eq!(canonical.lines().count(), 3);
+        assert!(canonical.contains("_:c14n0") && canonical.contains("_:c14n2"));
+
+        let labels = canonical_blank_node_labels(&cycle(&["a", "b", "c"])).unwrap();
+        assert_eq!(labels.len(), 3);
+        assert!(canonicalize(&cycle(&["a", "b", "c"]))
+            .unwrap()
+            .iter()
+            .all(|quad| quad.subject.to_string().starts_with("_:c14n")));
+    }
+
+    #[test]
+    fn canonicalization_without_blank_nodes() {
+        let ex = NamedNode::new_unchecked("http://example.com");
+        let dataset: Dataset = vec![
+            Quad::new(ex.clone(), ex.clone(), Literal::from("b"), None),
+            Quad::new(ex.clone(), ex.clone(), Literal::from("a"), ex.clone()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            to_canonical_n_quads(&dataset).unwrap(),
+            "<http://example.com> <http://example.com> \"a\" <http://example.com> .\n<http://example.com> <http://example.com> \"b\" .\n"
+        );
+    }
+
+    #[test]
+    fn permutations() {
+        let nodes: Vec<_> = (0..4)
+            .map(|i| BlankNode::new_unchecked(i.to_string()))
+            .collect();
+        let permutations: Vec<_> = Permutations::new(nodes.iter().collect()).collect();
+        assert_eq!(permutations.len(), 24);
+        assert_eq!(permutations[0], nodes.iter().collect::<Vec<_>>());
+        assert_eq!(permutations[23], nodes.iter().rev().collect::<Vec<_>>());
+        for (i, permutation) in permutations.iter().enumerate() {
+            assert!(!permutations[i + 1..].contains(permutation));
+        }
+        assert_eq!(Permutations::new(Vec::new()).count(), 1);
+    }
+
+    #[test]
+    fn canonicalization_of_poisoned_dataset() {
+        // A clique of indistinguishable blank nodes that has a factorial canonicalization cost
+        let p = NamedNode::new_unchecked("http://example.com/p");
+        let nodes: Vec<_> = (0..10)
+            .map(|i| BlankNode::new_unchecked(format!("b{}", i)))
+            .collect();
+        let dataset: Dataset = nodes
+            .iter()
+            .flat_map(|s| {
+                nodes
+                    .iter()
+                    .filter(move |o| *o != s)
+                    .map(|o| Quad::new(s.clone(), p.clone(), o.clone(), None))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert!(to_canonical_n_quads(&dataset).is_err());
+    }
+}
@@ -0,0 +1,542 @@
+use crate::model::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::hash_set;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+
+/// A simple in-memory [RDF graph](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-graph).
+///
+/// It is meant to manipulate small RDF documents without building a store.
+/// The equality is a set equality: two graphs with the same triples up to a renaming of the blank nodes are not equal.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+///
+/// let ex = NamedNode::new("http://example.com")?;
+/// let triple = Triple::new(ex.clone(), ex.clone(), ex.clone());
+///
+/// let mut graph = Graph::new();
+/// graph.insert(triple.clone());
+/// assert!(graph.contains(&triple));
+///
+/// // Pattern matching
+/// let subject = Subject::from(ex.clone());
+/// let results: Vec<_> = graph.triples_for_pattern(Some(&subject), None, None).collect();
+/// assert_eq!(vec![&triple], results);
+///
+/// // Set operations
+/// let other: Graph = vec![Triple::new(ex.clone(), ex.clone(), Literal::from(1))].into_iter().collect();
+/// assert_eq!(graph.union(&other).len(), 2);
+/// assert!(graph.intersection(&other).is_empty());
+/// assert_eq!(graph.difference(&other), graph);
+/// # oxigraph::Result::Ok(())
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Graph {
+    triples: HashSet<Triple>,
+}
+
+impl Graph {
+    /// Creates a new empty graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a triple to the graph. Returns `true` if the triple was not already in the graph.
+    pub fn insert(&mut self, triple: Triple) -> bool {
+        self.triples.insert(triple)
+    }
+
+    /// Removes a triple from the graph. Returns `true` if the triple was in the graph.
+    pub fn remove(&mut self, triple: &Triple) -> bool {
+        self.triples.remove(triple)
+    }
+
+    /// Checks if the graph contains a given triple
+    pub fn contains(&self, triple: &Triple) -> bool {
+        self.triples.contains(triple)
+    }
+
+    /// Returns the number of triples in the graph
+    pub fn len(&self) -> usize {
+        self.triples.len()
+    }
+
+    /// Returns if the graph is empty
+    pub fn is_empty(&self) -> bool {
+        self.triples.is_empty()
+    }
+
+    /// Removes all the triples of the graph
+    pub fn clear(&mut self) {
+        self.triples.clear()
+    }
+
+    /// Returns all the triples of the graph
+    pub fn iter(&self) -> impl Iterator<Item = &Triple> {
+        self.triples.iter()
+    }
+
+    /// Retrieves triples with a filter on each triple component
+    ///
+    /// The triples are found by iterating on the whole graph.
+    pub fn triples_for_pattern<'a>(
+        &'a self,
+        subject: Option<&'a Subject>,
+        predicate: Option<&'a NamedNode>,
+        object: Option<&'a Term>,
+    ) -> impl Iterator<Item = &'a Triple> + 'a {
+        self.triples.iter().filter(move |triple| {
+            subject.map_or(true, |s| *s == triple.subject)
+                && predicate.map_or(true, |p| *p == triple.predicate)
+                && object.map_or(true, |o| *o == triple.object)
+        })
+    }
+
+    /// Returns a new graph with the triples that are in this graph or in `other`
+    pub fn union(&self, other: &Self) -> Self {
+        self.triples.union(&other.triples).cloned().collect()
+    }
+
+    /// Returns a new graph with the triples that are both in this graph and in `other`
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.triples.intersection(&other.triples).cloned().collect()
+    }
+
+    /// Returns a new graph with the triples that are in this graph but not in `other`
+    pub fn difference(&self, other: &Self) -> Self {
+        self.triples.difference(&other.triples).cloned().collect()
+    }
+
+    /// Returns if the current graph is [isomorphic](https://www.w3.org/TR/rdf11-concepts/#dfn-graph-isomorphism) with another one.
+    ///
+    /// It is implemented using the canonicalization approach presented in
+    /// [Canonical Forms for Isomorphic and Equivalent RDF Graphs: Algorithms for Leaning and Labelling Blank Nodes, Aidan Hogan, 2017](http://aidanhogan.com/docs/rdf-canonicalisation.pdf)
+    /// like `MemoryStore::is_isomorphic`. The blank nodes inside of quoted triples are taken into account.
+    ///
+    /// Warning: This implementation worst-case complexity is in O(b!) with b the number of blank node node in the input graphs.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    ///
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let a: Graph = vec![Triple::new(BlankNode::new("a")?, ex.clone(), ex.clone())].into_iter().collect();
+    /// let b: Graph = vec![Triple::new(BlankNode::new("b")?, ex.clone(), ex.clone())].into_iter().collect();
+    /// assert_ne!(a, b);
+    /// assert!(a.is_isomorphic_to(&b));
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn is_isomorphic_to(&self, other: &Self) -> bool {
+        self.len() == other.len() && iso_canonicalize(self) == iso_canonicalize(other)
+    }
+}
+
+/// Formats the graph like in [N-Triples](https://www.w3.org/TR/n-triples/), one triple per line
+impl fmt::Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for triple in &self.triples {
+            writeln!(f, "{} .", triple)?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoIterator for Graph {
+    type Item = Triple;
+    type IntoIter = hash_set::IntoIter<Triple>;
+
+    fn into_iter(self) -> hash_set::IntoIter<Triple> {
+        self.triples.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Graph {
+    type Item = &'a Triple;
+    type IntoIter = hash_set::Iter<'a, Triple>;
+
+    fn into_iter(self) -> hash_set::Iter<'a, Triple> {
+        self.triples.iter()
+    }
+}
+
+impl FromIterator<Triple> for Graph {
+    fn from_iter<I: IntoIterator<Item = Triple>>(iter: I) -> Self {
+        Self {
+            triples: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Triple> for Graph {
+    fn extend<I: IntoIterator<Item = Triple>>(&mut self, iter: I) {
+        self.triples.extend(iter)
+    }
+}
+
+/// A simple in-memory [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset).
+///
+/// It is meant to manipulate small RDF documents without building a store.
+/// The equality is a set equality: two datasets with the same quads up to a renaming of the blank nodes are not equal.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+///
+/// let ex = NamedNode::new("http://example.com")?;
+/// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone());
+///
+/// let mut dataset = Dataset::new();
+/// dataset.insert(quad.clone());
+/// assert!(dataset.contains(&quad));
+///
+/// // Extraction of a graph
+/// let graph = dataset.graph(&ex.clone().into());
+/// assert!(graph.contains(&quad.clone().into()));
+///
+/// // Set operations
+/// let other: Dataset = vec![Quad::new(ex.clone(), ex.clone(), ex.clone(), None)].into_iter().collect();
+/// assert_eq!(dataset.union(&other).len(), 2);
+/// assert!(dataset.intersection(&other).is_empty());
+/// assert_eq!(dataset.difference(&other), dataset);
+/// # oxigraph::Result::Ok(())
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct Dataset {
+    quads: HashSet<Quad>,
+}
+
+impl Dataset {
+    /// Creates a new empty dataset
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a quad to the dataset. Returns `true` if the quad was not already in the dataset.
+    pub fn insert(&mut self, quad: Quad) -> bool {
+        self.quads.insert(quad)
+    }
+
+    /// Removes a quad from the dataset. Returns `true` if the quad was in the dataset.
+    pub fn remove(&mut self, quad: &Quad) -> bool {
+        self.quads.remove(quad)
+    }
+
+    /// Checks if the dataset contains a given quad
+    pub fn contains(&self, quad: &Quad) -> bool {
+        self.quads.contains(quad)
+    }
+
+    /// Returns the number of quads in the dataset
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    /// Returns if the dataset is empty
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    /// Removes all the quads of the dataset
+    pub fn clear(&mut self) {
+        self.quads.clear()
+    }
+
+    /// Returns all the quads of the dataset
+    pub fn iter(&self) -> impl Iterator<Item = &Quad> {
+        self.quads.iter()
+    }
+
+    /// Retrieves quads with a filter on each quad component
+    ///
+    /// The quads are found by iterating on the whole dataset.
+    pub fn quads_for_pattern<'a>(
+        &'a self,
+        subject: Option<&'a Subject>,
+        predicate: Option<&'a NamedNode>,
+        object: Option<&'a Term>,
+        graph_name: Option<&'a GraphName>,
+    ) -> impl Iterator<Item = &'a Quad> + 'a {
+        self.quads.iter().filter(move |quad| {
+            subject.map_or(true, |s| *s == quad.subject)
+                && predicate.map_or(true, |p| *p == quad.predicate)
+                && object.map_or(true, |o| *o == quad.object)
+                && graph_name.map_or(true, |g| *g == quad.graph_name)
+        })
+    }
+
+    /// Returns the triples of a graph of the dataset
+    pub fn graph(&self, graph_name: &GraphName) -> Graph {
+        self.quads_for_pattern(None, None, None, Some(graph_name))
+            .cloned()
+            .map(Triple::from)
+            .collect()
+    }
+
+    /// Returns a new dataset with the quads that are in this dataset or in `other`
+    pub fn union(&self, other: &Self) -> Self {
+        self.quads.union(&other.quads).cloned().collect()
+    }
+
+    /// Returns a new dataset with the quads that are both in this dataset and in `other`
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.quads.intersection(&other.quads).cloned().collect()
+    }
+
+    /// Returns a new dataset with the quads that are in this dataset but not in `other`
+    pub fn difference(&self, other: &Self) -> Self {
+        self.quads.difference(&other.quads).cloned().collect()
+    }
+}
+
+/// Formats the dataset like in [N-Quads](https://www.w3.org/TR/n-quads/), one quad per line
+impl fmt::Display for Dataset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for quad in &self.quads {
+            writeln!(f, "{} .", quad)?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoIterator for Dataset {
+    type Item = Quad;
+    type IntoIter = hash_set::IntoIter<Quad>;
+
+    fn into_iter(self) -> hash_set::IntoIter<Quad> {
+        self.quads.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Dataset {
+    type Item = &'a Quad;
+    type IntoIter = hash_set::Iter<'a, Quad>;
+
+    fn into_iter(self) -> hash_set::Iter<'a, Quad> {
+        self.quads.iter()
+    }
+}
+
+impl FromIterator<Quad> for Dataset {
+    fn from_iter<I: IntoIterator<Item = Quad>>(iter: I) -> Self {
+        Self {
+            quads: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<Quad> for Dataset {
+    fn extend<I: IntoIterator<Item = Quad>>(&mut self, iter: I) {
+        self.quads.extend(iter)
+    }
+}
+
+impl From<Graph> for Dataset {
+    /// Builds a dataset with the triples of the graph in its default graph
+    fn from(graph: Graph) -> Self {
+        graph
+            .into_iter()
+            .map(|triple| triple.in_graph(GraphName::DefaultGraph))
+            .collect()
+    }
+}
+
+// Isomorphism implementation
+
+type BlankNodeHashes<'a> = HashMap<&'a BlankNode, u64>;
+
+fn iso_canonicalize(graph: &Graph) -> Vec<String> {
+    let mut neighbourhoods: HashMap<&BlankNode, Vec<&Triple>> = HashMap::new();
+    for triple in graph {
+        let mut bnodes = HashSet::new();
+        add_triple_blank_nodes(triple, &mut bnodes);
+        for bnode in bnodes {
+            neighbourhoods.entry(bnode).or_default().push(triple);
+        }
+    }
+    let (hashes, partition) = hash_blank_nodes(
+        &neighbourhoods,
+        neighbourhoods.keys().map(|bnode| (*bnode, 0)).collect(),
+    );
+    distinguish(graph, &neighbourhoods, &hashes, &partition)
+}
+
+fn distinguish<'a>(
+    graph: &Graph,
+    neighbourhoods: &HashMap<&'a BlankNode, Vec<&'a Triple>>,
+    hashes: &BlankNodeHashes<'a>,
+    partition: &[(u64, Vec<&'a BlankNode>)],
+) -> Vec<String> {
+    let b_prime = partition
+        .iter()
+        .find_map(|(_, b)| if b.len() > 1 { Some(b) } else { None });
+    if let Some(b_prime) = b_prime {
+        b_prime
+            .iter()
+            .map(|b| {
+                let mut hash_prime = hashes.clone();
+                hash_prime.insert(*b, hash_tuple((hash_prime[b], 22)));
+                let (hash_prime_prime, partition_prime) =
+                    hash_blank_nodes(neighbourhoods, hash_prime);
+                distinguish(graph, neighbourhoods, &hash_prime_prime, &partition_prime)
+            })
+            .min()
+            .unwrap_or_else(Vec::new)
+    } else {
+        label(graph, hashes)
+    }
+}
+
+fn hash_blank_nodes<'a>(
+    neighbourhoods: &HashMap<&'a BlankNode, Vec<&'a Triple>>,
+    mut hashes: BlankNodeHashes<'a>,
+) -> (BlankNodeHashes<'a>, Vec<(u64, Vec<&'a BlankNode>)>) {
+    let mut to_hash = Vec::new();
+    let mut partition: HashMap<u64, Vec<&BlankNode>> = HashMap::new();
+    let mut partition_len = 0;
+    loop {
+        let mut new_hashes = HashMap::new();
+        for (bnode, old_hash) in &hashes {
+            for triple in &neighbourhoods[bnode] {
+                to_hash.push(hash_triple(triple, &hashes, bnode));
+            }
+            to_hash.sort_unstable();
+            let hash = hash_tuple((old_hash, &to_hash));
+            to_hash.clear();
+            new_hashes.insert(*bnode, hash);
+            partition.entry(hash).or_default().push(*bnode);
+        }
+        if partition.len() == partition_len {
+            let mut partition: Vec<_> = partition.into_iter().collect();
+            partition.sort_by(|(h1, b1), (h2, b2)| (b1.len(), h1).cmp(&(b2.len(), h2)));
+            return (hashes, partition);
+        }
+        hashes = new_hashes;
+        partition_len = partition.len();
+        partition.clear();
+    }
+}
+
+/// Hashes a triple with the current blank node hashes, `current` being marked as the blank node we are computing the hash of
+fn hash_triple(triple: &Triple, hashes: &BlankNodeHashes<'_>, current: &BlankNode) -> u64 {
+    hash_tuple((
+        hash_subject(&triple.subject, hashes, current),
+        &triple.predicate,
+        hash_term(&triple.object, hashes, current),
+    ))
+}
+
+fn hash_subject(subject: &Subject, hashes: &BlankNodeHashes<'_>, current: &BlankNode) -> u64 {
+    match subject {
+        Subject::NamedNode(node) => hash_tuple((0, node)),
+        Subject::BlankNode(node) => hash_tuple((1, hashes[node], node == current)),
+        Subject::Triple(triple) => hash_tuple((3, hash_triple(triple, hashes, current))),
+    }
+}
+
+fn hash_term(term: &Term, hashes: &BlankNodeHashes<'_>, current: &BlankNode) -> u64 {
+    match term {
+        Term::NamedNode(node) => hash_tuple((0, node)),
+        Term::BlankNode(node) => hash_tuple((1, hashes[node], node == current)),
+        Term::Literal(literal) => hash_tuple((2, literal)),
+        Term::Triple(triple) => hash_tuple((3, hash_triple(triple, hashes, current))),
+    }
+}
+
+fn hash_tuple(v: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    v.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn add_triple_blank_nodes<'a>(triple: &'a Triple, bnodes: &mut HashSet<&'a BlankNode>) {
+    match &triple.subject {
+        Subject::NamedNode(_) => (),
+        Subject::BlankNode(node) => {
+            bnodes.insert(node);
+        }
+        Subject::Triple(triple) => add_triple_blank_nodes(triple, bnodes),
+    }
+    match &triple.object {
+        Term::NamedNode(_) | Term::Literal(_) => (),
+        Term::BlankNode(node) => {
+            bnodes.insert(node);
+        }
+        Term::Triple(triple) => add_triple_blank_nodes(triple, bnodes),
+    }
+}
+
+fn label(graph: &Graph, hashes: &BlankNodeHashes<'_>) -> Vec<String> {
+    let mut data: Vec<_> = graph
+        .iter()
+        .map(|triple| label_triple(triple, hashes).to_string())
+        .collect();
+    data.sort();
+    data
+}
+
+fn label_triple(triple: &Triple, hashes: &BlankNodeHashes<'_>) -> Triple {
+    Triple {
+        subject: match &triple.subject {
+            Subject::BlankNode(node) => BlankNode::new_from_unique_id(hashes[node]).into(),
+            Subject::Triple(triple) => label_triple(triple, hashes).into(),
+            subject => subject.clone(),
+        },
+        predicate: triple.predicate.clone(),
+        object: match &triple.object {
+            Term::BlankNode(node) => BlankNode::new_from_unique_id(hashes[node]).into(),
+            Term::Triple(triple) => label_triple(triple, hashes).into(),
+            object => object.clone(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn isomorphism() {
+        let ex = NamedNode::new_unchecked("http://example.com");
+        let cycle = |ids: &[&str]| -> Graph {
+            (0..ids.len())
+                .map(|i| {
+                    Triple::new(
+                        BlankNode::new_unchecked(ids[i]),
+                        ex.clone(),
+                        BlankNode::new_unchecked(ids[(i + 1) % ids.len()]),
+                    )
+                })
+                .collect()
+        };
+        // Blank nodes that could only be distinguished by the backtracking
+        assert!(cycle(&["a", "b", "c"]).is_isomorphic_to(&cycle(&["c", "a", "b"])));
+        assert!(cycle(&["a", "b", "c"]).is_isomorphic_to(&cycle(&["x", "y", "z"])));
+        // Two cycles of size 2 are not a cycle of size 4
+        let mut two_cycles = cycle(&["a", "b"]);
+        two_cycles.extend(cycle(&["c", "d"]));
+        assert!(!two_cycles.is_isomorphic_to(&cycle(&["a", "b", "c", "d"])));
+
+        // Blank nodes in quoted triples
+        let quoted = |id: &str| -> Graph {
+            vec![Triple::new(
+                Triple::new(BlankNode::new_unchecked(id), ex.clone(), ex.clone()),
+                ex.clone(),
+                BlankNode::new_unchecked(id),
+            )]
+            .into_iter()
+            .collect()
+        };
+        assert!(quoted("a").is_isomorphic_to(&quoted("b")));
+        let mut other = Graph::new();
+        other.insert(Triple::new(
+            Triple::new(BlankNode::new_unchecked("a"), ex.clone(), ex.clone()),
+            ex.clone(),
+            BlankNode::new_unchecked("b"),
+        ));
+        assert!(!quoted("a").is_isomorphic_to(&other));
+    }
+}
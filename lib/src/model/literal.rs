@@ -7,9 +7,13 @@ use rio_api::model as rio;
 use std::borrow::Cow;
 use std::fmt;
 use std::option::Option;
+use std::str::FromStr;
+use std::sync::Arc;
 
 /// An RDF [literal](https://www.w3.org/TR/rdf11-concepts/#dfn-literal)
 ///
+/// The lexical form and the language tag are stored in reference counted buffers: cloning a `Literal` does not copy them.
+///
 /// The default string formatter is returning a N-Triples, Turtle and SPARQL compatible representation:
 /// ```
 /// # use oxilangtag::LanguageTagParseError;
@@ -37,21 +41,37 @@ pub struct Literal(LiteralContent);
 
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 enum LiteralContent {
-    String(String),
-    LanguageTaggedString { value: String, language: String },
-    TypedLiteral { value: String, datatype: NamedNode },
+    String(Arc<str>),
+    LanguageTaggedString {
+        value: Arc<str>,
+        language: Arc<str>,
+    },
+    TypedLiteral {
+        value: Arc<str>,
+        datatype: NamedNode,
+    },
 }
 
 impl Literal {
     /// Builds an RDF [simple literal](https://www.w3.org/TR/rdf11-concepts/#dfn-simple-literal)
     pub fn new_simple_literal(value: impl Into<String>) -> Self {
+        let value: String = value.into();
         Literal(LiteralContent::String(value.into()))
     }
 
     /// Builds an RDF [literal](https://www.w3.org/TR/rdf11-concepts/#dfn-literal) with a [datatype](https://www.w3.org/TR/rdf11-concepts/#dfn-datatype-iri)
     pub fn new_typed_literal(value: impl Into<String>, datatype: impl Into<NamedNode>) -> Self {
-        let value = value.into();
-        let datatype = datatype.into();
+        let value: String = value.into();
+        Self::new_shared_typed_literal(value.into(), datatype.into())
+    }
+
+    /// Builds an RDF [simple literal](https://www.w3.org/TR/rdf11-concepts/#dfn-simple-literal) sharing the buffer of its value
+    pub(crate) fn new_shared_simple_literal(value: Arc<str>) -> Self {
+        Literal(LiteralContent::String(value))
+    }
+
+    /// Builds an RDF [literal](https://www.w3.org/TR/rdf11-concepts/#dfn-literal) with a [datatype](https://www.w3.org/TR/rdf11-concepts/#dfn-datatype-iri) sharing the buffer of its value
+    pub(crate) fn new_shared_typed_literal(value: Arc<str>, datatype: NamedNode) -> Self {
         Literal(if datatype == *xsd::STRING {
             LiteralContent::String(value)
         } else {
@@ -64,11 +84,12 @@ impl Literal {
         value: impl Into<String>,
         language: impl Into<String>,
     ) -> Result<Self, LanguageTagParseError> {
+        let value: String = value.into();
         let mut language = language.into();
         language.make_ascii_lowercase();
         Ok(Literal(LiteralContent::LanguageTaggedString {
             value: value.into(),
-            language: LanguageTag::parse(language)?.into_inner(),
+            language: LanguageTag::parse(language)?.into_inner().into(),
         }))
     }
 
@@ -84,10 +105,16 @@ impl Literal {
         value: impl Into<String>,
         language: impl Into<String>,
     ) -> Self {
-        Literal(LiteralContent::LanguageTaggedString {
-            value: value.into(),
-            language: language.into(),
-        })
+        let value: String = value.into();
+        let language: String = language.into();
+        Self::new_shared_language_tagged_literal(value.into(), language.into())
+    }
+
+    /// Builds an RDF [language-tagged string](https://www.w3.org/TR/rdf11-concepts/#dfn-language-tagged-string) sharing the buffers of its value and of its language tag
+    ///
+    /// The language tag should be valid and lowercase like for [`new_language_tagged_literal_unchecked`](#method.new_language_tagged_literal_unchecked).
+    pub(crate) fn new_shared_language_tagged_literal(value: Arc<str>, language: Arc<str>) -> Self {
+        Literal(LiteralContent::LanguageTaggedString { value, language })
     }
 
     /// The literal [lexical form](https://www.w3.org/TR/rdf11-concepts/#dfn-lexical-form)
@@ -105,7 +132,7 @@ impl Literal {
     /// They are normalized to lowercase by this implementation.
     pub fn language(&self) -> Option<&str> {
         match &self.0 {
-            LiteralContent::LanguageTaggedString { language, .. } => Some(language),
+            LiteralContent::LanguageTaggedString { language, .. } => Some(language.as_ref()),
             _ => None,
         }
     }
@@ -136,11 +163,172 @@ impl Literal {
     /// Extract components from this literal
     pub fn destruct(self) -> (String, Option<NamedNode>, Option<String>) {
         match self.0 {
-            LiteralContent::String(s) => (s, None, None),
+            LiteralContent::String(s) => (s.to_string(), None, None),
             LiteralContent::LanguageTaggedString { value, language } => {
-                (value, None, Some(language))
+                (value.to_string(), None, Some(language.to_string()))
+            }
+            LiteralContent::TypedLiteral { value, datatype } => {
+                (value.to_string(), Some(datatype), None)
             }
-            LiteralContent::TypedLiteral { value, datatype } => (value, Some(datatype), None),
+        }
+    }
+
+    /// Returns the value of an [xsd:boolean](https://www.w3.org/TR/xmlschema11-2/#boolean) literal
+    ///
+    /// It returns `None` if the literal has another datatype or an invalid lexical form.
+    pub fn to_bool(&self) -> Option<bool> {
+        if self.datatype() != &*xsd::BOOLEAN {
+            return None;
+        }
+        match self.value() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an [xsd:integer](https://www.w3.org/TR/xmlschema11-2/#integer) literal, or of a literal with one of its derived datatypes like xsd:int or xsd:long
+    ///
+    /// It returns `None` if the literal has another datatype, an invalid lexical form or a value not fitting in an `i64`.
+    ///
+    /// ```
+    /// use oxigraph::model::Literal;
+    ///
+    /// assert_eq!(Literal::from(42).to_i64(), Some(42));
+    /// assert_eq!(Literal::from("42").to_i64(), None);
+    /// ```
+    pub fn to_i64(&self) -> Option<i64> {
+        match self.datatype().as_str() {
+            "http://www.w3.org/2001/XMLSchema#integer"
+            | "http://www.w3.org/2001/XMLSchema#byte"
+            | "http://www.w3.org/2001/XMLSchema#short"
+            | "http://www.w3.org/2001/XMLSchema#int"
+            | "http://www.w3.org/2001/XMLSchema#long"
+            | "http://www.w3.org/2001/XMLSchema#unsignedByte"
+            | "http://www.w3.org/2001/XMLSchema#unsignedShort"
+            | "http://www.w3.org/2001/XMLSchema#unsignedInt"
+            | "http://www.w3.org/2001/XMLSchema#unsignedLong"
+            | "http://www.w3.org/2001/XMLSchema#positiveInteger"
+            | "http://www.w3.org/2001/XMLSchema#negativeInteger"
+            | "http://www.w3.org/2001/XMLSchema#nonPositiveInteger"
+            | "http://www.w3.org/2001/XMLSchema#nonNegativeInteger" => self.value().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an [xsd:float](https://www.w3.org/TR/xmlschema11-2/#float) literal
+    pub fn to_f32(&self) -> Option<f32> {
+        self.parse_if_datatype(&xsd::FLOAT)
+    }
+
+    /// Returns the value of an [xsd:double](https://www.w3.org/TR/xmlschema11-2/#double) or [xsd:float](https://www.w3.org/TR/xmlschema11-2/#float) literal
+    pub fn to_f64(&self) -> Option<f64> {
+        if self.datatype() == &*xsd::FLOAT {
+            self.to_f32().map(f64::from)
+        } else {
+            self.parse_if_datatype(&xsd::DOUBLE)
+        }
+    }
+
+    /// Returns the value of an [xsd:decimal](https://www.w3.org/TR/xmlschema11-2/#decimal) literal
+    pub fn to_decimal(&self) -> Option<Decimal> {
+        self.parse_if_datatype(&xsd::DECIMAL)
+    }
+
+    /// Returns the value of an [xsd:date](https://www.w3.org/TR/xmlschema11-2/#date) literal
+    pub fn to_date(&self) -> Option<Date> {
+        self.parse_if_datatype(&xsd::DATE)
+    }
+
+    /// Returns the value of an [xsd:time](https://www.w3.org/TR/xmlschema11-2/#time) literal
+    pub fn to_time(&self) -> Option<Time> {
+        self.parse_if_datatype(&xsd::TIME)
+    }
+
+    /// Returns the value of an [xsd:dateTime](https://www.w3.org/TR/xmlschema11-2/#dateTime) or [xsd:dateTimeStamp](https://www.w3.org/TR/xmlschema11-2/#dateTimeStamp) literal
+    pub fn to_date_time(&self) -> Option<DateTime> {
+        match self.datatype().as_str() {
+            "http://www.w3.org/2001/XMLSchema#dateTime"
+            | "http://www.w3.org/2001/XMLSchema#dateTimeStamp" => self.value().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an [xsd:duration](https://www.w3.org/TR/xmlschema11-2/#duration) literal
+    pub fn to_duration(&self) -> Option<Duration> {
+        self.parse_if_datatype(&xsd::DURATION)
+    }
+
+    /// Returns the value of an [xsd:yearMonthDuration](https://www.w3.org/TR/xmlschema11-2/#yearMonthDuration) literal
+    pub fn to_year_month_duration(&self) -> Option<YearMonthDuration> {
+        self.parse_if_datatype(&xsd::YEAR_MONTH_DURATION)
+    }
+
+    /// Returns the value of an [xsd:dayTimeDuration](https://www.w3.org/TR/xmlschema11-2/#dayTimeDuration) literal
+    pub fn to_day_time_duration(&self) -> Option<DayTimeDuration> {
+        self.parse_if_datatype(&xsd::DAY_TIME_DURATION)
+    }
+
+    /// Returns the literal with the canonical lexical form of its value
+    ///
+    /// The numeric, boolean, date, time and duration literals are supported.
+    /// The other literals and the ones with an invalid lexical form are returned unchanged.
+    ///
+    /// ```
+    /// use oxigraph::model::Literal;
+    /// use oxigraph::model::vocab::xsd;
+    ///
+    /// assert_eq!(
+    ///     Literal::new_typed_literal("+042", xsd::INTEGER.clone()).canonicalize(),
+    ///     Literal::new_typed_literal("42", xsd::INTEGER.clone())
+    /// );
+    /// assert_eq!(
+    ///     Literal::new_typed_literal("1", xsd::BOOLEAN.clone()).canonicalize(),
+    ///     Literal::from(true)
+    /// );
+    /// ```
+    pub fn canonicalize(&self) -> Self {
+        if let Some(value) = self.canonical_value() {
+            Literal(LiteralContent::TypedLiteral {
+                value: value.into(),
+                datatype: self.datatype().clone(),
+            })
+        } else {
+            self.clone()
+        }
+    }
+
+    fn canonical_value(&self) -> Option<String> {
+        Some(match self.datatype().as_str() {
+            "http://www.w3.org/2001/XMLSchema#boolean" => self.to_bool()?.to_string(),
+            "http://www.w3.org/2001/XMLSchema#float" => {
+                Some(self.to_f32()?).filter(|v| v.is_finite())?.to_string()
+            }
+            "http://www.w3.org/2001/XMLSchema#double" => {
+                Some(self.to_f64()?).filter(|v| v.is_finite())?.to_string()
+            }
+            "http://www.w3.org/2001/XMLSchema#decimal" => self.to_decimal()?.to_string(),
+            "http://www.w3.org/2001/XMLSchema#date" => self.to_date()?.to_string(),
+            "http://www.w3.org/2001/XMLSchema#time" => self.to_time()?.to_string(),
+            "http://www.w3.org/2001/XMLSchema#dateTime"
+            | "http://www.w3.org/2001/XMLSchema#dateTimeStamp" => self.to_date_time()?.to_string(),
+            "http://www.w3.org/2001/XMLSchema#duration" => self.to_duration()?.to_string(),
+            "http://www.w3.org/2001/XMLSchema#yearMonthDuration" => {
+                self.to_year_month_duration()?.to_string()
+            }
+            "http://www.w3.org/2001/XMLSchema#dayTimeDuration" => {
+                self.to_day_time_duration()?.to_string()
+            }
+            // xsd:integer and its derived datatypes
+            _ => self.to_i64()?.to_string(),
+        })
+    }
+
+    fn parse_if_datatype<T: FromStr>(&self, datatype: &NamedNode) -> Option<T> {
+        if self.datatype() == datatype {
+            self.value().parse().ok()
+        } else {
+            None
         }
     }
 }
@@ -159,20 +347,20 @@ impl<'a> From<&'a str> for Literal {
 
 impl From<String> for Literal {
     fn from(value: String) -> Self {
-        Literal(LiteralContent::String(value))
+        Literal(LiteralContent::String(value.into()))
     }
 }
 
 impl<'a> From<Cow<'a, str>> for Literal {
     fn from(value: Cow<'a, str>) -> Self {
-        Literal(LiteralContent::String(value.into()))
+        Literal(LiteralContent::String(Arc::from(&*value)))
     }
 }
 
 impl From<bool> for Literal {
     fn from(value: bool) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::BOOLEAN.clone(),
         })
     }
@@ -181,7 +369,7 @@ impl From<bool> for Literal {
 impl From<i128> for Literal {
     fn from(value: i128) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::INTEGER.clone(),
         })
     }
@@ -190,7 +378,7 @@ impl From<i128> for Literal {
 impl From<i64> for Literal {
     fn from(value: i64) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::INTEGER.clone(),
         })
     }
@@ -199,7 +387,7 @@ impl From<i64> for Literal {
 impl From<i32> for Literal {
     fn from(value: i32) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::INTEGER.clone(),
         })
     }
@@ -208,7 +396,7 @@ impl From<i32> for Literal {
 impl From<i16> for Literal {
     fn from(value: i16) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::INTEGER.clone(),
         })
     }
@@ -217,7 +405,7 @@ impl From<i16> for Literal {
 impl From<u64> for Literal {
     fn from(value: u64) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::INTEGER.clone(),
         })
     }
@@ -226,7 +414,7 @@ impl From<u64> for Literal {
 impl From<u32> for Literal {
     fn from(value: u32) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::INTEGER.clone(),
         })
     }
@@ -235,7 +423,7 @@ impl From<u32> for Literal {
 impl From<u16> for Literal {
     fn from(value: u16) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::INTEGER.clone(),
         })
     }
@@ -244,7 +432,7 @@ impl From<u16> for Literal {
 impl From<f32> for Literal {
     fn from(value: f32) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::FLOAT.clone(),
         })
     }
@@ -253,7 +441,7 @@ impl From<f32> for Literal {
 impl From<f64> for Literal {
     fn from(value: f64) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::DOUBLE.clone(),
         })
     }
@@ -262,7 +450,7 @@ impl From<f64> for Literal {
 impl From<Decimal> for Literal {
     fn from(value: Decimal) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::DECIMAL.clone(),
         })
     }
@@ -271,7 +459,7 @@ impl From<Decimal> for Literal {
 impl From<Date> for Literal {
     fn from(value: Date) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::DATE.clone(),
         })
     }
@@ -280,7 +468,7 @@ impl From<Date> for Literal {
 impl From<Time> for Literal {
     fn from(value: Time) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::TIME.clone(),
         })
     }
@@ -289,7 +477,7 @@ impl From<Time> for Literal {
 impl From<DateTime> for Literal {
     fn from(value: DateTime) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::DATE_TIME.clone(),
         })
     }
@@ -298,7 +486,7 @@ impl From<DateTime> for Literal {
 impl From<Duration> for Literal {
     fn from(value: Duration) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::DURATION.clone(),
         })
     }
@@ -307,7 +495,7 @@ impl From<Duration> for Literal {
 impl From<YearMonthDuration> for Literal {
     fn from(value: YearMonthDuration) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::YEAR_MONTH_DURATION.clone(),
         })
     }
@@ -315,7 +503,7 @@ impl From<YearMonthDuration> for Literal {
 impl From<DayTimeDuration> for Literal {
     fn from(value: DayTimeDuration) -> Self {
         Literal(LiteralContent::TypedLiteral {
-            value: value.to_string(),
+            value: value.to_string().into(),
             datatype: xsd::DAY_TIME_DURATION.clone(),
         })
     }
@@ -341,3 +529,84 @@ impl<'a> From<&'a Literal> for rio::Literal<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonicalize() {
+        assert_eq!(
+            Literal::new_typed_literal("0042", xsd::UNSIGNED_BYTE.clone()).canonicalize(),
+            Literal::new_typed_literal("42", xsd::UNSIGNED_BYTE.clone())
+        );
+        assert_eq!(
+            Literal::new_typed_literal("01.50", xsd::DECIMAL.clone()).canonicalize(),
+            Literal::new_typed_literal("1.5", xsd::DECIMAL.clone())
+        );
+        assert_eq!(
+            Literal::new_typed_literal("1.5E0", xsd::DOUBLE.clone()).canonicalize(),
+            Literal::from(1.5_f64)
+        );
+        assert_eq!(
+            Literal::new_typed_literal("2020-01-01T00:00:00+00:00", xsd::DATE_TIME.clone())
+                .canonicalize(),
+            Literal::new_typed_literal("2020-01-01T00:00:00Z", xsd::DATE_TIME.clone())
+        );
+        assert_eq!(
+            Literal::new_typed_literal("PT24H", xsd::DAY_TIME_DURATION.clone()).canonicalize(),
+            Literal::new_typed_literal("P1D", xsd::DAY_TIME_DURATION.clone())
+        );
+
+        // Unsupported or invalid literals are kept as they are
+        let invalid = Literal::new_typed_literal("foo", xsd::INTEGER.clone());
+        assert_eq!(invalid.canonicalize(), invalid);
+        assert_eq!(Literal::from("01").canonicalize(), Literal::from("01"));
+        let infinity = Literal::new_typed_literal("INF", xsd::DOUBLE.clone());
+        assert_eq!(infinity.canonicalize(), infinity);
+    }
+
+    #[test]
+    fn typed_value_accessors() {
+        assert_eq!(Literal::from(true).to_bool(), Some(true));
+        assert_eq!(
+            Literal::new_typed_literal("0", xsd::BOOLEAN.clone()).to_bool(),
+            Some(false)
+        );
+        assert_eq!(Literal::from("true").to_bool(), None);
+
+        assert_eq!(Literal::from(-12_i64).to_i64(), Some(-12));
+        assert_eq!(
+            Literal::new_typed_literal("42", xsd::UNSIGNED_BYTE.clone()).to_i64(),
+            Some(42)
+        );
+        assert_eq!(
+            Literal::new_typed_literal("4a", xsd::INTEGER.clone()).to_i64(),
+            None
+        );
+
+        assert_eq!(Literal::from(1.5_f64).to_f64(), Some(1.5));
+        assert_eq!(Literal::from(1.5_f32).to_f64(), Some(1.5));
+        assert_eq!(Literal::from(1.5_f64).to_f32(), None);
+        assert_eq!(Literal::from(1_i64).to_f64(), None);
+
+        assert_eq!(
+            Literal::new_typed_literal("1.50", xsd::DECIMAL.clone()).to_decimal(),
+            Some("1.5".parse().unwrap())
+        );
+        assert_eq!(
+            Literal::new_typed_literal("2020-01-01T00:00:00Z", xsd::DATE_TIME.clone())
+                .to_date_time(),
+            Some("2020-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            Literal::new_typed_literal("2020-01-01", xsd::DATE.clone()).to_date_time(),
+            None
+        );
+        assert_eq!(
+            Literal::new_typed_literal("P1D", xsd::DAY_TIME_DURATION.clone())
+                .to_day_time_duration(),
+            Some("P1D".parse().unwrap())
+        );
+    }
+}
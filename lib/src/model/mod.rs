@@ -3,15 +3,19 @@
 //! Inspired by [RDF/JS](https://rdf.js.org/data-model-spec/) and [Apache Commons RDF](http://commons.apache.org/proper/commons-rdf/)
 
 mod blank_node;
+pub mod canonicalization;
+mod dataset;
 mod literal;
 mod named_node;
+pub mod skolemization;
 mod triple;
 pub mod vocab;
 pub(crate) mod xsd;
 
 pub use crate::model::blank_node::{BlankNode, BlankNodeIdParseError};
+pub use crate::model::dataset::{Dataset, Graph};
 pub use crate::model::literal::Literal;
 pub use crate::model::named_node::NamedNode;
-pub use crate::model::triple::{GraphName, NamedOrBlankNode, Quad, Term, Triple};
+pub use crate::model::triple::{GraphName, NamedOrBlankNode, Quad, Subject, Term, Triple};
 pub use oxilangtag::LanguageTagParseError;
 pub use oxiri::IriParseError;
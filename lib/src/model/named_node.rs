@@ -1,9 +1,12 @@
 use oxiri::{Iri, IriParseError};
 use rio_api::model as rio;
 use std::fmt;
+use std::sync::Arc;
 
 /// An RDF [IRI](https://www.w3.org/TR/rdf11-concepts/#dfn-iri)
 ///
+/// The IRI is stored in a reference counted buffer: cloning a `NamedNode` does not copy the IRI.
+///
 /// The default string formatter is returning a N-Triples, Turtle and SPARQL compatible representation:
 /// ```
 /// use oxigraph::model::NamedNode;
@@ -15,7 +18,7 @@ use std::fmt;
 /// ```
 #[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Hash)]
 pub struct NamedNode {
-    iri: String,
+    iri: Arc<str>,
 }
 
 impl NamedNode {
@@ -39,15 +42,21 @@ impl NamedNode {
     ///
     /// Except if you really know what you do, you should use [`parse`](#method.parse).
     pub fn new_unchecked(iri: impl Into<String>) -> Self {
+        let iri: String = iri.into();
         Self { iri: iri.into() }
     }
 
+    /// Builds an RDF [IRI](https://www.w3.org/TR/rdf11-concepts/#dfn-iri) sharing the buffer of an already validated IRI
+    pub(crate) fn new_from_shared(iri: Arc<str>) -> Self {
+        Self { iri }
+    }
+
     pub fn as_str(&self) -> &str {
-        self.iri.as_str()
+        &self.iri
     }
 
     pub fn into_string(self) -> String {
-        self.iri
+        self.iri.to_string()
     }
 }
 
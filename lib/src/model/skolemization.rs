@@ -0,0 +1,199 @@
+//! Implements the [skolemization](https://www.w3.org/TR/rdf11-concepts/#section-skolemization) of blank nodes.
+//!
+//! The blank nodes are replaced by IRIs of the form `{authority}/.well-known/genid/{id}`.
+//! These IRIs survive the systems that rename the blank nodes and could be mapped back to the original blank nodes.
+//!
+//! Usage example:
+//! ```
+//! use oxigraph::model::*;
+//! use oxigraph::model::skolemization::Skolemizer;
+//!
+//! let skolemizer = Skolemizer::new("http://example.com")?;
+//! let ex = NamedNode::new("http://example.com/p")?;
+//! let triple = Triple::new(BlankNode::new("a")?, ex.clone(), ex.clone());
+//!
+//! let skolemized = skolemizer.skolemize_triple(&triple);
+//! assert_eq!(skolemized.subject, NamedNode::new("http://example.com/.well-known/genid/a")?.into());
+//! assert_eq!(skolemizer.deskolemize_triple(&skolemized), triple);
+//! # oxigraph::Result::Ok(())
+//! ```
+
+use crate::model::*;
+
+/// Replaces the blank nodes by skolem IRIs with a given authority and maps these IRIs back to blank nodes.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Skolemizer {
+    prefix: String,
+}
+
+impl Skolemizer {
+    /// Builds a skolemizer minting the IRIs `{authority}/.well-known/genid/{id}`
+    ///
+    /// The authority should be an absolute IRI like `http://example.com`.
+    pub fn new(authority: &str) -> Result<Self, IriParseError> {
+        let prefix = format!("{}/.well-known/genid/", authority.trim_end_matches('/'));
+        NamedNode::new(prefix.as_str())?;
+        Ok(Self { prefix })
+    }
+
+    /// Returns the prefix shared by all the skolem IRIs
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the skolem IRI of a blank node
+    pub fn skolemize_blank_node(&self, blank_node: &BlankNode) -> NamedNode {
+        NamedNode::new_unchecked(format!("{}{}", self.prefix, blank_node.as_str()))
+    }
+
+    /// Returns the blank node of a skolem IRI minted by this skolemizer or `None` if the IRI is not one of them
+    pub fn deskolemize_named_node(&self, named_node: &NamedNode) -> Option<BlankNode> {
+        let id = named_node.as_str().strip_prefix(self.prefix.as_str())?;
+        BlankNode::new(id).ok()
+    }
+
+    /// Replaces the blank nodes of the triple, including the ones of its quoted triples
+    pub fn skolemize_triple(&self, triple: &Triple) -> Triple {
+        Triple {
+            subject: self.skolemize_subject(&triple.subject),
+            predicate: triple.predicate.clone(),
+            object: self.skolemize_term(&triple.object),
+        }
+    }
+
+    /// Replaces the skolem IRIs of the triple by blank nodes, including the ones of its quoted triples
+    ///
+    /// The predicates are kept as they are because they could not be blank nodes.
+    pub fn deskolemize_triple(&self, triple: &Triple) -> Triple {
+        Triple {
+            subject: self.deskolemize_subject(&triple.subject),
+            predicate: triple.predicate.clone(),
+            object: self.deskolemize_term(&triple.object),
+        }
+    }
+
+    /// Replaces the blank nodes of the quad, including its graph name
+    pub fn skolemize_quad(&self, quad: &Quad) -> Quad {
+        Quad {
+            subject: self.skolemize_subject(&quad.subject),
+            predicate: quad.predicate.clone(),
+            object: self.skolemize_term(&quad.object),
+            graph_name: match &quad.graph_name {
+                GraphName::BlankNode(bnode) => self.skolemize_blank_node(bnode).into(),
+                graph_name => graph_name.clone(),
+            },
+        }
+    }
+
+    /// Replaces the skolem IRIs of the quad by blank nodes, including its graph name
+    pub fn deskolemize_quad(&self, quad: &Quad) -> Quad {
+        Quad {
+            subject: self.deskolemize_subject(&quad.subject),
+            predicate: quad.predicate.clone(),
+            object: self.deskolemize_term(&quad.object),
+            graph_name: match &quad.graph_name {
+                GraphName::NamedNode(node) => self
+                    .deskolemize_named_node(node)
+                    .map_or_else(|| node.clone().into(), GraphName::from),
+                graph_name => graph_name.clone(),
+            },
+        }
+    }
+
+    /// Returns a copy of the graph without blank nodes
+    pub fn skolemize_graph(&self, graph: &Graph) -> Graph {
+        graph.iter().map(|t| self.skolemize_triple(t)).collect()
+    }
+
+    /// Returns a copy of the graph with the skolem IRIs replaced by blank nodes
+    pub fn deskolemize_graph(&self, graph: &Graph) -> Graph {
+        graph.iter().map(|t| self.deskolemize_triple(t)).collect()
+    }
+
+    /// Returns a copy of the dataset without blank nodes
+    pub fn skolemize_dataset(&self, dataset: &Dataset) -> Dataset {
+        dataset.iter().map(|q| self.skolemize_quad(q)).collect()
+    }
+
+    /// Returns a copy of the dataset with the skolem IRIs replaced by blank nodes
+    pub fn deskolemize_dataset(&self, dataset: &Dataset) -> Dataset {
+        dataset.iter().map(|q| self.deskolemize_quad(q)).collect()
+    }
+
+    fn skolemize_subject(&self, subject: &Subject) -> Subject {
+        match subject {
+            Subject::BlankNode(bnode) => self.skolemize_blank_node(bnode).into(),
+            Subject::Triple(triple) => self.skolemize_triple(triple).into(),
+            subject => subject.clone(),
+        }
+    }
+
+    fn deskolemize_subject(&self, subject: &Subject) -> Subject {
+        match subject {
+            Subject::NamedNode(node) => self
+                .deskolemize_named_node(node)
+                .map_or_else(|| node.clone().into(), Subject::from),
+            Subject::Triple(triple) => self.deskolemize_triple(triple).into(),
+            subject => subject.clone(),
+        }
+    }
+
+    fn skolemize_term(&self, term: &Term) -> Term {
+        match term {
+            Term::BlankNode(bnode) => self.skolemize_blank_node(bnode).into(),
+            Term::Triple(triple) => self.skolemize_triple(triple).into(),
+            term => term.clone(),
+        }
+    }
+
+    fn deskolemize_term(&self, term: &Term) -> Term {
+        match term {
+            Term::NamedNode(node) => self
+                .deskolemize_named_node(node)
+                .map_or_else(|| node.clone().into(), Term::from),
+            Term::Triple(triple) => self.deskolemize_triple(triple).into(),
+            term => term.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn skolemization_round_trip() {
+        let skolemizer = Skolemizer::new("http://example.com/").unwrap();
+        assert_eq!(skolemizer.prefix(), "http://example.com/.well-known/genid/");
+
+        let p = NamedNode::new_unchecked("http://example.com/p");
+        let a = BlankNode::new_unchecked("a");
+        let quoted = Triple::new(a.clone(), p.clone(), BlankNode::new_unchecked("b"));
+        let dataset: Dataset = vec![
+            Quad::new(a.clone(), p.clone(), quoted, None),
+            Quad::new(p.clone(), p.clone(), Literal::from("a"), a.clone()),
+        ]
+        .into_iter()
+        .collect();
+
+        let skolemized = skolemizer.skolemize_dataset(&dataset);
+        assert!(!skolemized.to_string().contains("_:"));
+        assert!(skolemized.contains(&Quad::new(
+            p.clone(),
+            p.clone(),
+            Literal::from("a"),
+            NamedNode::new_unchecked("http://example.com/.well-known/genid/a"),
+        )));
+        assert_eq!(skolemizer.deskolemize_dataset(&skolemized), dataset);
+
+        // IRIs that are not skolem IRIs of this skolemizer are kept
+        let other = NamedNode::new_unchecked("http://example.org/.well-known/genid/a");
+        assert_eq!(skolemizer.deskolemize_named_node(&other), None);
+        assert_eq!(skolemizer.deskolemize_named_node(&p), None);
+    }
+
+    #[test]
+    fn invalid_authority() {
+        assert!(Skolemizer::new("not an iri").is_err());
+    }
+}
@@ -57,13 +57,81 @@ impl<'a> From<&'a NamedOrBlankNode> for rio::NamedOrBlankNode<'a> {
     }
 }
 
+/// The possible subjects of a triple.
+/// It is the union of [IRIs](https://www.w3.org/TR/rdf11-concepts/#dfn-iri), [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node) and [RDF-star quoted triples](https://w3c.github.io/rdf-star/cg-spec/#dfn-quoted).
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum Subject {
+    NamedNode(NamedNode),
+    BlankNode(BlankNode),
+    Triple(Box<Triple>),
+}
+
+impl Subject {
+    pub fn is_named_node(&self) -> bool {
+        matches!(self, Subject::NamedNode(_))
+    }
+
+    pub fn is_blank_node(&self) -> bool {
+        matches!(self, Subject::BlankNode(_))
+    }
+
+    pub fn is_triple(&self) -> bool {
+        matches!(self, Subject::Triple(_))
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::NamedNode(node) => node.fmt(f),
+            Subject::BlankNode(node) => node.fmt(f),
+            Subject::Triple(triple) => write!(f, "<< {} >>", triple),
+        }
+    }
+}
+
+impl From<NamedNode> for Subject {
+    fn from(node: NamedNode) -> Self {
+        Subject::NamedNode(node)
+    }
+}
+
+impl From<BlankNode> for Subject {
+    fn from(node: BlankNode) -> Self {
+        Subject::BlankNode(node)
+    }
+}
+
+impl From<Triple> for Subject {
+    fn from(triple: Triple) -> Self {
+        Subject::Triple(Box::new(triple))
+    }
+}
+
+impl From<Box<Triple>> for Subject {
+    fn from(triple: Box<Triple>) -> Self {
+        Subject::Triple(triple)
+    }
+}
+
+impl From<NamedOrBlankNode> for Subject {
+    fn from(node: NamedOrBlankNode) -> Self {
+        match node {
+            NamedOrBlankNode::NamedNode(node) => node.into(),
+            NamedOrBlankNode::BlankNode(node) => node.into(),
+        }
+    }
+}
+
 /// An RDF [term](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-term)
-/// It is the union of [IRIs](https://www.w3.org/TR/rdf11-concepts/#dfn-iri), [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node) and [literals](https://www.w3.org/TR/rdf11-concepts/#dfn-literal).
+/// It is the union of [IRIs](https://www.w3.org/TR/rdf11-concepts/#dfn-iri), [blank nodes](https://www.w3.org/TR/rdf11-concepts/#dfn-blank-node), [literals](https://www.w3.org/TR/rdf11-concepts/#dfn-literal)
+/// and [RDF-star quoted triples](https://w3c.github.io/rdf-star/cg-spec/#dfn-quoted).
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum Term {
     NamedNode(NamedNode),
     BlankNode(BlankNode),
     Literal(Literal),
+    Triple(Box<Triple>),
 }
 
 impl Term {
@@ -87,6 +155,13 @@ impl Term {
             _ => false,
         }
     }
+
+    pub fn is_triple(&self) -> bool {
+        match self {
+            Term::Triple(_) => true,
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Term {
@@ -95,6 +170,7 @@ impl fmt::Display for Term {
             Term::NamedNode(node) => node.fmt(f),
             Term::BlankNode(node) => node.fmt(f),
             Term::Literal(literal) => literal.fmt(f),
+            Term::Triple(triple) => write!(f, "<< {} >>", triple),
         }
     }
 }
@@ -117,6 +193,18 @@ impl From<Literal> for Term {
     }
 }
 
+impl From<Triple> for Term {
+    fn from(triple: Triple) -> Self {
+        Term::Triple(Box::new(triple))
+    }
+}
+
+impl From<Box<Triple>> for Term {
+    fn from(triple: Box<Triple>) -> Self {
+        Term::Triple(triple)
+    }
+}
+
 impl From<NamedOrBlankNode> for Term {
     fn from(resource: NamedOrBlankNode) -> Self {
         match resource {
@@ -126,12 +214,12 @@ impl From<NamedOrBlankNode> for Term {
     }
 }
 
-impl<'a> From<&'a Term> for rio::Term<'a> {
-    fn from(node: &'a Term) -> Self {
-        match node {
-            Term::NamedNode(node) => rio::NamedNode::from(node).into(),
-            Term::BlankNode(node) => rio::BlankNode::from(node).into(),
-            Term::Literal(node) => rio::Literal::from(node).into(),
+impl From<Subject> for Term {
+    fn from(subject: Subject) -> Self {
+        match subject {
+            Subject::NamedNode(node) => node.into(),
+            Subject::BlankNode(node) => node.into(),
+            Subject::Triple(triple) => Term::Triple(triple),
         }
     }
 }
@@ -140,7 +228,7 @@ impl<'a> From<&'a Term> for rio::Term<'a> {
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct Triple {
     /// The [subject](https://www.w3.org/TR/rdf11-concepts/#dfn-subject) of this triple
-    pub subject: NamedOrBlankNode,
+    pub subject: Subject,
 
     /// The [predicate](https://www.w3.org/TR/rdf11-concepts/#dfn-predicate) of this triple
     pub predicate: NamedNode,
@@ -152,7 +240,7 @@ pub struct Triple {
 impl Triple {
     /// Builds an RDF [triple](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-triple)
     pub fn new(
-        subject: impl Into<NamedOrBlankNode>,
+        subject: impl Into<Subject>,
         predicate: impl Into<NamedNode>,
         object: impl Into<Term>,
     ) -> Self {
@@ -164,12 +252,12 @@ impl Triple {
     }
 
     #[deprecated(note = "Use directly the `subject` field")]
-    pub const fn subject(&self) -> &NamedOrBlankNode {
+    pub const fn subject(&self) -> &Subject {
         &self.subject
     }
 
     #[deprecated(note = "Use directly the `subject` field")]
-    pub fn subject_owned(self) -> NamedOrBlankNode {
+    pub fn subject_owned(self) -> Subject {
         self.subject
     }
 
@@ -204,19 +292,10 @@ impl Triple {
     }
 }
 
+/// Formats the triple like in [N-Triples](https://www.w3.org/TR/n-triples/), using the [N-Triples-star](https://w3c.github.io/rdf-star/cg-spec/#n-triples-star) syntax for the quoted triples
 impl fmt::Display for Triple {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        rio::Triple::from(self).fmt(f)
-    }
-}
-
-impl<'a> From<&'a Triple> for rio::Triple<'a> {
-    fn from(node: &'a Triple) -> Self {
-        rio::Triple {
-            subject: (&node.subject).into(),
-            predicate: (&node.predicate).into(),
-            object: (&node.object).into(),
-        }
+        write!(f, "{} {} {}", self.subject, self.predicate, self.object)
     }
 }
 
@@ -317,7 +396,7 @@ impl<'a> From<&'a GraphName> for Option<rio::NamedOrBlankNode<'a>> {
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct Quad {
     /// The [subject](https://www.w3.org/TR/rdf11-concepts/#dfn-subject) of this triple
-    pub subject: NamedOrBlankNode,
+    pub subject: Subject,
 
     /// The [predicate](https://www.w3.org/TR/rdf11-concepts/#dfn-predicate) of this triple
     pub predicate: NamedNode,
@@ -332,7 +411,7 @@ pub struct Quad {
 impl Quad {
     /// Builds an RDF [triple](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-triple) in a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset)
     pub fn new(
-        subject: impl Into<NamedOrBlankNode>,
+        subject: impl Into<Subject>,
         predicate: impl Into<NamedNode>,
         object: impl Into<Term>,
         graph_name: impl Into<GraphName>,
@@ -346,12 +425,12 @@ impl Quad {
     }
 
     #[deprecated(note = "Use directly the `subject` field")]
-    pub const fn subject(&self) -> &NamedOrBlankNode {
+    pub const fn subject(&self) -> &Subject {
         &self.subject
     }
 
     #[deprecated(note = "Use directly the `subject` field")]
-    pub fn subject_owned(self) -> NamedOrBlankNode {
+    pub fn subject_owned(self) -> Subject {
         self.subject
     }
 
@@ -391,25 +470,19 @@ impl Quad {
     }
 
     #[deprecated(note = "Use directly the struct fields")]
-    pub fn destruct(self) -> (NamedOrBlankNode, NamedNode, Term, GraphName) {
+    pub fn destruct(self) -> (Subject, NamedNode, Term, GraphName) {
         (self.subject, self.predicate, self.object, self.graph_name)
     }
 }
 
+/// Formats the quad like in [N-Quads](https://www.w3.org/TR/n-quads/), using the [N-Triples-star](https://w3c.github.io/rdf-star/cg-spec/#n-triples-star) syntax for the quoted triples
 impl fmt::Display for Quad {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        rio::Quad::from(self).fmt(f)
-    }
-}
-
-impl<'a> From<&'a Quad> for rio::Quad<'a> {
-    fn from(node: &'a Quad) -> Self {
-        rio::Quad {
-            subject: (&node.subject).into(),
-            predicate: (&node.predicate).into(),
-            object: (&node.object).into(),
-            graph_name: (&node.graph_name).into(),
+        write!(f, "{} {} {}", self.subject, self.predicate, self.object)?;
+        if !self.graph_name.is_default_graph() {
+            write!(f, " {}", self.graph_name)?;
         }
+        Ok(())
     }
 }
 
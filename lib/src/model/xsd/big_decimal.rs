@@ -0,0 +1,503 @@
+use super::decimal::{
+    DecimalOverflowError, ParseDecimalError, PARSE_UNEXPECTED_CHAR, PARSE_UNEXPECTED_END,
+};
+use super::*;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// The number of digits after "." kept by the divisions if the operands do not have more of them
+const DIVISION_DIGITS: u32 = 18;
+
+/// Arbitrary precision [XML Schema `decimal` datatype](https://www.w3.org/TR/xmlschema11-2/#decimal) implementation.
+///
+/// It is used for the `xsd:decimal` and `xsd:integer` values that do not fit in a `Decimal` or an `i64`.
+/// The additions, subtractions and multiplications are exact.
+/// The divisions are truncated after 18 digits after "." or the number of digits of the operands if it is bigger.
+#[derive(Eq, PartialEq, Debug, Clone, Hash, Default)]
+pub struct BigDecimal {
+    negative: bool,
+    digits: Vec<u8>, // little endian base 10 digits of value * 10^scale, without leading zeros
+    scale: u32,      // the number of digits after "."
+}
+
+impl BigDecimal {
+    fn new(negative: bool, digits: Vec<u8>, scale: u32) -> Self {
+        let mut value = Self {
+            negative,
+            digits,
+            scale,
+        };
+        value.normalize();
+        value
+    }
+
+    /// Removes the leading zeros and the trailing zeros after "." in order to get a canonical representation
+    #[allow(clippy::cast_possible_truncation)]
+    fn normalize(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        let trailing_zeros = self
+            .digits
+            .iter()
+            .take(self.scale as usize)
+            .take_while(|d| **d == 0)
+            .count();
+        self.digits.drain(..trailing_zeros);
+        self.scale -= trailing_zeros as u32;
+        if self.digits.is_empty() {
+            self.negative = false;
+            self.scale = 0;
+        }
+    }
+
+    /// Checks if the value has no digit after "."
+    #[inline]
+    pub fn is_integer(&self) -> bool {
+        self.scale == 0
+    }
+
+    /// [op:numeric-divide](https://www.w3.org/TR/xpath-functions/#func-numeric-divide)
+    ///
+    /// Returns `None` if `rhs` is zero.
+    pub fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        if rhs.digits.is_empty() {
+            return None;
+        }
+        let scale = DIVISION_DIGITS.max(self.scale).max(rhs.scale);
+        // self / rhs = (digits / 10^self.scale) / (rhs.digits / 10^rhs.scale)
+        let numerator = shift(&self.digits, rhs.scale + scale);
+        let denominator = shift(&rhs.digits, self.scale);
+        Some(Self::new(
+            self.negative != rhs.negative,
+            div_digits(&numerator, &denominator),
+            scale,
+        ))
+    }
+
+    /// [fn:abs](https://www.w3.org/TR/xpath-functions/#func-abs)
+    pub fn abs(&self) -> Self {
+        Self {
+            negative: false,
+            digits: self.digits.clone(),
+            scale: self.scale,
+        }
+    }
+
+    /// Removes the digits after "."
+    pub fn trunc(&self) -> Self {
+        let scale = (self.scale as usize).min(self.digits.len());
+        Self::new(self.negative, self.digits[scale..].to_vec(), 0)
+    }
+
+    /// [fn:floor](https://www.w3.org/TR/xpath-functions/#func-floor)
+    pub fn floor(&self) -> Self {
+        if self.negative && !self.is_integer() {
+            self.trunc() - Self::from(1_i64)
+        } else {
+            self.trunc()
+        }
+    }
+
+    /// [fn:ceiling](https://www.w3.org/TR/xpath-functions/#func-ceiling)
+    pub fn ceil(&self) -> Self {
+        if self.negative || self.is_integer() {
+            self.trunc()
+        } else {
+            self.trunc() + Self::from(1_i64)
+        }
+    }
+
+    /// [fn:round](https://www.w3.org/TR/xpath-functions/#func-round)
+    pub fn round(&self) -> Self {
+        (self.clone() + Self::new(false, vec![5], 1)).floor()
+    }
+
+    pub fn to_f32(&self) -> f32 {
+        self.to_string().parse().unwrap_or(f32::NAN)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Returns the digits of the two values with the same scale
+    fn aligned_digits(&self, other: &Self) -> (Vec<u8>, Vec<u8>, u32) {
+        let scale = self.scale.max(other.scale);
+        (
+            shift(&self.digits, scale - self.scale),
+            shift(&other.digits, scale - other.scale),
+            scale,
+        )
+    }
+}
+
+impl From<i64> for BigDecimal {
+    fn from(value: i64) -> Self {
+        Self::from(i128::from(value))
+    }
+}
+
+impl From<i128> for BigDecimal {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from(value: i128) -> Self {
+        let negative = value < 0;
+        let mut magnitude = if negative {
+            (value as u128).wrapping_neg()
+        } else {
+            value as u128
+        };
+        let mut digits = Vec::new();
+        while magnitude != 0 {
+            digits.push((magnitude % 10) as u8);
+            magnitude /= 10;
+        }
+        Self::new(negative, digits, 0)
+    }
+}
+
+impl From<Decimal> for BigDecimal {
+    fn from(value: Decimal) -> Self {
+        let mut value = Self::from(i128::from_be_bytes(value.to_be_bytes()));
+        value.scale = 18;
+        value.normalize();
+        value
+    }
+}
+
+impl TryFrom<&BigDecimal> for i64 {
+    type Error = DecimalOverflowError;
+
+    fn try_from(value: &BigDecimal) -> Result<i64, DecimalOverflowError> {
+        if !value.is_integer() || value.digits.len() > 19 {
+            return Err(DecimalOverflowError);
+        }
+        let mut result = 0_i128;
+        for digit in value.digits.iter().rev() {
+            result = result * 10 + i128::from(*digit);
+        }
+        if value.negative {
+            result = -result;
+        }
+        i64::try_from(result).map_err(|_| DecimalOverflowError)
+    }
+}
+
+impl TryFrom<&BigDecimal> for Decimal {
+    type Error = DecimalOverflowError;
+
+    /// Fails if the value does not fit exactly in a `Decimal`
+    fn try_from(value: &BigDecimal) -> Result<Decimal, DecimalOverflowError> {
+        value.to_string().parse().map_err(|_| DecimalOverflowError)
+    }
+}
+
+impl FromStr for BigDecimal {
+    type Err = ParseDecimalError;
+
+    /// Parses decimals lexical mapping
+    fn from_str(input: &str) -> Result<Self, ParseDecimalError> {
+        // (\+|-)?([0-9]+(\.[0-9]*)?|\.[0-9]+)
+        let (negative, input) = if let Some(input) = input.strip_prefix('-') {
+            (true, input)
+        } else {
+            (false, input.strip_prefix('+').unwrap_or(input))
+        };
+        let (before_dot, after_dot) = if let Some(dot) = input.find('.') {
+            (&input[..dot], &input[dot + 1..])
+        } else {
+            (input, "")
+        };
+        if before_dot.is_empty() && after_dot.is_empty() {
+            return Err(PARSE_UNEXPECTED_END);
+        }
+        let mut digits = Vec::with_capacity(before_dot.len() + after_dot.len());
+        for c in after_dot.bytes().rev().chain(before_dot.bytes().rev()) {
+            if !c.is_ascii_digit() {
+                return Err(PARSE_UNEXPECTED_CHAR);
+            }
+            digits.push(c - b'0');
+        }
+        let scale = u32::try_from(after_dot.len()).map_err(|_| PARSE_UNEXPECTED_CHAR)?;
+        Ok(Self::new(negative, digits, scale))
+    }
+}
+
+impl fmt::Display for BigDecimal {
+    /// Formats the decimal following its canonical representation
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let scale = self.scale as usize;
+        if self.digits.len() <= scale {
+            write!(f, "0")?;
+        } else {
+            for digit in self.digits[scale..].iter().rev() {
+                write!(f, "{}", digit)?;
+            }
+        }
+        if scale > 0 {
+            write!(f, ".")?;
+            for _ in self.digits.len()..scale {
+                write!(f, "0")?;
+            }
+            for digit in self.digits[..scale.min(self.digits.len())].iter().rev() {
+                write!(f, "{}", digit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Ord for BigDecimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (negative, _) => {
+                let (a, b, _) = self.aligned_digits(other);
+                let ordering = cmp_digits(&a, &b);
+                if negative {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        }
+    }
+}
+
+impl PartialOrd for BigDecimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Neg for BigDecimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(!self.negative, self.digits, self.scale)
+    }
+}
+
+impl Add for BigDecimal {
+    type Output = Self;
+
+    /// [op:numeric-add](https://www.w3.org/TR/xpath-functions/#func-numeric-add)
+    fn add(self, rhs: Self) -> Self {
+        let (a, b, scale) = self.aligned_digits(&rhs);
+        if self.negative == rhs.negative {
+            Self::new(self.negative, add_digits(&a, &b), scale)
+        } else if cmp_digits(&a, &b) == Ordering::Less {
+            Self::new(rhs.negative, sub_digits(&b, &a), scale)
+        } else {
+            Self::new(self.negative, sub_digits(&a, &b), scale)
+        }
+    }
+}
+
+impl Sub for BigDecimal {
+    type Output = Self;
+
+    /// [op:numeric-subtract](https://www.w3.org/TR/xpath-functions/#func-numeric-subtract)
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl Mul for BigDecimal {
+    type Output = Self;
+
+    /// [op:numeric-multiply](https://www.w3.org/TR/xpath-functions/#func-numeric-multiply)
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.negative != rhs.negative,
+            mul_digits(&self.digits, &rhs.digits),
+            self.scale + rhs.scale,
+        )
+    }
+}
+
+/// Multiplies by 10^n
+fn shift(digits: &[u8], n: u32) -> Vec<u8> {
+    if digits.is_empty() {
+        return Vec::new();
+    }
+    let mut result = vec![0; n as usize];
+    result.extend_from_slice(digits);
+    result
+}
+
+fn trim(mut digits: Vec<u8>) -> Vec<u8> {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    digits
+}
+
+fn cmp_digits(a: &[u8], b: &[u8]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = a.get(i).copied().unwrap_or(0) + b.get(i).copied().unwrap_or(0) + carry;
+        result.push(sum % 10);
+        carry = sum / 10;
+    }
+    if carry > 0 {
+        result.push(carry);
+    }
+    result
+}
+
+/// Computes a - b assuming a >= b
+fn sub_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for (i, digit) in a.iter().enumerate() {
+        let rhs = b.get(i).copied().unwrap_or(0) + borrow;
+        if *digit >= rhs {
+            result.push(digit - rhs);
+            borrow = 0;
+        } else {
+            result.push(digit + 10 - rhs);
+            borrow = 1;
+        }
+    }
+    trim(result)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn mul_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut accumulators = vec![0_u64; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            accumulators[i + j] += u64::from(*x) * u64::from(*y);
+        }
+    }
+    let mut result = Vec::with_capacity(accumulators.len());
+    let mut carry = 0;
+    for accumulator in accumulators {
+        let value = accumulator + carry;
+        result.push((value % 10) as u8);
+        carry = value / 10;
+    }
+    while carry > 0 {
+        result.push((carry % 10) as u8);
+        carry /= 10;
+    }
+    trim(result)
+}
+
+/// Computes the truncated division a / b using the long division algorithm
+fn div_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut quotient = Vec::with_capacity(a.len());
+    let mut remainder = Vec::new();
+    for digit in a.iter().rev() {
+        remainder.insert(0, *digit);
+        remainder = trim(remainder);
+        let mut q = 0;
+        while cmp_digits(&remainder, b) != Ordering::Less {
+            remainder = sub_digits(&remainder, b);
+            q += 1;
+        }
+        quotient.push(q);
+    }
+    quotient.reverse();
+    trim(quotient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(big("210").to_string(), "210");
+        assert_eq!(big("-1.23").to_string(), "-1.23");
+        assert_eq!(big("+100000.00").to_string(), "100000");
+        assert_eq!(big(".12200").to_string(), "0.122");
+        assert_eq!(big("-0.00").to_string(), "0");
+        assert_eq!(big("0.001").to_string(), "0.001");
+        assert_eq!(
+            big("123456789012345678901234567890.123456789012345678901234567890").to_string(),
+            "123456789012345678901234567890.12345678901234567890123456789"
+        );
+        assert!(BigDecimal::from_str("").is_err());
+        assert!(BigDecimal::from_str(".").is_err());
+        assert!(BigDecimal::from_str("1e3").is_err());
+        assert!(BigDecimal::from_str("1.2.3").is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(
+            big("99999999999999999999") + big("1"),
+            big("100000000000000000000")
+        );
+        assert_eq!(big("1.5") - big("2.25"), big("-0.75"));
+        assert_eq!(big("-1.5") + big("1.5"), BigDecimal::default());
+        assert_eq!(
+            big("12345678901234567890") * big("-98765432109876543210"),
+            big("-1219326311370217952237463801111263526900")
+        );
+        assert_eq!(big("0.1") * big("0.2"), big("0.02"));
+        assert_eq!(
+            big("1").checked_div(&big("3")),
+            Some(big("0.333333333333333333"))
+        );
+        assert_eq!(big("-10").checked_div(&big("4")), Some(big("-2.5")));
+        assert_eq!(big("1").checked_div(&big("0")), None);
+        assert_eq!(big("-12.75").trunc(), big("-12"));
+        assert_eq!(big("-12.75").floor(), big("-13"));
+        assert_eq!(big("-12.75").ceil(), big("-12"));
+        assert_eq!(big("12.25").ceil(), big("13"));
+        assert_eq!(big("-12.5").round(), big("-12"));
+        assert_eq!(big("12.5").round(), big("13"));
+    }
+
+    #[test]
+    fn cmp() {
+        assert!(big("100000000000000000000") > big("99999999999999999999"));
+        assert!(big("-100000000000000000000") < big("-99999999999999999999"));
+        assert!(big("-1") < big("0.5"));
+        assert!(big("0.25") < big("0.3"));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(
+            BigDecimal::from(i64::min_value()).to_string(),
+            "-9223372036854775808"
+        );
+        assert_eq!(
+            i64::try_from(&big("-9223372036854775808")).ok(),
+            Some(i64::min_value())
+        );
+        assert!(i64::try_from(&big("9223372036854775808")).is_err());
+        assert!(i64::try_from(&big("1.5")).is_err());
+        assert_eq!(
+            BigDecimal::from(Decimal::from_str("-1.25").unwrap()),
+            big("-1.25")
+        );
+        assert_eq!(
+            Decimal::try_from(&big("-1.25")).ok(),
+            Some(Decimal::from_str("-1.25").unwrap())
+        );
+        assert!(Decimal::try_from(&big("0.0000000000000000001")).is_err());
+    }
+}
@@ -105,7 +105,7 @@ impl DateTime {
     }
 
     /// [op:subtract-dateTimes](https://www.w3.org/TR/xpath-functions/#func-subtract-dateTimes)
-    pub fn checked_sub(&self, rhs: impl Into<Self>) -> Option<Duration> {
+    pub fn checked_sub(&self, rhs: impl Into<Self>) -> Option<DayTimeDuration> {
         self.timestamp.checked_sub(rhs.into().timestamp)
     }
 
@@ -280,7 +280,7 @@ impl Time {
     }
 
     /// [op:subtract-times](https://www.w3.org/TR/xpath-functions/#func-subtract-times)
-    pub fn checked_sub(&self, rhs: impl Into<Self>) -> Option<Duration> {
+    pub fn checked_sub(&self, rhs: impl Into<Self>) -> Option<DayTimeDuration> {
         self.timestamp.checked_sub(rhs.into().timestamp)
     }
 
@@ -428,7 +428,7 @@ impl Date {
     }
 
     /// [op:subtract-dates](https://www.w3.org/TR/xpath-functions/#func-subtract-dates)
-    pub fn checked_sub(&self, rhs: impl Into<Self>) -> Option<Duration> {
+    pub fn checked_sub(&self, rhs: impl Into<Self>) -> Option<DayTimeDuration> {
         self.timestamp.checked_sub(rhs.into().timestamp)
     }
 
@@ -801,13 +801,9 @@ impl Timestamp {
         })
     }
 
-    fn checked_sub(&self, rhs: Timestamp) -> Option<Duration> {
-        match (self.timezone_offset, rhs.timezone_offset) {
-            (Some(_), Some(_)) | (None, None) => {
-                Some(Duration::new(0, self.value.checked_sub(rhs.value)?))
-            }
-            _ => None, //TODO: implicit timezone
-        }
+    fn checked_sub(&self, rhs: Timestamp) -> Option<DayTimeDuration> {
+        // The implicit timezone is UTC
+        Some(DayTimeDuration::new(self.value.checked_sub(rhs.value)?))
     }
 
     fn checked_sub_seconds(&self, seconds: Decimal) -> Option<Self> {
@@ -324,12 +324,19 @@ impl FromStr for Decimal {
 
             let mut with_after_dot = false;
             while cursor < input.len() && b'0' <= input[cursor] && input[cursor] <= b'9' {
-                exp = exp.checked_div(10).ok_or(PARSE_UNDERFLOW)?;
-                value = value
-                    .checked_mul(10)
-                    .ok_or(PARSE_OVERFLOW)?
-                    .checked_add((input[cursor] - b'0').into())
-                    .ok_or(PARSE_OVERFLOW)?;
+                if exp == 1 {
+                    // There is no room left for more digits, only trailing zeros are allowed
+                    if input[cursor] != b'0' {
+                        return Err(PARSE_UNDERFLOW);
+                    }
+                } else {
+                    exp /= 10;
+                    value = value
+                        .checked_mul(10)
+                        .ok_or(PARSE_OVERFLOW)?
+                        .checked_add((input[cursor] - b'0').into())
+                        .ok_or(PARSE_OVERFLOW)?;
+                }
                 cursor += 1;
                 with_after_dot = true;
             }
@@ -375,10 +382,10 @@ const PARSE_OVERFLOW: ParseDecimalError = ParseDecimalError {
 const PARSE_UNDERFLOW: ParseDecimalError = ParseDecimalError {
     kind: ParseDecimalErrorKind::Underflow,
 };
-const PARSE_UNEXPECTED_CHAR: ParseDecimalError = ParseDecimalError {
+pub(super) const PARSE_UNEXPECTED_CHAR: ParseDecimalError = ParseDecimalError {
     kind: ParseDecimalErrorKind::UnexpectedChar,
 };
-const PARSE_UNEXPECTED_END: ParseDecimalError = ParseDecimalError {
+pub(super) const PARSE_UNEXPECTED_END: ParseDecimalError = ParseDecimalError {
     kind: ParseDecimalErrorKind::UnexpectedEnd,
 };
 
@@ -532,6 +539,13 @@ mod tests {
         );
         assert_eq!(Decimal::from_str("0.1220").unwrap().to_string(), "0.122");
         assert_eq!(Decimal::from_str(".12200").unwrap().to_string(), "0.122");
+        assert_eq!(
+            Decimal::from_str("1.0000000000000000000000")
+                .unwrap()
+                .to_string(),
+            "1"
+        );
+        assert!(Decimal::from_str("0.0000000000000000001").is_err());
         assert_eq!(
             Decimal::from_str(&Decimal::max_value().to_string()).unwrap(),
             Decimal::max_value()
@@ -292,6 +292,31 @@ impl YearMonthDuration {
             months: self.months.checked_sub(rhs.months)?,
         })
     }
+
+    /// [op:multiply-yearMonthDuration](https://www.w3.org/TR/xpath-functions/#func-multiply-yearMonthDuration)
+    ///
+    /// The result is rounded to the nearest month.
+    pub fn checked_mul(&self, rhs: impl Into<Decimal>) -> Option<Self> {
+        Self::from_decimal_months(Decimal::from(self.months).checked_mul(rhs)?)
+    }
+
+    /// [op:divide-yearMonthDuration](https://www.w3.org/TR/xpath-functions/#func-divide-yearMonthDuration)
+    ///
+    /// The result is rounded to the nearest month.
+    pub fn checked_div(&self, rhs: impl Into<Decimal>) -> Option<Self> {
+        Self::from_decimal_months(Decimal::from(self.months).checked_div(rhs)?)
+    }
+
+    /// [op:divide-yearMonthDuration-by-yearMonthDuration](https://www.w3.org/TR/xpath-functions/#func-divide-yearMonthDuration-by-yearMonthDuration)
+    pub fn checked_div_duration(&self, rhs: impl Into<Self>) -> Option<Decimal> {
+        Decimal::from(self.months).checked_div(rhs.into().months)
+    }
+
+    fn from_decimal_months(months: Decimal) -> Option<Self> {
+        Some(Self {
+            months: i64::try_from(months.round()).ok()?,
+        })
+    }
 }
 
 impl From<YearMonthDuration> for Duration {
@@ -444,6 +469,25 @@ impl DayTimeDuration {
             seconds: self.seconds.checked_sub(rhs.seconds)?,
         })
     }
+
+    /// [op:multiply-dayTimeDuration](https://www.w3.org/TR/xpath-functions/#func-multiply-dayTimeDuration)
+    pub fn checked_mul(&self, rhs: impl Into<Decimal>) -> Option<Self> {
+        Some(Self {
+            seconds: self.seconds.checked_mul(rhs)?,
+        })
+    }
+
+    /// [op:divide-dayTimeDuration](https://www.w3.org/TR/xpath-functions/#func-divide-dayTimeDuration)
+    pub fn checked_div(&self, rhs: impl Into<Decimal>) -> Option<Self> {
+        Some(Self {
+            seconds: self.seconds.checked_div(rhs)?,
+        })
+    }
+
+    /// [op:divide-dayTimeDuration-by-dayTimeDuration](https://www.w3.org/TR/xpath-functions/#func-divide-dayTimeDuration-by-dayTimeDuration)
+    pub fn checked_div_duration(&self, rhs: impl Into<Self>) -> Option<Decimal> {
+        self.seconds.checked_div(rhs.into().seconds)
+    }
 }
 
 impl From<DayTimeDuration> for Duration {
@@ -770,4 +814,49 @@ mod tests {
             Some(Duration::from_str("P1DT1H30M").unwrap())
         );
     }
+
+    #[test]
+    fn mul() {
+        assert_eq!(
+            YearMonthDuration::from_str("P2Y11M")
+                .unwrap()
+                .checked_mul(Decimal::from_str("2.3").unwrap()),
+            Some(YearMonthDuration::from_str("P6Y9M").unwrap())
+        );
+        assert_eq!(
+            DayTimeDuration::from_str("PT2H10M")
+                .unwrap()
+                .checked_mul(Decimal::from_str("2.1").unwrap()),
+            Some(DayTimeDuration::from_str("PT4H33M").unwrap())
+        );
+    }
+
+    #[test]
+    fn div() {
+        assert_eq!(
+            YearMonthDuration::from_str("P2Y11M")
+                .unwrap()
+                .checked_div(Decimal::from_str("1.5").unwrap()),
+            Some(YearMonthDuration::from_str("P1Y11M").unwrap())
+        );
+        assert_eq!(
+            DayTimeDuration::from_str("P1DT2H30M10.5S")
+                .unwrap()
+                .checked_div(Decimal::from_str("1.5").unwrap()),
+            Some(DayTimeDuration::from_str("PT17H40M7S").unwrap())
+        );
+        assert_eq!(YearMonthDuration::new(1).checked_div(0), None);
+        assert_eq!(
+            YearMonthDuration::from_str("P3Y4M")
+                .unwrap()
+                .checked_div_duration(YearMonthDuration::from_str("-P1Y4M").unwrap()),
+            Some(Decimal::from_str("-2.5").unwrap())
+        );
+        assert_eq!(
+            DayTimeDuration::from_str("P1D")
+                .unwrap()
+                .checked_div_duration(DayTimeDuration::from_str("PT16H").unwrap()),
+            Some(Decimal::from_str("1.5").unwrap())
+        );
+    }
 }
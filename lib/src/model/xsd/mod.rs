@@ -1,8 +1,10 @@
+mod big_decimal;
 pub mod date_time;
 pub mod decimal;
 mod duration;
 mod parser;
 
+pub use self::big_decimal::BigDecimal;
 pub use self::date_time::{Date, DateTime, Time};
 pub use self::decimal::Decimal;
 pub use self::duration::{DayTimeDuration, Duration, YearMonthDuration};
@@ -8,6 +8,7 @@ use std::collections::BTreeSet;
 use std::fmt;
 use std::ops::Add;
 
+/// The union of a `NamedNode` and a `Variable`, used in predicate and graph name positions
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum NamedNodeOrVariable {
     NamedNode(NamedNode),
@@ -35,10 +36,31 @@ impl From<Variable> for NamedNodeOrVariable {
     }
 }
 
+/// The union of a `Term`, a `Variable` and a quoted triple pattern, used in subject and object positions
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum TermOrVariable {
     Term(Term),
     Variable(Variable),
+    /// A [SPARQL-star](https://w3c.github.io/rdf-star/cg-spec/editors_draft.html#sparql-star) quoted triple pattern `<< s p o >>`
+    Triple(Box<TriplePattern>),
+}
+
+impl TermOrVariable {
+    fn add_variables<'a>(&'a self, vars: &mut BTreeSet<&'a Variable>) {
+        match self {
+            TermOrVariable::Term(_) => (),
+            TermOrVariable::Variable(var) => {
+                vars.insert(var);
+            }
+            TermOrVariable::Triple(triple) => {
+                triple.subject.add_variables(vars);
+                if let NamedNodeOrVariable::Variable(ref p) = triple.predicate {
+                    vars.insert(p);
+                }
+                triple.object.add_variables(vars);
+            }
+        }
+    }
 }
 
 impl fmt::Display for TermOrVariable {
@@ -46,6 +68,7 @@ impl fmt::Display for TermOrVariable {
         match self {
             TermOrVariable::Term(term) => term.fmt(f),
             TermOrVariable::Variable(var) => var.fmt(f),
+            TermOrVariable::Triple(triple) => write!(f, "<< {} >>", triple),
         }
     }
 }
@@ -74,6 +97,12 @@ impl From<Variable> for TermOrVariable {
     }
 }
 
+impl From<TriplePattern> for TermOrVariable {
+    fn from(triple: TriplePattern) -> Self {
+        TermOrVariable::Triple(Box::new(triple))
+    }
+}
+
 impl From<Term> for TermOrVariable {
     fn from(term: Term) -> Self {
         TermOrVariable::Term(term)
@@ -89,6 +118,9 @@ impl From<NamedNodeOrVariable> for TermOrVariable {
     }
 }
 
+/// The solutions of a [`VALUES`](https://www.w3.org/TR/sparql11-query/#inline-data) clause
+///
+/// Each row contains one optional value per variable. `None` means the variable is unbound.
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct StaticBindings {
     variables: Vec<Variable>,
@@ -126,6 +158,7 @@ impl Default for StaticBindings {
     }
 }
 
+/// A [triple pattern](https://www.w3.org/TR/sparql11-query/#defn_TriplePattern)
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct TriplePattern {
     pub subject: TermOrVariable,
@@ -153,15 +186,24 @@ impl fmt::Display for TriplePattern {
     }
 }
 
+/// A [property path expression](https://www.w3.org/TR/sparql11-query/#defn_PropertyPathExpr)
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum PropertyPath {
+    /// `iri`
     PredicatePath(NamedNode),
+    /// `^path`
     InversePath(Box<PropertyPath>),
+    /// `path1 / path2`
     SequencePath(Box<PropertyPath>, Box<PropertyPath>),
+    /// `path1 | path2`
     AlternativePath(Box<PropertyPath>, Box<PropertyPath>),
+    /// `path*`
     ZeroOrMorePath(Box<PropertyPath>),
+    /// `path+`
     OneOrMorePath(Box<PropertyPath>),
+    /// `path?`
     ZeroOrOnePath(Box<PropertyPath>),
+    /// `!(iri1 | ... | irin)`
     NegatedPropertySet(Vec<NamedNode>),
 }
 
@@ -193,7 +235,7 @@ impl<'a> fmt::Display for SparqlPropertyPath<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.0 {
             PropertyPath::PredicatePath(p) => write!(f, "{}", p),
-            PropertyPath::InversePath(p) => write!(f, "^{}", SparqlPropertyPath(&*p)),
+            PropertyPath::InversePath(p) => write!(f, "^{}", SparqlPathPrimary(&*p)),
             PropertyPath::SequencePath(a, b) => write!(
                 f,
                 "({} / {})",
@@ -206,9 +248,9 @@ impl<'a> fmt::Display for SparqlPropertyPath<'a> {
                 SparqlPropertyPath(&*a),
                 SparqlPropertyPath(&*b)
             ),
-            PropertyPath::ZeroOrMorePath(p) => write!(f, "{}*", SparqlPropertyPath(&*p)),
-            PropertyPath::OneOrMorePath(p) => write!(f, "{}+", SparqlPropertyPath(&*p)),
-            PropertyPath::ZeroOrOnePath(p) => write!(f, "{}?", SparqlPropertyPath(&*p)),
+            PropertyPath::ZeroOrMorePath(p) => write!(f, "{}*", SparqlPathPrimary(&*p)),
+            PropertyPath::OneOrMorePath(p) => write!(f, "{}+", SparqlPathPrimary(&*p)),
+            PropertyPath::ZeroOrOnePath(p) => write!(f, "{}?", SparqlPathPrimary(&*p)),
             PropertyPath::NegatedPropertySet(p) => write!(
                 f,
                 "!({})",
@@ -221,12 +263,28 @@ impl<'a> fmt::Display for SparqlPropertyPath<'a> {
     }
 }
 
+/// A path that could be followed by a path modifier or preceded by `^`
+struct SparqlPathPrimary<'a>(&'a PropertyPath);
+
+impl<'a> fmt::Display for SparqlPathPrimary<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            PropertyPath::PredicatePath(_)
+            | PropertyPath::SequencePath(..)
+            | PropertyPath::AlternativePath(..)
+            | PropertyPath::NegatedPropertySet(_) => SparqlPropertyPath(self.0).fmt(f),
+            p => write!(f, "({})", SparqlPropertyPath(p)),
+        }
+    }
+}
+
 impl From<NamedNode> for PropertyPath {
     fn from(p: NamedNode) -> Self {
         PropertyPath::PredicatePath(p)
     }
 }
 
+/// A triple pattern with a property path in the predicate position
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct PathPattern {
     pub subject: TermOrVariable,
@@ -268,6 +326,7 @@ impl<'a> fmt::Display for SparqlPathPattern<'a> {
     }
 }
 
+/// An element of a basic graph pattern: a `TriplePattern` or a `PathPattern`
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum TripleOrPathPattern {
     Triple(TriplePattern),
@@ -322,30 +381,51 @@ impl<'a> fmt::Display for SparqlTripleOrPathPattern<'a> {
     }
 }
 
+/// A [SPARQL expression](https://www.w3.org/TR/sparql11-query/#expressions)
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum Expression {
     NamedNode(NamedNode),
     Literal(Literal),
     Variable(Variable),
+    /// `a || b`
     Or(Box<Expression>, Box<Expression>),
+    /// `a && b`
     And(Box<Expression>, Box<Expression>),
+    /// `a = b`
     Equal(Box<Expression>, Box<Expression>),
+    /// `a != b`
     NotEqual(Box<Expression>, Box<Expression>),
+    /// `a > b`
     Greater(Box<Expression>, Box<Expression>),
+    /// `a >= b`
     GreaterOrEq(Box<Expression>, Box<Expression>),
+    /// `a < b`
     Lower(Box<Expression>, Box<Expression>),
+    /// `a <= b`
     LowerOrEq(Box<Expression>, Box<Expression>),
+    /// `a IN (b1, ..., bn)`
     In(Box<Expression>, Vec<Expression>),
+    /// `a NOT IN (b1, ..., bn)`
     NotIn(Box<Expression>, Vec<Expression>),
+    /// `a + b`
     Add(Box<Expression>, Box<Expression>),
+    /// `a - b`
     Sub(Box<Expression>, Box<Expression>),
+    /// `a * b`
     Mul(Box<Expression>, Box<Expression>),
+    /// `a / b`
     Div(Box<Expression>, Box<Expression>),
+    /// `+a`
     UnaryPlus(Box<Expression>),
+    /// `-a`
     UnaryMinus(Box<Expression>),
+    /// `!a`
     UnaryNot(Box<Expression>),
+    /// A call to a built-in or a custom function
     FunctionCall(Function, Vec<Expression>),
+    /// `EXISTS { pattern }`
     Exists(Box<GraphPattern>),
+    /// `BOUND(?v)`
     Bound(Variable),
 }
 
@@ -474,7 +554,7 @@ impl<'a> fmt::Display for SparqlExpression<'a> {
             Expression::In(a, b) => write!(
                 f,
                 "({} IN ({}))",
-                a,
+                SparqlExpression(&*a),
                 b.iter()
                     .map(|v| SparqlExpression(v).to_string())
                     .collect::<Vec<String>>()
@@ -483,29 +563,29 @@ impl<'a> fmt::Display for SparqlExpression<'a> {
             Expression::NotIn(a, b) => write!(
                 f,
                 "({} NOT IN ({}))",
-                a,
+                SparqlExpression(&*a),
                 b.iter()
                     .map(|v| SparqlExpression(v).to_string())
                     .collect::<Vec<String>>()
                     .join(", ")
             ),
             Expression::Add(a, b) => {
-                write!(f, "{} + {}", SparqlExpression(&*a), SparqlExpression(&*b))
+                write!(f, "({} + {})", SparqlExpression(&*a), SparqlExpression(&*b))
             }
             Expression::Sub(a, b) => {
-                write!(f, "{} - {}", SparqlExpression(&*a), SparqlExpression(&*b))
+                write!(f, "({} - {})", SparqlExpression(&*a), SparqlExpression(&*b))
             }
             Expression::Mul(a, b) => {
-                write!(f, "{} * {}", SparqlExpression(&*a), SparqlExpression(&*b))
+                write!(f, "({} * {})", SparqlExpression(&*a), SparqlExpression(&*b))
             }
             Expression::Div(a, b) => {
-                write!(f, "{} / {}", SparqlExpression(&*a), SparqlExpression(&*b))
+                write!(f, "({} / {})", SparqlExpression(&*a), SparqlExpression(&*b))
             }
-            Expression::UnaryPlus(e) => write!(f, "+{}", SparqlExpression(&*e)),
-            Expression::UnaryMinus(e) => write!(f, "-{}", SparqlExpression(&*e)),
+            Expression::UnaryPlus(e) => write!(f, "+({})", SparqlExpression(&*e)),
+            Expression::UnaryMinus(e) => write!(f, "-({})", SparqlExpression(&*e)),
             Expression::UnaryNot(e) => match e.as_ref() {
                 Expression::Exists(p) => write!(f, "NOT EXISTS {{ {} }}", SparqlGraphPattern(&*p)),
-                e => write!(f, "!{}", e),
+                e => write!(f, "!({})", SparqlExpression(e)),
             },
             Expression::FunctionCall(function, parameters) => {
                 write!(f, "{}(", function)?;
@@ -525,6 +605,10 @@ impl<'a> fmt::Display for SparqlExpression<'a> {
     }
 }
 
+/// A function callable in a SPARQL expression
+///
+/// All the [SPARQL 1.1 built-in functions](https://www.w3.org/TR/sparql11-query/#SparqlOps) are listed.
+/// `Custom` is used for the functions identified by an IRI like the XPath constructor functions.
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum Function {
     Str,
@@ -601,7 +685,7 @@ impl fmt::Display for Function {
             Function::LCase => write!(f, "LCASE"),
             Function::EncodeForURI => write!(f, "ENCODE_FOR_URI"),
             Function::Contains => write!(f, "CONTAINS"),
-            Function::StrStarts => write!(f, "STRSTATS"),
+            Function::StrStarts => write!(f, "STRSTARTS"),
             Function::StrEnds => write!(f, "STRENDS"),
             Function::StrBefore => write!(f, "STRBEFORE"),
             Function::StrAfter => write!(f, "STRAFTER"),
@@ -636,23 +720,40 @@ impl fmt::Display for Function {
     }
 }
 
+/// A [SPARQL algebra](https://www.w3.org/TR/sparql11-query/#sparqlAlgebra) graph pattern
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum GraphPattern {
+    /// A [basic graph pattern](https://www.w3.org/TR/sparql11-query/#defn_BasicGraphPattern)
     BGP(Vec<TripleOrPathPattern>),
+    /// [Join](https://www.w3.org/TR/sparql11-query/#defn_algJoin)
     Join(Box<GraphPattern>, Box<GraphPattern>),
+    /// [LeftJoin](https://www.w3.org/TR/sparql11-query/#defn_algLeftJoin) i.e. `OPTIONAL` with an optional filter
     LeftJoin(Box<GraphPattern>, Box<GraphPattern>, Option<Expression>),
+    /// [Filter](https://www.w3.org/TR/sparql11-query/#defn_algFilter)
     Filter(Expression, Box<GraphPattern>),
+    /// [Union](https://www.w3.org/TR/sparql11-query/#defn_algUnion)
     Union(Box<GraphPattern>, Box<GraphPattern>),
+    /// `GRAPH name { pattern }`
     Graph(NamedNodeOrVariable, Box<GraphPattern>),
+    /// [Extend](https://www.w3.org/TR/sparql11-query/#defn_extend) i.e. `BIND(expression AS ?variable)`
     Extend(Box<GraphPattern>, Variable, Expression),
+    /// [Minus](https://www.w3.org/TR/sparql11-query/#defn_algMinus)
     Minus(Box<GraphPattern>, Box<GraphPattern>),
+    /// `SERVICE name { pattern }`. The boolean is `true` if the `SILENT` keyword is set
     Service(NamedNodeOrVariable, Box<GraphPattern>, bool),
+    /// [Group](https://www.w3.org/TR/sparql11-query/#defn_algGroup) followed by the evaluation of aggregates bound to the given variables
     AggregateJoin(GroupPattern, Vec<(Aggregation, Variable)>),
+    /// Inline data i.e. `VALUES`
     Data(StaticBindings),
+    /// [OrderBy](https://www.w3.org/TR/sparql11-query/#defn_algOrdered)
     OrderBy(Box<GraphPattern>, Vec<OrderComparator>),
+    /// [Project](https://www.w3.org/TR/sparql11-query/#defn_algProjection)
     Project(Box<GraphPattern>, Vec<Variable>),
+    /// [Distinct](https://www.w3.org/TR/sparql11-query/#defn_algDistinct)
     Distinct(Box<GraphPattern>),
+    /// [Reduced](https://www.w3.org/TR/sparql11-query/#defn_algReduced)
     Reduced(Box<GraphPattern>),
+    /// [Slice](https://www.w3.org/TR/sparql11-query/#defn_algSlice) with an offset and an optional limit
     Slice(Box<GraphPattern>, usize, Option<usize>),
 }
 
@@ -762,23 +863,15 @@ impl GraphPattern {
                 for pattern in p {
                     match pattern {
                         TripleOrPathPattern::Triple(tp) => {
-                            if let TermOrVariable::Variable(ref s) = tp.subject {
-                                vars.insert(s);
-                            }
+                            tp.subject.add_variables(vars);
                             if let NamedNodeOrVariable::Variable(ref p) = tp.predicate {
                                 vars.insert(p);
                             }
-                            if let TermOrVariable::Variable(ref o) = tp.object {
-                                vars.insert(o);
-                            }
+                            tp.object.add_variables(vars);
                         }
                         TripleOrPathPattern::Path(ppp) => {
-                            if let TermOrVariable::Variable(ref s) = ppp.subject {
-                                vars.insert(s);
-                            }
-                            if let TermOrVariable::Variable(ref o) = ppp.object {
-                                vars.insert(o);
-                            }
+                            ppp.subject.add_variables(vars);
+                            ppp.object.add_variables(vars);
                         }
                     }
                 }
@@ -808,7 +901,8 @@ impl GraphPattern {
             }
             GraphPattern::Minus(a, _) => a.add_visible_variables(vars),
             GraphPattern::Service(_, p, _) => p.add_visible_variables(vars),
-            GraphPattern::AggregateJoin(_, a) => {
+            GraphPattern::AggregateJoin(GroupPattern(group, _), a) => {
+                vars.extend(group.iter());
                 for (_, v) in a {
                     vars.insert(v);
                 }
@@ -830,7 +924,7 @@ impl<'a> fmt::Display for SparqlGraphPattern<'a> {
         match self.0 {
             GraphPattern::BGP(p) => {
                 for pattern in p {
-                    write!(f, "{} .", SparqlTripleOrPathPattern(pattern))?
+                    write!(f, "{} . ", SparqlTripleOrPathPattern(pattern))?
                 }
                 Ok(())
             }
@@ -845,16 +939,16 @@ impl<'a> fmt::Display for SparqlGraphPattern<'a> {
                     write!(
                         f,
                         "{} OPTIONAL {{ {} FILTER({}) }}",
-                        SparqlGraphPattern(&*a),
-                        SparqlGraphPattern(&*b),
+                        SparqlGroupMember(&*a),
+                        SparqlGroupMember(&*b),
                         SparqlExpression(e)
                     )
                 } else {
                     write!(
                         f,
                         "{} OPTIONAL {{ {} }}",
-                        SparqlGraphPattern(&*a),
-                        SparqlGraphPattern(&*b)
+                        SparqlGroupMember(&*a),
+                        SparqlGroupMember(&*b)
                     )
                 }
             }
@@ -876,14 +970,14 @@ impl<'a> fmt::Display for SparqlGraphPattern<'a> {
             GraphPattern::Extend(p, v, e) => write!(
                 f,
                 "{} BIND({} AS {})",
-                SparqlGraphPattern(&*p),
+                SparqlGroupMember(&*p),
                 SparqlExpression(e),
                 v
             ),
             GraphPattern::Minus(a, b) => write!(
                 f,
                 "{} MINUS {{ {} }}",
-                SparqlGraphPattern(&*a),
+                SparqlGroupMember(&*a),
                 SparqlGraphPattern(&*b)
             ),
             GraphPattern::Service(n, p, s) => {
@@ -915,21 +1009,30 @@ impl<'a> fmt::Display for SparqlGraphPattern<'a> {
                     write!(f, " }}")
                 }
             }
-            GraphPattern::AggregateJoin(GroupPattern(group, p), agg) => write!(
-                f,
-                "{{ SELECT {} WHERE {{ {} }} GROUP BY {} }}",
-                agg.iter()
-                    .map(|(a, v)| format!("({} AS {})", SparqlAggregation(a), v))
-                    .chain(group.iter().map(|e| e.to_string()))
-                    .collect::<Vec<String>>()
-                    .join(" "),
-                SparqlGraphPattern(&*p),
-                group
-                    .iter()
-                    .map(|e| format!("({})", e.to_string()))
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            ),
+            GraphPattern::AggregateJoin(GroupPattern(group, p), agg) => {
+                write!(
+                    f,
+                    "{{ SELECT {} WHERE {{ {} }}",
+                    agg.iter()
+                        .map(|(a, v)| format!("({} AS {})", SparqlAggregation(a), v))
+                        .chain(group.iter().map(|e| e.to_string()))
+                        .collect::<Vec<String>>()
+                        .join(" "),
+                    SparqlGraphPattern(&*p)
+                )?;
+                if !group.is_empty() {
+                    write!(
+                        f,
+                        " GROUP BY {}",
+                        group
+                            .iter()
+                            .map(|e| format!("({})", e.to_string()))
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    )?;
+                }
+                write!(f, " }}")
+            }
             p => write!(
                 f,
                 "{{ {} }}",
@@ -942,6 +1045,20 @@ impl<'a> fmt::Display for SparqlGraphPattern<'a> {
     }
 }
 
+/// A graph pattern followed by other elements of the same group
+///
+/// The filters apply to the whole group in which they are written so they are enclosed in their own group.
+struct SparqlGroupMember<'a>(&'a GraphPattern);
+
+impl<'a> fmt::Display for SparqlGroupMember<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            GraphPattern::Filter(..) => write!(f, "{{ {} }}", SparqlGraphPattern(self.0)),
+            p => SparqlGraphPattern(p).fmt(f),
+        }
+    }
+}
+
 struct SparqlGraphRootPattern<'a> {
     algebra: &'a GraphPattern,
     dataset: &'a DatasetSpec,
@@ -949,76 +1066,148 @@ struct SparqlGraphRootPattern<'a> {
 
 impl<'a> fmt::Display for SparqlGraphRootPattern<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut distinct = false;
-        let mut reduced = false;
-        let mut order = None;
-        let mut start = 0;
-        let mut length = None;
-        let mut project: &[Variable] = &[];
-
-        let mut child = self.algebra;
+        let modifiers = SolutionModifiers::new(self.algebra);
+        write!(f, "SELECT ")?;
+        if modifiers.distinct {
+            write!(f, "DISTINCT ")?;
+        }
+        if modifiers.reduced {
+            write!(f, "REDUCED ")?;
+        }
+        write!(
+            f,
+            "{} {} WHERE {{ {} }}{}",
+            build_sparql_select_arguments(modifiers.project),
+            self.dataset,
+            SparqlGraphPattern(modifiers.child),
+            modifiers
+        )
+    }
+}
+
+/// The dataset and `WHERE` clause of the CONSTRUCT, DESCRIBE and ASK queries
+///
+/// The projection of all the variables added by the parser and the solution modifiers are written after the `WHERE` clause.
+/// The other solution modifiers are written as sub-queries.
+struct SparqlQueryPattern<'a> {
+    algebra: &'a GraphPattern,
+    dataset: &'a DatasetSpec,
+}
+
+impl<'a> fmt::Display for SparqlQueryPattern<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = SolutionModifiers::new(self.algebra);
+        if !modifiers.distinct
+            && !modifiers.reduced
+            && modifiers
+                .project
+                .iter()
+                .eq(modifiers.child.visible_variables())
+        {
+            write!(
+                f,
+                "{} WHERE {{ {} }}{}",
+                self.dataset,
+                SparqlGraphPattern(modifiers.child),
+                modifiers
+            )
+        } else {
+            write!(
+                f,
+                "{} WHERE {{ {} }}",
+                self.dataset,
+                SparqlGraphPattern(self.algebra)
+            )
+        }
+    }
+}
+
+/// The solution modifiers at the root of a query
+///
+/// They are only merged into the query if they are in the order they are evaluated in:
+/// ORDER BY, projection, DISTINCT or REDUCED and then OFFSET and LIMIT.
+/// The other ones are left in `child` and written as sub-queries.
+struct SolutionModifiers<'a> {
+    distinct: bool,
+    reduced: bool,
+    order: Option<&'a [OrderComparator]>,
+    start: usize,
+    length: Option<usize>,
+    project: &'a [Variable],
+    child: &'a GraphPattern,
+}
+
+impl<'a> SolutionModifiers<'a> {
+    fn new(algebra: &'a GraphPattern) -> Self {
+        let mut modifiers = Self {
+            distinct: false,
+            reduced: false,
+            order: None,
+            start: 0,
+            length: None,
+            project: &[],
+            child: algebra,
+        };
+        let mut stage = 0;
         loop {
-            match child {
-                GraphPattern::OrderBy(l, o) => {
-                    order = Some(o);
-                    child = &*l;
-                }
-                GraphPattern::Project(l, pv) if project.is_empty() => {
-                    project = pv;
-                    child = &*l;
+            match modifiers.child {
+                GraphPattern::Slice(l, s, len) if stage < 1 => {
+                    modifiers.start = *s;
+                    modifiers.length = *len;
+                    modifiers.child = l;
+                    stage = 1;
                 }
-                GraphPattern::Distinct(l) => {
-                    distinct = true;
-                    child = &*l;
+                GraphPattern::Distinct(l) if stage < 2 => {
+                    modifiers.distinct = true;
+                    modifiers.child = l;
+                    stage = 2;
                 }
-                GraphPattern::Reduced(l) => {
-                    reduced = true;
-                    child = &*l;
+                GraphPattern::Reduced(l) if stage < 2 => {
+                    modifiers.reduced = true;
+                    modifiers.child = l;
+                    stage = 2;
                 }
-                GraphPattern::Slice(l, s, len) => {
-                    start = *s;
-                    length = *len;
-                    child = l;
+                GraphPattern::Project(l, pv) if stage < 3 => {
+                    modifiers.project = pv;
+                    modifiers.child = l;
+                    stage = 3;
                 }
-                p => {
-                    write!(f, "SELECT ")?;
-                    if distinct {
-                        write!(f, "DISTINCT ")?;
-                    }
-                    if reduced {
-                        write!(f, "REDUCED ")?;
-                    }
-                    write!(
-                        f,
-                        "{} {} WHERE {{ {} }}",
-                        build_sparql_select_arguments(project),
-                        self.dataset,
-                        SparqlGraphPattern(p)
-                    )?;
-                    if let Some(order) = order {
-                        write!(
-                            f,
-                            " ORDER BY {}",
-                            order
-                                .iter()
-                                .map(|c| SparqlOrderComparator(c).to_string())
-                                .collect::<Vec<String>>()
-                                .join(" ")
-                        )?;
-                    }
-                    if start > 0 {
-                        write!(f, " OFFSET {}", start)?;
-                    }
-                    if let Some(length) = length {
-                        write!(f, " LIMIT {}", length)?;
-                    }
-                    return Ok(());
+                GraphPattern::OrderBy(l, o) if stage < 4 => {
+                    modifiers.order = Some(o);
+                    modifiers.child = l;
+                    stage = 4;
                 }
+                _ => return modifiers,
             }
         }
     }
 }
 
+/// Writes the ORDER BY, OFFSET and LIMIT clauses
+impl<'a> fmt::Display for SolutionModifiers<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(order) = self.order {
+            write!(
+                f,
+                " ORDER BY {}",
+                order
+                    .iter()
+                    .map(|c| SparqlOrderComparator(c).to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            )?;
+        }
+        if self.start > 0 {
+            write!(f, " OFFSET {}", self.start)?;
+        }
+        if let Some(length) = self.length {
+            write!(f, " LIMIT {}", length)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `GROUP BY` on the given variables of a graph pattern
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct GroupPattern(pub Vec<Variable>, pub Box<GraphPattern>);
 
@@ -1048,15 +1237,22 @@ fn build_sparql_select_arguments(args: &[Variable]) -> String {
     }
 }
 
+/// An [aggregate function](https://www.w3.org/TR/sparql11-query/#aggregates)
+///
+/// The boolean is `true` if the `DISTINCT` modifier is set.
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum Aggregation {
+    /// `COUNT(expression)` or `COUNT(*)` if there is no expression
     Count(Option<Box<Expression>>, bool),
     Sum(Box<Expression>, bool),
     Min(Box<Expression>, bool),
     Max(Box<Expression>, bool),
     Avg(Box<Expression>, bool),
     Sample(Box<Expression>, bool),
+    /// `GROUP_CONCAT(expression; SEPARATOR = separator)`
     GroupConcat(Box<Expression>, bool, Option<String>),
+    /// A call to a custom aggregate function identified by an IRI
+    Custom(NamedNode, Box<Expression>, bool),
 }
 
 impl fmt::Display for Aggregation {
@@ -1105,7 +1301,7 @@ impl fmt::Display for Aggregation {
             }
             Aggregation::Sample(e, distinct) => {
                 if *distinct {
-                    write!(f, "Aggregation(Distinct({}), Sum, {{}})", e)
+                    write!(f, "Aggregation(Distinct({}), Sample, {{}})", e)
                 } else {
                     write!(f, "Aggregation({}, Sample, {{}})", e)
                 }
@@ -1130,7 +1326,14 @@ impl fmt::Display for Aggregation {
                         fmt_str(s)
                     )
                 } else {
-                    write!(f, "Aggregation(Distinct({}), GroupConcat, {{}})", e)
+                    write!(f, "Aggregation({}, GroupConcat, {{}})", e)
+                }
+            }
+            Aggregation::Custom(name, e, distinct) => {
+                if *distinct {
+                    write!(f, "Aggregation(Distinct({}), {}, {{}})", e, name)
+                } else {
+                    write!(f, "Aggregation({}, {}, {{}})", e, name)
                 }
             }
         }
@@ -1213,6 +1416,13 @@ impl<'a> fmt::Display for SparqlAggregation<'a> {
                     write!(f, "GROUP_CONCAT({})", SparqlExpression(e))
                 }
             }
+            Aggregation::Custom(name, e, distinct) => {
+                if *distinct {
+                    write!(f, "{}(DISTINCT {})", name, SparqlExpression(e))
+                } else {
+                    write!(f, "{}({})", name, SparqlExpression(e))
+                }
+            }
         }
     }
 }
@@ -1221,6 +1431,7 @@ fn fmt_str(value: &str) -> rio::Literal<'_> {
     rio::Literal::Simple { value }
 }
 
+/// An `ORDER BY` condition
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum OrderComparator {
     Asc(Expression),
@@ -1253,6 +1464,9 @@ impl<'a> fmt::Display for SparqlOrderComparator<'a> {
     }
 }
 
+/// The [RDF dataset](https://www.w3.org/TR/sparql11-query/#specifyingDataset) specified with `FROM` and `FROM NAMED`
+///
+/// If it is empty, the dataset of the store is used.
 #[derive(Eq, PartialEq, Debug, Clone, Hash, Default)]
 pub struct DatasetSpec {
     pub default: Vec<NamedNode>,
@@ -1275,6 +1489,12 @@ impl DatasetSpec {
     }
 }
 
+impl DatasetSpec {
+    pub fn is_empty(&self) -> bool {
+        self.default.is_empty() && self.named.is_empty()
+    }
+}
+
 impl Add for DatasetSpec {
     type Output = Self;
 
@@ -1302,24 +1522,72 @@ const EMPTY_DATASET: DatasetSpec = DatasetSpec {
     named: Vec::new(),
 };
 
+/// Hints for the query planner given in the query text with `#pragma` comments
+///
+/// The supported pragmas are:
+/// * `#pragma join-order fixed`: the triple patterns are evaluated in the order they are written in.
+/// * `#pragma hash-join off`: the joins are always evaluated with nested loops.
+///
+/// The unknown pragmas are ignored like the other comments.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct QueryHints {
+    /// The evaluation order of the triple patterns is not optimized
+    pub fixed_join_order: bool,
+    /// The hash joins are not used
+    pub hash_join_disabled: bool,
+}
+
+impl QueryHints {
+    /// Applies the pragma `#pragma <pragma>` and returns `false` if it is not supported
+    pub(crate) fn apply_pragma(&mut self, pragma: &str) -> bool {
+        match pragma.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["join-order", "fixed"] => self.fixed_join_order = true,
+            ["join-order", "auto"] => self.fixed_join_order = false,
+            ["hash-join", "off"] => self.hash_join_disabled = true,
+            ["hash-join", "auto"] => self.hash_join_disabled = false,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl fmt::Display for QueryHints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.fixed_join_order {
+            writeln!(f, "#pragma join-order fixed")?;
+        }
+        if self.hash_join_disabled {
+            writeln!(f, "#pragma hash-join off")?;
+        }
+        Ok(())
+    }
+}
+
+/// A SPARQL query AST
+///
+/// It could be converted to a `Query` and evaluated with `prepare_parsed_query`.
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum QueryVariants {
+    /// [SELECT](https://www.w3.org/TR/sparql11-query/#select)
     Select {
         dataset: DatasetSpec,
         algebra: GraphPattern,
         base_iri: Option<Iri<String>>,
     },
+    /// [CONSTRUCT](https://www.w3.org/TR/sparql11-query/#construct)
     Construct {
         construct: Vec<TriplePattern>,
         dataset: DatasetSpec,
         algebra: GraphPattern,
         base_iri: Option<Iri<String>>,
     },
+    /// [DESCRIBE](https://www.w3.org/TR/sparql11-query/#describe)
     Describe {
         dataset: DatasetSpec,
         algebra: GraphPattern,
         base_iri: Option<Iri<String>>,
     },
+    /// [ASK](https://www.w3.org/TR/sparql11-query/#ask)
     Ask {
         dataset: DatasetSpec,
         algebra: GraphPattern,
@@ -1327,6 +1595,17 @@ pub enum QueryVariants {
     },
 }
 
+impl QueryVariants {
+    pub fn dataset(&self) -> &DatasetSpec {
+        match self {
+            QueryVariants::Select { dataset, .. }
+            | QueryVariants::Construct { dataset, .. }
+            | QueryVariants::Describe { dataset, .. }
+            | QueryVariants::Ask { dataset, .. } => dataset,
+        }
+    }
+}
+
 impl fmt::Display for QueryVariants {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -1351,17 +1630,13 @@ impl fmt::Display for QueryVariants {
                 }
                 write!(
                     f,
-                    "CONSTRUCT {{ {} }} {} WHERE {{ {} }}",
+                    "CONSTRUCT {{ {} }} {}",
                     construct
                         .iter()
                         .map(|t| t.to_string())
                         .collect::<Vec<String>>()
                         .join(" . "),
-                    dataset,
-                    SparqlGraphRootPattern {
-                        algebra,
-                        dataset: &EMPTY_DATASET
-                    }
+                    SparqlQueryPattern { algebra, dataset }
                 )
             }
             QueryVariants::Describe {
@@ -1372,15 +1647,7 @@ impl fmt::Display for QueryVariants {
                 if let Some(base_iri) = base_iri {
                     writeln!(f, "BASE <{}>", base_iri.as_str())?;
                 }
-                write!(
-                    f,
-                    "DESCRIBE * {} WHERE {{ {} }}",
-                    dataset,
-                    SparqlGraphRootPattern {
-                        algebra,
-                        dataset: &EMPTY_DATASET
-                    }
-                )
+                write!(f, "DESCRIBE * {}", SparqlQueryPattern { algebra, dataset })
             }
             QueryVariants::Ask {
                 dataset,
@@ -1390,15 +1657,223 @@ impl fmt::Display for QueryVariants {
                 if let Some(base_iri) = base_iri {
                     writeln!(f, "BASE <{}>", base_iri)?;
                 }
-                write!(
-                    f,
-                    "ASK {} WHERE {{ {} }}",
-                    dataset,
-                    SparqlGraphRootPattern {
-                        algebra,
-                        dataset: &EMPTY_DATASET
-                    }
-                )
+                write!(f, "ASK {}", SparqlQueryPattern { algebra, dataset })
+            }
+        }
+    }
+}
+
+/// A triple pattern in an optional graph used in the SPARQL update templates
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct QuadPattern {
+    pub subject: TermOrVariable,
+    pub predicate: NamedNodeOrVariable,
+    pub object: TermOrVariable,
+    pub graph_name: Option<NamedNodeOrVariable>,
+}
+
+impl QuadPattern {
+    pub fn new(
+        subject: impl Into<TermOrVariable>,
+        predicate: impl Into<NamedNodeOrVariable>,
+        object: impl Into<TermOrVariable>,
+        graph_name: Option<NamedNodeOrVariable>,
+    ) -> Self {
+        Self {
+            subject: subject.into(),
+            predicate: predicate.into(),
+            object: object.into(),
+            graph_name,
+        }
+    }
+}
+
+impl fmt::Display for QuadPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(graph_name) = &self.graph_name {
+            write!(
+                f,
+                "GRAPH {} {{ {} {} {} }}",
+                graph_name, self.subject, self.predicate, self.object
+            )
+        } else {
+            write!(f, "{} {} {}", self.subject, self.predicate, self.object)
+        }
+    }
+}
+
+struct SparqlQuadPatterns<'a>(&'a [QuadPattern]);
+
+impl<'a> fmt::Display for SparqlQuadPatterns<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pattern in self.0 {
+            write!(f, "{} . ", pattern)?;
+        }
+        Ok(())
+    }
+}
+
+/// The graphs targeted by a `CLEAR` or a `DROP` operation
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum GraphTarget {
+    NamedNode(NamedNode),
+    DefaultGraph,
+    NamedGraphs,
+    AllGraphs,
+}
+
+impl fmt::Display for GraphTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphTarget::NamedNode(node) => write!(f, "GRAPH {}", node),
+            GraphTarget::DefaultGraph => write!(f, "DEFAULT"),
+            GraphTarget::NamedGraphs => write!(f, "NAMED"),
+            GraphTarget::AllGraphs => write!(f, "ALL"),
+        }
+    }
+}
+
+struct SparqlGraphName<'a>(&'a GraphName);
+
+impl<'a> fmt::Display for SparqlGraphName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            GraphName::DefaultGraph => write!(f, "DEFAULT"),
+            graph_name => write!(f, "GRAPH {}", graph_name),
+        }
+    }
+}
+
+/// A [SPARQL 1.1 Update](https://www.w3.org/TR/sparql11-update/) operation
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum GraphUpdateOperation {
+    InsertData {
+        data: Vec<QuadPattern>,
+    },
+    DeleteData {
+        data: Vec<QuadPattern>,
+    },
+    DeleteInsert {
+        delete: Vec<QuadPattern>,
+        insert: Vec<QuadPattern>,
+        using: DatasetSpec,
+        algebra: GraphPattern,
+    },
+    Load {
+        silent: bool,
+        from: NamedNode,
+        to: Option<NamedNode>,
+    },
+    Clear {
+        silent: bool,
+        graph: GraphTarget,
+    },
+    Create {
+        silent: bool,
+        graph: NamedNode,
+    },
+    Drop {
+        silent: bool,
+        graph: GraphTarget,
+    },
+    Add {
+        silent: bool,
+        from: GraphName,
+        to: GraphName,
+    },
+    Copy {
+        silent: bool,
+        from: GraphName,
+        to: GraphName,
+    },
+    Move {
+        silent: bool,
+        from: GraphName,
+        to: GraphName,
+    },
+}
+
+impl fmt::Display for GraphUpdateOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphUpdateOperation::InsertData { data } => {
+                write!(f, "INSERT DATA {{ {}}}", SparqlQuadPatterns(data))
+            }
+            GraphUpdateOperation::DeleteData { data } => {
+                write!(f, "DELETE DATA {{ {}}}", SparqlQuadPatterns(data))
+            }
+            GraphUpdateOperation::DeleteInsert {
+                delete,
+                insert,
+                using,
+                algebra,
+            } => {
+                if !delete.is_empty() {
+                    write!(f, "DELETE {{ {}}} ", SparqlQuadPatterns(delete))?;
+                }
+                if !insert.is_empty() {
+                    write!(f, "INSERT {{ {}}} ", SparqlQuadPatterns(insert))?;
+                }
+                for g in &using.default {
+                    write!(f, "USING {} ", g)?;
+                }
+                for g in &using.named {
+                    write!(f, "USING NAMED {} ", g)?;
+                }
+                write!(f, "WHERE {{ {} }}", SparqlGraphPattern(algebra))
+            }
+            GraphUpdateOperation::Load { silent, from, to } => {
+                write!(f, "LOAD ")?;
+                if *silent {
+                    write!(f, "SILENT ")?;
+                }
+                write!(f, "{}", from)?;
+                if let Some(to) = to {
+                    write!(f, " INTO GRAPH {}", to)?;
+                }
+                Ok(())
+            }
+            GraphUpdateOperation::Clear { silent, graph } => {
+                write!(f, "CLEAR ")?;
+                if *silent {
+                    write!(f, "SILENT ")?;
+                }
+                write!(f, "{}", graph)
+            }
+            GraphUpdateOperation::Create { silent, graph } => {
+                write!(f, "CREATE ")?;
+                if *silent {
+                    write!(f, "SILENT ")?;
+                }
+                write!(f, "GRAPH {}", graph)
+            }
+            GraphUpdateOperation::Drop { silent, graph } => {
+                write!(f, "DROP ")?;
+                if *silent {
+                    write!(f, "SILENT ")?;
+                }
+                write!(f, "{}", graph)
+            }
+            GraphUpdateOperation::Add { silent, from, to } => {
+                write!(f, "ADD ")?;
+                if *silent {
+                    write!(f, "SILENT ")?;
+                }
+                write!(f, "{} TO {}", SparqlGraphName(from), SparqlGraphName(to))
+            }
+            GraphUpdateOperation::Copy { silent, from, to } => {
+                write!(f, "COPY ")?;
+                if *silent {
+                    write!(f, "SILENT ")?;
+                }
+                write!(f, "{} TO {}", SparqlGraphName(from), SparqlGraphName(to))
+            }
+            GraphUpdateOperation::Move { silent, from, to } => {
+                write!(f, "MOVE ")?;
+                if *silent {
+                    write!(f, "SILENT ")?;
+                }
+                write!(f, "{} TO {}", SparqlGraphName(from), SparqlGraphName(to))
             }
         }
     }
@@ -0,0 +1,146 @@
+//! String collations used to sort solutions with ORDER BY
+
+use crate::{Error, Result};
+#[cfg(feature = "collation")]
+use icu_collator::CollatorOptions;
+#[cfg(feature = "collation")]
+use icu_provider::DataLocale;
+use std::cmp::Ordering;
+
+/// The collation used to compare string literals in ORDER BY clauses and the MIN and MAX aggregates.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum Collation {
+    /// Compares the strings code point by code point.
+    ///
+    /// It is the default behavior.
+    Codepoint,
+    /// Compares the strings with the [Unicode Collation Algorithm](https://unicode.org/reports/tr10/)
+    /// tailored for the locale with the given [BCP 47](https://tools.ietf.org/html/bcp47) language tag.
+    ///
+    /// The strings are normalized, then the base letters are compared ignoring accents and case,
+    /// then the accents and at last the case (lower case first).
+    /// With the root collation, chosen by the `"und"` tag, `"cote" < "côte" < "Côte" < "coter"`.
+    /// The locales change these rules, e.g. `"sv"` sorts `"ö"` after `"z"`
+    /// and `"fr-CA"` compares the accents from the end of the strings.
+    ///
+    /// It requires the `"collation"` feature to be activated, the query preparation fails otherwise.
+    /// It also fails if the language tag is not valid.
+    Unicode(String),
+}
+
+impl Collation {
+    /// The `Unicode` collation for the locale with the given BCP 47 language tag
+    pub fn unicode(locale: impl Into<String>) -> Self {
+        Collation::Unicode(locale.into())
+    }
+}
+
+impl Default for Collation {
+    fn default() -> Self {
+        Collation::Codepoint
+    }
+}
+
+/// A `Collation` ready to compare strings
+#[derive(Default)]
+pub(crate) struct Collator {
+    /// The collator of the `Unicode` collation or `None` for the code point one
+    #[cfg(feature = "collation")]
+    inner: Option<icu_collator::Collator>,
+}
+
+impl Collator {
+    /// Loads the collation data
+    ///
+    /// Fails if the collation is not available with the activated features or if its locale is not valid.
+    pub(crate) fn new(collation: &Collation) -> Result<Self> {
+        match collation {
+            Collation::Codepoint => Ok(Self::default()),
+            #[cfg(feature = "collation")]
+            Collation::Unicode(locale) => {
+                let locale = locale
+                    .parse::<DataLocale>()
+                    .map_err(|error| Error::msg(format!("Invalid collation locale: {}", error)))?;
+                Ok(Self {
+                    inner: Some(
+                        icu_collator::Collator::try_new(&locale, CollatorOptions::new()).map_err(
+                            |error| {
+                                Error::msg(format!("The collation could not be loaded: {}", error))
+                            },
+                        )?,
+                    ),
+                })
+            }
+            #[cfg(not(feature = "collation"))]
+            Collation::Unicode(_) => Err(Error::msg(
+                "The Unicode collation requires the \"collation\" feature to be activated",
+            )),
+        }
+    }
+
+    /// Checks if the strings are compared code point by code point, i.e. without calling `compare`
+    pub(crate) fn is_codepoint(&self) -> bool {
+        #[cfg(feature = "collation")]
+        {
+            self.inner.is_none()
+        }
+        #[cfg(not(feature = "collation"))]
+        {
+            true
+        }
+    }
+
+    pub(crate) fn compare(&self, a: &str, b: &str) -> Ordering {
+        #[cfg(feature = "collation")]
+        {
+            if let Some(inner) = &self.inner {
+                return inner.compare(a, b);
+            }
+        }
+        a.cmp(b)
+    }
+}
+
+#[cfg(all(test, feature = "collation"))]
+mod tests {
+    use super::*;
+
+    fn sorted(locale: &str, words: &[&'static str]) -> Vec<&'static str> {
+        let collator = Collator::new(&Collation::unicode(locale)).unwrap();
+        let mut words = words.to_vec();
+        words.sort_by(|a, b| collator.compare(a, b));
+        words
+    }
+
+    #[test]
+    fn unicode_compare_levels() {
+        assert_eq!(
+            sorted(
+                "und",
+                &["coter", "Côte", "côte", "cote", "Cote", "côté", "cotë"]
+            ),
+            vec!["cote", "Cote", "cotë", "côte", "Côte", "côté", "coter"]
+        );
+    }
+
+    #[test]
+    fn unicode_compare_decomposed() {
+        let collator = Collator::new(&Collation::unicode("und")).unwrap();
+        assert_eq!(collator.compare("e\u{301}t\u{e9}", "été"), Ordering::Equal);
+        // The combining marks are all taken into account
+        assert_eq!(
+            collator.compare("a\u{323}\u{302}", "a\u{302}"),
+            Ordering::Greater
+        );
+        assert_eq!(collator.compare("Straße", "strasse"), Ordering::Greater);
+        assert_eq!(collator.compare("Straße", "strassf"), Ordering::Less);
+    }
+
+    #[test]
+    fn unicode_compare_locale() {
+        assert_eq!(sorted("und", &["zebra", "öl"]), vec!["öl", "zebra"]);
+        assert_eq!(sorted("sv", &["zebra", "öl"]), vec!["zebra", "öl"]);
+        assert_eq!(sorted("fr-CA", &["cotë", "côte"]), vec!["côte", "cotë"]);
+        assert!(Collator::new(&Collation::unicode("not a tag")).is_err());
+    }
+}
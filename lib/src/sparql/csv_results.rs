@@ -0,0 +1,147 @@
+//! Implementation of [SPARQL 1.1 Query Results CSV and TSV Formats](https://www.w3.org/TR/sparql11-results-csv-tsv/)
+
+use crate::model::*;
+use crate::sparql::model::*;
+use crate::Error;
+use crate::Result;
+use std::io::Write;
+
+pub fn write_csv_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Result<W> {
+    match results {
+        QueryResult::Solutions(solutions) => {
+            let size = solutions.variables().len();
+            let mut start_vars = true;
+            for variable in solutions.variables() {
+                if start_vars {
+                    start_vars = false;
+                } else {
+                    sink.write_all(b",")?;
+                }
+                write_escaped_csv_string(variable.as_str(), &mut sink)?;
+            }
+            sink.write_all(b"\r\n")?;
+            for solution in solutions {
+                let solution = solution?;
+                for i in 0..size {
+                    if i > 0 {
+                        sink.write_all(b",")?;
+                    }
+                    match solution.get(i) {
+                        Some(Term::NamedNode(uri)) => {
+                            write_escaped_csv_string(uri.as_str(), &mut sink)?
+                        }
+                        Some(Term::BlankNode(bnode)) => {
+                            sink.write_all(b"_:")?;
+                            sink.write_all(bnode.as_str().as_bytes())?;
+                        }
+                        Some(Term::Literal(literal)) => {
+                            write_escaped_csv_string(literal.value(), &mut sink)?
+                        }
+                        Some(Term::Triple(triple)) => {
+                            write_escaped_csv_string(&format!("<< {} >>", triple), &mut sink)?
+                        }
+                        None => (),
+                    }
+                }
+                sink.write_all(b"\r\n")?;
+            }
+        }
+        QueryResult::Boolean(_) => {
+            return Err(Error::msg(
+                "Booleans could not be formatted to SPARQL query results CSV format",
+            ));
+        }
+        QueryResult::Graph(_) => {
+            return Err(Error::msg(
+                "Graphs could not be formatted to SPARQL query results CSV format",
+            ));
+        }
+    }
+    Ok(sink)
+}
+
+fn write_escaped_csv_string(s: &str, sink: &mut impl Write) -> Result<()> {
+    if s.contains(&['"', ',', '\r', '\n'][..]) {
+        sink.write_all(b"\"")?;
+        for c in s.chars() {
+            if c == '"' {
+                sink.write_all(b"\"\"")?;
+            } else {
+                write!(sink, "{}", c)?;
+            }
+        }
+        sink.write_all(b"\"")?;
+    } else {
+        sink.write_all(s.as_bytes())?;
+    }
+    Ok(())
+}
+
+pub fn write_tsv_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Result<W> {
+    match results {
+        QueryResult::Solutions(solutions) => {
+            let size = solutions.variables().len();
+            let mut start_vars = true;
+            for variable in solutions.variables() {
+                if start_vars {
+                    start_vars = false;
+                } else {
+                    sink.write_all(b"\t")?;
+                }
+                write!(sink, "{}", variable)?;
+            }
+            sink.write_all(b"\n")?;
+            for solution in solutions {
+                let solution = solution?;
+                for i in 0..size {
+                    if i > 0 {
+                        sink.write_all(b"\t")?;
+                    }
+                    match solution.get(i) {
+                        Some(Term::NamedNode(uri)) => write!(sink, "<{}>", uri.as_str())?,
+                        Some(Term::BlankNode(bnode)) => write!(sink, "_:{}", bnode.as_str())?,
+                        Some(Term::Literal(literal)) => {
+                            write_escaped_tsv_string(literal.value(), &mut sink)?;
+                            if let Some(language) = literal.language() {
+                                write!(sink, "@{}", language)?;
+                            } else if !literal.is_plain() {
+                                write!(sink, "^^<{}>", literal.datatype().as_str())?;
+                            }
+                        }
+                        Some(Term::Triple(triple)) => write!(sink, "<< {} >>", triple)?,
+                        None => (),
+                    }
+                }
+                sink.write_all(b"\n")?;
+            }
+        }
+        QueryResult::Boolean(_) => {
+            return Err(Error::msg(
+                "Booleans could not be formatted to SPARQL query results TSV format",
+            ));
+        }
+        QueryResult::Graph(_) => {
+            return Err(Error::msg(
+                "Graphs could not be formatted to SPARQL query results TSV format",
+            ));
+        }
+    }
+    Ok(sink)
+}
+
+/// Writes a literal lexical form as a Turtle string, the tabulations and new lines are always escaped
+fn write_escaped_tsv_string(s: &str, sink: &mut impl Write) -> Result<()> {
+    sink.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '\t' => sink.write_all(b"\\t"),
+            '\n' => sink.write_all(b"\\n"),
+            '\r' => sink.write_all(b"\\r"),
+            '"' => sink.write_all(b"\\\""),
+            '\\' => sink.write_all(b"\\\\"),
+            c => write!(sink, "{}", c),
+        }?;
+    }
+    sink.write_all(b"\"")?;
+    Ok(())
+}
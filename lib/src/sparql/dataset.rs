@@ -0,0 +1,42 @@
+//! Access to the query dataset for the evaluation extensions
+
+use crate::model::*;
+use crate::sparql::plan::DatasetView;
+use crate::store::numeric_encoder::Decoder;
+use crate::store::ReadableEncodedStore;
+use crate::Result;
+
+/// Read access to the query dataset given to the extensions like the `DescribeHandler`s.
+pub trait QueryDataset {
+    /// Retrieves quads with a filter on each quad component
+    ///
+    /// The default graph is the default graph of the query dataset.
+    fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Box<dyn Iterator<Item = Result<Quad>> + '_>;
+}
+
+impl<S: ReadableEncodedStore> QueryDataset for DatasetView<S> {
+    fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Box<dyn Iterator<Item = Result<Quad>> + '_> {
+        Box::new(
+            self.quads_for_pattern_with_limit(
+                subject.map(|s| s.into()),
+                predicate.map(|p| p.into()),
+                object.map(|o| o.into()),
+                graph_name.map(|g| g.into()),
+                None,
+            )
+            .map(move |quad| self.decode_quad(&quad?)),
+        )
+    }
+}
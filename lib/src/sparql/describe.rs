@@ -0,0 +1,95 @@
+//! Extension point to customize the resource descriptions returned by DESCRIBE queries
+
+use crate::model::*;
+use crate::sparql::QueryDataset;
+use crate::Result;
+use std::collections::HashSet;
+
+/// Builds the descriptions of the resources returned by SPARQL [DESCRIBE](https://www.w3.org/TR/sparql11-query/#describe) queries.
+///
+/// The SPARQL specification leaves the content of the descriptions to the implementations.
+/// By default, the [Concise Bounded Description](https://www.w3.org/Submission/CBD/) of the resources in the default graph is returned.
+pub trait DescribeHandler {
+    /// Returns the triples describing `resource`.
+    ///
+    /// `dataset` gives access to the quads of the query dataset.
+    fn describe(&self, resource: &Term, dataset: &dyn QueryDataset) -> Result<Vec<Triple>>;
+}
+
+impl<F: Fn(&Term, &dyn QueryDataset) -> Result<Vec<Triple>>> DescribeHandler for F {
+    fn describe(&self, resource: &Term, dataset: &dyn QueryDataset) -> Result<Vec<Triple>> {
+        self(resource, dataset)
+    }
+}
+
+/// Describes the resources with their [Symmetric Concise Bounded Description](https://www.w3.org/Submission/CBD/#alternatives):
+/// the triples of the default graph with the resource as subject or object
+/// and recursively the ones of the blank nodes found at the other end of these triples.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::MemoryStore;
+/// use oxigraph::sparql::{QueryOptions, QueryResult, SymmetricConciseBoundedDescription};
+///
+/// let store = MemoryStore::new();
+/// let ex = NamedNode::new("http://example.com/ex")?;
+/// let p = NamedNode::new("http://example.com/p")?;
+/// store.insert(Quad::new(BlankNode::default(), p.clone(), ex.clone(), None));
+///
+/// let prepared_query = store.prepare_query(
+///     "DESCRIBE <http://example.com/ex>",
+///     QueryOptions::default().with_describe_handler(SymmetricConciseBoundedDescription)
+/// )?;
+/// if let QueryResult::Graph(triples) = prepared_query.exec()? {
+///     assert_eq!(triples.collect::<oxigraph::Result<Vec<_>>>()?.len(), 1);
+/// }
+/// # oxigraph::Result::Ok(())
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SymmetricConciseBoundedDescription;
+
+impl DescribeHandler for SymmetricConciseBoundedDescription {
+    fn describe(&self, resource: &Term, dataset: &dyn QueryDataset) -> Result<Vec<Triple>> {
+        let mut triples = Vec::default();
+        // The triples between two described resources are found from both sides
+        let mut already_returned = HashSet::new();
+        let mut to_describe = vec![resource.clone()];
+        let mut already_described = HashSet::new();
+        already_described.insert(resource.clone());
+        while let Some(resource) = to_describe.pop() {
+            let subject = match &resource {
+                Term::NamedNode(node) => Some(Subject::from(node.clone())),
+                Term::BlankNode(node) => Some(Subject::from(node.clone())),
+                Term::Triple(triple) => Some(Subject::Triple(triple.clone())),
+                Term::Literal(_) => None,
+            };
+            let outgoing = subject.map(|subject| {
+                dataset.quads_for_pattern(
+                    Some(&subject),
+                    None,
+                    None,
+                    Some(&GraphName::DefaultGraph),
+                )
+            });
+            let incoming = dataset.quads_for_pattern(
+                None,
+                None,
+                Some(&resource),
+                Some(&GraphName::DefaultGraph),
+            );
+            for quad in outgoing.into_iter().flatten().chain(incoming) {
+                let triple = Triple::from(quad?);
+                for neighbour in &[Term::from(triple.subject.clone()), triple.object.clone()] {
+                    if neighbour.is_blank_node() && already_described.insert(neighbour.clone()) {
+                        to_describe.push(neighbour.clone());
+                    }
+                }
+                if already_returned.insert(triple.clone()) {
+                    triples.push(triple);
+                }
+            }
+        }
+        Ok(triples)
+    }
+}
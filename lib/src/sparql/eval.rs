@@ -1,12 +1,22 @@
+use crate::model::vocab::xsd;
 use crate::model::xsd::*;
 use crate::model::BlankNode;
+use crate::model::GraphName;
+use crate::model::NamedNode;
+use crate::model::Term;
 use crate::model::Triple;
 use crate::sparql::algebra::GraphPattern;
+use crate::sparql::collation::Collator;
+use crate::sparql::explain::{
+    PlanExplainer, PlanNodeProfile, PlanProfiler, QueryExplanation, QueryStats, QueryStatsRecorder,
+};
+use crate::sparql::geo::{self, Geometry};
 use crate::sparql::model::*;
 use crate::sparql::plan::*;
-use crate::sparql::ServiceHandler;
+use crate::sparql::sort::sort_tuples;
+use crate::sparql::{CustomAggregate, DescribeHandler, PropertyFunction, ServiceHandler};
 use crate::store::numeric_encoder::*;
-use crate::store::ReadableEncodedStore;
+use crate::store::{literal_envelope, literal_words, text_words, ReadableEncodedStore};
 use crate::Error;
 use crate::Result;
 use digest::Digest;
@@ -18,13 +28,17 @@ use regex::{Regex, RegexBuilder};
 use rio_api::model as rio;
 use sha1::Sha1;
 use sha2::{Sha256, Sha384, Sha512};
+use std::any::Any;
 use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
-use std::convert::{TryFrom, TryInto};
-use std::hash::Hash;
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
 use std::iter::Iterator;
 use std::iter::{empty, once};
+use std::rc::Rc;
 use std::str;
+use std::time::Instant;
 
 const REGEX_SIZE_LIMIT: usize = 1_000_000;
 
@@ -34,20 +48,48 @@ pub(crate) struct SimpleEvaluator<S: ReadableEncodedStore> {
     dataset: DatasetView<S>,
     base_iri: Option<Iri<String>>,
     now: DateTime,
-    service_handler: Box<dyn ServiceHandler>,
+    blank_node_seed: u128,
+    service_handler: Rc<dyn ServiceHandler>,
+    custom_aggregates: Rc<HashMap<NamedNode, Rc<CustomAggregate>>>,
+    property_functions: Rc<HashMap<NamedNode, Rc<dyn PropertyFunction>>>,
+    collator: Collator,
+    sort_memory_limit: Option<usize>,
+    profiler: Option<PlanProfiler>,
+    stats: Option<QueryStatsRecorder>,
 }
 
 impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
     pub fn new(
         dataset: DatasetView<S>,
         base_iri: Option<Iri<String>>,
-        service_handler: Box<dyn ServiceHandler>,
+        service_handler: Rc<dyn ServiceHandler>,
+        custom_aggregates: Rc<HashMap<NamedNode, Rc<CustomAggregate>>>,
+        property_functions: Rc<HashMap<NamedNode, Rc<dyn PropertyFunction>>>,
+        collator: Collator,
+        sort_memory_limit: Option<usize>,
+        profiling: bool,
+        stats: bool,
     ) -> Self {
         Self {
             dataset,
             base_iri,
             now: DateTime::now().unwrap(),
+            blank_node_seed: random(),
             service_handler,
+            custom_aggregates,
+            property_functions,
+            collator,
+            sort_memory_limit,
+            profiler: if profiling {
+                Some(PlanProfiler::default())
+            } else {
+                None
+            },
+            stats: if stats {
+                Some(QueryStatsRecorder::default())
+            } else {
+                None
+            },
         }
     }
 
@@ -55,27 +97,36 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
         &'b self,
         plan: &'b PlanNode,
         variables: &[Variable],
+        bindings: &[(Variable, EncodedTerm)],
     ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
-        let iter = self.eval_plan(plan, EncodedTuple::with_capacity(variables.len()));
-        Ok(QueryResult::Solutions(
-            self.decode_bindings(iter, variables.to_vec()),
-        ))
+        let start = self.start_stats();
+        let iter = self.eval_plan_with_bindings(plan, variables, bindings);
+        let (variables, iter) = self.decode_bindings(iter, variables.to_vec()).destruct();
+        self.add_stats_duration(start);
+        Ok(QueryResult::Solutions(QuerySolutionsIterator::new(
+            variables,
+            self.timed(iter),
+        )))
     }
 
-    pub fn evaluate_ask_plan<'b>(&'b self, plan: &'b PlanNode) -> Result<QueryResult<'b>>
+    pub fn evaluate_ask_plan<'b>(
+        &'b self,
+        plan: &'b PlanNode,
+        variables: &[Variable],
+        bindings: &[(Variable, EncodedTerm)],
+    ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
-        match self
-            .eval_plan(
-                plan,
-                EncodedTuple::with_capacity(plan.maybe_bound_variables().len()),
-            )
-            .next()
-        {
+        let start = self.start_stats();
+        let result = self
+            .eval_plan_with_bindings(plan, variables, bindings)
+            .next();
+        self.add_stats_duration(start);
+        match result {
             Some(Ok(_)) => Ok(QueryResult::Boolean(true)),
             Some(Err(error)) => Err(error),
             None => Ok(QueryResult::Boolean(false)),
@@ -85,44 +136,260 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
     pub fn evaluate_construct_plan<'b>(
         &'b self,
         plan: &'b PlanNode,
+        variables: &[Variable],
+        bindings: &[(Variable, EncodedTerm)],
         construct: &'b [TripleTemplate],
     ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
-        Ok(QueryResult::Graph(Box::new(ConstructIterator {
+        let start = self.start_stats();
+        let iter = ConstructIterator {
             eval: self,
-            iter: self.eval_plan(
-                plan,
-                EncodedTuple::with_capacity(plan.maybe_bound_variables().len()),
-            ),
+            iter: self.eval_plan_with_bindings(plan, variables, bindings),
             template: construct,
             buffered_results: Vec::default(),
             bnodes: Vec::default(),
-        })))
+        };
+        self.add_stats_duration(start);
+        Ok(QueryResult::Graph(self.timed(iter)))
     }
 
-    pub fn evaluate_describe_plan<'b>(&'b self, plan: &'b PlanNode) -> Result<QueryResult<'b>>
+    pub fn evaluate_describe_plan<'b>(
+        &'b self,
+        plan: &'b PlanNode,
+        variables: &[Variable],
+        bindings: &[(Variable, EncodedTerm)],
+        describe_handler: Option<&'b dyn DescribeHandler>,
+    ) -> Result<QueryResult<'b>>
     where
         'a: 'b,
     {
-        Ok(QueryResult::Graph(Box::new(DescribeIterator {
-            eval: self,
-            iter: self.eval_plan(
-                plan,
-                EncodedTuple::with_capacity(plan.maybe_bound_variables().len()),
+        let start = self.start_stats();
+        let iter = self.eval_plan_with_bindings(plan, variables, bindings);
+        let iter: Box<dyn Iterator<Item = Result<Triple>> + 'b> =
+            if let Some(describe_handler) = describe_handler {
+                Box::new(CustomDescribeIterator {
+                    eval: self,
+                    iter,
+                    describe_handler,
+                    triples: Vec::default().into_iter(),
+                    to_describe: Vec::default(),
+                    already_described: HashSet::default(),
+                })
+            } else {
+                Box::new(DescribeIterator {
+                    eval: self,
+                    iter,
+                    quads: Box::new(empty()),
+                    to_describe: Vec::default(),
+                    already_described: HashSet::default(),
+                })
+            };
+        self.add_stats_duration(start);
+        Ok(QueryResult::Graph(self.timed(iter)))
+    }
+
+    /// Encodes the values of a solution in order to use them as initial bindings of an evaluation
+    pub fn encode_initial_bindings(
+        &self,
+        bindings: &QuerySolution,
+    ) -> Result<Vec<(Variable, EncodedTerm)>> {
+        let mut encoder = self.dataset.encoder();
+        bindings
+            .iter()
+            .map(|(variable, value)| Ok((variable.clone(), encoder.encode_term(value)?)))
+            .collect()
+    }
+
+    /// Returns the metrics of the last evaluation if they are recorded
+    pub fn stats(&self) -> QueryStats {
+        self.stats
+            .as_ref()
+            .map_or_else(QueryStats::default, QueryStatsRecorder::stats)
+    }
+
+    /// Resets the metrics and the profile for a new evaluation and returns its start time if the metrics are recorded
+    fn start_stats(&self) -> Option<Instant> {
+        if let Some(profiler) = &self.profiler {
+            profiler.reset();
+        }
+        let stats = self.stats.as_ref()?;
+        stats.reset();
+        Some(Instant::now())
+    }
+
+    fn add_stats_duration(&self, start: Option<Instant>) {
+        if let (Some(stats), Some(start)) = (&self.stats, start) {
+            stats.add_duration(start.elapsed());
+        }
+    }
+
+    pub fn explain_plan(
+        &self,
+        plan: &PlanNode,
+        variables: &[Variable],
+    ) -> Result<QueryExplanation> {
+        PlanExplainer::new(&self.dataset, self.profiler.as_ref()).explain(plan, variables)
+    }
+
+    /// Evaluates the root of a query plan with some initial bindings
+    ///
+    /// The bindings are injected below the query solution modifiers (DISTINCT, OFFSET, LIMIT and the projection)
+    /// in order to apply to all the variables of the WHERE clause, even the ones that are not projected.
+    fn eval_plan_with_bindings<'b>(
+        &'b self,
+        node: &'b PlanNode,
+        variables: &[Variable],
+        bindings: &[(Variable, EncodedTerm)],
+    ) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        self.profile(node, || {
+            self.eval_node_with_bindings(node, variables, bindings)
+        })
+    }
+
+    fn eval_node_with_bindings<'b>(
+        &'b self,
+        node: &'b PlanNode,
+        variables: &[Variable],
+        bindings: &[(Variable, EncodedTerm)],
+    ) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        match node {
+            PlanNode::HashDeduplicate { child } => Box::new(hash_deduplicate(
+                self.eval_plan_with_bindings(&*child, variables, bindings),
+            )),
+            PlanNode::Skip { child, count } => Box::new(
+                self.eval_plan_with_bindings(&*child, variables, bindings)
+                    .skip(*count),
             ),
-            quads: Box::new(empty()),
-        })))
+            PlanNode::Limit { child, count } => Box::new(
+                self.eval_plan_with_bindings(&*child, variables, bindings)
+                    .take(*count),
+            ),
+            PlanNode::Project {
+                child,
+                mapping,
+                child_variables,
+            } => {
+                let output_size = variables.len();
+                Box::new(
+                    self.eval_plan(&*child, bindings_tuple(child_variables, bindings))
+                        .map(move |tuple| {
+                            let tuple = tuple?;
+                            let mut output_tuple = EncodedTuple::with_capacity(output_size);
+                            for (input_key, output_key) in mapping.iter() {
+                                if let Some(value) = tuple.get(*input_key) {
+                                    output_tuple.set(*output_key, value)
+                                }
+                            }
+                            Ok(output_tuple)
+                        }),
+                )
+            }
+            node => self.eval_node(node, bindings_tuple(variables, bindings)),
+        }
     }
 
     fn eval_plan<'b>(&'b self, node: &'b PlanNode, from: EncodedTuple) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        self.profile(node, || self.eval_node(node, from))
+    }
+
+    /// Records the rows returned by the evaluation of `node` if the metrics are recorded and, if profiling is enabled, the time spent in it
+    ///
+    /// The iterator is returned unchanged if the metrics are not recorded and profiling is disabled.
+    fn profile<'b>(
+        &'b self,
+        node: &'b PlanNode,
+        eval: impl FnOnce() -> EncodedTuplesIterator<'b>,
+    ) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        if let Some(profiler) = &self.profiler {
+            let profile = profiler.node(node);
+            let start = Instant::now();
+            let iter = eval();
+            profile.add_duration(start.elapsed());
+            Box::new(ProfiledIterator {
+                iter,
+                profile: Some(profile),
+                stats: self.stats.as_ref(),
+            })
+        } else if self.stats.is_some() {
+            Box::new(ProfiledIterator {
+                iter: eval(),
+                profile: None,
+                stats: self.stats.as_ref(),
+            })
+        } else {
+            eval()
+        }
+    }
+
+    /// Adds the time spent in the iteration of `iter` to the query duration if the metrics are recorded
+    fn timed<'b, T: 'b>(
+        &'b self,
+        iter: impl Iterator<Item = T> + 'b,
+    ) -> Box<dyn Iterator<Item = T> + 'b> {
+        if let Some(stats) = &self.stats {
+            let mut iter = iter;
+            Box::new(std::iter::from_fn(move || {
+                let start = Instant::now();
+                let result = iter.next();
+                stats.add_duration(start.elapsed());
+                result
+            }))
+        } else {
+            Box::new(iter)
+        }
+    }
+
+    fn quads_for_pattern<'b>(
+        &'b self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'b> {
+        self.quads_for_pattern_with_limit(subject, predicate, object, graph_name, None)
+    }
+
+    fn quads_for_pattern_with_limit<'b>(
+        &'b self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: Option<usize>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'b> {
+        if let Some(stats) = &self.stats {
+            stats.add_quad_pattern_lookup();
+        }
+        self.dataset
+            .quads_for_pattern_with_limit(subject, predicate, object, graph_name, limit)
+    }
+
+    fn eval_node<'b>(&'b self, node: &'b PlanNode, from: EncodedTuple) -> EncodedTuplesIterator<'b>
     where
         'a: 'b,
     {
         match node {
             PlanNode::Init => Box::new(once(Ok(from))),
-            PlanNode::StaticBindings { tuples } => Box::new(tuples.iter().cloned().map(Ok)),
+            PlanNode::StaticBindings { tuples } => Box::new(
+                tuples
+                    .iter()
+                    .filter_map(move |tuple| tuple.combine_with(&from))
+                    .map(Ok),
+            ),
             PlanNode::Service {
                 variables,
                 silent,
@@ -149,12 +416,14 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 predicate,
                 object,
                 graph_name,
+                limit,
             } => Box::new(self.eval_plan(&*child, from).flat_map_ok(move |tuple| {
-                let mut iter = self.dataset.quads_for_pattern(
+                let mut iter = self.quads_for_pattern_with_limit(
                     get_pattern_value(subject, &tuple),
                     get_pattern_value(predicate, &tuple),
                     get_pattern_value(object, &tuple),
                     get_pattern_value(graph_name, &tuple),
+                    *limit,
                 );
                 if subject.is_var() && subject == predicate {
                     iter = Box::new(iter.filter(|quad| match quad {
@@ -211,60 +480,183 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 path,
                 object,
                 graph_name,
-            } => Box::new(self.eval_plan(&*child, from).flat_map_ok(move |tuple| {
-                let input_subject = get_pattern_value(subject, &tuple);
-                let input_object = get_pattern_value(object, &tuple);
-                let input_graph_name =
-                    if let Some(graph_name) = get_pattern_value(graph_name, &tuple) {
-                        graph_name
+            } => {
+                let mut named_graphs = None;
+                Box::new(self.eval_plan(&*child, from).flat_map_ok(
+                    move |tuple| -> EncodedTuplesIterator<'_> {
+                        if let Some(input_graph_name) = get_pattern_value(graph_name, &tuple) {
+                            self.eval_path_pattern(path, subject, object, input_graph_name, tuple)
+                        } else {
+                            // The graph name is not bound: we evaluate the path in each named graph
+                            let named_graphs = match self.named_graph_names(&mut named_graphs) {
+                                Ok(named_graphs) => named_graphs,
+                                Err(error) => return Box::new(once(Err(error))),
+                            };
+                            Box::new(named_graphs.flat_map_ok(move |input_graph_name| {
+                                let mut tuple = tuple.clone();
+                                put_pattern_value(graph_name, input_graph_name, &mut tuple);
+                                self.eval_path_pattern(
+                                    path,
+                                    subject,
+                                    object,
+                                    input_graph_name,
+                                    tuple,
+                                )
+                            }))
+                        }
+                    },
+                ))
+            }
+            PlanNode::FullTextSearch {
+                child,
+                literal,
+                query,
+                graph_name,
+            } => Box::new(self.eval_plan(&*child, from).flat_map_ok(
+                move |tuple| -> EncodedTuplesIterator<'_> {
+                    let query =
+                        match get_pattern_value(query, &tuple) {
+                            Some(EncodedTerm::StringLiteral { value_id })
+                            | Some(EncodedTerm::LangStringLiteral { value_id, .. }) => {
+                                match self.dataset.get_str(value_id) {
+                                    Ok(Some(query)) => query,
+                                    Ok(None) => return Box::new(empty()),
+                                    Err(error) => return Box::new(once(Err(error))),
+                                }
+                            }
+                            Some(_) => return Box::new(empty()),
+                            None => return Box::new(once(Err(Error::msg(
+                                "The full-text search query should be bound to a string literal",
+                            )))),
+                        };
+                    if let Some(value) = get_pattern_value(literal, &tuple) {
+                        let words: HashSet<String> = text_words(&query).collect();
+                        match literal_words(&self.dataset, value) {
+                            Ok(Some(literal_words))
+                                if !words.is_empty() && words.is_subset(&literal_words) =>
+                            {
+                                self.eval_full_text_match(*literal, *graph_name, value, tuple)
+                            }
+                            Ok(_) => Box::new(empty()),
+                            Err(error) => Box::new(once(Err(error))),
+                        }
                     } else {
-                        let result: EncodedTuplesIterator<'_> = Box::new(once(Err(Error::msg(
-                            "Unknown graph name is not allowed when evaluating property path",
-                        ))));
-                        return result;
-                    };
-                match (input_subject, input_object) {
-                    (Some(input_subject), Some(input_object)) => Box::new(
-                        self.eval_path_from(path, input_subject, input_graph_name)
-                            .filter_map(move |o| match o {
-                                Ok(o) => {
-                                    if o == input_object {
-                                        Some(Ok(tuple.clone()))
+                        Box::new(self.dataset.literals_matching(&query).flat_map(
+                            move |value| -> EncodedTuplesIterator<'_> {
+                                match value {
+                                    Ok(value) => self.eval_full_text_match(
+                                        *literal,
+                                        *graph_name,
+                                        value,
+                                        tuple.clone(),
+                                    ),
+                                    Err(error) => Box::new(once(Err(error))),
+                                }
+                            },
+                        ))
+                    }
+                },
+            )),
+            PlanNode::SpatialSearch {
+                child,
+                literal,
+                envelope,
+                within,
+            } => {
+                Box::new(self.eval_plan(&*child, from).flat_map_ok(
+                    move |tuple| -> EncodedTuplesIterator<'_> {
+                        if let Some(value) = get_pattern_value(literal, &tuple) {
+                            match literal_envelope(&self.dataset, value) {
+                                Ok(Some(value_envelope)) => {
+                                    let is_selected = if *within {
+                                        envelope.contains(&value_envelope)
                                     } else {
-                                        None
+                                        envelope.intersects(&value_envelope)
+                                    };
+                                    if is_selected {
+                                        Box::new(once(Ok(tuple)))
+                                    } else {
+                                        Box::new(empty())
                                     }
                                 }
-                                Err(error) => Some(Err(error)),
-                            }),
-                    ),
-                    (Some(input_subject), None) => Box::new(
-                        self.eval_path_from(path, input_subject, input_graph_name)
-                            .map(move |o| {
-                                let mut new_tuple = tuple.clone();
-                                put_pattern_value(object, o?, &mut new_tuple);
-                                Ok(new_tuple)
-                            }),
-                    ),
-                    (None, Some(input_object)) => Box::new(
-                        self.eval_path_to(path, input_object, input_graph_name)
-                            .map(move |s| {
-                                let mut new_tuple = tuple.clone();
-                                put_pattern_value(subject, s?, &mut new_tuple);
-                                Ok(new_tuple)
-                            }),
-                    ),
-                    (None, None) => {
-                        Box::new(self.eval_open_path(path, input_graph_name).map(move |so| {
-                            let mut new_tuple = tuple.clone();
-                            so.map(move |(s, o)| {
-                                put_pattern_value(subject, s, &mut new_tuple);
-                                put_pattern_value(object, o, &mut new_tuple);
-                                new_tuple
-                            })
-                        }))
+                                Ok(None) => Box::new(empty()),
+                                Err(error) => Box::new(once(Err(error))),
+                            }
+                        } else {
+                            Box::new(self.dataset.geometries_in(*envelope, *within).map(
+                                move |value| {
+                                    let mut tuple = tuple.clone();
+                                    put_pattern_value(literal, value?, &mut tuple);
+                                    Ok(tuple)
+                                },
+                            ))
+                        }
+                    },
+                ))
+            }
+            PlanNode::QuotedTriplePattern {
+                child,
+                triple,
+                subject,
+                predicate,
+                object,
+            } => Box::new(self.eval_plan(&*child, from).flat_map_ok(
+                move |tuple| -> EncodedTuplesIterator<'_> {
+                    match self.eval_quoted_triple_pattern(triple, subject, predicate, object, tuple)
+                    {
+                        Ok(Some(tuple)) => Box::new(once(Ok(tuple))),
+                        Ok(None) => Box::new(empty()),
+                        Err(error) => Box::new(once(Err(error))),
                     }
-                }
-            })),
+                },
+            )),
+            PlanNode::PropertyFunction {
+                child,
+                subject,
+                function,
+                object,
+                graph_name,
+            } => {
+                let mut named_graphs = None;
+                Box::new(self.eval_plan(&*child, from).flat_map_ok(
+                    move |tuple| -> EncodedTuplesIterator<'_> {
+                        if let Some(input_graph_name) = get_pattern_value(graph_name, &tuple) {
+                            match self.eval_property_function(
+                                function,
+                                subject,
+                                object,
+                                input_graph_name,
+                                &tuple,
+                            ) {
+                                Ok(tuples) => Box::new(tuples.into_iter().map(Ok)),
+                                Err(error) => Box::new(once(Err(error))),
+                            }
+                        } else {
+                            // The graph name is not bound: we evaluate the function in each named graph
+                            let named_graphs = match self.named_graph_names(&mut named_graphs) {
+                                Ok(named_graphs) => named_graphs,
+                                Err(error) => return Box::new(once(Err(error))),
+                            };
+                            Box::new(named_graphs.flat_map_ok(
+                                move |input_graph_name| -> EncodedTuplesIterator<'_> {
+                                    let mut tuple = tuple.clone();
+                                    put_pattern_value(graph_name, input_graph_name, &mut tuple);
+                                    match self.eval_property_function(
+                                        function,
+                                        subject,
+                                        object,
+                                        input_graph_name,
+                                        &tuple,
+                                    ) {
+                                        Ok(tuples) => Box::new(tuples.into_iter().map(Ok)),
+                                        Err(error) => Box::new(once(Err(error))),
+                                    }
+                                },
+                            ))
+                        }
+                    },
+                ))
+            }
             PlanNode::Join { left, right } => {
                 //TODO: very dumb implementation
                 let mut errors = Vec::default();
@@ -284,16 +676,43 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                     buffered_results: errors,
                 })
             }
+            PlanNode::HashJoin { left, right, keys } => {
+                let mut errors = Vec::default();
+                let mut right_values = HashMap::<_, Vec<_>>::default();
+                for result in self.eval_plan(&*right, from.clone()) {
+                    match result {
+                        Ok(tuple) => right_values
+                            .entry(tuple_key(&tuple, keys))
+                            .or_default()
+                            .push(tuple),
+                        Err(error) => errors.push(Err(error)),
+                    }
+                }
+                Box::new(HashJoinIterator {
+                    left_iter: self.eval_plan(&*left, from),
+                    right: right_values,
+                    keys,
+                    buffered_results: errors,
+                })
+            }
             PlanNode::AntiJoin { left, right } => {
                 //TODO: dumb implementation
+                // The right side of MINUS is evaluated independently of the current bindings
+                let mut errors = Vec::default();
                 let right: Vec<_> = self
-                    .eval_plan(&*right, from.clone())
-                    .filter_map(|result| result.ok())
+                    .eval_plan(&*right, EncodedTuple::with_capacity(from.capacity()))
+                    .filter_map(|result| match result {
+                        Ok(result) => Some(result),
+                        Err(error) => {
+                            errors.push(Err(error));
+                            None
+                        }
+                    })
                     .collect();
-                Box::new(AntiJoinIterator {
+                Box::new(errors.into_iter().chain(AntiJoinIterator {
                     left_iter: self.eval_plan(&*left, from),
                     right,
-                })
+                }))
             }
             PlanNode::LeftJoin {
                 left,
@@ -352,72 +771,91 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 }))
             }
             PlanNode::Sort { child, by } => {
-                let mut errors = Vec::default();
-                let mut values = self
-                    .eval_plan(&*child, from)
-                    .filter_map(|result| match result {
-                        Ok(result) => Some(result),
-                        Err(error) => {
-                            errors.push(Err(error));
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>();
-                values.sort_unstable_by(|a, b| {
-                    for comp in by {
-                        match comp {
-                            Comparator::Asc(expression) => {
-                                match self.cmp_according_to_expression(a, b, expression) {
-                                    Ordering::Greater => return Ordering::Greater,
-                                    Ordering::Less => return Ordering::Less,
-                                    Ordering::Equal => (),
+                let eval = self;
+                sort_tuples(
+                    self.eval_plan(&*child, from),
+                    self.sort_memory_limit,
+                    move |a, b| {
+                        for comp in by {
+                            match comp {
+                                Comparator::Asc(expression) => {
+                                    match eval.cmp_according_to_expression(a, b, expression) {
+                                        Ordering::Greater => return Ordering::Greater,
+                                        Ordering::Less => return Ordering::Less,
+                                        Ordering::Equal => (),
+                                    }
                                 }
-                            }
-                            Comparator::Desc(expression) => {
-                                match self.cmp_according_to_expression(a, b, expression) {
-                                    Ordering::Greater => return Ordering::Less,
-                                    Ordering::Less => return Ordering::Greater,
-                                    Ordering::Equal => (),
+                                Comparator::Desc(expression) => {
+                                    match eval.cmp_according_to_expression(a, b, expression) {
+                                        Ordering::Greater => return Ordering::Less,
+                                        Ordering::Less => return Ordering::Greater,
+                                        Ordering::Equal => (),
+                                    }
                                 }
                             }
                         }
-                    }
-                    Ordering::Equal
-                });
-                Box::new(errors.into_iter().chain(values.into_iter().map(Ok)))
-            }
-            PlanNode::HashDeduplicate { child } => {
-                Box::new(hash_deduplicate(self.eval_plan(&*child, from)))
-            }
-            PlanNode::Skip { child, count } => Box::new(self.eval_plan(&*child, from).skip(*count)),
-            PlanNode::Limit { child, count } => {
-                Box::new(self.eval_plan(&*child, from).take(*count))
+                        Ordering::Equal
+                    },
+                )
             }
-            PlanNode::Project { child, mapping } => {
-                //TODO: use from somewhere?
+            // These nodes are the solution modifiers of a sub-query:
+            // the sub-query is evaluated without the outer bindings and its solutions are then joined with them
+            PlanNode::HashDeduplicate { child } => join_with_outer_bindings(
+                from.clone(),
+                Box::new(hash_deduplicate(self.eval_plan(
+                    &*child,
+                    EncodedTuple::with_capacity(from.capacity()),
+                ))),
+            ),
+            PlanNode::Skip { child, count } => join_with_outer_bindings(
+                from.clone(),
                 Box::new(
-                    self.eval_plan(&*child, EncodedTuple::with_capacity(mapping.len()))
-                        .map(move |tuple| {
-                            let tuple = tuple?;
-                            let mut output_tuple = EncodedTuple::with_capacity(from.capacity());
-                            for (input_key, output_key) in mapping.iter() {
-                                if let Some(value) = tuple.get(*input_key) {
-                                    output_tuple.set(*output_key, value)
+                    self.eval_plan(&*child, EncodedTuple::with_capacity(from.capacity()))
+                        .skip(*count),
+                ),
+            ),
+            PlanNode::Limit { child, count } => join_with_outer_bindings(
+                from.clone(),
+                Box::new(
+                    self.eval_plan(&*child, EncodedTuple::with_capacity(from.capacity()))
+                        .take(*count),
+                ),
+            ),
+            PlanNode::Project { child, mapping, .. } => {
+                let output_size = from.capacity();
+                join_with_outer_bindings(
+                    from,
+                    Box::new(
+                        self.eval_plan(&*child, EncodedTuple::with_capacity(mapping.len()))
+                            .map(move |tuple| {
+                                let tuple = tuple?;
+                                let mut output_tuple = EncodedTuple::with_capacity(output_size);
+                                for (input_key, output_key) in mapping.iter() {
+                                    if let Some(value) = tuple.get(*input_key) {
+                                        output_tuple.set(*output_key, value)
+                                    }
                                 }
-                            }
-                            Ok(output_tuple)
-                        }),
+                                Ok(output_tuple)
+                            }),
+                    ),
                 )
             }
             PlanNode::Aggregate {
                 child,
                 key_mapping,
                 aggregates,
+                ..
             } => {
                 let tuple_size = from.capacity(); //TODO: not nice
                 let mut errors = Vec::default();
                 let mut accumulators_for_group =
                     HashMap::<Vec<Option<EncodedTerm>>, Vec<Box<dyn Accumulator>>>::default();
+                // COUNT(DISTINCT *) requires to keep track of the solutions already seen in each group
+                let count_distinct_solutions = aggregates
+                    .iter()
+                    .any(|(aggregate, _)| aggregate.distinct && aggregate.parameter.is_none());
+                let mut solutions_for_group =
+                    HashMap::<Vec<Option<EncodedTerm>>, HashSet<EncodedTuple>>::default();
                 self.eval_plan(child, from)
                     .filter_map(|result| match result {
                         Ok(result) => Some(result),
@@ -428,33 +866,39 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                     })
                     .for_each(|tuple| {
                         //TODO avoid copy for key?
-                        let key = key_mapping.iter().map(|(v, _)| tuple.get(*v)).collect();
+                        let key: Vec<_> = key_mapping.iter().map(|(v, _)| tuple.get(*v)).collect();
+                        let is_new_solution = !count_distinct_solutions
+                            || solutions_for_group
+                                .entry(key.clone())
+                                .or_insert_with(HashSet::default)
+                                .insert(tuple.clone());
 
                         let key_accumulators =
                             accumulators_for_group.entry(key).or_insert_with(|| {
                                 aggregates
                                     .iter()
-                                    .map(|(aggregate, _)| {
-                                        self.accumulator_for_aggregate(
-                                            &aggregate.function,
-                                            aggregate.distinct,
-                                        )
-                                    })
+                                    .map(|(aggregate, _)| self.accumulator_for_aggregate(aggregate))
                                     .collect::<Vec<_>>()
                             });
                         for (i, accumulator) in key_accumulators.iter_mut().enumerate() {
                             let (aggregate, _) = &aggregates[i];
-                            accumulator.add(
-                                aggregate
-                                    .parameter
-                                    .as_ref()
-                                    .and_then(|parameter| self.eval_expression(parameter, &tuple)),
-                            );
+                            if let Some(parameter) = &aggregate.parameter {
+                                accumulator.add(self.eval_expression(parameter, &tuple));
+                            } else if !aggregate.distinct || is_new_solution {
+                                // COUNT(*) counts the solutions and not the values of an expression
+                                accumulator.add(Some(true.into()));
+                            }
                         }
                     });
-                if accumulators_for_group.is_empty() {
-                    // There is always at least one group
-                    accumulators_for_group.insert(vec![None; key_mapping.len()], Vec::default());
+                if accumulators_for_group.is_empty() && key_mapping.is_empty() {
+                    // Without GROUP BY there is always exactly one group, even if it is empty
+                    accumulators_for_group.insert(
+                        Vec::default(),
+                        aggregates
+                            .iter()
+                            .map(|(aggregate, _)| self.accumulator_for_aggregate(aggregate))
+                            .collect(),
+                    );
                 }
                 Box::new(
                     errors
@@ -468,7 +912,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                                         result.set(*to_position, value);
                                     }
                                 }
-                                for (i, accumulator) in accumulators.into_iter().enumerate() {
+                                for (i, mut accumulator) in accumulators.into_iter().enumerate() {
                                     if let Some(value) = accumulator.state() {
                                         result.set(aggregates[i].1, value);
                                     }
@@ -500,10 +944,11 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
 
     fn accumulator_for_aggregate<'b>(
         &'b self,
-        function: &'b PlanAggregationFunction,
-        distinct: bool,
+        aggregate: &'b PlanAggregation,
     ) -> Box<dyn Accumulator + 'b> {
-        match function {
+        // For COUNT(DISTINCT *) the distinct solutions are filtered before the accumulator
+        let distinct = aggregate.distinct && aggregate.parameter.is_some();
+        match &aggregate.function {
             PlanAggregationFunction::Count => {
                 if distinct {
                     Box::new(DistinctAccumulator::new(CountAccumulator::default()))
@@ -513,18 +958,18 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
             }
             PlanAggregationFunction::Sum => {
                 if distinct {
-                    Box::new(DistinctAccumulator::new(SumAccumulator::default()))
+                    Box::new(DistinctAccumulator::new(SumAccumulator::new(self)))
                 } else {
-                    Box::new(SumAccumulator::default())
+                    Box::new(SumAccumulator::new(self))
                 }
             }
             PlanAggregationFunction::Min => Box::new(MinAccumulator::new(self)), // DISTINCT does not make sense with min
             PlanAggregationFunction::Max => Box::new(MaxAccumulator::new(self)), // DISTINCT does not make sense with max
             PlanAggregationFunction::Avg => {
                 if distinct {
-                    Box::new(DistinctAccumulator::new(AvgAccumulator::default()))
+                    Box::new(DistinctAccumulator::new(AvgAccumulator::new(self)))
                 } else {
-                    Box::new(AvgAccumulator::default())
+                    Box::new(AvgAccumulator::new(self))
                 }
             }
             PlanAggregationFunction::Sample => Box::new(SampleAccumulator::default()), // DISTINCT does not make sense with sample
@@ -537,6 +982,215 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                     Box::new(GroupConcatAccumulator::new(self, separator))
                 }
             }
+            PlanAggregationFunction::Custom(name) => {
+                // Unknown custom aggregates are evaluated as errors
+                let aggregate = self.custom_aggregates.get(name).map(|a| a.as_ref());
+                if distinct {
+                    Box::new(DistinctAccumulator::new(CustomAccumulator::new(
+                        self, aggregate,
+                    )))
+                } else {
+                    Box::new(CustomAccumulator::new(self, aggregate))
+                }
+            }
+        }
+    }
+
+    /// Unifies the quoted triple `triple` with its `subject`, `predicate` and `object` in `tuple`
+    ///
+    /// Returns `None` if they do not match.
+    /// Binds the full-text search literal and graph if the literal is the object of a quad of the graph
+    fn eval_full_text_match<'b>(
+        &'b self,
+        literal: PatternValue,
+        graph_name: PatternValue,
+        value: EncodedTerm,
+        mut tuple: EncodedTuple,
+    ) -> EncodedTuplesIterator<'b> {
+        put_pattern_value(&literal, value, &mut tuple);
+        if let Some(graph_name) = get_pattern_value(&graph_name, &tuple) {
+            match self
+                .dataset
+                .quads_for_pattern_with_limit(None, None, Some(value), Some(graph_name), Some(1))
+                .next()
+            {
+                Some(Ok(_)) => Box::new(once(Ok(tuple))),
+                Some(Err(error)) => Box::new(once(Err(error))),
+                None => Box::new(empty()),
+            }
+        } else {
+            let mut already_seen = HashSet::new();
+            Box::new(
+                self.dataset
+                    .quads_for_pattern_with_limit(None, None, Some(value), None, None)
+                    .filter_map(move |quad| match quad {
+                        Ok(quad) => {
+                            if already_seen.insert(quad.graph_name) {
+                                let mut new_tuple = tuple.clone();
+                                put_pattern_value(&graph_name, quad.graph_name, &mut new_tuple);
+                                Some(Ok(new_tuple))
+                            } else {
+                                None
+                            }
+                        }
+                        Err(error) => Some(Err(error)),
+                    }),
+            )
+        }
+    }
+
+    fn eval_quoted_triple_pattern(
+        &self,
+        triple: &PatternValue,
+        subject: &PatternValue,
+        predicate: &PatternValue,
+        object: &PatternValue,
+        mut tuple: EncodedTuple,
+    ) -> Result<Option<EncodedTuple>> {
+        match get_pattern_value(triple, &tuple) {
+            Some(EncodedTerm::Triple { id }) => {
+                let (s, p, o) = get_quoted_triple_terms(&self.dataset, id)?;
+                for (pattern, value) in &[(subject, s), (predicate, p), (object, o)] {
+                    match get_pattern_value(pattern, &tuple) {
+                        Some(bound) if bound != *value => return Ok(None),
+                        Some(_) => (),
+                        None => put_pattern_value(pattern, *value, &mut tuple),
+                    }
+                }
+                Ok(Some(tuple))
+            }
+            Some(_) => Ok(None),
+            None => {
+                if let (Some(s), Some(p), Some(o)) = (
+                    get_pattern_value(subject, &tuple),
+                    get_pattern_value(predicate, &tuple),
+                    get_pattern_value(object, &tuple),
+                ) {
+                    if s.is_literal() || !p.is_named_node() {
+                        return Ok(None);
+                    }
+                    let value = self.dataset.encoder().encode_quoted_triple_terms(s, p, o)?;
+                    put_pattern_value(triple, value, &mut tuple);
+                    Ok(Some(tuple))
+                } else {
+                    Err(Error::msg(
+                        "The quoted triple pattern should have its triple or all its terms bound",
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Evaluates a property function with the values of its subject and object in `tuple` and returns the new solutions
+    fn eval_property_function(
+        &self,
+        function: &NamedNode,
+        subject: &PatternValue,
+        object: &PatternValue,
+        graph_name: EncodedTerm,
+        tuple: &EncodedTuple,
+    ) -> Result<Vec<EncodedTuple>> {
+        let implementation = self.property_functions.get(function).ok_or_else(|| {
+            Error::msg(format!(
+                "The property function {} is not registered",
+                function
+            ))
+        })?;
+        let graph_name = match graph_name {
+            EncodedTerm::DefaultGraph => GraphName::DefaultGraph,
+            graph_name => match self.dataset.decode_term(graph_name)? {
+                Term::NamedNode(graph_name) => graph_name.into(),
+                Term::BlankNode(graph_name) => graph_name.into(),
+                Term::Literal(_) | Term::Triple(_) => return Ok(Vec::new()),
+            },
+        };
+        let input_subject = get_pattern_value(subject, tuple);
+        let input_object = get_pattern_value(object, tuple);
+        let pairs = implementation.evaluate(
+            input_subject
+                .map(|s| self.dataset.decode_term(s))
+                .transpose()?
+                .as_ref(),
+            input_object
+                .map(|o| self.dataset.decode_term(o))
+                .transpose()?
+                .as_ref(),
+            &graph_name,
+            &self.dataset,
+        )?;
+        let mut encoder = self.dataset.encoder();
+        let mut tuples = Vec::with_capacity(pairs.len());
+        for (output_subject, output_object) in pairs {
+            let output_subject = encoder.encode_term(&output_subject)?;
+            let output_object = encoder.encode_term(&output_object)?;
+            if input_subject.map_or(false, |s| s != output_subject)
+                || input_object.map_or(false, |o| o != output_object)
+            {
+                continue;
+            }
+            if subject == object && output_subject != output_object {
+                // The same variable is used as subject and object
+                continue;
+            }
+            let mut new_tuple = tuple.clone();
+            put_pattern_value(subject, output_subject, &mut new_tuple);
+            put_pattern_value(object, output_object, &mut new_tuple);
+            tuples.push(new_tuple);
+        }
+        Ok(tuples)
+    }
+
+    fn eval_path_pattern<'b>(
+        &'b self,
+        path: &'b PlanPropertyPath,
+        subject: &'b PatternValue,
+        object: &'b PatternValue,
+        graph_name: EncodedTerm,
+        tuple: EncodedTuple,
+    ) -> EncodedTuplesIterator<'b>
+    where
+        'a: 'b,
+    {
+        let input_subject = get_pattern_value(subject, &tuple);
+        let input_object = get_pattern_value(object, &tuple);
+        match (input_subject, input_object) {
+            (Some(input_subject), Some(input_object)) => Box::new(
+                self.eval_path_from(path, input_subject, graph_name)
+                    .filter_map(move |o| match o {
+                        Ok(o) => {
+                            if o == input_object {
+                                Some(Ok(tuple.clone()))
+                            } else {
+                                None
+                            }
+                        }
+                        Err(error) => Some(Err(error)),
+                    }),
+            ),
+            (Some(input_subject), None) => Box::new(
+                self.eval_path_from(path, input_subject, graph_name)
+                    .map(move |o| {
+                        let mut new_tuple = tuple.clone();
+                        put_pattern_value(object, o?, &mut new_tuple);
+                        Ok(new_tuple)
+                    }),
+            ),
+            (None, Some(input_object)) => Box::new(
+                self.eval_path_to(path, input_object, graph_name)
+                    .map(move |s| {
+                        let mut new_tuple = tuple.clone();
+                        put_pattern_value(subject, s?, &mut new_tuple);
+                        Ok(new_tuple)
+                    }),
+            ),
+            (None, None) => Box::new(self.eval_open_path(path, graph_name).map(move |so| {
+                let mut new_tuple = tuple.clone();
+                so.map(move |(s, o)| {
+                    put_pattern_value(subject, s, &mut new_tuple);
+                    put_pattern_value(object, o, &mut new_tuple);
+                    new_tuple
+                })
+            })),
         }
     }
 
@@ -551,8 +1205,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
     {
         match path {
             PlanPropertyPath::PredicatePath(p) => Box::new(
-                self.dataset
-                    .quads_for_pattern(Some(start), Some(*p), None, Some(graph_name))
+                self.quads_for_pattern(Some(start), Some(*p), None, Some(graph_name))
                     .map(|t| Ok(t?.object)),
             ),
             PlanPropertyPath::InversePath(p) => self.eval_path_to(p, start, graph_name),
@@ -577,8 +1230,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 once(Ok(start)).chain(self.eval_path_from(p, start, graph_name)),
             )),
             PlanPropertyPath::NegatedPropertySet(ps) => Box::new(
-                self.dataset
-                    .quads_for_pattern(Some(start), None, None, Some(graph_name))
+                self.quads_for_pattern(Some(start), None, None, Some(graph_name))
                     .filter_map(move |t| match t {
                         Ok(t) => {
                             if ps.contains(&t.predicate) {
@@ -604,8 +1256,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
     {
         match path {
             PlanPropertyPath::PredicatePath(p) => Box::new(
-                self.dataset
-                    .quads_for_pattern(None, Some(*p), Some(end), Some(graph_name))
+                self.quads_for_pattern(None, Some(*p), Some(end), Some(graph_name))
                     .map(|t| Ok(t?.subject)),
             ),
             PlanPropertyPath::InversePath(p) => self.eval_path_from(p, end, graph_name),
@@ -630,8 +1281,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 once(Ok(end)).chain(self.eval_path_to(p, end, graph_name)),
             )),
             PlanPropertyPath::NegatedPropertySet(ps) => Box::new(
-                self.dataset
-                    .quads_for_pattern(None, None, Some(end), Some(graph_name))
+                self.quads_for_pattern(None, None, Some(end), Some(graph_name))
                     .filter_map(move |t| match t {
                         Ok(t) => {
                             if ps.contains(&t.predicate) {
@@ -656,8 +1306,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
     {
         match path {
             PlanPropertyPath::PredicatePath(p) => Box::new(
-                self.dataset
-                    .quads_for_pattern(None, Some(*p), None, Some(graph_name))
+                self.quads_for_pattern(None, Some(*p), None, Some(graph_name))
                     .map(|t| t.map(|t| (t.subject, t.object))),
             ),
             PlanPropertyPath::InversePath(p) => Box::new(
@@ -694,8 +1343,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                     .chain(self.eval_open_path(p, graph_name)),
             )),
             PlanPropertyPath::NegatedPropertySet(ps) => Box::new(
-                self.dataset
-                    .quads_for_pattern(None, None, None, Some(graph_name))
+                self.quads_for_pattern(None, None, None, Some(graph_name))
                     .filter_map(move |t| match t {
                         Ok(t) => {
                             if ps.contains(&t.predicate) {
@@ -710,16 +1358,39 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
         }
     }
 
+    /// Returns the names of the named graphs of the dataset
+    ///
+    /// They are read from the store only once and then kept in `cache`.
+    fn named_graph_names(
+        &self,
+        cache: &mut Option<Rc<[EncodedTerm]>>,
+    ) -> Result<impl Iterator<Item = Result<EncodedTerm>>> {
+        let named_graphs = if let Some(named_graphs) = cache {
+            named_graphs.clone()
+        } else {
+            let named_graphs = self
+                .dataset
+                .named_graphs()
+                .collect::<Result<Rc<[EncodedTerm]>>>()?;
+            *cache = Some(named_graphs.clone());
+            named_graphs
+        };
+        Ok((0..named_graphs.len()).map(move |i| Ok(named_graphs[i])))
+    }
+
     fn get_subject_or_object_identity_pairs<'b>(
         &'b self,
         graph_name: EncodedTerm,
     ) -> impl Iterator<Item = Result<(EncodedTerm, EncodedTerm)>> + 'b {
-        self.dataset
-            .quads_for_pattern(None, None, None, Some(graph_name))
+        self.quads_for_pattern(None, None, None, Some(graph_name))
             .flat_map_ok(|t| once(Ok(t.subject)).chain(once(Ok(t.object))))
             .map(|e| e.map(|e| (e, e)))
     }
 
+    /// Evaluates an expression against a solution
+    ///
+    /// `None` is the SPARQL expression error, e.g. a type error or an unbound variable.
+    /// It leaves the variable unbound in BIND and rejects the solution in FILTER, the other solutions are not affected.
     #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
     fn eval_expression<'b>(
         &'b self,
@@ -729,13 +1400,18 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
         match expression {
             PlanExpression::Constant(t) => Some(*t),
             PlanExpression::Variable(v) => tuple.get(*v),
-            PlanExpression::Exists(node) => {
-                Some(self.eval_plan(node, tuple.clone()).next().is_some().into())
-            }
+            PlanExpression::Exists(node) => match self.eval_plan(node, tuple.clone()).next() {
+                Some(Ok(_)) => Some(true.into()),
+                Some(Err(_)) => None,
+                None => Some(false.into()),
+            },
             PlanExpression::Or(a, b) => {
                 match self.eval_expression(a, tuple).and_then(|v| self.to_bool(v)) {
                     Some(true) => Some(true.into()),
-                    Some(false) => self.eval_expression(b, tuple),
+                    Some(false) => self
+                        .eval_expression(b, tuple)
+                        .and_then(|v| self.to_bool(v))
+                        .map(|v| v.into()),
                     None => {
                         if Some(true)
                             == self.eval_expression(b, tuple).and_then(|v| self.to_bool(v))
@@ -751,7 +1427,10 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 .eval_expression(a, tuple)
                 .and_then(|v| self.to_bool(v))
             {
-                Some(true) => self.eval_expression(b, tuple),
+                Some(true) => self
+                    .eval_expression(b, tuple)
+                    .and_then(|v| self.to_bool(v))
+                    .map(|v| v.into()),
                 Some(false) => Some(false.into()),
                 None => {
                     if Some(false) == self.eval_expression(b, tuple).and_then(|v| self.to_bool(v)) {
@@ -823,47 +1502,22 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                     Some(false.into())
                 }
             }
-            PlanExpression::Add(a, b) => match self.parse_numeric_operands(a, b, tuple)? {
-                NumericBinaryOperands::Float(v1, v2) => Some((v1 + v2).into()),
-                NumericBinaryOperands::Double(v1, v2) => Some((v1 + v2).into()),
-                NumericBinaryOperands::Integer(v1, v2) => Some(v1.checked_add(v2)?.into()),
-                NumericBinaryOperands::Decimal(v1, v2) => Some(v1.checked_add(v2)?.into()),
-                NumericBinaryOperands::Duration(v1, v2) => Some(v1.checked_add(v2)?.into()),
-                NumericBinaryOperands::YearMonthDuration(v1, v2) => {
-                    Some(v1.checked_add(v2)?.into())
-                }
-                NumericBinaryOperands::DayTimeDuration(v1, v2) => Some(v1.checked_add(v2)?.into()),
-                NumericBinaryOperands::DateTimeDuration(v1, v2) => {
-                    Some(v1.checked_add_duration(v2)?.into())
-                }
-                NumericBinaryOperands::DateTimeYearMonthDuration(v1, v2) => {
-                    Some(v1.checked_add_year_month_duration(v2)?.into())
-                }
-                NumericBinaryOperands::DateTimeDayTimeDuration(v1, v2) => {
-                    Some(v1.checked_add_day_time_duration(v2)?.into())
-                }
-                NumericBinaryOperands::DateDuration(v1, v2) => {
-                    Some(v1.checked_add_duration(v2)?.into())
-                }
-                NumericBinaryOperands::DateYearMonthDuration(v1, v2) => {
-                    Some(v1.checked_add_year_month_duration(v2)?.into())
-                }
-                NumericBinaryOperands::DateDayTimeDuration(v1, v2) => {
-                    Some(v1.checked_add_day_time_duration(v2)?.into())
-                }
-                NumericBinaryOperands::TimeDuration(v1, v2) => {
-                    Some(v1.checked_add_duration(v2)?.into())
-                }
-                NumericBinaryOperands::TimeDayTimeDuration(v1, v2) => {
-                    Some(v1.checked_add_day_time_duration(v2)?.into())
-                }
-                _ => None,
-            },
+            PlanExpression::Add(a, b) => {
+                self.add_operands(self.parse_addition_operands(a, b, tuple)?)
+            }
             PlanExpression::Sub(a, b) => Some(match self.parse_numeric_operands(a, b, tuple)? {
                 NumericBinaryOperands::Float(v1, v2) => (v1 - v2).into(),
                 NumericBinaryOperands::Double(v1, v2) => (v1 - v2).into(),
-                NumericBinaryOperands::Integer(v1, v2) => v1.checked_sub(v2)?.into(),
-                NumericBinaryOperands::Decimal(v1, v2) => v1.checked_sub(v2)?.into(),
+                NumericBinaryOperands::Integer(v1, v2) => v1
+                    .checked_sub(v2)
+                    .map(Into::into)
+                    .or_else(|| self.build_integer_literal(BigDecimal::from(v1) - v2.into()))?,
+                NumericBinaryOperands::Decimal(v1, v2) => v1
+                    .checked_sub(v2)
+                    .map(Into::into)
+                    .or_else(|| self.build_decimal_literal(BigDecimal::from(v1) - v2.into()))?,
+                NumericBinaryOperands::BigInteger(v1, v2) => self.build_integer_literal(v1 - v2)?,
+                NumericBinaryOperands::BigDecimal(v1, v2) => self.build_decimal_literal(v1 - v2)?,
                 NumericBinaryOperands::DateTime(v1, v2) => v1.checked_sub(v2)?.into(),
                 NumericBinaryOperands::Date(v1, v2) => v1.checked_sub(v2)?.into(),
                 NumericBinaryOperands::Time(v1, v2) => v1.checked_sub(v2)?.into(),
@@ -890,23 +1544,33 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 NumericBinaryOperands::TimeDayTimeDuration(v1, v2) => {
                     v1.checked_sub_day_time_duration(v2)?.into()
                 }
+                NumericBinaryOperands::YearMonthDurationDecimal(_, _)
+                | NumericBinaryOperands::DayTimeDurationDecimal(_, _) => return None,
             }),
-            PlanExpression::Mul(a, b) => match self.parse_numeric_operands(a, b, tuple)? {
+            PlanExpression::Mul(a, b) => match self.parse_multiplication_operands(a, b, tuple)? {
                 NumericBinaryOperands::Float(v1, v2) => Some((v1 * v2).into()),
                 NumericBinaryOperands::Double(v1, v2) => Some((v1 * v2).into()),
-                NumericBinaryOperands::Integer(v1, v2) => Some(v1.checked_mul(v2)?.into()),
-                NumericBinaryOperands::Decimal(v1, v2) => Some(v1.checked_mul(v2)?.into()),
-                _ => None,
-            },
-            PlanExpression::Div(a, b) => match self.parse_numeric_operands(a, b, tuple)? {
-                NumericBinaryOperands::Float(v1, v2) => Some((v1 / v2).into()),
-                NumericBinaryOperands::Double(v1, v2) => Some((v1 / v2).into()),
-                NumericBinaryOperands::Integer(v1, v2) => {
-                    Some(Decimal::from(v1).checked_div(v2)?.into())
+                NumericBinaryOperands::Integer(v1, v2) => v1
+                    .checked_mul(v2)
+                    .map(Into::into)
+                    .or_else(|| self.build_integer_literal(BigDecimal::from(v1) * v2.into())),
+                // The product is computed exactly and stored as a Decimal if it fits
+                NumericBinaryOperands::Decimal(v1, v2) => {
+                    self.build_decimal_literal(BigDecimal::from(v1) * v2.into())
+                }
+                NumericBinaryOperands::BigInteger(v1, v2) => self.build_integer_literal(v1 * v2),
+                NumericBinaryOperands::BigDecimal(v1, v2) => self.build_decimal_literal(v1 * v2),
+                NumericBinaryOperands::YearMonthDurationDecimal(v1, v2) => {
+                    Some(v1.checked_mul(v2)?.into())
+                }
+                NumericBinaryOperands::DayTimeDurationDecimal(v1, v2) => {
+                    Some(v1.checked_mul(v2)?.into())
                 }
-                NumericBinaryOperands::Decimal(v1, v2) => Some(v1.checked_div(v2)?.into()),
                 _ => None,
             },
+            PlanExpression::Div(a, b) => {
+                self.divide_operands(self.parse_numeric_operands(a, b, tuple)?)
+            }
             PlanExpression::UnaryPlus(e) => match self.eval_expression(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some(value.into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.into()),
@@ -915,16 +1579,21 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::DurationLiteral(value) => Some(value.into()),
                 EncodedTerm::YearMonthDurationLiteral(value) => Some(value.into()),
                 EncodedTerm::DayTimeDurationLiteral(value) => Some(value.into()),
+                term @ EncodedTerm::TypedLiteral { .. } => self.map_big_numeric(term, |v| v),
                 _ => None,
             },
             PlanExpression::UnaryMinus(e) => match self.eval_expression(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some((-value).into()),
                 EncodedTerm::DoubleLiteral(value) => Some((-value).into()),
-                EncodedTerm::IntegerLiteral(value) => Some((-value).into()),
+                EncodedTerm::IntegerLiteral(value) => value
+                    .checked_neg()
+                    .map(Into::into)
+                    .or_else(|| self.build_integer_literal(-BigDecimal::from(value))),
                 EncodedTerm::DecimalLiteral(value) => Some((-value).into()),
                 EncodedTerm::DurationLiteral(value) => Some((-value).into()),
                 EncodedTerm::YearMonthDurationLiteral(value) => Some((-value).into()),
                 EncodedTerm::DayTimeDurationLiteral(value) => Some((-value).into()),
+                term @ EncodedTerm::TypedLiteral { .. } => self.map_big_numeric(term, |v| -v),
                 _ => None,
             },
             PlanExpression::UnaryNot(e) => self
@@ -983,21 +1652,42 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 }
             }
             PlanExpression::BNode(id) => match id {
-                Some(id) => Some(
-                    (&BlankNode::new(self.to_simple_string(self.eval_expression(id, tuple)?)?)
-                        .ok()?)
-                        .into(),
-                ),
+                Some(id) => {
+                    // The same blank node is returned for the same string in the same solution.
+                    // The blank nodes built by BNODE itself are not part of the solution identity
+                    let id = self.to_simple_string(self.eval_expression(id, tuple)?)?;
+                    let mut hasher = DefaultHasher::new();
+                    self.blank_node_seed.hash(&mut hasher);
+                    for (i, value) in tuple.iter().enumerate() {
+                        match value {
+                            Some(EncodedTerm::InlineBlankNode { id })
+                                if self.is_generated_blank_node(id) => {}
+                            Some(value) => {
+                                i.hash(&mut hasher);
+                                value.hash(&mut hasher);
+                            }
+                            None => (),
+                        }
+                    }
+                    id.hash(&mut hasher);
+                    Some(EncodedTerm::InlineBlankNode {
+                        id: self.generated_blank_node_id(hasher.finish()),
+                    })
+                }
                 None => Some(EncodedTerm::InlineBlankNode {
                     id: random::<u128>(),
                 }),
             },
             PlanExpression::Rand => Some(random::<f64>().into()),
             PlanExpression::Abs(e) => match self.eval_expression(e, tuple)? {
-                EncodedTerm::IntegerLiteral(value) => Some(value.checked_abs()?.into()),
+                EncodedTerm::IntegerLiteral(value) => value
+                    .checked_abs()
+                    .map(Into::into)
+                    .or_else(|| self.build_integer_literal(BigDecimal::from(value).abs())),
                 EncodedTerm::DecimalLiteral(value) => Some(value.abs().into()),
                 EncodedTerm::FloatLiteral(value) => Some(value.abs().into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.abs().into()),
+                term @ EncodedTerm::TypedLiteral { .. } => self.map_big_numeric(term, |v| v.abs()),
                 _ => None,
             },
             PlanExpression::Ceil(e) => match self.eval_expression(e, tuple)? {
@@ -1005,6 +1695,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::DecimalLiteral(value) => Some(value.ceil().into()),
                 EncodedTerm::FloatLiteral(value) => Some(value.ceil().into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.ceil().into()),
+                term @ EncodedTerm::TypedLiteral { .. } => self.map_big_numeric(term, |v| v.ceil()),
                 _ => None,
             },
             PlanExpression::Floor(e) => match self.eval_expression(e, tuple)? {
@@ -1012,13 +1703,19 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::DecimalLiteral(value) => Some(value.floor().into()),
                 EncodedTerm::FloatLiteral(value) => Some(value.floor().into()),
                 EncodedTerm::DoubleLiteral(value) => Some(value.floor().into()),
+                term @ EncodedTerm::TypedLiteral { .. } => {
+                    self.map_big_numeric(term, |v| v.floor())
+                }
                 _ => None,
             },
             PlanExpression::Round(e) => match self.eval_expression(e, tuple)? {
                 EncodedTerm::IntegerLiteral(value) => Some(value.into()),
                 EncodedTerm::DecimalLiteral(value) => Some(value.round().into()),
-                EncodedTerm::FloatLiteral(value) => Some(value.round().into()),
-                EncodedTerm::DoubleLiteral(value) => Some(value.round().into()),
+                EncodedTerm::FloatLiteral(value) => Some(round_f32(value).into()),
+                EncodedTerm::DoubleLiteral(value) => Some(round_f64(value).into()),
+                term @ EncodedTerm::TypedLiteral { .. } => {
+                    self.map_big_numeric(term, |v| v.round())
+                }
                 _ => None,
             },
             PlanExpression::Concat(l) => {
@@ -1042,43 +1739,24 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 let (source, language) =
                     self.to_string_and_language(self.eval_expression(source, tuple)?)?;
 
-                let starting_location: usize = if let EncodedTerm::IntegerLiteral(v) =
-                    self.eval_expression(starting_loc, tuple)?
-                {
-                    v.try_into().ok()?
-                } else {
-                    return None;
-                };
-                let length: Option<usize> = if let Some(length) = length {
-                    if let EncodedTerm::IntegerLiteral(v) = self.eval_expression(length, tuple)? {
-                        Some(v.try_into().ok()?)
-                    } else {
-                        return None;
-                    }
+                // We follow fn:substring: the characters at the positions p such that
+                // round(starting_loc) <= p < round(starting_loc) + round(length) are returned
+                let start = self.to_rounded_position(self.eval_expression(starting_loc, tuple)?)?;
+                let end = if let Some(length) = length {
+                    start + self.to_rounded_position(self.eval_expression(length, tuple)?)?
                 } else {
-                    None
+                    f64::INFINITY
                 };
-
-                // We want to slice on char indices, not byte indices
-                let mut start_iter = source
-                    .char_indices()
-                    .skip(starting_location.checked_sub(1)?)
-                    .peekable();
-                let result = if let Some((start_position, _)) = start_iter.peek().cloned() {
-                    if let Some(length) = length {
-                        let mut end_iter = start_iter.skip(length).peekable();
-                        if let Some((end_position, _)) = end_iter.peek() {
-                            &source[start_position..*end_position]
-                        } else {
-                            &source[start_position..]
-                        }
-                    } else {
-                        &source[start_position..]
-                    }
-                } else {
-                    ""
-                };
-                self.build_plain_literal(result, language)
+                let result = source
+                    .chars()
+                    .enumerate()
+                    .filter(|(i, _)| {
+                        let position = (i + 1) as f64;
+                        start <= position && position < end
+                    })
+                    .map(|(_, c)| c)
+                    .collect::<String>();
+                self.build_plain_literal(&result, language)
             }
             PlanExpression::StrLen(arg) => Some(
                 (self
@@ -1096,10 +1774,14 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                         None
                     },
                 )?;
+                if regex.is_match("") {
+                    return None; // The pattern should not match the empty string
+                }
                 let (text, language) =
                     self.to_string_and_language(self.eval_expression(arg, tuple)?)?;
-                let replacement =
-                    self.to_simple_string(self.eval_expression(replacement, tuple)?)?;
+                let replacement = to_regex_replacement(
+                    &self.to_simple_string(self.eval_expression(replacement, tuple)?)?,
+                )?;
                 self.build_plain_literal(&regex.replace_all(&text, replacement.as_str()), language)
             }
             PlanExpression::UCase(e) => {
@@ -1188,12 +1870,12 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 _ => None,
             },
             PlanExpression::Month(e) => match self.eval_expression(e, tuple)? {
-                EncodedTerm::DateLiteral(date) => Some(date.year().into()),
+                EncodedTerm::DateLiteral(date) => Some(date.month().into()),
                 EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.month().into()),
                 _ => None,
             },
             PlanExpression::Day(e) => match self.eval_expression(e, tuple)? {
-                EncodedTerm::DateLiteral(date) => Some(date.year().into()),
+                EncodedTerm::DateLiteral(date) => Some(date.day().into()),
                 EncodedTerm::DateTimeLiteral(date_time) => Some(date_time.day().into()),
                 _ => None,
             },
@@ -1343,6 +2025,9 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::StringLiteral { value_id } => {
                     parse_double_str(&*self.dataset.get_str(value_id).ok()??)
                 }
+                term @ EncodedTerm::TypedLiteral { .. } => {
+                    Some(self.to_big_decimal(term)?.to_f64().into())
+                }
                 _ => None,
             },
             PlanExpression::FloatCast(e) => match self.eval_expression(e, tuple)? {
@@ -1356,16 +2041,32 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::StringLiteral { value_id } => {
                     parse_float_str(&*self.dataset.get_str(value_id).ok()??)
                 }
+                term @ EncodedTerm::TypedLiteral { .. } => {
+                    Some(self.to_big_decimal(term)?.to_f32().into())
+                }
                 _ => None,
             },
             PlanExpression::IntegerCast(e) => match self.eval_expression(e, tuple)? {
                 EncodedTerm::FloatLiteral(value) => Some((value as i64).into()),
                 EncodedTerm::DoubleLiteral(value) => Some((value as i64).into()),
                 EncodedTerm::IntegerLiteral(value) => Some(value.into()),
-                EncodedTerm::DecimalLiteral(value) => Some(i64::try_from(value).ok()?.into()),
+                EncodedTerm::DecimalLiteral(value) => i64::try_from(value)
+                    .map(Into::into)
+                    .ok()
+                    .or_else(|| self.build_integer_literal(BigDecimal::from(value).trunc())),
                 EncodedTerm::BooleanLiteral(value) => Some(if value { 1 } else { 0 }.into()),
                 EncodedTerm::StringLiteral { value_id } => {
-                    parse_integer_str(&*self.dataset.get_str(value_id).ok()??)
+                    let value = self.dataset.get_str(value_id).ok()??;
+                    parse_integer_str(&value).or_else(|| {
+                        if value.contains('.') {
+                            None
+                        } else {
+                            self.build_integer_literal(value.parse().ok()?)
+                        }
+                    })
+                }
+                term @ EncodedTerm::TypedLiteral { .. } => {
+                    self.build_integer_literal(self.to_big_decimal(term)?.trunc())
                 }
                 _ => None,
             },
@@ -1378,7 +2079,12 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                     Some(Decimal::from(if value { 1 } else { 0 }).into())
                 }
                 EncodedTerm::StringLiteral { value_id } => {
-                    parse_decimal_str(&*self.dataset.get_str(value_id).ok()??)
+                    let value = self.dataset.get_str(value_id).ok()??;
+                    parse_decimal_str(&value)
+                        .or_else(|| self.build_decimal_literal(value.parse().ok()?))
+                }
+                term @ EncodedTerm::TypedLiteral { .. } => {
+                    self.build_decimal_literal(self.to_big_decimal(term)?)
                 }
                 _ => None,
             },
@@ -1435,6 +2141,22 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 }
                 _ => None,
             },
+            PlanExpression::GeoDistance(a, b, unit) => {
+                let a = self.to_geometry(self.eval_expression(a, tuple)?)?;
+                let b = self.to_geometry(self.eval_expression(b, tuple)?)?;
+                let unit =
+                    if let EncodedTerm::NamedNode { iri_id } = self.eval_expression(unit, tuple)? {
+                        self.dataset.get_str(iri_id).ok()??
+                    } else {
+                        return None;
+                    };
+                Some(a.distance(&b, &unit)?.into())
+            }
+            PlanExpression::GeoWithin(a, b) => Some(
+                self.to_geometry(self.eval_expression(a, tuple)?)?
+                    .within(&self.to_geometry(self.eval_expression(b, tuple)?)?)
+                    .into(),
+            ),
             PlanExpression::StringCast(e) => Some(EncodedTerm::StringLiteral {
                 value_id: self.to_string_id(self.eval_expression(e, tuple)?)?,
             }),
@@ -1449,6 +2171,9 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
             EncodedTerm::DoubleLiteral(value) => Some(value != 0_f64),
             EncodedTerm::IntegerLiteral(value) => Some(value != 0),
             EncodedTerm::DecimalLiteral(value) => Some(value != Decimal::default()),
+            EncodedTerm::TypedLiteral { .. } => {
+                Some(self.to_big_decimal(term)? != BigDecimal::default())
+            }
             _ => None,
         }
     }
@@ -1476,9 +2201,48 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 self.build_string_id(&value.to_string())
             }
             EncodedTerm::DayTimeDurationLiteral(value) => self.build_string_id(&value.to_string()),
+            EncodedTerm::Triple { .. } => None,
+        }
+    }
+
+    fn to_geometry(&self, term: EncodedTerm) -> Option<Geometry> {
+        match term {
+            EncodedTerm::TypedLiteral {
+                value_id,
+                datatype_id,
+            } if datatype_id == StrHash::new(geo::WKT_LITERAL) => {
+                self.dataset.get_str(value_id).ok()??.parse().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Converts a numeric to a position rounded like fn:round does
+    #[allow(clippy::cast_precision_loss)]
+    fn to_rounded_position(&self, term: EncodedTerm) -> Option<f64> {
+        match term {
+            EncodedTerm::IntegerLiteral(value) => Some(value as f64),
+            EncodedTerm::DecimalLiteral(value) => Some(value.round().to_f64()),
+            EncodedTerm::FloatLiteral(value) => Some(round_f32(value).into()),
+            EncodedTerm::DoubleLiteral(value) => Some(round_f64(value)),
+            _ => None,
         }
     }
 
+    /// Builds a blank node id from a 64 bits key, with a keyed checksum in its high bits
+    fn generated_blank_node_id(&self, low: u64) -> u128 {
+        let mut hasher = DefaultHasher::new();
+        self.blank_node_seed.hash(&mut hasher);
+        low.hash(&mut hasher);
+        (u128::from(hasher.finish()) << 64) | u128::from(low)
+    }
+
+    /// Returns if the blank node has been built by `BNODE(str)` during this evaluation
+    #[allow(clippy::cast_possible_truncation)]
+    fn is_generated_blank_node(&self, id: u128) -> bool {
+        self.generated_blank_node_id(id as u64) == id
+    }
+
     fn to_simple_string(&self, term: EncodedTerm) -> Option<String> {
         if let EncodedTerm::StringLiteral { value_id } = term {
             self.dataset.get_str(value_id).ok()?
@@ -1573,28 +2337,33 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
 
     fn compile_pattern(&self, pattern: EncodedTerm, flags: Option<EncodedTerm>) -> Option<Regex> {
         // TODO Avoid to compile the regex each time
-        let pattern = self.to_simple_string(pattern)?;
+        let mut pattern = self.to_simple_string(pattern)?;
+        let flags = if let Some(flags) = flags {
+            self.to_simple_string(flags)?
+        } else {
+            String::default()
+        };
+        if flags.contains('q') {
+            pattern = regex::escape(&pattern);
+        }
         let mut regex_builder = RegexBuilder::new(&pattern);
         regex_builder.size_limit(REGEX_SIZE_LIMIT);
-        if let Some(flags) = flags {
-            let flags = self.to_simple_string(flags)?;
-            for flag in flags.chars() {
-                match flag {
-                    's' => {
-                        regex_builder.dot_matches_new_line(true);
-                    }
-                    'm' => {
-                        regex_builder.multi_line(true);
-                    }
-                    'i' => {
-                        regex_builder.case_insensitive(true);
-                    }
-                    'x' => {
-                        regex_builder.ignore_whitespace(true);
-                    }
-                    'q' => (), //TODO: implement
-                    _ => (),
+        for flag in flags.chars() {
+            match flag {
+                's' => {
+                    regex_builder.dot_matches_new_line(true);
+                }
+                'm' => {
+                    regex_builder.multi_line(true);
+                }
+                'i' => {
+                    regex_builder.case_insensitive(true);
                 }
+                'x' => {
+                    regex_builder.ignore_whitespace(true);
+                }
+                'q' => (),
+                _ => return None, // invalid flag
             }
         }
         regex_builder.build().ok()
@@ -1606,12 +2375,229 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
         e2: &PlanExpression,
         tuple: &EncodedTuple,
     ) -> Option<NumericBinaryOperands> {
-        NumericBinaryOperands::new(
+        self.numeric_operands(
             self.eval_expression(e1, tuple)?,
             self.eval_expression(e2, tuple)?,
         )
     }
 
+    fn parse_addition_operands(
+        &self,
+        e1: &PlanExpression,
+        e2: &PlanExpression,
+        tuple: &EncodedTuple,
+    ) -> Option<NumericBinaryOperands> {
+        let a = self.eval_expression(e1, tuple)?;
+        let b = self.eval_expression(e2, tuple)?;
+        // The addition of a duration to a temporal value is commutative
+        if is_duration(a) && is_temporal(b) {
+            self.numeric_operands(b, a)
+        } else {
+            self.numeric_operands(a, b)
+        }
+    }
+
+    fn parse_multiplication_operands(
+        &self,
+        e1: &PlanExpression,
+        e2: &PlanExpression,
+        tuple: &EncodedTuple,
+    ) -> Option<NumericBinaryOperands> {
+        let a = self.eval_expression(e1, tuple)?;
+        let b = self.eval_expression(e2, tuple)?;
+        // The multiplication of a duration by a number is commutative
+        if is_duration(b) && !is_duration(a) {
+            self.numeric_operands(b, a)
+        } else {
+            self.numeric_operands(a, b)
+        }
+    }
+
+    fn numeric_operands(&self, a: EncodedTerm, b: EncodedTerm) -> Option<NumericBinaryOperands> {
+        NumericBinaryOperands::new(a, b).or_else(|| self.big_numeric_operands(a, b))
+    }
+
+    /// Builds the operands if one of them is a xsd:integer or a xsd:decimal too big to be inlined
+    fn big_numeric_operands(
+        &self,
+        a: EncodedTerm,
+        b: EncodedTerm,
+    ) -> Option<NumericBinaryOperands> {
+        match (a, b) {
+            (EncodedTerm::FloatLiteral(v1), b) => Some(NumericBinaryOperands::Float(
+                v1,
+                self.to_big_decimal(b)?.to_f32(),
+            )),
+            (EncodedTerm::DoubleLiteral(v1), b) => Some(NumericBinaryOperands::Double(
+                v1,
+                self.to_big_decimal(b)?.to_f64(),
+            )),
+            (a, EncodedTerm::FloatLiteral(v2)) => Some(NumericBinaryOperands::Float(
+                self.to_big_decimal(a)?.to_f32(),
+                v2,
+            )),
+            (a, EncodedTerm::DoubleLiteral(v2)) => Some(NumericBinaryOperands::Double(
+                self.to_big_decimal(a)?.to_f64(),
+                v2,
+            )),
+            (a, b) => {
+                let v1 = self.to_big_decimal(a)?;
+                let v2 = self.to_big_decimal(b)?;
+                Some(if self.is_integer(a) && self.is_integer(b) {
+                    NumericBinaryOperands::BigInteger(v1, v2)
+                } else {
+                    NumericBinaryOperands::BigDecimal(v1, v2)
+                })
+            }
+        }
+    }
+
+    /// [op:numeric-add](https://www.w3.org/TR/xpath-functions/#func-numeric-add) and the additions of durations and temporal values
+    fn add_operands(&self, operands: NumericBinaryOperands) -> Option<EncodedTerm> {
+        match operands {
+            NumericBinaryOperands::Float(v1, v2) => Some((v1 + v2).into()),
+            NumericBinaryOperands::Double(v1, v2) => Some((v1 + v2).into()),
+            NumericBinaryOperands::Integer(v1, v2) => v1
+                .checked_add(v2)
+                .map(Into::into)
+                .or_else(|| self.build_integer_literal(BigDecimal::from(v1) + v2.into())),
+            NumericBinaryOperands::Decimal(v1, v2) => v1
+                .checked_add(v2)
+                .map(Into::into)
+                .or_else(|| self.build_decimal_literal(BigDecimal::from(v1) + v2.into())),
+            NumericBinaryOperands::BigInteger(v1, v2) => self.build_integer_literal(v1 + v2),
+            NumericBinaryOperands::BigDecimal(v1, v2) => self.build_decimal_literal(v1 + v2),
+            NumericBinaryOperands::Duration(v1, v2) => Some(v1.checked_add(v2)?.into()),
+            NumericBinaryOperands::YearMonthDuration(v1, v2) => Some(v1.checked_add(v2)?.into()),
+            NumericBinaryOperands::DayTimeDuration(v1, v2) => Some(v1.checked_add(v2)?.into()),
+            NumericBinaryOperands::DateTimeDuration(v1, v2) => {
+                Some(v1.checked_add_duration(v2)?.into())
+            }
+            NumericBinaryOperands::DateTimeYearMonthDuration(v1, v2) => {
+                Some(v1.checked_add_year_month_duration(v2)?.into())
+            }
+            NumericBinaryOperands::DateTimeDayTimeDuration(v1, v2) => {
+                Some(v1.checked_add_day_time_duration(v2)?.into())
+            }
+            NumericBinaryOperands::DateDuration(v1, v2) => {
+                Some(v1.checked_add_duration(v2)?.into())
+            }
+            NumericBinaryOperands::DateYearMonthDuration(v1, v2) => {
+                Some(v1.checked_add_year_month_duration(v2)?.into())
+            }
+            NumericBinaryOperands::DateDayTimeDuration(v1, v2) => {
+                Some(v1.checked_add_day_time_duration(v2)?.into())
+            }
+            NumericBinaryOperands::TimeDuration(v1, v2) => {
+                Some(v1.checked_add_duration(v2)?.into())
+            }
+            NumericBinaryOperands::TimeDayTimeDuration(v1, v2) => {
+                Some(v1.checked_add_day_time_duration(v2)?.into())
+            }
+            _ => None,
+        }
+    }
+
+    /// [op:numeric-divide](https://www.w3.org/TR/xpath-functions/#func-numeric-divide)
+    ///
+    /// The xsd:integer and xsd:decimal divisions are done using `BigDecimal` to not lose precision.
+    fn divide_operands(&self, operands: NumericBinaryOperands) -> Option<EncodedTerm> {
+        match operands {
+            NumericBinaryOperands::Float(v1, v2) => Some((v1 / v2).into()),
+            NumericBinaryOperands::Double(v1, v2) => Some((v1 / v2).into()),
+            NumericBinaryOperands::Integer(v1, v2) => {
+                self.build_decimal_literal(BigDecimal::from(v1).checked_div(&BigDecimal::from(v2))?)
+            }
+            NumericBinaryOperands::Decimal(v1, v2) => {
+                self.build_decimal_literal(BigDecimal::from(v1).checked_div(&BigDecimal::from(v2))?)
+            }
+            NumericBinaryOperands::BigInteger(v1, v2)
+            | NumericBinaryOperands::BigDecimal(v1, v2) => {
+                self.build_decimal_literal(v1.checked_div(&v2)?)
+            }
+            NumericBinaryOperands::YearMonthDuration(v1, v2) => {
+                Some(v1.checked_div_duration(v2)?.into())
+            }
+            NumericBinaryOperands::DayTimeDuration(v1, v2) => {
+                Some(v1.checked_div_duration(v2)?.into())
+            }
+            NumericBinaryOperands::YearMonthDurationDecimal(v1, v2) => {
+                Some(v1.checked_div(v2)?.into())
+            }
+            NumericBinaryOperands::DayTimeDurationDecimal(v1, v2) => {
+                Some(v1.checked_div(v2)?.into())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a xsd:integer or xsd:decimal term, including the ones too big to be inlined
+    fn to_big_decimal(&self, term: EncodedTerm) -> Option<BigDecimal> {
+        match term {
+            EncodedTerm::IntegerLiteral(value) => Some(value.into()),
+            EncodedTerm::DecimalLiteral(value) => Some(value.into()),
+            EncodedTerm::TypedLiteral {
+                value_id,
+                datatype_id,
+            } => {
+                let is_integer = is_integer_datatype_id(datatype_id);
+                if !is_integer && datatype_id != StrHash::new(xsd::DECIMAL.as_str()) {
+                    return None;
+                }
+                let value = self.dataset.get_str(value_id).ok()??;
+                if is_integer && value.contains('.') {
+                    return None;
+                }
+                value.parse().ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn is_integer(&self, term: EncodedTerm) -> bool {
+        match term {
+            EncodedTerm::IntegerLiteral(_) => true,
+            EncodedTerm::TypedLiteral { datatype_id, .. } => is_integer_datatype_id(datatype_id),
+            _ => false,
+        }
+    }
+
+    /// Applies a function to a big xsd:integer or xsd:decimal keeping its datatype
+    fn map_big_numeric(
+        &self,
+        term: EncodedTerm,
+        f: impl FnOnce(BigDecimal) -> BigDecimal,
+    ) -> Option<EncodedTerm> {
+        let value = f(self.to_big_decimal(term)?);
+        if self.is_integer(term) {
+            self.build_integer_literal(value)
+        } else {
+            self.build_decimal_literal(value)
+        }
+    }
+
+    /// Builds a xsd:integer literal, inlined if it fits in an i64
+    fn build_integer_literal(&self, value: BigDecimal) -> Option<EncodedTerm> {
+        if let Ok(value) = i64::try_from(&value) {
+            return Some(value.into());
+        }
+        Some(EncodedTerm::TypedLiteral {
+            value_id: self.build_string_id(&value.to_string())?,
+            datatype_id: self.build_string_id(xsd::INTEGER.as_str())?,
+        })
+    }
+
+    /// Builds a xsd:decimal literal, inlined if it fits exactly in a Decimal
+    fn build_decimal_literal(&self, value: BigDecimal) -> Option<EncodedTerm> {
+        if let Ok(value) = Decimal::try_from(&value) {
+            return Some(value.into());
+        }
+        Some(EncodedTerm::TypedLiteral {
+            value_id: self.build_string_id(&value.to_string())?,
+            datatype_id: self.build_string_id(xsd::DECIMAL.as_str())?,
+        })
+    }
+
     fn decode_bindings<'b>(
         &'b self,
         iter: EncodedTuplesIterator<'b>,
@@ -1671,7 +2657,8 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
             | EncodedTerm::NamedNode { .. }
             | EncodedTerm::InlineBlankNode { .. }
             | EncodedTerm::NamedBlankNode { .. }
-            | EncodedTerm::LangStringLiteral { .. } => Some(a == b),
+            | EncodedTerm::LangStringLiteral { .. }
+            | EncodedTerm::Triple { .. } => Some(a == b),
             EncodedTerm::StringLiteral { value_id: a } => match b {
                 EncodedTerm::StringLiteral { value_id: b } => Some(a == b),
                 EncodedTerm::TypedLiteral { .. } => None,
@@ -1703,7 +2690,9 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::DoubleLiteral(b) => Some((a as f64) == b),
                 EncodedTerm::IntegerLiteral(b) => Some(a == b),
                 EncodedTerm::DecimalLiteral(b) => Some(Decimal::from(a) == b),
-                EncodedTerm::TypedLiteral { .. } => None,
+                EncodedTerm::TypedLiteral { .. } => {
+                    Some(BigDecimal::from(a) == self.to_big_decimal(b)?)
+                }
                 _ => Some(false),
             },
             EncodedTerm::DecimalLiteral(a) => match b {
@@ -1711,7 +2700,9 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::DoubleLiteral(b) => Some(a.to_f64() == b),
                 EncodedTerm::IntegerLiteral(b) => Some(a == Decimal::from(b)),
                 EncodedTerm::DecimalLiteral(b) => Some(a == b),
-                EncodedTerm::TypedLiteral { .. } => None,
+                EncodedTerm::TypedLiteral { .. } => {
+                    Some(BigDecimal::from(a) == self.to_big_decimal(b)?)
+                }
                 _ => Some(false),
             },
             EncodedTerm::TypedLiteral { .. } => match b {
@@ -1720,7 +2711,7 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 | EncodedTerm::InlineBlankNode { .. }
                 | EncodedTerm::NamedBlankNode { .. }
                 | EncodedTerm::LangStringLiteral { .. } => Some(false),
-                _ => None,
+                _ => Some(self.to_big_decimal(a)? == self.to_big_decimal(b)?),
             },
             EncodedTerm::DateLiteral(a) => match b {
                 EncodedTerm::DateLiteral(b) => Some(a == b),
@@ -1796,7 +2787,10 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                     EncodedTerm::NamedNode { .. }
                     | EncodedTerm::InlineBlankNode { .. }
                     | EncodedTerm::NamedBlankNode { .. } => Ordering::Greater,
-                    b => self.partial_cmp_literals(a, b).unwrap_or(Ordering::Equal),
+                    b => self
+                        .collate_literals(a, b)
+                        .or_else(|| self.partial_cmp_literals(a, b))
+                        .unwrap_or(Ordering::Equal),
                 },
             },
             (Some(_), None) => Ordering::Greater,
@@ -1805,6 +2799,17 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
         }
     }
 
+    /// Compares string literals according to the collation if it is not the code point one
+    fn collate_literals(&self, a: EncodedTerm, b: EncodedTerm) -> Option<Ordering> {
+        if self.collator.is_codepoint() {
+            return None;
+        }
+        Some(
+            self.collator
+                .compare(&self.to_string(a)?, &self.to_string(b)?),
+        )
+    }
+
     #[allow(clippy::cast_precision_loss)]
     fn partial_cmp_literals(&self, a: EncodedTerm, b: EncodedTerm) -> Option<Ordering> {
         match a {
@@ -1834,14 +2839,14 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::DoubleLiteral(ref b) => (a as f64).partial_cmp(b),
                 EncodedTerm::IntegerLiteral(ref b) => a.partial_cmp(b),
                 EncodedTerm::DecimalLiteral(b) => Decimal::from(a).partial_cmp(&b),
-                _ => None,
+                b => BigDecimal::from(a).partial_cmp(&self.to_big_decimal(b)?),
             },
             EncodedTerm::DecimalLiteral(a) => match b {
                 EncodedTerm::FloatLiteral(ref b) => a.to_f32().partial_cmp(b),
                 EncodedTerm::DoubleLiteral(ref b) => a.to_f64().partial_cmp(b),
                 EncodedTerm::IntegerLiteral(b) => a.partial_cmp(&Decimal::from(b)),
                 EncodedTerm::DecimalLiteral(ref b) => a.partial_cmp(b),
-                _ => None,
+                b => BigDecimal::from(a).partial_cmp(&self.to_big_decimal(b)?),
             },
             EncodedTerm::DateLiteral(a) => {
                 if let EncodedTerm::DateLiteral(ref b) = b {
@@ -1882,6 +2887,9 @@ impl<'a, S: ReadableEncodedStore + 'a> SimpleEvaluator<S> {
                 EncodedTerm::DayTimeDurationLiteral(ref b) => a.partial_cmp(b),
                 _ => None,
             },
+            EncodedTerm::TypedLiteral { .. } => self
+                .to_big_decimal(a)?
+                .partial_cmp(&self.to_big_decimal(b)?),
             _ => None,
         }
     }
@@ -1911,6 +2919,8 @@ enum NumericBinaryOperands {
     Double(f64, f64),
     Integer(i64, i64),
     Decimal(Decimal, Decimal),
+    BigInteger(BigDecimal, BigDecimal),
+    BigDecimal(BigDecimal, BigDecimal),
     Duration(Duration, Duration),
     YearMonthDuration(YearMonthDuration, YearMonthDuration),
     DayTimeDuration(DayTimeDuration, DayTimeDuration),
@@ -1925,6 +2935,8 @@ enum NumericBinaryOperands {
     DateDayTimeDuration(Date, DayTimeDuration),
     TimeDuration(Time, Duration),
     TimeDayTimeDuration(Time, DayTimeDuration),
+    YearMonthDurationDecimal(YearMonthDuration, Decimal),
+    DayTimeDurationDecimal(DayTimeDuration, Decimal),
 }
 
 impl NumericBinaryOperands {
@@ -2042,6 +3054,12 @@ impl NumericBinaryOperands {
             (EncodedTerm::TimeLiteral(v1), EncodedTerm::DayTimeDurationLiteral(v2)) => {
                 Some(NumericBinaryOperands::TimeDayTimeDuration(v1, v2))
             }
+            (EncodedTerm::YearMonthDurationLiteral(v1), v2) => Some(
+                NumericBinaryOperands::YearMonthDurationDecimal(v1, to_duration_factor(v2)?),
+            ),
+            (EncodedTerm::DayTimeDurationLiteral(v1), v2) => Some(
+                NumericBinaryOperands::DayTimeDurationDecimal(v1, to_duration_factor(v2)?),
+            ),
             _ => None,
         }
     }
@@ -2136,6 +3154,40 @@ impl<'a> Iterator for JoinIterator<'a> {
     }
 }
 
+struct HashJoinIterator<'a> {
+    left_iter: EncodedTuplesIterator<'a>,
+    right: HashMap<Vec<Option<EncodedTerm>>, Vec<EncodedTuple>>,
+    keys: &'a [usize],
+    buffered_results: Vec<Result<EncodedTuple>>,
+}
+
+impl<'a> Iterator for HashJoinIterator<'a> {
+    type Item = Result<EncodedTuple>;
+
+    fn next(&mut self) -> Option<Result<EncodedTuple>> {
+        loop {
+            if let Some(result) = self.buffered_results.pop() {
+                return Some(result);
+            }
+            let left_tuple = match self.left_iter.next()? {
+                Ok(left_tuple) => left_tuple,
+                Err(error) => return Some(Err(error)),
+            };
+            if let Some(right_tuples) = self.right.get(&tuple_key(&left_tuple, self.keys)) {
+                for right_tuple in right_tuples {
+                    if let Some(result_tuple) = left_tuple.combine_with(right_tuple) {
+                        self.buffered_results.push(Ok(result_tuple))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn tuple_key(tuple: &EncodedTuple, keys: &[usize]) -> Vec<Option<EncodedTerm>> {
+    keys.iter().map(|key| tuple.get(*key)).collect()
+}
+
 struct AntiJoinIterator<'a> {
     left_iter: EncodedTuplesIterator<'a>,
     right: Vec<EncodedTuple>,
@@ -2268,6 +3320,34 @@ impl<'a, S: ReadableEncodedStore> Iterator for UnionIterator<'a, S> {
     }
 }
 
+struct ProfiledIterator<'a> {
+    iter: EncodedTuplesIterator<'a>,
+    profile: Option<Rc<PlanNodeProfile>>,
+    stats: Option<&'a QueryStatsRecorder>,
+}
+
+impl<'a> Iterator for ProfiledIterator<'a> {
+    type Item = Result<EncodedTuple>;
+
+    fn next(&mut self) -> Option<Result<EncodedTuple>> {
+        let result = if let Some(profile) = &self.profile {
+            let start = Instant::now();
+            let result = self.iter.next();
+            profile.add_duration(start.elapsed());
+            if let Some(Ok(_)) = result {
+                profile.add_row();
+            }
+            result
+        } else {
+            self.iter.next()
+        };
+        if let (Some(stats), Some(Ok(_))) = (self.stats, &result) {
+            stats.add_solution();
+        }
+        result
+    }
+}
+
 struct ConstructIterator<'a, S: ReadableEncodedStore> {
     eval: &'a SimpleEvaluator<S>,
     iter: EncodedTuplesIterator<'a>,
@@ -2291,9 +3371,24 @@ impl<'a, S: ReadableEncodedStore + 'a> Iterator for ConstructIterator<'a, S> {
                 };
                 for template in self.template {
                     if let (Some(subject), Some(predicate), Some(object)) = (
-                        get_triple_template_value(&template.subject, &tuple, &mut self.bnodes),
-                        get_triple_template_value(&template.predicate, &tuple, &mut self.bnodes),
-                        get_triple_template_value(&template.object, &tuple, &mut self.bnodes),
+                        get_triple_template_value(
+                            &self.eval.dataset,
+                            &template.subject,
+                            &tuple,
+                            &mut self.bnodes,
+                        ),
+                        get_triple_template_value(
+                            &self.eval.dataset,
+                            &template.predicate,
+                            &tuple,
+                            &mut self.bnodes,
+                        ),
+                        get_triple_template_value(
+                            &self.eval.dataset,
+                            &template.object,
+                            &tuple,
+                            &mut self.bnodes,
+                        ),
                     ) {
                         self.buffered_results.push(decode_triple(
                             &self.eval.dataset,
@@ -2309,7 +3404,8 @@ impl<'a, S: ReadableEncodedStore + 'a> Iterator for ConstructIterator<'a, S> {
     }
 }
 
-fn get_triple_template_value(
+fn get_triple_template_value<S: ReadableEncodedStore>(
+    dataset: &DatasetView<S>,
     selector: &TripleTemplateValue,
     tuple: &EncodedTuple,
     bnodes: &mut Vec<BlankNode>,
@@ -2323,6 +3419,18 @@ fn get_triple_template_value(
             }
             Some((&bnodes[*id]).into())
         }
+        TripleTemplateValue::Triple(triple) => {
+            let subject = get_triple_template_value(dataset, &triple.subject, tuple, bnodes)?;
+            let predicate = get_triple_template_value(dataset, &triple.predicate, tuple, bnodes)?;
+            let object = get_triple_template_value(dataset, &triple.object, tuple, bnodes)?;
+            if subject.is_literal() || !predicate.is_named_node() {
+                return None;
+            }
+            dataset
+                .encoder()
+                .encode_quoted_triple_terms(subject, predicate, object)
+                .ok()
+        }
     }
 }
 
@@ -2333,16 +3441,20 @@ fn decode_triple(
     object: EncodedTerm,
 ) -> Result<Triple> {
     Ok(Triple::new(
-        decoder.decode_named_or_blank_node(subject)?,
+        decoder.decode_subject(subject)?,
         decoder.decode_named_node(predicate)?,
         decoder.decode_term(object)?,
     ))
 }
 
+/// Builds the [Concise Bounded Description](https://www.w3.org/Submission/CBD/) of the described resources:
+/// their triples in the default graph and recursively the ones of the blank nodes they point to.
 struct DescribeIterator<'a, S: ReadableEncodedStore> {
     eval: &'a SimpleEvaluator<S>,
     iter: EncodedTuplesIterator<'a>,
     quads: Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a>,
+    to_describe: Vec<EncodedTerm>,
+    already_described: HashSet<EncodedTerm>,
 }
 
 impl<'a, S: ReadableEncodedStore + 'a> Iterator for DescribeIterator<'a, S> {
@@ -2352,20 +3464,82 @@ impl<'a, S: ReadableEncodedStore + 'a> Iterator for DescribeIterator<'a, S> {
         loop {
             if let Some(quad) = self.quads.next() {
                 return Some(match quad {
-                    Ok(quad) => self.eval.dataset.decode_quad(&quad).map(|q| q.into()),
+                    Ok(quad) => {
+                        if quad.object.is_blank_node() && self.already_described.insert(quad.object)
+                        {
+                            self.to_describe.push(quad.object);
+                        }
+                        self.eval.dataset.decode_quad(&quad).map(|q| q.into())
+                    }
                     Err(error) => Err(error),
                 });
             }
+            if let Some(subject) = self.to_describe.pop() {
+                self.quads = self.eval.quads_for_pattern(
+                    Some(subject),
+                    None,
+                    None,
+                    Some(ENCODED_DEFAULT_GRAPH),
+                );
+                continue;
+            }
             let tuple = match self.iter.next()? {
                 Ok(tuple) => tuple,
                 Err(error) => return Some(Err(error)),
             };
             for subject in tuple.iter() {
                 if let Some(subject) = subject {
-                    self.quads =
-                        self.eval
-                            .dataset
-                            .quads_for_pattern(Some(subject), None, None, None);
+                    if !subject.is_literal() && self.already_described.insert(subject) {
+                        self.to_describe.push(subject);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds the descriptions of the described resources using a `DescribeHandler`
+struct CustomDescribeIterator<'a, S: ReadableEncodedStore> {
+    eval: &'a SimpleEvaluator<S>,
+    iter: EncodedTuplesIterator<'a>,
+    describe_handler: &'a dyn DescribeHandler,
+    triples: std::vec::IntoIter<Triple>,
+    to_describe: Vec<EncodedTerm>,
+    already_described: HashSet<EncodedTerm>,
+}
+
+impl<'a, S: ReadableEncodedStore + 'a> Iterator for CustomDescribeIterator<'a, S> {
+    type Item = Result<Triple>;
+
+    fn next(&mut self) -> Option<Result<Triple>> {
+        loop {
+            if let Some(triple) = self.triples.next() {
+                return Some(Ok(triple));
+            }
+            if let Some(resource) = self.to_describe.pop() {
+                let description = self
+                    .eval
+                    .dataset
+                    .decode_term(resource)
+                    .and_then(|resource| {
+                        self.describe_handler
+                            .describe(&resource, &self.eval.dataset)
+                    });
+                match description {
+                    Ok(description) => self.triples = description.into_iter(),
+                    Err(error) => return Some(Err(error)),
+                }
+                continue;
+            }
+            let tuple = match self.iter.next()? {
+                Ok(tuple) => tuple,
+                Err(error) => return Some(Err(error)),
+            };
+            for resource in tuple.iter() {
+                if let Some(resource) = resource {
+                    if !resource.is_literal() && self.already_described.insert(resource) {
+                        self.to_describe.push(resource);
+                    }
                 }
             }
         }
@@ -2440,6 +3614,27 @@ fn transitive_closure<'a, T: 'a + Copy + Eq + Hash, NI: Iterator<Item = Result<T
     errors.into_iter().map(Err).chain(all.into_iter().map(Ok))
 }
 
+fn bindings_tuple(variables: &[Variable], bindings: &[(Variable, EncodedTerm)]) -> EncodedTuple {
+    let mut tuple = EncodedTuple::with_capacity(variables.len());
+    for (variable, value) in bindings {
+        if let Some(key) = variables.iter().position(|v| v == variable) {
+            tuple.set(key, *value);
+        }
+    }
+    tuple
+}
+
+/// Joins the solutions of a sub-query, evaluated without the outer bindings, with the outer bindings `from`
+fn join_with_outer_bindings<'a>(
+    from: EncodedTuple,
+    iter: EncodedTuplesIterator<'a>,
+) -> EncodedTuplesIterator<'a> {
+    Box::new(iter.filter_map(move |tuple| match tuple {
+        Ok(tuple) => from.combine_with(&tuple).map(Ok),
+        Err(error) => Some(Err(error)),
+    }))
+}
+
 fn hash_deduplicate<T: Eq + Hash + Clone>(
     iter: impl Iterator<Item = Result<T>>,
 ) -> impl Iterator<Item = Result<T>> {
@@ -2514,7 +3709,7 @@ impl<T, O, I: Iterator<Item = Result<T>>, F: FnMut(T) -> U, U: IntoIterator<Item
 trait Accumulator {
     fn add(&mut self, element: Option<EncodedTerm>);
 
-    fn state(&self) -> Option<EncodedTerm>;
+    fn state(&mut self) -> Option<EncodedTerm>;
 }
 
 #[derive(Default, Debug)]
@@ -2539,7 +3734,7 @@ impl<T: Accumulator> Accumulator for DistinctAccumulator<T> {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.inner.state()
     }
 }
@@ -2550,81 +3745,77 @@ struct CountAccumulator {
 }
 
 impl Accumulator for CountAccumulator {
-    fn add(&mut self, _element: Option<EncodedTerm>) {
-        self.count += 1;
+    fn add(&mut self, element: Option<EncodedTerm>) {
+        // Expression errors are not counted
+        if element.is_some() {
+            self.count += 1;
+        }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         Some(self.count.into())
     }
 }
 
-#[derive(Debug)]
-struct SumAccumulator {
+struct SumAccumulator<'a, S: ReadableEncodedStore> {
+    eval: &'a SimpleEvaluator<S>,
     sum: Option<EncodedTerm>,
 }
 
-impl Default for SumAccumulator {
-    fn default() -> Self {
+impl<'a, S: ReadableEncodedStore + 'a> SumAccumulator<'a, S> {
+    fn new(eval: &'a SimpleEvaluator<S>) -> Self {
         Self {
+            eval,
             sum: Some(0.into()),
         }
     }
 }
 
-impl Accumulator for SumAccumulator {
+impl<'a, S: ReadableEncodedStore + 'a> Accumulator for SumAccumulator<'a, S> {
     fn add(&mut self, element: Option<EncodedTerm>) {
         if let Some(sum) = self.sum {
-            if let Some(operands) = element.and_then(|e| NumericBinaryOperands::new(sum, e)) {
-                //TODO: unify with addition?
-                self.sum = match operands {
-                    NumericBinaryOperands::Float(v1, v2) => Some((v1 + v2).into()),
-                    NumericBinaryOperands::Double(v1, v2) => Some((v1 + v2).into()),
-                    NumericBinaryOperands::Integer(v1, v2) => v1.checked_add(v2).map(|v| v.into()),
-                    NumericBinaryOperands::Decimal(v1, v2) => v1.checked_add(v2).map(|v| v.into()),
-                    NumericBinaryOperands::Duration(v1, v2) => v1.checked_add(v2).map(|v| v.into()),
-                    _ => None,
-                };
-            } else {
-                self.sum = None;
-            }
+            self.sum = element
+                .and_then(|e| self.eval.numeric_operands(sum, e))
+                .and_then(|operands| self.eval.add_operands(operands));
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.sum
     }
 }
 
-#[derive(Debug, Default)]
-struct AvgAccumulator {
-    sum: SumAccumulator,
+struct AvgAccumulator<'a, S: ReadableEncodedStore> {
+    eval: &'a SimpleEvaluator<S>,
+    sum: SumAccumulator<'a, S>,
     count: CountAccumulator,
 }
 
-impl Accumulator for AvgAccumulator {
+impl<'a, S: ReadableEncodedStore + 'a> AvgAccumulator<'a, S> {
+    fn new(eval: &'a SimpleEvaluator<S>) -> Self {
+        Self {
+            eval,
+            sum: SumAccumulator::new(eval),
+            count: CountAccumulator::default(),
+        }
+    }
+}
+
+impl<'a, S: ReadableEncodedStore + 'a> Accumulator for AvgAccumulator<'a, S> {
     fn add(&mut self, element: Option<EncodedTerm>) {
         self.sum.add(element);
         self.count.add(element);
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         let sum = self.sum.state()?;
         let count = self.count.state()?;
         if count == EncodedTerm::from(0) {
             Some(0.into())
         } else {
-            //TODO: deduplicate?
             //TODO: duration?
-            match NumericBinaryOperands::new(sum, count)? {
-                NumericBinaryOperands::Float(v1, v2) => Some((v1 / v2).into()),
-                NumericBinaryOperands::Double(v1, v2) => Some((v1 / v2).into()),
-                NumericBinaryOperands::Integer(v1, v2) => {
-                    Decimal::from(v1).checked_div(v2).map(|v| v.into())
-                }
-                NumericBinaryOperands::Decimal(v1, v2) => v1.checked_div(v2).map(|v| v.into()),
-                _ => None,
-            }
+            self.eval
+                .divide_operands(self.eval.numeric_operands(sum, count)?)
         }
     }
 }
@@ -2652,7 +3843,7 @@ impl<'a, S: ReadableEncodedStore + 'a> Accumulator for MinAccumulator<'a, S> {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.min.and_then(|v| v)
     }
 }
@@ -2680,7 +3871,7 @@ impl<'a, S: ReadableEncodedStore + 'a> Accumulator for MaxAccumulator<'a, S> {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.max.and_then(|v| v)
     }
 }
@@ -2697,7 +3888,7 @@ impl Accumulator for SampleAccumulator {
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.value
     }
 }
@@ -2740,7 +3931,7 @@ impl<'a, S: ReadableEncodedStore + 'a> Accumulator for GroupConcatAccumulator<'a
         }
     }
 
-    fn state(&self) -> Option<EncodedTerm> {
+    fn state(&mut self) -> Option<EncodedTerm> {
         self.concat.as_ref().and_then(|result| {
             self.eval
                 .build_plain_literal(result, self.language.and_then(|v| v))
@@ -2748,6 +3939,116 @@ impl<'a, S: ReadableEncodedStore + 'a> Accumulator for GroupConcatAccumulator<'a
     }
 }
 
+struct CustomAccumulator<'a, S: ReadableEncodedStore> {
+    eval: &'a SimpleEvaluator<S>,
+    aggregate: Option<&'a CustomAggregate>,
+    state: Option<Box<dyn Any>>,
+}
+
+impl<'a, S: ReadableEncodedStore + 'a> CustomAccumulator<'a, S> {
+    fn new(eval: &'a SimpleEvaluator<S>, aggregate: Option<&'a CustomAggregate>) -> Self {
+        Self {
+            eval,
+            aggregate,
+            state: aggregate.map(|aggregate| (aggregate.init)()),
+        }
+    }
+}
+
+impl<'a, S: ReadableEncodedStore + 'a> Accumulator for CustomAccumulator<'a, S> {
+    fn add(&mut self, element: Option<EncodedTerm>) {
+        // Expression errors are not given to the aggregate
+        if let (Some(aggregate), Some(state), Some(element)) =
+            (self.aggregate, self.state.as_mut(), element)
+        {
+            if let Ok(element) = self.eval.dataset.decode_term(element) {
+                (aggregate.accumulate)(state.as_mut(), element)
+            }
+        }
+    }
+
+    fn state(&mut self) -> Option<EncodedTerm> {
+        let result = (self.aggregate?.finish)(self.state.take()?)?;
+        self.eval.dataset.encoder().encode_term(&result).ok()
+    }
+}
+
+fn is_duration(term: EncodedTerm) -> bool {
+    matches!(
+        term,
+        EncodedTerm::DurationLiteral(_)
+            | EncodedTerm::YearMonthDurationLiteral(_)
+            | EncodedTerm::DayTimeDurationLiteral(_)
+    )
+}
+
+fn is_temporal(term: EncodedTerm) -> bool {
+    matches!(
+        term,
+        EncodedTerm::DateTimeLiteral(_) | EncodedTerm::DateLiteral(_) | EncodedTerm::TimeLiteral(_)
+    )
+}
+
+/// Returns the number a duration could be multiplied or divided by
+fn to_duration_factor(term: EncodedTerm) -> Option<Decimal> {
+    match term {
+        EncodedTerm::IntegerLiteral(value) => Some(value.into()),
+        EncodedTerm::DecimalLiteral(value) => Some(value),
+        EncodedTerm::FloatLiteral(value) if value.is_finite() => Some(Decimal::from_f32(value)),
+        EncodedTerm::DoubleLiteral(value) if value.is_finite() => Some(Decimal::from_f64(value)),
+        _ => None,
+    }
+}
+
+/// [fn:round](https://www.w3.org/TR/xpath-functions/#func-round): halves are rounded towards positive infinity
+fn round_f64(value: f64) -> f64 {
+    if value - value.floor() == 0.5 {
+        value.ceil()
+    } else {
+        value.round()
+    }
+}
+
+fn round_f32(value: f32) -> f32 {
+    if value - value.floor() == 0.5 {
+        value.ceil()
+    } else {
+        value.round()
+    }
+}
+
+/// Converts a [fn:replace](https://www.w3.org/TR/xpath-functions/#func-replace) replacement string into the `regex` crate syntax
+///
+/// Returns `None` if the replacement string is invalid
+fn to_regex_replacement(replacement: &str) -> Option<String> {
+    let mut result = String::with_capacity(replacement.len());
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                '\\' => result.push('\\'),
+                '$' => result.push_str("$$"),
+                _ => return None,
+            },
+            '$' => {
+                result.push_str("${");
+                let mut has_digit = false;
+                while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    result.push(*d);
+                    has_digit = true;
+                    chars.next();
+                }
+                if !has_digit {
+                    return None;
+                }
+                result.push('}');
+            }
+            c => result.push(c),
+        }
+    }
+    Some(result)
+}
+
 fn generate_uuid(buffer: &mut String) {
     let mut uuid = random::<u128>().to_ne_bytes();
     uuid[6] = (uuid[6] & 0x0F) | 0x40;
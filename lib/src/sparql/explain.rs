@@ -0,0 +1,694 @@
+use crate::sparql::model::Variable;
+use crate::sparql::plan::*;
+use crate::sparql::plan_builder::estimate_cardinality;
+use crate::store::numeric_encoder::{Decoder, EncodedTerm, ENCODED_DEFAULT_GRAPH};
+use crate::store::StoreStatistics;
+use crate::Result;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// A human readable description of the evaluation plan of a query.
+///
+/// It is returned by the `explain` method of the prepared queries and is a tree of plan operations.
+/// Each operation has a name, some attributes (patterns, variables...) and children operations.
+///
+/// Its `Display` implementation prints the plan as an indented tree:
+/// ```
+/// use oxigraph::MemoryStore;
+/// use oxigraph::sparql::QueryOptions;
+///
+/// let store = MemoryStore::new();
+/// let prepared_query = store.prepare_query("SELECT ?s WHERE { ?s <http://example.com/p> ?o }", QueryOptions::default())?;
+/// let explanation = prepared_query.explain()?;
+/// assert_eq!(explanation.name(), "Project");
+/// assert_eq!(explanation.children()[0].name(), "QuadPatternJoin");
+/// # oxigraph::Result::Ok(())
+/// ```
+///
+/// If the query has been prepared with `QueryOptions::with_profiling`, the explanation returned after `exec`
+/// also contains the number of rows returned by each operation and the time spent in it:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::MemoryStore;
+/// use oxigraph::sparql::{QueryOptions, QueryResult};
+///
+/// let store = MemoryStore::new();
+/// let ex = NamedNode::new("http://example.com")?;
+/// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+///
+/// let prepared_query = store.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default().with_profiling())?;
+/// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+///     assert_eq!(solutions.count(), 1);
+/// }
+/// let explanation = prepared_query.explain()?;
+/// assert_eq!(explanation.rows(), Some(1));
+/// assert_eq!(explanation.children()[0].rows(), Some(1));
+/// assert!(explanation.duration().is_some());
+/// # oxigraph::Result::Ok(())
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct QueryExplanation {
+    name: &'static str,
+    attributes: Vec<(&'static str, String)>,
+    children: Vec<QueryExplanation>,
+    profile: Option<NodeProfile>,
+}
+
+impl QueryExplanation {
+    /// The name of the operation like `QuadPatternJoin` or `LeftJoin`
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The attributes of the operation as pairs of (key, value)
+    pub fn attributes(&self) -> &[(&'static str, String)] {
+        &self.attributes
+    }
+
+    /// The operations that are evaluated in order to get the input of this operation
+    pub fn children(&self) -> &[QueryExplanation] {
+        &self.children
+    }
+
+    /// The number of rows returned by the operation during the query execution
+    ///
+    /// It is only available if the query has been prepared with `QueryOptions::with_profiling`.
+    /// If the operation is evaluated several times, for example on the right side of a join, the rows of all the evaluations are summed.
+    pub fn rows(&self) -> Option<usize> {
+        self.profile.map(|p| p.rows)
+    }
+
+    /// The wall-clock time spent in the operation and its children during the query execution
+    ///
+    /// It is only available if the query has been prepared with `QueryOptions::with_profiling`.
+    pub fn duration(&self) -> Option<Duration> {
+        self.profile.map(|p| p.duration)
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.name, indent = indent)?;
+        if !self.attributes.is_empty() {
+            write!(f, " (")?;
+            for (i, (key, value)) in self.attributes.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: {}", key, value)?;
+            }
+            write!(f, ")")?;
+        }
+        if let Some(profile) = self.profile {
+            write!(f, " [rows: {}, time: {:?}]", profile.rows, profile.duration)?;
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.fmt_indented(f, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for QueryExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+struct NodeProfile {
+    rows: usize,
+    duration: Duration,
+}
+
+/// Records the number of rows returned by each plan node and the time spent evaluating it
+#[derive(Default)]
+pub(crate) struct PlanProfiler {
+    nodes: RefCell<HashMap<*const PlanNode, Rc<PlanNodeProfile>>>,
+}
+
+impl PlanProfiler {
+    /// Forgets the records of the previous evaluation
+    pub fn reset(&self) {
+        self.nodes.borrow_mut().clear();
+    }
+
+    /// Returns the record of the given node
+    pub fn node(&self, node: &PlanNode) -> Rc<PlanNodeProfile> {
+        let key: *const PlanNode = node;
+        self.nodes.borrow_mut().entry(key).or_default().clone()
+    }
+
+    fn profile(&self, node: &PlanNode) -> NodeProfile {
+        let key: *const PlanNode = node;
+        self.nodes
+            .borrow()
+            .get(&key)
+            .map_or_else(NodeProfile::default, |p| NodeProfile {
+                rows: p.rows.get(),
+                duration: p.duration.get(),
+            })
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct PlanNodeProfile {
+    rows: Cell<usize>,
+    duration: Cell<Duration>,
+}
+
+impl PlanNodeProfile {
+    pub fn add_row(&self) {
+        self.rows.set(self.rows.get() + 1)
+    }
+
+    pub fn add_duration(&self, duration: Duration) {
+        self.duration.set(self.duration.get() + duration)
+    }
+}
+
+/// Metrics about the last execution of a prepared query.
+///
+/// They are returned by the `stats` method of the prepared queries if they have been prepared with `QueryOptions::with_stats`.
+/// The results are evaluated lazily so the metrics are only complete once the returned solutions or triples have all been consumed:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::MemoryStore;
+/// use oxigraph::sparql::{QueryOptions, QueryResult};
+///
+/// let store = MemoryStore::new();
+/// let ex = NamedNode::new("http://example.com")?;
+/// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+///
+/// let prepared_query = store.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default().with_stats())?;
+/// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+///     assert_eq!(solutions.count(), 1);
+/// }
+/// let stats = prepared_query.stats();
+/// assert_eq!(stats.quad_pattern_lookups(), 1);
+/// assert_eq!(stats.solutions(), 3); // The initial empty solution, the solution of the pattern and the projected one
+/// # oxigraph::Result::Ok(())
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash, Default)]
+pub struct QueryStats {
+    duration: Duration,
+    quad_pattern_lookups: usize,
+    solutions: usize,
+}
+
+impl QueryStats {
+    /// The wall-clock time spent evaluating the query, including the time spent in the iteration of the results
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The number of lookups of quad patterns in the store
+    pub fn quad_pattern_lookups(&self) -> usize {
+        self.quad_pattern_lookups
+    }
+
+    /// The number of intermediate solutions returned by all the operations of the query plan
+    pub fn solutions(&self) -> usize {
+        self.solutions
+    }
+}
+
+impl fmt::Display for QueryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "time: {:?}, quad pattern lookups: {}, solutions: {}",
+            self.duration, self.quad_pattern_lookups, self.solutions
+        )
+    }
+}
+
+/// Records the metrics of a query execution
+#[derive(Default)]
+pub(crate) struct QueryStatsRecorder {
+    duration: Cell<Duration>,
+    quad_pattern_lookups: Cell<usize>,
+    solutions: Cell<usize>,
+}
+
+impl QueryStatsRecorder {
+    pub fn reset(&self) {
+        self.duration.set(Duration::default());
+        self.quad_pattern_lookups.set(0);
+        self.solutions.set(0);
+    }
+
+    pub fn add_duration(&self, duration: Duration) {
+        self.duration.set(self.duration.get() + duration)
+    }
+
+    pub fn add_quad_pattern_lookup(&self) {
+        self.quad_pattern_lookups
+            .set(self.quad_pattern_lookups.get() + 1)
+    }
+
+    pub fn add_solution(&self) {
+        self.solutions.set(self.solutions.get() + 1)
+    }
+
+    pub fn stats(&self) -> QueryStats {
+        QueryStats {
+            duration: self.duration.get(),
+            quad_pattern_lookups: self.quad_pattern_lookups.get(),
+            solutions: self.solutions.get(),
+        }
+    }
+}
+
+pub(crate) struct PlanExplainer<'a, D: Decoder + StoreStatistics> {
+    decoder: &'a D,
+    profiler: Option<&'a PlanProfiler>,
+}
+
+impl<'a, D: Decoder + StoreStatistics> PlanExplainer<'a, D> {
+    pub fn new(decoder: &'a D, profiler: Option<&'a PlanProfiler>) -> Self {
+        Self { decoder, profiler }
+    }
+
+    pub fn explain(&self, node: &PlanNode, variables: &[Variable]) -> Result<QueryExplanation> {
+        let mut explanation = self.explain_node(node, variables)?;
+        explanation.profile = self.profiler.map(|profiler| profiler.profile(node));
+        Ok(explanation)
+    }
+
+    fn explain_node(&self, node: &PlanNode, variables: &[Variable]) -> Result<QueryExplanation> {
+        Ok(match node {
+            PlanNode::Init => QueryExplanation {
+                name: "Init",
+                attributes: Vec::default(),
+                children: Vec::default(),
+                profile: None,
+            },
+            PlanNode::StaticBindings { tuples } => QueryExplanation {
+                name: "StaticBindings",
+                attributes: vec![("rows", tuples.len().to_string())],
+                children: Vec::default(),
+                profile: None,
+            },
+            PlanNode::Service {
+                service_name,
+                child,
+                silent,
+                ..
+            } => QueryExplanation {
+                name: "Service",
+                attributes: vec![
+                    ("name", self.pattern_value(service_name, variables)?),
+                    ("silent", silent.to_string()),
+                ],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::QuadPatternJoin {
+                child,
+                subject,
+                predicate,
+                object,
+                graph_name,
+                limit,
+            } => {
+                let bound = child.maybe_bound_variables();
+                let mut attributes = vec![
+                    (
+                        "pattern",
+                        format!(
+                            "{} {} {}",
+                            self.pattern_value(subject, variables)?,
+                            self.pattern_value(predicate, variables)?,
+                            self.pattern_value(object, variables)?
+                        ),
+                    ),
+                    ("graph", self.pattern_value(graph_name, variables)?),
+                    (
+                        "lookup",
+                        format!(
+                            "({}, {}, {}, {})",
+                            lookup_key("s", subject, &bound),
+                            lookup_key("p", predicate, &bound),
+                            lookup_key("o", object, &bound),
+                            lookup_key("g", graph_name, &bound)
+                        ),
+                    ),
+                ];
+                if let Some(limit) = limit {
+                    attributes.push(("limit", limit.to_string()));
+                }
+                if let PatternValue::Constant(predicate) = predicate {
+                    if let Some(statistics) = self.decoder.predicate_statistics(*predicate) {
+                        attributes.push((
+                            "estimated cardinality",
+                            estimate_cardinality(
+                                statistics,
+                                lookup_key("s", subject, &bound) != "?",
+                                lookup_key("o", object, &bound) != "?",
+                            )
+                            .to_string(),
+                        ));
+                    }
+                }
+                QueryExplanation {
+                    name: "QuadPatternJoin",
+                    attributes,
+                    children: vec![self.explain(child, variables)?],
+                    profile: None,
+                }
+            }
+            PlanNode::PathPatternJoin {
+                child,
+                subject,
+                path,
+                object,
+                graph_name,
+            } => QueryExplanation {
+                name: "PathPatternJoin",
+                attributes: vec![
+                    (
+                        "pattern",
+                        format!(
+                            "{} {} {}",
+                            self.pattern_value(subject, variables)?,
+                            self.path(path)?,
+                            self.pattern_value(object, variables)?
+                        ),
+                    ),
+                    ("graph", self.pattern_value(graph_name, variables)?),
+                ],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::FullTextSearch {
+                child,
+                literal,
+                query,
+                graph_name,
+            } => QueryExplanation {
+                name: "FullTextSearch",
+                attributes: vec![
+                    ("literal", self.pattern_value(literal, variables)?),
+                    ("query", self.pattern_value(query, variables)?),
+                    ("graph", self.pattern_value(graph_name, variables)?),
+                ],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::SpatialSearch {
+                child,
+                literal,
+                envelope,
+                within,
+            } => QueryExplanation {
+                name: "SpatialSearch",
+                attributes: vec![
+                    ("literal", self.pattern_value(literal, variables)?),
+                    (
+                        "envelope",
+                        format!(
+                            "{} {} {} {}",
+                            envelope.min[0], envelope.min[1], envelope.max[0], envelope.max[1]
+                        ),
+                    ),
+                    ("within", within.to_string()),
+                ],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::QuotedTriplePattern {
+                child,
+                triple,
+                subject,
+                predicate,
+                object,
+            } => QueryExplanation {
+                name: "QuotedTriplePattern",
+                attributes: vec![
+                    ("triple", self.pattern_value(triple, variables)?),
+                    (
+                        "pattern",
+                        format!(
+                            "<< {} {} {} >>",
+                            self.pattern_value(subject, variables)?,
+                            self.pattern_value(predicate, variables)?,
+                            self.pattern_value(object, variables)?
+                        ),
+                    ),
+                ],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::PropertyFunction {
+                child,
+                subject,
+                function,
+                object,
+                graph_name,
+            } => QueryExplanation {
+                name: "PropertyFunction",
+                attributes: vec![
+                    (
+                        "pattern",
+                        format!(
+                            "{} {} {}",
+                            self.pattern_value(subject, variables)?,
+                            function,
+                            self.pattern_value(object, variables)?
+                        ),
+                    ),
+                    ("graph", self.pattern_value(graph_name, variables)?),
+                ],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::Join { left, right } => QueryExplanation {
+                name: "Join",
+                attributes: Vec::default(),
+                children: vec![
+                    self.explain(left, variables)?,
+                    self.explain(right, variables)?,
+                ],
+                profile: None,
+            },
+            PlanNode::HashJoin { left, right, keys } => QueryExplanation {
+                name: "HashJoin",
+                attributes: vec![("keys", variable_list(keys, variables))],
+                children: vec![
+                    self.explain(left, variables)?,
+                    self.explain(right, variables)?,
+                ],
+                profile: None,
+            },
+            PlanNode::AntiJoin { left, right } => QueryExplanation {
+                name: "AntiJoin",
+                attributes: Vec::default(),
+                children: vec![
+                    self.explain(left, variables)?,
+                    self.explain(right, variables)?,
+                ],
+                profile: None,
+            },
+            PlanNode::LeftJoin { left, right, .. } => QueryExplanation {
+                name: "LeftJoin",
+                attributes: Vec::default(),
+                children: vec![
+                    self.explain(left, variables)?,
+                    self.explain(right, variables)?,
+                ],
+                profile: None,
+            },
+            PlanNode::Filter { child, expression } => {
+                let mut used = BTreeSet::default();
+                expression.add_maybe_bound_variables(&mut used);
+                QueryExplanation {
+                    name: "Filter",
+                    attributes: vec![("variables", variable_list(&used, variables))],
+                    children: vec![self.explain(child, variables)?],
+                    profile: None,
+                }
+            }
+            PlanNode::Union { children } => QueryExplanation {
+                name: "Union",
+                attributes: Vec::default(),
+                children: children
+                    .iter()
+                    .map(|child| self.explain(child, variables))
+                    .collect::<Result<_>>()?,
+                profile: None,
+            },
+            PlanNode::Extend {
+                child,
+                position,
+                expression,
+            } => {
+                let mut used = BTreeSet::default();
+                expression.add_maybe_bound_variables(&mut used);
+                QueryExplanation {
+                    name: "Extend",
+                    attributes: vec![
+                        ("variable", variable_name(*position, variables)),
+                        ("variables", variable_list(&used, variables)),
+                    ],
+                    children: vec![self.explain(child, variables)?],
+                    profile: None,
+                }
+            }
+            PlanNode::Sort { child, by } => QueryExplanation {
+                name: "Sort",
+                attributes: vec![("keys", by.len().to_string())],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::HashDeduplicate { child } => QueryExplanation {
+                name: "HashDeduplicate",
+                attributes: Vec::default(),
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::Skip { child, count } => QueryExplanation {
+                name: "Skip",
+                attributes: vec![("count", count.to_string())],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::Limit { child, count } => QueryExplanation {
+                name: "Limit",
+                attributes: vec![("count", count.to_string())],
+                children: vec![self.explain(child, variables)?],
+                profile: None,
+            },
+            PlanNode::Project {
+                child,
+                mapping,
+                child_variables,
+            } => QueryExplanation {
+                name: "Project",
+                attributes: vec![(
+                    "variables",
+                    variable_list(mapping.iter().map(|(_, o)| o), variables),
+                )],
+                children: vec![self.explain(child, child_variables)?],
+                profile: None,
+            },
+            PlanNode::Aggregate {
+                child,
+                key_mapping,
+                aggregates,
+                child_variables,
+            } => QueryExplanation {
+                name: "Aggregate",
+                attributes: vec![
+                    (
+                        "keys",
+                        variable_list(key_mapping.iter().map(|(_, o)| o), variables),
+                    ),
+                    (
+                        "aggregates",
+                        aggregates
+                            .iter()
+                            .map(|(aggregate, v)| {
+                                format!(
+                                    "{} AS {}",
+                                    aggregation_name(aggregate),
+                                    variable_name(*v, variables)
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    ),
+                ],
+                children: vec![self.explain(child, child_variables)?],
+                profile: None,
+            },
+        })
+    }
+
+    fn pattern_value(&self, value: &PatternValue, variables: &[Variable]) -> Result<String> {
+        match value {
+            PatternValue::Constant(term) => self.term(*term),
+            PatternValue::Variable(v) => Ok(variable_name(*v, variables)),
+        }
+    }
+
+    fn term(&self, term: EncodedTerm) -> Result<String> {
+        Ok(if term == ENCODED_DEFAULT_GRAPH {
+            "DEFAULT".to_owned()
+        } else {
+            self.decoder.decode_term(term)?.to_string()
+        })
+    }
+
+    fn path(&self, path: &PlanPropertyPath) -> Result<String> {
+        Ok(match path {
+            PlanPropertyPath::PredicatePath(p) => self.term(*p)?,
+            PlanPropertyPath::InversePath(p) => format!("^({})", self.path(p)?),
+            PlanPropertyPath::SequencePath(a, b) => {
+                format!("({} / {})", self.path(a)?, self.path(b)?)
+            }
+            PlanPropertyPath::AlternativePath(a, b) => {
+                format!("({} | {})", self.path(a)?, self.path(b)?)
+            }
+            PlanPropertyPath::ZeroOrMorePath(p) => format!("({})*", self.path(p)?),
+            PlanPropertyPath::OneOrMorePath(p) => format!("({})+", self.path(p)?),
+            PlanPropertyPath::ZeroOrOnePath(p) => format!("({})?", self.path(p)?),
+            PlanPropertyPath::NegatedPropertySet(ps) => format!(
+                "!({})",
+                ps.iter()
+                    .map(|p| self.term(*p))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(" | ")
+            ),
+        })
+    }
+}
+
+fn variable_name(key: usize, variables: &[Variable]) -> String {
+    if let Some(variable) = variables.get(key) {
+        variable.to_string()
+    } else {
+        format!("?{}", key)
+    }
+}
+
+fn variable_list<'a>(keys: impl IntoIterator<Item = &'a usize>, variables: &[Variable]) -> String {
+    keys.into_iter()
+        .map(|k| variable_name(*k, variables))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the name of the position if it is known when the store is looked up and `?` if not
+fn lookup_key(name: &'static str, value: &PatternValue, bound: &BTreeSet<usize>) -> &'static str {
+    match value {
+        PatternValue::Constant(_) => name,
+        PatternValue::Variable(v) => {
+            if bound.contains(v) {
+                name
+            } else {
+                "?"
+            }
+        }
+    }
+}
+
+fn aggregation_name(aggregate: &PlanAggregation) -> String {
+    let name = match &aggregate.function {
+        PlanAggregationFunction::Count => "COUNT",
+        PlanAggregationFunction::Sum => "SUM",
+        PlanAggregationFunction::Min => "MIN",
+        PlanAggregationFunction::Max => "MAX",
+        PlanAggregationFunction::Avg => "AVG",
+        PlanAggregationFunction::Sample => "SAMPLE",
+        PlanAggregationFunction::GroupConcat { .. } => "GROUP_CONCAT",
+        PlanAggregationFunction::Custom(name) => name.as_str(),
+    };
+    if aggregate.distinct {
+        format!("{}(DISTINCT)", name)
+    } else {
+        name.to_owned()
+    }
+}
@@ -0,0 +1,615 @@
+//! Minimal support of the [GeoSPARQL](https://www.ogc.org/standards/geosparql) geometries serialized as WKT literals.
+//!
+//! Only the `POINT`, `LINESTRING` and `POLYGON` 2D geometries in the default `CRS84` coordinate system (longitude, latitude) are supported.
+//! Topological relations are computed in the plane defined by the coordinates and distances on a spherical earth.
+//! With the `spatial-index` feature, the `MemoryStore` indexes the bounding boxes of its WKT literals:
+//! the `geof:sfWithin` and `geof:distance` filters against a constant geometry only read the literals whose bounding box could match.
+
+use crate::Error;
+use crate::Result;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+pub const WKT_LITERAL: &str = "http://www.opengis.net/ont/geosparql#wktLiteral";
+pub const DISTANCE: &str = "http://www.opengis.net/def/function/geosparql/distance";
+pub const SF_WITHIN: &str = "http://www.opengis.net/def/function/geosparql/sfWithin";
+pub const SF_CONTAINS: &str = "http://www.opengis.net/def/function/geosparql/sfContains";
+
+const CRS84: &str = "http://www.opengis.net/def/crs/OGC/1.3/CRS84";
+const METRE: &str = "http://www.opengis.net/def/uom/OGC/1.0/metre";
+const RADIAN: &str = "http://www.opengis.net/def/uom/OGC/1.0/radian";
+const DEGREE: &str = "http://www.opengis.net/def/uom/OGC/1.0/degree";
+
+/// Mean earth radius in metres
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    longitude: f64,
+    latitude: f64,
+}
+
+/// A bounding box, the coordinates are `[longitude, latitude]`
+#[derive(Debug, Clone, Copy)]
+pub struct Envelope {
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+}
+
+impl Envelope {
+    fn to_bits(self) -> [u64; 4] {
+        [
+            self.min[0].to_bits(),
+            self.min[1].to_bits(),
+            self.max[0].to_bits(),
+            self.max[1].to_bits(),
+        ]
+    }
+
+    /// Checks if this envelope contains the other one, boundary included
+    pub fn contains(&self, other: &Self) -> bool {
+        self.min[0] <= other.min[0]
+            && self.min[1] <= other.min[1]
+            && other.max[0] <= self.max[0]
+            && other.max[1] <= self.max[1]
+    }
+
+    /// Checks if the two envelopes share at least a point
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min[0] <= other.max[0]
+            && other.min[0] <= self.max[0]
+            && self.min[1] <= other.max[1]
+            && other.min[1] <= self.max[1]
+    }
+
+    /// Returns an envelope containing all the points at most at `distance` metres from this envelope
+    pub fn expand(&self, distance: f64) -> Self {
+        let delta_latitude = (distance / EARTH_RADIUS).to_degrees();
+        let min_latitude = (self.min[1] - delta_latitude).max(-90.);
+        let max_latitude = (self.max[1] + delta_latitude).min(90.);
+        // The parallels are the shortest at the latitude the furthest from the equator
+        let scale = min_latitude
+            .abs()
+            .max(max_latitude.abs())
+            .to_radians()
+            .cos();
+        // Bound of both the great-circle distance and its equirectangular approximation
+        let chord = (distance / EARTH_RADIUS / 2.).sin() / scale;
+        let delta_longitude = if scale <= 0. || chord >= 1. {
+            f64::INFINITY
+        } else {
+            (2. * chord.asin())
+                .max(distance / EARTH_RADIUS / scale)
+                .to_degrees()
+        };
+        let (min_longitude, max_longitude) =
+            if self.min[0] - delta_longitude < -180. || self.max[0] + delta_longitude > 180. {
+                // The envelope would cross the antimeridian
+                (-180., 180.)
+            } else {
+                (self.min[0] - delta_longitude, self.max[0] + delta_longitude)
+            };
+        Self {
+            min: [min_longitude, min_latitude],
+            max: [max_longitude, max_latitude],
+        }
+    }
+}
+
+// The envelopes are compared by bits in order to be used in the query plans
+impl PartialEq for Envelope {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bits() == other.to_bits()
+    }
+}
+
+impl Eq for Envelope {}
+
+impl Hash for Envelope {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.to_bits().hash(state)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    Point(Point),
+    LineString(Vec<Point>),
+    /// The exterior ring followed by the holes
+    Polygon(Vec<Vec<Point>>),
+}
+
+impl Geometry {
+    /// Returns the distance between the two geometries in the given unit of measure
+    ///
+    /// Returns `None` if the unit is not supported.
+    pub fn distance(&self, other: &Self, unit: &str) -> Option<f64> {
+        let distance = if self.intersects(other) {
+            0.
+        } else {
+            self.vertices()
+                .iter()
+                .map(|p| other.distance_to_point(*p))
+                .chain(other.vertices().iter().map(|p| self.distance_to_point(*p)))
+                .fold(f64::INFINITY, f64::min)
+        };
+        match unit {
+            METRE => Some(distance),
+            RADIAN => Some(distance / EARTH_RADIUS),
+            DEGREE => Some((distance / EARTH_RADIUS).to_degrees()),
+            _ => None,
+        }
+    }
+
+    /// Checks if this geometry is within the other one, i.e. if all its points are in the other one, boundary included
+    pub fn within(&self, other: &Self) -> bool {
+        if !self.vertices().iter().all(|p| other.contains_point(*p)) {
+            return false;
+        }
+        // The segments are cut where they meet the other boundary: each piece should be inside or outside
+        for (a, b) in self.segments() {
+            let mut cuts = vec![0., 1.];
+            for (c, d) in other.segments() {
+                cuts.extend(segment_cuts(a, b, c, d));
+            }
+            cuts.sort_by(|t1: &f64, t2| t1.partial_cmp(t2).unwrap_or(Ordering::Equal));
+            if cuts
+                .windows(2)
+                .any(|w| !other.contains_point(point_on_segment(a, b, (w[0] + w[1]) / 2.)))
+            {
+                return false;
+            }
+        }
+        // A polygon should not contain the other boundary, for example a hole
+        if let Geometry::Polygon(_) = self {
+            if other.segments().any(|(c, d)| {
+                self.contains_point_in_interior(c)
+                    || self.contains_point_in_interior(point_on_segment(c, d, 0.5))
+            }) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Returns the smallest envelope containing the geometry
+    pub fn envelope(&self) -> Envelope {
+        let mut envelope = Envelope {
+            min: [f64::INFINITY, f64::INFINITY],
+            max: [f64::NEG_INFINITY, f64::NEG_INFINITY],
+        };
+        for p in self.vertices() {
+            envelope.min[0] = envelope.min[0].min(p.longitude);
+            envelope.min[1] = envelope.min[1].min(p.latitude);
+            envelope.max[0] = envelope.max[0].max(p.longitude);
+            envelope.max[1] = envelope.max[1].max(p.latitude);
+        }
+        envelope
+    }
+
+    fn intersects(&self, other: &Self) -> bool {
+        self.vertices().iter().any(|p| other.contains_point(*p))
+            || other.vertices().iter().any(|p| self.contains_point(*p))
+            || self.segments().any(|(a1, b1)| {
+                other
+                    .segments()
+                    .any(|(a2, b2)| segments_intersect(a1, b1, a2, b2))
+            })
+    }
+
+    fn vertices(&self) -> Vec<Point> {
+        match self {
+            Geometry::Point(p) => vec![*p],
+            Geometry::LineString(points) => points.clone(),
+            Geometry::Polygon(rings) => rings.iter().flatten().copied().collect(),
+        }
+    }
+
+    fn segments<'a>(&'a self) -> Box<dyn Iterator<Item = (Point, Point)> + 'a> {
+        match self {
+            Geometry::Point(_) => Box::new(std::iter::empty()),
+            Geometry::LineString(points) => Box::new(points.windows(2).map(|w| (w[0], w[1]))),
+            Geometry::Polygon(rings) => Box::new(
+                rings
+                    .iter()
+                    .flat_map(|ring| ring.windows(2).map(|w| (w[0], w[1]))),
+            ),
+        }
+    }
+
+    fn contains_point(&self, p: Point) -> bool {
+        match self {
+            Geometry::Point(q) => p == *q,
+            Geometry::LineString(_) => self.segments().any(|(a, b)| is_on_segment(p, a, b)),
+            Geometry::Polygon(rings) => {
+                if self.segments().any(|(a, b)| is_on_segment(p, a, b)) {
+                    return true;
+                }
+                let mut rings = rings.iter();
+                rings
+                    .next()
+                    .map_or(false, |exterior| is_in_ring(p, exterior))
+                    && rings.all(|hole| !is_in_ring(p, hole))
+            }
+        }
+    }
+
+    /// Checks if the point is in the polygon and not on its boundary
+    fn contains_point_in_interior(&self, p: Point) -> bool {
+        match self {
+            Geometry::Polygon(rings) => {
+                let mut rings = rings.iter();
+                !self.segments().any(|(a, b)| is_on_segment(p, a, b))
+                    && rings
+                        .next()
+                        .map_or(false, |exterior| is_in_ring(p, exterior))
+                    && rings.all(|hole| !is_in_ring(p, hole))
+            }
+            _ => false,
+        }
+    }
+
+    /// Distance in metres between the point and the closest point of the geometry
+    fn distance_to_point(&self, p: Point) -> f64 {
+        match self {
+            Geometry::Point(q) => haversine_distance(p, *q),
+            _ => {
+                if self.contains_point(p) {
+                    0.
+                } else {
+                    self.segments()
+                        .map(|(a, b)| segment_distance(p, a, b))
+                        .fold(f64::INFINITY, f64::min)
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Geometry {
+    type Err = Error;
+
+    /// Parses a [WKT literal](http://www.opengis.net/ont/geosparql#wktLiteral) lexical form
+    fn from_str(input: &str) -> Result<Self> {
+        let mut input = input.trim();
+        if input.starts_with('<') {
+            let end = input
+                .find('>')
+                .ok_or_else(|| Error::msg("Unclosed coordinate system IRI in WKT literal"))?;
+            let crs = &input[1..end];
+            if crs != CRS84 {
+                return Err(Error::msg(format!(
+                    "The coordinate system {} is not supported",
+                    crs
+                )));
+            }
+            input = input[end + 1..].trim_start();
+        }
+        let start = input
+            .find('(')
+            .ok_or_else(|| Error::msg(format!("Invalid WKT geometry: {}", input)))?;
+        let kind = input[..start].trim().to_ascii_uppercase();
+        let body = input[start..].trim();
+        match kind.as_str() {
+            "POINT" => {
+                let mut points = parse_points(strip_parentheses(body)?)?;
+                if points.len() == 1 {
+                    Ok(Geometry::Point(points.remove(0)))
+                } else {
+                    Err(Error::msg("A WKT point should have exactly one position"))
+                }
+            }
+            "LINESTRING" => Ok(Geometry::LineString(parse_points(strip_parentheses(
+                body,
+            )?)?)),
+            "POLYGON" => {
+                let mut rings = Vec::default();
+                for ring in split_top_level(strip_parentheses(body)?) {
+                    let ring = parse_points(strip_parentheses(ring.trim())?)?;
+                    if ring.len() < 4 || ring.first() != ring.last() {
+                        return Err(Error::msg(
+                            "A WKT polygon ring should be closed and have at least 4 positions",
+                        ));
+                    }
+                    rings.push(ring);
+                }
+                Ok(Geometry::Polygon(rings))
+            }
+            _ => Err(Error::msg(format!(
+                "The WKT geometry type {} is not supported",
+                kind
+            ))),
+        }
+    }
+}
+
+fn strip_parentheses(input: &str) -> Result<&str> {
+    if input.starts_with('(') && input.ends_with(')') {
+        Ok(&input[1..input.len() - 1])
+    } else {
+        Err(Error::msg(format!(
+            "Expecting a parenthesized WKT list, found {}",
+            input
+        )))
+    }
+}
+
+fn split_top_level(input: &str) -> Vec<&str> {
+    let mut parts = Vec::default();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+fn parse_points(input: &str) -> Result<Vec<Point>> {
+    input
+        .split(',')
+        .map(|position| {
+            let coordinates = position
+                .split_whitespace()
+                .map(|c| {
+                    c.parse::<f64>()
+                        .map_err(|_| Error::msg(format!("Invalid WKT coordinate: {}", c)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if coordinates.len() == 2 {
+                Ok(Point {
+                    longitude: coordinates[0],
+                    latitude: coordinates[1],
+                })
+            } else {
+                Err(Error::msg(format!(
+                    "Only 2D WKT positions are supported, found {}",
+                    position.trim()
+                )))
+            }
+        })
+        .collect()
+}
+
+/// Ray casting algorithm
+fn is_in_ring(p: Point, ring: &[Point]) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let (a, b) = (w[0], w[1]);
+        if (a.latitude > p.latitude) != (b.latitude > p.latitude)
+            && p.longitude
+                < (b.longitude - a.longitude) * (p.latitude - a.latitude)
+                    / (b.latitude - a.latitude)
+                    + a.longitude
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn cross_product(o: Point, a: Point, b: Point) -> f64 {
+    (a.longitude - o.longitude) * (b.latitude - o.latitude)
+        - (a.latitude - o.latitude) * (b.longitude - o.longitude)
+}
+
+fn is_on_segment(p: Point, a: Point, b: Point) -> bool {
+    cross_product(a, b, p).abs() <= f64::EPSILON
+        && p.longitude >= a.longitude.min(b.longitude)
+        && p.longitude <= a.longitude.max(b.longitude)
+        && p.latitude >= a.latitude.min(b.latitude)
+        && p.latitude <= a.latitude.max(b.latitude)
+}
+
+fn segments_intersect(a1: Point, b1: Point, a2: Point, b2: Point) -> bool {
+    let d1 = cross_product(a2, b2, a1);
+    let d2 = cross_product(a2, b2, b1);
+    let d3 = cross_product(a1, b1, a2);
+    let d4 = cross_product(a1, b1, b2);
+    ((d1 > 0. && d2 < 0.) || (d1 < 0. && d2 > 0.)) && ((d3 > 0. && d4 < 0.) || (d3 < 0. && d4 > 0.))
+        || is_on_segment(a1, a2, b2)
+        || is_on_segment(b1, a2, b2)
+        || is_on_segment(a2, a1, b1)
+        || is_on_segment(b2, a1, b1)
+}
+
+/// Positions on the segment `[a, b]` where it meets the segment `[c, d]`, as fractions of its length
+fn segment_cuts(a: Point, b: Point, c: Point, d: Point) -> Vec<f64> {
+    let (rx, ry) = (b.longitude - a.longitude, b.latitude - a.latitude);
+    let (sx, sy) = (d.longitude - c.longitude, d.latitude - c.latitude);
+    let (qx, qy) = (c.longitude - a.longitude, c.latitude - a.latitude);
+    let denominator = rx * sy - ry * sx;
+    if denominator.abs() > f64::EPSILON {
+        let t = (qx * sy - qy * sx) / denominator;
+        let u = (qx * ry - qy * rx) / denominator;
+        if (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u) {
+            vec![t]
+        } else {
+            Vec::new()
+        }
+    } else if cross_product(a, b, c).abs() <= f64::EPSILON {
+        // The segments are collinear: the ends of the other one cut this one
+        let length = rx * rx + ry * ry;
+        if length > 0. {
+            [c, d]
+                .iter()
+                .map(|p| {
+                    ((p.longitude - a.longitude) * rx + (p.latitude - a.latitude) * ry) / length
+                })
+                .filter(|t| (0. ..=1.).contains(t))
+                .collect()
+        } else {
+            Vec::new()
+        }
+    } else {
+        Vec::new()
+    }
+}
+
+fn point_on_segment(a: Point, b: Point, t: f64) -> Point {
+    Point {
+        longitude: a.longitude + t * (b.longitude - a.longitude),
+        latitude: a.latitude + t * (b.latitude - a.latitude),
+    }
+}
+
+/// Converts a distance in the given unit of measure to metres
+///
+/// Returns `None` if the unit is not supported.
+pub fn to_metres(distance: f64, unit: &str) -> Option<f64> {
+    match unit {
+        METRE => Some(distance),
+        RADIAN => Some(distance * EARTH_RADIUS),
+        DEGREE => Some(distance.to_radians() * EARTH_RADIUS),
+        _ => None,
+    }
+}
+
+/// Great-circle distance in metres
+fn haversine_distance(a: Point, b: Point) -> f64 {
+    let (lat1, lat2) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let delta_lat = lat2 - lat1;
+    let delta_lon = (b.longitude - a.longitude).to_radians();
+    let h =
+        (delta_lat / 2.).sin().powi(2) + lat1.cos() * lat2.cos() * (delta_lon / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * h.sqrt().min(1.).asin()
+}
+
+/// Distance in metres between a point and a segment using an equirectangular projection around the point
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let scale = p.latitude.to_radians().cos();
+    let project = |q: Point| {
+        (
+            (q.longitude - p.longitude).to_radians() * scale,
+            (q.latitude - p.latitude).to_radians(),
+        )
+    };
+    let (ax, ay) = project(a);
+    let (bx, by) = project(b);
+    let (dx, dy) = (bx - ax, by - ay);
+    let length = dx * dx + dy * dy;
+    let t = if length > 0. {
+        (-(ax * dx + ay * dy) / length).max(0.).min(1.)
+    } else {
+        0.
+    };
+    let (x, y) = (ax + t * dx, ay + t * dy);
+    EARTH_RADIUS * (x * x + y * y).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wkt() -> Result<()> {
+        assert_eq!(
+            Geometry::from_str("POINT(2.35 48.85)")?,
+            Geometry::Point(Point {
+                longitude: 2.35,
+                latitude: 48.85
+            })
+        );
+        assert_eq!(
+            Geometry::from_str("<http://www.opengis.net/def/crs/OGC/1.3/CRS84> Point (1 2)")?,
+            Geometry::Point(Point {
+                longitude: 1.,
+                latitude: 2.
+            })
+        );
+        assert!(Geometry::from_str(
+            "POLYGON((0 0, 1 0, 1 1, 0 1, 0 0), (0.2 0.2, 0.4 0.2, 0.4 0.4, 0.2 0.2))"
+        )
+        .is_ok());
+        assert!(Geometry::from_str("POLYGON((0 0, 1 0, 1 1))").is_err());
+        assert!(Geometry::from_str("POINT(1 2 3)").is_err());
+        assert!(
+            Geometry::from_str("<http://www.opengis.net/def/crs/EPSG/0/4326> POINT(1 2)").is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn within() -> Result<()> {
+        let square = Geometry::from_str(
+            "POLYGON((0 0, 10 0, 10 10, 0 10, 0 0), (4 4, 6 4, 6 6, 4 6, 4 4))",
+        )?;
+        assert!(Geometry::from_str("POINT(1 1)")?.within(&square));
+        assert!(Geometry::from_str("POINT(0 5)")?.within(&square));
+        assert!(!Geometry::from_str("POINT(5 5)")?.within(&square));
+        assert!(!Geometry::from_str("POINT(11 5)")?.within(&square));
+        assert!(Geometry::from_str("LINESTRING(1 1, 2 2)")?.within(&square));
+        assert!(Geometry::from_str("LINESTRING(0 0, 10 0)")?.within(&square));
+        // The line string crosses the hole between two vertices that are within the polygon
+        assert!(!Geometry::from_str("LINESTRING(3 5, 7 5)")?.within(&square));
+        // The polygon contains the hole
+        assert!(!Geometry::from_str("POLYGON((3 3, 7 3, 7 7, 3 7, 3 3))")?.within(&square));
+        assert!(Geometry::from_str("POLYGON((1 1, 3 1, 3 3, 1 3, 1 1))")?.within(&square));
+        assert!(square.within(&square));
+
+        // The segment between the two arms of a "U" leaves the polygon
+        let concave = Geometry::from_str("POLYGON((0 0, 3 0, 3 3, 2 3, 2 1, 1 1, 1 3, 0 3, 0 0))")?;
+        assert!(Geometry::from_str("POINT(0.5 2)")?.within(&concave));
+        assert!(!Geometry::from_str("POINT(1.5 2)")?.within(&concave));
+        assert!(!Geometry::from_str("LINESTRING(0.5 2, 2.5 2)")?.within(&concave));
+        assert!(Geometry::from_str("LINESTRING(0.5 2, 0.5 0.5, 2.5 0.5, 2.5 2)")?.within(&concave));
+        assert!(
+            !Geometry::from_str("POLYGON((0.5 0.5, 2.5 0.5, 2.5 2, 0.5 2, 0.5 0.5))")?
+                .within(&concave)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn distance() -> Result<()> {
+        let paris = Geometry::from_str("POINT(2.3522 48.8566)")?;
+        let london = Geometry::from_str("POINT(-0.1276 51.5072)")?;
+        let distance = paris.distance(&london, METRE).unwrap();
+        assert!((distance - 343_500.).abs() < 1_000., "{}", distance);
+        assert_eq!(paris.distance(&paris, METRE), Some(0.));
+        assert_eq!(paris.distance(&london, "http://example.com"), None);
+
+        let line = Geometry::from_str("LINESTRING(0 -1, 0 1)")?;
+        let point = Geometry::from_str("POINT(1 0)")?;
+        let distance = point.distance(&line, METRE).unwrap();
+        assert!((distance - 111_195.).abs() < 100., "{}", distance);
+        Ok(())
+    }
+
+    #[test]
+    fn envelope() -> Result<()> {
+        let line = Geometry::from_str("LINESTRING(0 -1, 2 1, 1 3)")?;
+        assert_eq!(
+            line.envelope(),
+            Envelope {
+                min: [0., -1.],
+                max: [2., 3.]
+            }
+        );
+
+        // The points at the given distance are in the expanded envelope
+        let paris = Geometry::from_str("POINT(2.3522 48.8566)")?;
+        let london = Geometry::from_str("POINT(-0.1276 51.5072)")?;
+        let distance = paris.distance(&london, METRE).unwrap();
+        let envelope = paris.envelope().expand(distance);
+        assert!(envelope.contains(&london.envelope()));
+        assert!(!paris
+            .envelope()
+            .expand(distance / 2.)
+            .contains(&london.envelope()));
+        for (position, distance) in &[("POINT(0 89)", 200_000.), ("POINT(179.9 0)", 100_000.)] {
+            let envelope = Geometry::from_str(position)?.envelope().expand(*distance);
+            assert_eq!((envelope.min[0], envelope.max[0]), (-180., 180.));
+        }
+        Ok(())
+    }
+}
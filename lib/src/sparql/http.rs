@@ -0,0 +1,225 @@
+//! A minimal blocking HTTP 1.1 client used to call SPARQL `SERVICE`s.
+
+use crate::{Error, Result};
+use std::cmp::min;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_REDIRECTS: usize = 8;
+
+/// Executes an HTTP GET request and returns the response `Content-Type` and body.
+pub fn get(url: &str, accept: &str) -> Result<(Option<String>, Box<dyn BufRead>)> {
+    let mut url = url.to_owned();
+    for _ in 0..MAX_REDIRECTS {
+        match get_once(&url, accept)? {
+            HttpResponse::Ok { content_type, body } => return Ok((content_type, body)),
+            HttpResponse::Redirect { location } => url = location,
+        }
+    }
+    Err(Error::msg(format!(
+        "Too many HTTP redirections for {}",
+        url
+    )))
+}
+
+enum HttpResponse {
+    Ok {
+        content_type: Option<String>,
+        body: Box<dyn BufRead>,
+    },
+    Redirect {
+        location: String,
+    },
+}
+
+fn get_once(url: &str, accept: &str) -> Result<HttpResponse> {
+    let (host, port, authority, path) = split_url(url)?;
+
+    let mut stream = TcpStream::connect((host, port))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: {}\r\nUser-Agent: Oxigraph\r\nConnection: close\r\n\r\n",
+        path, authority, accept
+    )?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split(' ')
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| Error::msg(format!("Invalid HTTP status line: {}", line.trim())))?;
+
+    let mut content_type = None;
+    let mut content_length = None;
+    let mut location = None;
+    let mut chunked = false;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(Error::msg("Unexpected end of the HTTP response headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(colon) = header.find(':') {
+            let name = header[..colon].trim().to_ascii_lowercase();
+            let value = header[colon + 1..].trim();
+            match name.as_str() {
+                "content-type" => content_type = Some(value.to_owned()),
+                "content-length" => {
+                    content_length = Some(value.parse::<u64>().map_err(|_| {
+                        Error::msg(format!("Invalid Content-Length header: {}", value))
+                    })?)
+                }
+                "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+                "location" => location = Some(value.to_owned()),
+                _ => (),
+            }
+        }
+    }
+
+    match status {
+        200..=299 => Ok(HttpResponse::Ok {
+            content_type,
+            body: if chunked {
+                Box::new(BufReader::new(ChunkedReader::new(reader)))
+            } else if let Some(content_length) = content_length {
+                Box::new(reader.take(content_length))
+            } else {
+                Box::new(reader)
+            },
+        }),
+        301 | 302 | 303 | 307 | 308 => Ok(HttpResponse::Redirect {
+            location: location.ok_or_else(|| {
+                Error::msg(format!("HTTP redirection without Location from {}", url))
+            })?,
+        }),
+        _ => Err(Error::msg(format!(
+            "{} returned the HTTP error code {}",
+            url, status
+        ))),
+    }
+}
+
+/// Splits an `http://` URL into host, port, authority and path with query
+fn split_url(url: &str) -> Result<(&str, u16, &str, String)> {
+    let rest = if url.starts_with("http://") {
+        &url["http://".len()..]
+    } else {
+        return Err(Error::msg(format!(
+            "Only http:// URLs are supported by the built-in HTTP client, found {}",
+            url
+        )));
+    };
+    let rest = rest.split('#').next().unwrap_or("");
+    let authority_end = rest
+        .find(|c: char| c == '/' || c == '?')
+        .unwrap_or_else(|| rest.len());
+    let authority = &rest[..authority_end];
+    let path = if rest[authority_end..].starts_with('/') {
+        rest[authority_end..].to_owned()
+    } else {
+        format!("/{}", &rest[authority_end..])
+    };
+    if authority.contains('@') {
+        return Err(Error::msg(format!(
+            "User information in URLs is not supported, found {}",
+            url
+        )));
+    }
+
+    let (host, port) = if authority.starts_with('[') {
+        // IPv6 address
+        let end = authority
+            .find(']')
+            .ok_or_else(|| Error::msg(format!("Invalid URL authority: {}", authority)))?;
+        (&authority[1..end], &authority[end + 1..])
+    } else if let Some(colon) = authority.rfind(':') {
+        (&authority[..colon], &authority[colon..])
+    } else {
+        (authority, "")
+    };
+    let port = if port.is_empty() {
+        80
+    } else if port.starts_with(':') {
+        port[1..]
+            .parse::<u16>()
+            .map_err(|_| Error::msg(format!("Invalid URL port: {}", port)))?
+    } else {
+        return Err(Error::msg(format!("Invalid URL authority: {}", authority)));
+    };
+    Ok((host, port, authority, path))
+}
+
+/// Decodes a body encoded with [chunked transfer coding](https://tools.ietf.org/html/rfc7230#section-4.1)
+struct ChunkedReader<R: BufRead> {
+    inner: R,
+    chunk_remaining: usize,
+    is_end: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            chunk_remaining: 0,
+            is_end: false,
+        }
+    }
+
+    fn read_chunk_size(&mut self) -> io::Result<usize> {
+        let mut line = String::new();
+        self.inner.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or("");
+        usize::from_str_radix(size.trim(), 16).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid HTTP chunk size: {}", line.trim()),
+            )
+        })
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.is_end || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.chunk_remaining == 0 {
+            self.chunk_remaining = self.read_chunk_size()?;
+            if self.chunk_remaining == 0 {
+                // We skip the trailers
+                let mut line = String::new();
+                while self.inner.read_line(&mut line)? > 2 {
+                    line.clear();
+                }
+                self.is_end = true;
+                return Ok(0);
+            }
+        }
+        let max = min(buf.len(), self.chunk_remaining);
+        let read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Unexpected end of an HTTP chunk",
+            ));
+        }
+        self.chunk_remaining -= read;
+        if self.chunk_remaining == 0 {
+            // Chunk data is followed by CRLF
+            let mut crlf = String::new();
+            self.inner.read_line(&mut crlf)?;
+        }
+        Ok(read)
+    }
+}
@@ -1,10 +1,11 @@
-//! Implementation of [SPARQL Query Results XML Format](https://www.w3.org/TR/sparql11-results-json/)
+//! Implementation of [SPARQL Query Results JSON Format](https://www.w3.org/TR/sparql11-results-json/)
 
 use crate::model::*;
 use crate::sparql::model::*;
 use crate::Error;
 use crate::Result;
-use std::io::Write;
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
 
 pub fn write_json_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Result<W> {
     match results {
@@ -43,30 +44,8 @@ pub fn write_json_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Re
                         sink.write_all(b",")?;
                     }
                     write_escaped_json_string(variable.as_str(), &mut sink)?;
-                    match value {
-                        Term::NamedNode(uri) => {
-                            sink.write_all(b":{\"type\":\"uri\",\"value\":")?;
-                            write_escaped_json_string(uri.as_str(), &mut sink)?;
-                            sink.write_all(b"}")?;
-                        }
-                        Term::BlankNode(bnode) => {
-                            sink.write_all(b":{\"type\":\"bnode\",\"value\":")?;
-                            write_escaped_json_string(bnode.as_str(), &mut sink)?;
-                            sink.write_all(b"}")?;
-                        }
-                        Term::Literal(literal) => {
-                            sink.write_all(b":{\"type\":\"literal\",\"value\":")?;
-                            write_escaped_json_string(literal.value(), &mut sink)?;
-                            if let Some(language) = literal.language() {
-                                sink.write_all(b",\"xml:lang\":")?;
-                                write_escaped_json_string(language, &mut sink)?;
-                            } else if !literal.is_plain() {
-                                sink.write_all(b",\"datatype\":")?;
-                                write_escaped_json_string(literal.datatype().as_str(), &mut sink)?;
-                            }
-                            sink.write_all(b"}")?;
-                        }
-                    }
+                    sink.write_all(b":")?;
+                    write_json_term(value, &mut sink)?;
                 }
                 sink.write_all(b"}")?;
             }
@@ -74,13 +53,50 @@ pub fn write_json_results<W: Write>(results: QueryResult<'_>, mut sink: W) -> Re
         }
         QueryResult::Graph(_) => {
             return Err(Error::msg(
-                "Graphs could not be formatted to SPARQL query results XML format",
+                "Graphs could not be formatted to SPARQL query results JSON format",
             ));
         }
     }
     Ok(sink)
 }
 
+fn write_json_term(term: &Term, sink: &mut impl Write) -> Result<()> {
+    match term {
+        Term::NamedNode(uri) => {
+            sink.write_all(b"{\"type\":\"uri\",\"value\":")?;
+            write_escaped_json_string(uri.as_str(), sink)?;
+            sink.write_all(b"}")?;
+        }
+        Term::BlankNode(bnode) => {
+            sink.write_all(b"{\"type\":\"bnode\",\"value\":")?;
+            write_escaped_json_string(bnode.as_str(), sink)?;
+            sink.write_all(b"}")?;
+        }
+        Term::Literal(literal) => {
+            sink.write_all(b"{\"type\":\"literal\",\"value\":")?;
+            write_escaped_json_string(literal.value(), sink)?;
+            if let Some(language) = literal.language() {
+                sink.write_all(b",\"xml:lang\":")?;
+                write_escaped_json_string(language, sink)?;
+            } else if !literal.is_plain() {
+                sink.write_all(b",\"datatype\":")?;
+                write_escaped_json_string(literal.datatype().as_str(), sink)?;
+            }
+            sink.write_all(b"}")?;
+        }
+        Term::Triple(triple) => {
+            sink.write_all(b"{\"type\":\"triple\",\"value\":{\"subject\":")?;
+            write_json_term(&triple.subject.clone().into(), sink)?;
+            sink.write_all(b",\"predicate\":")?;
+            write_json_term(&triple.predicate.clone().into(), sink)?;
+            sink.write_all(b",\"object\":")?;
+            write_json_term(&triple.object, sink)?;
+            sink.write_all(b"}}")?;
+        }
+    }
+    Ok(())
+}
+
 fn write_escaped_json_string(s: &str, sink: &mut impl Write) -> Result<()> {
     sink.write_all(b"\"")?;
     for c in s.chars() {
@@ -100,7 +116,7 @@ fn write_escaped_json_string(s: &str, sink: &mut impl Write) -> Result<()> {
                             let mut result = [b'\\', b'u', 0, 0, 0, 0];
                             for i in (2..6).rev() {
                                 let ch = c % 16;
-                                result[i] = ch + if ch < 10 { b'0' } else { b'A' };
+                                result[i] = if ch < 10 { b'0' + ch } else { b'A' + (ch - 10) };
                                 c /= 16;
                             }
                             sink.write_all(&result)
@@ -115,3 +131,463 @@ fn write_escaped_json_string(s: &str, sink: &mut impl Write) -> Result<()> {
     sink.write_all(b"\"")?;
     Ok(())
 }
+
+pub fn read_json_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>> {
+    let mut reader = JsonReader { reader: source };
+    let mut variables = None;
+    let mut buffered_solutions = None;
+    reader.expect_token(JsonToken::ObjectStart)?;
+    let mut is_first_key = true;
+    while let Some(key) = reader.read_object_key(&mut is_first_key)? {
+        match key.as_str() {
+            "head" => variables = Some(reader.read_head()?),
+            "boolean" => {
+                return match reader.read_token()? {
+                    JsonToken::Boolean(value) => Ok(QueryResult::Boolean(value)),
+                    token => Err(Error::msg(format!(
+                        "Expecting a boolean value, found {:?}",
+                        token
+                    ))),
+                }
+            }
+            "results" => {
+                reader.expect_token(JsonToken::ObjectStart)?;
+                let mut is_first_results_key = true;
+                while let Some(key) = reader.read_object_key(&mut is_first_results_key)? {
+                    if key != "bindings" {
+                        let token = reader.read_token()?;
+                        reader.skip_value(token)?;
+                        continue;
+                    }
+                    reader.expect_token(JsonToken::ArrayStart)?;
+                    if let Some(variables) = variables.take() {
+                        // The head is known, we stream the solutions
+                        let mapping: BTreeMap<_, _> = variables
+                            .iter()
+                            .enumerate()
+                            .map(|(i, variable)| (variable.clone(), i))
+                            .collect();
+                        return Ok(QueryResult::Solutions(QuerySolutionsIterator::new(
+                            variables.into_iter().map(Variable::new).collect(),
+                            Box::new(ResultsIterator {
+                                reader,
+                                mapping,
+                                is_first: true,
+                                is_end: false,
+                            }),
+                        )));
+                    }
+                    // The head is after the solutions, we have to load them in memory
+                    let mut solutions = Vec::default();
+                    let mut is_first_solution = true;
+                    while let Some(token) = reader.read_array_value(&mut is_first_solution)? {
+                        solutions.push(reader.read_solution(token)?);
+                    }
+                    buffered_solutions = Some(solutions);
+                }
+            }
+            _ => {
+                let token = reader.read_token()?;
+                reader.skip_value(token)?;
+            }
+        }
+    }
+
+    match (variables, buffered_solutions) {
+        (Some(variables), Some(solutions)) => {
+            let mapping: BTreeMap<_, _> = variables
+                .iter()
+                .enumerate()
+                .map(|(i, variable)| (variable.clone(), i))
+                .collect();
+            let solutions = solutions
+                .into_iter()
+                .map(|solution| map_solution(solution, &mapping))
+                .collect::<Vec<_>>();
+            Ok(QueryResult::Solutions(QuerySolutionsIterator::new(
+                variables.into_iter().map(Variable::new).collect(),
+                Box::new(solutions.into_iter()),
+            )))
+        }
+        (None, Some(_)) => Err(Error::msg(
+            "The SPARQL results JSON document does not contain a head",
+        )),
+        _ => Err(Error::msg(
+            "The SPARQL results JSON document should contain a results or a boolean key",
+        )),
+    }
+}
+
+struct ResultsIterator<R: BufRead> {
+    reader: JsonReader<R>,
+    mapping: BTreeMap<String, usize>,
+    is_first: bool,
+    is_end: bool,
+}
+
+impl<R: BufRead> Iterator for ResultsIterator<R> {
+    type Item = Result<Vec<Option<Term>>>;
+
+    fn next(&mut self) -> Option<Result<Vec<Option<Term>>>> {
+        let result = self.read_next().transpose();
+        if let Some(Err(_)) = result {
+            // The reader position is unknown after an error
+            self.is_end = true;
+        }
+        result
+    }
+}
+
+impl<R: BufRead> ResultsIterator<R> {
+    fn read_next(&mut self) -> Result<Option<Vec<Option<Term>>>> {
+        if self.is_end {
+            return Ok(None);
+        }
+        if let Some(token) = self.reader.read_array_value(&mut self.is_first)? {
+            let solution = self.reader.read_solution(token)?;
+            Ok(Some(map_solution(solution, &self.mapping)?))
+        } else {
+            self.is_end = true;
+            Ok(None)
+        }
+    }
+}
+
+fn map_solution(
+    solution: Vec<(String, Term)>,
+    mapping: &BTreeMap<String, usize>,
+) -> Result<Vec<Option<Term>>> {
+    let mut values = vec![None; mapping.len()];
+    for (variable, value) in solution {
+        let position = mapping.get(&variable).ok_or_else(|| {
+            Error::msg(format!(
+                "The variable {} is used in a solution but not declared in the head",
+                variable
+            ))
+        })?;
+        values[*position] = Some(value);
+    }
+    Ok(values)
+}
+
+#[derive(Eq, PartialEq, Debug, Clone)]
+enum JsonToken {
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Colon,
+    Comma,
+    String(String),
+    Number(String),
+    Boolean(bool),
+    Null,
+    Eof,
+}
+
+/// A minimal streaming JSON tokenizer
+struct JsonReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> JsonReader<R> {
+    fn read_head(&mut self) -> Result<Vec<String>> {
+        let mut variables = Vec::default();
+        self.expect_token(JsonToken::ObjectStart)?;
+        let mut is_first_key = true;
+        while let Some(key) = self.read_object_key(&mut is_first_key)? {
+            if key == "vars" {
+                self.expect_token(JsonToken::ArrayStart)?;
+                let mut is_first_variable = true;
+                while let Some(token) = self.read_array_value(&mut is_first_variable)? {
+                    if let JsonToken::String(variable) = token {
+                        variables.push(variable);
+                    } else {
+                        return Err(Error::msg(format!(
+                            "Expecting a variable name, found {:?}",
+                            token
+                        )));
+                    }
+                }
+            } else {
+                let token = self.read_token()?;
+                self.skip_value(token)?;
+            }
+        }
+        Ok(variables)
+    }
+
+    fn read_solution(&mut self, token: JsonToken) -> Result<Vec<(String, Term)>> {
+        if token != JsonToken::ObjectStart {
+            return Err(Error::msg(format!(
+                "Expecting a solution object, found {:?}",
+                token
+            )));
+        }
+        let mut solution = Vec::default();
+        let mut is_first_key = true;
+        while let Some(variable) = self.read_object_key(&mut is_first_key)? {
+            let value = self.read_term()?;
+            solution.push((variable, value));
+        }
+        Ok(solution)
+    }
+
+    fn read_term(&mut self) -> Result<Term> {
+        self.expect_token(JsonToken::ObjectStart)?;
+        let mut kind = None;
+        let mut value = None;
+        let mut lang = None;
+        let mut datatype = None;
+        let mut is_first_key = true;
+        while let Some(key) = self.read_object_key(&mut is_first_key)? {
+            match key.as_str() {
+                "type" => kind = Some(self.read_string()?),
+                "value" => value = Some(self.read_string()?),
+                "xml:lang" => lang = Some(self.read_string()?),
+                "datatype" => datatype = Some(self.read_string()?),
+                _ => {
+                    let token = self.read_token()?;
+                    self.skip_value(token)?;
+                }
+            }
+        }
+        let value = value.ok_or_else(|| Error::msg("The term should have a value key"))?;
+        match kind.as_deref() {
+            Some("uri") => Ok(NamedNode::new(value)?.into()),
+            Some("bnode") => Ok(BlankNode::new(value)?.into()),
+            Some("literal") | Some("typed-literal") => Ok(if let Some(datatype) = datatype {
+                Literal::new_typed_literal(value, NamedNode::new(datatype)?)
+            } else if let Some(lang) = lang {
+                Literal::new_language_tagged_literal(value, lang)?
+            } else {
+                Literal::new_simple_literal(value)
+            }
+            .into()),
+            Some(kind) => Err(Error::msg(format!("Unexpected term type: {}", kind))),
+            None => Err(Error::msg("The term should have a type key")),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        match self.read_token()? {
+            JsonToken::String(value) => Ok(value),
+            token => Err(Error::msg(format!("Expecting a string, found {:?}", token))),
+        }
+    }
+
+    /// Reads the next key of an object and the following colon, returns `None` at the end of the object
+    fn read_object_key(&mut self, is_first: &mut bool) -> Result<Option<String>> {
+        let mut token = self.read_token()?;
+        if token == JsonToken::ObjectEnd {
+            return Ok(None);
+        }
+        if *is_first {
+            *is_first = false;
+        } else if token == JsonToken::Comma {
+            token = self.read_token()?;
+        } else {
+            return Err(Error::msg(format!("Expecting a comma, found {:?}", token)));
+        }
+        if let JsonToken::String(key) = token {
+            self.expect_token(JsonToken::Colon)?;
+            Ok(Some(key))
+        } else {
+            Err(Error::msg(format!(
+                "Expecting an object key, found {:?}",
+                token
+            )))
+        }
+    }
+
+    /// Reads the first token of the next value of an array, returns `None` at the end of the array
+    fn read_array_value(&mut self, is_first: &mut bool) -> Result<Option<JsonToken>> {
+        let token = self.read_token()?;
+        if token == JsonToken::ArrayEnd {
+            return Ok(None);
+        }
+        if *is_first {
+            *is_first = false;
+            Ok(Some(token))
+        } else if token == JsonToken::Comma {
+            Ok(Some(self.read_token()?))
+        } else {
+            Err(Error::msg(format!("Expecting a comma, found {:?}", token)))
+        }
+    }
+
+    /// Skips the value starting with the given token
+    ///
+    /// The nested objects and arrays are tracked with an explicit stack to not overflow the call stack on deeply nested values.
+    fn skip_value(&mut self, token: JsonToken) -> Result<()> {
+        // The opened containers: if they are objects and if their first element has not been read yet
+        let mut stack = Vec::new();
+        let mut token = Some(token);
+        loop {
+            match token.take() {
+                Some(JsonToken::ObjectStart) => stack.push((true, true)),
+                Some(JsonToken::ArrayStart) => stack.push((false, true)),
+                Some(JsonToken::String(_))
+                | Some(JsonToken::Number(_))
+                | Some(JsonToken::Boolean(_))
+                | Some(JsonToken::Null)
+                | None => (),
+                Some(token) => {
+                    return Err(Error::msg(format!("Expecting a value, found {:?}", token)))
+                }
+            }
+            let (is_object, is_first) = match stack.last_mut() {
+                Some((is_object, is_first)) => (*is_object, is_first),
+                None => return Ok(()),
+            };
+            token = if is_object {
+                if self.read_object_key(is_first)?.is_some() {
+                    Some(self.read_token()?)
+                } else {
+                    None
+                }
+            } else {
+                self.read_array_value(is_first)?
+            };
+            if token.is_none() {
+                stack.pop();
+            }
+        }
+    }
+
+    fn expect_token(&mut self, expected: JsonToken) -> Result<()> {
+        let token = self.read_token()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(Error::msg(format!(
+                "Expecting {:?}, found {:?}",
+                expected, token
+            )))
+        }
+    }
+
+    fn read_token(&mut self) -> Result<JsonToken> {
+        let mut byte = self.read_byte()?;
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = byte {
+            byte = self.read_byte()?;
+        }
+        Ok(match byte {
+            None => JsonToken::Eof,
+            Some(b'{') => JsonToken::ObjectStart,
+            Some(b'}') => JsonToken::ObjectEnd,
+            Some(b'[') => JsonToken::ArrayStart,
+            Some(b']') => JsonToken::ArrayEnd,
+            Some(b':') => JsonToken::Colon,
+            Some(b',') => JsonToken::Comma,
+            Some(b'"') => JsonToken::String(self.read_string_content()?),
+            Some(b't') => {
+                self.expect_bytes(b"rue")?;
+                JsonToken::Boolean(true)
+            }
+            Some(b'f') => {
+                self.expect_bytes(b"alse")?;
+                JsonToken::Boolean(false)
+            }
+            Some(b'n') => {
+                self.expect_bytes(b"ull")?;
+                JsonToken::Null
+            }
+            Some(c) if c == b'-' || c.is_ascii_digit() => {
+                let mut number = vec![c];
+                while let Some(c) = self.peek_byte()? {
+                    if c.is_ascii_digit()
+                        || c == b'.'
+                        || c == b'e'
+                        || c == b'E'
+                        || c == b'+'
+                        || c == b'-'
+                    {
+                        number.push(c);
+                        self.reader.consume(1);
+                    } else {
+                        break;
+                    }
+                }
+                JsonToken::Number(String::from_utf8(number)?)
+            }
+            Some(c) => {
+                return Err(Error::msg(format!(
+                    "Unexpected character in JSON: {}",
+                    char::from(c)
+                )))
+            }
+        })
+    }
+
+    fn read_string_content(&mut self) -> Result<String> {
+        let mut buffer = Vec::default();
+        loop {
+            match self.read_byte()? {
+                None => return Err(Error::msg("Unexpected end of JSON string")),
+                Some(b'"') => return Ok(String::from_utf8(buffer)?),
+                Some(b'\\') => match self.read_byte()? {
+                    Some(b'"') => buffer.push(b'"'),
+                    Some(b'\\') => buffer.push(b'\\'),
+                    Some(b'/') => buffer.push(b'/'),
+                    Some(b'b') => buffer.push(8),
+                    Some(b'f') => buffer.push(12),
+                    Some(b'n') => buffer.push(b'\n'),
+                    Some(b'r') => buffer.push(b'\r'),
+                    Some(b't') => buffer.push(b'\t'),
+                    Some(b'u') => {
+                        let mut code_point = self.read_hex_code_unit()?;
+                        if (0xD800..0xDC00).contains(&code_point) {
+                            // We read the low surrogate
+                            self.expect_bytes(b"\\u")?;
+                            let low = self.read_hex_code_unit()?;
+                            if !(0xDC00..0xE000).contains(&low) {
+                                return Err(Error::msg("Invalid UTF-16 surrogate pair in JSON"));
+                            }
+                            code_point = 0x10000 + ((code_point - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        let c = char::from_u32(code_point)
+                            .ok_or_else(|| Error::msg("Invalid unicode escape in JSON"))?;
+                        let mut encoded = [0; 4];
+                        buffer.extend_from_slice(c.encode_utf8(&mut encoded).as_bytes());
+                    }
+                    _ => return Err(Error::msg("Invalid escape sequence in JSON")),
+                },
+                Some(c) => buffer.push(c),
+            }
+        }
+    }
+
+    fn read_hex_code_unit(&mut self) -> Result<u32> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .read_byte()?
+                .and_then(|c| char::from(c).to_digit(16))
+                .ok_or_else(|| Error::msg("Invalid unicode escape in JSON"))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn expect_bytes(&mut self, expected: &[u8]) -> Result<()> {
+        for e in expected {
+            if self.read_byte()? != Some(*e) {
+                return Err(Error::msg("Invalid JSON keyword"));
+            }
+        }
+        Ok(())
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn read_byte(&mut self) -> Result<Option<u8>> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+}
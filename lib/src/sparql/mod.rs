@@ -1,28 +1,54 @@
 //! [SPARQL](https://www.w3.org/TR/sparql11-overview/) implementation.
 
-mod algebra;
+pub mod algebra;
+mod collation;
+mod csv_results;
+mod dataset;
+mod describe;
 mod eval;
+mod explain;
+pub(crate) mod geo;
+mod http;
 mod json_results;
 mod model;
 mod parser;
 mod plan;
 mod plan_builder;
+mod property_function;
+mod rdfs;
+mod sort;
+mod update;
 mod xml_results;
 
-use crate::model::NamedNode;
-use crate::sparql::algebra::QueryVariants;
+use crate::model::{NamedNode, Term};
+use crate::sparql::algebra::{DatasetSpec, GraphUpdateOperation, QueryHints, QueryVariants};
+use crate::sparql::collation::Collator;
 use crate::sparql::eval::SimpleEvaluator;
 use crate::sparql::plan::TripleTemplate;
 use crate::sparql::plan::{DatasetView, PlanNode};
 use crate::sparql::plan_builder::PlanBuilder;
-use crate::store::ReadableEncodedStore;
+use crate::sparql::update::SimpleUpdateEvaluator;
+use crate::store::numeric_encoder::EncodedTerm;
+use crate::store::{ReadableEncodedStore, WritableEncodedStore};
 use crate::Error;
+use crate::FileSyntax;
 use crate::Result;
 use oxiri::Iri;
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 pub use crate::sparql::algebra::GraphPattern;
+pub use crate::sparql::collation::Collation;
+pub use crate::sparql::dataset::QueryDataset;
+pub use crate::sparql::describe::DescribeHandler;
+pub use crate::sparql::describe::SymmetricConciseBoundedDescription;
+pub use crate::sparql::explain::QueryExplanation;
+pub use crate::sparql::explain::QueryStats;
 pub use crate::sparql::model::QuerySolution;
 pub use crate::sparql::model::QuerySolutionsIterator;
+pub use crate::sparql::model::QueryVariables;
 #[deprecated(note = "Please directly use QuerySolutionsIterator type instead")]
 pub type BindingsIterator<'a> = QuerySolutionsIterator<'a>;
 pub use crate::sparql::model::QueryResult;
@@ -30,6 +56,9 @@ pub use crate::sparql::model::QueryResultSyntax;
 pub use crate::sparql::model::Variable;
 pub use crate::sparql::parser::Query;
 pub use crate::sparql::parser::SparqlParseError;
+pub use crate::sparql::parser::Update;
+pub use crate::sparql::property_function::ListMember;
+pub use crate::sparql::property_function::PropertyFunction;
 
 /// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/)
 #[deprecated(
@@ -38,7 +67,11 @@ pub use crate::sparql::parser::SparqlParseError;
 pub trait PreparedQuery {}
 
 /// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/)
-pub(crate) struct SimplePreparedQuery<S: ReadableEncodedStore>(SimplePreparedQueryAction<S>);
+pub(crate) struct SimplePreparedQuery<S: ReadableEncodedStore> {
+    action: SimplePreparedQueryAction<S>,
+    bindings: Vec<(Variable, EncodedTerm)>,
+    max_results: Option<usize>,
+}
 
 enum SimplePreparedQueryAction<S: ReadableEncodedStore> {
     Select {
@@ -48,40 +81,110 @@ enum SimplePreparedQueryAction<S: ReadableEncodedStore> {
     },
     Ask {
         plan: PlanNode,
+        variables: Vec<Variable>,
         evaluator: SimpleEvaluator<S>,
     },
     Construct {
         plan: PlanNode,
+        variables: Vec<Variable>,
         construct: Vec<TripleTemplate>,
         evaluator: SimpleEvaluator<S>,
     },
     Describe {
         plan: PlanNode,
+        variables: Vec<Variable>,
+        describe_handler: Option<Rc<dyn DescribeHandler>>,
         evaluator: SimpleEvaluator<S>,
     },
 }
 
 impl<S: ReadableEncodedStore> SimplePreparedQuery<S> {
     pub(crate) fn new(store: S, query: &str, options: QueryOptions<'_>) -> Result<Self> {
-        let dataset = DatasetView::new(store, options.default_graph_as_union);
-        Ok(Self(match Query::parse(query, options.base_iri)?.0 {
+        let query = Query::parse_with_custom_aggregates(
+            query,
+            options.base_iri,
+            options.custom_aggregates.keys().cloned().collect(),
+        )?;
+        Self::new_from_query(store, query, options)
+    }
+
+    pub(crate) fn new_from_query(
+        store: S,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<Self> {
+        let collator = Collator::new(&options.collation)?;
+        let hints = query.hints;
+        let mut query = query.query;
+        for rewriter in &options.rewriters {
+            query = rewriter.rewrite(query)?;
+        }
+        if options.rdfs_entailment {
+            query =
+                rdfs::rewrite_query(query, &options.property_functions.keys().cloned().collect());
+        }
+        // The dataset given in the options overrides the one of the query
+        let dataset = DatasetView::new(
+            store,
+            options.default_graph_as_union,
+            if options.dataset.is_empty() {
+                query.dataset()
+            } else {
+                &options.dataset
+            },
+        )?;
+        let property_functions = options.property_functions.keys().cloned().collect();
+        let action = match query {
             QueryVariants::Select {
                 algebra, base_iri, ..
             } => {
-                let (plan, variables) = PlanBuilder::build(dataset.encoder(), &algebra)?;
+                let (plan, variables) = PlanBuilder::build(
+                    dataset.encoder(),
+                    &dataset,
+                    &property_functions,
+                    &hints,
+                    &algebra,
+                )?;
                 SimplePreparedQueryAction::Select {
                     plan,
                     variables,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri, options.service_handler),
+                    evaluator: SimpleEvaluator::new(
+                        dataset,
+                        base_iri,
+                        options.service_handler,
+                        Rc::new(options.custom_aggregates),
+                        Rc::new(options.property_functions),
+                        collator,
+                        options.sort_memory_limit,
+                        options.profiling,
+                        options.stats,
+                    ),
                 }
             }
             QueryVariants::Ask {
                 algebra, base_iri, ..
             } => {
-                let (plan, _) = PlanBuilder::build(dataset.encoder(), &algebra)?;
+                let (plan, variables) = PlanBuilder::build(
+                    dataset.encoder(),
+                    &dataset,
+                    &property_functions,
+                    &hints,
+                    &algebra,
+                )?;
                 SimplePreparedQueryAction::Ask {
                     plan,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri, options.service_handler),
+                    variables,
+                    evaluator: SimpleEvaluator::new(
+                        dataset,
+                        base_iri,
+                        options.service_handler,
+                        Rc::new(options.custom_aggregates),
+                        Rc::new(options.property_functions),
+                        collator,
+                        options.sort_memory_limit,
+                        options.profiling,
+                        options.stats,
+                    ),
                 }
             }
             QueryVariants::Construct {
@@ -90,27 +193,63 @@ impl<S: ReadableEncodedStore> SimplePreparedQuery<S> {
                 base_iri,
                 ..
             } => {
-                let (plan, variables) = PlanBuilder::build(dataset.encoder(), &algebra)?;
+                let (plan, variables) = PlanBuilder::build(
+                    dataset.encoder(),
+                    &dataset,
+                    &property_functions,
+                    &hints,
+                    &algebra,
+                )?;
                 SimplePreparedQueryAction::Construct {
                     plan,
                     construct: PlanBuilder::build_graph_template(
                         dataset.encoder(),
                         &construct,
-                        variables,
+                        variables.clone(),
                     )?,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri, options.service_handler),
+                    variables,
+                    evaluator: SimpleEvaluator::new(
+                        dataset,
+                        base_iri,
+                        options.service_handler,
+                        Rc::new(options.custom_aggregates),
+                        Rc::new(options.property_functions),
+                        collator,
+                        options.sort_memory_limit,
+                        options.profiling,
+                        options.stats,
+                    ),
                 }
             }
             QueryVariants::Describe {
                 algebra, base_iri, ..
             } => {
-                let (plan, _) = PlanBuilder::build(dataset.encoder(), &algebra)?;
+                let (plan, variables) = PlanBuilder::build(
+                    dataset.encoder(),
+                    &dataset,
+                    &property_functions,
+                    &hints,
+                    &algebra,
+                )?;
                 SimplePreparedQueryAction::Describe {
                     plan,
-                    evaluator: SimpleEvaluator::new(dataset, base_iri, options.service_handler),
+                    variables,
+                    describe_handler: options.describe_handler,
+                    evaluator: SimpleEvaluator::new(
+                        dataset,
+                        base_iri,
+                        options.service_handler,
+                        Rc::new(options.custom_aggregates),
+                        Rc::new(options.property_functions),
+                        collator,
+                        options.sort_memory_limit,
+                        options.profiling,
+                        options.stats,
+                    ),
                 }
             }
-        }))
+        };
+        Self::with_options(action, options.variables, options.max_results)
     }
 
     /// Builds `SimplePreparedQuery` from an existing `GraphPattern`. This is used to support federated queries via `SERVICE` clauses
@@ -119,39 +258,253 @@ impl<S: ReadableEncodedStore> SimplePreparedQuery<S> {
         pattern: &GraphPattern,
         options: QueryOptions<'_>,
     ) -> Result<Self> {
-        let dataset = DatasetView::new(store, options.default_graph_as_union);
-        let (plan, variables) = PlanBuilder::build(dataset.encoder(), pattern)?;
+        let collator = Collator::new(&options.collation)?;
+        let dataset = DatasetView::new(store, options.default_graph_as_union, &options.dataset)?;
+        let (plan, variables) = PlanBuilder::build(
+            dataset.encoder(),
+            &dataset,
+            &options.property_functions.keys().cloned().collect(),
+            &QueryHints::default(),
+            pattern,
+        )?;
         let base_iri = if let Some(base_iri) = options.base_iri {
             Some(Iri::parse(base_iri.to_string())?)
         } else {
             None
         };
-        Ok(Self(SimplePreparedQueryAction::Select {
-            plan,
-            variables,
-            evaluator: SimpleEvaluator::new(dataset, base_iri, options.service_handler),
-        }))
+        Self::with_options(
+            SimplePreparedQueryAction::Select {
+                plan,
+                variables,
+                evaluator: SimpleEvaluator::new(
+                    dataset,
+                    base_iri,
+                    options.service_handler,
+                    Rc::new(options.custom_aggregates),
+                    Rc::new(options.property_functions),
+                    collator,
+                    options.sort_memory_limit,
+                    options.profiling,
+                    options.stats,
+                ),
+            },
+            options.variables,
+            options.max_results,
+        )
+    }
+
+    fn with_options(
+        action: SimplePreparedQueryAction<S>,
+        variables: QueryVariables,
+        max_results: Option<usize>,
+    ) -> Result<Self> {
+        let bindings = encode_initial_bindings(&action, &variables.into())?;
+        Ok(Self {
+            action,
+            bindings,
+            max_results,
+        })
     }
 
     /// Evaluates the query and returns its results
     pub fn exec(&self) -> Result<QueryResult<'_>> {
-        match &self.0 {
+        self.exec_encoded(&self.bindings)
+    }
+
+    /// Evaluates the query with some variables already bound to the values of `bindings`
+    ///
+    /// Fails if one of the variables is not used in the query.
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        let mut all_bindings = self.bindings.clone();
+        all_bindings.extend(encode_initial_bindings(&self.action, bindings)?);
+        self.exec_encoded(&all_bindings)
+    }
+
+    fn exec_encoded(&self, bindings: &[(Variable, EncodedTerm)]) -> Result<QueryResult<'_>> {
+        let results = match &self.action {
             SimplePreparedQueryAction::Select {
                 plan,
                 variables,
                 evaluator,
-            } => evaluator.evaluate_select_plan(plan, variables),
-            SimplePreparedQueryAction::Ask { plan, evaluator } => evaluator.evaluate_ask_plan(plan),
+            } => evaluator.evaluate_select_plan(plan, variables, bindings),
+            SimplePreparedQueryAction::Ask {
+                plan,
+                variables,
+                evaluator,
+            } => evaluator.evaluate_ask_plan(plan, variables, bindings),
             SimplePreparedQueryAction::Construct {
                 plan,
+                variables,
                 construct,
                 evaluator,
-            } => evaluator.evaluate_construct_plan(plan, construct),
-            SimplePreparedQueryAction::Describe { plan, evaluator } => {
-                evaluator.evaluate_describe_plan(plan)
+            } => evaluator.evaluate_construct_plan(plan, variables, bindings, construct),
+            SimplePreparedQueryAction::Describe {
+                plan,
+                variables,
+                describe_handler,
+                evaluator,
+            } => evaluator.evaluate_describe_plan(
+                plan,
+                variables,
+                bindings,
+                describe_handler.as_deref(),
+            ),
+        }?;
+        Ok(if let Some(max_results) = self.max_results {
+            match results {
+                QueryResult::Solutions(solutions) => {
+                    let (variables, iter) = solutions.destruct();
+                    QueryResult::Solutions(QuerySolutionsIterator::new(
+                        variables,
+                        Box::new(fail_after(iter, max_results)),
+                    ))
+                }
+                QueryResult::Graph(triples) => {
+                    QueryResult::Graph(Box::new(fail_after(triples, max_results)))
+                }
+                QueryResult::Boolean(value) => QueryResult::Boolean(value),
             }
+        } else {
+            results
+        })
+    }
+
+    /// Returns a description of the query evaluation plan
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        match &self.action {
+            SimplePreparedQueryAction::Select {
+                plan,
+                variables,
+                evaluator,
+            }
+            | SimplePreparedQueryAction::Ask {
+                plan,
+                variables,
+                evaluator,
+            }
+            | SimplePreparedQueryAction::Construct {
+                plan,
+                variables,
+                evaluator,
+                ..
+            }
+            | SimplePreparedQueryAction::Describe {
+                plan,
+                variables,
+                evaluator,
+                ..
+            } => evaluator.explain_plan(plan, variables),
         }
     }
+
+    /// Returns the metrics of the last execution of the query
+    pub fn stats(&self) -> QueryStats {
+        match &self.action {
+            SimplePreparedQueryAction::Select { evaluator, .. }
+            | SimplePreparedQueryAction::Ask { evaluator, .. }
+            | SimplePreparedQueryAction::Construct { evaluator, .. }
+            | SimplePreparedQueryAction::Describe { evaluator, .. } => evaluator.stats(),
+        }
+    }
+}
+
+/// Encodes the initial values of the query variables after checking that they are all used in the query
+fn encode_initial_bindings<S: ReadableEncodedStore>(
+    action: &SimplePreparedQueryAction<S>,
+    bindings: &QuerySolution,
+) -> Result<Vec<(Variable, EncodedTerm)>> {
+    match action {
+        SimplePreparedQueryAction::Select {
+            plan,
+            variables,
+            evaluator,
+        }
+        | SimplePreparedQueryAction::Ask {
+            plan,
+            variables,
+            evaluator,
+        }
+        | SimplePreparedQueryAction::Construct {
+            plan,
+            variables,
+            evaluator,
+            ..
+        }
+        | SimplePreparedQueryAction::Describe {
+            plan,
+            variables,
+            evaluator,
+            ..
+        } => {
+            let query_variables = bindable_variables(plan, variables);
+            for (variable, _) in bindings.iter() {
+                if !query_variables.contains(variable) {
+                    return Err(Error::msg(format!(
+                        "The variable {} is not used in the query",
+                        variable
+                    )));
+                }
+            }
+            evaluator.encode_initial_bindings(bindings)
+        }
+    }
+}
+
+/// Returns the variables of the query WHERE clause, i.e. the ones that could be given initial values
+fn bindable_variables<'a>(plan: &'a PlanNode, variables: &'a [Variable]) -> &'a [Variable] {
+    match plan {
+        PlanNode::HashDeduplicate { child }
+        | PlanNode::Skip { child, .. }
+        | PlanNode::Limit { child, .. } => bindable_variables(child, variables),
+        PlanNode::Project {
+            child_variables, ..
+        } => child_variables,
+        _ => variables,
+    }
+}
+
+/// A prepared [SPARQL update](https://www.w3.org/TR/sparql11-update/)
+pub(crate) struct SimplePreparedUpdate {
+    update: Update,
+    default_graph_as_union: bool,
+    service_handler: Rc<dyn ServiceHandler>,
+    load_directory: Option<PathBuf>,
+}
+
+impl SimplePreparedUpdate {
+    pub(crate) fn new(update: &str, options: QueryOptions<'_>) -> Result<Self> {
+        Ok(Self {
+            update: Update::parse(update, options.base_iri)?,
+            default_graph_as_union: options.default_graph_as_union,
+            service_handler: options.service_handler,
+            load_directory: options.load_directory,
+        })
+    }
+
+    /// The operations of the update, in the order they should be executed
+    pub(crate) fn operations(&self) -> &[GraphUpdateOperation] {
+        &self.update.operations
+    }
+
+    /// Executes one of the update operations
+    ///
+    /// The `WHERE` clauses are evaluated against `read` and the changes are written into `write`.
+    pub(crate) fn exec_operation<R: ReadableEncodedStore + Clone, W: WritableEncodedStore>(
+        &self,
+        operation: &GraphUpdateOperation,
+        read: R,
+        write: &mut W,
+    ) -> Result<()> {
+        SimpleUpdateEvaluator::new(
+            read,
+            write,
+            self.update.base_iri.clone(),
+            self.default_graph_as_union,
+            self.service_handler.clone(),
+            self.load_directory.as_deref(),
+        )
+        .eval(operation)
+    }
 }
 
 /// Handler for SPARQL SERVICEs.
@@ -186,15 +539,142 @@ impl ServiceHandler for EmptyServiceHandler {
         _: &NamedNode,
         _: &'a GraphPattern,
     ) -> Result<QuerySolutionsIterator<'a>> {
-        Err(Error::msg("The SERVICE feature is not implemented"))
+        Err(Error::msg("The SERVICE feature is disabled"))
+    }
+}
+
+/// Evaluates SERVICEs by sending a SPARQL query to the remote endpoint using the
+/// [SPARQL 1.1 Protocol](https://www.w3.org/TR/sparql11-protocol/).
+///
+/// Only `http://` endpoints returning the [SPARQL Query Results XML Format](http://www.w3.org/TR/rdf-sparql-XMLres/) are supported.
+struct SimpleServiceHandler;
+
+impl ServiceHandler for SimpleServiceHandler {
+    fn handle<'a>(
+        &'a self,
+        service_name: &NamedNode,
+        graph_pattern: &'a GraphPattern,
+    ) -> Result<QuerySolutionsIterator<'a>> {
+        let query = Query::from(QueryVariants::Select {
+            dataset: DatasetSpec::default(),
+            algebra: graph_pattern.clone(),
+            base_iri: None,
+        });
+        let url = format!(
+            "{}{}query={}",
+            service_name.as_str(),
+            if service_name.as_str().contains('?') {
+                '&'
+            } else {
+                '?'
+            },
+            percent_encode(&query.to_string())
+        );
+        let (content_type, body) = http::get(
+            &url,
+            &format!(
+                "{}, {}",
+                QueryResultSyntax::Xml.media_type(),
+                QueryResultSyntax::Json.media_type()
+            ),
+        )?;
+        let syntax = if let Some(content_type) = content_type {
+            match QueryResultSyntax::from_mime_type(&content_type) {
+                Some(QueryResultSyntax::Xml) => QueryResultSyntax::Xml,
+                Some(QueryResultSyntax::Json) => QueryResultSyntax::Json,
+                _ => {
+                    return Err(Error::msg(format!(
+                        "The service {} returned an unsupported content type: {}",
+                        service_name, content_type
+                    )))
+                }
+            }
+        } else {
+            QueryResultSyntax::Xml
+        };
+        match QueryResult::read(body, syntax)? {
+            QueryResult::Solutions(solutions) => Ok(solutions),
+            _ => Err(Error::msg(format!(
+                "The service {} did not return solutions",
+                service_name
+            ))),
+        }
     }
 }
 
-/// Options for SPARQL query parsing and evaluation like the query base IRI
+fn percent_encode(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                result.push(char::from(b))
+            }
+            b => result.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    result
+}
+
+/// Rewrites the queries between their parsing and their evaluation.
+///
+/// Might be used to inject access control filters or to expand shortcut predicates.
+pub trait QueryRewriter {
+    /// Returns the query to evaluate instead of `query`.
+    fn rewrite(&self, query: QueryVariants) -> Result<QueryVariants>;
+}
+
+impl<F: Fn(QueryVariants) -> Result<QueryVariants>> QueryRewriter for F {
+    fn rewrite(&self, query: QueryVariants) -> Result<QueryVariants> {
+        self(query)
+    }
+}
+
+/// A custom aggregate function registered with `QueryOptions::with_custom_aggregate`
+///
+/// The types of the aggregation states are erased in order to store aggregates with different states together.
+pub(crate) struct CustomAggregate {
+    pub(crate) init: Box<dyn Fn() -> Box<dyn Any>>,
+    pub(crate) accumulate: Box<dyn Fn(&mut dyn Any, Term)>,
+    pub(crate) finish: Box<dyn Fn(Box<dyn Any>) -> Option<Term>>,
+}
+
+/// Returns the elements of `iter` and an error if it has more than `max_results` elements
+fn fail_after<T>(
+    iter: impl Iterator<Item = Result<T>>,
+    max_results: usize,
+) -> impl Iterator<Item = Result<T>> {
+    iter.enumerate()
+        .map(move |(i, result)| {
+            if i < max_results {
+                result
+            } else {
+                Err(Error::msg(format!(
+                    "The query returns more than {} results",
+                    max_results
+                )))
+            }
+        })
+        .take(max_results.saturating_add(1))
+}
+
+/// Options for SPARQL query and update parsing and evaluation like the query base IRI
 pub struct QueryOptions<'a> {
     pub(crate) base_iri: Option<&'a str>,
     pub(crate) default_graph_as_union: bool,
-    pub(crate) service_handler: Box<dyn ServiceHandler>,
+    pub(crate) dataset: DatasetSpec,
+    pub(crate) max_results: Option<usize>,
+    pub(crate) profiling: bool,
+    pub(crate) stats: bool,
+    pub(crate) rdfs_entailment: bool,
+    pub(crate) rewriters: Vec<Rc<dyn QueryRewriter>>,
+    pub(crate) service_handler: Rc<dyn ServiceHandler>,
+    pub(crate) describe_handler: Option<Rc<dyn DescribeHandler>>,
+    pub(crate) custom_aggregates: HashMap<NamedNode, Rc<CustomAggregate>>,
+    pub(crate) property_functions: HashMap<NamedNode, Rc<dyn PropertyFunction>>,
+    pub(crate) variables: QueryVariables,
+    pub(crate) collation: Collation,
+    pub(crate) sort_memory_limit: Option<usize>,
+    pub(crate) load_directory: Option<PathBuf>,
 }
 
 impl<'a> Default for QueryOptions<'a> {
@@ -202,7 +682,20 @@ impl<'a> Default for QueryOptions<'a> {
         Self {
             base_iri: None,
             default_graph_as_union: false,
-            service_handler: Box::new(EmptyServiceHandler),
+            dataset: DatasetSpec::default(),
+            max_results: None,
+            profiling: false,
+            stats: false,
+            rdfs_entailment: false,
+            rewriters: Vec::new(),
+            service_handler: Rc::new(EmptyServiceHandler),
+            describe_handler: None,
+            custom_aggregates: HashMap::default(),
+            property_functions: HashMap::default(),
+            variables: QueryVariables::default(),
+            collation: Collation::default(),
+            sort_memory_limit: None,
+            load_directory: None,
         }
     }
 }
@@ -215,13 +708,489 @@ impl<'a> QueryOptions<'a> {
     }
 
     /// Consider the union of all graphs in the store as the default graph
+    ///
+    /// The triples present in multiple graphs are only returned once.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(NamedNode::new("http://example.com/g1")?.into())));
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(NamedNode::new("http://example.com/g2")?.into())));
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "SELECT ?s WHERE { ?s ?p ?o }",
+    ///     QueryOptions::default().with_default_graph_as_union()
+    /// )?;
+    /// let mut count = 0;
+    /// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+    ///     for solution in solutions {
+    ///         assert_eq!(solution?.get("s"), Some(&ex.clone().into()));
+    ///         count += 1;
+    ///     }
+    /// }
+    /// assert_eq!(count, 1);
+    /// # oxigraph::Result::Ok(())
+    /// ```
     pub const fn with_default_graph_as_union(mut self) -> Self {
         self.default_graph_as_union = true;
         self
     }
 
+    /// Adds a graph to the default graph of the query dataset.
+    ///
+    /// If this method or `with_named_graph` is used, the FROM and FROM NAMED clauses of the query are ignored.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let graph = NamedNode::new("http://example.com/graph")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(graph.clone().into())));
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "ASK { ?s ?p ?o }",
+    ///     QueryOptions::default().with_default_graph(graph)
+    /// )?;
+    /// if let QueryResult::Boolean(result) = prepared_query.exec()? {
+    ///     assert!(result);
+    /// }
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_default_graph(mut self, default_graph: impl Into<NamedNode>) -> Self {
+        self.dataset.default.push(default_graph.into());
+        self
+    }
+
+    /// Adds a named graph to the query dataset.
+    ///
+    /// If this method or `with_default_graph` is used, the FROM and FROM NAMED clauses of the query are ignored.
+    pub fn with_named_graph(mut self, named_graph: impl Into<NamedNode>) -> Self {
+        self.dataset.named.push(named_graph.into());
+        self
+    }
+
+    /// Limits the number of solutions or triples a query could return.
+    ///
+    /// The results iterator returns an error instead of the first result above the limit.
+    /// Useful to protect shared deployments from queries returning huge result sets.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// for i in 0..3 {
+    ///     let ex = NamedNode::new(format!("http://example.com/{}", i))?;
+    ///     store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+    /// }
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "SELECT ?s WHERE { ?s ?p ?o }",
+    ///     QueryOptions::default().with_max_results(2)
+    /// )?;
+    /// let mut results = Vec::new();
+    /// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+    ///     results = solutions.collect();
+    /// }
+    /// assert_eq!(results.len(), 3);
+    /// assert!(results[0].is_ok() && results[1].is_ok() && results[2].is_err());
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Records the number of rows returned by each operation of the query plan and the time spent in them.
+    ///
+    /// The records are returned by the `explain` method of the prepared query after `exec` has been called.
+    /// It slows down the evaluation a bit.
+    pub fn with_profiling(mut self) -> Self {
+        self.profiling = true;
+        self
+    }
+
+    /// Records the metrics of each execution of the query like its duration or the number of quad pattern lookups.
+    ///
+    /// They are returned by the `stats` method of the prepared query.
+    /// Without this option, the returned metrics are all zero.
+    /// It slows down the evaluation a bit.
+    pub fn with_stats(mut self) -> Self {
+        self.stats = true;
+        self
+    }
+
+    /// Evaluates the queries with the [RDFS entailment regime](https://www.w3.org/TR/sparql11-entailment/#RDFSEntailmentRegime).
+    ///
+    /// The triple patterns are rewritten to also match the triples entailed by the `rdfs:subClassOf`, `rdfs:subPropertyOf`,
+    /// `rdfs:domain` and `rdfs:range` statements of the queried graphs.
+    /// The patterns with a variable as predicate and the property paths are evaluated without entailment.
+    /// The rewriting is done after the one of the `QueryRewriter`s.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{GraphSyntax, MemoryStore};
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// store.load_graph(
+    ///     "@prefix ex: <http://example.com/> .
+    ///     @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+    ///     ex:Cat rdfs:subClassOf ex:Animal .
+    ///     ex:tom a ex:Cat .".as_bytes(),
+    ///     GraphSyntax::Turtle,
+    ///     &GraphName::DefaultGraph,
+    ///     None,
+    /// )?;
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "SELECT ?s WHERE { ?s a <http://example.com/Animal> }",
+    ///     QueryOptions::default().with_rdfs_entailment(),
+    /// )?;
+    /// if let QueryResult::Solutions(mut solutions) = prepared_query.exec()? {
+    ///     assert_eq!(solutions.next().unwrap()?.get("s"), Some(&NamedNode::new("http://example.com/tom")?.into()));
+    /// }
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_rdfs_entailment(mut self) -> Self {
+        self.rdfs_entailment = true;
+        self
+    }
+
+    /// Adds a `QueryRewriter` applied to the queries before their evaluation.
+    ///
+    /// The rewriters are applied in the order they have been added.
+    /// They are not applied to the SPARQL updates.
+    ///
+    /// Usage example restricting the queries to a public graph:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::sparql::algebra::*;
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let public = NamedNode::new("http://example.com/public")?;
+    /// let secret = NamedNode::new("http://example.com/secret")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), public.clone(), Some(public.clone().into())));
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), secret.clone(), Some(secret.into())));
+    ///
+    /// let options = QueryOptions::default().with_query_rewriter(move |query| match query {
+    ///     QueryVariants::Select { algebra, base_iri, .. } => Ok(QueryVariants::Select {
+    ///         dataset: DatasetSpec::new_with_default(public.clone()),
+    ///         algebra,
+    ///         base_iri,
+    ///     }),
+    ///     _ => Err(oxigraph::Error::msg("Only SELECT queries are allowed")),
+    /// });
+    /// let prepared_query = store.prepare_query("SELECT ?o WHERE { ?s ?p ?o }", options)?;
+    /// let mut results = Vec::new();
+    /// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+    ///     for solution in solutions {
+    ///         results.push(solution?.get("o").cloned());
+    ///     }
+    /// }
+    /// assert_eq!(results, vec![Some(NamedNode::new("http://example.com/public")?.into())]);
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_query_rewriter(mut self, rewriter: impl QueryRewriter + 'static) -> Self {
+        self.rewriters.push(Rc::new(rewriter));
+        self
+    }
+
+    /// Use a given `ServiceHandler` to execute SPARQL SERVICE calls.
+    ///
+    /// By default, SERVICE calls are disabled: queries containing a non silent SERVICE clause fail.
     pub fn with_service_handler(mut self, service_handler: impl ServiceHandler + 'static) -> Self {
-        self.service_handler = Box::new(service_handler);
+        self.service_handler = Rc::new(service_handler);
+        self
+    }
+
+    /// Use a given `DescribeHandler` to build the descriptions returned by DESCRIBE queries.
+    ///
+    /// By default, the [Concise Bounded Description](https://www.w3.org/Submission/CBD/) of the resources in the default graph is returned.
+    ///
+    /// Usage example returning the content of the named graph with the same name as the described resource:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{QueryDataset, QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from(1), Some(ex.clone().into())));
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from(2), None));
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "DESCRIBE <http://example.com>",
+    ///     QueryOptions::default().with_describe_handler(|resource: &Term, dataset: &dyn QueryDataset| -> oxigraph::Result<Vec<Triple>> {
+    ///         if let Term::NamedNode(graph_name) = resource {
+    ///             dataset
+    ///                 .quads_for_pattern(None, None, None, Some(&graph_name.clone().into()))
+    ///                 .map(|quad| quad.map(Triple::from))
+    ///                 .collect()
+    ///         } else {
+    ///             Ok(Vec::new())
+    ///         }
+    ///     })
+    /// )?;
+    /// if let QueryResult::Graph(triples) = prepared_query.exec()? {
+    ///     assert_eq!(triples.collect::<oxigraph::Result<Vec<_>>>()?, vec![Triple::new(ex.clone(), ex, Literal::from(1))]);
+    /// }
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_describe_handler(
+        mut self,
+        describe_handler: impl DescribeHandler + 'static,
+    ) -> Self {
+        self.describe_handler = Some(Rc::new(describe_handler));
+        self
+    }
+
+    /// Registers a custom aggregate function usable in the queries with the `<name>(expression)` and `<name>(DISTINCT expression)` syntaxes.
+    ///
+    /// For each group, the aggregation state is created by `init`, then `accumulate` is called with each value of the expression
+    /// and `finish` returns the aggregate value, if any.
+    /// The values for which the evaluation of the expression fails are ignored.
+    ///
+    /// Usage example computing a median:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    /// use std::str::FromStr;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// for i in &[1, 5, 3] {
+    ///     store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from(*i), None));
+    /// }
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "SELECT (<http://example.com/median>(?o) AS ?median) WHERE { ?s ?p ?o }",
+    ///     QueryOptions::default().with_custom_aggregate(
+    ///         NamedNode::new("http://example.com/median")?,
+    ///         Vec::new,
+    ///         |values: &mut Vec<f64>, value| {
+    ///             if let Term::Literal(value) = value {
+    ///                 if let Ok(value) = f64::from_str(value.value()) {
+    ///                     values.push(value);
+    ///                 }
+    ///             }
+    ///         },
+    ///         |mut values| {
+    ///             values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    ///             values.get(values.len() / 2).map(|median| Literal::from(*median).into())
+    ///         },
+    ///     ),
+    /// )?;
+    /// if let QueryResult::Solutions(mut solutions) = prepared_query.exec()? {
+    ///     assert_eq!(solutions.next().unwrap()?.get("median"), Some(&Literal::from(3.).into()));
+    /// }
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_custom_aggregate<T: 'static>(
+        mut self,
+        name: impl Into<NamedNode>,
+        init: impl Fn() -> T + 'static,
+        accumulate: impl Fn(&mut T, Term) + 'static,
+        finish: impl Fn(T) -> Option<Term> + 'static,
+    ) -> Self {
+        self.custom_aggregates.insert(
+            name.into(),
+            Rc::new(CustomAggregate {
+                init: Box::new(move || {
+                    let state: Box<dyn Any> = Box::new(init());
+                    state
+                }),
+                accumulate: Box::new(move |state: &mut dyn Any, value: Term| {
+                    if let Some(state) = state.downcast_mut::<T>() {
+                        accumulate(state, value)
+                    }
+                }),
+                finish: Box::new(move |state: Box<dyn Any>| {
+                    state.downcast::<T>().ok().and_then(|state| finish(*state))
+                }),
+            }),
+        );
+        self
+    }
+
+    /// Registers a `PropertyFunction` evaluating the triple patterns with the predicate `name`.
+    ///
+    /// Usage example with a function returning the length of string literals:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{QueryDataset, QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// let prepared_query = store.prepare_query(
+    ///     "SELECT ?length WHERE { \"foo\" <http://example.com/length> ?length }",
+    ///     QueryOptions::default().with_property_function(
+    ///         NamedNode::new("http://example.com/length")?,
+    ///         |subject: Option<&Term>, _: Option<&Term>, _: &GraphName, _: &dyn QueryDataset| -> oxigraph::Result<Vec<(Term, Term)>> {
+    ///             Ok(match subject {
+    ///                 Some(Term::Literal(literal)) => vec![(
+    ///                     literal.clone().into(),
+    ///                     Literal::from(literal.value().chars().count() as i64).into(),
+    ///                 )],
+    ///                 _ => Vec::new(),
+    ///             })
+    ///         },
+    ///     ),
+    /// )?;
+    /// if let QueryResult::Solutions(mut solutions) = prepared_query.exec()? {
+    ///     assert_eq!(solutions.next().unwrap()?.get("length"), Some(&Literal::from(3).into()));
+    /// }
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_property_function(
+        mut self,
+        name: impl Into<NamedNode>,
+        function: impl PropertyFunction + 'static,
+    ) -> Self {
+        self.property_functions
+            .insert(name.into(), Rc::new(function));
+        self
+    }
+
+    /// Sets initial values of some query variables.
+    ///
+    /// The values are given to the evaluator as RDF terms and not inserted into the query string,
+    /// making it safe to use them for values provided by untrusted users.
+    /// The query preparation fails if one of the variables is not used in the query.
+    pub fn with_variables(mut self, variables: QueryVariables) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// Sets the collation used to order string literals in ORDER BY clauses.
+    ///
+    /// By default, strings are compared code point by code point.
+    /// The query preparation fails if the collation requires a feature that is not activated.
+    ///
+    /// Usage example with the `"collation"` feature:
+    /// ```
+    /// # #[cfg(feature = "collation")] {
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{Collation, QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// for name in &["Zoé", "zoe", "Zorro"] {
+    ///     store.insert(Quad::new(ex.clone(), ex.clone(), Literal::new_simple_literal(*name), None));
+    /// }
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "SELECT ?o WHERE { ?s ?p ?o } ORDER BY ?o",
+    ///     QueryOptions::default().with_collation(Collation::unicode("und"))
+    /// )?;
+    /// let mut names = Vec::new();
+    /// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+    ///     for solution in solutions {
+    ///         names.push(solution?.get("o").unwrap().to_string());
+    ///     }
+    /// }
+    /// assert_eq!(names, vec!["\"zoe\"", "\"Zoé\"", "\"Zorro\""]);
+    /// # }
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    /// Limits the number of solutions kept in memory while evaluating ORDER BY clauses.
+    ///
+    /// Above this limit, the solutions are sorted by chunks written to temporary files in `std::env::temp_dir()` and merged afterwards.
+    /// It allows sorting result sets that do not fit in memory.
+    /// By default, all the solutions are sorted in memory.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// for i in 0..5 {
+    ///     let ex = NamedNode::new("http://example.com")?;
+    ///     store.insert(Quad::new(ex.clone(), ex, Literal::from(i), None));
+    /// }
+    ///
+    /// let prepared_query = store.prepare_query(
+    ///     "SELECT ?o WHERE { ?s ?p ?o } ORDER BY DESC(?o)",
+    ///     QueryOptions::default().with_sort_memory_limit(2)
+    /// )?;
+    /// let mut results = Vec::new();
+    /// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+    ///     for solution in solutions {
+    ///         results.push(solution?.get("o").cloned());
+    ///     }
+    /// }
+    /// assert_eq!(results, (0..5).rev().map(|i| Some(Literal::from(i).into())).collect::<Vec<_>>());
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_sort_memory_limit(mut self, max_solutions: usize) -> Self {
+        self.sort_memory_limit = Some(max_solutions);
+        self
+    }
+
+    /// Allows the SPARQL UPDATE `LOAD` operation to read the files in the given directory and its subdirectories.
+    ///
+    /// `LOAD` is disabled by default. Only `file:` IRIs are supported.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::MemoryStore;
+    /// use oxigraph::sparql::QueryOptions;
+    /// use std::env::temp_dir;
+    /// use std::fs::{remove_file, write};
+    ///
+    /// let file = temp_dir().join("oxigraph_load_example.nt");
+    /// write(&file, "<http://example.com> <http://example.com> <http://example.com> .")?;
+    ///
+    /// let store = MemoryStore::new();
+    /// let update = format!("LOAD <file://{}>", file.display());
+    /// store.prepare_update(&update, QueryOptions::default().with_load_directory(temp_dir()))?.exec()?;
+    /// assert_eq!(store.len(), 1);
+    /// # remove_file(file)?;
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn with_load_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.load_directory = Some(directory.into());
+        self
+    }
+
+    /// Evaluates the SPARQL SERVICE calls by sending HTTP requests to the remote endpoints.
+    ///
+    /// It should not be enabled when the queries come from untrusted users:
+    /// they would be able to make the query evaluator send requests to any `http://` URL.
+    pub fn with_simple_service_handler(mut self) -> Self {
+        self.service_handler = Rc::new(SimpleServiceHandler);
+        self
+    }
+
+    /// Disables SPARQL SERVICE calls: queries containing a non silent SERVICE clause will fail.
+    ///
+    /// It is the default behavior.
+    pub fn without_service_handler(mut self) -> Self {
+        self.service_handler = Rc::new(EmptyServiceHandler);
         self
     }
 }
@@ -1,14 +1,17 @@
 use crate::model::*;
-use crate::sparql::json_results::write_json_results;
+use crate::sparql::csv_results::{write_csv_results, write_tsv_results};
+use crate::sparql::json_results::{read_json_results, write_json_results};
 use crate::sparql::xml_results::{read_xml_results, write_xml_results};
+use crate::store::rio_triple;
 use crate::Error;
 use crate::{FileSyntax, GraphSyntax, Result};
 use rand::random;
 use rio_api::formatter::TriplesFormatter;
-use rio_turtle::{NTriplesFormatter, TurtleFormatter};
+use rio_turtle::TurtleFormatter;
 use rio_xml::RdfXmlFormatter;
 use std::fmt;
 use std::io::{BufRead, Write};
+use std::iter::FromIterator;
 use std::rc::Rc;
 
 /// Results of a [SPARQL query](https://www.w3.org/TR/sparql11-query/)
@@ -25,9 +28,9 @@ impl<'a> QueryResult<'a> {
     pub fn read(reader: impl BufRead + 'a, syntax: QueryResultSyntax) -> Result<Self> {
         match syntax {
             QueryResultSyntax::Xml => read_xml_results(reader),
-            QueryResultSyntax::Json => Err(Error::msg(
-                //TODO: implement
-                "JSON SPARQL results format parsing has not been implemented yet",
+            QueryResultSyntax::Json => read_json_results(reader),
+            QueryResultSyntax::Csv | QueryResultSyntax::Tsv => Err(Error::msg(
+                "CSV and TSV SPARQL results formats parsing is not supported",
             )),
         }
     }
@@ -36,30 +39,31 @@ impl<'a> QueryResult<'a> {
         match syntax {
             QueryResultSyntax::Xml => write_xml_results(self, writer),
             QueryResultSyntax::Json => write_json_results(self, writer),
+            QueryResultSyntax::Csv => write_csv_results(self, writer),
+            QueryResultSyntax::Tsv => write_tsv_results(self, writer),
         }
     }
 
-    pub fn write_graph<W: Write>(self, write: W, syntax: GraphSyntax) -> Result<W> {
+    pub fn write_graph<W: Write>(self, mut write: W, syntax: GraphSyntax) -> Result<W> {
         if let QueryResult::Graph(triples) = self {
             Ok(match syntax {
                 GraphSyntax::NTriples => {
-                    let mut formatter = NTriplesFormatter::new(write);
                     for triple in triples {
-                        formatter.format(&(&triple?).into())?;
+                        writeln!(write, "{} .", triple?)?;
                     }
-                    formatter.finish()
+                    write
                 }
                 GraphSyntax::Turtle => {
                     let mut formatter = TurtleFormatter::new(write);
                     for triple in triples {
-                        formatter.format(&(&triple?).into())?;
+                        formatter.format(&rio_triple(&triple?)?)?;
                     }
                     formatter.finish()?
                 }
                 GraphSyntax::RdfXml => {
                     let mut formatter = RdfXmlFormatter::new(write)?;
                     for triple in triples {
-                        formatter.format(&(&triple?).into())?;
+                        formatter.format(&rio_triple(&triple?)?)?;
                     }
                     formatter.finish()?
                 }
@@ -79,6 +83,10 @@ pub enum QueryResultSyntax {
     Xml,
     /// [SPARQL Query Results JSON Format](https://www.w3.org/TR/sparql11-results-json/)
     Json,
+    /// [SPARQL Query Results CSV Format](https://www.w3.org/TR/sparql11-results-csv-tsv/)
+    Csv,
+    /// [SPARQL Query Results TSV Format](https://www.w3.org/TR/sparql11-results-csv-tsv/)
+    Tsv,
 }
 
 impl FileSyntax for QueryResultSyntax {
@@ -86,6 +94,8 @@ impl FileSyntax for QueryResultSyntax {
         match self {
             QueryResultSyntax::Xml => "http://www.w3.org/ns/formats/SPARQL_Results_XML",
             QueryResultSyntax::Json => "http://www.w3.org/ns/formats/SPARQL_Results_JSON",
+            QueryResultSyntax::Csv => "http://www.w3.org/ns/formats/SPARQL_Results_CSV",
+            QueryResultSyntax::Tsv => "http://www.w3.org/ns/formats/SPARQL_Results_TSV",
         }
     }
 
@@ -93,6 +103,8 @@ impl FileSyntax for QueryResultSyntax {
         match self {
             QueryResultSyntax::Xml => "application/sparql-results+xml",
             QueryResultSyntax::Json => "application/sparql-results+json",
+            QueryResultSyntax::Csv => "text/csv",
+            QueryResultSyntax::Tsv => "text/tab-separated-values",
         }
     }
 
@@ -100,6 +112,8 @@ impl FileSyntax for QueryResultSyntax {
         match self {
             QueryResultSyntax::Xml => "srx",
             QueryResultSyntax::Json => "srj",
+            QueryResultSyntax::Csv => "csv",
+            QueryResultSyntax::Tsv => "tsv",
         }
     }
 
@@ -112,6 +126,8 @@ impl FileSyntax for QueryResultSyntax {
                 "application/sparql-results+json" | "application/json" | "text/json" => {
                     Some(QueryResultSyntax::Json)
                 }
+                "text/csv" => Some(QueryResultSyntax::Csv),
+                "text/tab-separated-values" | "text/tsv" => Some(QueryResultSyntax::Tsv),
                 _ => None,
             }
         } else {
@@ -242,6 +258,76 @@ impl QuerySolution {
     }
 }
 
+impl FromIterator<(Variable, Term)> for QuerySolution {
+    fn from_iter<I: IntoIterator<Item = (Variable, Term)>>(iter: I) -> Self {
+        let (variables, values) = iter
+            .into_iter()
+            .map(|(variable, value)| (variable, Some(value)))
+            .unzip();
+        Self {
+            values,
+            variables: Rc::new(variables),
+        }
+    }
+}
+
+/// Values given to some variables of a query before its evaluation.
+///
+/// It allows to build parameterized queries without inserting the parameter values into the query string:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::{MemoryStore, Result};
+/// use oxigraph::sparql::{QueryOptions, QueryResult, QueryVariables};
+///
+/// let store = MemoryStore::new();
+/// let ex = NamedNode::new("http://example.com")?;
+/// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from("foo"), None));
+///
+/// let user_input = Literal::from("foo");
+/// let prepared_query = store.prepare_query(
+///     "SELECT ?s WHERE { ?s ?p $name }",
+///     QueryOptions::default().with_variables(QueryVariables::new().bind("name", user_input)),
+/// )?;
+/// if let QueryResult::Solutions(mut solutions) = prepared_query.exec()? {
+///     assert_eq!(solutions.next().unwrap()?.get("s"), Some(&ex.into()));
+/// }
+///
+/// // The preparation fails if a variable is not used in the query
+/// assert!(store.prepare_query(
+///     "SELECT ?s WHERE { ?s ?p ?o }",
+///     QueryOptions::default().with_variables(QueryVariables::new().bind("name", Literal::from("foo"))),
+/// ).is_err());
+/// # Result::Ok(())
+/// ```
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct QueryVariables {
+    values: Vec<(Variable, Term)>,
+}
+
+impl QueryVariables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of the variable `name`, given without its `?` or `$` prefix
+    pub fn bind(mut self, name: impl Into<String>, value: impl Into<Term>) -> Self {
+        let variable = Variable::new(name);
+        let value = value.into();
+        if let Some(position) = self.values.iter().position(|(v, _)| *v == variable) {
+            self.values[position].1 = value;
+        } else {
+            self.values.push((variable, value));
+        }
+        self
+    }
+}
+
+impl From<QueryVariables> for QuerySolution {
+    fn from(variables: QueryVariables) -> Self {
+        variables.values.into_iter().collect()
+    }
+}
+
 /// A utility trait to get values for a given variable or tuple position
 pub trait VariableSolutionIndex {
     fn index(self, solution: &QuerySolution) -> Option<usize>;
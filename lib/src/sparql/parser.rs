@@ -8,25 +8,77 @@ use peg::error::ParseError;
 use peg::parser;
 use peg::str::LineCol;
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::error::Error;
 use std::str::Chars;
 use std::str::FromStr;
 use std::{char, fmt};
 
 /// A parsed [SPARQL query](https://www.w3.org/TR/sparql11-query/)
+///
+/// `to_string` returns a normalized SPARQL serialization of the query that could be parsed again:
+/// ```
+/// use oxigraph::sparql::Query;
+///
+/// let query = Query::parse(
+///     "PREFIX ex: <http://example.com/> SELECT ?s WHERE { ?s ex:p ?o FILTER(?o > (1 + 2) * 3) }",
+///     None,
+/// )?;
+/// assert_eq!(Query::parse(&query.to_string(), None)?, query);
+/// # oxigraph::Result::Ok(())
+/// ```
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
-pub struct Query(pub(crate) QueryVariants);
+pub struct Query {
+    pub(crate) query: QueryVariants,
+    pub(crate) hints: QueryHints,
+}
 
 impl fmt::Display for Query {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+        self.hints.fmt(f)?;
+        self.query.fmt(f)
+    }
+}
+
+impl From<QueryVariants> for Query {
+    fn from(query: QueryVariants) -> Self {
+        Self {
+            query,
+            hints: QueryHints::default(),
+        }
+    }
+}
+
+impl From<Query> for QueryVariants {
+    fn from(query: Query) -> Self {
+        query.query
     }
 }
 
 impl Query {
     /// Parses a SPARQL query
+    ///
+    /// The query is only validated, not evaluated: syntax errors and variable scoping errors are reported.
+    ///
+    /// ```
+    /// use oxigraph::sparql::Query;
+    ///
+    /// assert!(Query::parse("SELECT ?s WHERE { ?s ?p ?o }", None).is_ok());
+    /// // ?o is not a GROUP BY key
+    /// assert!(Query::parse("SELECT ?o WHERE { ?s ?p ?o } GROUP BY ?s", None).is_err());
+    /// // ?o is already bound when BIND is evaluated
+    /// assert!(Query::parse("SELECT * WHERE { ?s ?p ?o BIND(1 AS ?o) }", None).is_err());
+    /// ```
     pub fn parse(query: &str, base_iri: Option<&str>) -> Result<Self, SparqlParseError> {
+        Self::parse_with_custom_aggregates(query, base_iri, HashSet::default())
+    }
+
+    /// Parses a SPARQL query in which the calls to the functions named `custom_aggregates` are aggregates
+    pub(crate) fn parse_with_custom_aggregates(
+        query: &str,
+        base_iri: Option<&str>,
+        custom_aggregates: HashSet<NamedNode>,
+    ) -> Result<Self, SparqlParseError> {
         let mut state = ParserState {
             base_iri: if let Some(base_iri) = base_iri {
                 Some(
@@ -41,15 +93,84 @@ impl Query {
             used_bnodes: HashSet::default(),
             currently_used_bnodes: HashSet::default(),
             aggregations: Vec::default(),
+            custom_aggregates,
+            hints: QueryHints::default(),
         };
 
-        Ok(Self(
+        let query =
             parser::QueryUnit(&unescape_unicode_codepoints(query), &mut state).map_err(|e| {
                 SparqlParseError {
                     inner: SparqlParseErrorKind::Parser(e),
                 }
-            })?,
-        ))
+            })?;
+        Ok(Self {
+            query,
+            hints: state.hints,
+        })
+    }
+
+    /// The planner hints given with `#pragma` comments in the query
+    ///
+    /// ```
+    /// use oxigraph::sparql::Query;
+    ///
+    /// let query = Query::parse("#pragma join-order fixed\nSELECT ?s WHERE { ?s ?p ?o }", None)?;
+    /// assert!(query.hints().fixed_join_order);
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn hints(&self) -> &QueryHints {
+        &self.hints
+    }
+}
+
+/// A parsed [SPARQL update](https://www.w3.org/TR/sparql11-update/)
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct Update {
+    pub(crate) base_iri: Option<Iri<String>>,
+    pub(crate) operations: Vec<GraphUpdateOperation>,
+}
+
+impl fmt::Display for Update {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(base_iri) = &self.base_iri {
+            writeln!(f, "BASE <{}>", base_iri)?;
+        }
+        for operation in &self.operations {
+            writeln!(f, "{} ;", operation)?;
+        }
+        Ok(())
+    }
+}
+
+impl Update {
+    /// Parses a SPARQL update
+    pub fn parse(update: &str, base_iri: Option<&str>) -> Result<Self, SparqlParseError> {
+        let mut state = ParserState {
+            base_iri: if let Some(base_iri) = base_iri {
+                Some(
+                    Iri::parse(base_iri.to_owned()).map_err(|e| SparqlParseError {
+                        inner: SparqlParseErrorKind::InvalidBaseIri(e),
+                    })?,
+                )
+            } else {
+                None
+            },
+            namespaces: HashMap::default(),
+            used_bnodes: HashSet::default(),
+            currently_used_bnodes: HashSet::default(),
+            aggregations: Vec::default(),
+            custom_aggregates: HashSet::default(),
+            hints: QueryHints::default(),
+        };
+
+        let operations = parser::UpdateInit(&unescape_unicode_codepoints(update), &mut state)
+            .map_err(|e| SparqlParseError {
+                inner: SparqlParseErrorKind::Parser(e),
+            })?;
+        Ok(Self {
+            base_iri: state.base_iri,
+            operations,
+        })
     }
 }
 
@@ -228,6 +349,87 @@ fn new_join(l: GraphPattern, r: GraphPattern) -> GraphPattern {
     }
 }
 
+fn triple_patterns_in_graph(
+    patterns: Vec<TriplePattern>,
+    graph_name: Option<NamedNodeOrVariable>,
+) -> Vec<QuadPattern> {
+    patterns
+        .into_iter()
+        .map(|t| QuadPattern::new(t.subject, t.predicate, t.object, graph_name.clone()))
+        .collect()
+}
+
+fn quad_patterns_to_graph_pattern(patterns: &[QuadPattern]) -> GraphPattern {
+    patterns
+        .iter()
+        .map(|q| {
+            let bgp = GraphPattern::BGP(vec![TriplePattern::new(
+                q.subject.clone(),
+                q.predicate.clone(),
+                q.object.clone(),
+            )
+            .into()]);
+            if let Some(graph_name) = &q.graph_name {
+                GraphPattern::Graph(graph_name.clone(), Box::new(bgp))
+            } else {
+                bgp
+            }
+        })
+        .fold(GraphPattern::default(), new_join)
+}
+
+fn quad_pattern_has_variable(pattern: &QuadPattern) -> bool {
+    term_has_variable(&pattern.subject)
+        || matches!(pattern.predicate, NamedNodeOrVariable::Variable(_))
+        || term_has_variable(&pattern.object)
+        || matches!(pattern.graph_name, Some(NamedNodeOrVariable::Variable(_)))
+}
+
+fn term_has_variable(term: &TermOrVariable) -> bool {
+    match term {
+        TermOrVariable::Term(_) => false,
+        TermOrVariable::Variable(_) => true,
+        TermOrVariable::Triple(triple) => {
+            term_has_variable(&triple.subject)
+                || matches!(triple.predicate, NamedNodeOrVariable::Variable(_))
+                || term_has_variable(&triple.object)
+        }
+    }
+}
+
+fn quad_pattern_has_blank_node(pattern: &QuadPattern) -> bool {
+    term_has_blank_node(&pattern.subject) || term_has_blank_node(&pattern.object)
+}
+
+fn term_has_blank_node(term: &TermOrVariable) -> bool {
+    match term {
+        TermOrVariable::Term(term) => term.is_blank_node(),
+        TermOrVariable::Variable(_) => false,
+        TermOrVariable::Triple(triple) => {
+            term_has_blank_node(&triple.subject) || term_has_blank_node(&triple.object)
+        }
+    }
+}
+
+fn set_default_graph(
+    patterns: Vec<QuadPattern>,
+    graph_name: &NamedNodeOrVariable,
+) -> Vec<QuadPattern> {
+    patterns
+        .into_iter()
+        .map(|q| {
+            if q.graph_name.is_none() {
+                QuadPattern {
+                    graph_name: Some(graph_name.clone()),
+                    ..q
+                }
+            } else {
+                q
+            }
+        })
+        .collect()
+}
+
 fn not_empty_fold<T>(
     iter: impl Iterator<Item = T>,
     combine: impl Fn(T, T) -> T,
@@ -273,19 +475,17 @@ fn build_select(
     offset_limit: Option<(usize, Option<usize>)>,
     values: Option<GraphPattern>,
     state: &mut ParserState,
-) -> GraphPattern {
+) -> Result<GraphPattern, &'static str> {
     let mut p = wher;
 
     //GROUP BY
     let aggregations = state.aggregations.pop().unwrap_or_else(Vec::default);
     if group.is_none() && !aggregations.is_empty() {
-        let const_variable = Variable::new_random();
-        group = Some((
-            vec![const_variable.clone()],
-            vec![(Literal::from(1).into(), const_variable)],
-        ));
+        // Without GROUP BY all the solutions are in the same group
+        group = Some((Vec::default(), Vec::default()));
     }
 
+    let with_aggregate = group.is_some();
     if let Some((clauses, binds)) = group {
         for (e, v) in binds {
             p = GraphPattern::Extend(Box::new(p), v, e);
@@ -308,16 +508,26 @@ fn build_select(
     let mut pv: Vec<Variable> = Vec::default();
     match select.variables {
         Some(sel_items) => {
+            let visible_variables = p
+                .visible_variables()
+                .into_iter()
+                .cloned()
+                .collect::<BTreeSet<_>>();
             for sel_item in sel_items {
                 match sel_item {
-                    SelectionMember::Variable(v) => pv.push(v),
+                    SelectionMember::Variable(v) => {
+                        if with_aggregate && !visible_variables.contains(&v) {
+                            // With aggregates only the group keys could be projected
+                            return Err("The SELECT contains a variable that is not grouped");
+                        }
+                        pv.push(v)
+                    }
                     SelectionMember::Expression(e, v) => {
-                        if pv.contains(&v) {
-                            //TODO: fail
-                        } else {
-                            p = GraphPattern::Extend(Box::new(p), v.clone(), e);
-                            pv.push(v);
+                        if pv.contains(&v) || visible_variables.contains(&v) {
+                            return Err("The SELECT overrides an existing variable");
                         }
+                        p = GraphPattern::Extend(Box::new(p), v.clone(), e);
+                        pv.push(v);
                     }
                 }
             }
@@ -345,7 +555,7 @@ fn build_select(
     if let Some((offset, limit)) = offset_limit {
         m = GraphPattern::Slice(Box::new(m), offset, limit)
     }
-    m
+    Ok(m)
 }
 
 enum Either<L, R> {
@@ -359,6 +569,8 @@ pub struct ParserState {
     used_bnodes: HashSet<BlankNode>,
     currently_used_bnodes: HashSet<BlankNode>,
     aggregations: Vec<Vec<(Aggregation, Variable)>>,
+    custom_aggregates: HashSet<NamedNode>,
+    hints: QueryHints,
 }
 
 impl ParserState {
@@ -370,6 +582,26 @@ impl ParserState {
         }
     }
 
+    /// Builds a call to a custom function or to a custom aggregate if `name` is one of them
+    fn new_custom_function_call(
+        &mut self,
+        name: NamedNode,
+        distinct: bool,
+        arguments: Vec<Expression>,
+    ) -> Result<Expression, &'static str> {
+        if !self.custom_aggregates.contains(&name) {
+            return Ok(Expression::FunctionCall(Function::Custom(name), arguments));
+        }
+        let mut arguments = arguments;
+        if arguments.len() != 1 {
+            return Err("Custom aggregates should have exactly one argument");
+        }
+        let argument = arguments.pop().unwrap();
+        Ok(self
+            .new_aggregation(Aggregation::Custom(name, Box::new(argument), distinct))?
+            .into())
+    }
+
     fn new_aggregation(&mut self, agg: Aggregation) -> Result<Variable, &'static str> {
         let aggregations = self
             .aggregations
@@ -602,6 +834,9 @@ parser! {
             q
         }
 
+        //[3]
+        pub rule UpdateInit() -> Vec<GraphUpdateOperation> = Update()
+
         //[4]
         rule Prologue() = (BaseDecl() _ / PrefixDecl() _)* {}
 
@@ -616,17 +851,26 @@ parser! {
         }
 
         //[7]
-        rule SelectQuery() -> QueryVariants = s:SelectClause() _ d:DatasetClauses() _ w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {  //TODO: Modifier
-            QueryVariants::Select {
-                dataset: d,
-                algebra: build_select(s, w, g, h, o, l, v, state),
-                base_iri: state.base_iri.clone()
+        rule SelectQuery() -> QueryVariants = s:SelectClause() _ d:DatasetClauses() _ w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {?  //TODO: Modifier
+            if s.variables.is_none() && g.is_some() {
+                Err("SELECT * is not allowed with GROUP BY")
+            } else {
+                let algebra = build_select(s, w, g, h, o, l, v, state);
+                algebra.map(|algebra| QueryVariants::Select {
+                    dataset: d,
+                    algebra,
+                    base_iri: state.base_iri.clone()
+                })
             }
         }
 
         //[8]
-        rule SubSelect() -> GraphPattern = s:SelectClause() _ w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() { //TODO: Modifiers
-            build_select(s, w, g, h, o, l, v, state)
+        rule SubSelect() -> GraphPattern = s:SelectClause() _ w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {? //TODO: Modifiers
+            if s.variables.is_none() && g.is_some() {
+                Err("SELECT * is not allowed with GROUP BY")
+            } else {
+                build_select(s, w, g, h, o, l, v, state)
+            }
         }
 
         //[9]
@@ -652,60 +896,65 @@ parser! {
 
         //[10]
         rule ConstructQuery() -> QueryVariants =
-            i("CONSTRUCT") _ c:ConstructTemplate() _ d:DatasetClauses() _ w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {
-                QueryVariants::Construct {
+            i("CONSTRUCT") _ c:ConstructTemplate() _ d:DatasetClauses() _ w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {?
+                let algebra = build_select(Selection::default(), w, g, h, o, l, v, state);
+                algebra.map(|algebra| QueryVariants::Construct {
                     construct: c,
                     dataset: d,
-                    algebra: build_select(Selection::default(), w, g, h, o, l, v, state),
+                    algebra,
                     base_iri: state.base_iri.clone()
-                }
+                })
             } /
-            i("CONSTRUCT") _ d:DatasetClauses() _ i("WHERE") _ "{" _ c:ConstructQuery_optional_triple_template() _ "}" _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {
-                QueryVariants::Construct {
-                    construct: c.clone(),
+            i("CONSTRUCT") _ d:DatasetClauses() _ i("WHERE") _ "{" _ c:ConstructQuery_optional_triple_template() _ "}" _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {?
+                let algebra = build_select(
+                    Selection::default(),
+                    GraphPattern::BGP(c.iter().cloned().map(TripleOrPathPattern::from).collect()),
+                    g, h, o, l, v, state
+                );
+                algebra.map(|algebra| QueryVariants::Construct {
+                    construct: c,
                     dataset: d,
-                    algebra: build_select(
-                        Selection::default(),
-                        GraphPattern::BGP(c.into_iter().map(TripleOrPathPattern::from).collect()),
-                        g, h, o, l, v, state
-                    ),
+                    algebra,
                     base_iri: state.base_iri.clone()
-                }
+                })
             }
 
         rule ConstructQuery_optional_triple_template() -> Vec<TriplePattern> = TriplesTemplate() / { Vec::default() }
 
         //[11]
         rule DescribeQuery() -> QueryVariants =
-            i("DESCRIBE") _ "*" _ d:DatasetClauses() w:WhereClause()? _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {
-                QueryVariants::Describe {
+            i("DESCRIBE") _ "*" _ d:DatasetClauses() w:WhereClause()? _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {?
+                let algebra = build_select(Selection::default(), w.unwrap_or_else(GraphPattern::default), g, h, o, l, v, state);
+                algebra.map(|algebra| QueryVariants::Describe {
                     dataset: d,
-                    algebra: build_select(Selection::default(), w.unwrap_or_else(GraphPattern::default), g, h, o, l, v, state),
+                    algebra,
                     base_iri: state.base_iri.clone()
-                }
+                })
             } /
-            i("DESCRIBE") _ p:DescribeQuery_item()+ _ d:DatasetClauses() w:WhereClause()? _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {
-                QueryVariants::Describe {
+            i("DESCRIBE") _ p:DescribeQuery_item()+ _ d:DatasetClauses() w:WhereClause()? _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {?
+                let algebra = build_select(Selection {
+                    option: SelectionOption::Default,
+                    variables: Some(p.into_iter().map(|var_or_iri| match var_or_iri {
+                        NamedNodeOrVariable::NamedNode(n) => SelectionMember::Expression(n.into(), Variable::new_random()),
+                        NamedNodeOrVariable::Variable(v) => SelectionMember::Variable(v)
+                    }).collect())
+                }, w.unwrap_or_else(GraphPattern::default), g, h, o, l, v, state);
+                algebra.map(|algebra| QueryVariants::Describe {
                     dataset: d,
-                    algebra: build_select(Selection {
-                        option: SelectionOption::Default,
-                        variables: Some(p.into_iter().map(|var_or_iri| match var_or_iri {
-                            NamedNodeOrVariable::NamedNode(n) => SelectionMember::Expression(n.into(), Variable::new_random()),
-                            NamedNodeOrVariable::Variable(v) => SelectionMember::Variable(v)
-                        }).collect())
-                    }, w.unwrap_or_else(GraphPattern::default), g, h, o, l, v, state),
+                    algebra,
                     base_iri: state.base_iri.clone()
-                }
+                })
             }
         rule DescribeQuery_item() -> NamedNodeOrVariable = i:VarOrIri() _ { i }
 
         //[12]
-        rule AskQuery() -> QueryVariants = i("ASK") _ d:DatasetClauses() w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {
-            QueryVariants::Ask {
+        rule AskQuery() -> QueryVariants = i("ASK") _ d:DatasetClauses() w:WhereClause() _ g:GroupClause()? _ h:HavingClause()? _ o:OrderClause()? _ l:LimitOffsetClauses()? _ v:ValuesClause() {?
+            let algebra = build_select(Selection::default(), w, g, h, o, l, v, state);
+            algebra.map(|algebra| QueryVariants::Ask {
                 dataset: d,
-                algebra: build_select(Selection::default(), w, g, h, o, l, v, state),
+                algebra,
                 base_iri: state.base_iri.clone()
-            }
+            })
         }
 
         //[13]
@@ -795,6 +1044,162 @@ parser! {
             i("VALUES") _ p:DataBlock() { Some(p) } /
             { None }
 
+        //[29]
+        rule Update() -> Vec<GraphUpdateOperation> = _ Prologue() _ u:(Update1() ** (_ ";" _ Prologue() _)) _ (";" _ Prologue() _)? {
+            u
+        }
+
+        //[30]
+        rule Update1() -> GraphUpdateOperation = Load() / Clear() / Drop() / Create() / Add() / Move() / Copy() / InsertData() / DeleteData() / DeleteWhere() / Modify()
+        rule Update1_silent() -> bool = i("SILENT") { true } / { false }
+
+        //[31]
+        rule Load() -> GraphUpdateOperation = i("LOAD") _ silent:Update1_silent() _ from:iri() _ to:Load_to()? {
+            GraphUpdateOperation::Load { silent, from, to }
+        }
+        rule Load_to() -> NamedNode = i("INTO") _ g: GraphRef() { g }
+
+        //[32]
+        rule Clear() -> GraphUpdateOperation = i("CLEAR") _ silent:Update1_silent() _ graph:GraphRefAll() {
+            GraphUpdateOperation::Clear { silent, graph }
+        }
+
+        //[33]
+        rule Drop() -> GraphUpdateOperation = i("DROP") _ silent:Update1_silent() _ graph:GraphRefAll() {
+            GraphUpdateOperation::Drop { silent, graph }
+        }
+
+        //[34]
+        rule Create() -> GraphUpdateOperation = i("CREATE") _ silent:Update1_silent() _ graph:GraphRef() {
+            GraphUpdateOperation::Create { silent, graph }
+        }
+
+        //[35]
+        rule Add() -> GraphUpdateOperation = i("ADD") _ silent:Update1_silent() _ from:GraphOrDefault() _ i("TO") _ to:GraphOrDefault() {
+            GraphUpdateOperation::Add { silent, from, to }
+        }
+
+        //[36]
+        rule Move() -> GraphUpdateOperation = i("MOVE") _ silent:Update1_silent() _ from:GraphOrDefault() _ i("TO") _ to:GraphOrDefault() {
+            GraphUpdateOperation::Move { silent, from, to }
+        }
+
+        //[37]
+        rule Copy() -> GraphUpdateOperation = i("COPY") _ silent:Update1_silent() _ from:GraphOrDefault() _ i("TO") _ to:GraphOrDefault() {
+            GraphUpdateOperation::Copy { silent, from, to }
+        }
+
+        //[38]
+        rule InsertData() -> GraphUpdateOperation = i("INSERT") _ i("DATA") _ data:QuadData() {
+            GraphUpdateOperation::InsertData { data }
+        }
+
+        //[39]
+        rule DeleteData() -> GraphUpdateOperation = i("DELETE") _ i("DATA") _ data:QuadData() {?
+            if data.iter().any(quad_pattern_has_blank_node) {
+                Err("Blank nodes are not allowed in DELETE DATA")
+            } else {
+                Ok(GraphUpdateOperation::DeleteData { data })
+            }
+        }
+
+        //[40]
+        rule DeleteWhere() -> GraphUpdateOperation = i("DELETE") _ i("WHERE") _ d:QuadPattern() {?
+            if d.iter().any(quad_pattern_has_blank_node) {
+                Err("Blank nodes are not allowed in DELETE WHERE")
+            } else {
+                Ok(GraphUpdateOperation::DeleteInsert {
+                    algebra: quad_patterns_to_graph_pattern(&d),
+                    delete: d,
+                    insert: Vec::default(),
+                    using: DatasetSpec::default(),
+                })
+            }
+        }
+
+        //[41]
+        rule Modify() -> GraphUpdateOperation = with:Modify_with()? _ c:Modify_clauses() _ u:UsingClause_item()* i("WHERE") _ algebra:GroupGraphPattern() {?
+            let (delete, insert) = c;
+            let mut delete = delete.unwrap_or_else(Vec::default);
+            let mut insert = insert.unwrap_or_else(Vec::default);
+            let using = u.into_iter().fold(DatasetSpec::default(), |a, b| a + b);
+            let mut algebra = algebra;
+            if let Some(with) = with {
+                let with = NamedNodeOrVariable::from(with);
+                delete = set_default_graph(delete, &with);
+                insert = set_default_graph(insert, &with);
+                if using.default.is_empty() && using.named.is_empty() {
+                    algebra = GraphPattern::Graph(with, Box::new(algebra));
+                }
+            }
+            if delete.iter().any(quad_pattern_has_blank_node) {
+                Err("Blank nodes are not allowed in DELETE templates")
+            } else {
+                Ok(GraphUpdateOperation::DeleteInsert { delete, insert, using, algebra })
+            }
+        }
+        rule Modify_with() -> NamedNode = i("WITH") _ g:iri() _ { g }
+        rule Modify_clauses() -> (Option<Vec<QuadPattern>>, Option<Vec<QuadPattern>>) =
+            d:DeleteClause() _ i:InsertClause()? { (Some(d), i) } /
+            i:InsertClause() { (None, Some(i)) }
+
+        //[42]
+        rule DeleteClause() -> Vec<QuadPattern> = i("DELETE") _ q:QuadPattern() { q }
+
+        //[43]
+        rule InsertClause() -> Vec<QuadPattern> = i("INSERT") _ q:QuadPattern() { q }
+
+        //[44]
+        rule UsingClause() -> DatasetSpec = i("USING") _ d:(UsingClause_named() / UsingClause_default()) { d }
+        rule UsingClause_item() -> DatasetSpec = d:UsingClause() _ { d }
+        rule UsingClause_default() -> DatasetSpec = g:iri() {
+            DatasetSpec::new_with_default(g)
+        }
+        rule UsingClause_named() -> DatasetSpec = i("NAMED") _ g:iri() {
+            DatasetSpec::new_with_named(g)
+        }
+
+        //[46]
+        rule GraphRef() -> NamedNode = i("GRAPH") _ g:iri() { g }
+
+        //[45]
+        rule GraphOrDefault() -> GraphName =
+            i("DEFAULT") { GraphName::DefaultGraph } /
+            (i("GRAPH") _)? g:iri() { g.into() }
+
+        //[47]
+        rule GraphRefAll() -> GraphTarget  =
+            g:GraphRef() { GraphTarget::NamedNode(g) } /
+            i("DEFAULT") { GraphTarget::DefaultGraph } /
+            i("NAMED") { GraphTarget::NamedGraphs } /
+            i("ALL") { GraphTarget::AllGraphs }
+
+        //[48]
+        rule QuadPattern() -> Vec<QuadPattern> = "{" _ q:Quads() _ "}" { q }
+
+        //[49]
+        rule QuadData() -> Vec<QuadPattern> = "{" _ q:Quads() _ "}" {?
+            if q.iter().any(quad_pattern_has_variable) {
+                Err("Variables are not allowed in INSERT DATA and DELETE DATA")
+            } else {
+                Ok(q)
+            }
+        }
+
+        //[50]
+        rule Quads() -> Vec<QuadPattern> = q:(Quads_TriplesTemplate() / Quads_QuadsNotTriples())* {
+            q.into_iter().flatten().collect()
+        }
+        rule Quads_TriplesTemplate() -> Vec<QuadPattern> = t:TriplesTemplate() _ {
+            triple_patterns_in_graph(t, None)
+        }
+        rule Quads_QuadsNotTriples() -> Vec<QuadPattern> = q:QuadsNotTriples() _ ("." _)? { q }
+
+        //[51]
+        rule QuadsNotTriples() -> Vec<QuadPattern> = i("GRAPH") _ g:VarOrIri() _ "{" _ t:TriplesTemplate()? _ "}" {
+            triple_patterns_in_graph(t.unwrap_or_else(Vec::default), Some(g))
+        }
+
         //[52]
         rule TriplesTemplate() -> Vec<TriplePattern> =  h:TriplesSameSubject() _ t:TriplesTemplate_tail()? {
             let mut triples = h;
@@ -812,14 +1217,28 @@ parser! {
             "{" _ p:GroupGraphPatternSub() _ "}" { p } /
             "{" _ p:SubSelect() _ "}" { p }
 
+        // The group pattern with its own FILTER kept apart, in order to use it as the OPTIONAL condition
+        rule GroupGraphPattern_filtered() -> (GraphPattern, Option<Expression>) =
+            "{" _ p:GroupGraphPatternSub_filtered() _ "}" { p } /
+            "{" _ p:SubSelect() _ "}" { (p, None) }
+
         //[54]
-        rule GroupGraphPatternSub() -> GraphPattern = a:TriplesBlock()? _ b:GroupGraphPatternSub_item()* {
+        rule GroupGraphPatternSub() -> GraphPattern = p:GroupGraphPatternSub_filtered() {
+            let (g, filter) = p;
+            if let Some(filter) = filter {
+                GraphPattern::Filter(filter, Box::new(g))
+            } else {
+                g
+            }
+        }
+        rule GroupGraphPatternSub_filtered() -> (GraphPattern, Option<Expression>) = a:TriplesBlock()? _ b:GroupGraphPatternSub_item()* {?
             let mut p = a.map_or_else(Vec::default, |v| vec![PartialGraphPattern::Other(GraphPattern::BGP(v))]);
             for v in b {
                 p.extend_from_slice(&v)
             }
             let mut filter: Option<Expression> = None;
             let mut g = GraphPattern::default();
+            let mut error = None;
             for e in p {
                 match e {
                     PartialGraphPattern::Optional(p, f) => {
@@ -829,6 +1248,10 @@ parser! {
                         g = GraphPattern::Minus(Box::new(g), Box::new(p))
                     }
                     PartialGraphPattern::Bind(expr, var) => {
+                        if g.visible_variables().contains(&var) {
+                            error = Some("BIND is overriding an existing variable");
+                            break;
+                        }
                         g = GraphPattern::Extend(Box::new(g), var, expr)
                     }
                     PartialGraphPattern::Filter(expr) => filter = Some(if let Some(f) = filter {
@@ -844,10 +1267,10 @@ parser! {
             state.used_bnodes.extend(state.currently_used_bnodes.iter().cloned());
             state.currently_used_bnodes.clear();
 
-            if let Some(filter) = filter {
-                GraphPattern::Filter(filter, Box::new(g))
+            if let Some(error) = error {
+                Err(error)
             } else {
-                g
+                Ok((g, filter))
             }
         }
         rule GroupGraphPatternSub_item() -> Vec<PartialGraphPattern> = a:GraphPatternNotTriples() _ ("." _)? b:TriplesBlock()? _ {
@@ -874,12 +1297,9 @@ parser! {
         rule GraphPatternNotTriples() -> PartialGraphPattern = GroupOrUnionGraphPattern() / OptionalGraphPattern() / MinusGraphPattern() / GraphGraphPattern() / ServiceGraphPattern() / Filter() / Bind() / InlineData()
 
         //[57]
-        rule OptionalGraphPattern() -> PartialGraphPattern = i("OPTIONAL") _ p:GroupGraphPattern() {
-            if let GraphPattern::Filter(f, p) =  p {
-               PartialGraphPattern::Optional(*p, Some(f))
-            } else {
-               PartialGraphPattern::Optional(p, None)
-            }
+        rule OptionalGraphPattern() -> PartialGraphPattern = i("OPTIONAL") _ p:GroupGraphPattern_filtered() {
+            let (p, f) = p;
+            PartialGraphPattern::Optional(p, f)
         }
 
         //[58]
@@ -912,8 +1332,12 @@ parser! {
         rule InlineDataOneVar_value() -> Vec<Option<Term>> = t:DataBlockValue() _ { vec![t] }
 
         //[64]
-        rule InlineDataFull() -> StaticBindings = "(" _ vars:InlineDataFull_var()* _ ")" _ "{" _ val:InlineDataFull_values()* "}" {
-            StaticBindings::new(vars, val)
+        rule InlineDataFull() -> StaticBindings = "(" _ vars:InlineDataFull_var()* _ ")" _ "{" _ val:InlineDataFull_values()* "}" {?
+            if val.iter().all(|vals| vals.len() == vars.len()) {
+                Ok(StaticBindings::new(vars, val))
+            } else {
+                Err("The VALUES clause rows should have exactly the same number of values as there are variables")
+            }
         }
         rule InlineDataFull_var() -> Variable = v:Var() _ { v }
         rule InlineDataFull_values() -> Vec<Option<Term>> = "(" _ v:InlineDataFull_value()* _ ")" _ { v }
@@ -949,14 +1373,14 @@ parser! {
         rule Constraint() -> Expression = BrackettedExpression() / FunctionCall() / BuiltInCall()
 
         //[70]
-        rule FunctionCall() -> Expression = f: iri() _ a: ArgList() {
-            Expression::FunctionCall(Function::Custom(f), a)
+        rule FunctionCall() -> Expression = f: iri() _ a: ArgList() {?
+            state.new_custom_function_call(f, a.0, a.1)
         }
 
         //[71]
-        rule ArgList() -> Vec<Expression> = //TODO: support DISTINCT
-            "(" _ i("DISTINCT")? _ e:ArgList_item() **<1,> ("," _) _ ")" { e } /
-            NIL() { Vec::new() }
+        rule ArgList() -> (bool, Vec<Expression>) =
+            "(" _ d:$(i("DISTINCT"))? _ e:ArgList_item() **<1,> ("," _) _ ")" { (d.is_some(), e) } /
+            NIL() { (false, Vec::new()) }
         rule ArgList_item() -> Expression = e:Expression() _ { e }
 
         //[72]
@@ -1263,8 +1687,22 @@ parser! {
         //[106]
         rule VarOrTerm() -> TermOrVariable =
             v:Var() { v.into() } /
+            t:QuotedTP() { t.into() } /
             t:GraphTerm() { t.into() }
 
+        // SPARQL-star quoted triple patterns
+        rule QuotedTP() -> TriplePattern = "<<" _ s:QtSubjectOrObject() _ p:Verb() _ o:QtSubjectOrObject() _ ">>" {
+            TriplePattern::new(s, p, o)
+        }
+        rule QtSubjectOrObject() -> TermOrVariable =
+            v:Var() { v.into() } /
+            b:BlankNode() { b.into() } /
+            i:iri() { i.into() } /
+            l:RDFLiteral() { l.into() } /
+            l:NumericLiteral() { l.into() } /
+            l:BooleanLiteral() { l.into() } /
+            t:QuotedTP() { t.into() }
+
         //[107]
         rule VarOrIri() -> NamedNodeOrVariable =
             v:Var() { v.into() } /
@@ -1462,14 +1900,14 @@ parser! {
             i("SAMPLE") _ "(" _ e:Expression() _ ")" { Aggregation::Sample(Box::new(e), false) } /
             i("GROUP_CONCAT") _ "(" _ i("DISTINCT") _ e:Expression() _ ";" _ i("SEPARATOR") _ "=" _ s:String() _ ")" { Aggregation::GroupConcat(Box::new(e), true, Some(s)) } /
             i("GROUP_CONCAT") _ "(" _ i("DISTINCT") _ e:Expression() _ ")" { Aggregation::GroupConcat(Box::new(e), true, None) } /
-            i("GROUP_CONCAT") _ "(" _ e:Expression() _ ";" _ i("SEPARATOR") _ "=" _ s:String() _ ")" { Aggregation::GroupConcat(Box::new(e), true, Some(s)) } /
+            i("GROUP_CONCAT") _ "(" _ e:Expression() _ ";" _ i("SEPARATOR") _ "=" _ s:String() _ ")" { Aggregation::GroupConcat(Box::new(e), false, Some(s)) } /
             i("GROUP_CONCAT") _ "(" _ e:Expression() _ ")" { Aggregation::GroupConcat(Box::new(e), false, None) }
 
         //[128]
-        rule iriOrFunction() -> Expression = i: iri() _ a: ArgList()? {
+        rule iriOrFunction() -> Expression = i: iri() _ a: ArgList()? {?
             match a {
-                Some(a) => Expression::FunctionCall(Function::Custom(i), a),
-                None => i.into()
+                Some((distinct, a)) => state.new_custom_function_call(i, distinct, a),
+                None => Ok(i.into())
             }
         }
 
@@ -1686,7 +2124,15 @@ parser! {
         rule _() = quiet! { ([' ' | '\t' | '\n' | '\r'] / comment())* }
 
         //comment
-        rule comment() = quiet! { ['#'] (!['\r' | '\n'] [_])* }
+        rule comment() = quiet! { pragma() / ['#'] (!['\r' | '\n'] [_])* }
+
+        rule pragma() = "#pragma" [' ' | '\t']+ p:$((!['\r' | '\n'] [_])*) {?
+            if state.hints.apply_pragma(p) {
+                Ok(())
+            } else {
+                Err("supported pragma")
+            }
+        }
 
         rule i(literal: &'static str) = input: $([_]*<{literal.len()}>) {?
             if input.eq_ignore_ascii_case(literal) {
@@ -1,13 +1,18 @@
+use crate::model::NamedNode;
+use crate::sparql::algebra::DatasetSpec;
+use crate::sparql::geo::Envelope;
 use crate::sparql::model::Variable;
 use crate::sparql::GraphPattern;
 use crate::store::numeric_encoder::{
     EncodedQuad, EncodedTerm, Encoder, MemoryStrStore, StrContainer, StrHash, StrLookup,
     ENCODED_DEFAULT_GRAPH,
 };
-use crate::store::ReadableEncodedStore;
+use crate::store::{PredicateStatistics, ReadableEncodedStore, StoreStatistics};
 use crate::Result;
 use std::cell::{RefCell, RefMut};
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashSet};
+use std::iter::empty;
+use std::sync::Arc;
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum PlanNode {
@@ -28,6 +33,8 @@ pub enum PlanNode {
         predicate: PatternValue,
         object: PatternValue,
         graph_name: PatternValue,
+        /// The maximal number of quads read from the store, set if only a slice of the solutions is consumed
+        limit: Option<usize>,
     },
     PathPatternJoin {
         child: Box<PlanNode>,
@@ -36,10 +43,53 @@ pub enum PlanNode {
         object: PatternValue,
         graph_name: PatternValue,
     },
+    /// Binds `literal` to the string literals containing all the words of `query` that are the object of a quad of `graph_name`
+    FullTextSearch {
+        child: Box<PlanNode>,
+        literal: PatternValue,
+        query: PatternValue,
+        graph_name: PatternValue,
+    },
+    /// Binds `literal` to the WKT literals of the store whose bounding box is within `envelope` or, if `within` is false, intersects it
+    ///
+    /// It is only a pre-selection using the store spatial index: the exact geometry filter should still be applied.
+    SpatialSearch {
+        child: Box<PlanNode>,
+        literal: PatternValue,
+        envelope: Envelope,
+        within: bool,
+    },
+    /// Binds a quoted triple to its subject, predicate and object
+    ///
+    /// If `triple` is bound, its components are unified with `subject`, `predicate` and `object`.
+    /// If not, it is bound to the quoted triple built from them.
+    QuotedTriplePattern {
+        child: Box<PlanNode>,
+        triple: PatternValue,
+        subject: PatternValue,
+        predicate: PatternValue,
+        object: PatternValue,
+    },
+    /// Triple pattern of the graph `graph_name` whose predicate is a property function registered in the query options
+    PropertyFunction {
+        child: Box<PlanNode>,
+        subject: PatternValue,
+        function: NamedNode,
+        object: PatternValue,
+        graph_name: PatternValue,
+    },
     Join {
         left: Box<PlanNode>,
         right: Box<PlanNode>,
     },
+    /// Join evaluated by loading the right solutions in a hash table indexed by the values of the `keys` variables
+    ///
+    /// The `keys` variables must be always bound on both sides.
+    HashJoin {
+        left: Box<PlanNode>,
+        right: Box<PlanNode>,
+        keys: Vec<usize>,
+    },
     AntiJoin {
         left: Box<PlanNode>,
         right: Box<PlanNode>,
@@ -79,12 +129,14 @@ pub enum PlanNode {
     Project {
         child: Box<PlanNode>,
         mapping: Vec<(usize, usize)>, // pairs of (variable key in child, variable key in output)
+        child_variables: Vec<Variable>,
     },
     Aggregate {
         // By definition the group by key are the range 0..key_mapping.len()
         child: Box<PlanNode>,
         key_mapping: Vec<(usize, usize)>, // aggregate key pairs of (variable key in child, variable key in output)
         aggregates: Vec<(PlanAggregation, usize)>,
+        child_variables: Vec<Variable>,
     },
 }
 
@@ -114,6 +166,7 @@ impl PlanNode {
                 predicate,
                 object,
                 graph_name,
+                ..
             } => {
                 if let PatternValue::Variable(var) = subject {
                     set.insert(*var);
@@ -147,6 +200,53 @@ impl PlanNode {
                 }
                 child.add_maybe_bound_variables(set);
             }
+            PlanNode::FullTextSearch {
+                child,
+                literal,
+                query,
+                graph_name,
+            } => {
+                for value in &[literal, query, graph_name] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_maybe_bound_variables(set);
+            }
+            PlanNode::SpatialSearch { child, literal, .. } => {
+                if let PatternValue::Variable(var) = literal {
+                    set.insert(*var);
+                }
+                child.add_maybe_bound_variables(set);
+            }
+            PlanNode::QuotedTriplePattern {
+                child,
+                triple,
+                subject,
+                predicate,
+                object,
+            } => {
+                for value in &[triple, subject, predicate, object] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_maybe_bound_variables(set);
+            }
+            PlanNode::PropertyFunction {
+                child,
+                subject,
+                object,
+                graph_name,
+                ..
+            } => {
+                for value in &[subject, object, graph_name] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_maybe_bound_variables(set);
+            }
             PlanNode::Filter { child, expression } => {
                 expression.add_maybe_bound_variables(set);
                 child.add_maybe_bound_variables(set);
@@ -157,6 +257,7 @@ impl PlanNode {
                 }
             }
             PlanNode::Join { left, right, .. }
+            | PlanNode::HashJoin { left, right, .. }
             | PlanNode::AntiJoin { left, right, .. }
             | PlanNode::LeftJoin { left, right, .. } => {
                 left.add_maybe_bound_variables(set);
@@ -176,7 +277,7 @@ impl PlanNode {
             | PlanNode::HashDeduplicate { child }
             | PlanNode::Skip { child, .. }
             | PlanNode::Limit { child, .. } => child.add_maybe_bound_variables(set),
-            PlanNode::Project { mapping, child } => {
+            PlanNode::Project { mapping, child, .. } => {
                 let child_bound = child.maybe_bound_variables();
                 for (child_i, output_i) in mapping.iter() {
                     if child_bound.contains(child_i) {
@@ -196,6 +297,130 @@ impl PlanNode {
             }
         }
     }
+
+    /// Returns variables that are bound in all the solutions of the result set
+    pub fn always_bound_variables(&self) -> BTreeSet<usize> {
+        let mut set = BTreeSet::default();
+        self.add_always_bound_variables(&mut set);
+        set
+    }
+
+    pub fn add_always_bound_variables(&self, set: &mut BTreeSet<usize>) {
+        match self {
+            PlanNode::Init
+            | PlanNode::Service { .. }
+            | PlanNode::Aggregate { .. }
+            | PlanNode::StaticBindings { .. } => (),
+            PlanNode::QuadPatternJoin {
+                child,
+                subject,
+                predicate,
+                object,
+                graph_name,
+                ..
+            } => {
+                for value in &[subject, predicate, object, graph_name] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_always_bound_variables(set);
+            }
+            PlanNode::PathPatternJoin {
+                child,
+                subject,
+                object,
+                graph_name,
+                ..
+            } => {
+                for value in &[subject, object, graph_name] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_always_bound_variables(set);
+            }
+            PlanNode::FullTextSearch {
+                child,
+                literal,
+                graph_name,
+                ..
+            } => {
+                for value in &[literal, graph_name] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_always_bound_variables(set);
+            }
+            PlanNode::SpatialSearch { child, literal, .. } => {
+                if let PatternValue::Variable(var) = literal {
+                    set.insert(*var);
+                }
+                child.add_always_bound_variables(set);
+            }
+            PlanNode::QuotedTriplePattern {
+                child,
+                triple,
+                subject,
+                predicate,
+                object,
+            } => {
+                for value in &[triple, subject, predicate, object] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_always_bound_variables(set);
+            }
+            PlanNode::PropertyFunction {
+                child,
+                subject,
+                object,
+                graph_name,
+                ..
+            } => {
+                for value in &[subject, object, graph_name] {
+                    if let PatternValue::Variable(var) = value {
+                        set.insert(*var);
+                    }
+                }
+                child.add_always_bound_variables(set);
+            }
+            PlanNode::Union { children } => {
+                let mut children = children.iter();
+                if let Some(first) = children.next() {
+                    let mut common = first.always_bound_variables();
+                    for child in children {
+                        let child_bound = child.always_bound_variables();
+                        common.retain(|var| child_bound.contains(var));
+                    }
+                    set.extend(common);
+                }
+            }
+            PlanNode::Join { left, right } | PlanNode::HashJoin { left, right, .. } => {
+                left.add_always_bound_variables(set);
+                right.add_always_bound_variables(set);
+            }
+            PlanNode::AntiJoin { left, .. } | PlanNode::LeftJoin { left, .. } => {
+                left.add_always_bound_variables(set)
+            }
+            PlanNode::Filter { child, .. }
+            | PlanNode::Extend { child, .. }
+            | PlanNode::Sort { child, .. }
+            | PlanNode::HashDeduplicate { child }
+            | PlanNode::Skip { child, .. }
+            | PlanNode::Limit { child, .. } => child.add_always_bound_variables(set),
+            PlanNode::Project { mapping, child, .. } => {
+                let child_bound = child.always_bound_variables();
+                for (child_i, output_i) in mapping.iter() {
+                    if child_bound.contains(child_i) {
+                        set.insert(*output_i);
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
@@ -313,6 +538,12 @@ pub enum PlanExpression {
     YearMonthDurationCast(Box<PlanExpression>),
     DayTimeDurationCast(Box<PlanExpression>),
     StringCast(Box<PlanExpression>),
+    GeoDistance(
+        Box<PlanExpression>,
+        Box<PlanExpression>,
+        Box<PlanExpression>,
+    ),
+    GeoWithin(Box<PlanExpression>, Box<PlanExpression>),
 }
 
 impl PlanExpression {
@@ -393,12 +624,14 @@ impl PlanExpression {
             | PlanExpression::StrLang(a, b)
             | PlanExpression::StrDT(a, b)
             | PlanExpression::SameTerm(a, b)
+            | PlanExpression::GeoWithin(a, b)
             | PlanExpression::SubStr(a, b, None)
             | PlanExpression::Regex(a, b, None) => {
                 a.add_maybe_bound_variables(set);
                 b.add_maybe_bound_variables(set);
             }
             PlanExpression::If(a, b, c)
+            | PlanExpression::GeoDistance(a, b, c)
             | PlanExpression::SubStr(a, b, Some(c))
             | PlanExpression::Regex(a, b, Some(c))
             | PlanExpression::Replace(a, b, c, None) => {
@@ -447,6 +680,7 @@ pub enum PlanAggregationFunction {
     Avg,
     Sample,
     GroupConcat { separator: String },
+    Custom(NamedNode),
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
@@ -467,18 +701,19 @@ pub enum Comparator {
     Desc(PlanExpression),
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub struct TripleTemplate {
     pub subject: TripleTemplateValue,
     pub predicate: TripleTemplateValue,
     pub object: TripleTemplateValue,
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
 pub enum TripleTemplateValue {
     Constant(EncodedTerm),
     BlankNode(usize),
     Variable(usize),
+    Triple(Box<TripleTemplate>),
 }
 
 #[derive(Eq, PartialEq, Debug, Clone, Hash)]
@@ -561,69 +796,324 @@ pub(crate) struct DatasetView<S: ReadableEncodedStore> {
     store: S,
     extra: RefCell<MemoryStrStore>,
     default_graph_as_union: bool,
+    /// The graphs merged into the default graph if the dataset is given by FROM clauses
+    default: Option<Vec<EncodedTerm>>,
+    /// The graphs visible as named graphs if the dataset is given by FROM NAMED clauses
+    named: Option<Vec<EncodedTerm>>,
 }
 
 impl<S: ReadableEncodedStore> DatasetView<S> {
-    pub fn new(store: S, default_graph_as_union: bool) -> Self {
-        Self {
+    pub fn new(store: S, default_graph_as_union: bool, dataset: &DatasetSpec) -> Result<Self> {
+        let mut view = Self {
             store,
             extra: RefCell::new(MemoryStrStore::default()),
             default_graph_as_union,
+            default: None,
+            named: None,
+        };
+        if !dataset.is_empty() {
+            // A dataset without FROM NAMED clause has no named graphs and one without FROM clause an empty default graph
+            let (default, named) = {
+                let mut encoder = view.encoder();
+                (
+                    encode_graphs(&mut encoder, &dataset.default)?,
+                    encode_graphs(&mut encoder, &dataset.named)?,
+                )
+            };
+            view.default = Some(default);
+            view.named = Some(named);
         }
+        Ok(view)
     }
 
-    pub fn quads_for_pattern<'a>(
+    /// Returns the quads matching the pattern, at most `limit` ones if it is set
+    pub fn quads_for_pattern_with_limit<'a>(
         &'a self,
         subject: Option<EncodedTerm>,
         predicate: Option<EncodedTerm>,
         object: Option<EncodedTerm>,
         graph_name: Option<EncodedTerm>,
+        limit: Option<usize>,
     ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
-        if graph_name == None {
-            Box::new(
-                self.store
-                    .encoded_quads_for_pattern(subject, predicate, object, None)
-                    .filter(|quad| match quad {
-                        Err(_) => true,
-                        Ok(quad) => quad.graph_name != ENCODED_DEFAULT_GRAPH,
-                    }),
-            )
-        } else if graph_name == Some(ENCODED_DEFAULT_GRAPH) && self.default_graph_as_union {
-            Box::new(
-                self.store
-                    .encoded_quads_for_pattern(subject, predicate, object, None)
-                    .map(|quad| {
-                        let quad = quad?;
-                        Ok(EncodedQuad::new(
-                            quad.subject,
-                            quad.predicate,
-                            quad.object,
-                            ENCODED_DEFAULT_GRAPH,
-                        ))
-                    }),
-            )
+        let quads: Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> = match graph_name {
+            None => {
+                if let Some(named) = &self.named {
+                    Box::new(named.iter().flat_map(move |graph_name| {
+                        self.store.encoded_quads_for_pattern(
+                            subject,
+                            predicate,
+                            object,
+                            Some(*graph_name),
+                        )
+                    }))
+                } else {
+                    Box::new(
+                        self.store
+                            .encoded_quads_for_pattern(subject, predicate, object, None)
+                            .filter(|quad| match quad {
+                                Err(_) => true,
+                                Ok(quad) => quad.graph_name != ENCODED_DEFAULT_GRAPH,
+                            }),
+                    )
+                }
+            }
+            Some(EncodedTerm::DefaultGraph) => {
+                if let Some(default) = &self.default {
+                    if let [graph_name] = default.as_slice() {
+                        Box::new(
+                            self.store
+                                .encoded_quads_for_pattern(
+                                    subject,
+                                    predicate,
+                                    object,
+                                    Some(*graph_name),
+                                )
+                                .map(|quad| {
+                                    let quad = quad?;
+                                    Ok(EncodedQuad::new(
+                                        quad.subject,
+                                        quad.predicate,
+                                        quad.object,
+                                        ENCODED_DEFAULT_GRAPH,
+                                    ))
+                                }),
+                        )
+                    } else {
+                        merge_graphs(default.iter().flat_map(move |graph_name| {
+                            self.store.encoded_quads_for_pattern(
+                                subject,
+                                predicate,
+                                object,
+                                Some(*graph_name),
+                            )
+                        }))
+                    }
+                } else if self.default_graph_as_union {
+                    merge_sorted_graphs(
+                        self.store
+                            .encoded_quads_for_pattern(subject, predicate, object, None),
+                    )
+                } else {
+                    return self
+                        .store_quads_for_pattern(subject, predicate, object, graph_name, limit);
+                }
+            }
+            Some(graph_name) => {
+                if self
+                    .named
+                    .as_ref()
+                    .map_or(false, |named| !named.contains(&graph_name))
+                {
+                    Box::new(empty())
+                } else {
+                    return self.store_quads_for_pattern(
+                        subject,
+                        predicate,
+                        object,
+                        Some(graph_name),
+                        limit,
+                    );
+                }
+            }
+        };
+        if let Some(limit) = limit {
+            Box::new(quads.take(limit))
+        } else {
+            quads
+        }
+    }
+
+    fn store_quads_for_pattern<'a>(
+        &'a self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: Option<usize>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+        if let Some(limit) = limit {
+            self.store
+                .encoded_quads_for_pattern_with_limit(subject, predicate, object, graph_name, limit)
         } else {
             self.store
                 .encoded_quads_for_pattern(subject, predicate, object, graph_name)
         }
     }
 
+    /// Returns the names of the named graphs of the dataset
+    pub fn named_graphs<'a>(&'a self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        if let Some(named) = &self.named {
+            Box::new(named.iter().map(|graph_name| Ok(*graph_name)))
+        } else {
+            self.store.encoded_named_graphs()
+        }
+    }
+
+    pub fn literals_matching<'a>(
+        &'a self,
+        query: &str,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        self.store.encoded_literals_matching(query)
+    }
+
+    pub fn geometries_in<'a>(
+        &'a self,
+        envelope: Envelope,
+        within: bool,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        self.store.encoded_geometries_in(envelope, within)
+    }
+
     pub fn encoder<'a>(&'a self) -> impl Encoder + StrContainer + 'a {
         DatasetViewStrContainer {
             store: &self.store,
             extra: self.extra.borrow_mut(),
         }
     }
+
+    /// Returns the number of quads in the graphs of the dataset if it is given by FROM and FROM NAMED clauses
+    fn dataset_quads_count(&self) -> Option<u64> {
+        let default = self.default.as_ref()?;
+        let named = self.named.as_ref()?;
+        default
+            .iter()
+            .chain(
+                named
+                    .iter()
+                    .filter(|graph_name| !default.contains(graph_name)),
+            )
+            .map(|graph_name| self.store.graph_quads_count(*graph_name))
+            .sum()
+    }
+}
+
+fn encode_graphs(encoder: &mut impl Encoder, graphs: &[NamedNode]) -> Result<Vec<EncodedTerm>> {
+    let mut encoded = Vec::with_capacity(graphs.len());
+    for graph in graphs {
+        let graph = encoder.encode_named_node(graph)?;
+        if !encoded.contains(&graph) {
+            encoded.push(graph);
+        }
+    }
+    Ok(encoded)
+}
+
+/// Merges quads into the default graph
+///
+/// The merged graph is a set: the triples present in multiple graphs are returned once.
+/// The already returned triples are kept in memory, see `merge_sorted_graphs` for the union of all the graphs.
+fn merge_graphs<'a>(
+    quads: impl Iterator<Item = Result<EncodedQuad>> + 'a,
+) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+    let mut already_seen = HashSet::new();
+    Box::new(quads.filter_map(move |quad| match quad {
+        Ok(quad) => {
+            if already_seen.insert((quad.subject, quad.predicate, quad.object)) {
+                Some(Ok(EncodedQuad::new(
+                    quad.subject,
+                    quad.predicate,
+                    quad.object,
+                    ENCODED_DEFAULT_GRAPH,
+                )))
+            } else {
+                None
+            }
+        }
+        Err(error) => Some(Err(error)),
+    }))
+}
+
+/// Merges into the default graph quads where the ones with the same triple are next to each other
+///
+/// It is the case of the quads returned by `encoded_quads_for_pattern` without graph name:
+/// a duplicated triple is detected by comparing it with the previous one.
+fn merge_sorted_graphs<'a>(
+    quads: impl Iterator<Item = Result<EncodedQuad>> + 'a,
+) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+    let mut previous = None;
+    Box::new(quads.filter_map(move |quad| match quad {
+        Ok(quad) => {
+            let triple = Some((quad.subject, quad.predicate, quad.object));
+            if previous == triple {
+                None
+            } else {
+                previous = triple;
+                Some(Ok(EncodedQuad::new(
+                    quad.subject,
+                    quad.predicate,
+                    quad.object,
+                    ENCODED_DEFAULT_GRAPH,
+                )))
+            }
+        }
+        Err(error) => Some(Err(error)),
+    }))
+}
+
+impl<S: ReadableEncodedStore> StoreStatistics for DatasetView<S> {
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        let statistics = self.store.predicate_statistics(predicate)?;
+        // The dataset could not contain more quads than the graphs given by FROM and FROM NAMED clauses
+        Some(match self.dataset_quads_count() {
+            Some(count) => PredicateStatistics {
+                quads_count: statistics.quads_count.min(count),
+                distinct_subjects_count: statistics.distinct_subjects_count.min(count),
+                distinct_objects_count: statistics.distinct_objects_count.min(count),
+            },
+            None => statistics,
+        })
+    }
+
+    fn literals_matching_count(&self, query: &str) -> Option<u64> {
+        self.store.literals_matching_count(query)
+    }
+
+    fn geometries_in_count(&self, envelope: &Envelope, within: bool) -> Option<u64> {
+        self.store.geometries_in_count(envelope, within)
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        if graph_name == ENCODED_DEFAULT_GRAPH {
+            // The default graph of the dataset might be built from other graphs of the store
+            if let Some(default) = &self.default {
+                default
+                    .iter()
+                    .map(|graph_name| self.store.graph_quads_count(*graph_name))
+                    .sum()
+            } else if self.default_graph_as_union {
+                None
+            } else {
+                self.store.graph_quads_count(graph_name)
+            }
+        } else if self
+            .named
+            .as_ref()
+            .map_or(false, |named| !named.contains(&graph_name))
+        {
+            // The graph is not part of the dataset
+            Some(0)
+        } else {
+            self.store.graph_quads_count(graph_name)
+        }
+    }
 }
 
 impl<S: ReadableEncodedStore> StrLookup for DatasetView<S> {
-    fn get_str(&self, id: StrHash) -> Result<Option<String>> {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
         if let Some(value) = self.extra.borrow().get_str(id)? {
             Ok(Some(value))
         } else {
             self.store.get_str(id)
         }
     }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        if let Some(value) = self.extra.borrow().get_shared_str(id)? {
+            Ok(Some(value))
+        } else {
+            self.store.get_shared_str(id)
+        }
+    }
 }
 
 struct DatasetViewStrContainer<'a, S: ReadableEncodedStore> {
@@ -632,7 +1122,7 @@ struct DatasetViewStrContainer<'a, S: ReadableEncodedStore> {
 }
 
 impl<'a, S: ReadableEncodedStore> StrContainer for DatasetViewStrContainer<'a, S> {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
         if self.store.get_str(key)?.is_none() {
             self.extra.insert_str(key, value)
         } else {
@@ -1,20 +1,48 @@
-use crate::model::{BlankNode, Term};
+use crate::model::vocab::xsd;
+use crate::model::{BlankNode, Literal, NamedNode, Term};
 use crate::sparql::algebra::*;
+use crate::sparql::geo::{self, Envelope, Geometry};
 use crate::sparql::model::*;
 use crate::sparql::plan::*;
-use crate::store::numeric_encoder::{Encoder, ENCODED_DEFAULT_GRAPH};
+use crate::store::numeric_encoder::{EncodedTerm, Encoder, ENCODED_DEFAULT_GRAPH};
+use crate::store::{PredicateStatistics, StoreStatistics};
 use crate::Error;
 use crate::Result;
 use std::collections::{BTreeSet, HashSet};
 
-pub struct PlanBuilder<E: Encoder> {
+/// Predicate of the full-text search patterns
+///
+/// `?literal ox:matches "some words"` matches the string literals of the store containing all the given words.
+pub(crate) const FULL_TEXT_MATCHES: &str = "http://oxigraph.org/ns#matches";
+
+pub struct PlanBuilder<'a, E: Encoder> {
     encoder: E,
+    statistics: Option<&'a dyn StoreStatistics>,
+    property_functions: &'a HashSet<NamedNode>,
+    hints: &'a QueryHints,
 }
 
-impl<E: Encoder> PlanBuilder<E> {
-    pub fn build(encoder: E, pattern: &GraphPattern) -> Result<(PlanNode, Vec<Variable>)> {
+impl<'a, E: Encoder> PlanBuilder<'a, E> {
+    /// Builds the evaluation plan of a graph pattern
+    ///
+    /// The statistics are used to choose the evaluation order of the triple patterns.
+    /// The triple patterns with a predicate in `property_functions` are evaluated by the matching property function.
+    /// The `hints` given in the query restrict the optimizations.
+    pub fn build(
+        encoder: E,
+        statistics: &'a dyn StoreStatistics,
+        property_functions: &'a HashSet<NamedNode>,
+        hints: &'a QueryHints,
+        pattern: &GraphPattern,
+    ) -> Result<(PlanNode, Vec<Variable>)> {
         let mut variables = Vec::default();
-        let plan = PlanBuilder { encoder }.build_for_graph_pattern(
+        let plan = PlanBuilder {
+            encoder,
+            statistics: Some(statistics),
+            property_functions,
+            hints,
+        }
+        .build_for_graph_pattern(
             pattern,
             &mut variables,
             PatternValue::Constant(ENCODED_DEFAULT_GRAPH),
@@ -27,7 +55,13 @@ impl<E: Encoder> PlanBuilder<E> {
         template: &[TriplePattern],
         mut variables: Vec<Variable>,
     ) -> Result<Vec<TripleTemplate>> {
-        PlanBuilder { encoder }.build_for_graph_template(template, &mut variables)
+        PlanBuilder {
+            encoder,
+            statistics: None,
+            property_functions: &HashSet::default(),
+            hints: &QueryHints::default(),
+        }
+        .build_for_graph_template(template, &mut variables)
     }
 
     fn build_for_graph_pattern(
@@ -37,11 +71,12 @@ impl<E: Encoder> PlanBuilder<E> {
         graph_name: PatternValue,
     ) -> Result<PlanNode> {
         Ok(match pattern {
-            GraphPattern::BGP(p) => self.build_for_bgp(p, variables, graph_name)?,
-            GraphPattern::Join(a, b) => PlanNode::Join {
-                left: Box::new(self.build_for_graph_pattern(a, variables, graph_name)?),
-                right: Box::new(self.build_for_graph_pattern(b, variables, graph_name)?),
-            },
+            GraphPattern::BGP(p) => self.build_for_bgp(p, None, variables, graph_name)?,
+            GraphPattern::Join(a, b) => {
+                let left = self.build_for_graph_pattern(a, variables, graph_name)?;
+                let right = self.build_for_graph_pattern(b, variables, graph_name)?;
+                new_join(left, right, !self.hints.hash_join_disabled)
+            }
             GraphPattern::LeftJoin(a, b, e) => {
                 let left = self.build_for_graph_pattern(a, variables, graph_name)?;
                 let right = self.build_for_graph_pattern(b, variables, graph_name)?;
@@ -66,7 +101,12 @@ impl<E: Encoder> PlanBuilder<E> {
                 }
             }
             GraphPattern::Filter(e, p) => PlanNode::Filter {
-                child: Box::new(self.build_for_graph_pattern(p, variables, graph_name)?),
+                child: Box::new(if let GraphPattern::BGP(p) = &**p {
+                    // The filter might allow to pre-select the BGP solutions with the spatial index
+                    self.build_for_bgp(p, Some(e), variables, graph_name)?
+                } else {
+                    self.build_for_graph_pattern(p, variables, graph_name)?
+                }),
                 expression: self.build_for_expression(e, variables, graph_name)?,
             },
             GraphPattern::Union(a, b) => {
@@ -116,31 +156,31 @@ impl<E: Encoder> PlanBuilder<E> {
                 let mut inner_variables = key.clone();
                 let inner_graph_name =
                     self.convert_pattern_value_id(graph_name, variables, &mut inner_variables);
-
+                let child =
+                    self.build_for_graph_pattern(p, &mut inner_variables, inner_graph_name)?;
+                let key_mapping = key
+                    .iter()
+                    .map(|k| {
+                        (
+                            variable_key(&mut inner_variables, k),
+                            variable_key(variables, k),
+                        )
+                    })
+                    .collect();
+                let aggregates = aggregates
+                    .iter()
+                    .map(|(a, v)| {
+                        Ok((
+                            self.build_for_aggregate(a, &mut inner_variables, graph_name)?,
+                            variable_key(variables, v),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
                 PlanNode::Aggregate {
-                    child: Box::new(self.build_for_graph_pattern(
-                        p,
-                        &mut inner_variables,
-                        inner_graph_name,
-                    )?),
-                    key_mapping: key
-                        .iter()
-                        .map(|k| {
-                            (
-                                variable_key(&mut inner_variables, k),
-                                variable_key(variables, k),
-                            )
-                        })
-                        .collect(),
-                    aggregates: aggregates
-                        .iter()
-                        .map(|(a, v)| {
-                            Ok((
-                                self.build_for_aggregate(a, &mut inner_variables, graph_name)?,
-                                variable_key(variables, v),
-                            ))
-                        })
-                        .collect::<Result<Vec<_>>>()?,
+                    child: Box::new(child),
+                    key_mapping,
+                    aggregates,
+                    child_variables: inner_variables,
                 }
             }
             GraphPattern::Data(bs) => PlanNode::StaticBindings {
@@ -167,12 +207,10 @@ impl<E: Encoder> PlanBuilder<E> {
                 let mut inner_variables = new_variables.clone();
                 let inner_graph_name =
                     self.convert_pattern_value_id(graph_name, variables, &mut inner_variables);
+                let child =
+                    self.build_for_graph_pattern(l, &mut inner_variables, inner_graph_name)?;
                 PlanNode::Project {
-                    child: Box::new(self.build_for_graph_pattern(
-                        l,
-                        &mut inner_variables,
-                        inner_graph_name,
-                    )?),
+                    child: Box::new(child),
                     mapping: new_variables
                         .iter()
                         .enumerate()
@@ -180,6 +218,7 @@ impl<E: Encoder> PlanBuilder<E> {
                             (new_variable, variable_key(variables, variable))
                         })
                         .collect(),
+                    child_variables: inner_variables,
                 }
             }
             GraphPattern::Distinct(l) => PlanNode::HashDeduplicate {
@@ -188,6 +227,9 @@ impl<E: Encoder> PlanBuilder<E> {
             GraphPattern::Reduced(l) => self.build_for_graph_pattern(l, variables, graph_name)?,
             GraphPattern::Slice(l, start, length) => {
                 let mut plan = self.build_for_graph_pattern(l, variables, graph_name)?;
+                if let Some(length) = length {
+                    push_down_limit(&mut plan, start + length);
+                }
                 if *start > 0 {
                     plan = PlanNode::Skip {
                         child: Box::new(plan),
@@ -205,35 +247,242 @@ impl<E: Encoder> PlanBuilder<E> {
         })
     }
 
-    fn build_for_bgp(
+    /// Builds the plan of a BGP
+    ///
+    /// If the BGP is filtered by `filter`, its solutions might be pre-selected using the store spatial index.
+    /// The filter itself is not applied.
+    fn build_for_bgp<'p>(
         &mut self,
-        p: &[TripleOrPathPattern],
+        p: &'p [TripleOrPathPattern],
+        filter: Option<&'p Expression>,
         variables: &mut Vec<Variable>,
         graph_name: PatternValue,
     ) -> Result<PlanNode> {
         let mut plan = PlanNode::Init;
-        for pattern in sort_bgp(p) {
-            plan = match pattern {
-                TripleOrPathPattern::Triple(pattern) => PlanNode::QuadPatternJoin {
-                    child: Box::new(plan),
-                    subject: self
-                        .pattern_value_from_term_or_variable(&pattern.subject, variables)?,
-                    predicate: self
-                        .pattern_value_from_named_node_or_variable(&pattern.predicate, variables)?,
-                    object: self.pattern_value_from_term_or_variable(&pattern.object, variables)?,
+        let mut assigned_variables = HashSet::default();
+        let mut assigned_blank_nodes = HashSet::default();
+        // The estimated number of solutions of the current plan
+        let mut plan_cardinality = Some(1);
+        let property_functions = self.property_functions;
+        if let Some((variable, envelope, within, count)) =
+            filter.and_then(|filter| self.spatial_search(p, filter, graph_name))
+        {
+            plan = PlanNode::SpatialSearch {
+                child: Box::new(plan),
+                literal: PatternValue::Variable(variable_key(variables, variable)),
+                envelope,
+                within,
+            };
+            assigned_variables.insert(variable);
+            plan_cardinality = Some(count);
+        }
+        let patterns = if self.hints.fixed_join_order {
+            p.iter().collect()
+        } else {
+            sort_bgp(
+                p,
+                graph_name,
+                self.statistics,
+                property_functions,
+                assigned_variables.clone(),
+            )
+        };
+        for pattern in patterns {
+            let estimations = self.statistics.and_then(|statistics| {
+                Some((
+                    estimate_pattern_cardinality(
+                        pattern,
+                        graph_name,
+                        statistics,
+                        property_functions,
+                        &assigned_variables,
+                        &assigned_blank_nodes,
+                    )?,
+                    estimate_pattern_cardinality(
+                        pattern,
+                        graph_name,
+                        statistics,
+                        property_functions,
+                        &HashSet::default(),
+                        &HashSet::default(),
+                    )?,
+                ))
+            });
+            let use_hash_join = match (&plan, pattern, plan_cardinality, estimations) {
+                _ if self.hints.hash_join_disabled => false,
+                (PlanNode::Init, _, _, _) => false,
+                (_, TripleOrPathPattern::Triple(triple), _, _)
+                    if is_full_text_search(triple)
+                        || is_property_function(triple, property_functions) =>
+                {
+                    false
+                }
+                (_, _, Some(plan_cardinality), Some((_, scan_cardinality))) => {
+                    is_hash_join_cheaper(plan_cardinality, scan_cardinality)
+                }
+                _ => false,
+            };
+            plan_cardinality =
+                plan_cardinality.and_then(|c| Some(c.saturating_mul(estimations?.0)));
+            add_pattern_variables(pattern, &mut assigned_variables, &mut assigned_blank_nodes);
+            plan = if use_hash_join {
+                // The pattern is evaluated alone and joined with the previous ones
+                let right =
+                    self.build_for_bgp_pattern(pattern, PlanNode::Init, variables, graph_name)?;
+                new_hash_join(plan, right)
+            } else {
+                self.build_for_bgp_pattern(pattern, plan, variables, graph_name)?
+            };
+        }
+        Ok(plan)
+    }
+
+    /// Returns the spatial index pre-selection of the BGP solutions allowed by a filter if it is worth it
+    ///
+    /// The pre-selection binds a variable used as object in the BGP to the WKT literals whose bounding box is within
+    /// the returned envelope or, if the returned boolean is false, intersects it.
+    /// It is only used if the store knows the number of pre-selected literals and if they are fewer than the
+    /// estimated results of any of the BGP patterns.
+    fn spatial_search<'p>(
+        &self,
+        p: &'p [TripleOrPathPattern],
+        filter: &'p Expression,
+        graph_name: PatternValue,
+    ) -> Option<(&'p Variable, Envelope, bool, u64)> {
+        if self.hints.fixed_join_order {
+            return None;
+        }
+        let statistics = self.statistics?;
+        let (variable, envelope, within) = spatial_filter(filter)?;
+        if !p.iter().any(|pattern| match pattern.object() {
+            TermOrVariable::Variable(v) => v == variable,
+            _ => false,
+        }) {
+            return None;
+        }
+        let count = statistics.geometries_in_count(&envelope, within)?;
+        let pattern_cardinality = p
+            .iter()
+            .filter_map(|pattern| {
+                estimate_pattern_cardinality(
+                    pattern,
                     graph_name,
-                },
-                TripleOrPathPattern::Path(pattern) => PlanNode::PathPatternJoin {
-                    child: Box::new(plan),
-                    subject: self
-                        .pattern_value_from_term_or_variable(&pattern.subject, variables)?,
-                    path: self.build_for_path(&pattern.path)?,
-                    object: self.pattern_value_from_term_or_variable(&pattern.object, variables)?,
+                    statistics,
+                    self.property_functions,
+                    &HashSet::default(),
+                    &HashSet::default(),
+                )
+            })
+            .min();
+        if pattern_cardinality.map_or(true, |c| count < c) {
+            Some((variable, envelope, within, count))
+        } else {
+            None
+        }
+    }
+
+    fn build_for_bgp_pattern(
+        &mut self,
+        pattern: &TripleOrPathPattern,
+        plan: PlanNode,
+        variables: &mut Vec<Variable>,
+        graph_name: PatternValue,
+    ) -> Result<PlanNode> {
+        let mut quoted_triples = Vec::default();
+        let mut node = match pattern {
+            TripleOrPathPattern::Triple(pattern) if is_full_text_search(pattern) => {
+                PlanNode::FullTextSearch {
+                    child: Box::new(PlanNode::Init),
+                    literal: self.pattern_value_from_term_or_variable(
+                        &pattern.subject,
+                        variables,
+                        &mut quoted_triples,
+                    )?,
+                    query: self.pattern_value_from_term_or_variable(
+                        &pattern.object,
+                        variables,
+                        &mut quoted_triples,
+                    )?,
                     graph_name,
-                },
+                }
+            }
+            TripleOrPathPattern::Triple(TriplePattern {
+                subject,
+                predicate: NamedNodeOrVariable::NamedNode(function),
+                object,
+            }) if self.property_functions.contains(function) => PlanNode::PropertyFunction {
+                child: Box::new(PlanNode::Init),
+                subject: self.pattern_value_from_term_or_variable(
+                    subject,
+                    variables,
+                    &mut quoted_triples,
+                )?,
+                function: function.clone(),
+                object: self.pattern_value_from_term_or_variable(
+                    object,
+                    variables,
+                    &mut quoted_triples,
+                )?,
+                graph_name,
+            },
+            TripleOrPathPattern::Triple(pattern) => PlanNode::QuadPatternJoin {
+                child: Box::new(PlanNode::Init),
+                subject: self.pattern_value_from_term_or_variable(
+                    &pattern.subject,
+                    variables,
+                    &mut quoted_triples,
+                )?,
+                predicate: self
+                    .pattern_value_from_named_node_or_variable(&pattern.predicate, variables)?,
+                object: self.pattern_value_from_term_or_variable(
+                    &pattern.object,
+                    variables,
+                    &mut quoted_triples,
+                )?,
+                graph_name,
+                limit: None,
+            },
+            TripleOrPathPattern::Path(pattern) => PlanNode::PathPatternJoin {
+                child: Box::new(PlanNode::Init),
+                subject: self.pattern_value_from_term_or_variable(
+                    &pattern.subject,
+                    variables,
+                    &mut quoted_triples,
+                )?,
+                path: self.build_for_path(&pattern.path)?,
+                object: self.pattern_value_from_term_or_variable(
+                    &pattern.object,
+                    variables,
+                    &mut quoted_triples,
+                )?,
+                graph_name,
+            },
+        };
+
+        // The quoted triples with all their terms already bound are built before the pattern to be used as constants.
+        // The other ones are decomposed after it, the outer ones first.
+        // The inner quoted triples are always before the outer ones in `quoted_triples`.
+        let mut plan = plan;
+        let mut bound_variables = plan.always_bound_variables();
+        let mut to_decompose = Vec::new();
+        for quoted_triple in quoted_triples {
+            if quoted_triple.terms().iter().all(|value| match value {
+                PatternValue::Constant(_) => true,
+                PatternValue::Variable(v) => bound_variables.contains(v),
+            }) {
+                if let PatternValue::Variable(v) = quoted_triple.triple {
+                    bound_variables.insert(v);
+                }
+                plan = quoted_triple.into_plan(plan);
+            } else {
+                to_decompose.push(quoted_triple);
             }
         }
-        Ok(plan)
+        node = plug_pattern_chain(node, plan);
+        for quoted_triple in to_decompose.into_iter().rev() {
+            node = quoted_triple.into_plan(node);
+        }
+        Ok(node)
     }
 
     fn build_for_path(&mut self, path: &PropertyPath) -> Result<PlanPropertyPath> {
@@ -667,6 +916,44 @@ impl<E: Encoder> PlanBuilder<E> {
                             graph_name,
                             "string",
                         )?
+                    } else if name == geo::DISTANCE {
+                        if parameters.len() != 3 {
+                            return Err(Error::msg(
+                                "The geof:distance function takes three parameters",
+                            ));
+                        }
+                        PlanExpression::GeoDistance(
+                            Box::new(self.build_for_expression(
+                                &parameters[0],
+                                variables,
+                                graph_name,
+                            )?),
+                            Box::new(self.build_for_expression(
+                                &parameters[1],
+                                variables,
+                                graph_name,
+                            )?),
+                            Box::new(self.build_for_expression(
+                                &parameters[2],
+                                variables,
+                                graph_name,
+                            )?),
+                        )
+                    } else if name == geo::SF_WITHIN || name == geo::SF_CONTAINS {
+                        if parameters.len() != 2 {
+                            return Err(Error::msg(format!(
+                                "The {} function takes two parameters",
+                                name
+                            )));
+                        }
+                        let a = self.build_for_expression(&parameters[0], variables, graph_name)?;
+                        let b = self.build_for_expression(&parameters[1], variables, graph_name)?;
+                        // a contains b if and only if b is within a
+                        if name == geo::SF_WITHIN {
+                            PlanExpression::GeoWithin(Box::new(a), Box::new(b))
+                        } else {
+                            PlanExpression::GeoWithin(Box::new(b), Box::new(a))
+                        }
                     } else {
                         return Err(Error::msg(format!(
                             "Not supported custom function {}",
@@ -715,10 +1002,12 @@ impl<E: Encoder> PlanBuilder<E> {
             .collect()
     }
 
+    /// The quoted triples are replaced by new variables and added to `quoted_triples` to be bound to their terms
     fn pattern_value_from_term_or_variable(
         &mut self,
         term_or_variable: &TermOrVariable,
         variables: &mut Vec<Variable>,
+        quoted_triples: &mut Vec<QuotedTriple>,
     ) -> Result<PatternValue> {
         Ok(match term_or_variable {
             TermOrVariable::Variable(variable) => {
@@ -729,6 +1018,29 @@ impl<E: Encoder> PlanBuilder<E> {
                 //TODO: very bad hack to convert bnode to variable
             }
             TermOrVariable::Term(term) => PatternValue::Constant(self.encoder.encode_term(term)?),
+            TermOrVariable::Triple(triple) => {
+                let quoted_triple = QuotedTriple {
+                    triple: PatternValue::Variable(variable_key(
+                        variables,
+                        &Variable::new_random(),
+                    )),
+                    subject: self.pattern_value_from_term_or_variable(
+                        &triple.subject,
+                        variables,
+                        quoted_triples,
+                    )?,
+                    predicate: self
+                        .pattern_value_from_named_node_or_variable(&triple.predicate, variables)?,
+                    object: self.pattern_value_from_term_or_variable(
+                        &triple.object,
+                        variables,
+                        quoted_triples,
+                    )?,
+                };
+                let value = quoted_triple.triple;
+                quoted_triples.push(quoted_triple);
+                value
+            }
         })
     }
 
@@ -821,6 +1133,11 @@ impl<E: Encoder> PlanBuilder<E> {
                 parameter: Some(self.build_for_expression(e, variables, graph_name)?),
                 distinct: *distinct,
             },
+            Aggregation::Custom(name, e, distinct) => PlanAggregation {
+                function: PlanAggregationFunction::Custom(name.clone()),
+                parameter: Some(self.build_for_expression(e, variables, graph_name)?),
+                distinct: *distinct,
+            },
         })
     }
 
@@ -867,6 +1184,22 @@ impl<E: Encoder> PlanBuilder<E> {
             TermOrVariable::Term(term) => {
                 TripleTemplateValue::Constant(self.encoder.encode_term(term)?)
             }
+            TermOrVariable::Triple(triple) => {
+                TripleTemplateValue::Triple(Box::new(TripleTemplate {
+                    subject: self.template_value_from_term_or_variable(
+                        &triple.subject,
+                        variables,
+                        bnodes,
+                    )?,
+                    predicate: self
+                        .template_value_from_named_node_or_variable(&triple.predicate, variables)?,
+                    object: self.template_value_from_term_or_variable(
+                        &triple.object,
+                        variables,
+                        bnodes,
+                    )?,
+                }))
+            }
         })
     }
 
@@ -924,9 +1257,17 @@ impl<E: Encoder> PlanBuilder<E> {
             PlanNode::Init
             | PlanNode::StaticBindings { .. }
             | PlanNode::QuadPatternJoin { .. }
-            | PlanNode::PathPatternJoin { .. } => (),
+            | PlanNode::PathPatternJoin { .. }
+            | PlanNode::FullTextSearch { .. }
+            | PlanNode::SpatialSearch { .. }
+            | PlanNode::QuotedTriplePattern { .. }
+            | PlanNode::PropertyFunction { .. } => (),
             PlanNode::Filter { child, expression } => {
-                expression.add_maybe_bound_variables(set); //TODO: only if it is not already bound
+                // The variables bound by the child are the same as the ones of the outer solution
+                let child_bound = child.always_bound_variables();
+                let mut expression_variables = BTreeSet::new();
+                expression.add_maybe_bound_variables(&mut expression_variables);
+                set.extend(expression_variables.difference(&child_bound).copied());
                 self.add_left_join_problematic_variables(&*child, set);
             }
             PlanNode::Union { children } => {
@@ -934,7 +1275,7 @@ impl<E: Encoder> PlanBuilder<E> {
                     self.add_left_join_problematic_variables(&*child, set);
                 }
             }
-            PlanNode::Join { left, right, .. } => {
+            PlanNode::Join { left, right, .. } | PlanNode::HashJoin { left, right, .. } => {
                 self.add_left_join_problematic_variables(&*left, set);
                 self.add_left_join_problematic_variables(&*right, set);
             }
@@ -942,14 +1283,27 @@ impl<E: Encoder> PlanBuilder<E> {
                 self.add_left_join_problematic_variables(&*left, set);
             }
             PlanNode::LeftJoin { left, right, .. } => {
+                // If the pattern is well designed, the variables of the optional part shared with
+                // the outer solution are always bound by the left part and so are safe to inject
                 self.add_left_join_problematic_variables(&*left, set);
-                right.add_maybe_bound_variables(set);
+                let left_bound = left.always_bound_variables();
+                set.extend(
+                    right
+                        .maybe_bound_variables()
+                        .difference(&left_bound)
+                        .copied(),
+                );
             }
             PlanNode::Extend {
-                child, expression, ..
+                child,
+                position,
+                expression,
             } => {
-                expression.add_maybe_bound_variables(set); //TODO: only if it is not already bound
-                self.add_left_join_problematic_variables(&*child, set);
+                set.insert(*position);
+                let child_bound = child.always_bound_variables();
+                let mut expression_variables = BTreeSet::new();
+                expression.add_maybe_bound_variables(&mut expression_variables);
+                set.extend(expression_variables.difference(&child_bound).copied());
                 self.add_left_join_problematic_variables(&*child, set);
             }
             PlanNode::Service { child, .. }
@@ -959,7 +1313,7 @@ impl<E: Encoder> PlanBuilder<E> {
             | PlanNode::Limit { child, .. } => {
                 self.add_left_join_problematic_variables(&*child, set)
             }
-            PlanNode::Project { mapping, child } => {
+            PlanNode::Project { mapping, child, .. } => {
                 let mut child_bound = BTreeSet::new();
                 self.add_left_join_problematic_variables(&*child, &mut child_bound);
                 for (child_i, output_i) in mapping.iter() {
@@ -983,6 +1337,211 @@ impl<E: Encoder> PlanBuilder<E> {
     }
 }
 
+/// Pushes the maximal number of solutions that are going to be consumed down to the quad pattern read from the store
+///
+/// It only goes through the operations that return exactly one solution per input solution.
+fn push_down_limit(node: &mut PlanNode, max_solutions: usize) {
+    match node {
+        PlanNode::Project { child, .. } | PlanNode::Extend { child, .. } => {
+            push_down_limit(child, max_solutions)
+        }
+        PlanNode::QuadPatternJoin {
+            child,
+            subject,
+            predicate,
+            object,
+            graph_name,
+            limit,
+        } => {
+            // The lookup is done once and the quads are not filtered afterwards
+            if let PlanNode::Init = **child {
+                let values = [&*subject, &*predicate, &*object, &*graph_name];
+                if values.iter().enumerate().all(|(i, value)| {
+                    !value.is_var() || values[i + 1..].iter().all(|other| other != value)
+                }) {
+                    *limit = Some(max_solutions);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Builds the join of two plans
+///
+/// If the left plan is an OPTIONAL, the join is evaluated before it when the result is the same,
+/// i.e. when the variables shared by the right plan and the optional part are always bound by the mandatory part.
+/// It allows to filter the solutions before evaluating the optional parts.
+/// If `allow_hash_join` is `false`, a nested loop join is built when the right plan could not be fed with the left solutions.
+fn new_join(left: PlanNode, right: PlanNode, allow_hash_join: bool) -> PlanNode {
+    match left {
+        PlanNode::LeftJoin {
+            left: left_left,
+            right: left_right,
+            possible_problem_vars,
+        } if can_join_before_left_join(&left_left, &left_right, &right) => PlanNode::LeftJoin {
+            left: Box::new(new_join(*left_left, right, allow_hash_join)),
+            right: left_right,
+            possible_problem_vars,
+        },
+        left => {
+            if is_pattern_chain(&right) {
+                // We feed the right patterns with the left solutions in order to stream the join
+                plug_pattern_chain(right, left)
+            } else if allow_hash_join {
+                new_hash_join(left, right)
+            } else {
+                PlanNode::Join {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }
+            }
+        }
+    }
+}
+
+/// Builds a join indexing the right solutions by the variables always bound on both sides
+///
+/// If there are no such variables, a nested loop join is built.
+fn new_hash_join(left: PlanNode, right: PlanNode) -> PlanNode {
+    let right_bound = right.always_bound_variables();
+    let keys = left
+        .always_bound_variables()
+        .intersection(&right_bound)
+        .copied()
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        PlanNode::Join {
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    } else {
+        PlanNode::HashJoin {
+            left: Box::new(left),
+            right: Box::new(right),
+            keys,
+        }
+    }
+}
+
+/// Checks if reading all the matches of a pattern once to join them with a hash table is cheaper
+/// than looking up the matches of the pattern in the store for each input solution
+///
+/// A lookup costs far more than reading a match of a range scan, so we only compare the number of lookups and of matches.
+fn is_hash_join_cheaper(input_cardinality: u64, pattern_cardinality: u64) -> bool {
+    pattern_cardinality < input_cardinality
+}
+
+fn can_join_before_left_join(
+    left_join_left: &PlanNode,
+    left_join_right: &PlanNode,
+    join_right: &PlanNode,
+) -> bool {
+    let left_bound = left_join_left.always_bound_variables();
+    let join_right_variables = join_right.maybe_bound_variables();
+    left_join_right
+        .maybe_bound_variables()
+        .iter()
+        .all(|var| left_bound.contains(var) || !join_right_variables.contains(var))
+}
+
+/// Checks if the plan is only made of quad, path, full-text search, spatial search, quoted triple and property function patterns
+fn is_pattern_chain(node: &PlanNode) -> bool {
+    match node {
+        PlanNode::Init => true,
+        PlanNode::QuadPatternJoin { child, .. }
+        | PlanNode::PathPatternJoin { child, .. }
+        | PlanNode::FullTextSearch { child, .. }
+        | PlanNode::SpatialSearch { child, .. }
+        | PlanNode::QuotedTriplePattern { child, .. }
+        | PlanNode::PropertyFunction { child, .. } => is_pattern_chain(child),
+        _ => false,
+    }
+}
+
+/// Replaces the `Init` node at the start of a pattern chain by `input`
+fn plug_pattern_chain(chain: PlanNode, input: PlanNode) -> PlanNode {
+    match chain {
+        PlanNode::QuadPatternJoin {
+            child,
+            subject,
+            predicate,
+            object,
+            graph_name,
+            ..
+        } => PlanNode::QuadPatternJoin {
+            child: Box::new(plug_pattern_chain(*child, input)),
+            subject,
+            predicate,
+            object,
+            graph_name,
+            limit: None,
+        },
+        PlanNode::PathPatternJoin {
+            child,
+            subject,
+            path,
+            object,
+            graph_name,
+        } => PlanNode::PathPatternJoin {
+            child: Box::new(plug_pattern_chain(*child, input)),
+            subject,
+            path,
+            object,
+            graph_name,
+        },
+        PlanNode::FullTextSearch {
+            child,
+            literal,
+            query,
+            graph_name,
+        } => PlanNode::FullTextSearch {
+            child: Box::new(plug_pattern_chain(*child, input)),
+            literal,
+            query,
+            graph_name,
+        },
+        PlanNode::SpatialSearch {
+            child,
+            literal,
+            envelope,
+            within,
+        } => PlanNode::SpatialSearch {
+            child: Box::new(plug_pattern_chain(*child, input)),
+            literal,
+            envelope,
+            within,
+        },
+        PlanNode::QuotedTriplePattern {
+            child,
+            triple,
+            subject,
+            predicate,
+            object,
+        } => PlanNode::QuotedTriplePattern {
+            child: Box::new(plug_pattern_chain(*child, input)),
+            triple,
+            subject,
+            predicate,
+            object,
+        },
+        PlanNode::PropertyFunction {
+            child,
+            subject,
+            function,
+            object,
+            graph_name,
+        } => PlanNode::PropertyFunction {
+            child: Box::new(plug_pattern_chain(*child, input)),
+            subject,
+            function,
+            object,
+            graph_name,
+        },
+        _ => input,
+    }
+}
+
 fn variable_key(variables: &mut Vec<Variable>, variable: &Variable) -> usize {
     match slice_key(variables, variable) {
         Some(key) => key,
@@ -1012,23 +1571,264 @@ fn slice_key<T: Eq>(slice: &[T], element: &T) -> Option<usize> {
     None
 }
 
-fn sort_bgp(p: &[TripleOrPathPattern]) -> Vec<&TripleOrPathPattern> {
-    let mut assigned_variables = HashSet::default();
+/// Orders the patterns of a basic graph pattern in order to evaluate the most selective ones first
+///
+/// If the statistics allow to estimate the number of results of two patterns, the one with the fewest results is evaluated first.
+/// If not, the one with the most bound terms is evaluated first.
+fn sort_bgp<'a>(
+    p: &'a [TripleOrPathPattern],
+    graph_name: PatternValue,
+    statistics: Option<&dyn StoreStatistics>,
+    property_functions: &HashSet<NamedNode>,
+    mut assigned_variables: HashSet<&'a Variable>,
+) -> Vec<&'a TripleOrPathPattern> {
     let mut assigned_blank_nodes = HashSet::default();
     let mut new_p: Vec<_> = p.iter().collect();
 
     for i in 0..new_p.len() {
-        (&mut new_p[i..]).sort_by(|p1, p2| {
-            count_pattern_binds(p2, &assigned_variables, &assigned_blank_nodes).cmp(
-                &count_pattern_binds(p1, &assigned_variables, &assigned_blank_nodes),
-            )
+        // The estimations only depend on the already assigned variables so they are computed once per step
+        let mut candidates: Vec<_> = new_p[i..]
+            .iter()
+            .map(|pattern| {
+                (
+                    *pattern,
+                    statistics.and_then(|statistics| {
+                        estimate_pattern_cardinality(
+                            pattern,
+                            graph_name,
+                            statistics,
+                            property_functions,
+                            &assigned_variables,
+                            &assigned_blank_nodes,
+                        )
+                    }),
+                    count_pattern_binds(pattern, &assigned_variables, &assigned_blank_nodes),
+                )
+            })
+            .collect();
+        candidates.sort_by(|(_, e1, b1), (_, e2, b2)| match (e1, e2) {
+            (Some(e1), Some(e2)) if e1 != e2 => e1.cmp(e2),
+            _ => b2.cmp(b1),
         });
+        for (slot, (pattern, _, _)) in new_p[i..].iter_mut().zip(candidates) {
+            *slot = pattern;
+        }
         add_pattern_variables(new_p[i], &mut assigned_variables, &mut assigned_blank_nodes);
     }
 
     new_p
 }
 
+/// Estimates the number of results of a triple pattern using the predicate and graph statistics
+///
+/// Returns `None` for property paths and patterns without a constant predicate.
+fn estimate_pattern_cardinality(
+    pattern: &TripleOrPathPattern,
+    graph_name: PatternValue,
+    statistics: &dyn StoreStatistics,
+    property_functions: &HashSet<NamedNode>,
+    assigned_variables: &HashSet<&Variable>,
+    assigned_blank_nodes: &HashSet<&BlankNode>,
+) -> Option<u64> {
+    let predicate = match pattern {
+        TripleOrPathPattern::Triple(pattern)
+            if is_property_function(pattern, property_functions) =>
+        {
+            // Property functions usually need one of their arguments to be bound
+            return Some(
+                if is_term_bound(&pattern.subject, assigned_variables, assigned_blank_nodes)
+                    || is_term_bound(&pattern.object, assigned_variables, assigned_blank_nodes)
+                {
+                    0
+                } else {
+                    u64::MAX
+                },
+            );
+        }
+        TripleOrPathPattern::Triple(pattern) if is_full_text_search(pattern) => {
+            // The search could only be done if its query is known
+            return if !is_term_bound(&pattern.object, assigned_variables, assigned_blank_nodes) {
+                Some(u64::MAX)
+            } else if is_term_bound(&pattern.subject, assigned_variables, assigned_blank_nodes) {
+                Some(1)
+            } else if let TermOrVariable::Term(Term::Literal(query)) = &pattern.object {
+                statistics.literals_matching_count(query.value())
+            } else {
+                None
+            };
+        }
+        TripleOrPathPattern::Triple(TriplePattern {
+            predicate: NamedNodeOrVariable::NamedNode(predicate),
+            ..
+        }) => predicate,
+        _ => return None,
+    };
+    let estimation = estimate_cardinality(
+        statistics.predicate_statistics(EncodedTerm::from(predicate))?,
+        is_term_bound(pattern.subject(), assigned_variables, assigned_blank_nodes),
+        is_term_bound(pattern.object(), assigned_variables, assigned_blank_nodes),
+    );
+    // The pattern could not match more quads than the ones of its graph
+    Some(match graph_name {
+        PatternValue::Constant(graph_name) => statistics
+            .graph_quads_count(graph_name)
+            .map_or(estimation, |count| estimation.min(count)),
+        PatternValue::Variable(_) => estimation,
+    })
+}
+
+fn is_full_text_search(pattern: &TriplePattern) -> bool {
+    match &pattern.predicate {
+        NamedNodeOrVariable::NamedNode(predicate) => predicate.as_str() == FULL_TEXT_MATCHES,
+        NamedNodeOrVariable::Variable(_) => false,
+    }
+}
+
+/// Finds in a filter a geometry constraint on a variable the spatial index could pre-select
+///
+/// Returns the variable and the envelope its bounding box should be within or, if the returned boolean is false, intersect.
+fn spatial_filter(expression: &Expression) -> Option<(&Variable, Envelope, bool)> {
+    match expression {
+        Expression::And(a, b) => spatial_filter(a).or_else(|| spatial_filter(b)),
+        Expression::FunctionCall(Function::Custom(name), parameters) if parameters.len() == 2 => {
+            let (geometry, area) = if name.as_str() == geo::SF_WITHIN {
+                (&parameters[0], &parameters[1])
+            } else if name.as_str() == geo::SF_CONTAINS {
+                (&parameters[1], &parameters[0])
+            } else {
+                return None;
+            };
+            match (geometry, area) {
+                (Expression::Variable(variable), Expression::Literal(area)) => {
+                    Some((variable, wkt_envelope(area)?, true))
+                }
+                _ => None,
+            }
+        }
+        Expression::Lower(a, b) | Expression::LowerOrEq(a, b) => distance_filter(a, b),
+        Expression::Greater(a, b) | Expression::GreaterOrEq(a, b) => distance_filter(b, a),
+        _ => None,
+    }
+}
+
+/// Pre-selects the geometries of a `geof:distance(?v, "WKT", unit) < max` filter
+///
+/// The geometries at less than `max` of the constant one have a bounding box intersecting its expanded envelope.
+fn distance_filter<'a>(
+    distance: &'a Expression,
+    max: &Expression,
+) -> Option<(&'a Variable, Envelope, bool)> {
+    let parameters = match distance {
+        Expression::FunctionCall(Function::Custom(name), parameters)
+            if name.as_str() == geo::DISTANCE && parameters.len() == 3 =>
+        {
+            parameters
+        }
+        _ => return None,
+    };
+    let (variable, geometry) = match (&parameters[0], &parameters[1]) {
+        (Expression::Variable(variable), Expression::Literal(geometry))
+        | (Expression::Literal(geometry), Expression::Variable(variable)) => (variable, geometry),
+        _ => return None,
+    };
+    let unit = match &parameters[2] {
+        Expression::NamedNode(unit) => unit,
+        _ => return None,
+    };
+    let max = match max {
+        Expression::Literal(max)
+            if [&*xsd::INTEGER, &*xsd::DECIMAL, &*xsd::FLOAT, &*xsd::DOUBLE]
+                .contains(&max.datatype()) =>
+        {
+            max.value().parse::<f64>().ok()?
+        }
+        _ => return None,
+    };
+    if !max.is_finite() || max < 0. {
+        return None;
+    }
+    let max = geo::to_metres(max, unit.as_str())?;
+    Some((variable, wkt_envelope(geometry)?.expand(max), false))
+}
+
+fn wkt_envelope(literal: &Literal) -> Option<Envelope> {
+    if literal.datatype().as_str() == geo::WKT_LITERAL {
+        Some(literal.value().parse::<Geometry>().ok()?.envelope())
+    } else {
+        None
+    }
+}
+
+fn is_property_function(pattern: &TriplePattern, property_functions: &HashSet<NamedNode>) -> bool {
+    match &pattern.predicate {
+        NamedNodeOrVariable::NamedNode(predicate) => property_functions.contains(predicate),
+        NamedNodeOrVariable::Variable(_) => false,
+    }
+}
+
+/// Estimates the number of results of a triple pattern with a constant predicate
+pub(crate) fn estimate_cardinality(
+    statistics: PredicateStatistics,
+    is_subject_bound: bool,
+    is_object_bound: bool,
+) -> u64 {
+    // We assume that the quads are evenly distributed between the subjects and between the objects
+    let average_quads_per_subject =
+        statistics.quads_count / statistics.distinct_subjects_count.max(1);
+    let average_quads_per_object =
+        statistics.quads_count / statistics.distinct_objects_count.max(1);
+    match (is_subject_bound, is_object_bound) {
+        (true, true) => statistics.quads_count.min(1),
+        (true, false) => statistics.quads_count.min(average_quads_per_subject.max(1)),
+        (false, true) => statistics.quads_count.min(average_quads_per_object.max(1)),
+        (false, false) => statistics.quads_count,
+    }
+}
+
+fn is_term_bound(
+    term: &TermOrVariable,
+    assigned_variables: &HashSet<&Variable>,
+    assigned_blank_nodes: &HashSet<&BlankNode>,
+) -> bool {
+    match term {
+        TermOrVariable::Variable(v) => assigned_variables.contains(v),
+        TermOrVariable::Term(Term::BlankNode(bnode)) => assigned_blank_nodes.contains(bnode),
+        TermOrVariable::Term(_) => true,
+        TermOrVariable::Triple(triple) => {
+            is_term_bound(&triple.subject, assigned_variables, assigned_blank_nodes)
+                && match &triple.predicate {
+                    NamedNodeOrVariable::NamedNode(_) => true,
+                    NamedNodeOrVariable::Variable(v) => assigned_variables.contains(v),
+                }
+                && is_term_bound(&triple.object, assigned_variables, assigned_blank_nodes)
+        }
+    }
+}
+
+/// A quoted triple of a pattern, bound to `triple`
+struct QuotedTriple {
+    triple: PatternValue,
+    subject: PatternValue,
+    predicate: PatternValue,
+    object: PatternValue,
+}
+
+impl QuotedTriple {
+    fn terms(&self) -> [PatternValue; 3] {
+        [self.subject, self.predicate, self.object]
+    }
+
+    fn into_plan(self, child: PlanNode) -> PlanNode {
+        PlanNode::QuotedTriplePattern {
+            child: Box::new(child),
+            triple: self.triple,
+            subject: self.subject,
+            predicate: self.predicate,
+            object: self.object,
+        }
+    }
+}
+
 fn count_pattern_binds(
     pattern: &TripleOrPathPattern,
     assigned_variables: &HashSet<&Variable>,
@@ -1076,19 +1876,34 @@ fn add_pattern_variables<'a>(
     variables: &mut HashSet<&'a Variable>,
     blank_nodes: &mut HashSet<&'a BlankNode>,
 ) {
-    if let TermOrVariable::Variable(v) = pattern.subject() {
-        variables.insert(v);
-    } else if let TermOrVariable::Term(Term::BlankNode(bnode)) = pattern.subject() {
-        blank_nodes.insert(bnode);
-    }
+    add_term_variables(pattern.subject(), variables, blank_nodes);
     if let TripleOrPathPattern::Triple(t) = pattern {
         if let NamedNodeOrVariable::Variable(v) = &t.predicate {
             variables.insert(v);
         }
     }
-    if let TermOrVariable::Variable(v) = pattern.object() {
-        variables.insert(v);
-    } else if let TermOrVariable::Term(Term::BlankNode(bnode)) = pattern.object() {
-        blank_nodes.insert(bnode);
+    add_term_variables(pattern.object(), variables, blank_nodes);
+}
+
+fn add_term_variables<'a>(
+    term: &'a TermOrVariable,
+    variables: &mut HashSet<&'a Variable>,
+    blank_nodes: &mut HashSet<&'a BlankNode>,
+) {
+    match term {
+        TermOrVariable::Variable(v) => {
+            variables.insert(v);
+        }
+        TermOrVariable::Term(Term::BlankNode(bnode)) => {
+            blank_nodes.insert(bnode);
+        }
+        TermOrVariable::Term(_) => (),
+        TermOrVariable::Triple(triple) => {
+            add_term_variables(&triple.subject, variables, blank_nodes);
+            if let NamedNodeOrVariable::Variable(v) = &triple.predicate {
+                variables.insert(v);
+            }
+            add_term_variables(&triple.object, variables, blank_nodes);
+        }
     }
 }
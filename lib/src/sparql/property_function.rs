@@ -0,0 +1,117 @@
+//! Property functions: predicates of the triple patterns evaluated by custom code instead of a lookup in the dataset
+
+use crate::model::vocab::rdf;
+use crate::model::*;
+use crate::sparql::QueryDataset;
+use crate::{Error, Result};
+use std::collections::HashSet;
+
+/// A function evaluating the triple patterns using a given predicate, like the [Jena property functions](https://jena.apache.org/documentation/query/extension.html#property-functions).
+///
+/// The patterns `subject <function> object` of the basic graph patterns are evaluated using the function
+/// registered with `QueryOptions::with_property_function` instead of being looked up in the dataset.
+/// The planner evaluates them as soon as their subject or their object is bound.
+/// Inside of a `GRAPH ?g` pattern with `?g` not bound yet, the function is evaluated once per named graph.
+pub trait PropertyFunction {
+    /// Returns the pairs of subjects and objects matching the pattern.
+    ///
+    /// `subject` and `object` are `None` if they are variables that are not bound yet.
+    /// `graph_name` is the graph the pattern is evaluated in.
+    /// The returned pairs not compatible with the bound values are ignored.
+    fn evaluate(
+        &self,
+        subject: Option<&Term>,
+        object: Option<&Term>,
+        graph_name: &GraphName,
+        dataset: &dyn QueryDataset,
+    ) -> Result<Vec<(Term, Term)>>;
+}
+
+impl<
+        F: Fn(
+            Option<&Term>,
+            Option<&Term>,
+            &GraphName,
+            &dyn QueryDataset,
+        ) -> Result<Vec<(Term, Term)>>,
+    > PropertyFunction for F
+{
+    fn evaluate(
+        &self,
+        subject: Option<&Term>,
+        object: Option<&Term>,
+        graph_name: &GraphName,
+        dataset: &dyn QueryDataset,
+    ) -> Result<Vec<(Term, Term)>> {
+        self(subject, object, graph_name, dataset)
+    }
+}
+
+/// The members of an [RDF collection](https://www.w3.org/TR/rdf11-mt/#rdf-collections) in the graph of the pattern: `?list list:member ?member`.
+///
+/// The list should be bound.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::sparql::{ListMember, QueryOptions, QueryResult};
+/// use oxigraph::{GraphSyntax, MemoryStore};
+///
+/// let store = MemoryStore::new();
+/// store.load_graph(
+///     "@prefix ex: <http://example.com/> . ex:s ex:p (1 2 3) .".as_bytes(),
+///     GraphSyntax::Turtle,
+///     &GraphName::DefaultGraph,
+///     None,
+/// )?;
+///
+/// let prepared_query = store.prepare_query(
+///     "PREFIX list: <http://jena.apache.org/ARQ/list#> SELECT ?m WHERE { <http://example.com/s> <http://example.com/p> ?l . ?l list:member ?m }",
+///     QueryOptions::default().with_property_function(
+///         NamedNode::new("http://jena.apache.org/ARQ/list#member")?,
+///         ListMember,
+///     ),
+/// )?;
+/// if let QueryResult::Solutions(solutions) = prepared_query.exec()? {
+///     assert_eq!(solutions.collect::<oxigraph::Result<Vec<_>>>()?.len(), 3);
+/// }
+/// # oxigraph::Result::Ok(())
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListMember;
+
+impl PropertyFunction for ListMember {
+    fn evaluate(
+        &self,
+        subject: Option<&Term>,
+        _: Option<&Term>,
+        graph_name: &GraphName,
+        dataset: &dyn QueryDataset,
+    ) -> Result<Vec<(Term, Term)>> {
+        let list = subject.ok_or_else(|| Error::msg("The list of list:member should be bound"))?;
+        let mut members = Vec::default();
+        let mut visited = HashSet::new();
+        let mut current = list.clone();
+        // The visited nodes are tracked in order to stop on cyclic lists
+        while visited.insert(current.clone()) {
+            let node = match &current {
+                Term::NamedNode(node) => Subject::from(node.clone()),
+                Term::BlankNode(node) => Subject::from(node.clone()),
+                Term::Literal(_) | Term::Triple(_) => break,
+            };
+            for quad in
+                dataset.quads_for_pattern(Some(&node), Some(&rdf::FIRST), None, Some(graph_name))
+            {
+                members.push((list.clone(), quad?.object));
+            }
+            current = match dataset
+                .quads_for_pattern(Some(&node), Some(&rdf::REST), None, Some(graph_name))
+                .next()
+            {
+                Some(quad) => quad?.object,
+                None => break,
+            };
+        }
+        Ok(members)
+    }
+}
@@ -0,0 +1,423 @@
+//! Query rewriting implementing the [RDFS entailment regime](https://www.w3.org/TR/sparql11-entailment/#RDFSEntailmentRegime)
+//!
+//! The triple patterns are rewritten into patterns reading the `rdfs:subClassOf`, `rdfs:subPropertyOf`,
+//! `rdfs:domain` and `rdfs:range` statements of the queried graph.
+//! It covers the entailment rules rdfs2, rdfs3, rdfs5, rdfs7, rdfs9 and rdfs11.
+//! The triple patterns with a variable as predicate and the property path patterns are not rewritten.
+
+use crate::model::vocab::{rdf, rdfs};
+use crate::model::*;
+use crate::sparql::algebra::*;
+use crate::sparql::model::*;
+use crate::sparql::plan_builder::FULL_TEXT_MATCHES;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Rewrites `query` in order to return the answers entailed by the RDFS semantics
+///
+/// The triple patterns with a predicate in `builtin_predicates` are kept as is.
+pub(crate) fn rewrite_query(
+    query: QueryVariants,
+    builtin_predicates: &HashSet<NamedNode>,
+) -> QueryVariants {
+    let rewriter = RdfsRewriter { builtin_predicates };
+    match query {
+        QueryVariants::Select {
+            dataset,
+            algebra,
+            base_iri,
+        } => QueryVariants::Select {
+            dataset,
+            algebra: rewriter.rewrite_graph_pattern(algebra),
+            base_iri,
+        },
+        QueryVariants::Construct {
+            construct,
+            dataset,
+            algebra,
+            base_iri,
+        } => QueryVariants::Construct {
+            construct,
+            dataset,
+            algebra: rewriter.rewrite_graph_pattern(algebra),
+            base_iri,
+        },
+        QueryVariants::Describe {
+            dataset,
+            algebra,
+            base_iri,
+        } => QueryVariants::Describe {
+            dataset,
+            algebra: rewriter.rewrite_graph_pattern(algebra),
+            base_iri,
+        },
+        QueryVariants::Ask {
+            dataset,
+            algebra,
+            base_iri,
+        } => QueryVariants::Ask {
+            dataset,
+            algebra: rewriter.rewrite_graph_pattern(algebra),
+            base_iri,
+        },
+    }
+}
+
+struct RdfsRewriter<'a> {
+    builtin_predicates: &'a HashSet<NamedNode>,
+}
+
+impl<'a> RdfsRewriter<'a> {
+    fn rewrite_graph_pattern(&self, pattern: GraphPattern) -> GraphPattern {
+        match pattern {
+            GraphPattern::BGP(patterns) => self.rewrite_bgp(patterns),
+            GraphPattern::Join(a, b) => GraphPattern::Join(
+                Box::new(self.rewrite_graph_pattern(*a)),
+                Box::new(self.rewrite_graph_pattern(*b)),
+            ),
+            GraphPattern::LeftJoin(a, b, e) => GraphPattern::LeftJoin(
+                Box::new(self.rewrite_graph_pattern(*a)),
+                Box::new(self.rewrite_graph_pattern(*b)),
+                e.map(|e| self.rewrite_expression(e)),
+            ),
+            GraphPattern::Filter(e, p) => GraphPattern::Filter(
+                self.rewrite_expression(e),
+                Box::new(self.rewrite_graph_pattern(*p)),
+            ),
+            GraphPattern::Union(a, b) => GraphPattern::Union(
+                Box::new(self.rewrite_graph_pattern(*a)),
+                Box::new(self.rewrite_graph_pattern(*b)),
+            ),
+            GraphPattern::Graph(g, p) => {
+                GraphPattern::Graph(g, Box::new(self.rewrite_graph_pattern(*p)))
+            }
+            GraphPattern::Extend(p, v, e) => GraphPattern::Extend(
+                Box::new(self.rewrite_graph_pattern(*p)),
+                v,
+                self.rewrite_expression(e),
+            ),
+            GraphPattern::Minus(a, b) => GraphPattern::Minus(
+                Box::new(self.rewrite_graph_pattern(*a)),
+                Box::new(self.rewrite_graph_pattern(*b)),
+            ),
+            // The remote endpoints implement their own entailment regime
+            GraphPattern::Service(n, p, s) => GraphPattern::Service(n, p, s),
+            GraphPattern::AggregateJoin(GroupPattern(key, p), aggregates) => {
+                GraphPattern::AggregateJoin(
+                    GroupPattern(key, Box::new(self.rewrite_graph_pattern(*p))),
+                    aggregates
+                        .into_iter()
+                        .map(|(a, v)| (self.rewrite_aggregation(a), v))
+                        .collect(),
+                )
+            }
+            GraphPattern::Data(bs) => GraphPattern::Data(bs),
+            GraphPattern::OrderBy(l, o) => GraphPattern::OrderBy(
+                Box::new(self.rewrite_graph_pattern(*l)),
+                o.into_iter()
+                    .map(|comparator| match comparator {
+                        OrderComparator::Asc(e) => OrderComparator::Asc(self.rewrite_expression(e)),
+                        OrderComparator::Desc(e) => {
+                            OrderComparator::Desc(self.rewrite_expression(e))
+                        }
+                    })
+                    .collect(),
+            ),
+            GraphPattern::Project(l, v) => {
+                GraphPattern::Project(Box::new(self.rewrite_graph_pattern(*l)), v)
+            }
+            GraphPattern::Distinct(l) => {
+                GraphPattern::Distinct(Box::new(self.rewrite_graph_pattern(*l)))
+            }
+            GraphPattern::Reduced(l) => {
+                GraphPattern::Reduced(Box::new(self.rewrite_graph_pattern(*l)))
+            }
+            GraphPattern::Slice(l, start, length) => {
+                GraphPattern::Slice(Box::new(self.rewrite_graph_pattern(*l)), start, length)
+            }
+        }
+    }
+
+    fn rewrite_bgp(&self, patterns: Vec<TripleOrPathPattern>) -> GraphPattern {
+        // The rewritten patterns are evaluated in sub-queries so the blank nodes are replaced by variables
+        let mut blank_nodes = HashMap::new();
+        let mut kept = Vec::with_capacity(patterns.len());
+        let mut rewritten = Vec::new();
+        for pattern in patterns {
+            match pattern {
+                TripleOrPathPattern::Triple(pattern) => {
+                    let pattern = TriplePattern {
+                        subject: replace_blank_nodes(pattern.subject, &mut blank_nodes),
+                        predicate: pattern.predicate,
+                        object: replace_blank_nodes(pattern.object, &mut blank_nodes),
+                    };
+                    match self.rewrite_triple_pattern(&pattern) {
+                        Some(pattern) => rewritten.push(pattern),
+                        None => kept.push(TripleOrPathPattern::Triple(pattern)),
+                    }
+                }
+                TripleOrPathPattern::Path(pattern) => {
+                    kept.push(TripleOrPathPattern::Path(PathPattern {
+                        subject: replace_blank_nodes(pattern.subject, &mut blank_nodes),
+                        path: pattern.path,
+                        object: replace_blank_nodes(pattern.object, &mut blank_nodes),
+                    }))
+                }
+            }
+        }
+        rewritten.into_iter().fold(GraphPattern::BGP(kept), |a, b| {
+            GraphPattern::Join(Box::new(a), Box::new(b))
+        })
+    }
+
+    /// Returns the pattern matching the entailed triples or `None` if the pattern should be kept as is
+    fn rewrite_triple_pattern(&self, pattern: &TriplePattern) -> Option<GraphPattern> {
+        let predicate = match &pattern.predicate {
+            NamedNodeOrVariable::NamedNode(predicate) => predicate,
+            NamedNodeOrVariable::Variable(_) => return None,
+        };
+        if predicate.as_str() == FULL_TEXT_MATCHES || self.builtin_predicates.contains(predicate) {
+            return None;
+        }
+        let subject = &pattern.subject;
+        let object = &pattern.object;
+        let rewritten = if *predicate == *rdf::TYPE {
+            // rdfs9: the types are inherited from the super classes
+            let explicit = GraphPattern::from(TripleOrPathPattern::Path(PathPattern::new(
+                subject.clone(),
+                sequence(rdf::TYPE.clone(), super_classes()),
+                object.clone(),
+            )));
+            // rdfs2: the subjects of the triples have the domain of their predicate as type
+            let property = Variable::new_random();
+            let value = Variable::new_random();
+            let from_domain = GraphPattern::BGP(vec![
+                TripleOrPathPattern::Triple(TriplePattern::new(
+                    subject.clone(),
+                    property.clone(),
+                    value,
+                )),
+                TripleOrPathPattern::Path(PathPattern::new(
+                    property,
+                    sequence(
+                        sequence(super_properties(), rdfs::DOMAIN.clone()),
+                        super_classes(),
+                    ),
+                    object.clone(),
+                )),
+            ]);
+            // rdfs3: the objects of the triples have the range of their predicate as type
+            let property = Variable::new_random();
+            let value = Variable::new_random();
+            let from_range = GraphPattern::BGP(vec![
+                TripleOrPathPattern::Triple(TriplePattern::new(
+                    value,
+                    property.clone(),
+                    subject.clone(),
+                )),
+                TripleOrPathPattern::Path(PathPattern::new(
+                    property,
+                    sequence(
+                        sequence(super_properties(), rdfs::RANGE.clone()),
+                        super_classes(),
+                    ),
+                    object.clone(),
+                )),
+            ]);
+            GraphPattern::Union(
+                Box::new(GraphPattern::Union(
+                    Box::new(explicit),
+                    Box::new(from_domain),
+                )),
+                Box::new(from_range),
+            )
+        } else if *predicate == *rdfs::SUB_CLASS_OF || *predicate == *rdfs::SUB_PROPERTY_OF {
+            // rdfs5 and rdfs11: the hierarchies are transitive
+            GraphPattern::from(TripleOrPathPattern::Path(PathPattern::new(
+                subject.clone(),
+                PropertyPath::OneOrMorePath(Box::new(predicate.clone().into())),
+                object.clone(),
+            )))
+        } else {
+            // rdfs7: the triples are also stated with the super properties of their predicate
+            let property = Variable::new_random();
+            GraphPattern::BGP(vec![
+                TripleOrPathPattern::Triple(TriplePattern::new(
+                    subject.clone(),
+                    property.clone(),
+                    object.clone(),
+                )),
+                TripleOrPathPattern::Path(PathPattern::new(
+                    property,
+                    PropertyPath::ZeroOrMorePath(Box::new(rdfs::SUB_PROPERTY_OF.clone().into())),
+                    predicate.clone(),
+                )),
+            ])
+        };
+        // A triple entailed in multiple ways should only be returned once
+        let mut variables = BTreeSet::default();
+        add_variables(subject, &mut variables);
+        add_variables(object, &mut variables);
+        Some(GraphPattern::Distinct(Box::new(GraphPattern::Project(
+            Box::new(rewritten),
+            variables.into_iter().collect(),
+        ))))
+    }
+
+    fn rewrite_expression(&self, expression: Expression) -> Expression {
+        match expression {
+            Expression::NamedNode(_)
+            | Expression::Literal(_)
+            | Expression::Variable(_)
+            | Expression::Bound(_) => expression,
+            Expression::Or(a, b) => Expression::Or(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::And(a, b) => Expression::And(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::Equal(a, b) => Expression::Equal(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::NotEqual(a, b) => Expression::NotEqual(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::Greater(a, b) => Expression::Greater(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::GreaterOrEq(a, b) => Expression::GreaterOrEq(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::Lower(a, b) => Expression::Lower(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::LowerOrEq(a, b) => Expression::LowerOrEq(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::In(a, l) => Expression::In(
+                Box::new(self.rewrite_expression(*a)),
+                l.into_iter().map(|e| self.rewrite_expression(e)).collect(),
+            ),
+            Expression::NotIn(a, l) => Expression::NotIn(
+                Box::new(self.rewrite_expression(*a)),
+                l.into_iter().map(|e| self.rewrite_expression(e)).collect(),
+            ),
+            Expression::Add(a, b) => Expression::Add(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::Sub(a, b) => Expression::Sub(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::Mul(a, b) => Expression::Mul(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::Div(a, b) => Expression::Div(
+                Box::new(self.rewrite_expression(*a)),
+                Box::new(self.rewrite_expression(*b)),
+            ),
+            Expression::UnaryPlus(e) => {
+                Expression::UnaryPlus(Box::new(self.rewrite_expression(*e)))
+            }
+            Expression::UnaryMinus(e) => {
+                Expression::UnaryMinus(Box::new(self.rewrite_expression(*e)))
+            }
+            Expression::UnaryNot(e) => Expression::UnaryNot(Box::new(self.rewrite_expression(*e))),
+            Expression::FunctionCall(f, args) => Expression::FunctionCall(
+                f,
+                args.into_iter()
+                    .map(|e| self.rewrite_expression(e))
+                    .collect(),
+            ),
+            Expression::Exists(p) => Expression::Exists(Box::new(self.rewrite_graph_pattern(*p))),
+        }
+    }
+
+    fn rewrite_aggregation(&self, aggregation: Aggregation) -> Aggregation {
+        match aggregation {
+            Aggregation::Count(e, distinct) => {
+                Aggregation::Count(e.map(|e| Box::new(self.rewrite_expression(*e))), distinct)
+            }
+            Aggregation::Sum(e, distinct) => {
+                Aggregation::Sum(Box::new(self.rewrite_expression(*e)), distinct)
+            }
+            Aggregation::Min(e, distinct) => {
+                Aggregation::Min(Box::new(self.rewrite_expression(*e)), distinct)
+            }
+            Aggregation::Max(e, distinct) => {
+                Aggregation::Max(Box::new(self.rewrite_expression(*e)), distinct)
+            }
+            Aggregation::Avg(e, distinct) => {
+                Aggregation::Avg(Box::new(self.rewrite_expression(*e)), distinct)
+            }
+            Aggregation::Sample(e, distinct) => {
+                Aggregation::Sample(Box::new(self.rewrite_expression(*e)), distinct)
+            }
+            Aggregation::GroupConcat(e, distinct, separator) => {
+                Aggregation::GroupConcat(Box::new(self.rewrite_expression(*e)), distinct, separator)
+            }
+            Aggregation::Custom(name, e, distinct) => {
+                Aggregation::Custom(name, Box::new(self.rewrite_expression(*e)), distinct)
+            }
+        }
+    }
+}
+
+fn replace_blank_nodes(
+    term: TermOrVariable,
+    blank_nodes: &mut HashMap<BlankNode, Variable>,
+) -> TermOrVariable {
+    match term {
+        TermOrVariable::Term(Term::BlankNode(node)) => TermOrVariable::Variable(
+            blank_nodes
+                .entry(node)
+                .or_insert_with(Variable::new_random)
+                .clone(),
+        ),
+        TermOrVariable::Triple(triple) => TermOrVariable::Triple(Box::new(TriplePattern {
+            subject: replace_blank_nodes(triple.subject, blank_nodes),
+            predicate: triple.predicate,
+            object: replace_blank_nodes(triple.object, blank_nodes),
+        })),
+        term => term,
+    }
+}
+
+fn add_variables(term: &TermOrVariable, variables: &mut BTreeSet<Variable>) {
+    match term {
+        TermOrVariable::Term(_) => (),
+        TermOrVariable::Variable(variable) => {
+            variables.insert(variable.clone());
+        }
+        TermOrVariable::Triple(triple) => {
+            add_variables(&triple.subject, variables);
+            if let NamedNodeOrVariable::Variable(predicate) = &triple.predicate {
+                variables.insert(predicate.clone());
+            }
+            add_variables(&triple.object, variables);
+        }
+    }
+}
+
+/// `rdfs:subClassOf*`
+fn super_classes() -> PropertyPath {
+    PropertyPath::ZeroOrMorePath(Box::new(rdfs::SUB_CLASS_OF.clone().into()))
+}
+
+/// `rdfs:subPropertyOf*`
+fn super_properties() -> PropertyPath {
+    PropertyPath::ZeroOrMorePath(Box::new(rdfs::SUB_PROPERTY_OF.clone().into()))
+}
+
+fn sequence(a: impl Into<PropertyPath>, b: impl Into<PropertyPath>) -> PropertyPath {
+    PropertyPath::SequencePath(Box::new(a.into()), Box::new(b.into()))
+}
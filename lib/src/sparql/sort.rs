@@ -0,0 +1,280 @@
+//! Sorting of solutions for ORDER BY with spilling to temporary files on disk when there are too many of them
+
+use crate::sparql::plan::EncodedTuple;
+use crate::store::numeric_encoder::{write_term, TermReader};
+use crate::Error;
+use crate::Result;
+use rand::random;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::env::temp_dir;
+use std::fs::{remove_file, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// The maximal number of sorted sources merged at once
+///
+/// It bounds the number of files open at the same time and the number of tuples read ahead during a merge.
+const MERGE_FAN_IN: usize = 16;
+
+/// Sorts `tuples` according to `cmp`.
+///
+/// If `memory_limit` is set, at most `memory_limit` tuples are kept in memory.
+/// The other ones are sorted by chunks written in temporary files.
+/// These runs are merged by groups of `MERGE_FAN_IN` into bigger runs until they are few enough to be merged with the in-memory chunk.
+/// Errors returned by `tuples` are returned first.
+pub fn sort_tuples<'a>(
+    tuples: impl Iterator<Item = Result<EncodedTuple>> + 'a,
+    memory_limit: Option<usize>,
+    cmp: impl Fn(&EncodedTuple, &EncodedTuple) -> Ordering + 'a,
+) -> Box<dyn Iterator<Item = Result<EncodedTuple>> + 'a> {
+    let cmp = Rc::new(cmp);
+    let mut errors = Vec::default();
+    let mut buffer = Vec::default();
+    let mut runs = VecDeque::default();
+    for tuple in tuples {
+        match tuple {
+            Ok(tuple) => {
+                buffer.push(tuple);
+                if memory_limit.map_or(false, |limit| buffer.len() >= limit.max(1)) {
+                    buffer.sort_unstable_by(|a, b| cmp(a, b));
+                    match SortRun::write(buffer.drain(..).map(Ok)) {
+                        Ok(run) => runs.push_back(run),
+                        Err(error) => errors.push(Err(error)),
+                    }
+                }
+            }
+            Err(error) => errors.push(Err(error)),
+        }
+    }
+    buffer.sort_unstable_by(|a, b| cmp(a, b));
+    if runs.is_empty() {
+        return Box::new(errors.into_iter().chain(buffer.into_iter().map(Ok)));
+    }
+    // The oldest runs are merged first in order for the merged runs to have similar sizes
+    while runs.len() >= MERGE_FAN_IN {
+        let merged = open_runs(runs.drain(..MERGE_FAN_IN))
+            .and_then(|sources| MergeIterator::new(sources, cmp.clone()))
+            .and_then(SortRun::write);
+        match merged {
+            Ok(run) => runs.push_back(run),
+            Err(error) => {
+                errors.push(Err(error));
+                return Box::new(errors.into_iter());
+            }
+        }
+    }
+    let merge = open_runs(runs.drain(..)).and_then(|mut sources| {
+        sources.push(MergeSource::Memory(buffer.into_iter()));
+        MergeIterator::new(sources, cmp)
+    });
+    match merge {
+        Ok(merge) => Box::new(errors.into_iter().chain(merge)),
+        Err(error) => {
+            errors.push(Err(error));
+            Box::new(errors.into_iter())
+        }
+    }
+}
+
+fn open_runs(runs: impl Iterator<Item = SortRun>) -> Result<Vec<MergeSource>> {
+    runs.map(|run| Ok(MergeSource::Run(run.open()?))).collect()
+}
+
+/// Merges the sorted sources by always returning the smallest of their heads
+///
+/// The heads are kept in a binary heap in order to find the smallest one in logarithmic time.
+struct MergeIterator<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> {
+    sources: Vec<MergeSource>,
+    heads: BinaryHeap<MergeHead<F>>,
+    cmp: Rc<F>,
+    error: Option<Error>,
+}
+
+impl<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> MergeIterator<F> {
+    /// Opens the sources and reads their first tuples
+    fn new(sources: Vec<MergeSource>, cmp: Rc<F>) -> Result<Self> {
+        let mut merge = Self {
+            heads: BinaryHeap::with_capacity(sources.len()),
+            sources,
+            cmp,
+            error: None,
+        };
+        for source in 0..merge.sources.len() {
+            merge.read_head(source)?;
+        }
+        Ok(merge)
+    }
+
+    fn read_head(&mut self, source: usize) -> Result<()> {
+        if let Some(tuple) = self.sources[source].next()? {
+            self.heads.push(MergeHead {
+                tuple,
+                source,
+                cmp: self.cmp.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> Iterator for MergeIterator<F> {
+    type Item = Result<EncodedTuple>;
+
+    fn next(&mut self) -> Option<Result<EncodedTuple>> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let head = self.heads.pop()?;
+        if let Err(error) = self.read_head(head.source) {
+            self.error = Some(error);
+        }
+        Some(Ok(head.tuple))
+    }
+}
+
+/// The next tuple of a merged source
+///
+/// The ordering is reversed in order for the `BinaryHeap` max-heap to return the smallest tuple first.
+struct MergeHead<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> {
+    tuple: EncodedTuple,
+    source: usize,
+    cmp: Rc<F>,
+}
+
+impl<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> PartialEq for MergeHead<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> Eq for MergeHead<F> {}
+
+impl<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> PartialOrd for MergeHead<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: Fn(&EncodedTuple, &EncodedTuple) -> Ordering> Ord for MergeHead<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.cmp)(&other.tuple, &self.tuple).then_with(|| other.source.cmp(&self.source))
+    }
+}
+
+enum MergeSource {
+    Memory(std::vec::IntoIter<EncodedTuple>),
+    Run(SortRunReader),
+}
+
+impl MergeSource {
+    fn next(&mut self) -> Result<Option<EncodedTuple>> {
+        match self {
+            MergeSource::Memory(iter) => Ok(iter.next()),
+            MergeSource::Run(run) => run.read(),
+        }
+    }
+}
+
+/// A sorted chunk of tuples stored in a temporary file that is removed when dropped
+///
+/// The file is only open while the run is merged.
+struct SortRun {
+    path: PathBuf,
+}
+
+impl SortRun {
+    fn write(tuples: impl Iterator<Item = Result<EncodedTuple>>) -> Result<Self> {
+        let (path, file) = create_temp_file()?;
+        let run = Self { path };
+        write_tuples(file, tuples)?;
+        Ok(run)
+    }
+
+    fn open(self) -> Result<SortRunReader> {
+        Ok(SortRunReader {
+            reader: BufReader::new(File::open(&self.path)?),
+            _run: self,
+        })
+    }
+}
+
+impl Drop for SortRun {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.path);
+    }
+}
+
+/// Reads the tuples of a `SortRun` in order
+struct SortRunReader {
+    reader: BufReader<File>,
+    _run: SortRun,
+}
+
+impl SortRunReader {
+    fn read(&mut self) -> Result<Option<EncodedTuple>> {
+        let mut len = [0; 4];
+        if let Err(error) = self.reader.read_exact(&mut len) {
+            return if error.kind() == ErrorKind::UnexpectedEof {
+                Ok(None)
+            } else {
+                Err(error.into())
+            };
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        let mut tuple = EncodedTuple::with_capacity(len);
+        for i in 0..len {
+            let mut is_bound = [0];
+            self.reader.read_exact(&mut is_bound)?;
+            if is_bound[0] != 0 {
+                tuple.set(i, self.reader.read_term()?);
+            }
+        }
+        Ok(Some(tuple))
+    }
+}
+
+/// Creates a new file with a random name in the temporary directory
+///
+/// The file is created exclusively, so an existing file or symbolic link is never reused,
+/// and, on Unix, is only readable and writable by its owner.
+fn create_temp_file() -> Result<(PathBuf, File)> {
+    loop {
+        let path = temp_dir().join(format!("oxigraph-sort-{:x}", random::<u128>()));
+        let mut options = OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => (),
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// Writes the tuples to the file
+fn write_tuples(file: File, tuples: impl Iterator<Item = Result<EncodedTuple>>) -> Result<()> {
+    let mut writer = BufWriter::new(file);
+    let mut buffer = Vec::default();
+    for tuple in tuples {
+        let tuple = tuple?;
+        buffer.clear();
+        let values = tuple.iter().collect::<Vec<_>>();
+        buffer.extend_from_slice(&(values.len() as u32).to_be_bytes());
+        for value in values {
+            if let Some(value) = value {
+                buffer.push(1);
+                write_term(&mut buffer, value);
+            } else {
+                buffer.push(0);
+            }
+        }
+        writer.write_all(&buffer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
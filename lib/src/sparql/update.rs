@@ -0,0 +1,443 @@
+use crate::model::*;
+use crate::sparql::algebra::{
+    DatasetSpec, GraphPattern, GraphTarget, GraphUpdateOperation, NamedNodeOrVariable, QuadPattern,
+    QueryHints, TermOrVariable,
+};
+use crate::sparql::collation::Collator;
+use crate::sparql::eval::SimpleEvaluator;
+use crate::sparql::model::{QueryResult, Variable};
+use crate::sparql::plan::DatasetView;
+use crate::sparql::plan_builder::PlanBuilder;
+use crate::sparql::ServiceHandler;
+use crate::store::numeric_encoder::*;
+use crate::store::{
+    add_encoded_graph, copy_encoded_graph, load_graph, move_encoded_graph, ReadableEncodedStore,
+    WritableEncodedStore,
+};
+use crate::{Error, FileSyntax, GraphSyntax, Result};
+use oxiri::Iri;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Evaluates [SPARQL 1.1 Update](https://www.w3.org/TR/sparql11-update/) operations.
+///
+/// The `read` store is used to evaluate the `WHERE` clauses and the changes are written into `write`.
+pub(crate) struct SimpleUpdateEvaluator<'a, R, W> {
+    read: R,
+    write: &'a mut W,
+    base_iri: Option<Iri<String>>,
+    default_graph_as_union: bool,
+    service_handler: Rc<dyn ServiceHandler>,
+    /// The directory from which `LOAD` is allowed to read files, it is disabled if `None`
+    load_directory: Option<&'a Path>,
+}
+
+impl<'a, R: ReadableEncodedStore + Clone, W: WritableEncodedStore> SimpleUpdateEvaluator<'a, R, W> {
+    pub fn new(
+        read: R,
+        write: &'a mut W,
+        base_iri: Option<Iri<String>>,
+        default_graph_as_union: bool,
+        service_handler: Rc<dyn ServiceHandler>,
+        load_directory: Option<&'a Path>,
+    ) -> Self {
+        Self {
+            read,
+            write,
+            base_iri,
+            default_graph_as_union,
+            service_handler,
+            load_directory,
+        }
+    }
+
+    pub fn eval(&mut self, update: &GraphUpdateOperation) -> Result<()> {
+        match update {
+            GraphUpdateOperation::InsertData { data } => self.eval_insert_data(data),
+            GraphUpdateOperation::DeleteData { data } => self.eval_delete_data(data),
+            GraphUpdateOperation::DeleteInsert {
+                delete,
+                insert,
+                using,
+                algebra,
+            } => self.eval_delete_insert(delete, insert, using, algebra),
+            GraphUpdateOperation::Load { silent, from, to } => {
+                if let Err(error) = self.eval_load(from, to) {
+                    if *silent {
+                        Ok(())
+                    } else {
+                        Err(error)
+                    }
+                } else {
+                    Ok(())
+                }
+            }
+            GraphUpdateOperation::Clear { silent, graph } => self.eval_clear(graph, *silent),
+            GraphUpdateOperation::Create { silent, graph } => self.eval_create(graph, *silent),
+            GraphUpdateOperation::Drop { silent, graph } => self.eval_drop(graph, *silent),
+            GraphUpdateOperation::Add { silent, from, to } => {
+                if self.check_source_graph(from, *silent)? {
+                    let to = self.write.encode_graph_name(to)?;
+                    add_encoded_graph(&self.read, &mut *self.write, from.into(), to)
+                } else {
+                    Ok(())
+                }
+            }
+            GraphUpdateOperation::Copy { silent, from, to } => {
+                if self.check_source_graph(from, *silent)? {
+                    let to = self.write.encode_graph_name(to)?;
+                    copy_encoded_graph(&self.read, &mut *self.write, from.into(), to)
+                } else {
+                    Ok(())
+                }
+            }
+            GraphUpdateOperation::Move { silent, from, to } => {
+                if self.check_source_graph(from, *silent)? {
+                    let to = self.write.encode_graph_name(to)?;
+                    move_encoded_graph(&self.read, &mut *self.write, from.into(), to)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn eval_insert_data(&mut self, data: &[QuadPattern]) -> Result<()> {
+        let mut bnodes = HashMap::new();
+        for quad in data {
+            if let Some(quad) = instantiate_quad_pattern(quad, &|_: &Variable| None, &mut bnodes) {
+                self.insert(&quad)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn eval_delete_data(&mut self, data: &[QuadPattern]) -> Result<()> {
+        let mut bnodes = HashMap::new();
+        for quad in data {
+            if let Some(quad) = instantiate_quad_pattern(quad, &|_: &Variable| None, &mut bnodes) {
+                self.write.remove_encoded(&(&quad).into())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn eval_delete_insert(
+        &mut self,
+        delete: &[QuadPattern],
+        insert: &[QuadPattern],
+        using: &DatasetSpec,
+        algebra: &GraphPattern,
+    ) -> Result<()> {
+        // We evaluate the WHERE clause fully before applying any change
+        let mut to_delete = Vec::new();
+        let mut to_insert = Vec::new();
+        let dataset = DatasetView::new(self.read.clone(), self.default_graph_as_union, using)?;
+        let (plan, variables) = PlanBuilder::build(
+            dataset.encoder(),
+            &dataset,
+            &HashSet::default(),
+            &QueryHints::default(),
+            algebra,
+        )?;
+        let evaluator = SimpleEvaluator::new(
+            dataset,
+            self.base_iri.clone(),
+            self.service_handler.clone(),
+            Rc::new(HashMap::default()),
+            Rc::new(HashMap::default()),
+            Collator::default(),
+            None,
+            false,
+            false,
+        );
+        if let QueryResult::Solutions(solutions) =
+            evaluator.evaluate_select_plan(&plan, &variables, &[])?
+        {
+            for solution in solutions {
+                let solution = solution?;
+                let values = |v: &Variable| solution.get(v).cloned();
+                let mut bnodes = HashMap::new();
+                for quad in delete {
+                    if let Some(quad) = instantiate_quad_pattern(quad, &values, &mut bnodes) {
+                        to_delete.push(quad);
+                    }
+                }
+                for quad in insert {
+                    if let Some(quad) = instantiate_quad_pattern(quad, &values, &mut bnodes) {
+                        to_insert.push(quad);
+                    }
+                }
+            }
+        }
+
+        for quad in &to_delete {
+            self.write.remove_encoded(&quad.into())?;
+        }
+        for quad in &to_insert {
+            self.insert(quad)?;
+        }
+        Ok(())
+    }
+
+    fn eval_load(&mut self, from: &NamedNode, to: &Option<NamedNode>) -> Result<()> {
+        let directory = self.load_directory.ok_or_else(|| {
+            Error::msg(
+                "LOAD is disabled, it could be enabled with QueryOptions::with_load_directory",
+            )
+        })?;
+        //TODO: support other IRI schemes
+        let path = file_iri_to_path(from.as_str())?.canonicalize()?;
+        if !path.starts_with(directory.canonicalize()?) {
+            return Err(Error::msg(format!(
+                "LOAD is only allowed for the files in {}, found {}",
+                directory.display(),
+                from
+            )));
+        }
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let syntax = [
+            GraphSyntax::NTriples,
+            GraphSyntax::Turtle,
+            GraphSyntax::RdfXml,
+        ]
+        .iter()
+        .cloned()
+        .find(|s| s.file_extension() == extension)
+        .ok_or_else(|| Error::msg(format!("Not able to guess the syntax of the file {}", from)))?;
+        let to_graph_name = match to {
+            Some(to) => to.clone().into(),
+            None => GraphName::DefaultGraph,
+        };
+        load_graph(
+            &mut *self.write,
+            BufReader::new(File::open(&path)?),
+            syntax,
+            &to_graph_name,
+            Some(from.as_str()),
+        )
+    }
+
+    fn eval_clear(&mut self, graph: &GraphTarget, silent: bool) -> Result<()> {
+        match graph {
+            GraphTarget::NamedNode(graph_name) => {
+                if self.check_source_graph(&graph_name.clone().into(), silent)? {
+                    self.write.clear_encoded_graph(graph_name.into())
+                } else {
+                    Ok(())
+                }
+            }
+            GraphTarget::DefaultGraph => self.write.clear_encoded_graph(ENCODED_DEFAULT_GRAPH),
+            GraphTarget::NamedGraphs => {
+                for graph_name in self.named_graphs()? {
+                    self.write.clear_encoded_graph(graph_name)?;
+                }
+                Ok(())
+            }
+            GraphTarget::AllGraphs => {
+                self.write.clear_encoded_graph(ENCODED_DEFAULT_GRAPH)?;
+                for graph_name in self.named_graphs()? {
+                    self.write.clear_encoded_graph(graph_name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn eval_drop(&mut self, graph: &GraphTarget, silent: bool) -> Result<()> {
+        match graph {
+            GraphTarget::NamedNode(graph_name) => {
+                if self.check_source_graph(&graph_name.clone().into(), silent)? {
+                    self.write.remove_encoded_named_graph(graph_name.into())
+                } else {
+                    Ok(())
+                }
+            }
+            GraphTarget::DefaultGraph => self.write.clear_encoded_graph(ENCODED_DEFAULT_GRAPH),
+            GraphTarget::NamedGraphs => {
+                for graph_name in self.named_graphs()? {
+                    self.write.remove_encoded_named_graph(graph_name)?;
+                }
+                Ok(())
+            }
+            GraphTarget::AllGraphs => {
+                self.write.clear_encoded_graph(ENCODED_DEFAULT_GRAPH)?;
+                for graph_name in self.named_graphs()? {
+                    self.write.remove_encoded_named_graph(graph_name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn eval_create(&mut self, graph: &NamedNode, silent: bool) -> Result<()> {
+        if self.read.contains_encoded_named_graph(graph.into())? {
+            if silent {
+                Ok(())
+            } else {
+                Err(Error::msg(format!("The graph {} already exists", graph)))
+            }
+        } else {
+            let graph_name = self.write.encode_named_node(graph)?;
+            self.write.insert_encoded_named_graph(graph_name)
+        }
+    }
+
+    /// Checks that the graph an operation reads or removes exists, like the source graph of `ADD`, `COPY` and `MOVE`
+    /// or the graph of `CLEAR GRAPH` and `DROP GRAPH`
+    ///
+    /// Returns `false` if it does not exist and the operation is `SILENT`.
+    fn check_source_graph(&self, graph: &GraphName, silent: bool) -> Result<bool> {
+        if graph.is_default_graph() || self.read.contains_encoded_named_graph(graph.into())? {
+            Ok(true)
+        } else if silent {
+            Ok(false)
+        } else {
+            Err(Error::msg(format!("The graph {} does not exist", graph)))
+        }
+    }
+
+    /// The named graphs of the store, collected before being modified
+    fn named_graphs(&self) -> Result<Vec<EncodedTerm>> {
+        self.read.encoded_named_graphs().collect()
+    }
+
+    fn insert(&mut self, quad: &Quad) -> Result<()> {
+        let quad = self.write.encode_quad(quad)?;
+        self.write.insert_encoded(&quad)
+    }
+}
+
+fn instantiate_quad_pattern(
+    pattern: &QuadPattern,
+    values: &impl Fn(&Variable) -> Option<Term>,
+    bnodes: &mut HashMap<BlankNode, BlankNode>,
+) -> Option<Quad> {
+    let subject = match instantiate_term(&pattern.subject, values, bnodes)? {
+        Term::NamedNode(node) => Subject::from(node),
+        Term::BlankNode(node) => Subject::from(node),
+        Term::Literal(_) => return None,
+        Term::Triple(triple) => Subject::from(triple),
+    };
+    let predicate = instantiate_named_node(&pattern.predicate, values)?;
+    let object = instantiate_term(&pattern.object, values, bnodes)?;
+    let graph_name = if let Some(graph_name) = &pattern.graph_name {
+        GraphName::from(instantiate_named_node(graph_name, values)?)
+    } else {
+        GraphName::DefaultGraph
+    };
+    Some(Quad::new(subject, predicate, object, graph_name))
+}
+
+fn instantiate_term(
+    term: &TermOrVariable,
+    values: &impl Fn(&Variable) -> Option<Term>,
+    bnodes: &mut HashMap<BlankNode, BlankNode>,
+) -> Option<Term> {
+    match term {
+        TermOrVariable::Term(Term::BlankNode(bnode)) => Some(
+            bnodes
+                .entry(bnode.clone())
+                .or_insert_with(BlankNode::default)
+                .clone()
+                .into(),
+        ),
+        TermOrVariable::Term(term) => Some(term.clone()),
+        TermOrVariable::Variable(v) => values(v),
+        TermOrVariable::Triple(triple) => {
+            let subject = match instantiate_term(&triple.subject, values, bnodes)? {
+                Term::NamedNode(node) => Subject::from(node),
+                Term::BlankNode(node) => Subject::from(node),
+                Term::Literal(_) => return None,
+                Term::Triple(triple) => Subject::from(triple),
+            };
+            let predicate = instantiate_named_node(&triple.predicate, values)?;
+            let object = instantiate_term(&triple.object, values, bnodes)?;
+            Some(Triple::new(subject, predicate, object).into())
+        }
+    }
+}
+
+fn instantiate_named_node(
+    term: &NamedNodeOrVariable,
+    values: &impl Fn(&Variable) -> Option<Term>,
+) -> Option<NamedNode> {
+    match term {
+        NamedNodeOrVariable::NamedNode(node) => Some(node.clone()),
+        NamedNodeOrVariable::Variable(v) => match values(v)? {
+            Term::NamedNode(node) => Some(node),
+            _ => None,
+        },
+    }
+}
+
+/// Converts a `file:` IRI to a file system path
+///
+/// The host should be empty or `localhost` and the percent-encoded characters of the path are decoded.
+fn file_iri_to_path(iri: &str) -> Result<PathBuf> {
+    let invalid = || {
+        Error::msg(format!(
+            "LOAD is only supported for file: IRIs, found <{}>",
+            iri
+        ))
+    };
+    if !iri
+        .get(..5)
+        .map_or(false, |s| s.eq_ignore_ascii_case("file:"))
+        || iri.contains(&['?', '#'][..])
+    {
+        return Err(invalid());
+    }
+    let mut path = &iri[5..];
+    if path.starts_with("//") {
+        let end = path[2..].find('/').ok_or_else(invalid)? + 2;
+        let host = &path[2..end];
+        if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+            return Err(Error::msg(format!(
+                "LOAD is only supported for local files, found <{}>",
+                iri
+            )));
+        }
+        path = &path[end..];
+    }
+    if !path.starts_with('/') {
+        return Err(invalid());
+    }
+    let mut segments = Vec::new();
+    for segment in path[1..].split('/') {
+        let segment = percent_decode(segment).ok_or_else(invalid)?;
+        // The decoded segments should not introduce new path separators
+        if segment.contains(&['/', '\\', '\0'][..]) {
+            return Err(invalid());
+        }
+        segments.push(segment);
+    }
+    let mut result = PathBuf::new();
+    if cfg!(windows) && segments[0].len() == 2 && segments[0].ends_with(':') {
+        // file:///C:/foo is the path C:\foo
+        result.push(format!("{}\\", segments.remove(0)));
+    } else {
+        result.push("/");
+    }
+    result.extend(segments);
+    Ok(result)
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut input = value.bytes();
+    while let Some(b) = input.next() {
+        if b == b'%' {
+            let high = char::from(input.next()?).to_digit(16)?;
+            let low = char::from(input.next()?).to_digit(16)?;
+            bytes.push(u8::try_from(high * 16 + low).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
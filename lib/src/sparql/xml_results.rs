@@ -55,37 +55,7 @@ pub fn write_xml_results<W: Write>(results: QueryResult<'_>, sink: W) -> Result<
                     let mut binding_tag = BytesStart::borrowed_name(b"binding");
                     binding_tag.push_attribute(("name", variable.as_str()));
                     writer.write_event(Event::Start(binding_tag))?;
-                    match value {
-                        Term::NamedNode(uri) => {
-                            writer.write_event(Event::Start(BytesStart::borrowed_name(b"uri")))?;
-                            writer.write_event(Event::Text(BytesText::from_plain_str(
-                                uri.as_str(),
-                            )))?;
-                            writer.write_event(Event::End(BytesEnd::borrowed(b"uri")))?;
-                        }
-                        Term::BlankNode(bnode) => {
-                            writer
-                                .write_event(Event::Start(BytesStart::borrowed_name(b"bnode")))?;
-                            writer.write_event(Event::Text(BytesText::from_plain_str(
-                                bnode.as_str(),
-                            )))?;
-                            writer.write_event(Event::End(BytesEnd::borrowed(b"bnode")))?;
-                        }
-                        Term::Literal(literal) => {
-                            let mut literal_tag = BytesStart::borrowed_name(b"literal");
-                            if let Some(language) = literal.language() {
-                                literal_tag.push_attribute(("xml:lang", language));
-                            } else if !literal.is_plain() {
-                                literal_tag
-                                    .push_attribute(("datatype", literal.datatype().as_str()));
-                            }
-                            writer.write_event(Event::Start(literal_tag))?;
-                            writer.write_event(Event::Text(BytesText::from_plain_str(
-                                literal.value(),
-                            )))?;
-                            writer.write_event(Event::End(BytesEnd::borrowed(b"literal")))?;
-                        }
-                    }
+                    write_xml_term(value, &mut writer)?;
                     writer.write_event(Event::End(BytesEnd::borrowed(b"binding")))?;
                 }
                 writer.write_event(Event::End(BytesEnd::borrowed(b"result")))?;
@@ -102,6 +72,46 @@ pub fn write_xml_results<W: Write>(results: QueryResult<'_>, sink: W) -> Result<
     Ok(writer.into_inner())
 }
 
+fn write_xml_term<W: Write>(term: &Term, writer: &mut Writer<W>) -> Result<()> {
+    match term {
+        Term::NamedNode(uri) => {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"uri")))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(uri.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"uri")))?;
+        }
+        Term::BlankNode(bnode) => {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"bnode")))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(bnode.as_str())))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"bnode")))?;
+        }
+        Term::Literal(literal) => {
+            let mut literal_tag = BytesStart::borrowed_name(b"literal");
+            if let Some(language) = literal.language() {
+                literal_tag.push_attribute(("xml:lang", language));
+            } else if !literal.is_plain() {
+                literal_tag.push_attribute(("datatype", literal.datatype().as_str()));
+            }
+            writer.write_event(Event::Start(literal_tag))?;
+            writer.write_event(Event::Text(BytesText::from_plain_str(literal.value())))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"literal")))?;
+        }
+        Term::Triple(triple) => {
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"triple")))?;
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"subject")))?;
+            write_xml_term(&triple.subject.clone().into(), writer)?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"subject")))?;
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"predicate")))?;
+            write_xml_term(&triple.predicate.clone().into(), writer)?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"predicate")))?;
+            writer.write_event(Event::Start(BytesStart::borrowed_name(b"object")))?;
+            write_xml_term(&triple.object, writer)?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"object")))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(b"triple")))?;
+        }
+    }
+    Ok(())
+}
+
 pub fn read_xml_results<'a>(source: impl BufRead + 'a) -> Result<QueryResult<'a>> {
     enum State {
         Start,
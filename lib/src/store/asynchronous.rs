@@ -0,0 +1,177 @@
+//! Asynchronous wrapper of the stores for the [tokio](https://tokio.rs) runtime.
+
+use crate::model::*;
+use crate::sparql::{QueryOptions, QueryResult, QuerySolutionsIterator, Variable};
+use crate::store::Store;
+use crate::{DatasetSyntax, Error, GraphSyntax, Result};
+use std::io::BufRead;
+use tokio::task::spawn_blocking;
+
+/// Wraps a store to provide `async` variants of its operations.
+///
+/// The operations are executed on the blocking thread pool of the tokio runtime,
+/// so the futures could be awaited from the async tasks of a web service without blocking them.
+///
+/// The SPARQL query results and the quads matching a pattern are fully loaded in memory before being returned.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::store::AsyncStore;
+/// use oxigraph::sparql::{QueryOptions, QueryResult};
+/// use oxigraph::{GraphSyntax, MemoryStore, Result};
+///
+/// # fn main() -> Result<()> {
+/// tokio::runtime::Runtime::new()?.block_on(async {
+///     let store = AsyncStore::new(MemoryStore::new());
+///
+///     // insertion
+///     let file = b"<http://example.com> <http://example.com> <http://example.com> .";
+///     store.load_graph(file.as_ref(), GraphSyntax::NTriples, GraphName::DefaultGraph, None).await?;
+///
+///     // quad filter
+///     let ex = NamedNode::new("http://example.com")?;
+///     let results = store.quads_for_pattern(Some(ex.clone().into()), None, None, None).await?;
+///     assert_eq!(vec![Quad::new(ex.clone(), ex.clone(), ex.clone(), None)], results);
+///
+///     // SPARQL query
+///     if let QueryResult::Solutions(mut solutions) = store.query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default).await? {
+///         assert_eq!(solutions.next().unwrap()?.get("s"), Some(&ex.into()));
+///     }
+///     Ok(())
+/// })
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncStore<S> {
+    store: S,
+}
+
+impl<S: Store + Clone + Send + Sync + 'static> AsyncStore<S> {
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Returns the wrapped store
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// Executes a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) and returns its results.
+    ///
+    /// `QueryOptions` could not be sent to another thread so `options` is a function building them.
+    pub async fn query(
+        &self,
+        query: impl Into<String>,
+        options: impl FnOnce() -> QueryOptions<'static> + Send + 'static,
+    ) -> Result<QueryResult<'static>> {
+        let query = query.into();
+        let result = self
+            .run(move |store| {
+                let prepared = store.prepare_query(&query, options())?;
+                let result = match prepared.exec()? {
+                    QueryResult::Solutions(solutions) => {
+                        let (variables, values) = solutions.destruct();
+                        CollectedQueryResult::Solutions(variables, values.collect::<Result<_>>()?)
+                    }
+                    QueryResult::Boolean(value) => CollectedQueryResult::Boolean(value),
+                    QueryResult::Graph(triples) => {
+                        CollectedQueryResult::Graph(triples.collect::<Result<_>>()?)
+                    }
+                };
+                Ok(result)
+            })
+            .await?;
+        Ok(match result {
+            CollectedQueryResult::Solutions(variables, values) => QueryResult::Solutions(
+                QuerySolutionsIterator::new(variables, Box::new(values.into_iter().map(Ok))),
+            ),
+            CollectedQueryResult::Boolean(value) => QueryResult::Boolean(value),
+            CollectedQueryResult::Graph(triples) => {
+                QueryResult::Graph(Box::new(triples.into_iter().map(Ok)))
+            }
+        })
+    }
+
+    /// Executes a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/).
+    ///
+    /// `QueryOptions` could not be sent to another thread so `options` is a function building them.
+    pub async fn update(
+        &self,
+        update: impl Into<String>,
+        options: impl FnOnce() -> QueryOptions<'static> + Send + 'static,
+    ) -> Result<()> {
+        let update = update.into();
+        self.run(move |store| store.prepare_update(&update, options())?.exec())
+            .await
+    }
+
+    /// Retrieves quads with a filter on each quad component
+    pub async fn quads_for_pattern(
+        &self,
+        subject: Option<Subject>,
+        predicate: Option<NamedNode>,
+        object: Option<Term>,
+        graph_name: Option<GraphName>,
+    ) -> Result<Vec<Quad>> {
+        self.run(move |store| {
+            store
+                .quads_for_pattern(
+                    subject.as_ref(),
+                    predicate.as_ref(),
+                    object.as_ref(),
+                    graph_name.as_ref(),
+                )
+                .collect()
+        })
+        .await
+    }
+
+    /// Loads a graph file (i.e. triples) into the store
+    ///
+    /// See `MemoryStore::load_graph` for the loading semantics.
+    pub async fn load_graph(
+        &self,
+        reader: impl BufRead + Send + 'static,
+        syntax: GraphSyntax,
+        to_graph_name: GraphName,
+        base_iri: Option<String>,
+    ) -> Result<()> {
+        let mut reader = reader;
+        self.run(move |store| {
+            store.load_graph(&mut reader, syntax, &to_graph_name, base_iri.as_deref())
+        })
+        .await
+    }
+
+    /// Loads a dataset file (i.e. quads) into the store
+    ///
+    /// See `MemoryStore::load_dataset` for the loading semantics.
+    pub async fn load_dataset(
+        &self,
+        reader: impl BufRead + Send + 'static,
+        syntax: DatasetSyntax,
+        base_iri: Option<String>,
+    ) -> Result<()> {
+        let mut reader = reader;
+        self.run(move |store| store.load_dataset(&mut reader, syntax, base_iri.as_deref()))
+            .await
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        task: impl FnOnce(S) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let store = self.store.clone();
+        spawn_blocking(move || task(store))
+            .await
+            .map_err(|e| Error::msg(format!("The store task has failed: {}", e)))?
+    }
+}
+
+/// The results of a query collected in order to be sent back from the blocking thread
+enum CollectedQueryResult {
+    Solutions(Vec<Variable>, Vec<Vec<Option<Term>>>),
+    Boolean(bool),
+    Graph(Vec<Triple>),
+}
@@ -0,0 +1,427 @@
+//! Log of the changes committed to a store, used to replicate them into other stores.
+//!
+//! The log is a file of records appended one after the other.
+//! Each record is a commit: its sequence number, the length of its content and the list of its encoded operations.
+//! A record partially written at the end of the file is ignored by the readers and overwritten by the next writer.
+//!
+//! A record is appended before its commit is written to the store and the store keeps the sequence number of its last
+//! logged commit, so the records of the commits that have failed or have been lost in a crash are removed from the log.
+
+use crate::store::numeric_encoder::*;
+use crate::store::WritableEncodedStore;
+use crate::{Error, Result};
+use std::fs::{rename, File, OpenOptions};
+use std::io::{copy, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const INSERT_STR: u8 = 0;
+const INSERT_QUAD: u8 = 1;
+const REMOVE_QUAD: u8 = 2;
+const INSERT_NAMED_GRAPH: u8 = 3;
+const REMOVE_NAMED_GRAPH: u8 = 4;
+const SET_GRAPH_EXPIRATION: u8 = 5;
+const CLEAR_GRAPH: u8 = 6;
+
+const RECORD_HEADER_SIZE: u64 = 12;
+
+/// The operations of a commit, encoded in the format of the log
+#[derive(Default)]
+pub(crate) struct CommitLogRecord {
+    buffer: Vec<u8>,
+}
+
+impl CommitLogRecord {
+    pub fn insert_str(&mut self, key: StrHash, value: &str) {
+        self.buffer.push(INSERT_STR);
+        self.buffer.extend_from_slice(&key.to_be_bytes());
+        self.buffer
+            .extend_from_slice(&(value.len() as u32).to_be_bytes());
+        self.buffer.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn insert(&mut self, quad: &EncodedQuad) {
+        self.buffer.push(INSERT_QUAD);
+        write_spog_quad(&mut self.buffer, quad);
+    }
+
+    pub fn remove(&mut self, quad: &EncodedQuad) {
+        self.buffer.push(REMOVE_QUAD);
+        write_spog_quad(&mut self.buffer, quad);
+    }
+
+    pub fn insert_named_graph(&mut self, graph_name: EncodedTerm) {
+        self.buffer.push(INSERT_NAMED_GRAPH);
+        write_term(&mut self.buffer, graph_name);
+    }
+
+    pub fn remove_named_graph(&mut self, graph_name: EncodedTerm) {
+        self.buffer.push(REMOVE_NAMED_GRAPH);
+        write_term(&mut self.buffer, graph_name);
+    }
+
+    /// Records the removal of all the quads of a graph, done without removing them one by one
+    pub fn clear_graph(&mut self, graph_name: EncodedTerm) {
+        self.buffer.push(CLEAR_GRAPH);
+        write_term(&mut self.buffer, graph_name);
+    }
+
+    pub fn set_graph_expiration(
+        &mut self,
+        graph_name: EncodedTerm,
+        expiration: Option<SystemTime>,
+    ) -> Result<()> {
+        self.buffer.push(SET_GRAPH_EXPIRATION);
+        write_term(&mut self.buffer, graph_name);
+        if let Some(expiration) = expiration {
+            self.buffer.push(1);
+            write_timestamp(&mut self.buffer, expiration)?;
+        } else {
+            self.buffer.push(0);
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Applies the operations of the record to a store
+    pub fn apply(&self, store: &mut impl CommitLogTarget) -> Result<()> {
+        let mut reader = self.buffer.as_slice();
+        while !reader.is_empty() {
+            let mut kind = [0];
+            reader.read_exact(&mut kind)?;
+            match kind[0] {
+                INSERT_STR => {
+                    let mut key = [0; 16];
+                    reader.read_exact(&mut key)?;
+                    let mut len = [0; 4];
+                    reader.read_exact(&mut len)?;
+                    let mut value = vec![0; u32::from_be_bytes(len) as usize];
+                    reader.read_exact(&mut value)?;
+                    store.insert_str(StrHash::from_be_bytes(key), &String::from_utf8(value)?)?;
+                }
+                INSERT_QUAD => store.insert_encoded(&reader.read_spog_quad()?)?,
+                REMOVE_QUAD => store.remove_encoded(&reader.read_spog_quad()?)?,
+                INSERT_NAMED_GRAPH => store.insert_encoded_named_graph(reader.read_term()?)?,
+                REMOVE_NAMED_GRAPH => store.remove_encoded_named_graph(reader.read_term()?)?,
+                SET_GRAPH_EXPIRATION => {
+                    let graph_name = reader.read_term()?;
+                    let mut flag = [0];
+                    reader.read_exact(&mut flag)?;
+                    let expiration = if flag[0] == 0 {
+                        None
+                    } else {
+                        Some(read_timestamp(&mut reader)?)
+                    };
+                    store.set_encoded_graph_expiration(graph_name, expiration)?;
+                }
+                CLEAR_GRAPH => store.clear_encoded_graph(reader.read_term()?)?,
+                kind => {
+                    return Err(Error::msg(format!(
+                        "Unexpected operation kind in the commit log: {}",
+                        kind
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A store the records of a commit log could be applied to
+pub(crate) trait CommitLogTarget: WritableEncodedStore {
+    fn set_encoded_graph_expiration(
+        &mut self,
+        graph_name: EncodedTerm,
+        expiration: Option<SystemTime>,
+    ) -> Result<()>;
+}
+
+/// Appends the commits of a store to its log
+pub(crate) struct CommitLogWriter {
+    path: PathBuf,
+    state: Mutex<CommitLogWriterState>,
+}
+
+struct CommitLogWriterState {
+    file: File,
+    /// The position after the last complete record
+    end: u64,
+    next_sequence: u64,
+}
+
+impl CommitLogWriter {
+    /// Opens the log, creating it if it does not exist yet
+    ///
+    /// `committed` is the sequence number of the last commit written to the store, if known.
+    /// The records after it are the ones of commits that have failed and are removed.
+    pub fn open(path: &Path, committed: Option<u64>) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+        let len = file.metadata()?.len();
+        let mut next_sequence = 1;
+        // The position after the last complete record, the rest is a partially written record
+        let mut end = 0;
+        {
+            let mut reader = BufReader::new(&mut file);
+            while let Some((sequence, record_len)) = read_record_header(&mut reader)? {
+                let record_end = end + RECORD_HEADER_SIZE + record_len;
+                if record_end > len || committed.map_or(false, |committed| sequence > committed) {
+                    break;
+                }
+                next_sequence = sequence + 1;
+                end = record_end;
+                reader.seek(SeekFrom::Start(end))?;
+            }
+        }
+        if let Some(committed) = committed {
+            // The log has been truncated after the last commit
+            next_sequence = next_sequence.max(committed + 1);
+        }
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+        Ok(Self {
+            path: path.to_owned(),
+            state: Mutex::new(CommitLogWriterState {
+                file,
+                end,
+                next_sequence,
+            }),
+        })
+    }
+
+    /// Appends a commit to the log and then writes it with `write`
+    ///
+    /// `write` is given the sequence number of the commit in order to store it with the commit.
+    /// The log lock is kept during the write in order for the log to follow the order of the commits.
+    /// If the append or the write fails, the log is truncated back to its previous end and nothing is committed.
+    pub fn commit(
+        &self,
+        record: CommitLogRecord,
+        sync: bool,
+        write: impl FnOnce(u64) -> Result<()>,
+    ) -> Result<()> {
+        let mut state = self
+            .state
+            .lock()
+            .expect("the commit log mutex has been poisoned because of a panic");
+        let sequence = state.next_sequence;
+        let mut buffer = Vec::with_capacity(RECORD_HEADER_SIZE as usize + record.buffer.len());
+        buffer.extend_from_slice(&sequence.to_be_bytes());
+        buffer.extend_from_slice(&(record.buffer.len() as u32).to_be_bytes());
+        buffer.extend_from_slice(&record.buffer);
+        let result = state.append(&buffer, sync).and_then(|()| write(sequence));
+        if let Err(error) = result {
+            state.truncate_to_end()?;
+            return Err(error);
+        }
+        state.end += buffer.len() as u64;
+        state.next_sequence += 1;
+        Ok(())
+    }
+
+    /// Removes from the log the records with a sequence number lower or equal to `up_to`
+    ///
+    /// The remaining records are copied to a new file that replaces the log.
+    pub fn truncate(&self, up_to: u64) -> Result<()> {
+        let mut state = self
+            .state
+            .lock()
+            .expect("the commit log mutex has been poisoned because of a panic");
+        let mut start = 0;
+        {
+            let mut reader = BufReader::new(&mut state.file);
+            reader.seek(SeekFrom::Start(0))?;
+            while let Some((sequence, record_len)) = read_record_header(&mut reader)? {
+                if sequence > up_to {
+                    break;
+                }
+                start += RECORD_HEADER_SIZE + record_len;
+                reader.seek(SeekFrom::Start(start))?;
+            }
+        }
+        let end = state.end;
+        let mut new_path = self.path.clone().into_os_string();
+        new_path.push(".new");
+        let new_path = PathBuf::from(new_path);
+        {
+            let mut new_file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&new_path)?;
+            state.file.seek(SeekFrom::Start(start))?;
+            copy(&mut (&mut state.file).take(end - start), &mut new_file)?;
+            new_file.sync_all()?;
+        }
+        rename(&new_path, &self.path)?;
+        state.file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+        state.end = end - start;
+        let end = state.end;
+        state.file.seek(SeekFrom::Start(end))?;
+        Ok(())
+    }
+}
+
+impl CommitLogWriterState {
+    fn append(&mut self, buffer: &[u8], sync: bool) -> Result<()> {
+        self.file.write_all(buffer)?;
+        if sync {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Removes what has been written after the last complete record
+    fn truncate_to_end(&mut self) -> Result<()> {
+        self.file.set_len(self.end)?;
+        self.file.seek(SeekFrom::Start(self.end))?;
+        Ok(())
+    }
+}
+
+/// Iterates on the complete records of a log with a sequence number greater than `after`
+///
+/// It fails if the records following `after` have been removed by a truncation of the log.
+pub(crate) struct CommitLogReader<R: Read + Seek> {
+    reader: R,
+    after: u64,
+}
+
+impl<R: Read + Seek> CommitLogReader<R> {
+    pub fn new(reader: R, after: u64) -> Self {
+        Self { reader, after }
+    }
+
+    fn read_next(&mut self) -> Result<Option<(u64, CommitLogRecord)>> {
+        while let Some((sequence, len)) = read_record_header(&mut self.reader)? {
+            if sequence <= self.after {
+                self.reader.seek(SeekFrom::Current(len as i64))?;
+                continue;
+            }
+            if sequence != self.after + 1 {
+                return Err(Error::msg(format!(
+                    "The commit log does not contain the commit {} anymore, it has been truncated",
+                    self.after + 1
+                )));
+            }
+            let mut buffer = vec![0; len as usize];
+            return match self.reader.read_exact(&mut buffer) {
+                Ok(()) => {
+                    self.after = sequence;
+                    Ok(Some((sequence, CommitLogRecord { buffer })))
+                }
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+                Err(error) => Err(error.into()),
+            };
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read + Seek> Iterator for CommitLogReader<R> {
+    type Item = Result<(u64, CommitLogRecord)>;
+
+    fn next(&mut self) -> Option<Result<(u64, CommitLogRecord)>> {
+        self.read_next().transpose()
+    }
+}
+
+pub(crate) fn write_timestamp(buffer: &mut Vec<u8>, timestamp: SystemTime) -> Result<()> {
+    let timestamp = timestamp.duration_since(UNIX_EPOCH).map_err(Error::wrap)?;
+    buffer.extend_from_slice(&timestamp.as_secs().to_be_bytes());
+    buffer.extend_from_slice(&timestamp.subsec_nanos().to_be_bytes());
+    Ok(())
+}
+
+pub(crate) fn read_timestamp(reader: &mut &[u8]) -> Result<SystemTime> {
+    let mut secs = [0; 8];
+    reader.read_exact(&mut secs)?;
+    let mut nanos = [0; 4];
+    reader.read_exact(&mut nanos)?;
+    Ok(UNIX_EPOCH + Duration::new(u64::from_be_bytes(secs), u32::from_be_bytes(nanos)))
+}
+
+/// Reads the sequence number and the content length of a record or returns `None` at the end of the log
+fn read_record_header(reader: &mut impl Read) -> Result<Option<(u64, u64)>> {
+    let mut header = [0; RECORD_HEADER_SIZE as usize];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {
+            let mut sequence = [0; 8];
+            sequence.copy_from_slice(&header[..8]);
+            let mut len = [0; 4];
+            len.copy_from_slice(&header[8..]);
+            Ok(Some((
+                u64::from_be_bytes(sequence),
+                u32::from_be_bytes(len).into(),
+            )))
+        }
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+#[test]
+fn commit() -> Result<()> {
+    use crate::model::NamedNode;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+
+    let graph = EncodedTerm::from(&NamedNode::new("http://example.com")?);
+    let record = || {
+        let mut record = CommitLogRecord::default();
+        record.insert_named_graph(graph);
+        record
+    };
+    let mut path = temp_dir();
+    path.push(random::<u128>().to_string());
+    let sequences = |after| -> Result<Vec<u64>> {
+        CommitLogReader::new(BufReader::new(File::open(&path)?), after)
+            .map(|record| Ok(record?.0))
+            .collect()
+    };
+
+    {
+        let writer = CommitLogWriter::open(&path, None)?;
+        writer.commit(record(), false, |sequence| {
+            assert_eq!(sequence, 1);
+            Ok(())
+        })?;
+        // A failed write is removed from the log
+        assert!(writer
+            .commit(record(), false, |_| Err(Error::msg("write failure")))
+            .is_err());
+        assert_eq!(sequences(0)?, vec![1]);
+        writer.commit(record(), false, |sequence| {
+            assert_eq!(sequence, 2);
+            Ok(())
+        })?;
+        assert_eq!(sequences(0)?, vec![1, 2]);
+    }
+
+    // The commits not written to the store are removed from the log
+    {
+        let writer = CommitLogWriter::open(&path, Some(1))?;
+        assert_eq!(sequences(0)?, vec![1]);
+        writer.commit(record(), false, |sequence| {
+            assert_eq!(sequence, 2);
+            Ok(())
+        })?;
+
+        writer.truncate(1)?;
+        assert!(sequences(0).is_err());
+        assert_eq!(sequences(1)?, vec![2]);
+        writer.commit(record(), false, |_| Ok(()))?;
+        assert_eq!(sequences(1)?, vec![2, 3]);
+    }
+
+    remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,713 @@
+//! Object-safe traits allowing to choose the store implementation at runtime.
+
+use crate::model::skolemization::Skolemizer;
+use crate::model::*;
+use crate::sparql::{QueryExplanation, QueryOptions, QueryResult, QuerySolution, QueryStats};
+use crate::store::hybrid::{HybridPreparedQuery, HybridPreparedUpdate};
+use crate::store::memory::{MemoryPreparedQuery, MemoryPreparedUpdate};
+#[cfg(feature = "rocksdb")]
+use crate::store::rocksdb::{RocksDbPreparedQuery, RocksDbPreparedUpdate};
+#[cfg(feature = "sled")]
+use crate::store::sled::{SledPreparedQuery, SledPreparedUpdate};
+#[cfg(feature = "rocksdb")]
+use crate::store::RocksDbStore;
+#[cfg(feature = "sled")]
+use crate::store::SledStore;
+use crate::store::{HybridStore, MemoryStore};
+use crate::{DatasetSyntax, GraphSyntax, Result};
+use std::io::{BufRead, Write};
+
+/// The operations shared by all the stores, usable as a trait object.
+///
+/// It allows to choose the store implementation at runtime, for example from a configuration file.
+/// The methods are the ones of the stores, with the readers and writers passed as trait objects and the iterators boxed.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::store::Store;
+/// use oxigraph::sparql::{QueryOptions, QueryResult};
+/// use oxigraph::{MemoryStore, Result};
+///
+/// let store: Box<dyn Store> = Box::new(MemoryStore::new());
+///
+/// // insertion
+/// let ex = NamedNode::new("http://example.com")?;
+/// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+/// store.insert(&quad)?;
+///
+/// // quad filter
+/// let results = store.quads_for_pattern(Some(&ex.clone().into()), None, None, None).collect::<Result<Vec<_>>>()?;
+/// assert_eq!(vec![quad], results);
+///
+/// // SPARQL query
+/// let prepared_query = store.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default())?;
+/// if let QueryResult::Solutions(mut solutions) = prepared_query.exec()? {
+///     assert_eq!(solutions.next().unwrap()?.get("s"), Some(&ex.into()));
+/// }
+/// # Result::Ok(())
+/// ```
+pub trait Store {
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) and returns an object that could be used to execute it.
+    fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedQuery>>;
+
+    /// Prepares a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/) and returns an object that could be used to execute it.
+    fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedUpdate>>;
+
+    /// Retrieves quads with a filter on each quad component
+    fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Box<dyn Iterator<Item = Result<Quad>> + '_>;
+
+    /// Checks if this store contains a given quad
+    fn contains(&self, quad: &Quad) -> Result<bool>;
+
+    /// Returns the number of quads in the store
+    fn len(&self) -> Result<usize>;
+
+    /// Returns if the store is empty
+    fn is_empty(&self) -> Result<bool>;
+
+    /// Loads a graph file (i.e. triples) into the store
+    fn load_graph(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> Result<()>;
+
+    /// Loads a dataset file (i.e. quads) into the store
+    fn load_dataset(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()>;
+
+    /// Loads a graph file (i.e. triples) into the store after replacing its blank nodes by skolem IRIs
+    ///
+    /// The blank nodes of the file get fresh identifiers like with `load_graph` before being skolemized.
+    /// The file is fully parsed in memory before being inserted.
+    fn load_skolemized_graph(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+        skolemizer: &Skolemizer,
+    ) -> Result<()> {
+        let parsed = MemoryStore::new();
+        parsed.load_graph(reader, syntax, &GraphName::DefaultGraph, base_iri)?;
+        for quad in parsed.quads_for_pattern(None, None, None, None) {
+            let triple = skolemizer.skolemize_triple(&quad.into());
+            self.insert(&triple.in_graph(to_graph_name.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Loads a dataset file (i.e. quads) into the store after replacing its blank nodes by skolem IRIs
+    ///
+    /// The blank nodes of the file get fresh identifiers like with `load_dataset` before being skolemized.
+    /// The file is fully parsed in memory before being inserted.
+    fn load_skolemized_dataset(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+        skolemizer: &Skolemizer,
+    ) -> Result<()> {
+        let parsed = MemoryStore::new();
+        parsed.load_dataset(reader, syntax, base_iri)?;
+        for quad in parsed.quads_for_pattern(None, None, None, None) {
+            self.insert(&skolemizer.skolemize_quad(&quad))?;
+        }
+        Ok(())
+    }
+
+    /// Dumps a store graph into a file
+    fn dump_graph(
+        &self,
+        writer: &mut dyn Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()>;
+
+    /// Dumps the store dataset into a file
+    fn dump_dataset(&self, writer: &mut dyn Write, syntax: DatasetSyntax) -> Result<()>;
+
+    /// Adds a quad to this store
+    fn insert(&self, quad: &Quad) -> Result<()>;
+
+    /// Removes a quad from this store
+    fn remove(&self, quad: &Quad) -> Result<()>;
+
+    /// Returns all the store named graphs
+    fn named_graphs(&self) -> Box<dyn Iterator<Item = Result<NamedOrBlankNode>> + '_>;
+
+    /// Checks if the store contains a given graph
+    fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool>;
+
+    /// Adds a named graph to the store, even if it does not contain any quad
+    fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()>;
+
+    /// Removes all the quads of a graph. The graph itself stays in the store.
+    fn clear_graph(&self, graph_name: &GraphName) -> Result<()>;
+
+    /// Removes a named graph and all its quads from the store
+    fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()>;
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) returned by `Store::prepare_query`.
+pub trait PreparedQuery {
+    /// Evaluates the query and returns its results
+    fn exec(&self) -> Result<QueryResult<'_>>;
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>>;
+
+    /// Returns a description of the plan used to evaluate the query
+    fn explain(&self) -> Result<QueryExplanation>;
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    fn stats(&self) -> QueryStats;
+}
+
+/// A prepared [SPARQL update](https://www.w3.org/TR/sparql11-update/) returned by `Store::prepare_update`.
+pub trait PreparedUpdate {
+    /// Executes the update
+    fn exec(&self) -> Result<()>;
+}
+
+impl Store for MemoryStore {
+    fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedQuery>> {
+        Ok(Box::new(MemoryStore::prepare_query(self, query, options)?))
+    }
+
+    fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedUpdate>> {
+        Ok(Box::new(MemoryStore::prepare_update(
+            self, update, options,
+        )?))
+    }
+
+    fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Box<dyn Iterator<Item = Result<Quad>> + '_> {
+        Box::new(
+            MemoryStore::quads_for_pattern(self, subject, predicate, object, graph_name).map(Ok),
+        )
+    }
+
+    fn contains(&self, quad: &Quad) -> Result<bool> {
+        Ok(MemoryStore::contains(self, quad))
+    }
+
+    fn len(&self) -> Result<usize> {
+        Ok(MemoryStore::len(self))
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        Ok(MemoryStore::is_empty(self))
+    }
+
+    fn load_graph(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        MemoryStore::load_graph(self, reader, syntax, to_graph_name, base_iri)
+    }
+
+    fn load_dataset(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        MemoryStore::load_dataset(self, reader, syntax, base_iri)
+    }
+
+    fn dump_graph(
+        &self,
+        writer: &mut dyn Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        MemoryStore::dump_graph(self, writer, syntax, from_graph_name)
+    }
+
+    fn dump_dataset(&self, writer: &mut dyn Write, syntax: DatasetSyntax) -> Result<()> {
+        MemoryStore::dump_dataset(self, writer, syntax)
+    }
+
+    fn insert(&self, quad: &Quad) -> Result<()> {
+        MemoryStore::insert(self, quad.clone());
+        Ok(())
+    }
+
+    fn remove(&self, quad: &Quad) -> Result<()> {
+        MemoryStore::remove(self, quad);
+        Ok(())
+    }
+
+    fn named_graphs(&self) -> Box<dyn Iterator<Item = Result<NamedOrBlankNode>> + '_> {
+        Box::new(MemoryStore::named_graphs(self).map(Ok))
+    }
+
+    fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        Ok(MemoryStore::contains_named_graph(self, graph_name))
+    }
+
+    fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        MemoryStore::insert_named_graph(self, graph_name);
+        Ok(())
+    }
+
+    fn clear_graph(&self, graph_name: &GraphName) -> Result<()> {
+        MemoryStore::clear_graph(self, graph_name);
+        Ok(())
+    }
+
+    fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        MemoryStore::remove_named_graph(self, graph_name);
+        Ok(())
+    }
+}
+
+impl PreparedQuery for MemoryPreparedQuery {
+    fn exec(&self) -> Result<QueryResult<'_>> {
+        MemoryPreparedQuery::exec(self)
+    }
+
+    fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        MemoryPreparedQuery::exec_with_bindings(self, bindings)
+    }
+
+    fn explain(&self) -> Result<QueryExplanation> {
+        MemoryPreparedQuery::explain(self)
+    }
+
+    fn stats(&self) -> QueryStats {
+        MemoryPreparedQuery::stats(self)
+    }
+}
+
+impl PreparedUpdate for MemoryPreparedUpdate {
+    fn exec(&self) -> Result<()> {
+        MemoryPreparedUpdate::exec(self)
+    }
+}
+
+impl Store for HybridStore {
+    fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedQuery>> {
+        Ok(Box::new(HybridStore::prepare_query(self, query, options)?))
+    }
+
+    fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedUpdate>> {
+        Ok(Box::new(HybridStore::prepare_update(
+            self, update, options,
+        )?))
+    }
+
+    fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Box<dyn Iterator<Item = Result<Quad>> + '_> {
+        Box::new(HybridStore::quads_for_pattern(
+            self, subject, predicate, object, graph_name,
+        ))
+    }
+
+    fn contains(&self, quad: &Quad) -> Result<bool> {
+        HybridStore::contains(self, quad)
+    }
+
+    fn len(&self) -> Result<usize> {
+        Ok(HybridStore::len(self))
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        Ok(HybridStore::is_empty(self))
+    }
+
+    fn load_graph(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        HybridStore::load_graph(self, reader, syntax, to_graph_name, base_iri)
+    }
+
+    fn load_dataset(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        HybridStore::load_dataset(self, reader, syntax, base_iri)
+    }
+
+    fn dump_graph(
+        &self,
+        writer: &mut dyn Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        HybridStore::dump_graph(self, writer, syntax, from_graph_name)
+    }
+
+    fn dump_dataset(&self, writer: &mut dyn Write, syntax: DatasetSyntax) -> Result<()> {
+        HybridStore::dump_dataset(self, writer, syntax)
+    }
+
+    fn insert(&self, quad: &Quad) -> Result<()> {
+        HybridStore::insert(self, quad)
+    }
+
+    fn remove(&self, quad: &Quad) -> Result<()> {
+        HybridStore::remove(self, quad)
+    }
+
+    fn named_graphs(&self) -> Box<dyn Iterator<Item = Result<NamedOrBlankNode>> + '_> {
+        Box::new(HybridStore::named_graphs(self))
+    }
+
+    fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        Ok(HybridStore::contains_named_graph(self, graph_name))
+    }
+
+    fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        HybridStore::insert_named_graph(self, graph_name)
+    }
+
+    fn clear_graph(&self, graph_name: &GraphName) -> Result<()> {
+        HybridStore::clear_graph(self, graph_name)
+    }
+
+    fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        HybridStore::remove_named_graph(self, graph_name)
+    }
+}
+
+impl PreparedQuery for HybridPreparedQuery {
+    fn exec(&self) -> Result<QueryResult<'_>> {
+        HybridPreparedQuery::exec(self)
+    }
+
+    fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        HybridPreparedQuery::exec_with_bindings(self, bindings)
+    }
+
+    fn explain(&self) -> Result<QueryExplanation> {
+        HybridPreparedQuery::explain(self)
+    }
+
+    fn stats(&self) -> QueryStats {
+        HybridPreparedQuery::stats(self)
+    }
+}
+
+impl PreparedUpdate for HybridPreparedUpdate {
+    fn exec(&self) -> Result<()> {
+        HybridPreparedUpdate::exec(self)
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl Store for RocksDbStore {
+    fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedQuery>> {
+        Ok(Box::new(RocksDbStore::prepare_query(self, query, options)?))
+    }
+
+    fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedUpdate>> {
+        Ok(Box::new(RocksDbStore::prepare_update(
+            self, update, options,
+        )?))
+    }
+
+    fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Box<dyn Iterator<Item = Result<Quad>> + '_> {
+        Box::new(RocksDbStore::quads_for_pattern(
+            self, subject, predicate, object, graph_name,
+        ))
+    }
+
+    fn contains(&self, quad: &Quad) -> Result<bool> {
+        RocksDbStore::contains(self, quad)
+    }
+
+    fn len(&self) -> Result<usize> {
+        RocksDbStore::len(self)
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        RocksDbStore::is_empty(self)
+    }
+
+    fn load_graph(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        RocksDbStore::load_graph(self, reader, syntax, to_graph_name, base_iri)
+    }
+
+    fn load_dataset(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        RocksDbStore::load_dataset(self, reader, syntax, base_iri)
+    }
+
+    fn dump_graph(
+        &self,
+        writer: &mut dyn Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        RocksDbStore::dump_graph(self, writer, syntax, from_graph_name)
+    }
+
+    fn dump_dataset(&self, writer: &mut dyn Write, syntax: DatasetSyntax) -> Result<()> {
+        RocksDbStore::dump_dataset(self, writer, syntax)
+    }
+
+    fn insert(&self, quad: &Quad) -> Result<()> {
+        RocksDbStore::insert(self, quad)
+    }
+
+    fn remove(&self, quad: &Quad) -> Result<()> {
+        RocksDbStore::remove(self, quad)
+    }
+
+    fn named_graphs(&self) -> Box<dyn Iterator<Item = Result<NamedOrBlankNode>> + '_> {
+        Box::new(RocksDbStore::named_graphs(self))
+    }
+
+    fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        RocksDbStore::contains_named_graph(self, graph_name)
+    }
+
+    fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        RocksDbStore::insert_named_graph(self, graph_name)
+    }
+
+    fn clear_graph(&self, graph_name: &GraphName) -> Result<()> {
+        RocksDbStore::clear_graph(self, graph_name)
+    }
+
+    fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        RocksDbStore::remove_named_graph(self, graph_name)
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl PreparedQuery for RocksDbPreparedQuery {
+    fn exec(&self) -> Result<QueryResult<'_>> {
+        RocksDbPreparedQuery::exec(self)
+    }
+
+    fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        RocksDbPreparedQuery::exec_with_bindings(self, bindings)
+    }
+
+    fn explain(&self) -> Result<QueryExplanation> {
+        RocksDbPreparedQuery::explain(self)
+    }
+
+    fn stats(&self) -> QueryStats {
+        RocksDbPreparedQuery::stats(self)
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl PreparedUpdate for RocksDbPreparedUpdate {
+    fn exec(&self) -> Result<()> {
+        RocksDbPreparedUpdate::exec(self)
+    }
+}
+
+#[cfg(feature = "sled")]
+impl Store for SledStore {
+    fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedQuery>> {
+        Ok(Box::new(SledStore::prepare_query(self, query, options)?))
+    }
+
+    fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<Box<dyn PreparedUpdate>> {
+        Ok(Box::new(SledStore::prepare_update(self, update, options)?))
+    }
+
+    fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Box<dyn Iterator<Item = Result<Quad>> + '_> {
+        Box::new(SledStore::quads_for_pattern(
+            self, subject, predicate, object, graph_name,
+        ))
+    }
+
+    fn contains(&self, quad: &Quad) -> Result<bool> {
+        SledStore::contains(self, quad)
+    }
+
+    fn len(&self) -> Result<usize> {
+        SledStore::len(self)
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        SledStore::is_empty(self)
+    }
+
+    fn load_graph(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        SledStore::load_graph(self, reader, syntax, to_graph_name, base_iri)
+    }
+
+    fn load_dataset(
+        &self,
+        reader: &mut dyn BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        SledStore::load_dataset(self, reader, syntax, base_iri)
+    }
+
+    fn dump_graph(
+        &self,
+        writer: &mut dyn Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        SledStore::dump_graph(self, writer, syntax, from_graph_name)
+    }
+
+    fn dump_dataset(&self, writer: &mut dyn Write, syntax: DatasetSyntax) -> Result<()> {
+        SledStore::dump_dataset(self, writer, syntax)
+    }
+
+    fn insert(&self, quad: &Quad) -> Result<()> {
+        SledStore::insert(self, quad)
+    }
+
+    fn remove(&self, quad: &Quad) -> Result<()> {
+        SledStore::remove(self, quad)
+    }
+
+    fn named_graphs(&self) -> Box<dyn Iterator<Item = Result<NamedOrBlankNode>> + '_> {
+        Box::new(SledStore::named_graphs(self))
+    }
+
+    fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        SledStore::contains_named_graph(self, graph_name)
+    }
+
+    fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        SledStore::insert_named_graph(self, graph_name)
+    }
+
+    fn clear_graph(&self, graph_name: &GraphName) -> Result<()> {
+        SledStore::clear_graph(self, graph_name)
+    }
+
+    fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        SledStore::remove_named_graph(self, graph_name)
+    }
+}
+
+#[cfg(feature = "sled")]
+impl PreparedQuery for SledPreparedQuery {
+    fn exec(&self) -> Result<QueryResult<'_>> {
+        SledPreparedQuery::exec(self)
+    }
+
+    fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        SledPreparedQuery::exec_with_bindings(self, bindings)
+    }
+
+    fn explain(&self) -> Result<QueryExplanation> {
+        SledPreparedQuery::explain(self)
+    }
+
+    fn stats(&self) -> QueryStats {
+        SledPreparedQuery::stats(self)
+    }
+}
+
+#[cfg(feature = "sled")]
+impl PreparedUpdate for SledPreparedUpdate {
+    fn exec(&self) -> Result<()> {
+        SledPreparedUpdate::exec(self)
+    }
+}
@@ -0,0 +1,742 @@
+//! Read-only store backed by a compact file.
+//!
+//! The file is made of sections, all the integers are little endian:
+//! * a front-coded dictionary of the strings sorted in lexicographic order, split into blocks of `STRING_BLOCK_SIZE` strings,
+//!   and the offsets of these blocks;
+//! * the string hashes sorted with the position of their string in the dictionary;
+//! * the encoded terms sorted by their encoding, a term is identified by its position in this list, and their offsets;
+//! * the six quad indexes, each one is a sorted array of quads made of four 32 bits term identifiers;
+//! * the sorted identifiers of the named graphs.
+
+use crate::model::*;
+use crate::sparql::{
+    GraphPattern, Query, QueryExplanation, QueryOptions, QueryResult, QuerySolution, QueryStats,
+    SimplePreparedQuery,
+};
+use crate::store::numeric_encoder::*;
+use crate::store::{
+    dump_dataset, dump_graph, scan_predicate_statistics, sealed, FileContent, PredicateStatistics,
+    ReadableEncodedStore, StorageError, Store, StoreStatistics,
+};
+use crate::{DatasetSyntax, Error, GraphSyntax, Result};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::iter::empty;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC_NUMBER: &[u8; 8] = b"OXFROZEN";
+const FORMAT_VERSION: u64 = 1;
+/// The number of strings in a block of the dictionary, only the first string of a block is fully written
+const STRING_BLOCK_SIZE: usize = 16;
+const QUAD_SIZE: usize = 16;
+
+const STRING_BLOCKS: usize = 0;
+const STRING_BLOCK_OFFSETS: usize = 1;
+const STRING_HASHES: usize = 2;
+const TERMS: usize = 3;
+const TERM_OFFSETS: usize = 4;
+const FIRST_INDEX: usize = 5;
+const GRAPHS: usize = 11;
+const SECTION_COUNT: usize = 12;
+const HEADER_SIZE: usize = 16 + 16 * SECTION_COUNT;
+/// The size of an entry of the string hashes section: the hash and the position of the string
+const STRING_HASH_ENTRY_SIZE: usize = 20;
+
+/// The order of the quad components (subject, predicate, object and graph name) in each index
+const INDEX_ORDERS: [[usize; 4]; 6] = [
+    [0, 1, 2, 3], // spog
+    [1, 2, 0, 3], // posg
+    [2, 0, 1, 3], // ospg
+    [3, 0, 1, 2], // gspo
+    [3, 1, 2, 0], // gpos
+    [3, 2, 0, 1], // gosp
+];
+
+/// Read-only store backed by a compact file.
+/// It encodes a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset) and allows to query it using SPARQL.
+///
+/// The file is generated from any other store with `FrozenStore::create`.
+/// `FrozenStore::open` reads it into memory.
+/// With the `"memmap"` feature, `FrozenStore::open_mmap` maps it instead:
+/// the operating system loads its pages when the queries read them.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::store::FrozenStore;
+/// use oxigraph::sparql::{QueryOptions, QueryResult};
+/// use oxigraph::{MemoryStore, Result};
+/// # use std::fs::remove_file;
+///
+/// let store = MemoryStore::new();
+/// let ex = NamedNode::new("http://example.com")?;
+/// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+/// store.insert(quad.clone());
+///
+/// // generation
+/// FrozenStore::create("example.frozen", &store)?;
+/// # {
+/// let frozen = FrozenStore::open("example.frozen")?;
+///
+/// // quad filter
+/// let results: Result<Vec<Quad>> = frozen.quads_for_pattern(None, None, None, None).collect();
+/// assert_eq!(vec![quad], results?);
+///
+/// // SPARQL query
+/// let prepared_query = frozen.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default())?;
+/// let result = prepared_query.exec()?;
+/// if let QueryResult::Solutions(mut solutions) = result {
+///     assert_eq!(solutions.next().unwrap()?.get("s"), Some(&ex.into()));
+/// }
+/// # }
+/// # remove_file("example.frozen")?;
+/// # Result::Ok(())
+/// ```
+#[derive(Clone)]
+pub struct FrozenStore {
+    data: Arc<FileContent>,
+    sections: [Range<usize>; SECTION_COUNT],
+}
+
+impl FrozenStore {
+    /// Opens a file written by `FrozenStore::create` by reading it into memory
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::from_content(path, FileContent::read(path)?)
+    }
+
+    /// Opens a file written by `FrozenStore::create` by mapping it in memory
+    ///
+    /// To use it, the `"memmap"` feature needs to be activated.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the store or one of its clones is alive.
+    /// It includes calling `FrozenStore::create` with the same path.
+    #[cfg(feature = "memmap")]
+    #[allow(unsafe_code)]
+    pub unsafe fn open_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::from_content(path, FileContent::map(path)?)
+    }
+
+    fn from_content(path: &Path, data: FileContent) -> Result<Self> {
+        if data.len() < HEADER_SIZE || &data[0..8] != MAGIC_NUMBER {
+            return Err(invalid_file(path));
+        }
+        let version = read_u64(&data, 8);
+        if version != FORMAT_VERSION {
+            return Err(StorageError::UnsupportedFormat {
+                path: path.to_owned(),
+                version,
+                latest: FORMAT_VERSION,
+            }
+            .into());
+        }
+        let mut sections: [Range<usize>; SECTION_COUNT] = Default::default();
+        for (i, section) in sections.iter_mut().enumerate() {
+            let start = read_u64(&data, 16 + 16 * i) as usize;
+            let end = start.saturating_add(read_u64(&data, 24 + 16 * i) as usize);
+            if start < HEADER_SIZE || end > data.len() {
+                return Err(invalid_file(path));
+            }
+            *section = start..end;
+        }
+        let new = Self {
+            data: Arc::new(data),
+            sections,
+        };
+        if new.section(STRING_HASHES).len() % STRING_HASH_ENTRY_SIZE != 0
+            || new.section(TERM_OFFSETS).len() % 8 != 0
+            || new.section(GRAPHS).len() % 4 != 0
+            || (0..6).any(|index| new.section(FIRST_INDEX + index).len() % QUAD_SIZE != 0)
+        {
+            return Err(invalid_file(path));
+        }
+        Ok(new)
+    }
+
+    /// Writes the content of a store into a new file that could be opened with `FrozenStore::open`.
+    ///
+    /// The content of the store is loaded in memory during the generation.
+    /// If the file already exists, it is replaced.
+    pub fn create(path: impl AsRef<Path>, store: &dyn Store) -> Result<()> {
+        let mut strings = StringCollector::default();
+        strings.set_first_strings()?;
+        let mut quads = Vec::new();
+        for quad in store.quads_for_pattern(None, None, None, None) {
+            quads.push(strings.encode_quad(&quad?)?);
+        }
+        let mut graphs = Vec::new();
+        for graph_name in store.named_graphs() {
+            graphs.push(strings.encode_named_or_blank_node(&graph_name?)?);
+        }
+
+        // The terms are identified by their position in the list of their sorted encodings
+        let mut encoded_terms = quads
+            .iter()
+            .flat_map(|quad| vec![quad.subject, quad.predicate, quad.object, quad.graph_name])
+            .chain(graphs.iter().copied())
+            .map(|term| {
+                let mut buffer = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
+                write_term(&mut buffer, term);
+                (buffer, term)
+            })
+            .collect::<Vec<_>>();
+        encoded_terms.sort_by(|(a, _), (b, _)| a.cmp(b));
+        encoded_terms.dedup_by(|(a, _), (b, _)| a == b);
+        if encoded_terms.len() > u32::max_value() as usize {
+            return Err(Error::msg("The store contains too many terms to be frozen"));
+        }
+        let mut term_ids = HashMap::with_capacity(encoded_terms.len());
+        let mut terms = Vec::new();
+        let mut term_offsets = Vec::with_capacity(8 * encoded_terms.len());
+        for (id, (encoded, term)) in encoded_terms.iter().enumerate() {
+            term_ids.insert(*term, id as u32);
+            term_offsets.extend_from_slice(&(terms.len() as u64).to_le_bytes());
+            terms.extend_from_slice(encoded);
+        }
+
+        let mut sections = vec![Vec::new(); SECTION_COUNT];
+        let (blocks, block_offsets, hashes) = write_strings(strings.strings);
+        sections[STRING_BLOCKS] = blocks;
+        sections[STRING_BLOCK_OFFSETS] = block_offsets;
+        sections[STRING_HASHES] = hashes;
+        sections[TERMS] = terms;
+        sections[TERM_OFFSETS] = term_offsets;
+        let quads = quads
+            .iter()
+            .map(|quad| {
+                [
+                    term_ids[&quad.subject],
+                    term_ids[&quad.predicate],
+                    term_ids[&quad.object],
+                    term_ids[&quad.graph_name],
+                ]
+            })
+            .collect::<Vec<_>>();
+        for (index, order) in INDEX_ORDERS.iter().enumerate() {
+            let mut entries = quads
+                .iter()
+                .map(|quad| {
+                    [
+                        quad[order[0]],
+                        quad[order[1]],
+                        quad[order[2]],
+                        quad[order[3]],
+                    ]
+                })
+                .collect::<Vec<_>>();
+            entries.sort_unstable();
+            entries.dedup();
+            let section = &mut sections[FIRST_INDEX + index];
+            for entry in entries {
+                for id in &entry {
+                    section.extend_from_slice(&id.to_le_bytes());
+                }
+            }
+        }
+        let mut graphs = graphs
+            .iter()
+            .map(|graph_name| term_ids[graph_name])
+            .collect::<Vec<_>>();
+        graphs.sort_unstable();
+        graphs.dedup();
+        for id in graphs {
+            sections[GRAPHS].extend_from_slice(&id.to_le_bytes());
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC_NUMBER)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        let mut offset = HEADER_SIZE;
+        for section in &sections {
+            writer.write_all(&(offset as u64).to_le_bytes())?;
+            writer.write_all(&(section.len() as u64).to_le_bytes())?;
+            offset += section.len();
+        }
+        for section in &sections {
+            writer.write_all(section)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) and returns an object that could be used to execute it.
+    ///
+    /// The queries are evaluated against the file content without loading it in memory.
+    pub fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<FrozenPreparedQuery> {
+        Ok(FrozenPreparedQuery(SimplePreparedQuery::new(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
+    /// This is similar to `prepare_query`, but for a `Query` that has already been parsed or that has been built programmatically.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_parsed_query(
+        &self,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<FrozenPreparedQuery> {
+        Ok(FrozenPreparedQuery(SimplePreparedQuery::new_from_query(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
+    /// This is similar to `prepare_query`, but useful if a SPARQL query has already been parsed, which is the case when building `ServiceHandler`s for federated queries with `SERVICE` clauses. For examples, look in the tests.
+    pub fn prepare_query_from_pattern(
+        &self,
+        graph_pattern: &GraphPattern,
+        options: QueryOptions<'_>,
+    ) -> Result<FrozenPreparedQuery> {
+        Ok(FrozenPreparedQuery(SimplePreparedQuery::new_from_pattern(
+            self.clone(),
+            graph_pattern,
+            options,
+        )?))
+    }
+
+    /// Retrieves quads with a filter on each quad component
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> impl Iterator<Item = Result<Quad>> {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        let this = self.clone();
+        self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name)
+            .map(move |quad| this.decode_quad(&quad?))
+    }
+
+    /// Checks if this store contains a given quad
+    pub fn contains(&self, quad: &Quad) -> Result<bool> {
+        let quad: EncodedQuad = quad.into();
+        Ok(self
+            .encoded_quads_for_pattern_inner(
+                Some(quad.subject),
+                Some(quad.predicate),
+                Some(quad.object),
+                Some(quad.graph_name),
+            )
+            .next()
+            .is_some())
+    }
+
+    /// Returns the number of quads in the store
+    pub fn len(&self) -> usize {
+        self.section(FIRST_INDEX).len() / QUAD_SIZE
+    }
+
+    /// Returns if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the named graphs of the store, including the ones without quads.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn named_graphs(&self) -> impl Iterator<Item = Result<NamedOrBlankNode>> {
+        let this = self.clone();
+        self.encoded_named_graphs_inner()
+            .map(move |graph_name| this.decode_named_or_blank_node(graph_name?))
+    }
+
+    /// Checks if the store contains a given named graph, even if it does not contain any quad
+    pub fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        self.contains_encoded_named_graph(graph_name.into())
+    }
+
+    /// Dumps a store graph into a file.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_graph(
+        &self,
+        writer: impl Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        dump_graph(
+            self.quads_for_pattern(None, None, None, Some(from_graph_name))
+                .map(|quad| Ok(quad?.into())),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Dumps the store dataset into a file.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_dataset(&self, writer: impl Write, syntax: DatasetSyntax) -> Result<()> {
+        dump_dataset(
+            self.quads_for_pattern(None, None, None, None),
+            writer,
+            syntax,
+        )
+    }
+
+    fn section(&self, section: usize) -> &[u8] {
+        &self.data[self.sections[section].clone()]
+    }
+
+    fn term_count(&self) -> usize {
+        self.section(TERM_OFFSETS).len() / 8
+    }
+
+    fn term_bytes(&self, id: u32) -> Result<&[u8]> {
+        let id = id as usize;
+        let offsets = self.section(TERM_OFFSETS);
+        let terms = self.section(TERMS);
+        if id >= self.term_count() {
+            return Err(Error::msg(format!(
+                "The term {} is not in the frozen store",
+                id
+            )));
+        }
+        let start = read_u64(offsets, 8 * id) as usize;
+        let end = if id + 1 == self.term_count() {
+            terms.len()
+        } else {
+            read_u64(offsets, 8 * (id + 1)) as usize
+        };
+        terms
+            .get(start..end)
+            .ok_or_else(|| Error::msg("The frozen store term offsets are invalid"))
+    }
+
+    fn term(&self, id: u32) -> Result<EncodedTerm> {
+        Cursor::new(self.term_bytes(id)?).read_term()
+    }
+
+    /// Returns the identifier of a term or `None` if the term is not in the store
+    fn term_id(&self, term: EncodedTerm) -> Result<Option<u32>> {
+        let mut buffer = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
+        write_term(&mut buffer, term);
+        let mut start = 0;
+        let mut end = self.term_count();
+        while start < end {
+            let middle = (start + end) / 2;
+            let current = self.term_bytes(middle as u32)?;
+            if current < buffer.as_slice() {
+                start = middle + 1;
+            } else if current > buffer.as_slice() {
+                end = middle;
+            } else {
+                return Ok(Some(middle as u32));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the string at the given position of the dictionary
+    fn string(&self, position: usize) -> Result<String> {
+        let blocks = self.section(STRING_BLOCKS);
+        let block_offsets = self.section(STRING_BLOCK_OFFSETS);
+        let block = position / STRING_BLOCK_SIZE;
+        if 8 * block + 8 > block_offsets.len() {
+            return Err(Error::msg("The frozen store string position is invalid"));
+        }
+        let mut cursor = read_u64(block_offsets, 8 * block) as usize;
+        let mut value = Vec::new();
+        for i in 0..=position % STRING_BLOCK_SIZE {
+            let shared = if i == 0 {
+                0
+            } else {
+                read_var_int(blocks, &mut cursor)?
+            };
+            let suffix_len = read_var_int(blocks, &mut cursor)?;
+            let suffix = blocks
+                .get(cursor..cursor + suffix_len)
+                .ok_or_else(|| Error::msg("The frozen store dictionary is invalid"))?;
+            value.truncate(shared);
+            value.extend_from_slice(suffix);
+            cursor += suffix_len;
+        }
+        Ok(String::from_utf8(value)?)
+    }
+
+    /// Returns the range of the quads of an index starting with the given identifiers
+    fn index_range(&self, index: usize, prefix: &[u32]) -> Range<usize> {
+        let entries = self.section(FIRST_INDEX + index);
+        let compare = |position: usize| {
+            (0..prefix.len())
+                .map(|i| read_u32(entries, position * QUAD_SIZE + 4 * i))
+                .cmp(prefix.iter().copied())
+        };
+        let count = entries.len() / QUAD_SIZE;
+        let start = lower_bound(count, |position| compare(position) == Ordering::Less);
+        let end = lower_bound(count, |position| compare(position) != Ordering::Greater);
+        start..end
+    }
+
+    fn encoded_quads_for_pattern_inner(
+        &self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>>> {
+        let mut pattern = [None; 4];
+        for (i, term) in [subject, predicate, object, graph_name].iter().enumerate() {
+            if let Some(term) = term {
+                match self.term_id(*term) {
+                    Ok(Some(id)) => pattern[i] = Some(id),
+                    Ok(None) => return Box::new(empty()),
+                    Err(error) => return Box::new(Some(Err(error)).into_iter()),
+                }
+            }
+        }
+        // The bound components are a prefix of one of the indexes
+        let bound_count = pattern.iter().filter(|id| id.is_some()).count();
+        let index = INDEX_ORDERS
+            .iter()
+            .position(|order| order[..bound_count].iter().all(|c| pattern[*c].is_some()))
+            .unwrap_or(0);
+        let order = INDEX_ORDERS[index];
+        let prefix = order[..bound_count]
+            .iter()
+            .filter_map(|c| pattern[*c])
+            .collect::<Vec<_>>();
+        let this = self.clone();
+        Box::new(self.index_range(index, &prefix).map(move |position| {
+            let entries = this.section(FIRST_INDEX + index);
+            let mut ids = [0; 4];
+            for (i, component) in order.iter().enumerate() {
+                ids[*component] = read_u32(entries, position * QUAD_SIZE + 4 * i);
+            }
+            Ok(EncodedQuad::new(
+                this.term(ids[0])?,
+                this.term(ids[1])?,
+                this.term(ids[2])?,
+                this.term(ids[3])?,
+            ))
+        }))
+    }
+
+    fn encoded_named_graphs_inner(&self) -> impl Iterator<Item = Result<EncodedTerm>> {
+        let this = self.clone();
+        (0..self.section(GRAPHS).len() / 4)
+            .map(move |position| this.term(read_u32(this.section(GRAPHS), 4 * position)))
+    }
+}
+
+impl sealed::MergeSource for FrozenStore {
+    fn encoded_quads_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + '_> {
+        self.encoded_quads_for_pattern(None, None, None, None)
+    }
+
+    fn encoded_named_graphs_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + '_> {
+        self.encoded_named_graphs()
+    }
+}
+
+impl StrLookup for FrozenStore {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        let hashes = self.section(STRING_HASHES);
+        let key = id.to_be_bytes();
+        let count = hashes.len() / STRING_HASH_ENTRY_SIZE;
+        let position = lower_bound(count, |position| {
+            hashes[position * STRING_HASH_ENTRY_SIZE..position * STRING_HASH_ENTRY_SIZE + 16]
+                < key[..]
+        });
+        if position == count
+            || hashes[position * STRING_HASH_ENTRY_SIZE..position * STRING_HASH_ENTRY_SIZE + 16]
+                != key[..]
+        {
+            return Ok(None);
+        }
+        let string_position = read_u32(hashes, position * STRING_HASH_ENTRY_SIZE + 16);
+        Ok(Some(self.string(string_position as usize)?))
+    }
+}
+
+impl StoreStatistics for FrozenStore {
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        scan_predicate_statistics(self, predicate)
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        let id = match self.term_id(graph_name).ok()? {
+            Some(id) => id,
+            None => return Some(0),
+        };
+        Some(self.index_range(3, &[id]).len() as u64)
+    }
+}
+
+impl ReadableEncodedStore for FrozenStore {
+    fn encoded_quads_for_pattern<'a>(
+        &'a self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+        self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name)
+    }
+
+    fn encoded_named_graphs<'a>(&'a self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        Box::new(self.encoded_named_graphs_inner())
+    }
+
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        let id = match self.term_id(graph_name)? {
+            Some(id) => id,
+            None => return Ok(false),
+        };
+        let graphs = self.section(GRAPHS);
+        let count = graphs.len() / 4;
+        let position = lower_bound(count, |position| read_u32(graphs, 4 * position) < id);
+        Ok(position < count && read_u32(graphs, 4 * position) == id)
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) for the `FrozenStore`.
+pub struct FrozenPreparedQuery(SimplePreparedQuery<FrozenStore>);
+
+impl FrozenPreparedQuery {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    ///
+    /// It allows to execute the same query with different parameters without parsing it again.
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// Collects the strings used by the quads of the store to freeze
+#[derive(Default)]
+struct StringCollector {
+    strings: HashMap<StrHash, String>,
+}
+
+impl StrContainer for StringCollector {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if let Some(stored) = self.strings.get(&key) {
+            return check_str_hash_collision(key, stored.as_bytes(), value);
+        }
+        self.strings.insert(key, value.to_owned());
+        Ok(())
+    }
+}
+
+/// Writes the front-coded dictionary, the offsets of its blocks and the sorted hashes
+fn write_strings(strings: HashMap<StrHash, String>) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let mut strings = strings.into_iter().collect::<Vec<_>>();
+    strings.sort_by(|(_, a), (_, b)| a.cmp(b));
+    let mut blocks = Vec::new();
+    let mut block_offsets = Vec::new();
+    let mut hashes = Vec::with_capacity(strings.len());
+    let mut previous: &[u8] = &[];
+    for (position, (hash, value)) in strings.iter().enumerate() {
+        let value = value.as_bytes();
+        if position % STRING_BLOCK_SIZE == 0 {
+            block_offsets.extend_from_slice(&(blocks.len() as u64).to_le_bytes());
+            write_var_int(&mut blocks, value.len());
+            blocks.extend_from_slice(value);
+        } else {
+            let shared = previous
+                .iter()
+                .zip(value)
+                .take_while(|(a, b)| a == b)
+                .count();
+            write_var_int(&mut blocks, shared);
+            write_var_int(&mut blocks, value.len() - shared);
+            blocks.extend_from_slice(&value[shared..]);
+        }
+        previous = value;
+        hashes.push((hash.to_be_bytes(), position as u32));
+    }
+    hashes.sort_unstable();
+    let mut hashes_section = Vec::with_capacity(STRING_HASH_ENTRY_SIZE * hashes.len());
+    for (hash, position) in hashes {
+        hashes_section.extend_from_slice(&hash);
+        hashes_section.extend_from_slice(&position.to_le_bytes());
+    }
+    (blocks, block_offsets, hashes_section)
+}
+
+fn write_var_int(sink: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        sink.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    sink.push(value as u8);
+}
+
+fn read_var_int(buffer: &[u8], cursor: &mut usize) -> Result<usize> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *buffer
+            .get(*cursor)
+            .ok_or_else(|| Error::msg("The frozen store dictionary is invalid"))?;
+        *cursor += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(buffer: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(buffer[offset..offset + 8].try_into().unwrap())
+}
+
+/// Returns the first position in `0..count` for which `is_before` is false, `is_before` should be true then false
+fn lower_bound(count: usize, is_before: impl Fn(usize) -> bool) -> usize {
+    let mut start = 0;
+    let mut end = count;
+    while start < end {
+        let middle = (start + end) / 2;
+        if is_before(middle) {
+            start = middle + 1;
+        } else {
+            end = middle;
+        }
+    }
+    start
+}
+
+fn invalid_file(path: &Path) -> Error {
+    Error::msg(format!("The file {} is not a frozen store", path.display()))
+}
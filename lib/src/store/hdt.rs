@@ -0,0 +1,1361 @@
+//! Read-only store backed by a [HDT](https://www.rdfhdt.org/) file.
+//!
+//! Only the HDT files with a four sections dictionary compressed with Plain Front Coding and bitmap triples
+//! in the subject, predicate, object order are supported. It is the format written by the reference implementations.
+//!
+//! The file checksums are written by `HdtStore::write` but are not checked when the file is opened.
+
+use crate::model::vocab::xsd;
+use crate::model::*;
+use crate::sparql::{
+    GraphPattern, Query, QueryExplanation, QueryOptions, QueryResult, QuerySolution, QueryStats,
+    SimplePreparedQuery,
+};
+use crate::store::numeric_encoder::*;
+use crate::store::{
+    dump_dataset, dump_graph, sealed, FileContent, PredicateStatistics, ReadableEncodedStore,
+    Store, StoreStatistics,
+};
+use crate::{DatasetSyntax, Error, GraphSyntax, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::iter::empty;
+use std::ops::Range;
+use std::path::Path;
+use std::str;
+use std::sync::Arc;
+
+const COOKIE: &[u8; 4] = b"$HDT";
+const GLOBAL_CONTROL_INFORMATION: u8 = 1;
+const HEADER_CONTROL_INFORMATION: u8 = 2;
+const DICTIONARY_CONTROL_INFORMATION: u8 = 3;
+const TRIPLES_CONTROL_INFORMATION: u8 = 4;
+const HDT_V1_FORMAT: &str = "<http://purl.org/HDT/hdt#HDTv1>";
+const FOUR_SECTIONS_DICTIONARY_FORMAT: &str = "<http://purl.org/HDT/hdt#dictionaryFour>";
+const BITMAP_TRIPLES_FORMAT: &str = "<http://purl.org/HDT/hdt#triplesBitmap>";
+const PFC_SECTION_TYPE: u8 = 2;
+const LOG_SEQUENCE_TYPE: u8 = 1;
+const BITMAP_TYPE: u8 = 1;
+const SPO_ORDER: &str = "1";
+/// The number of strings in a Plain Front Coding block written by `HdtStore::write`
+const PFC_BLOCK_SIZE: usize = 16;
+/// The number of set bits between two samples of the positions of the set bits of a bitmap
+const SELECT_SAMPLE_RATE: usize = 1024;
+
+const SHARED_SECTION: u64 = 0;
+const SUBJECTS_SECTION: u64 = 1;
+const PREDICATES_SECTION: u64 = 2;
+const OBJECTS_SECTION: u64 = 3;
+
+/// The part of the term string a string of the hash index is
+const WHOLE_PART: u64 = 0;
+const VALUE_PART: u64 = 1;
+const DATATYPE_PART: u64 = 2;
+const LANGUAGE_PART: u64 = 3;
+
+/// Read-only store backed by a memory-mapped [HDT](https://www.rdfhdt.org/) file.
+/// It allows to query the triples of the file using SPARQL without importing them into another store.
+/// The triples are in the default graph of the store.
+///
+/// The triple patterns with a bound subject are evaluated using the HDT index.
+/// The other patterns are evaluated by scanning all the triples of the file.
+///
+/// When the file is opened, the strings of its dictionary are read in order to build an in-memory index from their hashes to their positions.
+/// This index is needed to translate the terms used by the query evaluator and uses around 24 bytes per distinct string.
+///
+/// `HdtStore::open` reads the file into memory.
+/// With the `"memmap"` feature, `HdtStore::open_mmap` maps it instead.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::store::HdtStore;
+/// use oxigraph::sparql::{QueryOptions, QueryResult};
+/// use oxigraph::{MemoryStore, Result};
+/// # use std::fs::remove_file;
+///
+/// let store = MemoryStore::new();
+/// let ex = NamedNode::new("http://example.com")?;
+/// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+/// store.insert(quad.clone());
+///
+/// // export of the default graph
+/// HdtStore::write("example.hdt", &store)?;
+/// # {
+/// let hdt = HdtStore::open("example.hdt")?;
+///
+/// // quad filter
+/// let results: Result<Vec<Quad>> = hdt.quads_for_pattern(None, None, None, None).collect();
+/// assert_eq!(vec![quad], results?);
+///
+/// // SPARQL query
+/// let prepared_query = hdt.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default())?;
+/// let result = prepared_query.exec()?;
+/// if let QueryResult::Solutions(mut solutions) = result {
+///     assert_eq!(solutions.next().unwrap()?.get("s"), Some(&ex.into()));
+/// }
+/// # }
+/// # remove_file("example.hdt")?;
+/// # Result::Ok(())
+/// ```
+#[derive(Clone)]
+pub struct HdtStore {
+    file: Arc<HdtFile>,
+}
+
+struct HdtFile {
+    data: FileContent,
+    /// The dictionary sections: shared subjects and objects, subjects, predicates and objects
+    sections: [PfcSection; 4],
+    bitmap_y: Bitmap,
+    bitmap_z: Bitmap,
+    array_y: LogSequence,
+    array_z: LogSequence,
+    /// The hashes of the strings of the dictionary terms with their location, sorted by hash
+    str_index: Vec<(StrHash, u64)>,
+    /// The strings the query evaluator expects to find in all the stores
+    first_strings: MemoryStrStore,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Role {
+    Subject,
+    Predicate,
+    Object,
+}
+
+impl HdtStore {
+    /// Opens a HDT file by reading it into memory
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_content(FileContent::read(path.as_ref())?)
+    }
+
+    /// Opens a HDT file by mapping it in memory
+    ///
+    /// To use it, the `"memmap"` feature needs to be activated.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the store or one of its clones is alive.
+    /// It includes calling `HdtStore::write` with the same path.
+    #[cfg(feature = "memmap")]
+    #[allow(unsafe_code)]
+    pub unsafe fn open_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_content(FileContent::map(path.as_ref())?)
+    }
+
+    fn from_content(data: FileContent) -> Result<Self> {
+        let mut reader = HdtReader {
+            data: &data,
+            position: 0,
+        };
+        let global = reader.control_information(GLOBAL_CONTROL_INFORMATION)?;
+        if global.format != HDT_V1_FORMAT {
+            return Err(Error::msg(format!(
+                "The HDT format {} is not supported",
+                global.format
+            )));
+        }
+        let header = reader.control_information(HEADER_CONTROL_INFORMATION)?;
+        reader.skip(header.property("length")?)?;
+        let dictionary = reader.control_information(DICTIONARY_CONTROL_INFORMATION)?;
+        if dictionary.format != FOUR_SECTIONS_DICTIONARY_FORMAT {
+            return Err(Error::msg(format!(
+                "The HDT dictionary format {} is not supported",
+                dictionary.format
+            )));
+        }
+        let sections = [
+            reader.pfc_section()?,
+            reader.pfc_section()?,
+            reader.pfc_section()?,
+            reader.pfc_section()?,
+        ];
+        let triples = reader.control_information(TRIPLES_CONTROL_INFORMATION)?;
+        if triples.format != BITMAP_TRIPLES_FORMAT {
+            return Err(Error::msg(format!(
+                "The HDT triples format {} is not supported",
+                triples.format
+            )));
+        }
+        if triples.properties.get("order").map(String::as_str) != Some(SPO_ORDER) {
+            return Err(Error::msg(
+                "Only the HDT triples in the subject, predicate, object order are supported",
+            ));
+        }
+        let bitmap_y = reader.bitmap()?;
+        let bitmap_z = reader.bitmap()?;
+        let array_y = reader.log_sequence()?;
+        let array_z = reader.log_sequence()?;
+        if bitmap_y.len != array_y.len || bitmap_z.len != array_z.len {
+            return Err(Error::msg("The HDT triples sizes are inconsistent"));
+        }
+        let mut file = HdtFile {
+            data,
+            sections,
+            bitmap_y,
+            bitmap_z,
+            array_y,
+            array_z,
+            str_index: Vec::new(),
+            first_strings: MemoryStrStore::default(),
+        };
+        file.build_str_index()?;
+        Ok(Self {
+            file: Arc::new(file),
+        })
+    }
+
+    /// Writes the default graph of a store into a new HDT file that could be opened with `HdtStore::open`.
+    ///
+    /// The content of the graph is loaded in memory during the generation.
+    /// If the file already exists, it is replaced.
+    /// HDT has no syntax for quoted triples so writing a graph containing some fails.
+    pub fn write(path: impl AsRef<Path>, store: &dyn Store) -> Result<()> {
+        let mut triples = Vec::new();
+        for quad in store.quads_for_pattern(None, None, None, Some(&GraphName::DefaultGraph)) {
+            let quad = quad?;
+            if quad.subject.is_triple() || quad.object.is_triple() {
+                return Err(Error::msg("The quoted triples could not be written to HDT"));
+            }
+            triples.push((
+                hdt_string(&quad.subject.into()),
+                hdt_string(&quad.predicate.into()),
+                hdt_string(&quad.object),
+            ));
+        }
+        let subjects = triples
+            .iter()
+            .map(|(s, _, _)| s.as_str())
+            .collect::<BTreeSet<_>>();
+        let predicates = triples
+            .iter()
+            .map(|(_, p, _)| p.as_str())
+            .collect::<BTreeSet<_>>();
+        let objects = triples
+            .iter()
+            .map(|(_, _, o)| o.as_str())
+            .collect::<BTreeSet<_>>();
+        let shared = subjects.intersection(&objects).copied().collect::<Vec<_>>();
+        let subjects_only = subjects.difference(&objects).copied().collect::<Vec<_>>();
+        let objects_only = objects.difference(&subjects).copied().collect::<Vec<_>>();
+        let predicates = predicates.into_iter().collect::<Vec<_>>();
+
+        let id = |first: &[&str], second: &[&str], value: &str| -> u64 {
+            match first.binary_search(&value) {
+                Ok(position) => position as u64 + 1,
+                Err(_) => {
+                    (first.len() + second.binary_search(&value).unwrap_or_default()) as u64 + 1
+                }
+            }
+        };
+        let mut id_triples = triples
+            .iter()
+            .map(|(s, p, o)| {
+                (
+                    id(&shared, &subjects_only, s),
+                    id(&[], &predicates, p),
+                    id(&shared, &objects_only, o),
+                )
+            })
+            .collect::<Vec<_>>();
+        id_triples.sort_unstable();
+        id_triples.dedup();
+
+        let mut array_y = Vec::new();
+        let mut bitmap_y = Vec::new();
+        let mut array_z = Vec::new();
+        let mut bitmap_z = Vec::new();
+        for (i, (s, p, o)) in id_triples.iter().enumerate() {
+            let next = id_triples.get(i + 1);
+            array_z.push(*o);
+            let is_last_of_pair = next.map_or(true, |(ns, np, _)| ns != s || np != p);
+            bitmap_z.push(is_last_of_pair);
+            if is_last_of_pair {
+                array_y.push(*p);
+                bitmap_y.push(next.map_or(true, |(ns, _, _)| ns != s));
+            }
+        }
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_control_information(&mut writer, GLOBAL_CONTROL_INFORMATION, HDT_V1_FORMAT, "")?;
+        let header = format!(
+            "_:dataset <http://rdfs.org/ns/void#triples> \"{}\" .\n",
+            id_triples.len()
+        );
+        write_control_information(
+            &mut writer,
+            HEADER_CONTROL_INFORMATION,
+            "ntriples",
+            &format!("length={};", header.len()),
+        )?;
+        writer.write_all(header.as_bytes())?;
+        write_control_information(
+            &mut writer,
+            DICTIONARY_CONTROL_INFORMATION,
+            FOUR_SECTIONS_DICTIONARY_FORMAT,
+            &format!(
+                "mapping=1;elements={};",
+                shared.len() + subjects_only.len() + predicates.len() + objects_only.len()
+            ),
+        )?;
+        for section in &[shared, subjects_only, predicates, objects_only] {
+            write_pfc_section(&mut writer, section)?;
+        }
+        write_control_information(
+            &mut writer,
+            TRIPLES_CONTROL_INFORMATION,
+            BITMAP_TRIPLES_FORMAT,
+            &format!("order={};numTriples={};", SPO_ORDER, id_triples.len()),
+        )?;
+        write_bitmap(&mut writer, &bitmap_y)?;
+        write_bitmap(&mut writer, &bitmap_z)?;
+        write_log_sequence(&mut writer, &array_y)?;
+        write_log_sequence(&mut writer, &array_z)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) and returns an object that could be used to execute it.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<HdtPreparedQuery> {
+        Ok(HdtPreparedQuery(SimplePreparedQuery::new(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
+    /// This is similar to `prepare_query`, but for a `Query` that has already been parsed or that has been built programmatically.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_parsed_query(
+        &self,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<HdtPreparedQuery> {
+        Ok(HdtPreparedQuery(SimplePreparedQuery::new_from_query(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
+    /// This is similar to `prepare_query`, but useful if a SPARQL query has already been parsed, which is the case when building `ServiceHandler`s for federated queries with `SERVICE` clauses. For examples, look in the tests.
+    pub fn prepare_query_from_pattern(
+        &self,
+        graph_pattern: &GraphPattern,
+        options: QueryOptions<'_>,
+    ) -> Result<HdtPreparedQuery> {
+        Ok(HdtPreparedQuery(SimplePreparedQuery::new_from_pattern(
+            self.clone(),
+            graph_pattern,
+            options,
+        )?))
+    }
+
+    /// Retrieves quads with a filter on each quad component
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> impl Iterator<Item = Result<Quad>> {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        let this = self.clone();
+        self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name)
+            .map(move |quad| this.decode_quad(&quad?))
+    }
+
+    /// Checks if this store contains a given quad
+    pub fn contains(&self, quad: &Quad) -> Result<bool> {
+        let quad: EncodedQuad = quad.into();
+        Ok(self
+            .encoded_quads_for_pattern_inner(
+                Some(quad.subject),
+                Some(quad.predicate),
+                Some(quad.object),
+                Some(quad.graph_name),
+            )
+            .next()
+            .is_some())
+    }
+
+    /// Returns the number of quads in the store
+    pub fn len(&self) -> usize {
+        self.file.array_z.len
+    }
+
+    /// Returns if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Dumps a store graph into a file.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_graph(
+        &self,
+        writer: impl Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        dump_graph(
+            self.quads_for_pattern(None, None, None, Some(from_graph_name))
+                .map(|quad| Ok(quad?.into())),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Dumps the store dataset into a file.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_dataset(&self, writer: impl Write, syntax: DatasetSyntax) -> Result<()> {
+        dump_dataset(
+            self.quads_for_pattern(None, None, None, None),
+            writer,
+            syntax,
+        )
+    }
+
+    fn encoded_quads_for_pattern_inner(
+        &self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>>> {
+        if graph_name.map_or(false, |graph_name| graph_name != ENCODED_DEFAULT_GRAPH) {
+            return Box::new(empty());
+        }
+        let mut ids = [None; 3];
+        for (i, (term, role)) in [
+            (subject, Role::Subject),
+            (predicate, Role::Predicate),
+            (object, Role::Object),
+        ]
+        .iter()
+        .enumerate()
+        {
+            if let Some(term) = term {
+                match self.term_id(*term, *role) {
+                    Ok(Some(id)) => ids[i] = Some(id),
+                    Ok(None) => return Box::new(empty()),
+                    Err(error) => return Box::new(Some(Err(error)).into_iter()),
+                }
+            }
+        }
+        let [subject, predicate, object] = ids;
+        let this = self.clone();
+        Box::new(
+            self.file
+                .triple_ids(subject, predicate, object)
+                .map(move |(s, p, o)| {
+                    Ok(EncodedQuad::new(
+                        this.file.encoded_term(Role::Subject, s)?,
+                        this.file.encoded_term(Role::Predicate, p)?,
+                        this.file.encoded_term(Role::Object, o)?,
+                        ENCODED_DEFAULT_GRAPH,
+                    ))
+                }),
+        )
+    }
+
+    /// Returns the HDT identifier of a term or `None` if the term is not in the dictionary
+    fn term_id(&self, term: EncodedTerm, role: Role) -> Result<Option<u64>> {
+        let (first, second) = term.str_ids();
+        for id in first.into_iter().chain(second) {
+            if self.get_str(id)?.is_none() {
+                return Ok(None);
+            }
+        }
+        Ok(self
+            .file
+            .locate(role, &hdt_string(&self.decode_term(term)?)))
+    }
+}
+
+impl HdtFile {
+    fn section(&self, section: u64) -> &PfcSection {
+        &self.sections[section as usize]
+    }
+
+    fn shared_len(&self) -> u64 {
+        self.sections[SHARED_SECTION as usize].len as u64
+    }
+
+    /// Returns the dictionary section and the position in it of an identifier
+    fn location(&self, role: Role, id: u64) -> Result<(u64, usize)> {
+        let (section, position) = match role {
+            Role::Predicate => (PREDICATES_SECTION, id - 1),
+            Role::Subject if id <= self.shared_len() => (SHARED_SECTION, id - 1),
+            Role::Object if id <= self.shared_len() => (SHARED_SECTION, id - 1),
+            Role::Subject => (SUBJECTS_SECTION, id - self.shared_len() - 1),
+            Role::Object => (OBJECTS_SECTION, id - self.shared_len() - 1),
+        };
+        if id == 0 || position as usize >= self.section(section).len {
+            return Err(Error::msg(format!(
+                "The identifier {} is not in the HDT dictionary",
+                id
+            )));
+        }
+        Ok((section, position as usize))
+    }
+
+    fn locate(&self, role: Role, value: &str) -> Option<u64> {
+        if role == Role::Predicate {
+            return Some(self.section(PREDICATES_SECTION).locate(&self.data, value)? as u64 + 1);
+        }
+        if let Some(position) = self.section(SHARED_SECTION).locate(&self.data, value) {
+            return Some(position as u64 + 1);
+        }
+        let section = if role == Role::Subject {
+            SUBJECTS_SECTION
+        } else {
+            OBJECTS_SECTION
+        };
+        Some(self.section(section).locate(&self.data, value)? as u64 + self.shared_len() + 1)
+    }
+
+    fn encoded_term(&self, role: Role, id: u64) -> Result<EncodedTerm> {
+        let (section, position) = self.location(role, id)?;
+        let value = self.section(section).extract(&self.data, position)?;
+        Ok((&parse_hdt_term(&value)?).into())
+    }
+
+    fn build_str_index(&mut self) -> Result<()> {
+        let mut str_index = Vec::new();
+        for section in 0..4 {
+            self.section(section)
+                .for_each(&self.data, |position, value| {
+                    let term = parse_hdt_term(value)?;
+                    for (part, value) in term_parts(&term) {
+                        let hash = StrHash::new(value);
+                        if !hash.is_inline() {
+                            str_index.push((hash, section << 62 | part << 60 | position as u64));
+                        }
+                    }
+                    Ok(())
+                })?;
+        }
+        str_index.sort_unstable_by_key(|(hash, _)| *hash);
+        str_index.dedup_by_key(|(hash, _)| *hash);
+        self.str_index = str_index;
+        Ok(())
+    }
+
+    fn get_str_from_index(&self, id: StrHash) -> Result<Option<String>> {
+        let location = match self.str_index.binary_search_by_key(&id, |(hash, _)| *hash) {
+            Ok(position) => self.str_index[position].1,
+            Err(_) => return Ok(None),
+        };
+        let value = self
+            .section(location >> 62)
+            .extract(&self.data, (location & ((1 << 60) - 1)) as usize)?;
+        let term = parse_hdt_term(&value)?;
+        let part = (location >> 60) & 3;
+        Ok(term_parts(&term)
+            .into_iter()
+            .find(|(p, _)| *p == part)
+            .map(|(_, value)| value.to_owned()))
+    }
+
+    /// Returns the identifiers of the triples matching a pattern
+    fn triple_ids(
+        self: &Arc<Self>,
+        subject: Option<u64>,
+        predicate: Option<u64>,
+        object: Option<u64>,
+    ) -> Box<dyn Iterator<Item = (u64, u64, u64)>> {
+        let scan = match subject {
+            Some(subject) => {
+                if subject == 0 || subject > self.bitmap_y.ones as u64 {
+                    return Box::new(empty());
+                }
+                let y_start = self.y_start(subject);
+                let y_end = self.y_end(subject);
+                if let Some(predicate) = predicate {
+                    let y = match self
+                        .array_y
+                        .binary_search(&self.data, y_start..y_end, predicate)
+                    {
+                        Some(y) => y,
+                        None => return Box::new(empty()),
+                    };
+                    let z_start = self.z_start(y);
+                    let z_end = self.z_end(y);
+                    if let Some(object) = object {
+                        return Box::new(
+                            self.array_z
+                                .binary_search(&self.data, z_start..z_end, object)
+                                .map(|_| (subject, predicate, object))
+                                .into_iter(),
+                        );
+                    }
+                    TripleIdsScan {
+                        file: self.clone(),
+                        subject,
+                        y,
+                        z: z_start,
+                        z_end,
+                    }
+                } else {
+                    TripleIdsScan {
+                        file: self.clone(),
+                        subject,
+                        y: y_start,
+                        z: self.z_start(y_start),
+                        z_end: self.z_end(y_end - 1),
+                    }
+                }
+            }
+            None => TripleIdsScan {
+                file: self.clone(),
+                subject: 1,
+                y: 0,
+                z: 0,
+                z_end: self.array_z.len,
+            },
+        };
+        Box::new(scan.filter(move |(_, p, o)| {
+            predicate.map_or(true, |predicate| predicate == *p)
+                && object.map_or(true, |object| object == *o)
+        }))
+    }
+
+    fn y_start(&self, subject: u64) -> usize {
+        if subject == 1 {
+            0
+        } else {
+            self.bitmap_y.select(&self.data, subject as usize - 2) + 1
+        }
+    }
+
+    fn y_end(&self, subject: u64) -> usize {
+        self.bitmap_y.select(&self.data, subject as usize - 1) + 1
+    }
+
+    fn z_start(&self, y: usize) -> usize {
+        if y == 0 {
+            0
+        } else {
+            self.bitmap_z.select(&self.data, y - 1) + 1
+        }
+    }
+
+    fn z_end(&self, y: usize) -> usize {
+        self.bitmap_z.select(&self.data, y) + 1
+    }
+}
+
+/// Iterates over the triples of the bitmap triples between two positions of the objects array
+struct TripleIdsScan {
+    file: Arc<HdtFile>,
+    subject: u64,
+    y: usize,
+    z: usize,
+    z_end: usize,
+}
+
+impl Iterator for TripleIdsScan {
+    type Item = (u64, u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64, u64)> {
+        if self.z >= self.z_end {
+            return None;
+        }
+        let data = &self.file.data;
+        let triple = (
+            self.subject,
+            self.file.array_y.get(data, self.y),
+            self.file.array_z.get(data, self.z),
+        );
+        if self.file.bitmap_z.get(data, self.z) {
+            if self.file.bitmap_y.get(data, self.y) {
+                self.subject += 1;
+            }
+            self.y += 1;
+        }
+        self.z += 1;
+        Some(triple)
+    }
+}
+
+impl sealed::MergeSource for HdtStore {
+    fn encoded_quads_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + '_> {
+        self.encoded_quads_for_pattern(None, None, None, None)
+    }
+
+    fn encoded_named_graphs_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + '_> {
+        self.encoded_named_graphs()
+    }
+}
+
+impl StrLookup for HdtStore {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        if let Some(value) = self.file.first_strings.get_stored_str(id)? {
+            return Ok(Some(value));
+        }
+        self.file.get_str_from_index(id)
+    }
+}
+
+impl StoreStatistics for HdtStore {
+    // The statistics would require to scan the triples for the patterns without subject
+    fn predicate_statistics(&self, _predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        None
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        Some(if graph_name == ENCODED_DEFAULT_GRAPH {
+            self.len() as u64
+        } else {
+            0
+        })
+    }
+}
+
+impl ReadableEncodedStore for HdtStore {
+    fn encoded_quads_for_pattern<'a>(
+        &'a self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+        self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name)
+    }
+
+    fn encoded_named_graphs<'a>(&'a self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        Box::new(empty())
+    }
+
+    fn contains_encoded_named_graph(&self, _graph_name: EncodedTerm) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) for the `HdtStore`.
+pub struct HdtPreparedQuery(SimplePreparedQuery<HdtStore>);
+
+impl HdtPreparedQuery {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    ///
+    /// It allows to execute the same query with different parameters without parsing it again.
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// A dictionary section compressed with Plain Front Coding
+struct PfcSection {
+    len: usize,
+    block_size: usize,
+    /// The offsets of the blocks in the text
+    blocks: LogSequence,
+    text_start: usize,
+    text_len: usize,
+}
+
+impl PfcSection {
+    fn text<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.text_start..self.text_start + self.text_len]
+    }
+
+    /// Returns the string at a position of the section
+    fn extract(&self, data: &[u8], position: usize) -> Result<String> {
+        let text = self.text(data);
+        let mut cursor = self.blocks.get(data, position / self.block_size) as usize;
+        let mut value = read_c_str(text, &mut cursor)?.to_vec();
+        for _ in 0..position % self.block_size {
+            let shared = read_vbyte(text, &mut cursor)? as usize;
+            value.truncate(shared);
+            value.extend_from_slice(read_c_str(text, &mut cursor)?);
+        }
+        Ok(String::from_utf8(value)?)
+    }
+
+    /// Returns the position of a string in the section or `None` if the string is not in it
+    fn locate(&self, data: &[u8], value: &str) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        let text = self.text(data);
+        let value = value.as_bytes();
+        let block_count = (self.len - 1) / self.block_size + 1;
+        // The last block whose first string is lower or equal to the value
+        let mut start = 0;
+        let mut end = block_count;
+        while end - start > 1 {
+            let middle = (start + end) / 2;
+            let mut cursor = self.blocks.get(data, middle) as usize;
+            if read_c_str(text, &mut cursor).ok()? <= value {
+                start = middle;
+            } else {
+                end = middle;
+            }
+        }
+        let mut cursor = self.blocks.get(data, start) as usize;
+        let mut current = read_c_str(text, &mut cursor).ok()?.to_vec();
+        let mut position = start * self.block_size;
+        loop {
+            if current.as_slice() == value {
+                return Some(position);
+            }
+            position += 1;
+            if position >= self.len || position % self.block_size == 0 {
+                return None;
+            }
+            let shared = read_vbyte(text, &mut cursor).ok()? as usize;
+            current.truncate(shared);
+            current.extend_from_slice(read_c_str(text, &mut cursor).ok()?);
+        }
+    }
+
+    fn for_each(&self, data: &[u8], mut f: impl FnMut(usize, &str) -> Result<()>) -> Result<()> {
+        let text = self.text(data);
+        let mut value = Vec::new();
+        let mut cursor = 0;
+        for position in 0..self.len {
+            if position % self.block_size == 0 {
+                cursor = self.blocks.get(data, position / self.block_size) as usize;
+                value.clear();
+            } else {
+                value.truncate(read_vbyte(text, &mut cursor)? as usize);
+            }
+            value.extend_from_slice(read_c_str(text, &mut cursor)?);
+            f(position, str::from_utf8(&value).map_err(Error::wrap)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// A sequence of integers of a fixed number of bits
+struct LogSequence {
+    bits: usize,
+    len: usize,
+    start: usize,
+}
+
+impl LogSequence {
+    fn get(&self, data: &[u8], position: usize) -> u64 {
+        if self.bits == 0 {
+            return 0;
+        }
+        let bit_position = position * self.bits;
+        let start = self.start + bit_position / 8;
+        let mut buffer = [0; 16];
+        let available = (self.start + (self.bits * self.len + 7) / 8).saturating_sub(start);
+        let len = available.min(16);
+        buffer[..len].copy_from_slice(&data[start..start + len]);
+        let value = u128::from_le_bytes(buffer) >> (bit_position % 8);
+        (value & ((1 << self.bits) - 1)) as u64
+    }
+
+    /// Returns the position of a value in a sorted range of the sequence
+    fn binary_search(&self, data: &[u8], range: Range<usize>, value: u64) -> Option<usize> {
+        let mut start = range.start;
+        let mut end = range.end;
+        while start < end {
+            let middle = (start + end) / 2;
+            let current = self.get(data, middle);
+            if current < value {
+                start = middle + 1;
+            } else if current > value {
+                end = middle;
+            } else {
+                return Some(middle);
+            }
+        }
+        None
+    }
+}
+
+/// A sequence of bits with an index of the positions of its set bits
+struct Bitmap {
+    len: usize,
+    start: usize,
+    /// The number of set bits
+    ones: usize,
+    /// The positions of the set bits whose rank is a multiple of `SELECT_SAMPLE_RATE`
+    samples: Vec<usize>,
+}
+
+impl Bitmap {
+    fn get(&self, data: &[u8], position: usize) -> bool {
+        data[self.start + position / 8] >> (position % 8) & 1 == 1
+    }
+
+    fn word(&self, data: &[u8], index: usize) -> u64 {
+        let start = self.start + 8 * index;
+        let end = (start + 8).min(self.start + (self.len + 7) / 8);
+        let mut buffer = [0; 8];
+        buffer[..end - start].copy_from_slice(&data[start..end]);
+        u64::from_le_bytes(buffer)
+    }
+
+    /// Returns the position of the set bit of the given rank, starting from 0
+    ///
+    /// The rank must be lower than the number of set bits.
+    fn select(&self, data: &[u8], rank: usize) -> usize {
+        let sample = self.samples[rank / SELECT_SAMPLE_RATE];
+        let mut remaining = rank % SELECT_SAMPLE_RATE;
+        let mut index = sample / 64;
+        let mut word = self.word(data, index) & (!0 << (sample % 64));
+        loop {
+            let ones = word.count_ones() as usize;
+            if remaining < ones {
+                for _ in 0..remaining {
+                    word &= word - 1;
+                }
+                return 64 * index + word.trailing_zeros() as usize;
+            }
+            remaining -= ones;
+            index += 1;
+            word = self.word(data, index);
+        }
+    }
+}
+
+/// Reads the components of a HDT file
+struct HdtReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+struct ControlInformation {
+    format: String,
+    properties: HashMap<String, String>,
+}
+
+impl ControlInformation {
+    fn property(&self, key: &str) -> Result<usize> {
+        self.properties
+            .get(key)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| Error::msg(format!("The HDT property {} is not set", key)))
+    }
+}
+
+impl<'a> HdtReader<'a> {
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or_else(|| Error::msg("Unexpected end of the HDT file"))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn vbyte(&mut self) -> Result<usize> {
+        Ok(read_vbyte(self.data, &mut self.position)? as usize)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<usize> {
+        let start = self.position;
+        if start.saturating_add(len) > self.data.len() {
+            return Err(Error::msg("Unexpected end of the HDT file"));
+        }
+        self.position += len;
+        Ok(start)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        Ok(str::from_utf8(read_c_str(self.data, &mut self.position)?)
+            .map_err(Error::wrap)?
+            .to_owned())
+    }
+
+    fn control_information(&mut self, expected_type: u8) -> Result<ControlInformation> {
+        let start = self.skip(4)?;
+        if &self.data[start..start + 4] != COOKIE || self.byte()? != expected_type {
+            return Err(Error::msg("Invalid HDT control information"));
+        }
+        let format = self.string()?;
+        let properties = self
+            .string()?
+            .split(';')
+            .filter_map(|property| {
+                let mut parts = property.splitn(2, '=');
+                Some((parts.next()?.to_owned(), parts.next()?.to_owned()))
+            })
+            .collect();
+        self.skip(2)?; // CRC16
+        Ok(ControlInformation { format, properties })
+    }
+
+    fn pfc_section(&mut self) -> Result<PfcSection> {
+        if self.byte()? != PFC_SECTION_TYPE {
+            return Err(Error::msg(
+                "Only the HDT dictionary sections compressed with Plain Front Coding are supported",
+            ));
+        }
+        let len = self.vbyte()?;
+        let text_len = self.vbyte()?;
+        let block_size = self.vbyte()?;
+        self.skip(1)?; // CRC8
+        let blocks = self.log_sequence()?;
+        let text_start = self.skip(text_len)?;
+        self.skip(4)?; // CRC32
+        if block_size == 0 || (len > 0 && blocks.len < (len - 1) / block_size + 1) {
+            return Err(Error::msg("Invalid HDT dictionary section"));
+        }
+        Ok(PfcSection {
+            len,
+            block_size,
+            blocks,
+            text_start,
+            text_len,
+        })
+    }
+
+    fn log_sequence(&mut self) -> Result<LogSequence> {
+        if self.byte()? != LOG_SEQUENCE_TYPE {
+            return Err(Error::msg("Unsupported HDT sequence type"));
+        }
+        let bits = self.byte()? as usize;
+        let len = self.vbyte()?;
+        self.skip(1)?; // CRC8
+        if bits > 64 {
+            return Err(Error::msg("Invalid HDT sequence"));
+        }
+        // The length is read from the file and could overflow
+        let data_len = bits
+            .checked_mul(len)
+            .and_then(|bits| bits.checked_add(7))
+            .ok_or_else(|| Error::msg("Invalid HDT sequence"))?
+            / 8;
+        let start = self.skip(data_len)?;
+        self.skip(4)?; // CRC32
+        Ok(LogSequence { bits, len, start })
+    }
+
+    fn bitmap(&mut self) -> Result<Bitmap> {
+        if self.byte()? != BITMAP_TYPE {
+            return Err(Error::msg("Unsupported HDT bitmap type"));
+        }
+        let len = self.vbyte()?;
+        self.skip(1)?; // CRC8
+        let data_len = len
+            .checked_add(7)
+            .ok_or_else(|| Error::msg("Invalid HDT bitmap"))?
+            / 8;
+        let start = self.skip(data_len)?;
+        self.skip(4)?; // CRC32
+        let mut bitmap = Bitmap {
+            len,
+            start,
+            ones: 0,
+            samples: Vec::new(),
+        };
+        for index in 0..(len + 63) / 64 {
+            let mut word = bitmap.word(self.data, index);
+            while word != 0 {
+                if bitmap.ones % SELECT_SAMPLE_RATE == 0 {
+                    bitmap
+                        .samples
+                        .push(64 * index + word.trailing_zeros() as usize);
+                }
+                bitmap.ones += 1;
+                word &= word - 1;
+            }
+        }
+        if len > 0 && !bitmap.get(self.data, len - 1) {
+            return Err(Error::msg("Invalid HDT bitmap"));
+        }
+        Ok(bitmap)
+    }
+}
+
+fn read_vbyte(data: &[u8], cursor: &mut usize) -> Result<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data
+            .get(*cursor)
+            .ok_or_else(|| Error::msg("Unexpected end of the HDT file"))?;
+        *cursor += 1;
+        if shift > 63 {
+            return Err(Error::msg("Invalid HDT variable size integer"));
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        // The last byte of the integer has its high bit set
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+fn read_c_str<'a>(data: &'a [u8], cursor: &mut usize) -> Result<&'a [u8]> {
+    let start = *cursor;
+    let len = data
+        .get(start..)
+        .and_then(|rest| rest.iter().position(|b| *b == 0))
+        .ok_or_else(|| Error::msg("Unexpected end of the HDT file"))?;
+    *cursor = start + len + 1;
+    Ok(&data[start..start + len])
+}
+
+/// Parses a term of the HDT dictionary
+fn parse_hdt_term(value: &str) -> Result<Term> {
+    if value.starts_with('"') {
+        let end = value
+            .rfind('"')
+            .filter(|end| *end > 0)
+            .ok_or_else(|| Error::msg(format!("Invalid HDT literal {}", value)))?;
+        let lexical_form = &value[1..end];
+        let suffix = &value[end + 1..];
+        Ok(if suffix.is_empty() {
+            Literal::new_simple_literal(lexical_form)
+        } else if suffix.starts_with('@') {
+            Literal::new_language_tagged_literal_unchecked(
+                lexical_form,
+                suffix[1..].to_ascii_lowercase(),
+            )
+        } else if suffix.starts_with("^^<") && suffix.ends_with('>') {
+            Literal::new_typed_literal(
+                lexical_form,
+                NamedNode::new_unchecked(&suffix[3..suffix.len() - 1]),
+            )
+        } else {
+            return Err(Error::msg(format!("Invalid HDT literal {}", value)));
+        }
+        .into())
+    } else if value.starts_with("_:") {
+        Ok(BlankNode::new_unchecked(&value[2..]).into())
+    } else {
+        Ok(NamedNode::new_unchecked(value).into())
+    }
+}
+
+/// Serializes a term like in the HDT dictionary
+fn hdt_string(term: &Term) -> String {
+    match term {
+        Term::NamedNode(node) => node.as_str().to_owned(),
+        Term::BlankNode(node) => format!("_:{}", node.as_str()),
+        Term::Literal(literal) => {
+            if let Some(language) = literal.language() {
+                format!("\"{}\"@{}", literal.value(), language)
+            } else if *literal.datatype() == *xsd::STRING {
+                format!("\"{}\"", literal.value())
+            } else {
+                format!("\"{}\"^^<{}>", literal.value(), literal.datatype().as_str())
+            }
+        }
+        Term::Triple(triple) => format!("<< {} >>", triple),
+    }
+}
+
+/// Returns the strings of the encoding of a term with their part of the term
+fn term_parts(term: &Term) -> Vec<(u64, &str)> {
+    match term {
+        Term::NamedNode(node) => vec![(WHOLE_PART, node.as_str())],
+        Term::BlankNode(node) => vec![(WHOLE_PART, node.as_str())],
+        Term::Literal(literal) => {
+            if let Some(language) = literal.language() {
+                vec![(VALUE_PART, literal.value()), (LANGUAGE_PART, language)]
+            } else {
+                vec![
+                    (VALUE_PART, literal.value()),
+                    (DATATYPE_PART, literal.datatype().as_str()),
+                ]
+            }
+        }
+        Term::Triple(_) => Vec::new(),
+    }
+}
+
+fn write_vbyte(sink: &mut Vec<u8>, mut value: u64) {
+    while value > 0x7f {
+        sink.push((value & 0x7f) as u8);
+        value >>= 7;
+    }
+    sink.push(value as u8 | 0x80);
+}
+
+fn write_control_information(
+    writer: &mut impl Write,
+    control_type: u8,
+    format: &str,
+    properties: &str,
+) -> Result<()> {
+    let mut buffer = COOKIE.to_vec();
+    buffer.push(control_type);
+    buffer.extend_from_slice(format.as_bytes());
+    buffer.push(0);
+    buffer.extend_from_slice(properties.as_bytes());
+    buffer.push(0);
+    let crc = crc16(&buffer);
+    buffer.extend_from_slice(&crc.to_le_bytes());
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+fn write_pfc_section(writer: &mut impl Write, strings: &[&str]) -> Result<()> {
+    let mut text = Vec::new();
+    let mut blocks = Vec::new();
+    let mut previous: &[u8] = &[];
+    for (position, value) in strings.iter().enumerate() {
+        let value = value.as_bytes();
+        if position % PFC_BLOCK_SIZE == 0 {
+            blocks.push(text.len() as u64);
+            text.extend_from_slice(value);
+        } else {
+            let shared = previous
+                .iter()
+                .zip(value)
+                .take_while(|(a, b)| a == b)
+                .count();
+            write_vbyte(&mut text, shared as u64);
+            text.extend_from_slice(&value[shared..]);
+        }
+        text.push(0);
+        previous = value;
+    }
+    blocks.push(text.len() as u64);
+    let mut buffer = vec![PFC_SECTION_TYPE];
+    write_vbyte(&mut buffer, strings.len() as u64);
+    write_vbyte(&mut buffer, text.len() as u64);
+    write_vbyte(&mut buffer, PFC_BLOCK_SIZE as u64);
+    buffer.push(crc8(&buffer));
+    writer.write_all(&buffer)?;
+    write_log_sequence(writer, &blocks)?;
+    writer.write_all(&text)?;
+    writer.write_all(&crc32(&text).to_le_bytes())?;
+    Ok(())
+}
+
+fn write_log_sequence(writer: &mut impl Write, values: &[u64]) -> Result<()> {
+    let max = values.iter().copied().max().unwrap_or(0);
+    let bits = (64 - max.leading_zeros()) as usize;
+    let mut buffer = vec![LOG_SEQUENCE_TYPE, bits as u8];
+    write_vbyte(&mut buffer, values.len() as u64);
+    buffer.push(crc8(&buffer));
+    writer.write_all(&buffer)?;
+    let mut data = vec![0; (bits * values.len() + 7) / 8];
+    for (position, value) in values.iter().enumerate() {
+        for bit in 0..bits {
+            if value >> bit & 1 == 1 {
+                let bit_position = position * bits + bit;
+                data[bit_position / 8] |= 1 << (bit_position % 8);
+            }
+        }
+    }
+    writer.write_all(&data)?;
+    writer.write_all(&crc32(&data).to_le_bytes())?;
+    Ok(())
+}
+
+fn write_bitmap(writer: &mut impl Write, bits: &[bool]) -> Result<()> {
+    let mut buffer = vec![BITMAP_TYPE];
+    write_vbyte(&mut buffer, bits.len() as u64);
+    buffer.push(crc8(&buffer));
+    writer.write_all(&buffer)?;
+    let mut data = vec![0; (bits.len() + 7) / 8];
+    for (position, bit) in bits.iter().enumerate() {
+        if *bit {
+            data[position / 8] |= 1 << (position % 8);
+        }
+    }
+    writer.write_all(&data)?;
+    writer.write_all(&crc32(&data).to_le_bytes())?;
+    Ok(())
+}
+
+/// CRC-8-CCITT, used by HDT for the small headers
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0_u8;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                crc << 1 ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-16-ANSI, used by HDT for the control information
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0_u16;
+    for byte in data {
+        crc ^= u16::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0xa001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// CRC-32C, used by HDT for the data blocks
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[test]
+fn overflowing_sizes() {
+    let mut data = vec![LOG_SEQUENCE_TYPE, 64];
+    write_vbyte(&mut data, u64::MAX / 8);
+    data.push(0); // CRC8
+    let mut reader = HdtReader {
+        data: &data,
+        position: 0,
+    };
+    assert!(reader.log_sequence().is_err());
+
+    let mut data = vec![BITMAP_TYPE];
+    write_vbyte(&mut data, u64::MAX);
+    data.push(0); // CRC8
+    let mut reader = HdtReader {
+        data: &data,
+        position: 0,
+    };
+    assert!(reader.bitmap().is_err());
+}
@@ -0,0 +1,1012 @@
+//! Store keeping its most recently written indexes in memory and the rest of its data on disk.
+
+use crate::model::*;
+use crate::sparql::{
+    Query, QueryExplanation, QueryOptions, QueryResult, QuerySolution, QueryStats,
+    SimplePreparedQuery, SimplePreparedUpdate,
+};
+use crate::store::memory::{shard_position, MemoryStoreIndexes, SHARD_COUNT};
+use crate::store::numeric_encoder::*;
+use crate::store::*;
+use crate::{DatasetSyntax, GraphSyntax, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, remove_file, File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter::once;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Store for the datasets slightly larger than the available memory.
+/// It encodes a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset) and allows to query and update it using SPARQL.
+///
+/// The quads are split by subject between shards like in the `MemoryStore`.
+/// Only the indexes of the most recently written shards are kept in memory.
+/// The quads of the other shards are spilled to files sorted in the SPOG, POSG and OSPG orders.
+/// Only the blocks of these files that might contain the quads matching a pattern are read again when the shard is queried.
+/// A spilled shard is loaded back in memory when it is written.
+/// The strings are written to disk and only their positions in the file are kept in memory.
+///
+/// The data is not persisted: the files written in the directory of the store are removed when it is dropped.
+/// The writes are not atomic: the graph-level operations are done one shard after the other.
+///
+/// Usage example:
+/// ```
+/// use oxigraph::model::*;
+/// use oxigraph::{HybridStore, Result};
+/// use oxigraph::sparql::{QueryOptions, QueryResult};
+/// # use std::fs::remove_dir_all;
+///
+/// # {
+/// let store = HybridStore::new("example_hybrid")?;
+///
+/// // insertion
+/// let ex = NamedNode::new("http://example.com")?;
+/// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+/// store.insert(&quad)?;
+///
+/// // quad filter
+/// let results: Result<Vec<Quad>> = store.quads_for_pattern(None, None, None, None).collect();
+/// assert_eq!(vec![quad], results?);
+///
+/// // SPARQL query
+/// let prepared_query = store.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", QueryOptions::default())?;
+/// let result = prepared_query.exec()?;
+/// if let QueryResult::Solutions(mut solutions) = result {
+///     assert_eq!(solutions.next().unwrap()?.get("s"), Some(&ex.into()));
+/// }
+/// #
+/// # }
+/// # remove_dir_all("example_hybrid")?;
+/// # Result::Ok(())
+/// ```
+#[derive(Clone)]
+pub struct HybridStore {
+    inner: Arc<HybridStoreInner>,
+}
+
+struct HybridStoreInner {
+    directory: PathBuf,
+    shards: [RwLock<HybridShard>; SHARD_COUNT],
+    strings: DiskStrStore,
+    max_hot_shards: usize,
+    /// Incremented on each write in order to find the least recently written shards
+    clock: AtomicU64,
+}
+
+/// The quads of a shard, in memory or spilled to disk
+struct HybridShard {
+    /// The indexes of the shard or only its named graphs and the number of quads of each graph if it is spilled
+    indexes: MemoryStoreIndexes,
+    /// If the quads of the shard are in its files and not in `indexes`
+    is_spilled: bool,
+    /// The blocks of each file of the shard if it is spilled, in the order of `SPILLED_INDEXES`
+    spilled_blocks: Vec<Vec<SpilledBlock>>,
+    /// The value of the store clock during the last write to the shard
+    last_write: u64,
+}
+
+impl Default for HybridShard {
+    fn default() -> Self {
+        // An empty shard is spilled without writing any file
+        Self {
+            indexes: MemoryStoreIndexes::default(),
+            is_spilled: true,
+            spilled_blocks: Vec::new(),
+            last_write: 0,
+        }
+    }
+}
+
+/// Options for a `HybridStore`.
+///
+/// See `HybridStore::new_with_options` for a usage example.
+#[derive(Debug, Clone, Copy)]
+pub struct HybridStoreOptions {
+    max_hot_shards: usize,
+}
+
+impl Default for HybridStoreOptions {
+    fn default() -> Self {
+        Self { max_hot_shards: 4 }
+    }
+}
+
+impl HybridStoreOptions {
+    /// Sets the number of shards whose indexes are kept in memory, between 1 and 16. The default is 4.
+    ///
+    /// Each shard contains about one sixteenth of the quads.
+    pub fn with_max_hot_shards(mut self, max_hot_shards: usize) -> Self {
+        self.max_hot_shards = max_hot_shards.max(1).min(SHARD_COUNT);
+        self
+    }
+}
+
+impl HybridStore {
+    /// Creates a new empty `HybridStore` writing its files in the given directory.
+    ///
+    /// The directory is created if it does not exist.
+    /// It should not be shared with another store.
+    pub fn new(directory: impl AsRef<Path>) -> Result<Self> {
+        Self::new_with_options(directory, HybridStoreOptions::default())
+    }
+
+    /// Creates a new empty `HybridStore` with the given options.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::hybrid::HybridStoreOptions;
+    /// use oxigraph::{HybridStore, Result};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = HybridStore::new_with_options(
+    ///     "example_hybrid_options",
+    ///     HybridStoreOptions::default().with_max_hot_shards(1),
+    /// )?;
+    ///
+    /// // The quads of the shards that are not in memory are read from disk
+    /// for i in 0..100 {
+    ///     let ex = NamedNode::new(format!("http://example.com/{}", i))?;
+    ///     store.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None))?;
+    /// }
+    /// assert_eq!(store.quads_for_pattern(None, None, None, None).count(), 100);
+    /// # }
+    /// # remove_dir_all("example_hybrid_options")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn new_with_options(
+        directory: impl AsRef<Path>,
+        options: HybridStoreOptions,
+    ) -> Result<Self> {
+        let directory = directory.as_ref().to_owned();
+        create_dir_all(&directory)?;
+        let mut new = Self {
+            inner: Arc::new(HybridStoreInner {
+                strings: DiskStrStore::new(&directory.join("strings"))?,
+                directory,
+                shards: Default::default(),
+                max_hot_shards: options.max_hot_shards,
+                clock: AtomicU64::new(0),
+            }),
+        };
+        new.set_first_strings()?;
+        Ok(new)
+    }
+
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) and returns an object that could be used to execute it.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<HybridPreparedQuery> {
+        Ok(HybridPreparedQuery(SimplePreparedQuery::new(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
+    /// This is similar to `prepare_query`, but for a `Query` that has already been parsed or that has been built programmatically.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_parsed_query(
+        &self,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<HybridPreparedQuery> {
+        Ok(HybridPreparedQuery(SimplePreparedQuery::new_from_query(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
+    /// Prepares a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/) and returns an object that could be used to execute it.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<HybridPreparedUpdate> {
+        Ok(HybridPreparedUpdate {
+            store: self.clone(),
+            update: SimplePreparedUpdate::new(update, options)?,
+        })
+    }
+
+    /// Retrieves quads with a filter on each quad component
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> impl Iterator<Item = Result<Quad>> {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        let this = self.clone();
+        let quads = self.encoded_quads_for_pattern_inner(
+            subject,
+            predicate,
+            object,
+            graph_name,
+            usize::MAX,
+        );
+        let (quads, error) = match quads {
+            Ok(quads) => (quads, None),
+            Err(error) => (Vec::new(), Some(Err(error))),
+        };
+        quads
+            .into_iter()
+            .map(move |quad| this.decode_quad(&quad))
+            .chain(error)
+    }
+
+    /// Checks if this store contains a given quad
+    pub fn contains(&self, quad: &Quad) -> Result<bool> {
+        let quad = quad.into();
+        self.contains_encoded(&quad)
+    }
+
+    /// Returns the number of quads in the store
+    ///
+    /// The number of quads of each graph is maintained by the store so the quads are not counted one by one.
+    pub fn len(&self) -> usize {
+        self.inner
+            .shards
+            .iter()
+            .map(|shard| read_shard(shard).indexes.graph_lens.values().sum::<usize>())
+            .sum()
+    }
+
+    /// Returns if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of quads in a given graph
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn len_graph(&self, graph_name: &GraphName) -> usize {
+        let graph_name = graph_name.into();
+        self.inner
+            .shards
+            .iter()
+            .map(|shard| {
+                read_shard(shard)
+                    .indexes
+                    .graph_lens
+                    .get(&graph_name)
+                    .copied()
+                    .unwrap_or(0)
+            })
+            .sum()
+    }
+
+    /// Returns statistics about the content of the store.
+    ///
+    /// All the quads are read, including the ones of the spilled shards.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn stats(&self) -> Result<StoreStats> {
+        let quads = self.encoded_quads_for_pattern_inner(None, None, None, None, usize::MAX)?;
+        scan_store_stats(
+            quads.into_iter().map(Ok),
+            self.encoded_named_graphs_inner().len(),
+            self.inner.strings.len(),
+        )
+    }
+
+    /// Returns the named graphs of the store, including the ones without quads.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn named_graphs(&self) -> impl Iterator<Item = Result<NamedOrBlankNode>> {
+        let this = self.clone();
+        self.encoded_named_graphs_inner()
+            .into_iter()
+            .map(move |graph_name| this.decode_named_or_blank_node(graph_name))
+    }
+
+    /// Checks if the store contains a given named graph, even if it does not contain any quad
+    pub fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> bool {
+        self.contains_encoded_named_graph_inner(graph_name.into())
+    }
+
+    /// Adds a named graph to the store, even if it does not contain any quad
+    pub fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        let mut this = self;
+        let graph_name = this.encode_named_or_blank_node(graph_name)?;
+        this.insert_encoded_named_graph(graph_name)
+    }
+
+    /// Removes all the quads of a graph. The graph itself stays in the store.
+    pub fn clear_graph(&self, graph_name: &GraphName) -> Result<()> {
+        let mut this = self;
+        this.clear_encoded_graph(graph_name.into())
+    }
+
+    /// Removes a named graph and all its quads from the store
+    pub fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        let mut this = self;
+        this.remove_encoded_named_graph(graph_name.into())
+    }
+
+    /// Loads a graph file (i.e. triples) into the store
+    ///
+    /// Warning: the triples are written one after the other.
+    /// If the parsing fails in the middle of the file, only a part of it is written.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn load_graph(
+        &self,
+        reader: impl BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        let mut store = self;
+        load_graph(&mut store, reader, syntax, to_graph_name, base_iri)
+    }
+
+    /// Loads a dataset file (i.e. quads) into the store.
+    ///
+    /// Warning: the quads are written one after the other.
+    /// If the parsing fails in the middle of the file, only a part of it is written.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn load_dataset(
+        &self,
+        reader: impl BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        let mut store = self;
+        load_dataset(&mut store, reader, syntax, base_iri)
+    }
+
+    /// Dumps a store graph into a file.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_graph(
+        &self,
+        writer: impl Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        dump_graph(
+            self.quads_for_pattern(None, None, None, Some(from_graph_name))
+                .map(|quad| Ok(quad?.into())),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Dumps the store dataset into a file.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_dataset(&self, writer: impl Write, syntax: DatasetSyntax) -> Result<()> {
+        dump_dataset(
+            self.quads_for_pattern(None, None, None, None),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Adds a quad to this store.
+    pub fn insert(&self, quad: &Quad) -> Result<()> {
+        let mut this = self;
+        let quad = this.encode_quad(quad)?;
+        this.insert_encoded(&quad)
+    }
+
+    /// Removes a quad from this store.
+    pub fn remove(&self, quad: &Quad) -> Result<()> {
+        let mut this = self;
+        this.remove_encoded(&quad.into())
+    }
+
+    /// Takes the write lock of a shard after loading it in memory if it has been spilled
+    fn write_shard(&self, position: usize) -> Result<RwLockWriteGuard<'_, HybridShard>> {
+        let mut shard = self.inner.shards[position]
+            .write()
+            .expect("the Hybrid store mutex has been poisoned because of a panic");
+        if shard.is_spilled {
+            let quads = self.read_spilled_quads(position, &shard)?;
+            shard.indexes.restore_quads(quads, self)?;
+            shard.is_spilled = false;
+            shard.spilled_blocks.clear();
+        }
+        shard.last_write = self.inner.clock.fetch_add(1, Ordering::Relaxed);
+        Ok(shard)
+    }
+
+    /// Spills the least recently written shards until at most `max_hot_shards` shards are in memory
+    ///
+    /// Only one shard is locked at a time.
+    fn spill_least_recently_written_shards(&self) -> Result<()> {
+        loop {
+            let hot_shards = self
+                .inner
+                .shards
+                .iter()
+                .enumerate()
+                .filter_map(|(position, shard)| {
+                    let shard = read_shard(shard);
+                    if shard.is_spilled {
+                        None
+                    } else {
+                        Some((shard.last_write, position))
+                    }
+                })
+                .collect::<Vec<_>>();
+            if hot_shards.len() <= self.inner.max_hot_shards {
+                return Ok(());
+            }
+            if let Some((_, position)) = hot_shards.into_iter().min() {
+                let mut shard = self.inner.shards[position]
+                    .write()
+                    .expect("the Hybrid store mutex has been poisoned because of a panic");
+                if !shard.is_spilled {
+                    self.spill_shard(position, &mut shard)?;
+                }
+            }
+        }
+    }
+
+    fn spill_shard(&self, position: usize, shard: &mut HybridShard) -> Result<()> {
+        let quads = shard.indexes.take_quads();
+        let written = SPILLED_INDEXES
+            .iter()
+            .map(|index| self.write_spilled_index(position, *index, &quads))
+            .collect::<io::Result<Vec<_>>>();
+        match written {
+            Ok(blocks) => {
+                shard.is_spilled = true;
+                shard.spilled_blocks = blocks;
+                Ok(())
+            }
+            Err(error) => {
+                // The shard stays in memory
+                shard.indexes.restore_quads(quads, self)?;
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Writes the quads sorted in the index order and returns the blocks of the written file
+    fn write_spilled_index(
+        &self,
+        position: usize,
+        index: SpilledIndex,
+        quads: &[EncodedQuad],
+    ) -> io::Result<Vec<SpilledBlock>> {
+        let mut keys = quads
+            .iter()
+            .map(|quad| {
+                let mut key = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
+                index.write(&mut key, quad);
+                key
+            })
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        let mut writer = BufWriter::new(File::create(shard_path(
+            &self.inner.directory,
+            position,
+            index,
+        ))?);
+        let mut blocks = Vec::with_capacity(keys.len() / SPILLED_BLOCK_LEN + 1);
+        let mut offset = 0;
+        for (i, key) in keys.iter().enumerate() {
+            if i % SPILLED_BLOCK_LEN == 0 {
+                blocks.push(SpilledBlock {
+                    first_key: key.clone(),
+                    offset,
+                    len: SPILLED_BLOCK_LEN.min(keys.len() - i),
+                });
+            }
+            writer.write_all(key)?;
+            offset += key.len() as u64;
+        }
+        writer.flush()?;
+        Ok(blocks)
+    }
+
+    fn read_spilled_quads(&self, position: usize, shard: &HybridShard) -> Result<Vec<EncodedQuad>> {
+        self.read_spilled_blocks(position, shard, SpilledIndex::Spog, &[])
+    }
+
+    /// Reads the blocks of a spilled shard file that might contain the quads whose key starts with `prefix`
+    fn read_spilled_blocks(
+        &self,
+        position: usize,
+        shard: &HybridShard,
+        index: SpilledIndex,
+        prefix: &[u8],
+    ) -> Result<Vec<EncodedQuad>> {
+        let blocks = match shard.spilled_blocks.get(index as usize) {
+            Some(blocks) if !blocks.is_empty() => blocks,
+            _ => return Ok(Vec::new()),
+        };
+        // The matching quads start in the last block whose first key is lower than the prefix
+        let start = match blocks.binary_search_by(|block| block.first_key.as_slice().cmp(prefix)) {
+            Ok(i) | Err(i) => i.saturating_sub(1),
+        };
+        let mut reader = BufReader::new(File::open(shard_path(
+            &self.inner.directory,
+            position,
+            index,
+        ))?);
+        reader.seek(SeekFrom::Start(blocks[start].offset))?;
+        let mut quads = Vec::new();
+        for block in &blocks[start..] {
+            if block.first_key.as_slice() > prefix && !block.first_key.starts_with(prefix) {
+                break;
+            }
+            for _ in 0..block.len {
+                quads.push(index.read(&mut reader)?);
+            }
+        }
+        Ok(quads)
+    }
+
+    fn contains_encoded(&self, quad: &EncodedQuad) -> Result<bool> {
+        Ok(!self
+            .encoded_quads_for_pattern_inner(
+                Some(quad.subject),
+                Some(quad.predicate),
+                Some(quad.object),
+                Some(quad.graph_name),
+                1,
+            )?
+            .is_empty())
+    }
+
+    fn encoded_quads_for_pattern_inner(
+        &self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: usize,
+    ) -> Result<Vec<EncodedQuad>> {
+        // All the quads with the same subject are in the same shard
+        let positions = if let Some(subject) = subject {
+            vec![shard_position(subject)]
+        } else {
+            (0..SHARD_COUNT).collect()
+        };
+        let mut quads = Vec::new();
+        for position in positions {
+            if quads.len() >= limit {
+                break;
+            }
+            let shard = read_shard(&self.inner.shards[position]);
+            if shard.is_spilled {
+                let (index, prefix) =
+                    spilled_index_for_pattern(subject, predicate, object, graph_name);
+                quads.extend(
+                    self.read_spilled_blocks(position, &shard, index, &prefix)?
+                        .into_iter()
+                        .filter(|quad| {
+                            subject.map_or(true, |s| s == quad.subject)
+                                && predicate.map_or(true, |p| p == quad.predicate)
+                                && object.map_or(true, |o| o == quad.object)
+                                && graph_name.map_or(true, |g| g == quad.graph_name)
+                        })
+                        .take(limit - quads.len()),
+                );
+            } else {
+                quads.extend(shard.indexes.encoded_quads_for_pattern(
+                    subject,
+                    predicate,
+                    object,
+                    graph_name,
+                    limit - quads.len(),
+                ));
+            }
+        }
+        Ok(quads)
+    }
+
+    fn encoded_named_graphs_inner(&self) -> Vec<EncodedTerm> {
+        let mut graphs = HashSet::new();
+        for shard in self.inner.shards.iter() {
+            graphs.extend(read_shard(shard).indexes.graphs.iter().copied());
+        }
+        graphs.into_iter().collect()
+    }
+
+    fn contains_encoded_named_graph_inner(&self, graph_name: EncodedTerm) -> bool {
+        self.inner
+            .shards
+            .iter()
+            .any(|shard| read_shard(shard).indexes.graphs.contains(&graph_name))
+    }
+}
+
+impl Drop for HybridStoreInner {
+    fn drop(&mut self) {
+        let _ = remove_file(self.directory.join("strings"));
+        for position in 0..SHARD_COUNT {
+            for index in &SPILLED_INDEXES {
+                let _ = remove_file(shard_path(&self.directory, position, *index));
+            }
+        }
+    }
+}
+
+impl StrLookup for HybridStore {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        self.inner.strings.get(id)
+    }
+}
+
+impl StrContainer for HybridStore {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.inner.strings.insert(key, value)
+    }
+}
+
+impl<'a> StrContainer for &'a HybridStore {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.inner.strings.insert(key, value)
+    }
+}
+
+impl StoreStatistics for HybridStore {
+    fn predicate_statistics(&self, _predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        // Computing them would require to read the spilled shards
+        None
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        Some(
+            self.inner
+                .shards
+                .iter()
+                .map(|shard| {
+                    read_shard(shard)
+                        .indexes
+                        .graph_lens
+                        .get(&graph_name)
+                        .copied()
+                        .unwrap_or(0) as u64
+                })
+                .sum(),
+        )
+    }
+}
+
+impl ReadableEncodedStore for HybridStore {
+    fn encoded_quads_for_pattern<'a>(
+        &'a self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+        self.encoded_quads_for_pattern_with_limit(
+            subject,
+            predicate,
+            object,
+            graph_name,
+            usize::MAX,
+        )
+    }
+
+    fn encoded_quads_for_pattern_with_limit<'a>(
+        &'a self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: usize,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+        match self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name, limit) {
+            Ok(quads) => Box::new(quads.into_iter().map(Ok)),
+            Err(error) => Box::new(once(Err(error))),
+        }
+    }
+
+    fn encoded_named_graphs<'a>(&'a self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        Box::new(self.encoded_named_graphs_inner().into_iter().map(Ok))
+    }
+
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        Ok(self.contains_encoded_named_graph_inner(graph_name))
+    }
+}
+
+impl<'a> WritableEncodedStore for &'a HybridStore {
+    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        let store = *self;
+        store
+            .write_shard(shard_position(quad.subject))?
+            .indexes
+            .insert(quad, store)?;
+        store.spill_least_recently_written_shards()
+    }
+
+    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        let store = *self;
+        let position = shard_position(quad.subject);
+        if !store.contains_encoded(quad)? {
+            // Avoids to load the shard in memory
+            return Ok(());
+        }
+        store.write_shard(position)?.indexes.remove(quad, store)?;
+        store.spill_least_recently_written_shards()
+    }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        // The named graphs without quads are kept in the shard of their name, the spilled shards keep them in memory
+        self.inner.shards[shard_position(graph_name)]
+            .write()
+            .expect("the Hybrid store mutex has been poisoned because of a panic")
+            .indexes
+            .graphs
+            .insert(graph_name);
+        Ok(())
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        let store = *self;
+        for position in 0..SHARD_COUNT {
+            if read_shard(&store.inner.shards[position])
+                .indexes
+                .graph_lens
+                .contains_key(&graph_name)
+            {
+                let mut shard = store.write_shard(position)?;
+                for quad in shard
+                    .indexes
+                    .encoded_quads_for_graph(graph_name, usize::MAX)
+                {
+                    shard.indexes.remove(&quad, store)?;
+                }
+                drop(shard);
+                store.spill_least_recently_written_shards()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.clear_encoded_graph(graph_name)?;
+        for shard in self.inner.shards.iter() {
+            shard
+                .write()
+                .expect("the Hybrid store mutex has been poisoned because of a panic")
+                .indexes
+                .graphs
+                .remove(&graph_name);
+        }
+        Ok(())
+    }
+}
+
+/// The number of quads of a block of a spilled shard file
+const SPILLED_BLOCK_LEN: usize = 1024;
+
+const SPILLED_INDEXES: [SpilledIndex; 3] =
+    [SpilledIndex::Spog, SpilledIndex::Posg, SpilledIndex::Ospg];
+
+/// The orders in which the quads of a spilled shard are written
+#[derive(Clone, Copy)]
+enum SpilledIndex {
+    Spog = 0,
+    Posg = 1,
+    Ospg = 2,
+}
+
+impl SpilledIndex {
+    fn name(self) -> &'static str {
+        match self {
+            SpilledIndex::Spog => "spog",
+            SpilledIndex::Posg => "posg",
+            SpilledIndex::Ospg => "ospg",
+        }
+    }
+
+    fn write(self, sink: &mut Vec<u8>, quad: &EncodedQuad) {
+        match self {
+            SpilledIndex::Spog => write_spog_quad(sink, quad),
+            SpilledIndex::Posg => write_posg_quad(sink, quad),
+            SpilledIndex::Ospg => write_ospg_quad(sink, quad),
+        }
+    }
+
+    fn read(self, reader: &mut impl Read) -> Result<EncodedQuad> {
+        match self {
+            SpilledIndex::Spog => reader.read_spog_quad(),
+            SpilledIndex::Posg => reader.read_posg_quad(),
+            SpilledIndex::Ospg => reader.read_ospg_quad(),
+        }
+    }
+}
+
+/// A block of consecutive quads of a spilled shard file
+struct SpilledBlock {
+    /// The written key of the first quad of the block
+    first_key: Vec<u8>,
+    /// The position of the block in the file
+    offset: u64,
+    /// The number of quads in the block
+    len: usize,
+}
+
+fn shard_path(directory: &Path, position: usize, index: SpilledIndex) -> PathBuf {
+    directory.join(format!("shard-{}-{}", position, index.name()))
+}
+
+/// Returns the spilled shard file to read for a pattern and the key prefix of its matching quads
+fn spilled_index_for_pattern(
+    subject: Option<EncodedTerm>,
+    predicate: Option<EncodedTerm>,
+    object: Option<EncodedTerm>,
+    graph_name: Option<EncodedTerm>,
+) -> (SpilledIndex, Vec<u8>) {
+    let (index, terms) = if subject.is_some() {
+        (SpilledIndex::Spog, [subject, predicate, object, graph_name])
+    } else if predicate.is_some() {
+        (SpilledIndex::Posg, [predicate, object, subject, graph_name])
+    } else if object.is_some() {
+        (SpilledIndex::Ospg, [object, subject, predicate, graph_name])
+    } else {
+        (SpilledIndex::Spog, [None, None, None, None])
+    };
+    let mut prefix = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
+    for term in terms.iter().take_while(|term| term.is_some()).flatten() {
+        write_term(&mut prefix, *term);
+    }
+    (index, prefix)
+}
+
+fn read_shard(shard: &RwLock<HybridShard>) -> RwLockReadGuard<'_, HybridShard> {
+    shard
+        .read()
+        .expect("the Hybrid store mutex has been poisoned because of a panic")
+}
+
+/// The strings written one after the other in a file, only their positions are kept in memory
+struct DiskStrStore {
+    file: Mutex<DiskStrFile>,
+    positions: RwLock<HashMap<StrHash, (u64, usize)>>,
+}
+
+struct DiskStrFile {
+    writer: BufWriter<File>,
+    /// The file opened a second time in order to not move the position of the writer
+    reader: File,
+    len: u64,
+}
+
+impl DiskStrStore {
+    fn new(path: &Path) -> Result<Self> {
+        let writer = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self {
+            file: Mutex::new(DiskStrFile {
+                writer: BufWriter::new(writer),
+                reader: File::open(path)?,
+                len: 0,
+            }),
+            positions: RwLock::default(),
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.positions
+            .read()
+            .expect("the Hybrid store mutex has been poisoned because of a panic")
+            .len()
+    }
+
+    fn get(&self, key: StrHash) -> Result<Option<String>> {
+        let (start, len) = if let Some(position) = self
+            .positions
+            .read()
+            .expect("the Hybrid store mutex has been poisoned because of a panic")
+            .get(&key)
+        {
+            *position
+        } else {
+            return Ok(None);
+        };
+        let mut file = self.lock_file();
+        file.writer.flush()?;
+        file.reader.seek(SeekFrom::Start(start))?;
+        let mut value = vec![0; len];
+        file.reader.read_exact(&mut value)?;
+        Ok(Some(String::from_utf8(value)?))
+    }
+
+    fn insert(&self, key: StrHash, value: &str) -> Result<()> {
+        let is_stored = self
+            .positions
+            .read()
+            .expect("the Hybrid store mutex has been poisoned because of a panic")
+            .contains_key(&key);
+        if is_stored {
+            return match self.get(key)? {
+                Some(stored) => check_str_hash_collision(key, stored.as_bytes(), value),
+                None => Ok(()),
+            };
+        }
+        let mut file = self.lock_file();
+        let mut positions = self
+            .positions
+            .write()
+            .expect("the Hybrid store mutex has been poisoned because of a panic");
+        if positions.contains_key(&key) {
+            return Ok(());
+        }
+        file.writer.write_all(value.as_bytes())?;
+        positions.insert(key, (file.len, value.len()));
+        file.len += value.len() as u64;
+        Ok(())
+    }
+
+    fn lock_file(&self) -> MutexGuard<'_, DiskStrFile> {
+        self.file
+            .lock()
+            .expect("the Hybrid store mutex has been poisoned because of a panic")
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) for the `HybridStore`.
+pub struct HybridPreparedQuery(SimplePreparedQuery<HybridStore>);
+
+impl HybridPreparedQuery {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    ///
+    /// It allows to execute the same query with different parameters without parsing it again.
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// A prepared [SPARQL update](https://www.w3.org/TR/sparql11-update/) for the `HybridStore`.
+pub struct HybridPreparedUpdate {
+    store: HybridStore,
+    update: SimplePreparedUpdate,
+}
+
+impl HybridPreparedUpdate {
+    /// Executes the update
+    pub fn exec(&self) -> Result<()> {
+        for operation in self.update.operations() {
+            self.update
+                .exec_operation(operation, self.store.clone(), &mut &self.store)?;
+        }
+        Ok(())
+    }
+}
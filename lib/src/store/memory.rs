@@ -1,23 +1,40 @@
 //! In-memory store.
 
 use crate::model::*;
-use crate::sparql::{QueryOptions, QueryResult, SimplePreparedQuery};
+#[cfg(feature = "spatial-index")]
+use crate::sparql::geo::Envelope;
+use crate::sparql::{
+    Query, QueryExplanation, QueryOptions, QueryResult, QuerySolution, QueryStats,
+    SimplePreparedQuery, SimplePreparedUpdate,
+};
 use crate::store::numeric_encoder::*;
+use crate::store::pending::{PendingChanges, PendingChangesView};
 use crate::store::*;
 use crate::{DatasetSyntax, GraphSyntax, Result};
+#[cfg(feature = "spatial-index")]
+use rstar::{RTree, RTreeObject, AABB};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::hash::{BuildHasherDefault, Hash, Hasher};
-use std::io::BufRead;
-use std::iter::FromIterator;
-use std::mem::size_of;
+use std::io::{BufRead, Write};
+#[cfg(feature = "full-text")]
+use std::iter::empty;
+use std::iter::{once, FromIterator};
+use std::mem::{replace, size_of, take};
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 /// In-memory store.
 /// It encodes a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset) and allows to query and update it using SPARQL.
 /// It is cheap to build using the `MemoryStore::new()` method.
 ///
+/// The quads are split by subject between shards with their own lock:
+/// the writes of quads with different subjects could be done in parallel from multiple threads.
+/// The transactions and the graph-level operations lock all the shards.
+///
+/// The shards are shared copy-on-write with the stores created by `MemoryStore::fork`.
+///
 /// Usage example:
 /// ```
 /// use oxigraph::model::*;
@@ -44,23 +61,95 @@ use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 /// ```
 #[derive(Clone)]
 pub struct MemoryStore {
-    indexes: Arc<RwLock<MemoryStoreIndexes>>,
+    /// The quads split by subject between shards with their own lock in order for the writes to not block each other
+    ///
+    /// The content of a shard is shared with the forks of the store until one of them writes to it.
+    shards: Arc<[RwLock<Arc<MemoryStoreIndexes>>; SHARD_COUNT]>,
+    /// The strings split by key between shards with their own lock, shared with the decoded terms
+    id2str: Arc<[RwLock<Arc<HashMap<StrHash, Arc<str>>>>; SHARD_COUNT]>,
+    watchers: Arc<Watchers>,
 }
 
-type TrivialHashMap<K, V> = HashMap<K, V, BuildHasherDefault<TrivialHasher>>;
-type TrivialHashSet<T> = HashSet<T, BuildHasherDefault<TrivialHasher>>;
+pub(crate) const SHARD_COUNT: usize = 16;
+
+pub(crate) type TrivialHashMap<K, V> = HashMap<K, V, BuildHasherDefault<TrivialHasher>>;
+pub(crate) type TrivialHashSet<T> = HashSet<T, BuildHasherDefault<TrivialHasher>>;
 type TripleMap<T> = TrivialHashMap<T, TrivialHashMap<T, TrivialHashSet<T>>>;
 type QuadMap<T> = TrivialHashMap<T, TripleMap<T>>;
 
-#[derive(Default)]
-struct MemoryStoreIndexes {
+/// The indexes of the quads of a shard
+#[derive(Default, Clone)]
+pub(crate) struct MemoryStoreIndexes {
     spog: QuadMap<EncodedTerm>,
     posg: QuadMap<EncodedTerm>,
     ospg: QuadMap<EncodedTerm>,
     gspo: QuadMap<EncodedTerm>,
     gpos: QuadMap<EncodedTerm>,
     gosp: QuadMap<EncodedTerm>,
-    id2str: HashMap<StrHash, String>,
+    /// The named graphs of the shard quads and the named graphs without quads assigned to the shard
+    pub(crate) graphs: TrivialHashSet<EncodedTerm>,
+    /// The number of quads of each graph in the shard, the graphs without quads are omitted
+    pub(crate) graph_lens: TrivialHashMap<EncodedTerm, usize>,
+    #[cfg(feature = "full-text")]
+    words: HashMap<String, TrivialHashSet<EncodedTerm>>,
+    /// The WKT literals of the shard quads indexed by bounding box
+    #[cfg(feature = "spatial-index")]
+    geometries: RTree<IndexedGeometry>,
+}
+
+/// A WKT literal with its bounding box
+#[cfg(feature = "spatial-index")]
+#[derive(Clone, PartialEq)]
+struct IndexedGeometry {
+    literal: EncodedTerm,
+    envelope: AABB<[f64; 2]>,
+}
+
+#[cfg(feature = "spatial-index")]
+impl RTreeObject for IndexedGeometry {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> AABB<[f64; 2]> {
+        self.envelope
+    }
+}
+
+#[cfg(feature = "spatial-index")]
+impl IndexedGeometry {
+    fn new(strings: &impl StrLookup, literal: EncodedTerm) -> Result<Option<Self>> {
+        Ok(literal_envelope(strings, literal)?.map(|envelope| Self {
+            literal,
+            envelope: AABB::from_corners(envelope.min, envelope.max),
+        }))
+    }
+}
+
+/// The write locks of some shards that send the changes done with them to the watchers when released
+struct MemoryStoreWriteGuard<'a> {
+    store: &'a MemoryStore,
+    /// The locked shards with their position, sorted by position
+    shards: Vec<(usize, RwLockWriteGuard<'a, Arc<MemoryStoreIndexes>>)>,
+    /// The changes done since the locks have been taken, recorded only if there are watchers
+    events: Option<Vec<EncodedQuadEvent>>,
+}
+
+impl MemoryStoreWriteGuard<'_> {
+    fn shard(&mut self, term: EncodedTerm) -> &mut MemoryStoreIndexes {
+        let position = shard_position(term);
+        self.shards
+            .iter_mut()
+            .find(|(p, _)| *p == position)
+            .map(|(_, shard)| Arc::make_mut(&mut **shard))
+            .expect("the shard of the term has not been locked")
+    }
+}
+
+impl Drop for MemoryStoreWriteGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(events) = self.events.take() {
+            self.store.watchers.notify(self.store, &events).unwrap(); // Could never fail
+        }
+    }
 }
 
 impl Default for MemoryStore {
@@ -73,7 +162,9 @@ impl MemoryStore {
     /// Constructs a new `MemoryStore`
     pub fn new() -> Self {
         let mut new = Self {
-            indexes: Arc::new(RwLock::default()),
+            shards: Arc::default(),
+            id2str: Arc::default(),
+            watchers: Arc::default(),
         };
         new.set_first_strings().unwrap();
         new
@@ -112,6 +203,51 @@ impl MemoryStore {
         )?))
     }
 
+    /// This is similar to `prepare_query`, but for a `Query` that has already been parsed or that has been built programmatically.
+    ///
+    /// The base IRI of the query is used instead of `QueryOptions::with_base_iri`.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::sparql::algebra::*;
+    /// use oxigraph::sparql::{Query, QueryOptions, QueryResult, Variable};
+    /// use oxigraph::{MemoryStore, Result};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+    ///
+    /// // SELECT ?s WHERE { ?s <http://example.com> ?o }
+    /// let s = Variable::new("s");
+    /// let pattern = GraphPattern::BGP(vec![TriplePattern::new(s.clone(), ex.clone(), Variable::new("o")).into()]);
+    /// let query = Query::from(QueryVariants::Select {
+    ///     dataset: DatasetSpec::default(),
+    ///     algebra: GraphPattern::Project(Box::new(pattern), vec![s]),
+    ///     base_iri: None,
+    /// });
+    ///
+    /// let mut results = Vec::new();
+    /// if let QueryResult::Solutions(solutions) = store.prepare_parsed_query(query, QueryOptions::default())?.exec()? {
+    ///     for solution in solutions {
+    ///         results.push(solution?.get("s").cloned());
+    ///     }
+    /// }
+    /// assert_eq!(results, vec![Some(ex.into())]);
+    /// # Result::Ok(())
+    /// ```
+    pub fn prepare_parsed_query(
+        &self,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<MemoryPreparedQuery> {
+        Ok(MemoryPreparedQuery(SimplePreparedQuery::new_from_query(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
     /// This is similar to `prepare_query`, but useful if a SPARQL query has already been parsed, which is the case when building `ServiceHandler`s for federated queries with `SERVICE` clauses. For examples, look in the tests.
     pub fn prepare_query_from_pattern(
         &self,
@@ -125,6 +261,39 @@ impl MemoryStore {
         )?))
     }
 
+    /// Prepares a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/) and returns an object that could be used to execute it.
+    ///
+    /// All the operations of the update are executed in a single transaction, each of them seeing the changes of the previous ones.
+    /// The `LOAD` operation only supports `file://` IRIs of files in the directory set with `QueryOptions::with_load_directory`.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{MemoryStore, Result};
+    /// use oxigraph::sparql::QueryOptions;
+    ///
+    /// let store = MemoryStore::new();
+    ///
+    /// // insertion
+    /// let prepared_update = store.prepare_update("INSERT DATA { <http://example.com> <http://example.com> <http://example.com> }", QueryOptions::default())?;
+    /// prepared_update.exec()?;
+    ///
+    /// // quad filter
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// assert!(store.contains(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None)));
+    /// # Result::Ok(())
+    /// ```
+    pub fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<MemoryPreparedUpdate> {
+        Ok(MemoryPreparedUpdate {
+            store: self.clone(),
+            update: SimplePreparedUpdate::new(update, options)?,
+        })
+    }
+
     /// Retrieves quads with a filter on each quad component
     ///
     /// Usage example:
@@ -146,7 +315,7 @@ impl MemoryStore {
     /// ```
     pub fn quads_for_pattern(
         &self,
-        subject: Option<&NamedOrBlankNode>,
+        subject: Option<&Subject>,
         predicate: Option<&NamedNode>,
         object: Option<&Term>,
         graph_name: Option<&GraphName>,
@@ -156,7 +325,7 @@ impl MemoryStore {
         let object = object.map(|o| o.into());
         let graph_name = graph_name.map(|g| g.into());
         let this = self.clone();
-        self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name)
+        self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name, usize::MAX)
             .into_iter()
             .map(
                 move |quad| this.decode_quad(&quad).unwrap(), // Could not fail
@@ -170,32 +339,176 @@ impl MemoryStore {
     }
 
     /// Returns the number of quads in the store
+    ///
+    /// The number of quads of each graph is maintained by the store so the quads are not counted one by one.
     pub fn len(&self) -> usize {
-        self.indexes()
-            .spog
-            .values()
-            .map(|v| {
-                v.values()
-                    .map(|v| v.values().map(|v| v.len()).sum::<usize>())
-                    .sum::<usize>()
-            })
+        self.read_all_shards()
+            .iter()
+            .map(|shard| shard.graph_lens.values().sum::<usize>())
             .sum()
     }
 
     /// Returns if the store is empty
     pub fn is_empty(&self) -> bool {
-        self.indexes().spog.is_empty()
+        self.read_all_shards()
+            .iter()
+            .all(|shard| shard.spog.is_empty())
+    }
+
+    /// Returns the number of quads in a given graph
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone()));
+    ///
+    /// assert_eq!(store.len(), 2);
+    /// assert_eq!(store.len_graph(&GraphName::DefaultGraph), 1);
+    /// assert_eq!(store.len_graph(&ex.into()), 1);
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn len_graph(&self, graph_name: &GraphName) -> usize {
+        self.graph_len(graph_name.into())
+    }
+
+    /// Returns statistics about the content of the store.
+    ///
+    /// The distinct terms are the keys of the indexes, so the quads are not read one by one.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from(1), None));
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from(2), ex.clone()));
+    ///
+    /// let stats = store.stats();
+    /// assert_eq!(stats.quads_count, 2);
+    /// assert_eq!(stats.distinct_subjects_count, 1);
+    /// assert_eq!(stats.distinct_predicates_count, 1);
+    /// assert_eq!(stats.distinct_objects_count, 2);
+    /// assert_eq!(stats.named_graphs_count, 1);
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn stats(&self) -> StoreStats {
+        let mut quads_count = 0;
+        // A subject is in only one shard so the distinct subjects of the shards could be summed
+        let mut distinct_subjects_count = 0;
+        let mut predicates = TrivialHashSet::default();
+        let mut objects = TrivialHashSet::default();
+        let mut graphs = TrivialHashSet::default();
+        for shard in &self.read_all_shards() {
+            quads_count += shard.graph_lens.values().sum::<usize>();
+            distinct_subjects_count += shard.spog.len();
+            predicates.extend(shard.posg.keys().copied());
+            objects.extend(shard.ospg.keys().copied());
+            graphs.extend(shard.graphs.iter().copied());
+        }
+        StoreStats {
+            quads_count,
+            distinct_subjects_count,
+            distinct_predicates_count: predicates.len(),
+            distinct_objects_count: objects.len(),
+            named_graphs_count: graphs.len(),
+            strings_count: self
+                .id2str
+                .iter()
+                .map(|shard| {
+                    shard
+                        .read()
+                        .expect("the Memory store mutex has been poisoned because of a panic")
+                        .len()
+                })
+                .sum(),
+        }
+    }
+
+    /// Creates a copy of the store that could be modified independently of it.
+    ///
+    /// The copy is cheap: the two stores share their content until one of them writes to it.
+    /// The first write to a shard of the store copies this shard, so about a sixteenth of the content.
+    /// The fork does not share the watchers of the store.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    /// store.insert(quad.clone());
+    ///
+    /// // What if the quad is removed?
+    /// let fork = store.fork();
+    /// fork.remove(&quad);
+    /// assert!(!fork.contains(&quad));
+    /// assert!(store.contains(&quad));
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn fork(&self) -> Self {
+        let shards = self.read_all_shards();
+        let new = Self::new();
+        for (new_shard, shard) in new.id2str.iter().zip(self.id2str.iter()) {
+            *new_shard
+                .write()
+                .expect("the Memory store mutex has been poisoned because of a panic") = shard
+                .read()
+                .expect("the Memory store mutex has been poisoned because of a panic")
+                .clone();
+        }
+        for (new_shard, shard) in new.shards.iter().zip(shards) {
+            *new_shard
+                .write()
+                .expect("the Memory store mutex has been poisoned because of a panic") =
+                Arc::clone(&shard);
+        }
+        new
+    }
+
+    /// Returns a view of the store frozen at the time of the call.
+    ///
+    /// The writes done to the store after the snapshot creation are not visible from it,
+    /// allowing long-running exports and queries to see a consistent dataset while the store is updated.
+    /// The snapshot is a `fork` of the store so it is cheap to create and could be written without changing the store.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    ///
+    /// let snapshot = store.snapshot();
+    /// store.insert(quad.clone());
+    /// assert!(store.contains(&quad));
+    /// assert!(!snapshot.contains(&quad));
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn snapshot(&self) -> Self {
+        self.fork()
     }
 
     /// Executes a transaction.
     ///
     /// The transaction is executed if the given closure returns `Ok`.
-    /// Nothing is done if the clusre returns `Err`.
+    /// Nothing is done if the closure returns `Err`.
+    /// The changes are applied all at once: the other readers never see a part of them.
     ///
     /// Usage example:
     /// ```
     /// use oxigraph::model::*;
-    /// use oxigraph::{MemoryStore, Result};
+    /// use oxigraph::{Error, MemoryStore, Result};
     ///
     /// let store = MemoryStore::new();
     ///
@@ -206,10 +519,17 @@ impl MemoryStore {
     /// store.transaction(|transaction| {
     ///     transaction.insert(quad.clone());
     ///     Ok(())
-    /// });
+    /// })?;
     ///
     /// // quad filter
     /// assert!(store.contains(&quad));
+    ///
+    /// // rollback
+    /// assert!(store.transaction(|transaction| {
+    ///     transaction.remove(&quad);
+    ///     Err(Error::msg("Abort"))
+    /// }).is_err());
+    /// assert!(store.contains(&quad));
     /// # Result::Ok(())
     /// ```
     pub fn transaction<'a>(
@@ -219,7 +539,7 @@ impl MemoryStore {
         let mut transaction = MemoryTransaction {
             store: self,
             ops: Vec::new(),
-            strings: Vec::new(),
+            changes: PendingChanges::default(),
         };
         f(&mut transaction)?;
         transaction.commit()
@@ -257,6 +577,9 @@ impl MemoryStore {
 
     /// Loads a dataset file (i.e. quads) into the store.
     ///
+    /// The N-Quads files are parsed and encoded by multiple threads.
+    /// The quads are still written in the order of the file by the calling thread.
+    ///
     /// Usage example:
     /// ```
     /// use oxigraph::model::*;
@@ -284,6 +607,62 @@ impl MemoryStore {
         load_dataset(&mut store, reader, syntax, base_iri)
     }
 
+    /// Dumps a store graph into a file.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{MemoryStore, Result, GraphSyntax};
+    ///
+    /// let file = "<http://example.com> <http://example.com> <http://example.com> .\n".as_bytes();
+    ///
+    /// let store = MemoryStore::new();
+    /// store.load_graph(file, GraphSyntax::NTriples, &GraphName::DefaultGraph, None)?;
+    ///
+    /// let mut buffer = Vec::new();
+    /// store.dump_graph(&mut buffer, GraphSyntax::NTriples, &GraphName::DefaultGraph)?;
+    /// assert_eq!(file, buffer.as_slice());
+    /// # Result::Ok(())
+    /// ```
+    pub fn dump_graph(
+        &self,
+        writer: impl Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        dump_graph(
+            self.quads_for_pattern(None, None, None, Some(from_graph_name))
+                .map(|quad| Ok(quad.into())),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Dumps the store dataset into a file.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{MemoryStore, Result, DatasetSyntax};
+    ///
+    /// let file = "<http://example.com> <http://example.com> <http://example.com> <http://example.com> .\n".as_bytes();
+    ///
+    /// let store = MemoryStore::new();
+    /// store.load_dataset(file, DatasetSyntax::NQuads, None)?;
+    ///
+    /// let mut buffer = Vec::new();
+    /// store.dump_dataset(&mut buffer, DatasetSyntax::NQuads)?;
+    /// assert_eq!(file, buffer.as_slice());
+    /// # Result::Ok(())
+    /// ```
+    pub fn dump_dataset(&self, writer: impl Write, syntax: DatasetSyntax) -> Result<()> {
+        dump_dataset(
+            self.quads_for_pattern(None, None, None, None).map(Ok),
+            writer,
+            syntax,
+        )
+    }
+
     /// Adds a quad to this store.
     #[allow(clippy::needless_pass_by_value)]
     pub fn insert(&self, quad: Quad) {
@@ -292,6 +671,70 @@ impl MemoryStore {
         store.insert_encoded(&quad).unwrap(); // Could never fail
     }
 
+    /// Adds quads to this store.
+    ///
+    /// The quads are encoded before taking any lock then the shards they are inserted in are all locked at the same time.
+    /// The insertion is atomic: the other readers never see a part of it.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert_all(vec![
+    ///     Quad::new(ex.clone(), ex.clone(), Literal::from(1), None),
+    ///     Quad::new(ex.clone(), ex.clone(), Literal::from(2), None),
+    /// ]);
+    /// assert_eq!(store.len(), 2);
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn insert_all(&self, quads: impl IntoIterator<Item = Quad>) {
+        let mut store = self;
+        let mut quads_by_shard = vec![Vec::new(); SHARD_COUNT];
+        for quad in quads {
+            let quad = store.encode_quad(&quad).unwrap(); // Could never fail
+            quads_by_shard[shard_position(quad.subject)].push(quad);
+        }
+        let mut shards = self.write_shards(
+            quads_by_shard
+                .iter()
+                .enumerate()
+                .filter(|(_, quads)| !quads.is_empty())
+                .map(|(position, _)| position)
+                .collect::<Vec<_>>(),
+        );
+        for quad in quads_by_shard.iter().flatten() {
+            shards.insert_encoded(quad).unwrap(); // Could never fail
+        }
+    }
+
+    /// Adds all the quads and named graphs of another store to this store.
+    ///
+    /// The quads are streamed from the other store without decoding their terms, only the strings they use are copied.
+    /// The changes are applied atomically at the end of the merge.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let staging = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone());
+    /// staging.insert(quad.clone());
+    ///
+    /// let store = MemoryStore::new();
+    /// store.merge_from(&staging)?;
+    /// assert!(store.contains(&quad));
+    /// assert!(store.contains_named_graph(&ex.into()));
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn merge_from(&self, other: &impl MergeSource) -> Result<()> {
+        self.transaction(|transaction| merge_encoded(other, transaction))
+    }
+
     /// Removes a quad from this store.
     pub fn remove(&self, quad: &Quad) {
         let mut store = self;
@@ -299,43 +742,774 @@ impl MemoryStore {
         store.remove_encoded(&quad).unwrap(); // Could never fail
     }
 
-    /// Returns if the current dataset is [isomorphic](https://www.w3.org/TR/rdf11-concepts/#dfn-dataset-isomorphism) with another one.
+    /// Removes all the quads matching a pattern from this store.
     ///
-    /// It is implemented using the canonicalization approach presented in
-    /// [Canonical Forms for Isomorphic and Equivalent RDF Graphs: Algorithms for Leaning and Labelling Blank Nodes, Aidan Hogan, 2017](http://aidanhogan.com/docs/rdf-canonicalisation.pdf)
+    /// The removal is atomic: the other readers never see a part of it.
     ///
-    /// Warning: This implementation worst-case complexity is in O(b!) with b the number of blank node node in the input graphs.
-    pub fn is_isomorphic(&self, other: &Self) -> bool {
-        iso_canonicalize(self) == iso_canonicalize(other)
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let other = NamedNode::new("http://example.com/other")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from(1), None));
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from(2), None));
+    /// store.insert(Quad::new(ex.clone(), other.clone(), Literal::from(3), None));
+    ///
+    /// store.remove_quads_for_pattern(Some(&ex.clone().into()), Some(&ex), None, None);
+    /// assert_eq!(store.len(), 1);
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn remove_quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        // The matching quads are looked up under the write locks in order for the removal to be atomic
+        let mut shards = if let Some(subject) = subject {
+            // All the quads with the same subject are in the same shard
+            self.write_shard_of(subject)
+        } else {
+            self.write_all_shards()
+        };
+        let quads: Vec<_> = shards
+            .shards
+            .iter()
+            .flat_map(|(_, shard)| {
+                shard.encoded_quads_for_pattern(subject, predicate, object, graph_name, usize::MAX)
+            })
+            .collect();
+        for quad in quads {
+            shards.remove_encoded(&quad).unwrap(); // Could never fail
+        }
     }
 
-    fn indexes(&self) -> RwLockReadGuard<'_, MemoryStoreIndexes> {
-        self.indexes
-            .read()
-            .expect("the Memory store mutex has been poisoned because of a panic")
+    /// Returns the named graphs of the store, including the ones without quads.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{MemoryStore, Result};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedOrBlankNode::from(NamedNode::new("http://example.com")?);
+    ///
+    /// // an empty named graph
+    /// store.insert_named_graph(&ex);
+    /// assert_eq!(vec![ex.clone()], store.named_graphs().collect::<Vec<_>>());
+    /// assert!(store.is_empty());
+    ///
+    /// // graph removal
+    /// store.remove_named_graph(&ex);
+    /// assert!(!store.contains_named_graph(&ex));
+    /// # Result::Ok(())
+    /// ```
+    pub fn named_graphs(&self) -> impl Iterator<Item = NamedOrBlankNode> {
+        let this = self.clone();
+        self.encoded_named_graphs_inner().into_iter().map(
+            move |graph_name| this.decode_named_or_blank_node(graph_name).unwrap(), // Could not fail
+        )
     }
 
-    fn indexes_mut(&self) -> RwLockWriteGuard<'_, MemoryStoreIndexes> {
-        self.indexes
-            .write()
-            .expect("the Memory store mutex has been poisoned because of a panic")
+    /// Checks if the store contains a given named graph, even if it does not contain any quad
+    ///
+    /// See `named_graphs` for a usage example.
+    pub fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> bool {
+        self.contains_encoded_named_graph_inner(graph_name.into())
     }
 
-    fn contains_encoded(&self, quad: &EncodedQuad) -> bool {
-        self.indexes().spog.get(&quad.subject).map_or(false, |pog| {
-            pog.get(&quad.predicate).map_or(false, |og| {
-                og.get(&quad.object)
-                    .map_or(false, |g| g.contains(&quad.graph_name))
-            })
-        })
+    /// Adds a named graph to the store, even if it does not contain any quad
+    ///
+    /// See `named_graphs` for a usage example.
+    pub fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) {
+        let mut store = self;
+        let graph_name = store.encode_named_or_blank_node(graph_name).unwrap(); // Could never fail
+        store.insert_encoded_named_graph(graph_name).unwrap(); // Could never fail
     }
 
-    fn encoded_quads_for_pattern_inner(
-        &self,
-        subject: Option<EncodedTerm>,
-        predicate: Option<EncodedTerm>,
-        object: Option<EncodedTerm>,
-        graph_name: Option<EncodedTerm>,
+    /// Removes all the quads of a graph. The graph itself stays in the store.
+    pub fn clear_graph(&self, graph_name: &GraphName) {
+        let mut store = self;
+        store.clear_encoded_graph(graph_name.into()).unwrap(); // Could never fail
+    }
+
+    /// Removes a named graph and all its quads from the store
+    ///
+    /// See `named_graphs` for a usage example.
+    pub fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) {
+        let mut store = self;
+        store.remove_encoded_named_graph(graph_name.into()).unwrap(); // Could never fail
+    }
+
+    /// Adds the quads of the graph `from` to the graph `to`, like the SPARQL [`ADD`](https://www.w3.org/TR/sparql11-update/#add) operation.
+    ///
+    /// The quads are copied without decoding their terms.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let g = GraphName::from(NamedNode::new("http://example.com/g")?);
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+    ///
+    /// // add
+    /// store.add_graph(&GraphName::DefaultGraph, &g);
+    /// assert!(store.contains(&Quad::new(ex.clone(), ex.clone(), ex.clone(), g.clone())));
+    /// assert_eq!(2, store.len());
+    ///
+    /// // move
+    /// store.move_graph(&g, &GraphName::DefaultGraph);
+    /// assert_eq!(1, store.len());
+    /// assert_eq!(0, store.named_graphs().count());
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn add_graph(&self, from: &GraphName, to: &GraphName) {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            add_encoded_graph(self, transaction, from.into(), to)
+        })
+        .unwrap(); // Could never fail
+    }
+
+    /// Replaces the content of the graph `to` by the one of the graph `from`, like the SPARQL [`COPY`](https://www.w3.org/TR/sparql11-update/#copy) operation.
+    ///
+    /// See `add_graph` for a usage example.
+    pub fn copy_graph(&self, from: &GraphName, to: &GraphName) {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            copy_encoded_graph(self, transaction, from.into(), to)
+        })
+        .unwrap(); // Could never fail
+    }
+
+    /// Moves the content of the graph `from` into the graph `to`, like the SPARQL [`MOVE`](https://www.w3.org/TR/sparql11-update/#move) operation.
+    ///
+    /// The graph `from` is removed from the store, or only cleared if it is the default graph.
+    ///
+    /// See `add_graph` for a usage example.
+    pub fn move_graph(&self, from: &GraphName, to: &GraphName) {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            move_encoded_graph(self, transaction, from.into(), to)
+        })
+        .unwrap(); // Could never fail
+    }
+
+    /// Subscribes to the changes of the store.
+    ///
+    /// Each message received contains the changes of an atomic write to the store,
+    /// like a transaction or the insertion of the quads of a shard by `insert_all`, in the order they have been applied.
+    /// Only the actual changes are reported: inserting a quad that is already in the store does not generate any event.
+    /// The subscription ends when the receiver is dropped.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::store::QuadEvent;
+    /// use oxigraph::MemoryStore;
+    ///
+    /// let store = MemoryStore::new();
+    /// let changes = store.watch();
+    ///
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    /// store.transaction(|transaction| {
+    ///     transaction.insert(quad.clone());
+    ///     transaction.insert(quad.clone());
+    ///     Ok(())
+    /// })?;
+    /// store.remove(&quad);
+    ///
+    /// assert_eq!(changes.try_recv(), Ok(vec![QuadEvent::Inserted(quad.clone())]));
+    /// assert_eq!(changes.try_recv(), Ok(vec![QuadEvent::Removed(quad)]));
+    /// assert!(changes.try_recv().is_err());
+    /// # oxigraph::Result::Ok(())
+    /// ```
+    pub fn watch(&self) -> Receiver<Vec<QuadEvent>> {
+        self.watchers.watch()
+    }
+
+    /// Returns if the current dataset is [isomorphic](https://www.w3.org/TR/rdf11-concepts/#dfn-dataset-isomorphism) with another one.
+    ///
+    /// It is implemented using the canonicalization approach presented in
+    /// [Canonical Forms for Isomorphic and Equivalent RDF Graphs: Algorithms for Leaning and Labelling Blank Nodes, Aidan Hogan, 2017](http://aidanhogan.com/docs/rdf-canonicalisation.pdf)
+    ///
+    /// Warning: This implementation worst-case complexity is in O(b!) with b the number of blank node node in the input graphs.
+    pub fn is_isomorphic(&self, other: &Self) -> bool {
+        iso_canonicalize(self) == iso_canonicalize(other)
+    }
+
+    fn read_shard_of(&self, term: EncodedTerm) -> RwLockReadGuard<'_, Arc<MemoryStoreIndexes>> {
+        read_shard(&self.shards[shard_position(term)])
+    }
+
+    /// Takes the read locks of all the shards at the same time in order to not see a part of a write done on multiple shards
+    fn read_all_shards(&self) -> Vec<RwLockReadGuard<'_, Arc<MemoryStoreIndexes>>> {
+        self.shards.iter().map(read_shard).collect()
+    }
+
+    /// Takes the write locks of the given shards, the positions should be sorted in order to avoid deadlocks
+    fn write_shards(
+        &self,
+        positions: impl IntoIterator<Item = usize>,
+    ) -> MemoryStoreWriteGuard<'_> {
+        MemoryStoreWriteGuard {
+            store: self,
+            shards: positions
+                .into_iter()
+                .map(|position| {
+                    (
+                        position,
+                        self.shards[position]
+                            .write()
+                            .expect("the Memory store mutex has been poisoned because of a panic"),
+                    )
+                })
+                .collect(),
+            events: if self.watchers.is_watched() {
+                Some(Vec::new())
+            } else {
+                None
+            },
+        }
+    }
+
+    fn write_shard_of(&self, term: EncodedTerm) -> MemoryStoreWriteGuard<'_> {
+        self.write_shards(once(shard_position(term)))
+    }
+
+    fn write_all_shards(&self) -> MemoryStoreWriteGuard<'_> {
+        self.write_shards(0..SHARD_COUNT)
+    }
+
+    fn insert_id2str(&self, key: StrHash, value: &str) -> Result<()> {
+        let mut id2str = self.id2str[shard_position(key)]
+            .write()
+            .expect("the Memory store mutex has been poisoned because of a panic");
+        if let Some(stored) = id2str.get(&key) {
+            return check_str_hash_collision(key, stored.as_bytes(), value);
+        }
+        Arc::make_mut(&mut id2str).insert(key, value.into());
+        Ok(())
+    }
+
+    fn contains_encoded(&self, quad: &EncodedQuad) -> bool {
+        self.read_shard_of(quad.subject).contains(quad)
+    }
+
+    fn encoded_quads_for_pattern_inner(
+        &self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: usize,
+    ) -> Vec<EncodedQuad> {
+        if let Some(subject) = subject {
+            // All the quads with the same subject are in the same shard
+            return self.read_shard_of(subject).encoded_quads_for_pattern(
+                Some(subject),
+                predicate,
+                object,
+                graph_name,
+                limit,
+            );
+        }
+        let mut quads = Vec::new();
+        for shard in &self.read_all_shards() {
+            if quads.len() >= limit {
+                break;
+            }
+            quads.extend(shard.encoded_quads_for_pattern(
+                None,
+                predicate,
+                object,
+                graph_name,
+                limit - quads.len(),
+            ));
+        }
+        quads
+    }
+
+    fn encoded_named_graphs_inner(&self) -> Vec<EncodedTerm> {
+        let mut graphs = TrivialHashSet::default();
+        for shard in &self.read_all_shards() {
+            graphs.extend(shard.graphs.iter().copied());
+        }
+        graphs.into_iter().collect()
+    }
+
+    fn contains_encoded_named_graph_inner(&self, graph_name: EncodedTerm) -> bool {
+        self.read_all_shards()
+            .iter()
+            .any(|shard| shard.graphs.contains(&graph_name))
+    }
+
+    fn graph_len(&self, graph_name: EncodedTerm) -> usize {
+        self.read_all_shards()
+            .iter()
+            .map(|shard| shard.graph_lens.get(&graph_name).copied().unwrap_or(0))
+            .sum()
+    }
+}
+
+impl sealed::MergeSource for MemoryStore {
+    fn encoded_quads_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + '_> {
+        self.encoded_quads_for_pattern(None, None, None, None)
+    }
+
+    fn encoded_named_graphs_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + '_> {
+        self.encoded_named_graphs()
+    }
+}
+
+impl StrLookup for MemoryStore {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        Ok(self
+            .get_stored_shared_str(id)?
+            .map(|value| value.to_string()))
+    }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        Ok(self.id2str[shard_position(id)]
+            .read()
+            .expect("the Memory store mutex has been poisoned because of a panic")
+            .get(&id)
+            .cloned())
+    }
+}
+
+impl StrContainer for MemoryStore {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.insert_id2str(key, value)
+    }
+}
+
+impl<'a> StrContainer for &'a MemoryStore {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.insert_id2str(key, value)
+    }
+}
+
+impl StrContainer for MemoryStoreWriteGuard<'_> {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.store.insert_id2str(key, value)
+    }
+}
+
+impl<'a> ReadableEncodedStore for MemoryStore {
+    fn encoded_quads_for_pattern<'b>(
+        &'b self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'b> {
+        Box::new(
+            self.encoded_quads_for_pattern_inner(
+                subject,
+                predicate,
+                object,
+                graph_name,
+                usize::MAX,
+            )
+            .into_iter()
+            .map(Ok),
+        )
+    }
+
+    fn encoded_quads_for_pattern_with_limit<'b>(
+        &'b self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: usize,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'b> {
+        // We avoid to copy the quads that are not going to be read
+        Box::new(
+            self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name, limit)
+                .into_iter()
+                .map(Ok),
+        )
+    }
+
+    fn encoded_named_graphs<'b>(&'b self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b> {
+        Box::new(self.encoded_named_graphs_inner().into_iter().map(Ok))
+    }
+
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        Ok(self.contains_encoded_named_graph_inner(graph_name))
+    }
+
+    #[cfg(feature = "full-text")]
+    fn encoded_literals_matching<'b>(
+        &'b self,
+        query: &str,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b> {
+        let words: Vec<_> = text_words(query).collect();
+        if words.is_empty() {
+            return Box::new(empty());
+        }
+        // Each shard indexes the literals used by its quads
+        let mut literals = TrivialHashSet::default();
+        for shard in &self.read_all_shards() {
+            let mut sets: Vec<_> = words.iter().map(|word| shard.words.get(word)).collect();
+            if sets.iter().any(Option::is_none) {
+                continue;
+            }
+            sets.sort_by_key(|set| set.map_or(0, |set| set.len()));
+            let (smallest, others) = sets.split_first().unwrap();
+            literals.extend(option_set_flatten(*smallest).filter(|literal| {
+                others
+                    .iter()
+                    .all(|set| set.map_or(false, |set| set.contains(literal)))
+            }));
+        }
+        Box::new(literals.into_iter().map(Ok))
+    }
+
+    #[cfg(feature = "spatial-index")]
+    fn encoded_geometries_in<'b>(
+        &'b self,
+        envelope: Envelope,
+        within: bool,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b> {
+        let envelope = AABB::from_corners(envelope.min, envelope.max);
+        // A literal is indexed by all the shards with quads using it
+        let mut literals = TrivialHashSet::default();
+        for shard in &self.read_all_shards() {
+            if within {
+                literals.extend(
+                    shard
+                        .geometries
+                        .locate_in_envelope(&envelope)
+                        .map(|geometry| geometry.literal),
+                );
+            } else {
+                literals.extend(
+                    shard
+                        .geometries
+                        .locate_in_envelope_intersecting(&envelope)
+                        .map(|geometry| geometry.literal),
+                );
+            }
+        }
+        Box::new(literals.into_iter().map(Ok))
+    }
+}
+
+impl StoreStatistics for MemoryStore {
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        let mut quads_count = 0;
+        let mut distinct_subjects_count = 0;
+        let mut objects = TrivialHashSet::default();
+        for shard in &self.read_all_shards() {
+            if let Some(osg) = shard.posg.get(&predicate) {
+                // A subject is in only one shard so the distinct subjects of the shards could be summed
+                let mut subjects = TrivialHashSet::default();
+                for (o, sg) in osg {
+                    objects.insert(*o);
+                    for (s, g) in sg {
+                        quads_count += g.len() as u64;
+                        subjects.insert(*s);
+                    }
+                }
+                distinct_subjects_count += subjects.len() as u64;
+            }
+        }
+        Some(PredicateStatistics {
+            quads_count,
+            distinct_subjects_count,
+            distinct_objects_count: objects.len() as u64,
+        })
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        Some(self.graph_len(graph_name) as u64)
+    }
+
+    #[cfg(feature = "full-text")]
+    fn literals_matching_count(&self, query: &str) -> Option<u64> {
+        let words: Vec<_> = text_words(query).collect();
+        if words.is_empty() {
+            return Some(0);
+        }
+        // The literals of a shard contain at most the number of literals with its least frequent word
+        Some(
+            self.read_all_shards()
+                .iter()
+                .map(|shard| {
+                    words
+                        .iter()
+                        .map(|word| shard.words.get(word).map_or(0, |set| set.len() as u64))
+                        .min()
+                        .unwrap_or(0)
+                })
+                .sum(),
+        )
+    }
+
+    #[cfg(feature = "spatial-index")]
+    fn geometries_in_count(&self, envelope: &Envelope, within: bool) -> Option<u64> {
+        let envelope = AABB::from_corners(envelope.min, envelope.max);
+        Some(
+            self.read_all_shards()
+                .iter()
+                .map(|shard| {
+                    if within {
+                        shard.geometries.locate_in_envelope(&envelope).count() as u64
+                    } else {
+                        shard
+                            .geometries
+                            .locate_in_envelope_intersecting(&envelope)
+                            .count() as u64
+                    }
+                })
+                .sum(),
+        )
+    }
+}
+
+impl WritableEncodedStore for MemoryStore {
+    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.write_shard_of(quad.subject).insert_encoded(quad)
+    }
+
+    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.write_shard_of(quad.subject).remove_encoded(quad)
+    }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_shard_of(graph_name)
+            .insert_encoded_named_graph(graph_name)
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_all_shards().clear_encoded_graph(graph_name)
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_all_shards()
+            .remove_encoded_named_graph(graph_name)
+    }
+}
+
+impl<'a> WritableEncodedStore for &'a MemoryStore {
+    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.write_shard_of(quad.subject).insert_encoded(quad)
+    }
+
+    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.write_shard_of(quad.subject).remove_encoded(quad)
+    }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_shard_of(graph_name)
+            .insert_encoded_named_graph(graph_name)
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_all_shards().clear_encoded_graph(graph_name)
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_all_shards()
+            .remove_encoded_named_graph(graph_name)
+    }
+}
+
+impl MemoryStoreIndexes {
+    /// Inserts a quad in the indexes and returns if it was not already there
+    #[cfg_attr(
+        not(any(feature = "full-text", feature = "spatial-index")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn insert(&mut self, quad: &EncodedQuad, strings: &impl StrLookup) -> Result<bool> {
+        #[cfg(feature = "full-text")]
+        {
+            if !self.ospg.contains_key(&quad.object) {
+                if let Some(words) = literal_words(strings, quad.object)? {
+                    for word in words {
+                        self.words.entry(word).or_default().insert(quad.object);
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "spatial-index")]
+        {
+            if !self.ospg.contains_key(&quad.object) {
+                if let Some(geometry) = IndexedGeometry::new(strings, quad.object)? {
+                    self.geometries.insert(geometry);
+                }
+            }
+        }
+        if quad.graph_name != ENCODED_DEFAULT_GRAPH {
+            self.graphs.insert(quad.graph_name);
+        }
+        if !insert_into_quad_map(
+            &mut self.gosp,
+            quad.graph_name,
+            quad.object,
+            quad.subject,
+            quad.predicate,
+        ) {
+            // The quad is already in all the indexes
+            return Ok(false);
+        }
+        *self.graph_lens.entry(quad.graph_name).or_default() += 1;
+        insert_into_quad_map(
+            &mut self.gpos,
+            quad.graph_name,
+            quad.predicate,
+            quad.object,
+            quad.subject,
+        );
+        insert_into_quad_map(
+            &mut self.gspo,
+            quad.graph_name,
+            quad.subject,
+            quad.predicate,
+            quad.object,
+        );
+        insert_into_quad_map(
+            &mut self.ospg,
+            quad.object,
+            quad.subject,
+            quad.predicate,
+            quad.graph_name,
+        );
+        insert_into_quad_map(
+            &mut self.posg,
+            quad.predicate,
+            quad.object,
+            quad.subject,
+            quad.graph_name,
+        );
+        insert_into_quad_map(
+            &mut self.spog,
+            quad.subject,
+            quad.predicate,
+            quad.object,
+            quad.graph_name,
+        );
+        Ok(true)
+    }
+
+    /// Removes a quad from the indexes and returns if it was there
+    #[cfg_attr(
+        not(any(feature = "full-text", feature = "spatial-index")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn remove(&mut self, quad: &EncodedQuad, strings: &impl StrLookup) -> Result<bool> {
+        if !remove_from_quad_map(
+            &mut self.gosp,
+            &quad.graph_name,
+            &quad.object,
+            &quad.subject,
+            &quad.predicate,
+        ) {
+            // The quad is in none of the indexes
+            return Ok(false);
+        }
+        let mut graph_is_empty = false;
+        if let Some(len) = self.graph_lens.get_mut(&quad.graph_name) {
+            *len -= 1;
+            graph_is_empty = *len == 0;
+        }
+        if graph_is_empty {
+            self.graph_lens.remove(&quad.graph_name);
+        }
+        remove_from_quad_map(
+            &mut self.gpos,
+            &quad.graph_name,
+            &quad.predicate,
+            &quad.object,
+            &quad.subject,
+        );
+        remove_from_quad_map(
+            &mut self.gspo,
+            &quad.graph_name,
+            &quad.subject,
+            &quad.predicate,
+            &quad.object,
+        );
+        remove_from_quad_map(
+            &mut self.ospg,
+            &quad.object,
+            &quad.subject,
+            &quad.predicate,
+            &quad.graph_name,
+        );
+        remove_from_quad_map(
+            &mut self.posg,
+            &quad.predicate,
+            &quad.object,
+            &quad.subject,
+            &quad.graph_name,
+        );
+        remove_from_quad_map(
+            &mut self.spog,
+            &quad.subject,
+            &quad.predicate,
+            &quad.object,
+            &quad.graph_name,
+        );
+        #[cfg(feature = "full-text")]
+        {
+            if !self.ospg.contains_key(&quad.object) {
+                if let Some(words) = literal_words(strings, quad.object)? {
+                    for word in words {
+                        let mut is_empty = false;
+                        if let Some(literals) = self.words.get_mut(&word) {
+                            literals.remove(&quad.object);
+                            is_empty = literals.is_empty();
+                        }
+                        if is_empty {
+                            self.words.remove(&word);
+                        }
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "spatial-index")]
+        {
+            if !self.ospg.contains_key(&quad.object) {
+                if let Some(geometry) = IndexedGeometry::new(strings, quad.object)? {
+                    self.geometries.remove(&geometry);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    pub(crate) fn contains(&self, quad: &EncodedQuad) -> bool {
+        self.spog.get(&quad.subject).map_or(false, |pog| {
+            pog.get(&quad.predicate).map_or(false, |og| {
+                og.get(&quad.object)
+                    .map_or(false, |g| g.contains(&quad.graph_name))
+            })
+        })
+    }
+
+    pub(crate) fn encoded_quads_for_pattern(
+        &self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
         match subject {
             Some(subject) => match predicate {
@@ -343,34 +1517,35 @@ impl MemoryStore {
                     Some(object) => match graph_name {
                         Some(graph_name) => {
                             let quad = EncodedQuad::new(subject, predicate, object, graph_name);
-                            if self.contains_encoded(&quad) {
+                            if self.contains(&quad) {
                                 vec![quad]
                             } else {
                                 vec![]
                             }
                         }
-                        None => self
-                            .encoded_quads_for_subject_predicate_object(subject, predicate, object),
+                        None => self.encoded_quads_for_subject_predicate_object(
+                            subject, predicate, object, limit,
+                        ),
                     },
                     None => match graph_name {
                         Some(graph_name) => self.encoded_quads_for_subject_predicate_graph(
-                            subject, predicate, graph_name,
+                            subject, predicate, graph_name, limit,
                         ),
-                        None => self.encoded_quads_for_subject_predicate(subject, predicate),
+                        None => self.encoded_quads_for_subject_predicate(subject, predicate, limit),
                     },
                 },
                 None => match object {
                     Some(object) => match graph_name {
-                        Some(graph_name) => {
-                            self.encoded_quads_for_subject_object_graph(subject, object, graph_name)
-                        }
-                        None => self.encoded_quads_for_subject_object(subject, object),
+                        Some(graph_name) => self.encoded_quads_for_subject_object_graph(
+                            subject, object, graph_name, limit,
+                        ),
+                        None => self.encoded_quads_for_subject_object(subject, object, limit),
                     },
                     None => match graph_name {
                         Some(graph_name) => {
-                            self.encoded_quads_for_subject_graph(subject, graph_name)
+                            self.encoded_quads_for_subject_graph(subject, graph_name, limit)
                         }
-                        None => self.encoded_quads_for_subject(subject),
+                        None => self.encoded_quads_for_subject(subject, limit),
                     },
                 },
             },
@@ -378,40 +1553,70 @@ impl MemoryStore {
                 Some(predicate) => match object {
                     Some(object) => match graph_name {
                         Some(graph_name) => self.encoded_quads_for_predicate_object_graph(
-                            predicate, object, graph_name,
+                            predicate, object, graph_name, limit,
                         ),
-                        None => self.encoded_quads_for_predicate_object(predicate, object),
+                        None => self.encoded_quads_for_predicate_object(predicate, object, limit),
                     },
                     None => match graph_name {
                         Some(graph_name) => {
-                            self.encoded_quads_for_predicate_graph(predicate, graph_name)
+                            self.encoded_quads_for_predicate_graph(predicate, graph_name, limit)
                         }
-                        None => self.encoded_quads_for_predicate(predicate),
+                        None => self.encoded_quads_for_predicate(predicate, limit),
                     },
                 },
                 None => match object {
                     Some(object) => match graph_name {
-                        Some(graph_name) => self.encoded_quads_for_object_graph(object, graph_name),
-                        None => self.encoded_quads_for_object(object),
+                        Some(graph_name) => {
+                            self.encoded_quads_for_object_graph(object, graph_name, limit)
+                        }
+                        None => self.encoded_quads_for_object(object, limit),
                     },
                     None => match graph_name {
-                        Some(graph_name) => self.encoded_quads_for_graph(graph_name),
-                        None => self.encoded_quads(),
+                        Some(graph_name) => self.encoded_quads_for_graph(graph_name, limit),
+                        None => self.encoded_quads(limit),
                     },
                 },
             },
         }
     }
 
-    fn encoded_quads(&self) -> Vec<EncodedQuad> {
-        quad_map_flatten(&self.indexes().gspo)
-            .map(|(g, s, p, o)| EncodedQuad::new(s, p, o, g))
-            .collect()
+    fn encoded_quads(&self, limit: usize) -> Vec<EncodedQuad> {
+        quad_map_flatten(&self.gspo)
+            .map(|(g, s, p, o)| EncodedQuad::new(s, p, o, g))
+            .take(limit)
+            .collect()
+    }
+
+    /// Removes all the quads from the indexes and returns them.
+    ///
+    /// The named graphs and the number of quads of each graph are kept.
+    pub(crate) fn take_quads(&mut self) -> Vec<EncodedQuad> {
+        let quads = self.encoded_quads(usize::MAX);
+        *self = Self {
+            graphs: take(&mut self.graphs),
+            graph_lens: take(&mut self.graph_lens),
+            ..Self::default()
+        };
+        quads
+    }
+
+    /// Inserts back the quads returned by `take_quads`
+    pub(crate) fn restore_quads(
+        &mut self,
+        quads: impl IntoIterator<Item = EncodedQuad>,
+        strings: &impl StrLookup,
+    ) -> Result<()> {
+        self.graph_lens.clear();
+        for quad in quads {
+            self.insert(&quad, strings)?;
+        }
+        Ok(())
     }
 
-    fn encoded_quads_for_subject(&self, subject: EncodedTerm) -> Vec<EncodedQuad> {
-        option_triple_map_flatten(self.indexes().spog.get(&subject))
+    fn encoded_quads_for_subject(&self, subject: EncodedTerm, limit: usize) -> Vec<EncodedQuad> {
+        option_triple_map_flatten(self.spog.get(&subject))
             .map(|(p, o, g)| EncodedQuad::new(subject, p, o, g))
+            .take(limit)
             .collect()
     }
 
@@ -419,15 +1624,12 @@ impl MemoryStore {
         &self,
         subject: EncodedTerm,
         predicate: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
-        option_pair_map_flatten(
-            self.indexes()
-                .spog
-                .get(&subject)
-                .and_then(|pog| pog.get(&predicate)),
-        )
-        .map(|(o, g)| EncodedQuad::new(subject, predicate, o, g))
-        .collect()
+        option_pair_map_flatten(self.spog.get(&subject).and_then(|pog| pog.get(&predicate)))
+            .map(|(o, g)| EncodedQuad::new(subject, predicate, o, g))
+            .take(limit)
+            .collect()
     }
 
     fn encoded_quads_for_subject_predicate_object(
@@ -435,15 +1637,16 @@ impl MemoryStore {
         subject: EncodedTerm,
         predicate: EncodedTerm,
         object: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
         option_set_flatten(
-            self.indexes()
-                .spog
+            self.spog
                 .get(&subject)
                 .and_then(|pog| pog.get(&predicate))
                 .and_then(|og| og.get(&object)),
         )
         .map(|g| EncodedQuad::new(subject, predicate, object, g))
+        .take(limit)
         .collect()
     }
 
@@ -451,20 +1654,22 @@ impl MemoryStore {
         &self,
         subject: EncodedTerm,
         object: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
-        option_pair_map_flatten(
-            self.indexes()
-                .ospg
-                .get(&object)
-                .and_then(|spg| spg.get(&subject)),
-        )
-        .map(|(p, g)| EncodedQuad::new(subject, p, object, g))
-        .collect()
+        option_pair_map_flatten(self.ospg.get(&object).and_then(|spg| spg.get(&subject)))
+            .map(|(p, g)| EncodedQuad::new(subject, p, object, g))
+            .take(limit)
+            .collect()
     }
 
-    fn encoded_quads_for_predicate(&self, predicate: EncodedTerm) -> Vec<EncodedQuad> {
-        option_triple_map_flatten(self.indexes().posg.get(&predicate))
+    fn encoded_quads_for_predicate(
+        &self,
+        predicate: EncodedTerm,
+        limit: usize,
+    ) -> Vec<EncodedQuad> {
+        option_triple_map_flatten(self.posg.get(&predicate))
             .map(|(o, s, g)| EncodedQuad::new(s, predicate, o, g))
+            .take(limit)
             .collect()
     }
 
@@ -472,26 +1677,29 @@ impl MemoryStore {
         &self,
         predicate: EncodedTerm,
         object: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
-        option_pair_map_flatten(
-            self.indexes()
-                .posg
-                .get(&predicate)
-                .and_then(|osg| osg.get(&object)),
-        )
-        .map(|(s, g)| EncodedQuad::new(s, predicate, object, g))
-        .collect()
+        option_pair_map_flatten(self.posg.get(&predicate).and_then(|osg| osg.get(&object)))
+            .map(|(s, g)| EncodedQuad::new(s, predicate, object, g))
+            .take(limit)
+            .collect()
     }
 
-    fn encoded_quads_for_object(&self, object: EncodedTerm) -> Vec<EncodedQuad> {
-        option_triple_map_flatten(self.indexes().ospg.get(&object))
+    fn encoded_quads_for_object(&self, object: EncodedTerm, limit: usize) -> Vec<EncodedQuad> {
+        option_triple_map_flatten(self.ospg.get(&object))
             .map(|(s, p, g)| EncodedQuad::new(s, p, object, g))
+            .take(limit)
             .collect()
     }
 
-    fn encoded_quads_for_graph(&self, graph_name: EncodedTerm) -> Vec<EncodedQuad> {
-        option_triple_map_flatten(self.indexes().gspo.get(&graph_name))
+    pub(crate) fn encoded_quads_for_graph(
+        &self,
+        graph_name: EncodedTerm,
+        limit: usize,
+    ) -> Vec<EncodedQuad> {
+        option_triple_map_flatten(self.gspo.get(&graph_name))
             .map(|(s, p, o)| EncodedQuad::new(s, p, o, graph_name))
+            .take(limit)
             .collect()
     }
 
@@ -499,15 +1707,12 @@ impl MemoryStore {
         &self,
         subject: EncodedTerm,
         graph_name: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
-        option_pair_map_flatten(
-            self.indexes()
-                .gspo
-                .get(&graph_name)
-                .and_then(|spo| spo.get(&subject)),
-        )
-        .map(|(p, o)| EncodedQuad::new(subject, p, o, graph_name))
-        .collect()
+        option_pair_map_flatten(self.gspo.get(&graph_name).and_then(|spo| spo.get(&subject)))
+            .map(|(p, o)| EncodedQuad::new(subject, p, o, graph_name))
+            .take(limit)
+            .collect()
     }
 
     fn encoded_quads_for_subject_predicate_graph(
@@ -515,15 +1720,16 @@ impl MemoryStore {
         subject: EncodedTerm,
         predicate: EncodedTerm,
         graph_name: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
         option_set_flatten(
-            self.indexes()
-                .gspo
+            self.gspo
                 .get(&graph_name)
                 .and_then(|spo| spo.get(&subject))
                 .and_then(|po| po.get(&predicate)),
         )
         .map(|o| EncodedQuad::new(subject, predicate, o, graph_name))
+        .take(limit)
         .collect()
     }
 
@@ -532,15 +1738,16 @@ impl MemoryStore {
         subject: EncodedTerm,
         object: EncodedTerm,
         graph_name: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
         option_set_flatten(
-            self.indexes()
-                .gosp
+            self.gosp
                 .get(&graph_name)
                 .and_then(|osp| osp.get(&object))
                 .and_then(|sp| sp.get(&subject)),
         )
         .map(|p| EncodedQuad::new(subject, p, object, graph_name))
+        .take(limit)
         .collect()
     }
 
@@ -548,14 +1755,15 @@ impl MemoryStore {
         &self,
         predicate: EncodedTerm,
         graph_name: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
         option_pair_map_flatten(
-            self.indexes()
-                .gpos
+            self.gpos
                 .get(&graph_name)
                 .and_then(|pos| pos.get(&predicate)),
         )
         .map(|(o, s)| EncodedQuad::new(s, predicate, o, graph_name))
+        .take(limit)
         .collect()
     }
 
@@ -564,15 +1772,16 @@ impl MemoryStore {
         predicate: EncodedTerm,
         object: EncodedTerm,
         graph_name: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
         option_set_flatten(
-            self.indexes()
-                .gpos
+            self.gpos
                 .get(&graph_name)
                 .and_then(|pos| pos.get(&predicate))
                 .and_then(|os| os.get(&object)),
         )
         .map(|s| EncodedQuad::new(s, predicate, object, graph_name))
+        .take(limit)
         .collect()
     }
 
@@ -580,199 +1789,106 @@ impl MemoryStore {
         &self,
         object: EncodedTerm,
         graph_name: EncodedTerm,
+        limit: usize,
     ) -> Vec<EncodedQuad> {
-        option_pair_map_flatten(
-            self.indexes()
-                .gosp
-                .get(&graph_name)
-                .and_then(|osp| osp.get(&object)),
-        )
-        .map(|(s, p)| EncodedQuad::new(s, p, object, graph_name))
-        .collect()
-    }
-}
-
-impl StrLookup for MemoryStore {
-    fn get_str(&self, id: StrHash) -> Result<Option<String>> {
-        //TODO: avoid copy by adding a lifetime limit to get_str
-        self.indexes().get_str(id)
-    }
-}
-
-impl StrLookup for MemoryStoreIndexes {
-    fn get_str(&self, id: StrHash) -> Result<Option<String>> {
-        //TODO: avoid copy by adding a lifetime limit to get_str
-        Ok(self.id2str.get(&id).cloned())
-    }
-}
-
-impl StrContainer for MemoryStore {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
-        self.indexes_mut().insert_str(key, value)
+        option_pair_map_flatten(self.gosp.get(&graph_name).and_then(|osp| osp.get(&object)))
+            .map(|(s, p)| EncodedQuad::new(s, p, object, graph_name))
+            .take(limit)
+            .collect()
     }
 }
 
-impl<'a> StrContainer for &'a MemoryStore {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
-        self.indexes_mut().insert_str(key, value)
+impl WritableEncodedStore for MemoryStoreWriteGuard<'_> {
+    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        let store = self.store;
+        if self.shard(quad.subject).insert(quad, store)? {
+            if let Some(events) = &mut self.events {
+                events.push(EncodedQuadEvent::Inserted(*quad));
+            }
+        }
+        Ok(())
     }
-}
 
-impl StrContainer for MemoryStoreIndexes {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
-        self.id2str.entry(key).or_insert_with(|| value.to_owned());
+    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        let store = self.store;
+        if self.shard(quad.subject).remove(quad, store)? {
+            if let Some(events) = &mut self.events {
+                events.push(EncodedQuadEvent::Removed(*quad));
+            }
+        }
         Ok(())
     }
-}
 
-impl<'a> ReadableEncodedStore for MemoryStore {
-    fn encoded_quads_for_pattern<'b>(
-        &'b self,
-        subject: Option<EncodedTerm>,
-        predicate: Option<EncodedTerm>,
-        object: Option<EncodedTerm>,
-        graph_name: Option<EncodedTerm>,
-    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'b> {
-        Box::new(
-            self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name)
-                .into_iter()
-                .map(Ok),
-        )
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        // The named graphs without quads are kept in the shard of their name
+        self.shard(graph_name).graphs.insert(graph_name);
+        Ok(())
     }
-}
 
-impl WritableEncodedStore for MemoryStore {
-    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        self.indexes_mut().insert_encoded(quad)
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        let quads: Vec<_> = self
+            .shards
+            .iter()
+            .flat_map(|(_, shard)| shard.encoded_quads_for_graph(graph_name, usize::MAX))
+            .collect();
+        for quad in quads {
+            self.remove_encoded(&quad)?;
+        }
+        Ok(())
     }
 
-    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        self.indexes_mut().remove_encoded(quad)
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.clear_encoded_graph(graph_name)?;
+        for (_, shard) in &mut self.shards {
+            // The shards not containing the graph are not copied if they are shared with a fork
+            if shard.graphs.contains(&graph_name) {
+                Arc::make_mut(&mut **shard).graphs.remove(&graph_name);
+            }
+        }
+        Ok(())
     }
 }
 
-impl<'a> WritableEncodedStore for &'a MemoryStore {
-    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        self.indexes_mut().insert_encoded(quad)
-    }
-
-    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        self.indexes_mut().remove_encoded(quad)
-    }
+/// Returns the position of the shard storing the quads with a given subject or the string with a given key
+pub(crate) fn shard_position(value: impl Hash) -> usize {
+    (hash_tuple(value) % SHARD_COUNT as u64) as usize
 }
 
-impl WritableEncodedStore for MemoryStoreIndexes {
-    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        insert_into_quad_map(
-            &mut self.gosp,
-            quad.graph_name,
-            quad.object,
-            quad.subject,
-            quad.predicate,
-        );
-        insert_into_quad_map(
-            &mut self.gpos,
-            quad.graph_name,
-            quad.predicate,
-            quad.object,
-            quad.subject,
-        );
-        insert_into_quad_map(
-            &mut self.gspo,
-            quad.graph_name,
-            quad.subject,
-            quad.predicate,
-            quad.object,
-        );
-        insert_into_quad_map(
-            &mut self.ospg,
-            quad.object,
-            quad.subject,
-            quad.predicate,
-            quad.graph_name,
-        );
-        insert_into_quad_map(
-            &mut self.posg,
-            quad.predicate,
-            quad.object,
-            quad.subject,
-            quad.graph_name,
-        );
-        insert_into_quad_map(
-            &mut self.spog,
-            quad.subject,
-            quad.predicate,
-            quad.object,
-            quad.graph_name,
-        );
-        Ok(())
-    }
-
-    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        remove_from_quad_map(
-            &mut self.gosp,
-            &quad.graph_name,
-            &quad.object,
-            &quad.subject,
-            &quad.predicate,
-        );
-        remove_from_quad_map(
-            &mut self.gpos,
-            &quad.graph_name,
-            &quad.predicate,
-            &quad.object,
-            &quad.subject,
-        );
-        remove_from_quad_map(
-            &mut self.gspo,
-            &quad.graph_name,
-            &quad.subject,
-            &quad.predicate,
-            &quad.object,
-        );
-        remove_from_quad_map(
-            &mut self.ospg,
-            &quad.object,
-            &quad.subject,
-            &quad.predicate,
-            &quad.graph_name,
-        );
-        remove_from_quad_map(
-            &mut self.posg,
-            &quad.predicate,
-            &quad.object,
-            &quad.subject,
-            &quad.graph_name,
-        );
-        remove_from_quad_map(
-            &mut self.spog,
-            &quad.subject,
-            &quad.predicate,
-            &quad.object,
-            &quad.graph_name,
-        );
-        Ok(())
-    }
+fn read_shard(
+    shard: &RwLock<Arc<MemoryStoreIndexes>>,
+) -> RwLockReadGuard<'_, Arc<MemoryStoreIndexes>> {
+    shard
+        .read()
+        .expect("the Memory store mutex has been poisoned because of a panic")
 }
 
-fn insert_into_quad_map<T: Eq + Hash>(map: &mut QuadMap<T>, e1: T, e2: T, e3: T, e4: T) {
+/// Returns if the element was not already in the map
+fn insert_into_quad_map<T: Eq + Hash>(map: &mut QuadMap<T>, e1: T, e2: T, e3: T, e4: T) -> bool {
     map.entry(e1)
         .or_default()
         .entry(e2)
         .or_default()
         .entry(e3)
         .or_default()
-        .insert(e4);
+        .insert(e4)
 }
 
-fn remove_from_quad_map<T: Eq + Hash>(map1: &mut QuadMap<T>, e1: &T, e2: &T, e3: &T, e4: &T) {
+/// Returns if the element was in the map
+fn remove_from_quad_map<T: Eq + Hash>(
+    map1: &mut QuadMap<T>,
+    e1: &T,
+    e2: &T,
+    e3: &T,
+    e4: &T,
+) -> bool {
+    let mut removed = false;
     let mut map2empty = false;
     if let Some(map2) = map1.get_mut(e1) {
         let mut map3empty = false;
         if let Some(map3) = map2.get_mut(e2) {
             let mut set4empty = false;
             if let Some(set4) = map3.get_mut(e3) {
-                set4.remove(e4);
+                removed = set4.remove(e4);
                 set4empty = set4.is_empty();
             }
             if set4empty {
@@ -788,6 +1904,7 @@ fn remove_from_quad_map<T: Eq + Hash>(map1: &mut QuadMap<T>, e1: &T, e2: &T, e3:
     if map2empty {
         map1.remove(e1);
     }
+    removed
 }
 
 fn option_set_flatten<'a, T: Clone>(
@@ -842,18 +1959,115 @@ impl MemoryPreparedQuery {
     pub fn exec(&self) -> Result<QueryResult<'_>> {
         self.0.exec()
     }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    ///
+    /// It allows to execute the same query with different parameters without parsing it again.
+    /// The evaluation fails if one of the variables is not used in the query.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{MemoryStore, Result};
+    /// use oxigraph::sparql::{QueryOptions, QueryResult, QuerySolution, Variable};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(Quad::new(ex.clone(), ex.clone(), Literal::from("foo"), None));
+    ///
+    /// let prepared_query = store.prepare_query("SELECT ?o WHERE { ?s ?p ?o }", QueryOptions::default())?;
+    /// let bindings: QuerySolution = vec![(Variable::new("s"), ex.into())].into_iter().collect();
+    /// if let QueryResult::Solutions(mut solutions) = prepared_query.exec_with_bindings(&bindings)? {
+    ///     assert_eq!(solutions.next().unwrap()?.get("o"), Some(&Literal::from("foo").into()));
+    /// }
+    /// # Result::Ok(())
+    /// ```
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) evaluated against a `MemoryStore` during a transaction.
+pub struct MemoryTransactionPreparedQuery<'a>(
+    SimplePreparedQuery<PendingChangesView<'a, MemoryStore>>,
+);
+
+impl MemoryTransactionPreparedQuery<'_> {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// A prepared [SPARQL update](https://www.w3.org/TR/sparql11-update/) for the `MemoryStore`.
+pub struct MemoryPreparedUpdate {
+    store: MemoryStore,
+    update: SimplePreparedUpdate,
+}
+
+impl MemoryPreparedUpdate {
+    /// Executes the update
+    ///
+    /// All the operations of the update are executed in the same transaction:
+    /// the changes are only written if all of them succeed.
+    pub fn exec(&self) -> Result<()> {
+        self.store.transaction(|transaction| {
+            for operation in self.update.operations() {
+                // The operations see the changes of the previous ones but not their own changes:
+                // the transaction records the changes of the operation apart from the previous ones
+                let previous = take(&mut transaction.changes);
+                self.update.exec_operation(
+                    operation,
+                    PendingChangesView::new(self.store.clone(), &previous),
+                    transaction,
+                )?;
+                let changes = replace(&mut transaction.changes, previous);
+                transaction.changes.extend(changes);
+            }
+            Ok(())
+        })
+    }
 }
 
 /// Allows to insert and delete quads during a transaction with the `MemoryStore`.
 pub struct MemoryTransaction<'a> {
     store: &'a MemoryStore,
     ops: Vec<TransactionOp>,
-    strings: Vec<(StrHash, String)>,
+    /// The changes done by the operations, in order for the queries of the transaction to see them
+    changes: PendingChanges,
 }
 
 enum TransactionOp {
     Insert(EncodedQuad),
     Delete(EncodedQuad),
+    InsertNamedGraph(EncodedTerm),
+    ClearGraph(EncodedTerm),
+    DeleteNamedGraph(EncodedTerm),
 }
 
 impl<'a> MemoryTransaction<'a> {
@@ -869,7 +2083,7 @@ impl<'a> MemoryTransaction<'a> {
     /// // insertion
     /// let file = b"<http://example.com> <http://example.com> <http://example.com> .";
     /// store.transaction(|transaction| {
-    ///     store.load_graph(file.as_ref(), GraphSyntax::NTriples, &GraphName::DefaultGraph, None)
+    ///     transaction.load_graph(file.as_ref(), GraphSyntax::NTriples, &GraphName::DefaultGraph, None)
     /// })?;
     ///
     /// // quad filter
@@ -899,7 +2113,9 @@ impl<'a> MemoryTransaction<'a> {
     ///
     /// // insertion
     /// let file = b"<http://example.com> <http://example.com> <http://example.com> <http://example.com> .";
-    /// store.load_dataset(file.as_ref(), DatasetSyntax::NQuads, None);
+    /// store.transaction(|transaction| {
+    ///     transaction.load_dataset(file.as_ref(), DatasetSyntax::NQuads, None)
+    /// })?;
     ///
     /// // quad filter
     /// let results: Vec<Quad> = store.quads_for_pattern(None, None, None, None).collect();
@@ -929,13 +2145,57 @@ impl<'a> MemoryTransaction<'a> {
         self.remove_encoded(&quad).unwrap(); // Could never fail
     }
 
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) evaluated against the store with the changes of the transaction done so far.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{MemoryStore, Result};
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    ///
+    /// let store = MemoryStore::new();
+    /// let ex = NamedNode::new("http://example.com")?;
+    ///
+    /// store.transaction(|transaction| {
+    ///     transaction.insert(Quad::new(ex.clone(), ex.clone(), ex.clone(), None));
+    ///
+    ///     // The query sees the quad inserted by the transaction
+    ///     let prepared_query = transaction.prepare_query("ASK { ?s ?p ?o }", QueryOptions::default())?;
+    ///     if let QueryResult::Boolean(result) = prepared_query.exec()? {
+    ///         assert!(result);
+    ///     }
+    ///     Ok(())
+    /// })?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<MemoryTransactionPreparedQuery<'_>> {
+        Ok(MemoryTransactionPreparedQuery(SimplePreparedQuery::new(
+            PendingChangesView::new(self.store.clone(), &self.changes),
+            query,
+            options,
+        )?))
+    }
+
     fn commit(self) -> Result<()> {
-        let mut indexes = self.store.indexes_mut();
-        indexes.id2str.extend(self.strings);
+        for (key, value) in self.changes.strings() {
+            self.store.insert_id2str(key, value)?;
+        }
+        let mut shards = self.store.write_all_shards();
         for op in self.ops {
             match op {
-                TransactionOp::Insert(quad) => indexes.insert_encoded(&quad)?,
-                TransactionOp::Delete(quad) => indexes.remove_encoded(&quad)?,
+                TransactionOp::Insert(quad) => shards.insert_encoded(&quad)?,
+                TransactionOp::Delete(quad) => shards.remove_encoded(&quad)?,
+                TransactionOp::InsertNamedGraph(graph_name) => {
+                    shards.insert_encoded_named_graph(graph_name)?
+                }
+                TransactionOp::ClearGraph(graph_name) => shards.clear_encoded_graph(graph_name)?,
+                TransactionOp::DeleteNamedGraph(graph_name) => {
+                    shards.remove_encoded_named_graph(graph_name)?
+                }
             }
         }
         Ok(())
@@ -943,8 +2203,8 @@ impl<'a> MemoryTransaction<'a> {
 }
 
 impl StrContainer for MemoryTransaction<'_> {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
-        self.strings.push((key, value.to_owned()));
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.changes.insert_str(key, value);
         Ok(())
     }
 }
@@ -952,18 +2212,41 @@ impl StrContainer for MemoryTransaction<'_> {
 impl WritableEncodedStore for MemoryTransaction<'_> {
     fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
         self.ops.push(TransactionOp::Insert(*quad));
+        self.changes.insert(quad);
         Ok(())
     }
 
     fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
         self.ops.push(TransactionOp::Delete(*quad));
+        self.changes.remove(quad);
+        Ok(())
+    }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.ops.push(TransactionOp::InsertNamedGraph(graph_name));
+        self.changes.insert_named_graph(graph_name);
+        Ok(())
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.ops.push(TransactionOp::ClearGraph(graph_name));
+        self.changes.clear_graph(graph_name);
+        Ok(())
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.ops.push(TransactionOp::DeleteNamedGraph(graph_name));
+        self.changes.remove_named_graph(graph_name);
         Ok(())
     }
 }
 
 impl PartialEq for MemoryStore {
     fn eq(&self, other: &Self) -> bool {
-        self.indexes().spog == other.indexes().spog
+        self.shards
+            .iter()
+            .zip(other.shards.iter())
+            .all(|(a, b)| read_shard(a).spog == read_shard(b).spog)
     }
 }
 
@@ -979,9 +2262,7 @@ impl FromIterator<Quad> for MemoryStore {
 
 impl Extend<Quad> for MemoryStore {
     fn extend<T: IntoIterator<Item = Quad>>(&mut self, iter: T) {
-        for quad in iter {
-            self.insert(quad);
-        }
+        self.insert_all(iter)
     }
 }
 
@@ -1052,7 +2333,7 @@ fn hash_bnodes(
         let mut new_hashes =
             TrivialHashMap::with_hasher(BuildHasherDefault::<TrivialHasher>::default());
         for (bnode, old_hash) in &hashes {
-            for q in g.encoded_quads_for_subject(*bnode) {
+            for q in g.encoded_quads_for_pattern_inner(Some(*bnode), None, None, None, usize::MAX) {
                 to_hash.push((
                     hash_term(q.predicate, &hashes),
                     hash_term(q.object, &hashes),
@@ -1060,7 +2341,7 @@ fn hash_bnodes(
                     0,
                 ));
             }
-            for q in g.encoded_quads_for_object(*bnode) {
+            for q in g.encoded_quads_for_pattern_inner(None, None, Some(*bnode), None, usize::MAX) {
                 to_hash.push((
                     hash_term(q.subject, &hashes),
                     hash_term(q.predicate, &hashes),
@@ -1068,7 +2349,7 @@ fn hash_bnodes(
                     1,
                 ));
             }
-            for q in g.encoded_quads_for_graph(*bnode) {
+            for q in g.encoded_quads_for_pattern_inner(None, None, None, Some(*bnode), usize::MAX) {
                 to_hash.push((
                     hash_term(q.subject, &hashes),
                     hash_term(q.predicate, &hashes),
@@ -1095,7 +2376,7 @@ fn hash_bnodes(
 
 fn bnodes(g: &MemoryStore) -> TrivialHashSet<EncodedTerm> {
     let mut bnodes = TrivialHashSet::with_hasher(BuildHasherDefault::<TrivialHasher>::default());
-    for q in g.encoded_quads() {
+    for q in g.encoded_quads_for_pattern_inner(None, None, None, None, usize::MAX) {
         if q.subject.is_blank_node() {
             bnodes.insert(q.subject);
         }
@@ -1112,7 +2393,7 @@ fn bnodes(g: &MemoryStore) -> TrivialHashSet<EncodedTerm> {
 fn label(g: &MemoryStore, hashes: &TrivialHashMap<EncodedTerm, u64>) -> Vec<Vec<u8>> {
     //TODO: better representation?
     let mut data: Vec<_> = g
-        .encoded_quads()
+        .encoded_quads_for_pattern_inner(None, None, None, None, usize::MAX)
         .into_iter()
         .map(|q| {
             let mut buffer = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE * 4);
@@ -1157,7 +2438,7 @@ fn hash_tuple(v: impl Hash) -> u64 {
 }
 
 #[derive(Default)]
-struct TrivialHasher {
+pub(crate) struct TrivialHasher {
     value: u64,
 }
 
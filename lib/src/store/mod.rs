@@ -3,14 +3,33 @@
 //! They encode a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset)
 //! and allow querying and updating them using SPARQL.
 
+#[cfg(feature = "tokio")]
+mod asynchronous;
+#[cfg(feature = "rocksdb")]
+mod commit_log;
+mod dynamic;
+pub mod frozen;
+pub mod hdt;
+pub mod hybrid;
 pub mod memory;
 pub(crate) mod numeric_encoder;
+#[cfg(not(target_arch = "wasm32"))]
+mod parallel_loader;
+mod pending;
 #[cfg(feature = "rocksdb")]
 pub mod rocksdb;
 #[cfg(feature = "sled")]
 pub mod sled;
+mod turtle_star;
 
+use crate::sparql::geo::{self, Envelope, Geometry};
 use crate::sparql::GraphPattern;
+#[cfg(feature = "tokio")]
+pub use crate::store::asynchronous::AsyncStore;
+pub use crate::store::dynamic::{PreparedQuery, PreparedUpdate, Store};
+pub use crate::store::frozen::FrozenStore;
+pub use crate::store::hdt::HdtStore;
+pub use crate::store::hybrid::HybridStore;
 pub use crate::store::memory::MemoryStore;
 #[cfg(feature = "rocksdb")]
 pub use crate::store::rocksdb::RocksDbStore;
@@ -19,15 +38,254 @@ pub use crate::store::sled::SledStore;
 
 use crate::model::*;
 use crate::store::numeric_encoder::*;
+use crate::store::turtle_star::parse_turtle_star;
 use crate::{DatasetSyntax, Error, GraphSyntax, Result};
+use rio_api::formatter::{QuadsFormatter, TriplesFormatter};
+use rio_api::model as rio;
 use rio_api::parser::{QuadsParser, TriplesParser};
-use rio_turtle::{NQuadsParser, NTriplesParser, TriGParser, TurtleParser};
-use rio_xml::RdfXmlParser;
-use std::collections::HashMap;
-use std::io::BufRead;
-use std::iter::Iterator;
+use rio_turtle::{NTriplesParser, TriGFormatter, TriGParser, TurtleFormatter, TurtleParser};
+use rio_xml::{RdfXmlFormatter, RdfXmlParser};
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::iter::{empty, Iterator};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Mutex, MutexGuard};
+
+/// An error raised by the storage backend of a store.
+///
+/// It is wrapped into the `Error` returned by the stores and could be retrieved using `Error::source`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum StorageError {
+    /// The database is already opened by another process or another store of this process
+    AlreadyLocked { path: PathBuf },
+    /// The database has been written with an older on-disk format and should be upgraded with the `migrate` function of the store
+    ///
+    /// The stores without `migrate` function should be dumped with the older version of Oxigraph and reloaded.
+    OutdatedFormat {
+        path: PathBuf,
+        version: u64,
+        latest: u64,
+    },
+    /// The database has been written by a newer version of Oxigraph with an on-disk format this version does not support
+    UnsupportedFormat {
+        path: PathBuf,
+        version: u64,
+        latest: u64,
+    },
+    /// A quad or a named graph written by the transaction has been changed by another write since the transaction beginning
+    ///
+    /// The transaction has not been committed and could be retried.
+    TransactionConflict,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::AlreadyLocked { path } => write!(
+                f,
+                "The database {} is already opened by another process or store",
+                path.display()
+            ),
+            StorageError::OutdatedFormat {
+                path,
+                version,
+                latest,
+            } => write!(
+                f,
+                "The database {} uses the on-disk format version {} and should be migrated to the version {}",
+                path.display(),
+                version,
+                latest
+            ),
+            StorageError::UnsupportedFormat {
+                path,
+                version,
+                latest,
+            } => write!(
+                f,
+                "The database {} uses the on-disk format version {} but only the versions up to {} are supported",
+                path.display(),
+                version,
+                latest
+            ),
+            StorageError::TransactionConflict => write!(
+                f,
+                "The transaction has been aborted because it conflicts with a concurrent write"
+            ),
+        }
+    }
+}
+
+impl error::Error for StorageError {}
+
+/// A change of the quads of a store, as reported by the `watch` method of the stores.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum QuadEvent {
+    /// The quad has been added to the store
+    Inserted(Quad),
+    /// The quad has been removed from the store
+    Removed(Quad),
+}
+
+/// The inconsistencies between the indexes and the string store found by the `validate` method of the stores.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct ValidationReport {
+    /// The number of quads found in some of the indexes but not in all of them
+    pub incomplete_quads: usize,
+    /// The number of quads using strings missing from the string store
+    pub dangling_quads: usize,
+    /// The number of graph names of the quads missing from the named graphs index
+    pub missing_named_graphs: usize,
+}
+
+impl ValidationReport {
+    /// Checks if no inconsistency has been found
+    pub fn is_valid(&self) -> bool {
+        self.incomplete_quads == 0 && self.dangling_quads == 0 && self.missing_named_graphs == 0
+    }
+}
+
+/// Statistics about the content of a store returned by the `stats` method of the stores.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct StoreStats {
+    /// The number of quads
+    pub quads_count: usize,
+    /// The number of distinct subjects of the quads
+    pub distinct_subjects_count: usize,
+    /// The number of distinct predicates of the quads
+    pub distinct_predicates_count: usize,
+    /// The number of distinct objects of the quads
+    pub distinct_objects_count: usize,
+    /// The number of named graphs, including the ones without quads
+    pub named_graphs_count: usize,
+    /// The number of strings stored in the dictionary of the store, the short strings inlined in the terms are not counted
+    pub strings_count: usize,
+}
+
+/// Computes the quad statistics by reading all the quads
+///
+/// The distinct terms are collected in memory.
+pub(crate) fn scan_store_stats(
+    quads: impl IntoIterator<Item = Result<EncodedQuad>>,
+    named_graphs_count: usize,
+    strings_count: usize,
+) -> Result<StoreStats> {
+    let mut quads_count = 0;
+    let mut subjects = HashSet::new();
+    let mut predicates = HashSet::new();
+    let mut objects = HashSet::new();
+    for quad in quads {
+        let quad = quad?;
+        quads_count += 1;
+        subjects.insert(quad.subject);
+        predicates.insert(quad.predicate);
+        objects.insert(quad.object);
+    }
+    Ok(StoreStats {
+        quads_count,
+        distinct_subjects_count: subjects.len(),
+        distinct_predicates_count: predicates.len(),
+        distinct_objects_count: objects.len(),
+        named_graphs_count,
+        strings_count,
+    })
+}
+
+/// Checks if all the strings of a quad are in the string store
+///
+/// `stored` contains the ids of the strings already found, in order to look them up only once.
+pub(crate) fn has_stored_strings(
+    store: &impl StrLookup,
+    quad: &EncodedQuad,
+    stored: &mut HashSet<StrHash>,
+) -> Result<bool> {
+    for term in &[quad.subject, quad.predicate, quad.object, quad.graph_name] {
+        let (first, second) = term.str_ids();
+        for id in first.into_iter().chain(second) {
+            if id.is_inline() || stored.contains(&id) {
+                continue;
+            }
+            if store.get_stored_str(id)?.is_none() {
+                return Ok(false);
+            }
+            stored.insert(id);
+        }
+    }
+    Ok(true)
+}
 
-pub(crate) trait ReadableEncodedStore: StrLookup {
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub(crate) enum EncodedQuadEvent {
+    Inserted(EncodedQuad),
+    Removed(EncodedQuad),
+}
+
+/// The receivers of the changes of a store
+#[derive(Default)]
+pub(crate) struct Watchers {
+    senders: Mutex<Vec<Sender<Vec<QuadEvent>>>>,
+}
+
+impl Watchers {
+    pub fn watch(&self) -> Receiver<Vec<QuadEvent>> {
+        let (sender, receiver) = channel();
+        self.senders().push(sender);
+        receiver
+    }
+
+    /// Checks if there is a receiver for the changes, in order to not record them if there is none
+    pub fn is_watched(&self) -> bool {
+        !self.senders().is_empty()
+    }
+
+    /// Decodes the changes of an atomic write and sends them to the receivers
+    ///
+    /// The senders of the dropped receivers are removed.
+    pub fn notify(&self, lookup: &impl StrLookup, events: &[EncodedQuadEvent]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let mut senders = self.senders();
+        if senders.is_empty() {
+            return Ok(());
+        }
+        let events = events
+            .iter()
+            .map(|event| {
+                Ok(match event {
+                    EncodedQuadEvent::Inserted(quad) => {
+                        QuadEvent::Inserted(lookup.decode_quad(quad)?)
+                    }
+                    EncodedQuadEvent::Removed(quad) => {
+                        QuadEvent::Removed(lookup.decode_quad(quad)?)
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        senders.retain(|sender| sender.send(events.clone()).is_ok());
+        Ok(())
+    }
+
+    fn senders(&self) -> MutexGuard<'_, Vec<Sender<Vec<QuadEvent>>>> {
+        self.senders
+            .lock()
+            .expect("the watchers mutex has been poisoned because of a panic")
+    }
+}
+
+pub(crate) trait ReadableEncodedStore: StrLookup + StoreStatistics {
+    /// Returns the quads matching the pattern
+    ///
+    /// If the graph name is not given, the quads with the same triple must be next to each other:
+    /// the union of all the graphs is built by comparing each triple with the previous one.
     fn encoded_quads_for_pattern<'a>(
         &'a self,
         subject: Option<EncodedTerm>,
@@ -35,15 +293,354 @@ pub(crate) trait ReadableEncodedStore: StrLookup {
         object: Option<EncodedTerm>,
         graph_name: Option<EncodedTerm>,
     ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a>;
+
+    /// Returns at most `limit` quads matching the pattern
+    ///
+    /// The default implementation stops the iteration of `encoded_quads_for_pattern` after `limit` quads.
+    fn encoded_quads_for_pattern_with_limit<'a>(
+        &'a self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+        limit: usize,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
+        Box::new(
+            self.encoded_quads_for_pattern(subject, predicate, object, graph_name)
+                .take(limit),
+        )
+    }
+
+    /// Returns the names of the named graphs of the store, including the ones without quads
+    fn encoded_named_graphs<'a>(&'a self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a>;
+
+    /// Checks if the store contains a named graph, even if it does not contain any quad
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool>;
+
+    /// Returns the string literals of the store that contain all the words of `query`
+    ///
+    /// The default implementation scans all the quads of the store.
+    fn encoded_literals_matching<'a>(
+        &'a self,
+        query: &str,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        scan_literals_matching(self, query)
+    }
+
+    /// Returns the WKT literals of the store whose bounding box is within `envelope` or, if `within` is false, intersects it
+    ///
+    /// The default implementation scans all the quads of the store.
+    fn encoded_geometries_in<'a>(
+        &'a self,
+        envelope: Envelope,
+        within: bool,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        let mut already_seen = HashSet::new();
+        Box::new(
+            self.encoded_quads_for_pattern(None, None, None, None)
+                .filter_map(move |quad| {
+                    let literal = match quad {
+                        Ok(quad) => quad.object,
+                        Err(error) => return Some(Err(error)),
+                    };
+                    if !already_seen.insert(literal) {
+                        return None;
+                    }
+                    match literal_envelope(self, literal) {
+                        Ok(Some(literal_envelope))
+                            if if within {
+                                envelope.contains(&literal_envelope)
+                            } else {
+                                envelope.intersects(&literal_envelope)
+                            } =>
+                        {
+                            Some(Ok(literal))
+                        }
+                        Ok(_) => None,
+                        Err(error) => Some(Err(error)),
+                    }
+                }),
+        )
+    }
+}
+
+/// Returns the string literals of a store that contain all the words of `query` by scanning all its quads
+pub(crate) fn scan_literals_matching<'a>(
+    store: &'a (impl ReadableEncodedStore + ?Sized),
+    query: &str,
+) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+    let words: HashSet<String> = text_words(query).collect();
+    if words.is_empty() {
+        return Box::new(empty());
+    }
+    let mut already_seen = HashSet::new();
+    Box::new(
+        store
+            .encoded_quads_for_pattern(None, None, None, None)
+            .filter_map(move |quad| {
+                let literal = match quad {
+                    Ok(quad) => quad.object,
+                    Err(error) => return Some(Err(error)),
+                };
+                if !already_seen.insert(literal) {
+                    return None;
+                }
+                match literal_words(store, literal) {
+                    Ok(Some(literal_words)) if words.is_subset(&literal_words) => Some(Ok(literal)),
+                    Ok(_) => None,
+                    Err(error) => Some(Err(error)),
+                }
+            }),
+    )
+}
+
+/// Splits a text into lowercase words for the full-text search
+pub(crate) fn text_words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Checks if a term is a string literal, i.e. a literal indexed by the full-text search
+#[cfg(feature = "full-text")]
+pub(crate) fn is_string_literal(term: EncodedTerm) -> bool {
+    matches!(
+        term,
+        EncodedTerm::StringLiteral { .. } | EncodedTerm::LangStringLiteral { .. }
+    )
+}
+
+/// Returns the words of a string literal or `None` if the term is not a string literal
+pub(crate) fn literal_words(
+    store: &(impl StrLookup + ?Sized),
+    literal: EncodedTerm,
+) -> Result<Option<HashSet<String>>> {
+    let value_id = match literal {
+        EncodedTerm::StringLiteral { value_id }
+        | EncodedTerm::LangStringLiteral { value_id, .. } => value_id,
+        _ => return Ok(None),
+    };
+    Ok(store
+        .get_str(value_id)?
+        .map(|value| text_words(&value).collect()))
+}
+
+/// Returns the bounding box of a WKT literal or `None` if the term is not a valid WKT literal
+pub(crate) fn literal_envelope(
+    store: &(impl StrLookup + ?Sized),
+    literal: EncodedTerm,
+) -> Result<Option<Envelope>> {
+    let value_id = match literal {
+        EncodedTerm::TypedLiteral {
+            value_id,
+            datatype_id,
+        } if datatype_id == StrHash::new(geo::WKT_LITERAL) => value_id,
+        _ => return Ok(None),
+    };
+    Ok(store
+        .get_str(value_id)?
+        .and_then(|value| value.parse::<Geometry>().ok())
+        .map(|geometry| geometry.envelope()))
+}
+
+/// Statistics about the store content used by the query planner to estimate the selectivity of patterns
+pub(crate) trait StoreStatistics {
+    /// Returns estimated statistics about the quads with the given predicate or `None` if they are not known
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics>;
+
+    /// Returns the estimated number of quads in the given graph or `None` if it is not known
+    fn graph_quads_count(&self, _graph_name: EncodedTerm) -> Option<u64> {
+        None
+    }
+
+    /// Returns an upper bound of the number of string literals containing all the words of `query` or `None` if it is not known
+    fn literals_matching_count(&self, _query: &str) -> Option<u64> {
+        None
+    }
+
+    /// Returns an upper bound of the number of WKT literals returned by `encoded_geometries_in` or `None` if the store has no spatial index
+    ///
+    /// The query planner only reads the geometries from the store if it is known.
+    fn geometries_in_count(&self, _envelope: &Envelope, _within: bool) -> Option<u64> {
+        None
+    }
+}
+
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub(crate) struct PredicateStatistics {
+    /// Number of quads with the predicate
+    pub quads_count: u64,
+    /// Number of distinct subjects of the quads with the predicate
+    pub distinct_subjects_count: u64,
+    /// Number of distinct objects of the quads with the predicate
+    pub distinct_objects_count: u64,
+}
+
+/// Maximal number of quads read by `scan_predicate_statistics`
+const STATISTICS_SCAN_LIMIT: usize = 10_000;
+
+/// Estimates the predicate statistics by reading at most `STATISTICS_SCAN_LIMIT` quads.
+///
+/// It is used by the stores that do not maintain counters: the estimations saturate for very common predicates.
+pub(crate) fn scan_predicate_statistics(
+    store: &impl ReadableEncodedStore,
+    predicate: EncodedTerm,
+) -> Option<PredicateStatistics> {
+    let mut quads_count = 0;
+    let mut subjects = HashSet::new();
+    let mut objects = HashSet::new();
+    for quad in store
+        .encoded_quads_for_pattern(None, Some(predicate), None, None)
+        .take(STATISTICS_SCAN_LIMIT)
+    {
+        let quad = quad.ok()?;
+        subjects.insert(quad.subject);
+        objects.insert(quad.object);
+        quads_count += 1;
+    }
+    Some(PredicateStatistics {
+        quads_count,
+        distinct_subjects_count: subjects.len() as u64,
+        distinct_objects_count: objects.len() as u64,
+    })
 }
 
 pub(crate) trait WritableEncodedStore: StrContainer {
     fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()>;
 
     fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()>;
+
+    /// Adds a named graph to the store, even if it does not contain any quad
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()>;
+
+    /// Removes all the quads of a graph, the graph itself is kept
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()>;
+
+    /// Removes a named graph and all its quads
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()>;
+}
+
+/// Inserts the quads of the graph `from` into the graph `to`, like the SPARQL `ADD` operation
+///
+/// The quads are copied without being decoded. `to` should already be encoded into `write`.
+pub(crate) fn add_encoded_graph<R: ReadableEncodedStore + ?Sized, W: WritableEncodedStore>(
+    read: &R,
+    write: &mut W,
+    from: EncodedTerm,
+    to: EncodedTerm,
+) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    if to != ENCODED_DEFAULT_GRAPH {
+        write.insert_encoded_named_graph(to)?;
+    }
+    for quad in read.encoded_quads_for_pattern(None, None, None, Some(from)) {
+        let quad = quad?;
+        write.insert_encoded(&EncodedQuad::new(
+            quad.subject,
+            quad.predicate,
+            quad.object,
+            to,
+        ))?;
+    }
+    Ok(())
+}
+
+/// Replaces the content of the graph `to` by the one of the graph `from`, like the SPARQL `COPY` operation
+pub(crate) fn copy_encoded_graph<R: ReadableEncodedStore + ?Sized, W: WritableEncodedStore>(
+    read: &R,
+    write: &mut W,
+    from: EncodedTerm,
+    to: EncodedTerm,
+) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    write.clear_encoded_graph(to)?;
+    add_encoded_graph(read, write, from, to)
 }
 
-fn load_graph<S: WritableEncodedStore>(
+/// Moves the content of the graph `from` into the graph `to`, like the SPARQL `MOVE` operation
+///
+/// The graph `from` is removed afterward, or only cleared if it is the default graph.
+pub(crate) fn move_encoded_graph<R: ReadableEncodedStore + ?Sized, W: WritableEncodedStore>(
+    read: &R,
+    write: &mut W,
+    from: EncodedTerm,
+    to: EncodedTerm,
+) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    copy_encoded_graph(read, write, from, to)?;
+    if from == ENCODED_DEFAULT_GRAPH {
+        write.clear_encoded_graph(from)
+    } else {
+        write.remove_encoded_named_graph(from)
+    }
+}
+
+/// A store whose content could be merged into another store with their `merge_from` methods.
+///
+/// All the stores of this crate encode the terms in the same way.
+/// The quads are streamed from one store to the other without being decoded, only the strings they use are copied.
+///
+/// This trait is implemented by the stores of this crate and could not be implemented outside of it.
+pub trait MergeSource: sealed::MergeSource {}
+
+impl<S: sealed::MergeSource> MergeSource for S {}
+
+mod sealed {
+    use crate::store::numeric_encoder::*;
+    use crate::Result;
+
+    pub trait MergeSource: StrLookup {
+        fn encoded_quads_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + '_>;
+
+        fn encoded_named_graphs_to_merge(
+            &self,
+        ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + '_>;
+    }
+}
+
+/// Copies all the quads and named graphs of `from` into `write` with the strings they use
+pub(crate) fn merge_encoded<W: WritableEncodedStore>(
+    from: &impl MergeSource,
+    write: &mut W,
+) -> Result<()> {
+    let mut copied_strings = HashSet::new();
+    for quad in from.encoded_quads_to_merge() {
+        let quad = quad?;
+        for term in &[quad.subject, quad.predicate, quad.object, quad.graph_name] {
+            copy_term_strings(from, write, *term, &mut copied_strings)?;
+        }
+        write.insert_encoded(&quad)?;
+    }
+    for graph_name in from.encoded_named_graphs_to_merge() {
+        let graph_name = graph_name?;
+        copy_term_strings(from, write, graph_name, &mut copied_strings)?;
+        write.insert_encoded_named_graph(graph_name)?;
+    }
+    Ok(())
+}
+
+fn copy_term_strings(
+    from: &impl StrLookup,
+    write: &mut impl StrContainer,
+    term: EncodedTerm,
+    copied_strings: &mut HashSet<StrHash>,
+) -> Result<()> {
+    let (first, second) = term.str_ids();
+    for id in first.into_iter().chain(second) {
+        if !id.is_inline() && copied_strings.insert(id) {
+            write.insert_str(id, &get_required_str(from, id)?)?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn load_graph<S: WritableEncodedStore>(
     store: &mut S,
     reader: impl BufRead,
     syntax: GraphSyntax,
@@ -52,30 +649,110 @@ fn load_graph<S: WritableEncodedStore>(
 ) -> Result<()> {
     let base_iri = base_iri.unwrap_or("");
     match syntax {
-        GraphSyntax::NTriples => {
-            load_from_triple_parser(store, NTriplesParser::new(reader)?, to_graph_name)
+        GraphSyntax::NTriples => load_ntriples(store, reader, to_graph_name),
+        GraphSyntax::Turtle => load_turtle(store, reader, to_graph_name, base_iri),
+        GraphSyntax::RdfXml => {
+            let mut bnode_map = HashMap::default();
+            load_from_triple_parser(
+                store,
+                RdfXmlParser::new(reader, base_iri)?,
+                to_graph_name,
+                &mut bnode_map,
+            )
         }
-        GraphSyntax::Turtle => {
-            load_from_triple_parser(store, TurtleParser::new(reader, base_iri)?, to_graph_name)
+    }
+}
+
+/// The minimal size in bytes of the chunks of lines in which the N-Triples files are loaded
+const NTRIPLES_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Loads a N-Triples file by chunks of lines
+///
+/// The chunks containing quoted triples are parsed as N-Triples-star, the other ones using rio.
+fn load_ntriples<S: WritableEncodedStore>(
+    store: &mut S,
+    mut reader: impl BufRead,
+    to_graph_name: &GraphName,
+) -> Result<()> {
+    let mut bnode_map = HashMap::default();
+    loop {
+        let mut chunk = Vec::with_capacity(NTRIPLES_CHUNK_SIZE + 1024);
+        while chunk.len() < NTRIPLES_CHUNK_SIZE {
+            if reader.read_until(b'\n', &mut chunk)? == 0 {
+                break;
+            }
         }
-        GraphSyntax::RdfXml => {
-            load_from_triple_parser(store, RdfXmlParser::new(reader, base_iri)?, to_graph_name)
+        if chunk.is_empty() {
+            return Ok(());
         }
+        if has_quoted_triple(&chunk) {
+            load_from_turtle_star(store, &chunk, true, "", to_graph_name, &mut bnode_map)?;
+        } else {
+            load_from_triple_parser(
+                store,
+                NTriplesParser::new(chunk.as_slice())?,
+                to_graph_name,
+                &mut bnode_map,
+            )?;
+        }
+    }
+}
+
+/// Loads a Turtle file, parsed as Turtle-star if it contains quoted triples
+fn load_turtle<S: WritableEncodedStore>(
+    store: &mut S,
+    mut reader: impl BufRead,
+    to_graph_name: &GraphName,
+    base_iri: &str,
+) -> Result<()> {
+    let mut bnode_map = HashMap::default();
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    if has_quoted_triple(&data) {
+        load_from_turtle_star(store, &data, false, base_iri, to_graph_name, &mut bnode_map)
+    } else {
+        load_from_triple_parser(
+            store,
+            TurtleParser::new(data.as_slice(), base_iri)?,
+            to_graph_name,
+            &mut bnode_map,
+        )
     }
 }
 
+/// Checks if the data may contain a quoted triple `<< s p o >>`
+fn has_quoted_triple(data: &[u8]) -> bool {
+    data.windows(2).any(|window| window == b"<<")
+}
+
+fn load_from_turtle_star<S: WritableEncodedStore>(
+    store: &mut S,
+    data: &[u8],
+    is_ntriples: bool,
+    base_iri: &str,
+    to_graph_name: &GraphName,
+    bnode_map: &mut HashMap<String, u128>,
+) -> Result<()> {
+    let data = str::from_utf8(data).map_err(Error::wrap)?;
+    let to_graph_name = store.encode_graph_name(to_graph_name)?;
+    parse_turtle_star(data, is_ntriples, base_iri, bnode_map, &mut |triple| {
+        let quad = store.encode_triple_in_graph(&triple, to_graph_name)?;
+        store.insert_encoded(&quad)
+    })
+}
+
 fn load_from_triple_parser<S: WritableEncodedStore, P: TriplesParser>(
     store: &mut S,
     mut parser: P,
     to_graph_name: &GraphName,
+    bnode_map: &mut HashMap<String, u128>,
 ) -> Result<()>
 where
     Error: From<P::Error>,
 {
-    let mut bnode_map = HashMap::default();
     let to_graph_name = store.encode_graph_name(to_graph_name)?;
     parser.parse_all(&mut move |t| {
-        let quad = store.encode_rio_triple_in_graph(t, to_graph_name, &mut bnode_map)?;
+        let quad = store.encode_rio_triple_in_graph(t, to_graph_name, bnode_map)?;
         store.insert_encoded(&quad)
     })
 }
@@ -88,7 +765,12 @@ fn load_dataset<S: WritableEncodedStore>(
 ) -> Result<()> {
     let base_iri = base_iri.unwrap_or("");
     match syntax {
-        DatasetSyntax::NQuads => load_from_quad_parser(store, NQuadsParser::new(reader)?),
+        #[cfg(not(target_arch = "wasm32"))]
+        DatasetSyntax::NQuads => parallel_loader::load_nquads_in_parallel(store, reader),
+        #[cfg(target_arch = "wasm32")]
+        DatasetSyntax::NQuads => {
+            load_from_quad_parser(store, rio_turtle::NQuadsParser::new(reader)?)
+        }
         DatasetSyntax::TriG => load_from_quad_parser(store, TriGParser::new(reader, base_iri)?),
     }
 }
@@ -106,3 +788,139 @@ where
         store.insert_encoded(&quad)
     })
 }
+
+/// Writes the triples one by one while they are read from the store
+///
+/// The quoted triples are written using the N-Triples-star syntax and are not supported by the other formats.
+/// The content of a file opened by a read-only store
+pub(crate) enum FileContent {
+    /// The file has been read into memory
+    Memory(Vec<u8>),
+    /// The file is memory-mapped
+    #[cfg(feature = "memmap")]
+    Mapped(memmap::Mmap),
+}
+
+impl FileContent {
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        Ok(FileContent::Memory(std::fs::read(path)?))
+    }
+
+    /// Maps the file in memory
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while the returned value is alive.
+    #[cfg(feature = "memmap")]
+    #[allow(unsafe_code)]
+    pub(crate) unsafe fn map(path: &Path) -> Result<Self> {
+        Ok(FileContent::Mapped(memmap::Mmap::map(
+            &std::fs::File::open(path)?,
+        )?))
+    }
+}
+
+impl Deref for FileContent {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileContent::Memory(data) => data,
+            #[cfg(feature = "memmap")]
+            FileContent::Mapped(data) => data,
+        }
+    }
+}
+
+pub(crate) fn dump_graph(
+    triples: impl Iterator<Item = Result<Triple>>,
+    mut writer: impl Write,
+    syntax: GraphSyntax,
+) -> Result<()> {
+    match syntax {
+        GraphSyntax::NTriples => {
+            for triple in triples {
+                writeln!(writer, "{} .", triple?)?;
+            }
+        }
+        GraphSyntax::Turtle => {
+            let mut formatter = TurtleFormatter::new(writer);
+            for triple in triples {
+                formatter.format(&rio_triple(&triple?)?)?;
+            }
+            formatter.finish()?;
+        }
+        GraphSyntax::RdfXml => {
+            let mut formatter = RdfXmlFormatter::new(writer)?;
+            for triple in triples {
+                formatter.format(&rio_triple(&triple?)?)?;
+            }
+            formatter.finish()?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the quads one by one while they are read from the store
+///
+/// The quoted triples are written using the N-Triples-star syntax and are not supported by TriG.
+pub(crate) fn dump_dataset(
+    quads: impl Iterator<Item = Result<Quad>>,
+    mut writer: impl Write,
+    syntax: DatasetSyntax,
+) -> Result<()> {
+    match syntax {
+        DatasetSyntax::NQuads => {
+            for quad in quads {
+                writeln!(writer, "{} .", quad?)?;
+            }
+        }
+        DatasetSyntax::TriG => {
+            let mut formatter = TriGFormatter::new(writer);
+            for quad in quads {
+                formatter.format(&rio_quad(&quad?)?)?;
+            }
+            formatter.finish()?;
+        }
+    }
+    Ok(())
+}
+
+/// Converts a triple to the model of the rio formatters, that do not support the quoted triples
+pub(crate) fn rio_triple(triple: &Triple) -> Result<rio::Triple<'_>> {
+    Ok(rio::Triple {
+        subject: rio_subject(&triple.subject)?,
+        predicate: (&triple.predicate).into(),
+        object: rio_term(&triple.object)?,
+    })
+}
+
+fn rio_quad(quad: &Quad) -> Result<rio::Quad<'_>> {
+    Ok(rio::Quad {
+        subject: rio_subject(&quad.subject)?,
+        predicate: (&quad.predicate).into(),
+        object: rio_term(&quad.object)?,
+        graph_name: (&quad.graph_name).into(),
+    })
+}
+
+fn rio_subject(subject: &Subject) -> Result<rio::NamedOrBlankNode<'_>> {
+    match subject {
+        Subject::NamedNode(node) => Ok(rio::NamedNode::from(node).into()),
+        Subject::BlankNode(node) => Ok(rio::BlankNode::from(node).into()),
+        Subject::Triple(_) => Err(quoted_triple_not_supported()),
+    }
+}
+
+fn rio_term(term: &Term) -> Result<rio::Term<'_>> {
+    match term {
+        Term::NamedNode(node) => Ok(rio::NamedNode::from(node).into()),
+        Term::BlankNode(node) => Ok(rio::BlankNode::from(node).into()),
+        Term::Literal(literal) => Ok(rio::Literal::from(literal).into()),
+        Term::Triple(_) => Err(quoted_triple_not_supported()),
+    }
+}
+
+fn quoted_triple_not_supported() -> Error {
+    Error::msg("The quoted triples could only be serialized using N-Triples or N-Quads")
+}
@@ -15,40 +15,93 @@ use std::hash::Hasher;
 use std::io::{Cursor, Read};
 use std::mem::size_of;
 use std::str;
+use std::sync::Arc;
 
-#[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Copy, Clone, Hash)]
-#[repr(transparent)]
+/// The id of a string.
+///
+/// The strings of at most 15 bytes are inlined in their id: a byte with their length followed by their content.
+/// The other strings are identified by their hash and should be stored in a `StrContainer`.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Copy, Clone)]
 pub struct StrHash {
-    hash: u128,
+    bytes: [u8; 16],
+    inline: bool,
 }
 
+pub const MAX_INLINE_STR_LEN: usize = 15;
+
 impl StrHash {
     pub fn new(value: &str) -> Self {
-        let mut hasher = SipHasher24::new();
-        hasher.write(value.as_bytes());
-        Self {
-            hash: hasher.finish128().into(),
+        if value.len() <= MAX_INLINE_STR_LEN {
+            let mut bytes = [0; 16];
+            bytes[0] = value.len() as u8;
+            bytes[1..=value.len()].copy_from_slice(value.as_bytes());
+            Self {
+                bytes,
+                inline: true,
+            }
+        } else {
+            let mut hasher = SipHasher24::new();
+            hasher.write(value.as_bytes());
+            let hash: u128 = hasher.finish128().into();
+            Self::constant(hash)
         }
     }
 
     const fn constant(hash: u128) -> Self {
-        Self { hash }
+        Self {
+            bytes: hash.to_be_bytes(),
+            inline: false,
+        }
     }
 
     #[inline]
     pub fn from_be_bytes(bytes: [u8; 16]) -> Self {
         Self {
-            hash: u128::from_be_bytes(bytes),
+            bytes,
+            inline: false,
+        }
+    }
+
+    #[inline]
+    pub fn from_inline_be_bytes(bytes: [u8; 16]) -> Self {
+        Self {
+            bytes,
+            inline: true,
         }
     }
 
     #[inline]
     pub fn to_be_bytes(&self) -> [u8; 16] {
-        self.hash.to_be_bytes()
+        self.bytes
+    }
+
+    /// Returns if the string is inlined in the id and so is not stored in the string store
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.inline
+    }
+
+    /// Returns the string if it is inlined in the id
+    pub fn inline_str(&self) -> Option<&str> {
+        if self.inline {
+            str::from_utf8(self.bytes.get(1..=usize::from(self.bytes[0]))?).ok()
+        } else {
+            None
+        }
     }
 }
 
-const EMPTY_STRING_ID: StrHash = StrHash::constant(0xf4f2ced447ab02427de0a38047d74950);
+impl Hash for StrHash {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hashes the id as a single integer like the plain hashes used to be
+        state.write_u128(u128::from_ne_bytes(self.bytes))
+    }
+}
+
+const EMPTY_STRING_ID: StrHash = StrHash {
+    bytes: [0; 16],
+    inline: true,
+};
 const RDF_LANG_STRING_ID: StrHash = StrHash::constant(0x8fab6bc1501d6d114e5d4e0116f67a49);
 const XSD_STRING_ID: StrHash = StrHash::constant(0xe72300970ee9bf77f2df7bdb300e3d84);
 const XSD_BOOLEAN_ID: StrHash = StrHash::constant(0xfafac8b356be81954f64e70756e59e32);
@@ -82,6 +135,11 @@ const TYPE_TIME_LITERAL: u8 = 15;
 const TYPE_DURATION_LITERAL: u8 = 16;
 const TYPE_YEAR_MONTH_DURATION_LITERAL: u8 = 17;
 const TYPE_DAY_TIME_DURATION_LITERAL: u8 = 18;
+const TYPE_TRIPLE_ID: u8 = 19;
+
+// Flags set on the type byte if the first or the second string of the term is inlined
+const INLINE_FIRST_STR_FLAG: u8 = 0b0100_0000;
+const INLINE_SECOND_STR_FLAG: u8 = 0b1000_0000;
 
 pub const ENCODED_DEFAULT_GRAPH: EncodedTerm = EncodedTerm::DefaultGraph;
 pub const ENCODED_EMPTY_STRING_LITERAL: EncodedTerm = EncodedTerm::StringLiteral {
@@ -161,6 +219,10 @@ pub enum EncodedTerm {
     DurationLiteral(Duration),
     YearMonthDurationLiteral(YearMonthDuration),
     DayTimeDurationLiteral(DayTimeDuration),
+    /// A quoted triple identified by the hash of the encoding of its subject, predicate and object
+    Triple {
+        id: StrHash,
+    },
 }
 
 impl PartialEq for EncodedTerm {
@@ -235,6 +297,7 @@ impl PartialEq for EncodedTerm {
             (EncodedTerm::DayTimeDurationLiteral(a), EncodedTerm::DayTimeDurationLiteral(b)) => {
                 a == b
             }
+            (EncodedTerm::Triple { id: a }, EncodedTerm::Triple { id: b }) => a == b,
             (_, _) => false,
         }
     }
@@ -275,6 +338,7 @@ impl Hash for EncodedTerm {
             EncodedTerm::DurationLiteral(value) => value.hash(state),
             EncodedTerm::YearMonthDurationLiteral(value) => value.hash(state),
             EncodedTerm::DayTimeDurationLiteral(value) => value.hash(state),
+            EncodedTerm::Triple { id } => id.hash(state),
         }
     }
 }
@@ -361,8 +425,59 @@ impl EncodedTerm {
             EncodedTerm::DurationLiteral(_) => TYPE_DURATION_LITERAL,
             EncodedTerm::YearMonthDurationLiteral(_) => TYPE_YEAR_MONTH_DURATION_LITERAL,
             EncodedTerm::DayTimeDurationLiteral(_) => TYPE_DAY_TIME_DURATION_LITERAL,
+            EncodedTerm::Triple { .. } => TYPE_TRIPLE_ID,
+        }
+    }
+
+    /// Returns the ids of the strings of the term in their serialization order
+    pub fn str_ids(&self) -> (Option<StrHash>, Option<StrHash>) {
+        match *self {
+            EncodedTerm::NamedNode { iri_id } => (Some(iri_id), None),
+            EncodedTerm::NamedBlankNode { id_id } => (Some(id_id), None),
+            EncodedTerm::StringLiteral { value_id } => (Some(value_id), None),
+            EncodedTerm::LangStringLiteral {
+                value_id,
+                language_id,
+            } => (Some(language_id), Some(value_id)),
+            EncodedTerm::TypedLiteral {
+                value_id,
+                datatype_id,
+            } => (Some(datatype_id), Some(value_id)),
+            EncodedTerm::Triple { id } => (Some(id), None),
+            _ => (None, None),
         }
     }
+
+    /// Returns the term with its string ids replaced using `map`
+    pub fn map_str_ids(self, mut map: impl FnMut(StrHash) -> Result<StrHash>) -> Result<Self> {
+        Ok(match self {
+            EncodedTerm::NamedNode { iri_id } => EncodedTerm::NamedNode {
+                iri_id: map(iri_id)?,
+            },
+            EncodedTerm::NamedBlankNode { id_id } => {
+                EncodedTerm::NamedBlankNode { id_id: map(id_id)? }
+            }
+            EncodedTerm::StringLiteral { value_id } => EncodedTerm::StringLiteral {
+                value_id: map(value_id)?,
+            },
+            EncodedTerm::LangStringLiteral {
+                value_id,
+                language_id,
+            } => EncodedTerm::LangStringLiteral {
+                value_id: map(value_id)?,
+                language_id: map(language_id)?,
+            },
+            EncodedTerm::TypedLiteral {
+                value_id,
+                datatype_id,
+            } => EncodedTerm::TypedLiteral {
+                value_id: map(value_id)?,
+                datatype_id: map(datatype_id)?,
+            },
+            EncodedTerm::Triple { id } => EncodedTerm::Triple { id: map(id)? },
+            term => term,
+        })
+    }
 }
 
 impl From<bool> for EncodedTerm {
@@ -551,16 +666,58 @@ impl From<&NamedOrBlankNode> for EncodedTerm {
     }
 }
 
+impl From<&Subject> for EncodedTerm {
+    fn from(node: &Subject) -> Self {
+        match node {
+            Subject::NamedNode(node) => node.into(),
+            Subject::BlankNode(node) => node.into(),
+            Subject::Triple(triple) => triple.as_ref().into(),
+        }
+    }
+}
+
 impl From<&Term> for EncodedTerm {
     fn from(node: &Term) -> Self {
         match node {
             Term::NamedNode(node) => node.into(),
             Term::BlankNode(node) => node.into(),
             Term::Literal(literal) => literal.into(),
+            Term::Triple(triple) => triple.as_ref().into(),
+        }
+    }
+}
+
+impl From<&Triple> for EncodedTerm {
+    fn from(triple: &Triple) -> Self {
+        EncodedTerm::Triple {
+            id: StrHash::new(&encode_triple_str(
+                (&triple.subject).into(),
+                (&triple.predicate).into(),
+                (&triple.object).into(),
+            )),
         }
     }
 }
 
+/// Builds the string identifying a quoted triple in the string store: the hexadecimal serialization of its encoded terms
+fn encode_triple_str(subject: EncodedTerm, predicate: EncodedTerm, object: EncodedTerm) -> String {
+    let mut buffer = Vec::with_capacity(3 * WRITTEN_TERM_MAX_SIZE);
+    write_term(&mut buffer, subject);
+    write_term(&mut buffer, predicate);
+    write_term(&mut buffer, object);
+    hex::encode(buffer)
+}
+
+fn decode_triple_str(value: &str) -> Result<(EncodedTerm, EncodedTerm, EncodedTerm)> {
+    let buffer = hex::decode(value).map_err(Error::wrap)?;
+    let mut cursor = Cursor::new(buffer);
+    Ok((
+        cursor.read_term()?,
+        cursor.read_term()?,
+        cursor.read_term()?,
+    ))
+}
+
 impl From<&GraphName> for EncodedTerm {
     fn from(node: &GraphName) -> Self {
         match node {
@@ -620,15 +777,13 @@ impl<R: Read> TermReader for R {
     fn read_term(&mut self) -> Result<EncodedTerm> {
         let mut type_buffer = [0];
         self.read_exact(&mut type_buffer)?;
-        match type_buffer[0] {
+        let inline_first = type_buffer[0] & INLINE_FIRST_STR_FLAG != 0;
+        let inline_second = type_buffer[0] & INLINE_SECOND_STR_FLAG != 0;
+        match type_buffer[0] & !(INLINE_FIRST_STR_FLAG | INLINE_SECOND_STR_FLAG) {
             TYPE_DEFAULT_GRAPH_ID => Ok(EncodedTerm::DefaultGraph),
-            TYPE_NAMED_NODE_ID => {
-                let mut buffer = [0; 16];
-                self.read_exact(&mut buffer)?;
-                Ok(EncodedTerm::NamedNode {
-                    iri_id: StrHash::from_be_bytes(buffer),
-                })
-            }
+            TYPE_NAMED_NODE_ID => Ok(EncodedTerm::NamedNode {
+                iri_id: read_str_id(self, inline_first)?,
+            }),
             TYPE_INLINE_BLANK_NODE_ID => {
                 let mut buffer = [0; 16];
                 self.read_exact(&mut buffer)?;
@@ -636,40 +791,28 @@ impl<R: Read> TermReader for R {
                     id: u128::from_be_bytes(buffer),
                 })
             }
-            TYPE_NAMED_BLANK_NODE_ID => {
-                let mut buffer = [0; 16];
-                self.read_exact(&mut buffer)?;
-                Ok(EncodedTerm::NamedBlankNode {
-                    id_id: StrHash::from_be_bytes(buffer),
-                })
-            }
+            TYPE_NAMED_BLANK_NODE_ID => Ok(EncodedTerm::NamedBlankNode {
+                id_id: read_str_id(self, inline_first)?,
+            }),
             TYPE_LANG_STRING_LITERAL_ID => {
-                let mut language_buffer = [0; 16];
-                self.read_exact(&mut language_buffer)?;
-                let mut value_buffer = [0; 16];
-                self.read_exact(&mut value_buffer)?;
+                let language_id = read_str_id(self, inline_first)?;
+                let value_id = read_str_id(self, inline_second)?;
                 Ok(EncodedTerm::LangStringLiteral {
-                    language_id: StrHash::from_be_bytes(language_buffer),
-                    value_id: StrHash::from_be_bytes(value_buffer),
+                    language_id,
+                    value_id,
                 })
             }
             TYPE_TYPED_LITERAL_ID => {
-                let mut datatype_buffer = [0; 16];
-                self.read_exact(&mut datatype_buffer)?;
-                let mut value_buffer = [0; 16];
-                self.read_exact(&mut value_buffer)?;
+                let datatype_id = read_str_id(self, inline_first)?;
+                let value_id = read_str_id(self, inline_second)?;
                 Ok(EncodedTerm::TypedLiteral {
-                    datatype_id: StrHash::from_be_bytes(datatype_buffer),
-                    value_id: StrHash::from_be_bytes(value_buffer),
-                })
-            }
-            TYPE_STRING_LITERAL => {
-                let mut buffer = [0; 16];
-                self.read_exact(&mut buffer)?;
-                Ok(EncodedTerm::StringLiteral {
-                    value_id: StrHash::from_be_bytes(buffer),
+                    datatype_id,
+                    value_id,
                 })
             }
+            TYPE_STRING_LITERAL => Ok(EncodedTerm::StringLiteral {
+                value_id: read_str_id(self, inline_first)?,
+            }),
             TYPE_BOOLEAN_LITERAL_TRUE => Ok(EncodedTerm::BooleanLiteral(true)),
             TYPE_BOOLEAN_LITERAL_FALSE => Ok(EncodedTerm::BooleanLiteral(false)),
             TYPE_FLOAT_LITERAL => {
@@ -730,6 +873,9 @@ impl<R: Read> TermReader for R {
                     DayTimeDuration::from_be_bytes(buffer),
                 ))
             }
+            TYPE_TRIPLE_ID => Ok(EncodedTerm::Triple {
+                id: read_str_id(self, inline_first)?,
+            }),
             _ => Err(Error::msg("the term buffer has an invalid type id")),
         }
     }
@@ -813,10 +959,28 @@ impl<R: Read> TermReader for R {
     }
 }
 
-pub const WRITTEN_TERM_MAX_SIZE: usize = size_of::<u8>() + 2 * size_of::<StrHash>();
+fn read_str_id(reader: &mut impl Read, inline: bool) -> Result<StrHash> {
+    let mut buffer = [0; 16];
+    reader.read_exact(&mut buffer)?;
+    Ok(if inline {
+        StrHash::from_inline_be_bytes(buffer)
+    } else {
+        StrHash::from_be_bytes(buffer)
+    })
+}
+
+pub const WRITTEN_TERM_MAX_SIZE: usize = size_of::<u8>() + 2 * 16;
 
 pub fn write_term(sink: &mut Vec<u8>, term: EncodedTerm) {
-    sink.push(term.type_id());
+    let mut type_id = term.type_id();
+    let (first, second) = term.str_ids();
+    if first.map_or(false, |id| id.is_inline()) {
+        type_id |= INLINE_FIRST_STR_FLAG;
+    }
+    if second.map_or(false, |id| id.is_inline()) {
+        type_id |= INLINE_SECOND_STR_FLAG;
+    }
+    sink.push(type_id);
     match term {
         EncodedTerm::DefaultGraph => {}
         EncodedTerm::NamedNode { iri_id } => sink.extend_from_slice(&iri_id.to_be_bytes()),
@@ -850,6 +1014,7 @@ pub fn write_term(sink: &mut Vec<u8>, term: EncodedTerm) {
             sink.extend_from_slice(&value.to_be_bytes())
         }
         EncodedTerm::DayTimeDurationLiteral(value) => sink.extend_from_slice(&value.to_be_bytes()),
+        EncodedTerm::Triple { id } => sink.extend_from_slice(&id.to_be_bytes()),
     }
 }
 
@@ -920,15 +1085,67 @@ impl QuadEncoding {
 }
 
 pub trait StrLookup {
-    fn get_str(&self, id: StrHash) -> Result<Option<String>>;
+    /// Returns a string stored in the string store
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>>;
+
+    /// Returns a string stored in the string store in a reference counted buffer.
+    ///
+    /// Should be overridden by the lookups able to share their buffers with the returned terms.
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        Ok(self.get_stored_str(id)?.map(Arc::from))
+    }
+
+    fn get_str(&self, id: StrHash) -> Result<Option<String>> {
+        if let Some(value) = id.inline_str() {
+            Ok(Some(value.to_owned()))
+        } else {
+            self.get_stored_str(id)
+        }
+    }
+
+    /// Returns a string in a reference counted buffer
+    fn get_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        if let Some(value) = id.inline_str() {
+            Ok(Some(value.into()))
+        } else {
+            self.get_stored_shared_str(id)
+        }
+    }
+}
+
+/// Fails if the string already stored with a hash is not `value`
+///
+/// The hashes are 128 bits long so a collision is very unlikely, but it would silently replace a term by another one.
+/// The stores call it when they get a string with a hash they already contain.
+pub fn check_str_hash_collision(key: StrHash, stored: &[u8], value: &str) -> Result<()> {
+    if stored == value.as_bytes() {
+        Ok(())
+    } else {
+        Err(Error::msg(format!(
+            "The strings '{}' and '{}' have the same hash {:x}",
+            String::from_utf8_lossy(stored),
+            value,
+            u128::from_be_bytes(key.to_be_bytes())
+        )))
+    }
 }
 
 pub trait StrContainer {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()>;
+    /// Stores a string that is not inlined in its id
+    ///
+    /// It should fail if another string with the same id is already stored, see `check_str_hash_collision`.
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()>;
+
+    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if key.is_inline() {
+            Ok(())
+        } else {
+            self.insert_stored_str(key, value)
+        }
+    }
 
     /// Should be called when the bytes store is created
     fn set_first_strings(&mut self) -> Result<()> {
-        self.insert_str(EMPTY_STRING_ID, "")?;
         self.insert_str(RDF_LANG_STRING_ID, rdf::LANG_STRING.as_str())?;
         self.insert_str(XSD_STRING_ID, xsd::STRING.as_str())?;
         self.insert_str(XSD_BOOLEAN_ID, xsd::BOOLEAN.as_str())?;
@@ -950,7 +1167,7 @@ pub trait StrContainer {
 }
 
 pub struct MemoryStrStore {
-    id2str: HashMap<StrHash, String>,
+    id2str: HashMap<StrHash, Arc<str>>,
 }
 
 impl Default for MemoryStrStore {
@@ -964,15 +1181,21 @@ impl Default for MemoryStrStore {
 }
 
 impl StrLookup for MemoryStrStore {
-    fn get_str(&self, id: StrHash) -> Result<Option<String>> {
-        //TODO: avoid copy by adding a lifetime limit to get_str
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        Ok(self.id2str.get(&id).map(|value| value.to_string()))
+    }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
         Ok(self.id2str.get(&id).cloned())
     }
 }
 
 impl StrContainer for MemoryStrStore {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
-        self.id2str.entry(key).or_insert_with(|| value.to_owned());
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if let Some(stored) = self.id2str.get(&key) {
+            return check_str_hash_collision(key, stored.as_bytes(), value);
+        }
+        self.id2str.insert(key, value.into());
         Ok(())
     }
 }
@@ -995,14 +1218,36 @@ pub trait Encoder {
         }
     }
 
+    fn encode_subject(&mut self, term: &Subject) -> Result<EncodedTerm> {
+        match term {
+            Subject::NamedNode(named_node) => self.encode_named_node(named_node),
+            Subject::BlankNode(blank_node) => self.encode_blank_node(blank_node),
+            Subject::Triple(triple) => self.encode_quoted_triple(triple),
+        }
+    }
+
     fn encode_term(&mut self, term: &Term) -> Result<EncodedTerm> {
         match term {
             Term::NamedNode(named_node) => self.encode_named_node(named_node),
             Term::BlankNode(blank_node) => self.encode_blank_node(blank_node),
             Term::Literal(literal) => self.encode_literal(literal),
+            Term::Triple(triple) => self.encode_quoted_triple(triple),
         }
     }
 
+    fn encode_quoted_triple(&mut self, triple: &Triple) -> Result<EncodedTerm> {
+        let encoded = self.encode_triple_in_graph(triple, ENCODED_DEFAULT_GRAPH)?;
+        self.encode_quoted_triple_terms(encoded.subject, encoded.predicate, encoded.object)
+    }
+
+    /// Encodes the quoted triple made of already encoded terms
+    fn encode_quoted_triple_terms(
+        &mut self,
+        subject: EncodedTerm,
+        predicate: EncodedTerm,
+        object: EncodedTerm,
+    ) -> Result<EncodedTerm>;
+
     fn encode_graph_name(&mut self, name: &GraphName) -> Result<EncodedTerm> {
         match name {
             GraphName::NamedNode(named_node) => self.encode_named_node(named_node),
@@ -1013,7 +1258,7 @@ pub trait Encoder {
 
     fn encode_quad(&mut self, quad: &Quad) -> Result<EncodedQuad> {
         Ok(EncodedQuad {
-            subject: self.encode_named_or_blank_node(&quad.subject)?,
+            subject: self.encode_subject(&quad.subject)?,
             predicate: self.encode_named_node(&quad.predicate)?,
             object: self.encode_term(&quad.object)?,
             graph_name: self.encode_graph_name(&quad.graph_name)?,
@@ -1026,7 +1271,7 @@ pub trait Encoder {
         graph_name: EncodedTerm,
     ) -> Result<EncodedQuad> {
         Ok(EncodedQuad {
-            subject: self.encode_named_or_blank_node(&triple.subject)?,
+            subject: self.encode_subject(&triple.subject)?,
             predicate: self.encode_named_node(&triple.predicate)?,
             object: self.encode_term(&triple.object)?,
             graph_name,
@@ -1100,6 +1345,18 @@ pub trait Encoder {
 }
 
 impl<S: StrContainer> Encoder for S {
+    fn encode_quoted_triple_terms(
+        &mut self,
+        subject: EncodedTerm,
+        predicate: EncodedTerm,
+        object: EncodedTerm,
+    ) -> Result<EncodedTerm> {
+        let value = encode_triple_str(subject, predicate, object);
+        let id = StrHash::new(&value);
+        self.insert_str(id, &value)?;
+        Ok(EncodedTerm::Triple { id })
+    }
+
     fn encode_rio_named_node(&mut self, named_node: rio::NamedNode<'_>) -> Result<EncodedTerm> {
         let iri_id = StrHash::new(named_node.iri);
         self.insert_str(iri_id, named_node.iri)?;
@@ -1206,6 +1463,27 @@ impl<S: StrContainer> Encoder for S {
     }
 }
 
+/// Checks if the datatype is xsd:integer or one of its derived datatypes
+pub fn is_integer_datatype_id(datatype_id: StrHash) -> bool {
+    datatype_id == XSD_INTEGER_ID
+        || [
+            "http://www.w3.org/2001/XMLSchema#byte",
+            "http://www.w3.org/2001/XMLSchema#short",
+            "http://www.w3.org/2001/XMLSchema#int",
+            "http://www.w3.org/2001/XMLSchema#long",
+            "http://www.w3.org/2001/XMLSchema#unsignedByte",
+            "http://www.w3.org/2001/XMLSchema#unsignedShort",
+            "http://www.w3.org/2001/XMLSchema#unsignedInt",
+            "http://www.w3.org/2001/XMLSchema#unsignedLong",
+            "http://www.w3.org/2001/XMLSchema#positiveInteger",
+            "http://www.w3.org/2001/XMLSchema#negativeInteger",
+            "http://www.w3.org/2001/XMLSchema#nonPositiveInteger",
+            "http://www.w3.org/2001/XMLSchema#nonNegativeInteger",
+        ]
+        .iter()
+        .any(|iri| datatype_id == StrHash::new(iri))
+}
+
 pub fn parse_boolean_str(value: &str) -> Option<EncodedTerm> {
     match value {
         "true" | "1" => Some(EncodedTerm::BooleanLiteral(true)),
@@ -1260,6 +1538,15 @@ pub fn parse_day_time_duration_str(value: &str) -> Option<EncodedTerm> {
 pub trait Decoder {
     fn decode_term(&self, encoded: EncodedTerm) -> Result<Term>;
 
+    fn decode_subject(&self, encoded: EncodedTerm) -> Result<Subject> {
+        match self.decode_term(encoded)? {
+            Term::NamedNode(named_node) => Ok(named_node.into()),
+            Term::BlankNode(blank_node) => Ok(blank_node.into()),
+            Term::Literal(_) => Err(Error::msg("A literal has ben found instead of a subject")),
+            Term::Triple(triple) => Ok(triple.into()),
+        }
+    }
+
     fn decode_named_or_blank_node(&self, encoded: EncodedTerm) -> Result<NamedOrBlankNode> {
         match self.decode_term(encoded)? {
             Term::NamedNode(named_node) => Ok(named_node.into()),
@@ -1267,6 +1554,9 @@ pub trait Decoder {
             Term::Literal(_) => Err(Error::msg(
                 "A literal has ben found instead of a named node",
             )),
+            Term::Triple(_) => Err(Error::msg(
+                "A quoted triple has been found instead of a named node",
+            )),
         }
     }
 
@@ -1279,12 +1569,15 @@ pub trait Decoder {
             Term::Literal(_) => Err(Error::msg(
                 "A literal has ben found instead of a named node",
             )),
+            Term::Triple(_) => Err(Error::msg(
+                "A quoted triple has been found instead of a named node",
+            )),
         }
     }
 
     fn decode_triple(&self, encoded: &EncodedQuad) -> Result<Triple> {
         Ok(Triple::new(
-            self.decode_named_or_blank_node(encoded.subject)?,
+            self.decode_subject(encoded.subject)?,
             self.decode_named_node(encoded.predicate)?,
             self.decode_term(encoded.object)?,
         ))
@@ -1292,7 +1585,7 @@ pub trait Decoder {
 
     fn decode_quad(&self, encoded: &EncodedQuad) -> Result<Quad> {
         Ok(Quad::new(
-            self.decode_named_or_blank_node(encoded.subject)?,
+            self.decode_subject(encoded.subject)?,
             self.decode_named_node(encoded.predicate)?,
             self.decode_term(encoded.object)?,
             match encoded.graph_name {
@@ -1303,6 +1596,7 @@ pub trait Decoder {
     }
 }
 
+/// The strings of the decoded terms are shared with the lookup if it overrides `StrLookup::get_shared_str`.
 impl<S: StrLookup> Decoder for S {
     fn decode_term(&self, encoded: EncodedTerm) -> Result<Term> {
         match encoded {
@@ -1310,29 +1604,30 @@ impl<S: StrLookup> Decoder for S {
                 Err(Error::msg("The default graph tag is not a valid term"))
             }
             EncodedTerm::NamedNode { iri_id } => {
-                Ok(NamedNode::new_unchecked(get_required_str(self, iri_id)?).into())
+                Ok(NamedNode::new_from_shared(get_required_shared_str(self, iri_id)?).into())
             }
             EncodedTerm::InlineBlankNode { id } => Ok(BlankNode::new_from_unique_id(id).into()),
             EncodedTerm::NamedBlankNode { id_id } => {
-                Ok(BlankNode::new_unchecked(get_required_str(self, id_id)?).into())
-            }
-            EncodedTerm::StringLiteral { value_id } => {
-                Ok(Literal::new_simple_literal(get_required_str(self, value_id)?).into())
+                Ok(BlankNode::new_from_shared(get_required_shared_str(self, id_id)?).into())
             }
+            EncodedTerm::StringLiteral { value_id } => Ok(Literal::new_shared_simple_literal(
+                get_required_shared_str(self, value_id)?,
+            )
+            .into()),
             EncodedTerm::LangStringLiteral {
                 value_id,
                 language_id,
-            } => Ok(Literal::new_language_tagged_literal_unchecked(
-                get_required_str(self, value_id)?,
-                get_required_str(self, language_id)?,
+            } => Ok(Literal::new_shared_language_tagged_literal(
+                get_required_shared_str(self, value_id)?,
+                get_required_shared_str(self, language_id)?,
             )
             .into()),
             EncodedTerm::TypedLiteral {
                 value_id,
                 datatype_id,
-            } => Ok(Literal::new_typed_literal(
-                get_required_str(self, value_id)?,
-                NamedNode::new_unchecked(get_required_str(self, datatype_id)?),
+            } => Ok(Literal::new_shared_typed_literal(
+                get_required_shared_str(self, value_id)?,
+                NamedNode::new_from_shared(get_required_shared_str(self, datatype_id)?),
             )
             .into()),
             EncodedTerm::BooleanLiteral(value) => Ok(Literal::from(value).into()),
@@ -1346,11 +1641,22 @@ impl<S: StrLookup> Decoder for S {
             EncodedTerm::DurationLiteral(value) => Ok(Literal::from(value).into()),
             EncodedTerm::YearMonthDurationLiteral(value) => Ok(Literal::from(value).into()),
             EncodedTerm::DayTimeDurationLiteral(value) => Ok(Literal::from(value).into()),
+            EncodedTerm::Triple { id } => {
+                let (subject, predicate, object) = get_quoted_triple_terms(self, id)?;
+                Ok(self
+                    .decode_triple(&EncodedQuad::new(
+                        subject,
+                        predicate,
+                        object,
+                        ENCODED_DEFAULT_GRAPH,
+                    ))?
+                    .into())
+            }
         }
     }
 }
 
-fn get_required_str(lookup: &impl StrLookup, id: StrHash) -> Result<String> {
+pub fn get_required_str(lookup: &impl StrLookup, id: StrHash) -> Result<String> {
     lookup.get_str(id)?.ok_or_else(|| {
         Error::msg(format!(
             "Not able to find the string with id {:?} in the string store",
@@ -1359,6 +1665,23 @@ fn get_required_str(lookup: &impl StrLookup, id: StrHash) -> Result<String> {
     })
 }
 
+/// Returns the encoded subject, predicate and object of the quoted triple with the given id
+pub fn get_quoted_triple_terms(
+    lookup: &impl StrLookup,
+    id: StrHash,
+) -> Result<(EncodedTerm, EncodedTerm, EncodedTerm)> {
+    decode_triple_str(&get_required_str(lookup, id)?)
+}
+
+fn get_required_shared_str(lookup: &impl StrLookup, id: StrHash) -> Result<Arc<str>> {
+    lookup.get_shared_str(id)?.ok_or_else(|| {
+        Error::msg(format!(
+            "Not able to find the string with id {:?} in the string store",
+            id
+        ))
+    })
+}
+
 #[test]
 fn test_encoding() {
     let mut store = MemoryStrStore::default();
@@ -1385,6 +1708,16 @@ fn test_encoding() {
         Literal::new_typed_literal("01:01:01Z", xsd::TIME.clone()).into(),
         Literal::new_typed_literal("PT1S", xsd::DURATION.clone()).into(),
         Literal::new_typed_literal("-foo", NamedNode::new_unchecked("http://foo.com")).into(),
+        Triple::new(
+            Triple::new(
+                BlankNode::new_unchecked("foo-bnode"),
+                NamedNode::new_unchecked("http://foo.com"),
+                Literal::from(1),
+            ),
+            NamedNode::new_unchecked("http://bar.com"),
+            Literal::new_simple_literal("foo-literal"),
+        )
+        .into(),
     ];
     for term in terms {
         let encoded = store.encode_term(&term).unwrap();
@@ -1400,4 +1733,68 @@ fn test_str_hash() {
         StrHash::new("http://www.w3.org/1999/02/22-rdf-syntax-ns#langString"),
         RDF_LANG_STRING_ID
     );
+    assert_eq!(StrHash::new("en-US").inline_str(), Some("en-US"));
+    assert_eq!(
+        StrHash::new("fifteen bytes!!").inline_str(),
+        Some("fifteen bytes!!")
+    );
+    assert!(!StrHash::new("sixteen bytes!!!").is_inline());
+}
+
+#[test]
+fn test_inline_strings() {
+    let mut store = MemoryStrStore::default();
+    let terms: Vec<Term> = vec![
+        NamedNode::new_unchecked("ex:s").into(),
+        BlankNode::new_unchecked("b").into(),
+        Literal::new_simple_literal("").into(),
+        Literal::new_language_tagged_literal("été", "fr")
+            .unwrap()
+            .into(),
+        Literal::new_language_tagged_literal("a longer string value", "en")
+            .unwrap()
+            .into(),
+        Literal::new_typed_literal(
+            "foo",
+            NamedNode::new_unchecked("http://example.com/datatype"),
+        )
+        .into(),
+    ];
+    for term in terms {
+        let encoded = store.encode_term(&term).unwrap();
+        let mut buffer = Vec::default();
+        write_term(&mut buffer, encoded);
+        assert_eq!(Cursor::new(&buffer).read_term().unwrap(), encoded);
+        assert_eq!(term, store.decode_term(encoded).unwrap());
+    }
+    // Only the long strings are stored
+    assert!(store
+        .id2str
+        .values()
+        .all(|value| value.len() > MAX_INLINE_STR_LEN));
+}
+
+#[test]
+fn test_shared_decoding() {
+    let mut store = MemoryStrStore::default();
+    let encoded = store
+        .encode_named_node(&NamedNode::new_unchecked("http://example.com/foo"))
+        .unwrap();
+    let first = store.decode_named_node(encoded).unwrap();
+    let second = store.decode_named_node(encoded).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.as_str().as_ptr(), second.as_str().as_ptr());
+}
+
+#[test]
+fn test_str_hash_collision() {
+    let mut store = MemoryStrStore::default();
+    let value = "http://example.com/value";
+    let key = StrHash::new(value);
+    store.insert_str(key, value).unwrap();
+    // Inserting again the same string is fine
+    store.insert_str(key, value).unwrap();
+    // Another string with the same hash is rejected and the stored one is kept
+    assert!(store.insert_str(key, "http://example.com/other").is_err());
+    assert_eq!(store.get_str(key).unwrap().as_deref(), Some(value));
 }
@@ -0,0 +1,239 @@
+//! Multi-threaded N-Quads loading pipeline.
+//!
+//! The file is split on line boundaries into chunks that are parsed and encoded by a pool of worker threads,
+//! one per CPU.
+//! The encoded chunks are then written to the store by the calling thread in the order of the file.
+//! The chunks keep their first line number in the file in order for the syntax errors to point to the right line.
+//!
+//! The blank node labels are mapped to identifiers using a hash keyed by a random key drawn for each load,
+//! so all the workers map a label to the same blank node without sharing a table.
+
+use crate::store::numeric_encoder::*;
+use crate::store::WritableEncodedStore;
+use crate::{Error, Result};
+use rand::random;
+use rio_api::model as rio;
+use rio_api::parser::{ParseError, QuadsParser};
+use rio_turtle::{NQuadsParser, TurtleError};
+use siphasher::sip128::{Hasher128, SipHasher24};
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::hash::Hasher;
+use std::io::BufRead;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::spawn;
+
+/// The minimal size in bytes of a chunk, a chunk always ends at the end of a line
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// The maximal number of chunks read but not written yet for each worker, it bounds the memory used by the pipeline
+const MAX_CHUNKS_IN_FLIGHT_PER_WORKER: usize = 2;
+
+/// A chunk of the file to parse
+struct Chunk {
+    /// The position of the chunk in the file, the chunks are written in this order
+    position: usize,
+    /// The number of lines of the file before the chunk
+    first_line: u64,
+    content: Vec<u8>,
+}
+
+/// The quads of a chunk encoded with the strings they use
+#[derive(Default)]
+struct EncodedChunk {
+    strings: HashMap<StrHash, String>,
+    quads: Vec<EncodedQuad>,
+}
+
+impl StrContainer for EncodedChunk {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if let Some(stored) = self.strings.get(&key) {
+            return check_str_hash_collision(key, stored.as_bytes(), value);
+        }
+        self.strings.insert(key, value.to_owned());
+        Ok(())
+    }
+}
+
+/// Loads a N-Quads file into a store using multiple threads for parsing and encoding
+pub(crate) fn load_nquads_in_parallel<S: WritableEncodedStore>(
+    store: &mut S,
+    mut reader: impl BufRead,
+) -> Result<()> {
+    let bnodes_key = random::<u128>();
+    let worker_count = num_cpus::get();
+    let (job_sender, job_receiver) = channel::<Chunk>();
+    let job_receiver = Arc::new(Mutex::new(job_receiver));
+    let (result_sender, result_receiver) = channel();
+    for _ in 0..worker_count {
+        let job_receiver = job_receiver.clone();
+        let result_sender = result_sender.clone();
+        spawn(move || run_worker(&job_receiver, &result_sender, bnodes_key));
+    }
+    drop(result_sender);
+
+    // The chunks are numbered in the file order and written in this order
+    let mut next_to_read = 0;
+    let mut next_to_write = 0;
+    let mut next_line = 0;
+    let mut is_end_reached = false;
+    let mut encoded_chunks = HashMap::new();
+    loop {
+        while !is_end_reached
+            && next_to_read - next_to_write < MAX_CHUNKS_IN_FLIGHT_PER_WORKER * worker_count
+        {
+            let (content, line_count) = read_chunk(&mut reader)?;
+            if content.is_empty() {
+                is_end_reached = true;
+            } else {
+                job_sender
+                    .send(Chunk {
+                        position: next_to_read,
+                        first_line: next_line,
+                        content,
+                    })
+                    .map_err(|_| Error::msg("The N-Quads loading threads have stopped"))?;
+                next_to_read += 1;
+                next_line += line_count;
+            }
+        }
+        if next_to_write == next_to_read {
+            return Ok(());
+        }
+        let (position, chunk) = result_receiver
+            .recv()
+            .map_err(|_| Error::msg("The N-Quads loading threads have stopped"))?;
+        encoded_chunks.insert(position, chunk);
+        while let Some(chunk) = encoded_chunks.remove(&next_to_write) {
+            write_chunk(store, chunk?)?;
+            next_to_write += 1;
+        }
+    }
+}
+
+/// Reads complete lines until the chunk is at least `CHUNK_SIZE` bytes long or the end of the file is reached
+///
+/// Returns the chunk and its number of lines.
+fn read_chunk(reader: &mut impl BufRead) -> Result<(Vec<u8>, u64)> {
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE + 1024);
+    let mut line_count = 0;
+    while chunk.len() < CHUNK_SIZE {
+        if reader.read_until(b'\n', &mut chunk)? == 0 {
+            break;
+        }
+        line_count += 1;
+    }
+    Ok((chunk, line_count))
+}
+
+/// Parses and encodes the chunks until the job channel is closed
+fn run_worker(
+    jobs: &Mutex<Receiver<Chunk>>,
+    results: &Sender<(usize, Result<EncodedChunk>)>,
+    bnodes_key: u128,
+) {
+    loop {
+        let job = jobs
+            .lock()
+            .expect("the N-Quads loading jobs mutex has been poisoned because of a panic")
+            .recv();
+        let chunk = match job {
+            Ok(chunk) => chunk,
+            Err(_) => return,
+        };
+        if results
+            .send((chunk.position, encode_chunk(&chunk, bnodes_key)))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn encode_chunk(chunk: &Chunk, bnodes_key: u128) -> Result<EncodedChunk> {
+    let mut encoded = EncodedChunk::default();
+    let mut bnodes_map = HashMap::default();
+    let mut quads = Vec::new();
+    NQuadsParser::new(chunk.content.as_slice())?
+        .parse_all(&mut |q| -> Result<()> {
+            for blank_node in quad_blank_nodes(&q) {
+                if !bnodes_map.contains_key(blank_node) {
+                    bnodes_map.insert(blank_node.to_owned(), blank_node_id(blank_node, bnodes_key));
+                }
+            }
+            quads.push(encoded.encode_rio_quad(q, &mut bnodes_map)?);
+            Ok(())
+        })
+        .map_err(|error| locate_syntax_error(error, chunk.first_line))?;
+    encoded.quads = quads;
+    Ok(encoded)
+}
+
+/// Adds to a syntax error of a chunk its line in the file, the parser only knowing the line in the chunk
+fn locate_syntax_error(error: Error, first_line: u64) -> Error {
+    let line = error::Error::source(&error)
+        .and_then(|error| error.downcast_ref::<TurtleError>())
+        .and_then(|error| error.textual_position())
+        .map(|position| first_line + position.line_number() + 1);
+    if let Some(line) = line {
+        Error::wrap(ChunkSyntaxError { line, error })
+    } else {
+        error
+    }
+}
+
+/// A syntax error with the number, starting from 1, of the line of the file where it occurred
+///
+/// The positions given by the wrapped error are relative to the chunk.
+#[derive(Debug)]
+struct ChunkSyntaxError {
+    line: u64,
+    error: Error,
+}
+
+impl fmt::Display for ChunkSyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid N-Quads at line {}: {}", self.line, self.error)
+    }
+}
+
+impl error::Error for ChunkSyntaxError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+fn write_chunk<S: WritableEncodedStore>(store: &mut S, chunk: EncodedChunk) -> Result<()> {
+    for (key, value) in &chunk.strings {
+        store.insert_str(*key, value)?;
+    }
+    for quad in &chunk.quads {
+        store.insert_encoded(quad)?;
+    }
+    Ok(())
+}
+
+/// Returns the labels of the blank nodes used in a quad
+fn quad_blank_nodes<'a>(quad: &rio::Quad<'a>) -> impl Iterator<Item = &'a str> {
+    let subject = match quad.subject {
+        rio::NamedOrBlankNode::BlankNode(b) => Some(b.id),
+        rio::NamedOrBlankNode::NamedNode(_) => None,
+    };
+    let object = match quad.object {
+        rio::Term::BlankNode(b) => Some(b.id),
+        _ => None,
+    };
+    let graph_name = match quad.graph_name {
+        Some(rio::NamedOrBlankNode::BlankNode(b)) => Some(b.id),
+        _ => None,
+    };
+    subject.into_iter().chain(object).chain(graph_name)
+}
+
+/// Maps a blank node label to an identifier that is the same in all the chunks of a load
+fn blank_node_id(label: &str, key: u128) -> u128 {
+    let mut hasher = SipHasher24::new_with_keys((key >> 64) as u64, key as u64);
+    hasher.write(label.as_bytes());
+    hasher.finish128().into()
+}
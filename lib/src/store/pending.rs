@@ -0,0 +1,263 @@
+//! Changes of a transaction not committed yet and the view of a store with them applied.
+//!
+//! The transactions record their changes in a `PendingChanges` in order for the queries executed during the transaction
+//! to see them: a `PendingChangesView` reads the store as if the changes were already committed.
+
+use crate::store::numeric_encoder::*;
+use crate::store::{PredicateStatistics, ReadableEncodedStore, StoreStatistics};
+use crate::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// The changes done by a transaction and not committed yet
+#[derive(Default, Clone)]
+pub(crate) struct PendingChanges {
+    /// The quads inserted by the transaction, they are in the store after it
+    inserted: HashSet<EncodedQuad>,
+    /// The quads removed by the transaction, they are not in the store after it
+    removed: HashSet<EncodedQuad>,
+    /// The graphs cleared by the transaction, only the quads inserted after the clear are in the store after it
+    cleared_graphs: HashSet<EncodedTerm>,
+    inserted_graphs: HashSet<EncodedTerm>,
+    removed_graphs: HashSet<EncodedTerm>,
+    strings: HashMap<StrHash, String>,
+}
+
+impl PendingChanges {
+    pub fn insert(&mut self, quad: &EncodedQuad) {
+        self.removed.remove(quad);
+        self.inserted.insert(*quad);
+        if quad.graph_name != ENCODED_DEFAULT_GRAPH {
+            self.removed_graphs.remove(&quad.graph_name);
+            self.inserted_graphs.insert(quad.graph_name);
+        }
+    }
+
+    pub fn remove(&mut self, quad: &EncodedQuad) {
+        self.inserted.remove(quad);
+        self.removed.insert(*quad);
+    }
+
+    pub fn insert_named_graph(&mut self, graph_name: EncodedTerm) {
+        self.removed_graphs.remove(&graph_name);
+        self.inserted_graphs.insert(graph_name);
+    }
+
+    pub fn clear_graph(&mut self, graph_name: EncodedTerm) {
+        self.inserted.retain(|quad| quad.graph_name != graph_name);
+        self.removed.retain(|quad| quad.graph_name != graph_name);
+        self.cleared_graphs.insert(graph_name);
+    }
+
+    pub fn remove_named_graph(&mut self, graph_name: EncodedTerm) {
+        self.clear_graph(graph_name);
+        self.inserted_graphs.remove(&graph_name);
+        self.removed_graphs.insert(graph_name);
+    }
+
+    pub fn insert_str(&mut self, key: StrHash, value: &str) {
+        self.strings.insert(key, value.to_owned());
+    }
+
+    /// Applies on top of these changes the changes done after them
+    pub fn extend(&mut self, later: Self) {
+        for graph_name in later.cleared_graphs {
+            self.clear_graph(graph_name);
+        }
+        for graph_name in later.removed_graphs {
+            self.remove_named_graph(graph_name);
+        }
+        for graph_name in later.inserted_graphs {
+            self.insert_named_graph(graph_name);
+        }
+        for quad in &later.removed {
+            self.remove(quad);
+        }
+        for quad in &later.inserted {
+            self.insert(quad);
+        }
+        self.strings.extend(later.strings);
+    }
+
+    /// The strings inserted by the transaction
+    pub fn strings(&self) -> impl Iterator<Item = (StrHash, &str)> {
+        self.strings
+            .iter()
+            .map(|(key, value)| (*key, value.as_str()))
+    }
+
+    /// Returns `Some` if the changes decide if the quad is in the store and `None` if it depends on the store content
+    fn contains(&self, quad: &EncodedQuad) -> Option<bool> {
+        if self.inserted.contains(quad) {
+            Some(true)
+        } else if self.removed.contains(quad) || self.cleared_graphs.contains(&quad.graph_name) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// The quads of a `PendingChangesView` matching a pattern
+///
+/// The inserted quads are returned just after the store quads with the same triple
+/// in order to keep the quads with the same triple next to each other.
+struct PendingQuadsIterator<'a> {
+    store_quads: Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a>,
+    changes: &'a PendingChanges,
+    /// The graphs of the inserted quads matching the pattern and not returned yet, by triple
+    inserted: HashMap<(EncodedTerm, EncodedTerm, EncodedTerm), Vec<EncodedTerm>>,
+    pending: Vec<EncodedQuad>,
+}
+
+impl Iterator for PendingQuadsIterator<'_> {
+    type Item = Result<EncodedQuad>;
+
+    fn next(&mut self) -> Option<Result<EncodedQuad>> {
+        loop {
+            if let Some(quad) = self.pending.pop() {
+                return Some(Ok(quad));
+            }
+            match self.store_quads.next() {
+                Some(Ok(quad)) => {
+                    if let Some(graph_names) =
+                        self.inserted
+                            .remove(&(quad.subject, quad.predicate, quad.object))
+                    {
+                        self.pending
+                            .extend(graph_names.into_iter().map(|graph_name| {
+                                EncodedQuad::new(
+                                    quad.subject,
+                                    quad.predicate,
+                                    quad.object,
+                                    graph_name,
+                                )
+                            }));
+                    }
+                    // The inserted quads are returned from the changes
+                    if self.changes.contains(&quad).is_none() {
+                        return Some(Ok(quad));
+                    }
+                }
+                Some(Err(error)) => return Some(Err(error)),
+                None => {
+                    if self.inserted.is_empty() {
+                        return None;
+                    }
+                    self.pending
+                        .extend(self.inserted.drain().flat_map(|(triple, graph_names)| {
+                            graph_names.into_iter().map(move |graph_name| {
+                                EncodedQuad::new(triple.0, triple.1, triple.2, graph_name)
+                            })
+                        }));
+                }
+            }
+        }
+    }
+}
+
+/// A store seen as if the pending changes of a transaction were committed
+#[derive(Clone)]
+pub(crate) struct PendingChangesView<'a, S> {
+    store: S,
+    changes: &'a PendingChanges,
+}
+
+impl<'a, S> PendingChangesView<'a, S> {
+    pub fn new(store: S, changes: &'a PendingChanges) -> Self {
+        Self { store, changes }
+    }
+}
+
+impl<S: StrLookup> StrLookup for PendingChangesView<'_, S> {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        if let Some(value) = self.changes.strings.get(&id) {
+            Ok(Some(value.clone()))
+        } else {
+            self.store.get_stored_str(id)
+        }
+    }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        if let Some(value) = self.changes.strings.get(&id) {
+            Ok(Some(value.as_str().into()))
+        } else {
+            self.store.get_stored_shared_str(id)
+        }
+    }
+}
+
+impl<S: StoreStatistics> StoreStatistics for PendingChangesView<'_, S> {
+    // The changes of a transaction are small compared to the store, the statistics of the store are good estimates
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        self.store.predicate_statistics(predicate)
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        self.store.graph_quads_count(graph_name)
+    }
+}
+
+impl<S: ReadableEncodedStore> ReadableEncodedStore for PendingChangesView<'_, S> {
+    fn encoded_quads_for_pattern<'b>(
+        &'b self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'b> {
+        let pattern = [subject, predicate, object, graph_name];
+        let mut inserted: HashMap<_, Vec<_>> = HashMap::new();
+        for quad in &self.changes.inserted {
+            if [quad.subject, quad.predicate, quad.object, quad.graph_name]
+                .iter()
+                .zip(&pattern)
+                .all(|(term, expected)| expected.map_or(true, |e| e == *term))
+            {
+                inserted
+                    .entry((quad.subject, quad.predicate, quad.object))
+                    .or_default()
+                    .push(quad.graph_name);
+            }
+        }
+        Box::new(PendingQuadsIterator {
+            store_quads: self
+                .store
+                .encoded_quads_for_pattern(subject, predicate, object, graph_name),
+            changes: self.changes,
+            inserted,
+            pending: Vec::new(),
+        })
+    }
+
+    fn encoded_named_graphs<'b>(&'b self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b> {
+        let changes = self.changes;
+        Box::new(
+            self.store
+                .encoded_named_graphs()
+                .filter(move |graph_name| match graph_name {
+                    Ok(graph_name) => {
+                        !changes.removed_graphs.contains(graph_name)
+                            && !changes.inserted_graphs.contains(graph_name)
+                    }
+                    Err(_) => true,
+                })
+                .chain(
+                    changes
+                        .inserted_graphs
+                        .iter()
+                        .map(|graph_name| Ok(*graph_name)),
+                ),
+        )
+    }
+
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        if self.changes.inserted_graphs.contains(&graph_name) {
+            Ok(true)
+        } else if self.changes.removed_graphs.contains(&graph_name) {
+            Ok(false)
+        } else {
+            self.store.contains_encoded_named_graph(graph_name)
+        }
+    }
+}
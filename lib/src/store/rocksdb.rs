@@ -1,16 +1,43 @@
 //! Store based on the [RocksDB](https://rocksdb.org/) key-value database.
 
 use crate::model::*;
-use crate::sparql::{GraphPattern, QueryOptions, QueryResult, SimplePreparedQuery};
+use crate::sparql::{
+    GraphPattern, Query, QueryExplanation, QueryOptions, QueryResult, QuerySolution, QueryStats,
+    SimplePreparedQuery, SimplePreparedUpdate,
+};
+use crate::store::commit_log::{
+    read_timestamp, write_timestamp, CommitLogReader, CommitLogRecord, CommitLogTarget,
+    CommitLogWriter,
+};
 use crate::store::numeric_encoder::*;
-use crate::store::{load_dataset, load_graph, ReadableEncodedStore, WritableEncodedStore};
+use crate::store::pending::{PendingChanges, PendingChangesView};
+use crate::store::{
+    add_encoded_graph, copy_encoded_graph, dump_dataset, dump_graph, has_stored_strings,
+    load_dataset, load_graph, merge_encoded, move_encoded_graph, sealed, EncodedQuadEvent,
+    MergeSource, PredicateStatistics, QuadEvent, ReadableEncodedStore, StorageError,
+    StoreStatistics, StoreStats, ValidationReport, Watchers, WritableEncodedStore,
+};
+#[cfg(feature = "full-text")]
+use crate::store::{is_string_literal, literal_words, scan_literals_matching, text_words};
 use crate::{DatasetSyntax, GraphSyntax, Result};
+use fs2::{lock_contended_error, FileExt};
+use rocksdb::backup::{BackupEngine, BackupEngineOptions, RestoreOptions};
 use rocksdb::*;
-use std::io::BufRead;
-use std::mem::take;
-use std::path::Path;
+use std::cmp::Ordering;
+use std::collections::binary_heap::PeekMut;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io::{BufRead, BufReader, Cursor, Read, Write};
+#[cfg(feature = "full-text")]
+use std::iter::{empty, once};
+use std::mem::{replace, take};
+use std::path::{Path, PathBuf};
 use std::str;
-use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Store based on the [RocksDB](https://rocksdb.org/) key-value database.
 /// It encodes a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset) and allows to query and update it using SPARQL.
@@ -50,6 +77,506 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct RocksDbStore {
     db: Arc<DB>,
+    /// The lock file of the database, declared after `db` to be released only after it is closed
+    lock: Option<Arc<File>>,
+    indexes: IndexSet,
+    durability: RocksDbDurability,
+    isolation: RocksDbIsolation,
+    /// Held while a batch is written, and during the whole transactions with the serializable isolation
+    commit_lock: Arc<Mutex<()>>,
+    commit_log: Option<Arc<CommitLogWriter>>,
+    watchers: Arc<Watchers>,
+    layout: RocksDbLayout,
+    /// The named graphs with a column family of their own in the `ColumnFamilyPerGraph` layout
+    ///
+    /// It is locked for writing while their column families are created or dropped and the batch using them is written,
+    /// and for reading while a snapshot is taken in order for the snapshot to keep the column families it sees.
+    graph_cfs: Arc<RwLock<HashSet<EncodedTerm>>>,
+    /// The options of the column families created for the named graphs
+    graph_cf_options: Arc<Options>,
+}
+
+/// The optional permutation indexes of a [`RocksDbStore`](struct.RocksDbStore.html), named after the order of the quad components in their keys.
+///
+/// The SPOG and POSG indexes are always maintained: they are used to keep the statistics up to date.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum RocksDbIndex {
+    /// Object, subject, predicate, graph name
+    OSPG,
+    /// Graph name, subject, predicate, object
+    GSPO,
+    /// Graph name, predicate, object, subject
+    GPOS,
+    /// Graph name, object, subject, predicate
+    GOSP,
+}
+
+impl RocksDbIndex {
+    const ALL: [RocksDbIndex; 4] = [
+        RocksDbIndex::OSPG,
+        RocksDbIndex::GSPO,
+        RocksDbIndex::GPOS,
+        RocksDbIndex::GOSP,
+    ];
+}
+
+/// A set of optional indexes encoded as a bit mask
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+struct IndexSet(u8);
+
+impl IndexSet {
+    const ALL: IndexSet = IndexSet(0b1111);
+
+    fn contains(self, index: RocksDbIndex) -> bool {
+        self.0 & (1 << index as u8) != 0
+    }
+
+    fn intersection(self, other: IndexSet) -> IndexSet {
+        IndexSet(self.0 & other.0)
+    }
+
+    /// Reads the indexes maintained by a database, all of them if they have never been chosen
+    fn read(db: &DB) -> Result<Self> {
+        Ok(db
+            .get_pinned_cf(get_cf(db, STATS_CF), &INDEXES_KEY)?
+            .and_then(|value| value.first().copied())
+            .map_or(IndexSet::ALL, IndexSet))
+    }
+}
+
+impl From<&[RocksDbIndex]> for IndexSet {
+    fn from(indexes: &[RocksDbIndex]) -> Self {
+        IndexSet(
+            indexes
+                .iter()
+                .fold(0, |mask, index| mask | (1 << *index as u8)),
+        )
+    }
+}
+
+/// Tuning options of the RocksDB database of a [`RocksDbStore`](struct.RocksDbStore.html) opened with `open_with_options`.
+///
+/// The RocksDB defaults are used for the options that are not set.
+/// Except the layout, they are not saved in the database: they apply only until the store is closed.
+///
+/// See `RocksDbStore::open_with_options` for a usage example.
+#[derive(Debug, Clone, Default)]
+pub struct RocksDbOptions {
+    block_cache_size: Option<usize>,
+    compression: Option<RocksDbCompression>,
+    write_buffer_size: Option<usize>,
+    block_restart_interval: Option<i32>,
+    bloom_filter_bits_per_key: Option<i32>,
+    max_background_jobs: Option<i32>,
+    durability: RocksDbDurability,
+    isolation: RocksDbIsolation,
+    lock_timeout: Duration,
+    commit_log: Option<PathBuf>,
+    expiration_sweep_interval: Option<Duration>,
+    layout: Option<RocksDbLayout>,
+}
+
+impl RocksDbOptions {
+    /// Sets the size in bytes of the LRU cache of uncompressed blocks, shared by all the indexes.
+    ///
+    /// RocksDB uses a 8MB cache per index by default.
+    pub fn with_block_cache_size(mut self, size: usize) -> Self {
+        self.block_cache_size = Some(size);
+        self
+    }
+
+    /// Sets the algorithm used to compress the blocks written on disk.
+    pub fn with_compression(mut self, compression: RocksDbCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Sets the size in bytes of the in-memory buffer of each index written on disk when full.
+    ///
+    /// Bigger buffers speed up big loads at the cost of memory and a longer recovery on the next opening.
+    pub fn with_write_buffer_size(mut self, size: usize) -> Self {
+        self.write_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the number of keys between two restart points of the key prefix compression in the blocks.
+    ///
+    /// Each key is stored without the prefix it shares with the previous one, except the restart points that are stored in full.
+    /// The keys of the indexes repeat the same terms, so longer intervals shrink the database at the cost of slower seeks.
+    /// The default is 64 keys.
+    pub fn with_block_restart_interval(mut self, interval: i32) -> Self {
+        self.block_restart_interval = Some(interval);
+        self
+    }
+
+    /// Sets the number of bits per key of the bloom filters of the indexes, or disables them with 0.
+    ///
+    /// The filters allow `contains` and the patterns with all the quad components set to skip the disk reads for most of the absent quads.
+    /// More bits lower the false positive rate at the cost of memory. The default is 10 bits, about 1% of false positives.
+    pub fn with_bloom_filter_bits_per_key(mut self, bits: i32) -> Self {
+        self.bloom_filter_bits_per_key = Some(bits);
+        self
+    }
+
+    /// Sets the maximal number of concurrent background flushes and compactions.
+    pub fn with_max_background_jobs(mut self, jobs: i32) -> Self {
+        self.max_background_jobs = Some(jobs);
+        self
+    }
+
+    /// Sets how the commits are persisted. The default is `RocksDbDurability::Wal`.
+    pub fn with_durability(mut self, durability: RocksDbDurability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Sets the isolation of the transactions executed with `RocksDbStore::transaction`. The default is `RocksDbIsolation::Snapshot`.
+    pub fn with_isolation(mut self, isolation: RocksDbIsolation) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    /// Waits up to `timeout` for the database to be closed by the other process or store keeping it open.
+    ///
+    /// By default, opening a database already opened elsewhere fails immediately with `StorageError::AlreadyLocked`.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Appends all the commits to the log file at `path`, creating it if it does not exist.
+    ///
+    /// Other stores could replicate the changes by reading the log with `RocksDbStore::replicate`.
+    /// The log grows with each commit: `RocksDbStore::truncate_commit_log` removes the commits already replicated.
+    /// The bulk loads could not be logged and fail if this option is set.
+    pub fn with_commit_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.commit_log = Some(path.into());
+        self
+    }
+
+    /// Removes the expired named graphs every `interval` in a background thread.
+    ///
+    /// The thread stops after the store is closed.
+    /// See `RocksDbStore::set_graph_expiration` to set the expiration time of the graphs.
+    pub fn with_expiration_sweep_interval(mut self, interval: Duration) -> Self {
+        self.expiration_sweep_interval = Some(interval);
+        self
+    }
+
+    /// Sets how the quads are split between the column families. The default is `RocksDbLayout::SharedColumnFamilies`.
+    ///
+    /// Unlike the other options, the layout is saved in the database and kept by the next openings.
+    /// It could only be changed while the database does not contain any quad: opening a non-empty database with another layout fails.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use oxigraph::store::rocksdb::{RocksDbLayout, RocksDbOptions};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open_with_options(
+    ///     "example_layout.db",
+    ///     RocksDbOptions::default().with_layout(RocksDbLayout::ColumnFamilyPerGraph),
+    /// )?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone()))?;
+    ///
+    /// // The column family of the graph is dropped
+    /// store.clear_graph(&ex.clone().into())?;
+    /// assert!(store.is_empty()?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_layout.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn with_layout(mut self, layout: RocksDbLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Locks the database and opens it with the column families of its named graphs
+    ///
+    /// Returns the named graphs with a column family.
+    fn open_db(&self, path: &Path) -> Result<(DB, File, HashSet<EncodedTerm>)> {
+        let lock = self.lock(path)?;
+        let graph_names = graph_column_families(path);
+        let options = self.column_family_options();
+        let db = DB::open_cf_descriptors(
+            &self.db_options(),
+            path,
+            COLUMN_FAMILIES
+                .iter()
+                .map(|name| (*name).to_owned())
+                .chain(
+                    graph_names
+                        .iter()
+                        .map(|graph_name| graph_cf_name(*graph_name)),
+                )
+                .map(|name| ColumnFamilyDescriptor::new(name, options.clone())),
+        )?;
+        Ok((db, lock, graph_names))
+    }
+
+    /// Takes an exclusive OS lock on the `LOCK_FILE` file of the database directory,
+    /// retrying while it is held by another process or store until the lock timeout is reached
+    fn lock(&self, path: &Path) -> Result<File> {
+        create_dir_all(path)?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.join(LOCK_FILE))?;
+        let start = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(file),
+                Err(error) if error.kind() == lock_contended_error().kind() => {
+                    if start.elapsed() >= self.lock_timeout {
+                        return Err(StorageError::AlreadyLocked {
+                            path: path.to_owned(),
+                        }
+                        .into());
+                    }
+                    sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    fn db_options(&self) -> Options {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        options.set_compaction_style(DBCompactionStyle::Universal);
+        if let Some(jobs) = self.max_background_jobs {
+            options.set_max_background_jobs(jobs);
+        }
+        options
+    }
+
+    /// The options of all the column families
+    ///
+    /// The column families get copies of the returned options in order to share the block cache.
+    fn column_family_options(&self) -> Options {
+        // A single block based table configuration is used in order to share its cache between the column families
+        let mut block_based_options = BlockBasedOptions::default();
+        if let Some(size) = self.block_cache_size {
+            block_based_options.set_lru_cache(size);
+        }
+        block_based_options.set_block_restart_interval(
+            self.block_restart_interval
+                .unwrap_or(DEFAULT_BLOCK_RESTART_INTERVAL),
+        );
+        // The format version 4 also removes the shared prefixes of the keys of the index blocks
+        block_based_options.set_format_version(4);
+        let bloom_filter_bits_per_key = self
+            .bloom_filter_bits_per_key
+            .unwrap_or(DEFAULT_BLOOM_FILTER_BITS_PER_KEY);
+        if bloom_filter_bits_per_key > 0 {
+            // A full filter per file instead of one per block, checked once per lookup
+            block_based_options.set_bloom_filter(bloom_filter_bits_per_key, false);
+        }
+        let mut options = Options::default();
+        options.set_block_based_table_factory(&block_based_options);
+        if let Some(compression) = self.compression {
+            options.set_compression_type(compression.into());
+        }
+        if let Some(size) = self.write_buffer_size {
+            options.set_write_buffer_size(size);
+        }
+        options
+    }
+}
+
+/// How the commits of a [`RocksDbStore`](struct.RocksDbStore.html) are persisted, from the safest to the fastest.
+///
+/// It applies to all the writes except the bulk loads, which always bypass the write-ahead log.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum RocksDbDurability {
+    /// The write-ahead log is synced to disk (fsync) before each commit returns.
+    /// The committed changes survive a machine crash.
+    Sync,
+    /// The commits are written to the write-ahead log without waiting for it to be synced to disk.
+    /// The committed changes survive a process crash but the last ones may be lost if the machine crashes.
+    Wal,
+    /// The write-ahead log is not used: the commits are only in memory until RocksDB flushes them to disk.
+    /// The changes not flushed yet are lost if the process crashes. They are flushed when the store is closed.
+    NoWal,
+}
+
+impl Default for RocksDbDurability {
+    fn default() -> Self {
+        RocksDbDurability::Wal
+    }
+}
+
+/// How the transactions of a [`RocksDbStore`](struct.RocksDbStore.html) are isolated from the concurrent writes, from the weakest to the strongest.
+///
+/// It applies to the transactions executed with `RocksDbStore::transaction` and to the SPARQL updates.
+/// The other writes, like `insert` or `load_graph`, are not isolated: they are applied as soon as they are done.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum RocksDbIsolation {
+    /// The SPARQL updates read the latest committed state of the store and the transactions never fail because of the concurrent writes.
+    /// Two concurrent updates reading and rewriting the same quads may lose one of the writes.
+    ReadCommitted,
+    /// The SPARQL updates read a snapshot of the store taken at the beginning of the transaction.
+    /// The commit fails with `StorageError::TransactionConflict` if a quad or a named graph written by the transaction has been inserted or removed by another write since the snapshot.
+    /// The SPARQL updates failing because of a conflict are retried a few times.
+    Snapshot,
+    /// The transactions are executed one after the other: each transaction keeps the store write lock from its beginning to its commit.
+    /// They never conflict but they do not run in parallel.
+    /// The closure of a transaction should not write to the store outside of the transaction or it would wait forever for the lock.
+    Serializable,
+}
+
+impl Default for RocksDbIsolation {
+    fn default() -> Self {
+        RocksDbIsolation::Snapshot
+    }
+}
+
+/// How the quads of a [`RocksDbStore`](struct.RocksDbStore.html) are split between the RocksDB column families.
+///
+/// It is chosen with `RocksDbOptions::with_layout` and saved in the database.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum RocksDbLayout {
+    /// All the quads are stored in the same indexes.
+    ///
+    /// It is the default.
+    SharedColumnFamilies,
+    /// The quads of each named graph are stored in a column family of their own, the default graph ones in the shared indexes.
+    ///
+    /// Clearing or removing a named graph drops its column family instead of removing its quads one by one,
+    /// and the named graphs are compacted apart from each other.
+    /// The column families are opened with the database and created or dropped when the writes using them are committed.
+    ///
+    /// The predicate statistics and the full-text index are not updated when a graph is dropped: `RocksDbStore::optimize` computes them again.
+    /// The patterns without graph name read the indexes of all the named graphs, so this layout suits the datasets made of a few big graphs.
+    ColumnFamilyPerGraph,
+}
+
+impl RocksDbLayout {
+    /// Reads the layout of a database, `None` if it has never been chosen
+    fn read(db: &DB) -> Result<Option<Self>> {
+        Ok(db
+            .get_pinned_cf(get_cf(db, STATS_CF), &LAYOUT_KEY)?
+            .and_then(|value| value.first().copied())
+            .map(|value| {
+                if value == 1 {
+                    RocksDbLayout::ColumnFamilyPerGraph
+                } else {
+                    RocksDbLayout::SharedColumnFamilies
+                }
+            }))
+    }
+}
+
+impl Default for RocksDbLayout {
+    fn default() -> Self {
+        RocksDbLayout::SharedColumnFamilies
+    }
+}
+
+/// The compression algorithms supported by [`RocksDbOptions`](struct.RocksDbOptions.html).
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum RocksDbCompression {
+    /// No compression
+    None,
+    /// [Snappy](https://github.com/google/snappy), the RocksDB default
+    Snappy,
+    /// [zlib](https://zlib.net/)
+    Zlib,
+    /// [bzip2](https://www.sourceware.org/bzip2/)
+    Bz2,
+    /// [LZ4](https://lz4.github.io/lz4/)
+    Lz4,
+    /// LZ4 high compression mode
+    Lz4hc,
+    /// [Zstandard](https://facebook.github.io/zstd/)
+    Zstd,
+}
+
+impl From<RocksDbCompression> for DBCompressionType {
+    fn from(compression: RocksDbCompression) -> Self {
+        match compression {
+            RocksDbCompression::None => DBCompressionType::None,
+            RocksDbCompression::Snappy => DBCompressionType::Snappy,
+            RocksDbCompression::Zlib => DBCompressionType::Zlib,
+            RocksDbCompression::Bz2 => DBCompressionType::Bz2,
+            RocksDbCompression::Lz4 => DBCompressionType::Lz4,
+            RocksDbCompression::Lz4hc => DBCompressionType::Lz4hc,
+            RocksDbCompression::Zstd => DBCompressionType::Zstd,
+        }
+    }
+}
+
+/// Provenance metadata attached to a quad of a [`RocksDbStore`](struct.RocksDbStore.html) with `insert_with_provenance`.
+///
+/// See `RocksDbStore::insert_with_provenance` for a usage example.
+#[derive(Eq, PartialEq, Debug, Clone, Hash, Default)]
+pub struct QuadProvenance {
+    /// Where the quad comes from, like the IRI of a file or of a SPARQL endpoint
+    pub source: Option<String>,
+    /// When the quad has been produced or inserted
+    pub timestamp: Option<SystemTime>,
+    /// Who inserted the quad, like a user or a service name
+    pub agent: Option<String>,
+}
+
+impl QuadProvenance {
+    fn write(&self, buffer: &mut Vec<u8>) -> Result<()> {
+        if let Some(timestamp) = self.timestamp {
+            buffer.push(1);
+            write_timestamp(buffer, timestamp)?;
+        } else {
+            buffer.push(0);
+        }
+        write_optional_str(buffer, self.source.as_deref());
+        write_optional_str(buffer, self.agent.as_deref());
+        Ok(())
+    }
+
+    fn read(mut reader: &[u8]) -> Result<Self> {
+        let mut flag = [0];
+        reader.read_exact(&mut flag)?;
+        let timestamp = if flag[0] == 0 {
+            None
+        } else {
+            Some(read_timestamp(&mut reader)?)
+        };
+        Ok(Self {
+            source: read_optional_str(&mut reader)?,
+            timestamp,
+            agent: read_optional_str(&mut reader)?,
+        })
+    }
+}
+
+fn write_optional_str(buffer: &mut Vec<u8>, value: Option<&str>) {
+    if let Some(value) = value {
+        buffer.push(1);
+        buffer.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        buffer.extend_from_slice(value.as_bytes());
+    } else {
+        buffer.push(0);
+    }
+}
+
+fn read_optional_str(reader: &mut &[u8]) -> Result<Option<String>> {
+    let mut flag = [0];
+    reader.read_exact(&mut flag)?;
+    if flag[0] == 0 {
+        return Ok(None);
+    }
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let mut value = vec![0; u32::from_be_bytes(len) as usize];
+    reader.read_exact(&mut value)?;
+    Ok(Some(String::from_utf8(value)?))
 }
 
 const ID2STR_CF: &str = "id2str";
@@ -59,46 +586,386 @@ const OSPG_CF: &str = "ospg";
 const GSPO_CF: &str = "gspo";
 const GPOS_CF: &str = "gpos";
 const GOSP_CF: &str = "gosp";
+const STATS_CF: &str = "stats";
+const GRAPHS_CF: &str = "graphs";
+const PROVENANCE_CF: &str = "provenance";
+const EXPIRATIONS_CF: &str = "expirations";
+const WORDS_CF: &str = "words";
 
 //TODO: indexes for the default graph and indexes for the named graphs (no more Optional and space saving)
 
-const COLUMN_FAMILIES: [&str; 7] = [
-    ID2STR_CF, SPOG_CF, POSG_CF, OSPG_CF, GSPO_CF, GPOS_CF, GOSP_CF,
+/// The prefix of the column families of the named graphs in the `ColumnFamilyPerGraph` layout, followed by the hexadecimal encoded graph name
+const GRAPH_CF_PREFIX: &str = "graph_";
+
+// The keys of the column families of the named graphs: a tag followed by the key of the shared column family
+const SPOG_TAG: u8 = 0;
+const POSG_TAG: u8 = 1;
+const OSPG_TAG: u8 = 2;
+const GSPO_TAG: u8 = 3;
+const GPOS_TAG: u8 = 4;
+const GOSP_TAG: u8 = 5;
+const PROVENANCE_TAG: u8 = 6;
+
+const COLUMN_FAMILIES: [&str; 12] = [
+    ID2STR_CF,
+    SPOG_CF,
+    POSG_CF,
+    OSPG_CF,
+    GSPO_CF,
+    GPOS_CF,
+    GOSP_CF,
+    STATS_CF,
+    GRAPHS_CF,
+    PROVENANCE_CF,
+    EXPIRATIONS_CF,
+    WORDS_CF,
 ];
 
+// Keys of the statistics column family: the statistic kind followed by the encoded term it is about
+const STATISTICS_INITIALIZED_KEY: [u8; 1] = [0];
+const PREDICATE_QUADS_COUNT: u8 = 1;
+const PREDICATE_DISTINCT_SUBJECTS_COUNT: u8 = 2;
+const PREDICATE_DISTINCT_OBJECTS_COUNT: u8 = 3;
+const GRAPH_QUADS_COUNT: u8 = 4;
+// The number of quads with a string literal as object, the literals are in the words index if it is not 0
+#[cfg(feature = "full-text")]
+const LITERAL_QUADS_COUNT: u8 = 5;
+// The number of literals containing a word, followed by the word instead of an encoded term
+#[cfg(feature = "full-text")]
+const WORD_LITERALS_COUNT: u8 = 6;
+// The optional indexes maintained by the store
+const INDEXES_KEY: [u8; 1] = [u8::MAX];
+// The sequence number of the last commit applied by `replicate`
+const REPLICATED_SEQUENCE_KEY: [u8; 1] = [u8::MAX - 1];
+// The version of the on-disk format of the database
+const FORMAT_VERSION_KEY: [u8; 1] = [u8::MAX - 2];
+// The sequence number of the last commit appended to the commit log, written with the commit
+const LOGGED_SEQUENCE_KEY: [u8; 1] = [u8::MAX - 3];
+// Set while the words index of the string literals is maintained, i.e. the store is only written with the full-text feature
+const WORDS_INDEX_KEY: [u8; 1] = [u8::MAX - 4];
+// The layout of the quads chosen when the database has been created, 1 for `ColumnFamilyPerGraph`
+const LAYOUT_KEY: [u8; 1] = [u8::MAX - 5];
+
+// The versions of the on-disk format:
+// 0: the databases written before the version has been stamped
+// 1: the statistics and the named graphs index are maintained
+// 2: the strings of at most 15 bytes are inlined in the encoded terms
+const LATEST_FORMAT_VERSION: u64 = 2;
+
 const MAX_TRANSACTION_SIZE: usize = 1024;
+/// RocksDB stores a full key every 16 keys by default, the quad keys share longer prefixes
+const DEFAULT_BLOCK_RESTART_INTERVAL: i32 = 64;
+const DEFAULT_BLOOM_FILTER_BITS_PER_KEY: i32 = 10;
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+/// The file of the database directory locked while the database is opened for writing
+const LOCK_FILE: &str = "OXIGRAPH_LOCK";
+const BULK_LOAD_BATCH_SIZE: usize = 1_000_000;
+const MAX_UPDATE_CONFLICT_RETRIES: usize = 10;
 
 #[derive(Clone)]
 struct RocksDbStoreHandle<'a> {
     db: &'a DB,
-    id2str_cf: &'a ColumnFamily,
-    spog_cf: &'a ColumnFamily,
-    posg_cf: &'a ColumnFamily,
-    ospg_cf: &'a ColumnFamily,
-    gspo_cf: &'a ColumnFamily,
-    gpos_cf: &'a ColumnFamily,
-    gosp_cf: &'a ColumnFamily,
+    id2str_cf: ColumnFamilyRef<'a>,
+    spog_cf: ColumnFamilyRef<'a>,
+    posg_cf: ColumnFamilyRef<'a>,
+    ospg_cf: ColumnFamilyRef<'a>,
+    gspo_cf: ColumnFamilyRef<'a>,
+    gpos_cf: ColumnFamilyRef<'a>,
+    gosp_cf: ColumnFamilyRef<'a>,
+    stats_cf: ColumnFamilyRef<'a>,
+    graphs_cf: ColumnFamilyRef<'a>,
+    provenance_cf: ColumnFamilyRef<'a>,
+    expirations_cf: ColumnFamilyRef<'a>,
+    /// The words of the string literals followed by the literals, for the full-text search
+    #[cfg(feature = "full-text")]
+    words_cf: ColumnFamilyRef<'a>,
+    indexes: IndexSet,
+    durability: RocksDbDurability,
+    commit_lock: &'a Mutex<()>,
+    /// The log the commits are appended to, if any
+    commit_log: Option<&'a CommitLogWriter>,
+    watchers: &'a Watchers,
+    layout: RocksDbLayout,
+    graph_cfs: &'a RwLock<HashSet<EncodedTerm>>,
+    graph_cf_options: &'a Options,
+    /// The snapshot the reads are done from, if any
+    snapshot: Option<&'a Snapshot<'a>>,
+    /// The column families of the named graphs when the snapshot has been taken
+    snapshot_graph_cfs: Option<&'a HashMap<EncodedTerm, ColumnFamilyRef<'a>>>,
+}
+
+/// Where a part of an index is stored
+#[derive(Clone, Copy)]
+struct IndexLocation<'a> {
+    cf: ColumnFamilyRef<'a>,
+    /// The tag of the index keys in the column families of the named graphs
+    tag: Option<u8>,
+}
+
+/// A permutation index of the quads
+#[derive(Clone, Copy)]
+struct QuadIndex<'a> {
+    cf: ColumnFamilyRef<'a>,
+    /// The tag of the keys in the column families of the named graphs
+    tag: u8,
+    encoding: QuadEncoding,
+    write: fn(&mut Vec<u8>, &EncodedQuad),
+    /// The positions of the key components in (subject, predicate, object, graph name)
+    order: [usize; 4],
 }
 
 impl RocksDbStore {
     /// Opens a `RocksDbStore`
+    ///
+    /// The store maintains approximate statistics about its content used by the query planner
+    /// and the list of its named graphs.
+    ///
+    /// The databases written with an older on-disk format are not opened: the returned error wraps a `StorageError::OutdatedFormat`.
+    /// They should be upgraded first with `migrate`.
+    ///
+    /// The optional indexes chosen by the last call to `open_with_indexes` are maintained, all of them if there was none.
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
-        let mut options = Options::default();
-        options.create_if_missing(true);
-        options.create_missing_column_families(true);
-        options.set_compaction_style(DBCompactionStyle::Universal);
+        Self::do_open(path, None, &RocksDbOptions::default())
+    }
+
+    /// Opens a `RocksDbStore` maintaining only the given optional indexes in addition to the SPOG and POSG ones.
+    ///
+    /// Less indexes mean less writes and less disk space but the patterns not covered by the maintained indexes
+    /// are evaluated by filtering the quads of a less selective index.
+    ///
+    /// The choice is saved in the database and kept by the next calls to `open`.
+    /// The indexes added since the previous opening are built from the SPOG index and the removed ones are deleted.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use oxigraph::store::rocksdb::RocksDbIndex;
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// // A store without the named graph indexes
+    /// let store = RocksDbStore::open_with_indexes("example_indexes.db", &[RocksDbIndex::OSPG])?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone());
+    /// store.insert(&quad)?;
+    ///
+    /// // The patterns on the graph name are still supported
+    /// let results: Result<Vec<Quad>> = store.quads_for_pattern(None, None, None, Some(&ex.into())).collect();
+    /// assert_eq!(vec![quad], results?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_indexes.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn open_with_indexes(path: impl AsRef<Path>, indexes: &[RocksDbIndex]) -> Result<Self> {
+        Self::do_open(path, Some(indexes.into()), &RocksDbOptions::default())
+    }
+
+    /// Opens a `RocksDbStore` with the given RocksDB tuning options.
+    ///
+    /// The defaults of RocksDB are not well suited for big datasets:
+    /// a bigger block cache, bigger write buffers and more background jobs usually help.
+    /// The durability mode allows to trade the safety of the commits for the write throughput.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use oxigraph::store::rocksdb::{RocksDbCompression, RocksDbDurability, RocksDbOptions};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open_with_options(
+    ///     "example_options.db",
+    ///     RocksDbOptions::default()
+    ///         .with_block_cache_size(512 * 1024 * 1024)
+    ///         .with_compression(RocksDbCompression::Lz4)
+    ///         .with_write_buffer_size(128 * 1024 * 1024)
+    ///         .with_block_restart_interval(128)
+    ///         .with_bloom_filter_bits_per_key(16)
+    ///         .with_max_background_jobs(4)
+    ///         .with_durability(RocksDbDurability::Sync),
+    /// )?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    /// store.insert(&quad)?;
+    /// assert!(store.contains(&quad)?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_options.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn open_with_options(path: impl AsRef<Path>, options: RocksDbOptions) -> Result<Self> {
+        Self::do_open(path, None, &options)
+    }
 
-        let new = Self {
-            db: Arc::new(DB::open_cf(&options, path, &COLUMN_FAMILIES)?),
+    fn do_open(
+        path: impl AsRef<Path>,
+        indexes: Option<IndexSet>,
+        options: &RocksDbOptions,
+    ) -> Result<Self> {
+        let (db, lock, graph_cfs) = options.open_db(path.as_ref())?;
+        let mut new = Self {
+            indexes: IndexSet::read(&db)?,
+            layout: RocksDbLayout::read(&db)?.unwrap_or_default(),
+            db: Arc::new(db),
+            lock: Some(Arc::new(lock)),
+            durability: options.durability,
+            isolation: options.isolation,
+            commit_lock: Arc::default(),
+            commit_log: None,
+            watchers: Arc::default(),
+            graph_cfs: Arc::new(RwLock::new(graph_cfs)),
+            graph_cf_options: Arc::new(options.column_family_options()),
         };
+        let handle = new.handle();
+        check_format_version(path.as_ref(), handle.format_version()?)?;
+        // Stamps the new databases
+        handle.set_format_version(LATEST_FORMAT_VERSION)?;
+        if let Some(commit_log) = &options.commit_log {
+            let committed = handle
+                .db
+                .get_pinned_cf(handle.stats_cf, &LOGGED_SEQUENCE_KEY)?
+                .map(|value| decode_statistic(&value));
+            new.commit_log = Some(Arc::new(CommitLogWriter::open(commit_log, committed)?));
+        }
+        if let Some(layout) = options.layout {
+            match RocksDbLayout::read(&new.db)? {
+                Some(saved) if saved != layout => {
+                    return Err(crate::Error::msg(format!(
+                        "The database uses the {:?} layout and not the {:?} one",
+                        saved, layout
+                    )))
+                }
+                Some(_) => (),
+                None => {
+                    if layout != RocksDbLayout::SharedColumnFamilies && !new.handle().is_empty()? {
+                        return Err(crate::Error::msg(format!(
+                            "The {:?} layout could only be chosen for an empty database",
+                            layout
+                        )));
+                    }
+                    new.handle().set_layout(layout)?;
+                    new.layout = layout;
+                }
+            }
+        }
+        if let Some(indexes) = indexes {
+            if indexes != new.indexes {
+                new.handle().change_indexes(indexes)?;
+                new.indexes = indexes;
+            }
+        }
+        new.handle().sync_words_index()?;
 
         let mut transaction = new.handle().auto_transaction();
+        // All the stores contain these strings, there is no need to log them
+        transaction.inner.log = None;
         transaction.set_first_strings()?;
         transaction.commit()?;
 
+        if let Some(interval) = options.expiration_sweep_interval {
+            new.spawn_expiration_sweep(interval);
+        }
+
         Ok(new)
     }
 
+    /// Upgrades a database written with an older on-disk format to the format of this version of Oxigraph.
+    ///
+    /// The missing statistics and named graphs index are computed.
+    /// Nothing is done if the database already uses the latest format.
+    /// It fails with a `StorageError::UnsupportedFormat` if the database has been written by a newer version of Oxigraph.
+    ///
+    /// Usage example:
+    /// ```no_run
+    /// use oxigraph::{Result, RocksDbStore};
+    ///
+    /// RocksDbStore::migrate("old.db")?;
+    /// let store = RocksDbStore::open("old.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn migrate(path: impl AsRef<Path>) -> Result<()> {
+        let (db, lock, graph_cfs) = RocksDbOptions::default().open_db(path.as_ref())?;
+        let store = Self {
+            indexes: IndexSet::read(&db)?,
+            layout: RocksDbLayout::read(&db)?.unwrap_or_default(),
+            db: Arc::new(db),
+            lock: Some(Arc::new(lock)),
+            durability: RocksDbDurability::default(),
+            isolation: RocksDbIsolation::default(),
+            commit_lock: Arc::default(),
+            commit_log: None,
+            watchers: Arc::default(),
+            graph_cfs: Arc::new(RwLock::new(graph_cfs)),
+            graph_cf_options: Arc::default(),
+        };
+        let handle = store.handle();
+        let version = handle.format_version()?;
+        if version > LATEST_FORMAT_VERSION {
+            check_format_version(path.as_ref(), version)?;
+        }
+        handle.migrate(version)
+    }
+
+    /// Opens a `RocksDbStore` in read-only mode.
+    ///
+    /// The database is not locked, so several processes could open it concurrently
+    /// while another one keeps it open for writing. The changes done after the opening are not visible.
+    /// All write operations on the returned store fail.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    ///
+    /// let store = RocksDbStore::open("example_read_only.db")?;
+    /// store.insert(&quad)?;
+    ///
+    /// let read_only = RocksDbStore::open_read_only("example_read_only.db")?;
+    /// assert!(read_only.contains(&quad)?);
+    /// assert!(read_only.remove(&quad).is_err());
+    /// #
+    /// # }
+    /// # remove_dir_all("example_read_only.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self> {
+        let options = Options::default();
+        let graph_cfs = graph_column_families(path.as_ref());
+        let db = DB::open_cf_for_read_only(
+            &options,
+            path.as_ref(),
+            COLUMN_FAMILIES.iter().map(|name| (*name).to_owned()).chain(
+                graph_cfs
+                    .iter()
+                    .map(|graph_name| graph_cf_name(*graph_name)),
+            ),
+            false,
+        )?;
+        let store = Self {
+            indexes: IndexSet::read(&db)?,
+            layout: RocksDbLayout::read(&db)?.unwrap_or_default(),
+            db: Arc::new(db),
+            lock: None,
+            durability: RocksDbDurability::default(),
+            isolation: RocksDbIsolation::default(),
+            commit_lock: Arc::default(),
+            commit_log: None,
+            watchers: Arc::default(),
+            graph_cfs: Arc::new(RwLock::new(graph_cfs)),
+            graph_cf_options: Arc::default(),
+        };
+        check_format_version(path.as_ref(), store.handle().format_version()?)?;
+        Ok(store)
+    }
+
     /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) and returns an object that could be used to execute it.
     ///
     /// See `MemoryStore` for a usage example.
@@ -114,6 +981,21 @@ impl RocksDbStore {
         )?))
     }
 
+    /// This is similar to `prepare_query`, but for a `Query` that has already been parsed or that has been built programmatically.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_parsed_query<'a>(
+        &'a self,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<RocksDbPreparedQuery> {
+        Ok(RocksDbPreparedQuery(SimplePreparedQuery::new_from_query(
+            (*self).clone(),
+            query,
+            options,
+        )?))
+    }
+
     /// This is similar to `prepare_query`, but useful if a SPARQL query has already been parsed, which is the case when building `ServiceHandler`s for federated queries with `SERVICE` clauses. For examples, look in the tests.
     pub fn prepare_query_from_pattern<'a>(
         &'a self,
@@ -127,12 +1009,28 @@ impl RocksDbStore {
         )?))
     }
 
+    /// Prepares a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/) and returns an object that could be used to execute it.
+    ///
+    /// All the operations of the update are executed in a single transaction, each of them seeing the changes of the previous ones.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<RocksDbPreparedUpdate> {
+        Ok(RocksDbPreparedUpdate {
+            store: self.clone(),
+            update: SimplePreparedUpdate::new(update, options)?,
+        })
+    }
+
     /// Retrieves quads with a filter on each quad component
     ///
     /// See `MemoryStore` for a usage example.
     pub fn quads_for_pattern<'a>(
         &'a self,
-        subject: Option<&NamedOrBlankNode>,
+        subject: Option<&Subject>,
         predicate: Option<&NamedNode>,
         object: Option<&Term>,
         graph_name: Option<&GraphName>,
@@ -155,19 +1053,222 @@ impl RocksDbStore {
         self.handle().contains(&quad)
     }
 
+    /// Returns the number of quads in the store
+    ///
+    /// It is read from the counters maintained by the store, so the quads are not counted one by one.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_len.db")?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None))?;
+    /// store.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone()))?;
+    ///
+    /// assert_eq!(store.len()?, 2);
+    /// assert_eq!(store.len_graph(&GraphName::DefaultGraph)?, 1);
+    /// assert!(!store.is_empty()?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_len.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn len(&self) -> Result<usize> {
+        Ok(self.handle().len()? as usize)
+    }
+
+    /// Returns if the store is empty
+    pub fn is_empty(&self) -> Result<bool> {
+        self.handle().is_empty()
+    }
+
+    /// Returns the number of quads in a given graph
+    pub fn len_graph(&self, graph_name: &GraphName) -> Result<usize> {
+        Ok(self
+            .handle()
+            .statistic(GRAPH_QUADS_COUNT, graph_name.into())? as usize)
+    }
+
+    /// Returns statistics about the content of the store.
+    ///
+    /// The distinct terms are counted by jumping from one term to the next one in the indexes, so one read per distinct term is done.
+    /// If the OSPG index is not maintained, the objects are collected in memory from the POSG index.
+    /// The named graphs and the string store are fully read.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn stats(&self) -> Result<StoreStats> {
+        self.handle().stats()
+    }
+
+    /// Returns a read-only view of the store frozen at the time of the call.
+    ///
+    /// The writes done after the snapshot creation are not visible from it,
+    /// allowing long-running exports and queries to see a consistent dataset while the store is updated.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use oxigraph::sparql::{QueryOptions, QueryResult};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_snapshot.db")?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    ///
+    /// let snapshot = store.snapshot();
+    /// store.insert(&quad)?;
+    /// assert!(store.contains(&quad)?);
+    /// assert!(!snapshot.contains(&quad)?);
+    ///
+    /// // SPARQL query
+    /// let prepared_query = snapshot.prepare_query("ASK { ?s ?p ?o }", QueryOptions::default())?;
+    /// let result = prepared_query.exec()?;
+    /// if let QueryResult::Boolean(result) = result {
+    ///     assert!(!result);
+    /// }
+    /// #
+    /// # }
+    /// # remove_dir_all("example_snapshot.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn snapshot(&self) -> RocksDbSnapshot<'_> {
+        if self.layout == RocksDbLayout::SharedColumnFamilies {
+            return RocksDbSnapshot {
+                store: self,
+                snapshot: Arc::new(self.db.snapshot()),
+                graph_cfs: HashMap::default(),
+            };
+        }
+        // The column families could not be dropped before their handles are taken
+        let graph_cfs = read_graph_cfs(&self.graph_cfs);
+        let snapshot = self.db.snapshot();
+        let graph_cfs = graph_cfs
+            .iter()
+            .filter_map(|graph_name| {
+                Some((*graph_name, self.db.cf_handle(&graph_cf_name(*graph_name))?))
+            })
+            .collect();
+        RocksDbSnapshot {
+            store: self,
+            snapshot: Arc::new(snapshot),
+            graph_cfs,
+        }
+    }
+
     /// Executes a transaction.
     ///
     /// The transaction is executed if the given closure returns `Ok`.
     /// Nothing is done if the closure returns `Err`.
+    /// The changes are written in a single atomic batch.
+    ///
+    /// The transaction is isolated from the concurrent writes following the `RocksDbIsolation` set in the options.
+    /// With the default snapshot isolation, it fails with `StorageError::TransactionConflict`
+    /// if a quad it writes has been changed by another write since its beginning. It could then be retried.
     ///
     /// See `MemoryStore` for a usage example.
     pub fn transaction<'a>(
         &'a self,
         f: impl FnOnce(&mut RocksDbTransaction<'a>) -> Result<()>,
     ) -> Result<()> {
-        let mut transaction = self.handle().transaction();
-        f(&mut transaction)?;
-        transaction.commit()
+        if self.try_transaction(f)? {
+            Ok(())
+        } else {
+            Err(StorageError::TransactionConflict.into())
+        }
+    }
+
+    /// Executes an optimistic transaction, retrying it up to `max_retries` times if it conflicts with a concurrent write.
+    ///
+    /// The transaction is executed with the snapshot isolation whatever the `RocksDbIsolation` set in the options:
+    /// it does not block the other writes and its commit fails if a quad or a named graph it writes
+    /// has been changed by another write since its beginning.
+    /// The closure is then called again on a new snapshot. It should read the store using `RocksDbTransaction::snapshot`.
+    ///
+    /// It fails with `StorageError::TransactionConflict` if the transaction still conflicts after the retries.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{RocksDbStore, Result};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example.db")?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let counter = Quad::new(ex.clone(), ex.clone(), Literal::from(0), None);
+    /// store.insert(&counter)?;
+    ///
+    /// // Increments the counter
+    /// store.optimistic_transaction(10, |transaction| {
+    ///     let old = transaction
+    ///         .snapshot()
+    ///         .unwrap()
+    ///         .quads_for_pattern(Some(&ex.clone().into()), Some(&ex), None, None)
+    ///         .next()
+    ///         .unwrap()?;
+    ///     let value = match &old.object {
+    ///         Term::Literal(value) => value.value().parse::<i64>().unwrap(),
+    ///         _ => 0,
+    ///     };
+    ///     transaction.remove(&old)?;
+    ///     transaction.insert(&Quad::new(ex.clone(), ex.clone(), Literal::from(value + 1), None))
+    /// })?;
+    /// assert!(store.contains(&Quad::new(ex.clone(), ex.clone(), Literal::from(1), None))?);
+    /// # };
+    /// #
+    /// # remove_dir_all("example.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn optimistic_transaction<'a>(
+        &'a self,
+        max_retries: usize,
+        mut f: impl FnMut(&mut RocksDbTransaction<'a>) -> Result<()>,
+    ) -> Result<()> {
+        for _ in 0..=max_retries {
+            if self.try_transaction_with_isolation(RocksDbIsolation::Snapshot, &mut f)? {
+                return Ok(());
+            }
+        }
+        Err(StorageError::TransactionConflict.into())
+    }
+
+    /// Executes a transaction and returns `false` if it has not been committed because of a conflict
+    fn try_transaction<'a>(
+        &'a self,
+        f: impl FnOnce(&mut RocksDbTransaction<'a>) -> Result<()>,
+    ) -> Result<bool> {
+        self.try_transaction_with_isolation(self.isolation, f)
+    }
+
+    fn try_transaction_with_isolation<'a>(
+        &'a self,
+        isolation: RocksDbIsolation,
+        f: impl FnOnce(&mut RocksDbTransaction<'a>) -> Result<()>,
+    ) -> Result<bool> {
+        let mut transaction = self.handle().transaction(self);
+        match isolation {
+            RocksDbIsolation::ReadCommitted => {
+                f(&mut transaction)?;
+                transaction.commit()
+            }
+            RocksDbIsolation::Snapshot => {
+                transaction.snapshot = Some(self.snapshot());
+                f(&mut transaction)?;
+                transaction.commit()
+            }
+            RocksDbIsolation::Serializable => {
+                let _lock = self.handle().lock_commits();
+                f(&mut transaction)?;
+                transaction.inner.write_batch_locked()?;
+                Ok(true)
+            }
+        }
     }
 
     /// Loads a graph file (i.e. triples) into the store
@@ -205,6 +1306,422 @@ impl RocksDbStore {
         transaction.commit()
     }
 
+    /// Loads a dataset file (i.e. quads) into the store using a write path optimized for big imports.
+    ///
+    /// The quads are written in big sorted batches that bypass the write-ahead log
+    /// and the statistics used by the query planner are rebuilt from the store content at the end.
+    /// It is much faster than `load_dataset` but it is only meant for initial imports:
+    /// rebuilding the statistics scans the full store and, if the process crashes during the load,
+    /// an arbitrary part of the file may be written.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{DatasetSyntax, Result, RocksDbStore};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_bulk_load.db")?;
+    ///
+    /// // insertion
+    /// let file = b"<http://example.com> <http://example.com> <http://example.com> <http://example.com> .";
+    /// store.bulk_load_dataset(file.as_ref(), DatasetSyntax::NQuads, None)?;
+    ///
+    /// // quad filter
+    /// let results: Result<Vec<Quad>> = store.quads_for_pattern(None, None, None, None).collect();
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// assert_eq!(vec![Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(ex.into()))], results?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_bulk_load.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn bulk_load_dataset(
+        &self,
+        reader: impl BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        if self.commit_log.is_some() {
+            return Err(crate::Error::msg(
+                "The bulk loads could not be written to the commit log",
+            ));
+        }
+        let mut loader = self.handle().bulk_loader();
+        load_dataset(&mut loader, reader, syntax, base_iri)?;
+        loader.finish()
+    }
+
+    /// Computes again the statistics used by the query planner and compacts all the database files.
+    ///
+    /// RocksDB compacts its files in background while it is written, so calling this method is never required.
+    /// It is useful after a big import like `bulk_load_dataset` in order to get the best read performances right away.
+    /// It rewrites all the database files, so it takes a long time and needs free disk space for a copy of the database.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::{DatasetSyntax, Result, RocksDbStore};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_optimize.db")?;
+    /// let file = b"<http://example.com> <http://example.com> <http://example.com> <http://example.com> .";
+    /// store.bulk_load_dataset(file.as_ref(), DatasetSyntax::NQuads, None)?;
+    /// store.optimize()?;
+    /// assert_eq!(store.len()?, 1);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_optimize.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn optimize(&self) -> Result<()> {
+        self.handle().optimize()
+    }
+
+    /// Returns the named graphs of the store, including the ones without quads.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_named_graphs.db")?;
+    /// let ex = NamedOrBlankNode::from(NamedNode::new("http://example.com")?);
+    ///
+    /// // an empty named graph
+    /// store.insert_named_graph(&ex)?;
+    /// assert_eq!(vec![ex.clone()], store.named_graphs().collect::<Result<Vec<_>>>()?);
+    ///
+    /// // graph removal
+    /// store.remove_named_graph(&ex)?;
+    /// assert!(!store.contains_named_graph(&ex)?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_named_graphs.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn named_graphs(&self) -> impl Iterator<Item = Result<NamedOrBlankNode>> + '_ {
+        self.handle()
+            .named_graphs()
+            .map(move |graph_name| self.decode_named_or_blank_node(graph_name?))
+    }
+
+    /// Checks if the store contains a given named graph, even if it does not contain any quad
+    ///
+    /// See `named_graphs` for a usage example.
+    pub fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        self.handle().contains_named_graph(graph_name.into())
+    }
+
+    /// Adds a named graph to the store, even if it does not contain any quad
+    ///
+    /// See `named_graphs` for a usage example.
+    pub fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        let mut transaction = self.handle().auto_transaction();
+        let graph_name = transaction.encode_named_or_blank_node(graph_name)?;
+        transaction.insert_encoded_named_graph(graph_name)?;
+        transaction.commit()
+    }
+
+    /// Removes all the quads of a graph. The graph itself stays in the store.
+    ///
+    /// Warning: The quads are removed in batch. If the removal fails in the middle,
+    /// only a part of them may be removed. Use a transaction if you do not want that.
+    ///
+    /// With the `RocksDbLayout::ColumnFamilyPerGraph` layout, the column family of a named graph is dropped at once instead.
+    pub fn clear_graph(&self, graph_name: &GraphName) -> Result<()> {
+        let mut transaction = self.handle().auto_transaction();
+        transaction.clear_encoded_graph(graph_name.into())?;
+        transaction.commit()
+    }
+
+    /// Removes a named graph and all its quads from the store
+    ///
+    /// Warning: The quads are removed in batch. If the removal fails in the middle,
+    /// only a part of them may be removed. Use a transaction if you do not want that.
+    /// With the `RocksDbLayout::ColumnFamilyPerGraph` layout, the column family of the graph is dropped at once instead.
+    ///
+    /// See `named_graphs` for a usage example.
+    pub fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        let mut transaction = self.handle().auto_transaction();
+        transaction.remove_encoded_named_graph(graph_name.into())?;
+        transaction.commit()
+    }
+
+    /// Sets the time after which a named graph is removed from the store, or keeps it forever if `expiration` is `None`.
+    ///
+    /// It is useful to cache remote data in the store.
+    /// The expired graphs are removed by `remove_expired_graphs`, called periodically
+    /// if the store has been opened with `RocksDbOptions::with_expiration_sweep_interval`.
+    /// The expiration is forgotten when the graph is removed.
+    /// Like the other writes, the change is appended to the commit log, if any.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use std::time::{Duration, SystemTime};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_expiration.db")?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone()))?;
+    ///
+    /// let expiration = SystemTime::now() - Duration::from_secs(1);
+    /// store.set_graph_expiration(&ex.clone().into(), Some(expiration))?;
+    /// assert_eq!(store.graph_expiration(&ex.clone().into())?, Some(expiration));
+    ///
+    /// assert_eq!(store.remove_expired_graphs()?, 1);
+    /// assert!(!store.contains_named_graph(&ex.into())?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_expiration.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn set_graph_expiration(
+        &self,
+        graph_name: &NamedOrBlankNode,
+        expiration: Option<SystemTime>,
+    ) -> Result<()> {
+        let mut transaction = self.handle().auto_transaction();
+        transaction
+            .inner
+            .set_graph_expiration(graph_name.into(), expiration)?;
+        transaction.commit()
+    }
+
+    /// Returns the time after which a named graph is removed from the store, if any.
+    ///
+    /// See `set_graph_expiration` for a usage example.
+    pub fn graph_expiration(&self, graph_name: &NamedOrBlankNode) -> Result<Option<SystemTime>> {
+        let handle = self.handle();
+        handle
+            .db
+            .get_pinned_cf(handle.expirations_cf, &encode_term(graph_name.into()))?
+            .map(|value| read_timestamp(&mut value.as_ref()))
+            .transpose()
+    }
+
+    /// Removes the named graphs whose expiration time is passed and returns their number.
+    ///
+    /// Each graph is removed like with `remove_named_graph`.
+    ///
+    /// See `set_graph_expiration` for a usage example.
+    pub fn remove_expired_graphs(&self) -> Result<usize> {
+        let handle = self.handle();
+        let now = SystemTime::now();
+        let mut expired = Vec::default();
+        let mut iter = handle.db.raw_iterator_cf(handle.expirations_cf);
+        iter.seek_to_first();
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if read_timestamp(&mut &value[..])? <= now {
+                expired.push(Cursor::new(key).read_term()?);
+            }
+            iter.next();
+        }
+        iter.status()?;
+        for graph_name in &expired {
+            let mut transaction = handle.auto_transaction();
+            transaction.remove_encoded_named_graph(*graph_name)?;
+            transaction.commit()?;
+        }
+        Ok(expired.len())
+    }
+
+    /// Calls `remove_expired_graphs` every `interval` until the store is closed
+    fn spawn_expiration_sweep(&self, interval: Duration) {
+        let db = Arc::downgrade(&self.db);
+        let lock = self.lock.as_ref().map(Arc::downgrade);
+        let indexes = self.indexes;
+        let layout = self.layout;
+        let durability = self.durability;
+        let isolation = self.isolation;
+        let commit_lock = self.commit_lock.clone();
+        let commit_log = self.commit_log.clone();
+        let watchers = self.watchers.clone();
+        let graph_cfs = self.graph_cfs.clone();
+        let graph_cf_options = self.graph_cf_options.clone();
+        spawn(move || loop {
+            sleep(interval);
+            // The lock is upgraded first in order to not be released while the database is still open
+            let lock = lock.as_ref().and_then(Weak::upgrade);
+            let store = match db.upgrade() {
+                Some(db) => RocksDbStore {
+                    db,
+                    lock,
+                    indexes,
+                    durability,
+                    isolation,
+                    commit_lock: commit_lock.clone(),
+                    commit_log: commit_log.clone(),
+                    watchers: watchers.clone(),
+                    layout,
+                    graph_cfs: graph_cfs.clone(),
+                    graph_cf_options: graph_cf_options.clone(),
+                },
+                None => return, // The store is closed
+            };
+            // The graphs that failed to be removed are retried at the next sweep
+            let _ = store.remove_expired_graphs();
+        });
+    }
+
+    /// Adds the quads of the graph `from` to the graph `to`, like the SPARQL `ADD` operation.
+    ///
+    /// The changes are written in a single atomic batch.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn add_graph(&self, from: &GraphName, to: &GraphName) -> Result<()> {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            add_encoded_graph(self, transaction, from.into(), to)
+        })
+    }
+
+    /// Replaces the content of the graph `to` by the one of the graph `from`, like the SPARQL `COPY` operation.
+    ///
+    /// The changes are written in a single atomic batch.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn copy_graph(&self, from: &GraphName, to: &GraphName) -> Result<()> {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            copy_encoded_graph(self, transaction, from.into(), to)
+        })
+    }
+
+    /// Moves the content of the graph `from` into the graph `to`, like the SPARQL `MOVE` operation.
+    ///
+    /// The graph `from` is removed from the store, or only cleared if it is the default graph.
+    /// The changes are written in a single atomic batch.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn move_graph(&self, from: &GraphName, to: &GraphName) -> Result<()> {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            move_encoded_graph(self, transaction, from.into(), to)
+        })
+    }
+
+    /// Cross-checks the indexes and the string store and returns the inconsistencies found.
+    ///
+    /// All the indexes are read, so it is slow on big databases.
+    /// It is useful after a crash or a disk failure.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_validate.db")?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// store.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None))?;
+    /// assert!(store.validate()?.is_valid());
+    /// #
+    /// # }
+    /// # remove_dir_all("example_validate.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn validate(&self) -> Result<ValidationReport> {
+        self.handle().validate(false)
+    }
+
+    /// Does the same checks as `validate` and repairs the inconsistencies found.
+    ///
+    /// The quads missing from some indexes are added back to them.
+    /// The quads using strings missing from the string store are removed from all the indexes.
+    /// The missing named graphs are added and the statistics are computed again.
+    /// The repairs are not sent to the commit log or to the watchers.
+    ///
+    /// The returned report describes the inconsistencies found before the repair.
+    pub fn validate_and_repair(&self) -> Result<ValidationReport> {
+        self.handle().validate(true)
+    }
+
+    /// Creates a new backup of the store in the `backup_path` directory using the RocksDB [backup engine](https://github.com/facebook/rocksdb/wiki/How-to-backup-RocksDB).
+    ///
+    /// The store could still be read and written during the backup.
+    /// The backups are incremental: the files already saved in `backup_path` by a previous backup are not copied again.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    /// {
+    ///     let store = RocksDbStore::open("example_backup.db")?;
+    ///     store.insert(&quad)?;
+    ///     store.backup("example_backup.backup")?;
+    /// }
+    ///
+    /// let restored = RocksDbStore::restore("example_backup.backup", "example_restored.db")?;
+    /// assert!(restored.contains(&quad)?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_backup.db")?;
+    /// # remove_dir_all("example_backup.backup")?;
+    /// # remove_dir_all("example_restored.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn backup(&self, backup_path: impl AsRef<Path>) -> Result<()> {
+        let mut engine = BackupEngine::open(&BackupEngineOptions::default(), backup_path)?;
+        engine.create_new_backup(&self.db)?;
+        Ok(())
+    }
+
+    /// Restores the latest backup saved in the `backup_path` directory by `backup` into `path` and opens it.
+    ///
+    /// The content of the database at `path` is replaced by the backup one.
+    /// It should not be opened while restoring.
+    ///
+    /// See `backup` for a usage example.
+    pub fn restore(backup_path: impl AsRef<Path>, path: impl AsRef<Path>) -> Result<Self> {
+        let mut engine = BackupEngine::open(&BackupEngineOptions::default(), backup_path)?;
+        engine.restore_from_latest_backup(&path, &path, &RestoreOptions::default())?;
+        Self::open(path)
+    }
+
+    /// Dumps a store graph into a file.
+    ///
+    /// The triples are written while they are read from the store without loading the graph in memory.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_graph(
+        &self,
+        writer: impl Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        dump_graph(
+            self.quads_for_pattern(None, None, None, Some(from_graph_name))
+                .map(|quad| Ok(quad?.into())),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Dumps the store dataset into a file.
+    ///
+    /// The quads are written while they are read from the store without loading the dataset in memory.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_dataset(&self, writer: impl Write, syntax: DatasetSyntax) -> Result<()> {
+        dump_dataset(
+            self.quads_for_pattern(None, None, None, None),
+            writer,
+            syntax,
+        )
+    }
+
     /// Adds a quad to this store.
     pub fn insert(&self, quad: &Quad) -> Result<()> {
         let mut transaction = self.handle().auto_transaction();
@@ -213,6 +1730,218 @@ impl RocksDbStore {
         transaction.commit()
     }
 
+    /// Adds a quad to this store with some provenance metadata.
+    ///
+    /// The metadata replaces the one previously attached to the quad, if any.
+    /// It is removed with the quad.
+    /// The provenance metadata is not written to the commit log, so it is not replicated.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use oxigraph::store::rocksdb::QuadProvenance;
+    /// use std::time::SystemTime;
+    /// # use std::fs::remove_dir_all;
+    ///
+    /// # {
+    /// let store = RocksDbStore::open("example_provenance.db")?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    /// let provenance = QuadProvenance {
+    ///     source: Some("http://example.com/data.ttl".to_owned()),
+    ///     timestamp: Some(SystemTime::now()),
+    ///     agent: Some("importer".to_owned()),
+    /// };
+    /// store.insert_with_provenance(&quad, &provenance)?;
+    /// assert_eq!(store.provenance(&quad)?, Some(provenance.clone()));
+    ///
+    /// let results: Result<Vec<_>> = store.quads_with_provenance_for_pattern(None, None, None, None).collect();
+    /// assert_eq!(results?, vec![(quad.clone(), Some(provenance))]);
+    ///
+    /// store.remove(&quad)?;
+    /// assert_eq!(store.provenance(&quad)?, None);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_provenance.db")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn insert_with_provenance(&self, quad: &Quad, provenance: &QuadProvenance) -> Result<()> {
+        let mut transaction = self.handle().auto_transaction();
+        let quad = transaction.encode_quad(quad)?;
+        transaction.insert_encoded(&quad)?;
+        transaction.inner.set_provenance(&quad, provenance)?;
+        transaction.commit()
+    }
+
+    /// Returns the provenance metadata attached to a quad of the store, if any.
+    ///
+    /// See `insert_with_provenance` for a usage example.
+    pub fn provenance(&self, quad: &Quad) -> Result<Option<QuadProvenance>> {
+        self.handle().provenance(&quad.into())
+    }
+
+    /// Retrieves the quads matching a pattern with their provenance metadata, if any.
+    ///
+    /// See `insert_with_provenance` for a usage example.
+    pub fn quads_with_provenance_for_pattern<'a>(
+        &'a self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> impl Iterator<Item = Result<(Quad, Option<QuadProvenance>)>> + 'a
+    where
+        Self: 'a,
+    {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        let handle = self.handle();
+        self.handle()
+            .encoded_quads_for_pattern(subject, predicate, object, graph_name)
+            .map(move |quad| {
+                let quad = quad?;
+                Ok((self.decode_quad(&quad)?, handle.provenance(&quad)?))
+            })
+    }
+
+    /// Adds quads to this store.
+    ///
+    /// The quads are written in batches of about a thousand changes instead of one batch per quad.
+    ///
+    /// Warning: If an error happens in the middle of the insertion,
+    /// only a part of the quads may be written. Use a (memory greedy) transaction if you do not want that.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn insert_all(&self, quads: impl IntoIterator<Item = Quad>) -> Result<()> {
+        let mut transaction = self.handle().auto_transaction();
+        for quad in quads {
+            let quad = transaction.encode_quad(&quad)?;
+            transaction.insert_encoded(&quad)?;
+        }
+        transaction.commit()
+    }
+
+    /// Adds all the quads and named graphs of another store to this store.
+    ///
+    /// The quads are streamed from the other store without decoding their terms, only the strings they use are copied.
+    /// They are written in batches of about a thousand changes.
+    ///
+    /// Warning: If an error happens in the middle of the merge,
+    /// only a part of the quads may be written. Use a (memory greedy) transaction if you do not want that.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn merge_from(&self, other: &impl MergeSource) -> Result<()> {
+        let mut transaction = self.handle().auto_transaction();
+        merge_encoded(other, &mut transaction)?;
+        transaction.commit()
+    }
+
+    /// Applies the commits of a log written by a store opened with `RocksDbOptions::with_commit_log`
+    /// that have not been applied yet. Returns the number of applied commits.
+    ///
+    /// Each commit is applied atomically with the position of this store in the log,
+    /// so the replication resumes where it stopped, even after a crash.
+    /// The log could be appended to concurrently: only the completely written commits are applied.
+    /// The store should not be modified by other means in order to stay a copy of the logging store.
+    /// It fails if the commits following the last one applied have been removed by `RocksDbStore::truncate_commit_log`.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use oxigraph::store::rocksdb::RocksDbOptions;
+    /// # use std::fs::{remove_dir_all, remove_file};
+    ///
+    /// # {
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    ///
+    /// let primary = RocksDbStore::open_with_options(
+    ///     "example_primary.db",
+    ///     RocksDbOptions::default().with_commit_log("example_primary.log"),
+    /// )?;
+    /// primary.insert(&quad)?;
+    ///
+    /// let replica = RocksDbStore::open("example_replica.db")?;
+    /// replica.replicate("example_primary.log")?;
+    /// assert!(replica.contains(&quad)?);
+    /// #
+    /// # }
+    /// # remove_dir_all("example_primary.db")?;
+    /// # remove_dir_all("example_replica.db")?;
+    /// # remove_file("example_primary.log")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn replicate(&self, commit_log: impl AsRef<Path>) -> Result<usize> {
+        let handle = self.handle();
+        let after = self.replicated_sequence()?;
+        let mut count = 0;
+        for record in CommitLogReader::new(BufReader::new(File::open(commit_log)?), after) {
+            let (sequence, record) = record?;
+            let mut transaction = handle.transaction(self);
+            record.apply(&mut transaction)?;
+            transaction.inner.batch.put_cf(
+                handle.stats_cf,
+                &REPLICATED_SEQUENCE_KEY,
+                &sequence.to_be_bytes(),
+            );
+            transaction.commit()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Returns the sequence number of the last commit applied by `replicate`, 0 if there is none.
+    pub fn replicated_sequence(&self) -> Result<u64> {
+        let handle = self.handle();
+        Ok(handle
+            .db
+            .get_pinned_cf(handle.stats_cf, &REPLICATED_SEQUENCE_KEY)?
+            .map_or(0, |value| decode_statistic(&value)))
+    }
+
+    /// Removes from the commit log of this store the commits with a sequence number lower or equal to `up_to`,
+    /// for example the ones all the replicas have applied according to `RocksDbStore::replicated_sequence`.
+    ///
+    /// The replicas that have not applied these commits yet fail to replicate from the truncated log.
+    /// It fails if the store has not been opened with `RocksDbOptions::with_commit_log`.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, RocksDbStore};
+    /// use oxigraph::store::rocksdb::RocksDbOptions;
+    /// # use std::fs::{remove_dir_all, remove_file};
+    ///
+    /// # {
+    /// let ex = NamedNode::new("http://example.com")?;
+    ///
+    /// let primary = RocksDbStore::open_with_options(
+    ///     "example_truncate_primary.db",
+    ///     RocksDbOptions::default().with_commit_log("example_truncate_primary.log"),
+    /// )?;
+    /// primary.insert(&Quad::new(ex.clone(), ex.clone(), ex.clone(), None))?;
+    ///
+    /// let replica = RocksDbStore::open("example_truncate_replica.db")?;
+    /// replica.replicate("example_truncate_primary.log")?;
+    /// primary.truncate_commit_log(replica.replicated_sequence()?)?;
+    /// #
+    /// # }
+    /// # remove_dir_all("example_truncate_primary.db")?;
+    /// # remove_dir_all("example_truncate_replica.db")?;
+    /// # remove_file("example_truncate_primary.log")?;
+    /// # Result::Ok(())
+    /// ```
+    pub fn truncate_commit_log(&self, up_to: u64) -> Result<()> {
+        self.commit_log
+            .as_ref()
+            .ok_or_else(|| crate::Error::msg("The store has no commit log"))?
+            .truncate(up_to)
+    }
+
     /// Removes a quad from this store.
     pub fn remove(&self, quad: &Quad) -> Result<()> {
         let mut transaction = self.handle().auto_transaction();
@@ -221,6 +1950,44 @@ impl RocksDbStore {
         transaction.commit()
     }
 
+    /// Removes all the quads matching a pattern from this store.
+    ///
+    /// The removal is done in a single transaction: the other readers never see a part of it.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn remove_quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Result<()> {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        let handle = self.handle();
+        let mut transaction = handle.transaction(self);
+        for quad in handle.encoded_quads_for_pattern(subject, predicate, object, graph_name) {
+            transaction.inner.remove(&quad?)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Subscribes to the changes of the store.
+    ///
+    /// Each message received contains the changes of an atomic write to the store, in the order they have been applied.
+    /// The operations that are not done in a transaction, like `load_graph` or `insert_all`,
+    /// are written in batches of about a thousand changes, each of them being reported in its own message.
+    /// The changes done by `bulk_load_dataset` are not reported.
+    /// The subscription ends when the receiver is dropped.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn watch(&self) -> Receiver<Vec<QuadEvent>> {
+        self.watchers.watch()
+    }
+
     fn handle(&self) -> RocksDbStoreHandle<'_> {
         RocksDbStoreHandle {
             db: &self.db,
@@ -231,18 +1998,90 @@ impl RocksDbStore {
             gspo_cf: get_cf(&self.db, GSPO_CF),
             gpos_cf: get_cf(&self.db, GPOS_CF),
             gosp_cf: get_cf(&self.db, GOSP_CF),
+            stats_cf: get_cf(&self.db, STATS_CF),
+            graphs_cf: get_cf(&self.db, GRAPHS_CF),
+            provenance_cf: get_cf(&self.db, PROVENANCE_CF),
+            expirations_cf: get_cf(&self.db, EXPIRATIONS_CF),
+            #[cfg(feature = "full-text")]
+            words_cf: get_cf(&self.db, WORDS_CF),
+            indexes: self.indexes,
+            durability: self.durability,
+            commit_lock: &self.commit_lock,
+            commit_log: self.commit_log.as_deref(),
+            watchers: &self.watchers,
+            layout: self.layout,
+            graph_cfs: &self.graph_cfs,
+            graph_cf_options: &self.graph_cf_options,
+            snapshot: None,
+            snapshot_graph_cfs: None,
         }
     }
 }
 
-impl StrLookup for RocksDbStore {
-    fn get_str(&self, id: StrHash) -> Result<Option<String>> {
+impl sealed::MergeSource for RocksDbStore {
+    fn encoded_quads_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + '_> {
+        self.encoded_quads_for_pattern(None, None, None, None)
+    }
+
+    fn encoded_named_graphs_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + '_> {
+        self.encoded_named_graphs()
+    }
+}
+
+impl StrLookup for RocksDbStoreHandle<'_> {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
         Ok(self
-            .db
-            .get_cf(get_cf(&self.db, ID2STR_CF), &id.to_be_bytes())?
+            .get(self.id2str_cf, &id.to_be_bytes())?
             .map(String::from_utf8)
             .transpose()?)
     }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        if self.snapshot.is_some() {
+            return Ok(self.get_stored_str(id)?.map(Arc::from));
+        }
+        // The string is copied only once, from the RocksDB buffer to the shared one
+        Ok(self
+            .db
+            .get_pinned_cf(self.id2str_cf, &id.to_be_bytes())?
+            .map(|value| str::from_utf8(&value).map(Arc::from))
+            .transpose()
+            .map_err(crate::Error::wrap)?)
+    }
+}
+
+impl StrLookup for RocksDbStore {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        self.handle().get_stored_str(id)
+    }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        self.handle().get_stored_shared_str(id)
+    }
+}
+
+impl StoreStatistics for RocksDbStore {
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        let handle = self.handle();
+        Some(PredicateStatistics {
+            quads_count: handle.statistic(PREDICATE_QUADS_COUNT, predicate).ok()?,
+            distinct_subjects_count: handle
+                .statistic(PREDICATE_DISTINCT_SUBJECTS_COUNT, predicate)
+                .ok()?,
+            distinct_objects_count: handle
+                .statistic(PREDICATE_DISTINCT_OBJECTS_COUNT, predicate)
+                .ok()?,
+        })
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        self.handle().statistic(GRAPH_QUADS_COUNT, graph_name).ok()
+    }
+
+    #[cfg(feature = "full-text")]
+    fn literals_matching_count(&self, query: &str) -> Option<u64> {
+        self.handle().literals_matching_count(query).ok()?
+    }
 }
 
 impl ReadableEncodedStore for RocksDbStore {
@@ -258,15 +2097,75 @@ impl ReadableEncodedStore for RocksDbStore {
                 .encoded_quads_for_pattern(subject, predicate, object, graph_name),
         )
     }
+
+    fn encoded_named_graphs<'a>(&'a self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        Box::new(self.handle().named_graphs())
+    }
+
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        self.handle().contains_named_graph(graph_name)
+    }
+
+    #[cfg(feature = "full-text")]
+    fn encoded_literals_matching<'a>(
+        &'a self,
+        query: &str,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        match self.handle().literals_matching(query) {
+            Ok(Some(literals)) => literals,
+            // The stores opened in read-only mode are not always indexed
+            Ok(None) => scan_literals_matching(self, query),
+            Err(error) => Box::new(once(Err(error))),
+        }
+    }
 }
 
 impl<'a> RocksDbStoreHandle<'a> {
-    fn transaction(&self) -> RocksDbTransaction<'a> {
+    /// Prevents the other transactions to commit until the returned guard is dropped
+    fn lock_commits(&self) -> MutexGuard<'a, ()> {
+        self.commit_lock
+            .lock()
+            .expect("the RocksDB commit mutex has been poisoned because of a panic")
+    }
+
+    /// Writes a batch following the durability mode of the store
+    fn write(&self, batch: WriteBatch) -> Result<()> {
+        let mut options = WriteOptions::default();
+        match self.durability {
+            RocksDbDurability::Sync => options.set_sync(true),
+            RocksDbDurability::Wal => (),
+            RocksDbDurability::NoWal => options.disable_wal(true),
+        }
+        self.db.write_opt(batch, &options)?;
+        Ok(())
+    }
+
+    fn transaction(&self, store: &'a RocksDbStore) -> RocksDbTransaction<'a> {
         RocksDbTransaction {
+            store,
+            changes: PendingChanges::default(),
+            snapshot: None,
+            written: HashSet::default(),
+            written_graphs: HashSet::default(),
+            cleared_graphs: HashSet::default(),
             inner: RocksDbInnerTransaction {
                 handle: self.clone(),
                 batch: WriteBatch::default(),
                 buffer: Vec::default(),
+                statistics: HashMap::default(),
+                pending: HashMap::default(),
+                pending_subject_predicates: HashMap::default(),
+                pending_predicate_objects: HashMap::default(),
+                log: self.commit_log.map(|_| CommitLogRecord::default()),
+                events: if self.watchers.is_watched() {
+                    Some(Vec::default())
+                } else {
+                    None
+                },
+                #[cfg(feature = "full-text")]
+                strings: HashMap::default(),
+                graph_writes: Vec::default(),
+                cleared_graphs: HashSet::default(),
             },
         }
     }
@@ -277,29 +2176,947 @@ impl<'a> RocksDbStoreHandle<'a> {
                 handle: self.clone(),
                 batch: WriteBatch::default(),
                 buffer: Vec::default(),
+                statistics: HashMap::default(),
+                pending: HashMap::default(),
+                pending_subject_predicates: HashMap::default(),
+                pending_predicate_objects: HashMap::default(),
+                log: self.commit_log.map(|_| CommitLogRecord::default()),
+                events: if self.watchers.is_watched() {
+                    Some(Vec::default())
+                } else {
+                    None
+                },
+                #[cfg(feature = "full-text")]
+                strings: HashMap::default(),
+                graph_writes: Vec::default(),
+                cleared_graphs: HashSet::default(),
             },
         }
     }
 
-    fn contains(&self, quad: &EncodedQuad) -> Result<bool> {
-        let mut buffer = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
-        write_spog_quad(&mut buffer, quad);
-        Ok(self.db.get_pinned_cf(self.spog_cf, &buffer)?.is_some())
+    fn bulk_loader(&self) -> RocksDbBulkLoader<'a> {
+        RocksDbBulkLoader {
+            handle: self.clone(),
+            id2str: HashMap::default(),
+            quads: Vec::default(),
+        }
     }
 
-    fn encoded_quads_for_pattern(
-        &self,
-        subject: Option<EncodedTerm>,
-        predicate: Option<EncodedTerm>,
+    /// Reads a value from the snapshot of the handle, if any, or from the latest state of the database
+    fn get(&self, cf: ColumnFamilyRef<'a>, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(if let Some(snapshot) = self.snapshot {
+            snapshot.get_cf_opt(cf, key, ReadOptions::default())?
+        } else {
+            self.db.get_cf(cf, key)?
+        })
+    }
+
+    /// Checks if a key is in the snapshot of the handle, if any, or in the latest state of the database
+    fn contains_key(&self, cf: ColumnFamilyRef<'a>, key: &[u8]) -> Result<bool> {
+        Ok(if self.snapshot.is_some() {
+            self.get(cf, key)?.is_some()
+        } else {
+            self.db.get_pinned_cf(cf, key)?.is_some()
+        })
+    }
+
+    /// Opens an iterator on the snapshot of the handle, if any, or on the latest state of the database
+    fn iter(&self, cf: ColumnFamilyRef<'a>) -> DBRawIterator<'a> {
+        if let Some(snapshot) = self.snapshot {
+            snapshot.raw_iterator_cf_opt(cf, ReadOptions::default())
+        } else {
+            self.db.raw_iterator_cf(cf)
+        }
+    }
+
+    /// Checks if the quads of a graph are stored in a column family of their own
+    fn is_in_graph_cf(&self, graph_name: EncodedTerm) -> bool {
+        self.layout == RocksDbLayout::ColumnFamilyPerGraph && graph_name != ENCODED_DEFAULT_GRAPH
+    }
+
+    /// Returns the column family of a named graph, if it has one, in the snapshot of the handle, if any
+    fn graph_cf(&self, graph_name: EncodedTerm) -> Option<ColumnFamilyRef<'a>> {
+        if let Some(graph_cfs) = self.snapshot_graph_cfs {
+            graph_cfs.get(&graph_name).copied()
+        } else {
+            self.db.cf_handle(&graph_cf_name(graph_name))
+        }
+    }
+
+    /// Returns the column families of all the named graphs having one, in the snapshot of the handle, if any
+    fn all_graph_cfs(&self) -> Vec<ColumnFamilyRef<'a>> {
+        if let Some(graph_cfs) = self.snapshot_graph_cfs {
+            graph_cfs.values().copied().collect()
+        } else {
+            read_graph_cfs(self.graph_cfs)
+                .iter()
+                .filter_map(|graph_name| self.db.cf_handle(&graph_cf_name(*graph_name)))
+                .collect()
+        }
+    }
+
+    /// Returns the column family of a named graph, after creating it if it does not exist yet
+    ///
+    /// `graph_cfs` is the content of the locked `graph_cfs` of the store.
+    fn create_graph_cf(
+        &self,
+        graph_cfs: &mut HashSet<EncodedTerm>,
+        graph_name: EncodedTerm,
+    ) -> Result<ColumnFamilyRef<'a>> {
+        let name = graph_cf_name(graph_name);
+        if !graph_cfs.contains(&graph_name) {
+            self.db.create_cf(&name, self.graph_cf_options)?;
+            graph_cfs.insert(graph_name);
+        }
+        Ok(get_cf(self.db, &name))
+    }
+
+    /// Drops the column family of a named graph, if it has one
+    ///
+    /// `graph_cfs` is the content of the locked `graph_cfs` of the store.
+    fn drop_graph_cf(
+        &self,
+        graph_cfs: &mut HashSet<EncodedTerm>,
+        graph_name: EncodedTerm,
+    ) -> Result<()> {
+        if graph_cfs.remove(&graph_name) {
+            self.db.drop_cf(&graph_cf_name(graph_name))?;
+        }
+        Ok(())
+    }
+
+    /// Returns where the keys of an index are stored for the quads of a graph, or of all the graphs if `graph_name` is `None`
+    fn index_locations(
+        &self,
+        index: &QuadIndex<'a>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Vec<IndexLocation<'a>> {
+        let shared = IndexLocation {
+            cf: index.cf,
+            tag: None,
+        };
+        match graph_name {
+            Some(graph_name) if self.is_in_graph_cf(graph_name) => self
+                .graph_cf(graph_name)
+                .map(|cf| IndexLocation {
+                    cf,
+                    tag: Some(index.tag),
+                })
+                .into_iter()
+                .collect(),
+            None if self.layout == RocksDbLayout::ColumnFamilyPerGraph => {
+                let mut locations = vec![shared];
+                locations.extend(self.all_graph_cfs().into_iter().map(|cf| IndexLocation {
+                    cf,
+                    tag: Some(index.tag),
+                }));
+                locations
+            }
+            _ => vec![shared],
+        }
+    }
+
+    /// Returns the column family and the key of a quad written with `write`
+    ///
+    /// The key is prefixed with `tag` if the quad is stored in the column family of its graph
+    /// and it is `None` if this column family does not exist.
+    fn quad_key(
+        &self,
+        cf: ColumnFamilyRef<'a>,
+        tag: u8,
+        write: fn(&mut Vec<u8>, &EncodedQuad),
+        quad: &EncodedQuad,
+    ) -> Option<(ColumnFamilyRef<'a>, Vec<u8>)> {
+        let mut key = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE + 1);
+        let cf = if self.is_in_graph_cf(quad.graph_name) {
+            key.push(tag);
+            self.graph_cf(quad.graph_name)?
+        } else {
+            cf
+        };
+        write(&mut key, quad);
+        Some((cf, key))
+    }
+
+    fn index_key(
+        &self,
+        index: &QuadIndex<'a>,
+        quad: &EncodedQuad,
+    ) -> Option<(ColumnFamilyRef<'a>, Vec<u8>)> {
+        self.quad_key(index.cf, index.tag, index.write, quad)
+    }
+
+    fn contains(&self, quad: &EncodedQuad) -> Result<bool> {
+        match self.quad_key(self.spog_cf, SPOG_TAG, write_spog_quad, quad) {
+            Some((cf, key)) => self.contains_key(cf, &key),
+            None => Ok(false),
+        }
+    }
+
+    fn provenance(&self, quad: &EncodedQuad) -> Result<Option<QuadProvenance>> {
+        let (cf, key) =
+            match self.quad_key(self.provenance_cf, PROVENANCE_TAG, write_spog_quad, quad) {
+                Some(location) => location,
+                None => return Ok(None),
+            };
+        self.get(cf, &key)?
+            .map(|value| QuadProvenance::read(&value))
+            .transpose()
+    }
+
+    fn statistic(&self, kind: u8, term: EncodedTerm) -> Result<u64> {
+        Ok(self
+            .get(self.stats_cf, &statistic_key(kind, term))?
+            .map_or(0, |value| decode_statistic(&value)))
+    }
+
+    /// Checks if the words index of the string literals is maintained
+    fn is_words_index_maintained(&self) -> Result<bool> {
+        self.contains_key(self.stats_cf, &WORDS_INDEX_KEY)
+    }
+
+    /// Builds the words index if it is not maintained yet or, without the full-text feature,
+    /// marks it as not maintained anymore because the next writes are not going to update it
+    fn sync_words_index(&self) -> Result<()> {
+        #[cfg(feature = "full-text")]
+        {
+            if !self.is_words_index_maintained()? {
+                self.rebuild_words_index()?;
+            }
+        }
+        #[cfg(not(feature = "full-text"))]
+        {
+            if self.is_words_index_maintained()? {
+                let mut batch = WriteBatch::default();
+                batch.delete_cf(self.stats_cf, &WORDS_INDEX_KEY);
+                self.write(batch)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of string literals containing a word
+    #[cfg(feature = "full-text")]
+    fn word_statistic(&self, word: &str) -> Result<u64> {
+        Ok(self
+            .get(self.stats_cf, &word_statistic_key(word))?
+            .map_or(0, |value| decode_statistic(&value)))
+    }
+
+    /// Returns an upper bound of the number of string literals containing all the words of `query`
+    /// or `None` if the words index is not maintained
+    #[cfg(feature = "full-text")]
+    fn literals_matching_count(&self, query: &str) -> Result<Option<u64>> {
+        if !self.is_words_index_maintained()? {
+            return Ok(None);
+        }
+        let mut count = None;
+        for word in text_words(query) {
+            let word_count = self.word_statistic(&word)?;
+            count = Some(count.map_or(word_count, |count: u64| count.min(word_count)));
+        }
+        Ok(Some(count.unwrap_or(0)))
+    }
+
+    /// Returns the string literals containing all the words of `query` or `None` if the words index is not maintained
+    ///
+    /// The literals containing the least frequent word are read from the index and filtered with the other words.
+    #[cfg(feature = "full-text")]
+    #[allow(clippy::type_complexity)]
+    fn literals_matching(
+        &self,
+        query: &str,
+    ) -> Result<Option<Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a>>> {
+        if !self.is_words_index_maintained()? {
+            return Ok(None);
+        }
+        let words: HashSet<String> = text_words(query).collect();
+        let mut rarest: Option<(&str, u64)> = None;
+        for word in &words {
+            let count = self.word_statistic(word)?;
+            if rarest.map_or(true, |(_, rarest_count)| count < rarest_count) {
+                rarest = Some((word, count));
+            }
+        }
+        let mut prefix = match rarest {
+            Some((word, _)) => word.as_bytes().to_vec(),
+            None => return Ok(Some(Box::new(empty()))),
+        };
+        prefix.push(0);
+        let mut iter = self.iter(self.words_cf);
+        iter.seek(&prefix);
+        let handle = self.clone();
+        Ok(Some(Box::new(
+            DecodingWordLiteralIterator { iter, prefix }.filter_map(move |literal| {
+                let literal = match literal {
+                    Ok(literal) => literal,
+                    Err(error) => return Some(Err(error)),
+                };
+                if words.len() == 1 {
+                    return Some(Ok(literal));
+                }
+                match literal_words(&handle, literal) {
+                    Ok(Some(literal_words)) if words.is_subset(&literal_words) => Some(Ok(literal)),
+                    Ok(_) => None,
+                    Err(error) => Some(Err(error)),
+                }
+            }),
+        )))
+    }
+
+    /// Adds to the batch the words index entries of a string literal or their removal if `insert` is false
+    ///
+    /// Returns the words of the literal.
+    #[cfg(feature = "full-text")]
+    fn write_literal_words(
+        &self,
+        batch: &mut WriteBatch,
+        strings: &impl StrLookup,
+        literal: EncodedTerm,
+        insert: bool,
+    ) -> Result<HashSet<String>> {
+        let words = literal_words(strings, literal)?.unwrap_or_default();
+        for word in &words {
+            let key = word_literal_key(word, literal);
+            if insert {
+                batch.put_cf(self.words_cf, &key, &[]);
+            } else {
+                batch.delete_cf(self.words_cf, &key);
+            }
+        }
+        Ok(words)
+    }
+
+    /// Sums the quads count statistics of the default graph and of the named graphs
+    fn len(&self) -> Result<u64> {
+        let mut len = self.statistic(GRAPH_QUADS_COUNT, ENCODED_DEFAULT_GRAPH)?;
+        for graph_name in self.named_graphs() {
+            len += self.statistic(GRAPH_QUADS_COUNT, graph_name?)?;
+        }
+        Ok(len)
+    }
+
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.quads().next().is_none())
+    }
+
+    fn stats(&self) -> Result<StoreStats> {
+        let distinct_objects_count = if self.indexes.contains(RocksDbIndex::OSPG) {
+            self.count_distinct_first_terms(&self.optional_index(RocksDbIndex::OSPG))?
+        } else {
+            let mut objects = HashSet::new();
+            for quad in self.posg_quads(Vec::default()) {
+                objects.insert(quad?.object);
+            }
+            objects.len()
+        };
+        Ok(StoreStats {
+            quads_count: self.len()? as usize,
+            distinct_subjects_count: self.count_distinct_first_terms(&self.spog_index())?,
+            distinct_predicates_count: self.count_distinct_first_terms(&self.posg_index())?,
+            distinct_objects_count,
+            named_graphs_count: self.count_keys(self.graphs_cf)?,
+            strings_count: self.count_keys(self.id2str_cf)?,
+        })
+    }
+
+    /// Counts the distinct terms at the beginning of the keys of an index
+    ///
+    /// If the index is split between the column families of the named graphs, the terms are collected in memory.
+    fn count_distinct_first_terms(&self, index: &QuadIndex<'a>) -> Result<usize> {
+        let locations = self.index_locations(index, None);
+        if let [location] = locations.as_slice() {
+            let mut count = 0;
+            self.for_each_first_term(*location, |_| count += 1)?;
+            return Ok(count);
+        }
+        let mut terms = HashSet::new();
+        for location in locations {
+            self.for_each_first_term(location, |term| {
+                terms.insert(term);
+            })?;
+        }
+        Ok(terms.len())
+    }
+
+    /// Calls `f` on each distinct term at the beginning of the keys of an index location
+    ///
+    /// After each term the iterator jumps directly to the next one.
+    fn for_each_first_term(
+        &self,
+        location: IndexLocation<'a>,
+        mut f: impl FnMut(EncodedTerm),
+    ) -> Result<()> {
+        let tag = location.tag.into_iter().collect::<Vec<_>>();
+        let mut iter = self.iter(location.cf);
+        iter.seek(&tag);
+        while let Some(key) = iter.key() {
+            if !key.starts_with(&tag) {
+                break;
+            }
+            let term = Cursor::new(&key[tag.len()..]).read_term()?;
+            f(term);
+            let mut prefix = Vec::with_capacity(tag.len() + WRITTEN_TERM_MAX_SIZE);
+            prefix.extend_from_slice(&tag);
+            write_term(&mut prefix, term);
+            match prefix_successor(prefix) {
+                Some(next) => iter.seek(&next),
+                None => break,
+            }
+        }
+        iter.status()?;
+        Ok(())
+    }
+
+    fn count_keys(&self, cf: ColumnFamilyRef<'a>) -> Result<usize> {
+        let mut count = 0;
+        let mut iter = self.iter(cf);
+        iter.seek_to_first();
+        while iter.valid() {
+            count += 1;
+            iter.next();
+        }
+        iter.status()?;
+        Ok(count)
+    }
+
+    fn named_graphs(&self) -> DecodingGraphIterator<'a> {
+        let mut iter = self.iter(self.graphs_cf);
+        iter.seek_to_first();
+        DecodingGraphIterator { iter }
+    }
+
+    fn contains_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        let mut buffer = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
+        write_term(&mut buffer, graph_name);
+        self.contains_key(self.graphs_cf, &buffer)
+    }
+
+    /// Returns the indexes maintained by the store
+    fn maintained_indexes(&self) -> Vec<QuadIndex<'a>> {
+        let mut indexes = vec![self.spog_index(), self.posg_index()];
+        for index in &RocksDbIndex::ALL {
+            if self.indexes.contains(*index) {
+                indexes.push(self.optional_index(*index));
+            }
+        }
+        indexes
+    }
+
+    fn spog_index(&self) -> QuadIndex<'a> {
+        QuadIndex {
+            cf: self.spog_cf,
+            tag: SPOG_TAG,
+            encoding: QuadEncoding::SPOG,
+            write: write_spog_quad,
+            order: [0, 1, 2, 3],
+        }
+    }
+
+    fn posg_index(&self) -> QuadIndex<'a> {
+        QuadIndex {
+            cf: self.posg_cf,
+            tag: POSG_TAG,
+            encoding: QuadEncoding::POSG,
+            write: write_posg_quad,
+            order: [1, 2, 0, 3],
+        }
+    }
+
+    fn optional_index(&self, index: RocksDbIndex) -> QuadIndex<'a> {
+        match index {
+            RocksDbIndex::OSPG => QuadIndex {
+                cf: self.ospg_cf,
+                tag: OSPG_TAG,
+                encoding: QuadEncoding::OSPG,
+                write: write_ospg_quad,
+                order: [2, 0, 1, 3],
+            },
+            RocksDbIndex::GSPO => QuadIndex {
+                cf: self.gspo_cf,
+                tag: GSPO_TAG,
+                encoding: QuadEncoding::GSPO,
+                write: write_gspo_quad,
+                order: [3, 0, 1, 2],
+            },
+            RocksDbIndex::GPOS => QuadIndex {
+                cf: self.gpos_cf,
+                tag: GPOS_TAG,
+                encoding: QuadEncoding::GPOS,
+                write: write_gpos_quad,
+                order: [3, 1, 2, 0],
+            },
+            RocksDbIndex::GOSP => QuadIndex {
+                cf: self.gosp_cf,
+                tag: GOSP_TAG,
+                encoding: QuadEncoding::GOSP,
+                write: write_gosp_quad,
+                order: [3, 2, 0, 1],
+            },
+        }
+    }
+
+    /// Builds the optional indexes of `indexes` that are not maintained yet and deletes the ones not in `indexes`
+    ///
+    /// The saved set of indexes never contains an index that is partially written.
+    fn change_indexes(&self, indexes: IndexSet) -> Result<()> {
+        self.save_indexes(self.indexes.intersection(indexes))?;
+        for index in &RocksDbIndex::ALL {
+            let quad_index = self.optional_index(*index);
+            if self.indexes.contains(*index) && !indexes.contains(*index) {
+                let mut batch = WriteBatch::default();
+                for location in self.index_locations(&quad_index, None) {
+                    let tag = location.tag.into_iter().collect::<Vec<_>>();
+                    let mut iter = self.db.raw_iterator_cf(location.cf);
+                    iter.seek(&tag);
+                    while let Some(key) = iter.key() {
+                        if !key.starts_with(&tag) {
+                            break;
+                        }
+                        batch.delete_cf(location.cf, key);
+                        if batch.len() > MAX_TRANSACTION_SIZE {
+                            self.write(take(&mut batch))?;
+                        }
+                        iter.next();
+                    }
+                }
+                self.write(batch)?;
+            } else if !self.indexes.contains(*index) && indexes.contains(*index) {
+                let mut batch = WriteBatch::default();
+                for quad in self.quads() {
+                    if let Some((cf, key)) = self.index_key(&quad_index, &quad?) {
+                        batch.put_cf(cf, &key, &[]);
+                    }
+                    if batch.len() > MAX_TRANSACTION_SIZE {
+                        self.write(take(&mut batch))?;
+                    }
+                }
+                self.write(batch)?;
+            }
+        }
+        self.save_indexes(indexes)
+    }
+
+    fn save_indexes(&self, indexes: IndexSet) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.put_cf(self.stats_cf, &INDEXES_KEY, &[indexes.0]);
+        self.write(batch)?;
+        Ok(())
+    }
+
+    /// Returns the on-disk format version of the database
+    ///
+    /// The databases without version are the ones written before it has been stamped, except the new empty ones.
+    fn format_version(&self) -> Result<u64> {
+        if let Some(value) = self.db.get_pinned_cf(self.stats_cf, &FORMAT_VERSION_KEY)? {
+            return Ok(decode_statistic(&value));
+        }
+        let mut iter = self.db.raw_iterator_cf(self.id2str_cf);
+        iter.seek_to_first();
+        iter.status()?;
+        Ok(if iter.valid() {
+            0
+        } else {
+            LATEST_FORMAT_VERSION
+        })
+    }
+
+    fn set_layout(&self, layout: RocksDbLayout) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        let value = match layout {
+            RocksDbLayout::SharedColumnFamilies => 0,
+            RocksDbLayout::ColumnFamilyPerGraph => 1,
+        };
+        batch.put_cf(self.stats_cf, &LAYOUT_KEY, &[value]);
+        self.write(batch)
+    }
+
+    fn set_format_version(&self, version: u64) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.put_cf(self.stats_cf, &FORMAT_VERSION_KEY, &version.to_be_bytes());
+        self.write(batch)
+    }
+
+    /// Upgrades the database from the given format version to the latest one, one version after the other
+    fn migrate(&self, version: u64) -> Result<()> {
+        if version < 1 {
+            if self
+                .db
+                .get_pinned_cf(self.stats_cf, &STATISTICS_INITIALIZED_KEY)?
+                .is_none()
+            {
+                self.rebuild_statistics()?;
+            }
+            if self.named_graphs().next().is_none() {
+                self.rebuild_named_graphs()?;
+            }
+        }
+        if version < 2 {
+            self.inline_short_strings()?;
+        }
+        self.set_format_version(LATEST_FORMAT_VERSION)
+    }
+
+    /// Rewrites the terms encoded with the hashes of short strings with the strings inlined instead
+    ///
+    /// The short strings are then removed from the string store and the statistics are computed again.
+    fn inline_short_strings(&self) -> Result<()> {
+        let inline = |id: StrHash| -> Result<StrHash> {
+            Ok(if id.is_inline() {
+                id
+            } else {
+                StrHash::new(&get_required_str(self, id)?)
+            })
+        };
+        let inline_term = |term: EncodedTerm| term.map_str_ids(&inline);
+
+        let indexes = self.maintained_indexes();
+        let mut batch = WriteBatch::default();
+        let mut buffer = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
+        for quad in self.quads() {
+            let quad = quad?;
+            let new_quad = EncodedQuad::new(
+                inline_term(quad.subject)?,
+                inline_term(quad.predicate)?,
+                inline_term(quad.object)?,
+                inline_term(quad.graph_name)?,
+            );
+            if new_quad == quad {
+                continue;
+            }
+            for index in &indexes {
+                (index.write)(&mut buffer, &quad);
+                batch.delete_cf(index.cf, &buffer);
+                buffer.clear();
+                (index.write)(&mut buffer, &new_quad);
+                batch.put_cf(index.cf, &buffer, &[]);
+                buffer.clear();
+            }
+            write_spog_quad(&mut buffer, &quad);
+            if let Some(provenance) = self.db.get_pinned_cf(self.provenance_cf, &buffer)? {
+                batch.delete_cf(self.provenance_cf, &buffer);
+                buffer.clear();
+                write_spog_quad(&mut buffer, &new_quad);
+                batch.put_cf(self.provenance_cf, &buffer, provenance);
+            }
+            buffer.clear();
+            if batch.len() > MAX_TRANSACTION_SIZE {
+                self.write(take(&mut batch))?;
+            }
+        }
+
+        // The named graphs and their expiration times are keyed by graph name
+        for cf in &[self.graphs_cf, self.expirations_cf] {
+            let mut iter = self.db.raw_iterator_cf(*cf);
+            iter.seek_to_first();
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                let new_key = encode_term(inline_term(Cursor::new(key).read_term()?)?);
+                if new_key != key {
+                    batch.delete_cf(*cf, key);
+                    batch.put_cf(*cf, &new_key, value);
+                }
+                if batch.len() > MAX_TRANSACTION_SIZE {
+                    self.write(take(&mut batch))?;
+                }
+                iter.next();
+            }
+            iter.status()?;
+        }
+        self.write(take(&mut batch))?;
+
+        let mut iter = self.db.raw_iterator_cf(self.id2str_cf);
+        iter.seek_to_first();
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if value.len() <= MAX_INLINE_STR_LEN {
+                batch.delete_cf(self.id2str_cf, key);
+            }
+            if batch.len() > MAX_TRANSACTION_SIZE {
+                self.write(take(&mut batch))?;
+            }
+            iter.next();
+        }
+        iter.status()?;
+        self.write(batch)?;
+        self.recompute_statistics()
+    }
+
+    /// Fills the named graphs index from the GSPO index
+    ///
+    /// After each graph the iterator seeks directly to the next one, so only one read per graph is done.
+    /// All the quads are read if the GSPO index is not maintained.
+    fn rebuild_named_graphs(&self) -> Result<()> {
+        if !self.indexes.contains(RocksDbIndex::GSPO) {
+            let mut batch = WriteBatch::default();
+            for quad in self.quads() {
+                let graph_name = quad?.graph_name;
+                if graph_name != ENCODED_DEFAULT_GRAPH {
+                    batch.put_cf(self.graphs_cf, &encode_term(graph_name), &[]);
+                }
+            }
+            self.write(batch)?;
+            return Ok(());
+        }
+        let mut batch = WriteBatch::default();
+        let mut iter = self.db.raw_iterator_cf(self.gspo_cf);
+        iter.seek_to_first();
+        while let Some(key) = iter.key() {
+            let graph_name = QuadEncoding::GSPO.decode(key)?.graph_name;
+            let mut prefix = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
+            write_term(&mut prefix, graph_name);
+            if graph_name != ENCODED_DEFAULT_GRAPH {
+                batch.put_cf(self.graphs_cf, &prefix, &[]);
+            }
+            if let Some(next) = prefix_successor(prefix) {
+                iter.seek(&next);
+            } else {
+                break;
+            }
+        }
+        self.write(batch)?;
+        Ok(())
+    }
+
+    /// Cross-checks the indexes and the string store and repairs the inconsistencies if `repair` is set
+    ///
+    /// Each quad is checked only in the first index it is found in, using a point lookup in each other index.
+    fn validate(&self, repair: bool) -> Result<ValidationReport> {
+        let indexes = self.maintained_indexes();
+        let mut report = ValidationReport::default();
+        let mut stored_strings = HashSet::new();
+        let mut checked_graphs = HashSet::new();
+        let mut batch = WriteBatch::default();
+        for (position, index) in indexes.iter().enumerate() {
+            for quad in self.inner_quads(*index, Vec::default(), None) {
+                let quad = quad?;
+                let mut is_in_all_indexes = true;
+                let mut is_already_checked = false;
+                for (other_position, other) in indexes.iter().enumerate() {
+                    if other_position == position {
+                        continue;
+                    }
+                    let is_in_other = match self.index_key(other, &quad) {
+                        Some((cf, key)) => self.db.get_pinned_cf(cf, &key)?.is_some(),
+                        None => false,
+                    };
+                    if is_in_other && other_position < position {
+                        is_already_checked = true;
+                    }
+                    is_in_all_indexes &= is_in_other;
+                }
+                if is_already_checked {
+                    continue;
+                }
+
+                if !has_stored_strings(self, &quad, &mut stored_strings)? {
+                    report.dangling_quads += 1;
+                    if repair {
+                        for index in &indexes {
+                            if let Some((cf, key)) = self.index_key(index, &quad) {
+                                batch.delete_cf(cf, &key);
+                            }
+                        }
+                        if let Some((cf, key)) = self.quad_key(
+                            self.provenance_cf,
+                            PROVENANCE_TAG,
+                            write_spog_quad,
+                            &quad,
+                        ) {
+                            batch.delete_cf(cf, &key);
+                        }
+                    }
+                    continue;
+                }
+                if !is_in_all_indexes {
+                    report.incomplete_quads += 1;
+                    if repair {
+                        for index in &indexes {
+                            if let Some((cf, key)) = self.index_key(index, &quad) {
+                                batch.put_cf(cf, &key, &[]);
+                            }
+                        }
+                    }
+                }
+                if quad.graph_name != ENCODED_DEFAULT_GRAPH
+                    && checked_graphs.insert(quad.graph_name)
+                    && !self.contains_named_graph(quad.graph_name)?
+                {
+                    report.missing_named_graphs += 1;
+                    if repair {
+                        batch.put_cf(self.graphs_cf, &encode_term(quad.graph_name), &[]);
+                    }
+                }
+                if batch.len() > MAX_TRANSACTION_SIZE {
+                    self.write(take(&mut batch))?;
+                }
+            }
+            // The next indexes see the quads already repaired
+            self.write(take(&mut batch))?;
+        }
+        if repair && !report.is_valid() {
+            self.recompute_statistics()?;
+        }
+        Ok(report)
+    }
+
+    /// Computes again the statistics and runs a full manual compaction of all the column families
+    fn optimize(&self) -> Result<()> {
+        self.recompute_statistics()?;
+        for name in &COLUMN_FAMILIES {
+            self.db
+                .compact_range_cf(get_cf(self.db, name), None::<&[u8]>, None::<&[u8]>);
+        }
+        for cf in self.all_graph_cfs() {
+            self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        }
+        Ok(())
+    }
+
+    /// Deletes the statistics and computes them again from the content of the indexes
+    fn recompute_statistics(&self) -> Result<()> {
+        // The statistics keys contain the encoded terms, the special keys are only one byte long
+        let mut batch = WriteBatch::default();
+        let mut iter = self.db.raw_iterator_cf(self.stats_cf);
+        iter.seek_to_first();
+        while let Some(key) = iter.key() {
+            if key.len() > 1 {
+                batch.delete_cf(self.stats_cf, key);
+            }
+            if batch.len() > MAX_TRANSACTION_SIZE {
+                self.write(take(&mut batch))?;
+            }
+            iter.next();
+        }
+        iter.status()?;
+        self.write(batch)?;
+        self.rebuild_statistics()
+    }
+
+    /// Computes the statistics from the content of the indexes
+    ///
+    /// The quads with the same subject and predicate are next to each other in the SPOG index
+    /// and the ones with the same predicate and object in the POSG index.
+    fn rebuild_statistics(&self) -> Result<()> {
+        let mut statistics = HashMap::<_, u64>::default();
+        let mut previous = None;
+        for quad in self.quads() {
+            let quad = quad?;
+            *statistics
+                .entry((PREDICATE_QUADS_COUNT, quad.predicate))
+                .or_default() += 1;
+            *statistics
+                .entry((GRAPH_QUADS_COUNT, quad.graph_name))
+                .or_default() += 1;
+            if previous != Some((quad.subject, quad.predicate)) {
+                *statistics
+                    .entry((PREDICATE_DISTINCT_SUBJECTS_COUNT, quad.predicate))
+                    .or_default() += 1;
+                previous = Some((quad.subject, quad.predicate));
+            }
+        }
+        let mut previous = None;
+        for quad in self.posg_quads(Vec::default()) {
+            let quad = quad?;
+            if previous != Some((quad.predicate, quad.object)) {
+                *statistics
+                    .entry((PREDICATE_DISTINCT_OBJECTS_COUNT, quad.predicate))
+                    .or_default() += 1;
+                previous = Some((quad.predicate, quad.object));
+            }
+        }
+
+        let mut batch = WriteBatch::default();
+        for ((kind, term), value) in statistics {
+            batch.put_cf(
+                self.stats_cf,
+                &statistic_key(kind, term),
+                &value.to_be_bytes(),
+            );
+        }
+        batch.put_cf(self.stats_cf, &STATISTICS_INITIALIZED_KEY, &[]);
+        self.write(batch)?;
+        #[cfg(feature = "full-text")]
+        self.rebuild_words_index()?;
+        Ok(())
+    }
+
+    /// Deletes the words index of the string literals and builds it again from the content of the indexes
+    #[cfg(feature = "full-text")]
+    fn rebuild_words_index(&self) -> Result<()> {
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(self.stats_cf, &WORDS_INDEX_KEY);
+        let mut iter = self.db.raw_iterator_cf(self.words_cf);
+        iter.seek_to_first();
+        while let Some(key) = iter.key() {
+            batch.delete_cf(self.words_cf, key);
+            if batch.len() > MAX_TRANSACTION_SIZE {
+                self.write(take(&mut batch))?;
+            }
+            iter.next();
+        }
+        iter.status()?;
+        let mut iter = self.db.raw_iterator_cf(self.stats_cf);
+        iter.seek(&[LITERAL_QUADS_COUNT]);
+        while let Some(key) = iter.key() {
+            if key[0] > WORD_LITERALS_COUNT {
+                break;
+            }
+            batch.delete_cf(self.stats_cf, key);
+            if batch.len() > MAX_TRANSACTION_SIZE {
+                self.write(take(&mut batch))?;
+            }
+            iter.next();
+        }
+        iter.status()?;
+
+        let mut literals = HashMap::<_, u64>::default();
+        for quad in self.quads() {
+            let quad = quad?;
+            if is_string_literal(quad.object) {
+                *literals.entry(quad.object).or_default() += 1;
+            }
+        }
+        let mut words = HashMap::<_, u64>::default();
+        for (literal, count) in literals {
+            batch.put_cf(
+                self.stats_cf,
+                &statistic_key(LITERAL_QUADS_COUNT, literal),
+                &count.to_be_bytes(),
+            );
+            for word in self.write_literal_words(&mut batch, self, literal, true)? {
+                *words.entry(word).or_default() += 1;
+            }
+            if batch.len() > MAX_TRANSACTION_SIZE {
+                self.write(take(&mut batch))?;
+            }
+        }
+        for (word, count) in words {
+            batch.put_cf(
+                self.stats_cf,
+                &word_statistic_key(&word),
+                &count.to_be_bytes(),
+            );
+        }
+        batch.put_cf(self.stats_cf, &WORDS_INDEX_KEY, &[]);
+        self.write(batch)
+    }
+
+    fn encoded_quads_for_pattern(
+        &self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
         object: Option<EncodedTerm>,
         graph_name: Option<EncodedTerm>,
     ) -> DecodingIndexIterator<'a> {
+        if self.indexes != IndexSet::ALL {
+            return self
+                .quads_for_pattern_in_maintained_indexes([subject, predicate, object, graph_name]);
+        }
         match subject {
             Some(subject) => match predicate {
                 Some(predicate) => match object {
                     Some(object) => match graph_name {
                         Some(graph_name) => self
-                            .spog_quads(encode_term_quad(subject, predicate, object, graph_name)),
+                            .spog_quad(&EncodedQuad::new(subject, predicate, object, graph_name)),
                         None => self.quads_for_subject_predicate_object(subject, predicate, object),
                     },
                     None => match graph_name {
@@ -350,6 +3167,39 @@ impl<'a> RocksDbStoreHandle<'a> {
         }
     }
 
+    /// Reads the maintained index with the longest key prefix made of the bound components and filters the other ones
+    fn quads_for_pattern_in_maintained_indexes(
+        &self,
+        pattern: [Option<EncodedTerm>; 4],
+    ) -> DecodingIndexIterator<'a> {
+        if let [Some(subject), Some(predicate), Some(object), Some(graph_name)] = pattern {
+            return self.spog_quad(&EncodedQuad::new(subject, predicate, object, graph_name));
+        }
+        let indexes = self.maintained_indexes();
+        let mut index = indexes[0];
+        let mut best_prefix_len = 0;
+        for candidate in indexes {
+            let prefix_len = candidate
+                .order
+                .iter()
+                .take_while(|position| pattern[**position].is_some())
+                .count();
+            if prefix_len > best_prefix_len {
+                index = candidate;
+                best_prefix_len = prefix_len;
+            }
+        }
+        let mut prefix = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
+        for position in &index.order[..best_prefix_len] {
+            if let Some(term) = pattern[*position] {
+                write_term(&mut prefix, term);
+            }
+        }
+        let mut iter = self.inner_quads(index, prefix, pattern[3]);
+        iter.filter = pattern;
+        iter
+    }
+
     fn quads(&self) -> DecodingIndexIterator<'a> {
         self.spog_quads(Vec::default())
     }
@@ -400,7 +3250,7 @@ impl<'a> RocksDbStoreHandle<'a> {
     }
 
     fn quads_for_graph(&self, graph_name: EncodedTerm) -> DecodingIndexIterator<'a> {
-        self.gspo_quads(encode_term(graph_name))
+        self.gspo_quads(graph_name, encode_term(graph_name))
     }
 
     fn quads_for_subject_graph(
@@ -408,7 +3258,7 @@ impl<'a> RocksDbStoreHandle<'a> {
         subject: EncodedTerm,
         graph_name: EncodedTerm,
     ) -> DecodingIndexIterator<'a> {
-        self.gspo_quads(encode_term_pair(graph_name, subject))
+        self.gspo_quads(graph_name, encode_term_pair(graph_name, subject))
     }
 
     fn quads_for_subject_predicate_graph(
@@ -417,7 +3267,10 @@ impl<'a> RocksDbStoreHandle<'a> {
         predicate: EncodedTerm,
         graph_name: EncodedTerm,
     ) -> DecodingIndexIterator<'a> {
-        self.gspo_quads(encode_term_triple(graph_name, subject, predicate))
+        self.gspo_quads(
+            graph_name,
+            encode_term_triple(graph_name, subject, predicate),
+        )
     }
 
     fn quads_for_subject_object_graph(
@@ -426,7 +3279,7 @@ impl<'a> RocksDbStoreHandle<'a> {
         object: EncodedTerm,
         graph_name: EncodedTerm,
     ) -> DecodingIndexIterator<'a> {
-        self.gosp_quads(encode_term_triple(graph_name, object, subject))
+        self.gosp_quads(graph_name, encode_term_triple(graph_name, object, subject))
     }
 
     fn quads_for_predicate_graph(
@@ -434,7 +3287,7 @@ impl<'a> RocksDbStoreHandle<'a> {
         predicate: EncodedTerm,
         graph_name: EncodedTerm,
     ) -> DecodingIndexIterator<'a> {
-        self.gpos_quads(encode_term_pair(graph_name, predicate))
+        self.gpos_quads(graph_name, encode_term_pair(graph_name, predicate))
     }
 
     fn quads_for_predicate_object_graph(
@@ -443,7 +3296,10 @@ impl<'a> RocksDbStoreHandle<'a> {
         object: EncodedTerm,
         graph_name: EncodedTerm,
     ) -> DecodingIndexIterator<'a> {
-        self.gpos_quads(encode_term_triple(graph_name, predicate, object))
+        self.gpos_quads(
+            graph_name,
+            encode_term_triple(graph_name, predicate, object),
+        )
     }
 
     fn quads_for_object_graph(
@@ -451,45 +3307,92 @@ impl<'a> RocksDbStoreHandle<'a> {
         object: EncodedTerm,
         graph_name: EncodedTerm,
     ) -> DecodingIndexIterator<'a> {
-        self.gosp_quads(encode_term_pair(graph_name, object))
+        self.gosp_quads(graph_name, encode_term_pair(graph_name, object))
     }
 
-    fn spog_quads(&self, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
-        self.inner_quads(self.spog_cf, prefix, QuadEncoding::SPOG)
+    /// Returns the quad if it is in the store
+    ///
+    /// The index is first checked with a point lookup: unlike the seeks, it uses the bloom filters
+    /// and skips the disk reads if the quad is absent.
+    fn spog_quad(&self, quad: &EncodedQuad) -> DecodingIndexIterator<'a> {
+        // If the lookup fails, the index is read by the seek
+        if !self.contains(quad).unwrap_or(true) {
+            return DecodingIndexIterator {
+                sources: BinaryHeap::default(),
+                encoding: QuadEncoding::SPOG,
+                filter: [None; 4],
+            };
+        }
+        self.inner_quads(
+            self.spog_index(),
+            encode_term_quad(quad.subject, quad.predicate, quad.object, quad.graph_name),
+            Some(quad.graph_name),
+        )
+    }
+
+    fn spog_quads(&self, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
+        self.inner_quads(self.spog_index(), prefix, None)
     }
 
     fn posg_quads(&self, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
-        self.inner_quads(self.posg_cf, prefix, QuadEncoding::POSG)
+        self.inner_quads(self.posg_index(), prefix, None)
     }
 
     fn ospg_quads(&self, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
-        self.inner_quads(self.ospg_cf, prefix, QuadEncoding::OSPG)
+        self.inner_quads(self.optional_index(RocksDbIndex::OSPG), prefix, None)
     }
 
-    fn gspo_quads(&self, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
-        self.inner_quads(self.gspo_cf, prefix, QuadEncoding::GSPO)
+    fn gspo_quads(&self, graph_name: EncodedTerm, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
+        self.inner_quads(
+            self.optional_index(RocksDbIndex::GSPO),
+            prefix,
+            Some(graph_name),
+        )
     }
 
-    fn gpos_quads(&self, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
-        self.inner_quads(self.gpos_cf, prefix, QuadEncoding::GPOS)
+    fn gpos_quads(&self, graph_name: EncodedTerm, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
+        self.inner_quads(
+            self.optional_index(RocksDbIndex::GPOS),
+            prefix,
+            Some(graph_name),
+        )
     }
 
-    fn gosp_quads(&self, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
-        self.inner_quads(self.gosp_cf, prefix, QuadEncoding::GOSP)
+    fn gosp_quads(&self, graph_name: EncodedTerm, prefix: Vec<u8>) -> DecodingIndexIterator<'a> {
+        self.inner_quads(
+            self.optional_index(RocksDbIndex::GOSP),
+            prefix,
+            Some(graph_name),
+        )
     }
 
+    /// Reads the keys of an index starting with `prefix` in the quads of a graph, or of all the graphs if `graph_name` is `None`
     fn inner_quads(
         &self,
-        cf: &ColumnFamily,
+        index: QuadIndex<'a>,
         prefix: Vec<u8>,
-        encoding: QuadEncoding,
+        graph_name: Option<EncodedTerm>,
     ) -> DecodingIndexIterator<'a> {
-        let mut iter = self.db.raw_iterator_cf(cf);
-        iter.seek(&prefix);
+        let sources = self
+            .index_locations(&index, graph_name)
+            .into_iter()
+            .map(|location| {
+                let mut source_prefix = Vec::with_capacity(1 + prefix.len());
+                source_prefix.extend(location.tag);
+                source_prefix.extend_from_slice(&prefix);
+                let mut iter = self.iter(location.cf);
+                iter.seek(&source_prefix);
+                IndexSource {
+                    iter,
+                    prefix: source_prefix,
+                    tag_len: if location.tag.is_some() { 1 } else { 0 },
+                }
+            })
+            .collect();
         DecodingIndexIterator {
-            iter,
-            prefix,
-            encoding,
+            sources,
+            encoding: index.encoding,
+            filter: [None; 4],
         }
     }
 }
@@ -502,31 +3405,440 @@ impl RocksDbPreparedQuery {
     pub fn exec(&self) -> Result<QueryResult<'_>> {
         self.0.exec()
     }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    ///
+    /// It allows to execute the same query with different parameters without parsing it again.
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// A read-only view of a `RocksDbStore` frozen at the time of its creation.
+///
+/// It is returned by `RocksDbStore::snapshot`.
+#[derive(Clone)]
+pub struct RocksDbSnapshot<'a> {
+    store: &'a RocksDbStore,
+    snapshot: Arc<Snapshot<'a>>,
+    /// The column families of the named graphs when the snapshot has been taken, empty with the shared layout
+    graph_cfs: HashMap<EncodedTerm, ColumnFamilyRef<'a>>,
+}
+
+impl<'a> RocksDbSnapshot<'a> {
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) evaluated against the snapshot.
+    ///
+    /// See `RocksDbStore::snapshot` for a usage example.
+    pub fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<RocksDbSnapshotPreparedQuery<'a>> {
+        Ok(RocksDbSnapshotPreparedQuery(SimplePreparedQuery::new(
+            self.clone(),
+            query,
+            options,
+        )?))
+    }
+
+    /// This is similar to `prepare_query`, but for a `Query` that has already been parsed or that has been built programmatically.
+    pub fn prepare_parsed_query(
+        &self,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<RocksDbSnapshotPreparedQuery<'a>> {
+        Ok(RocksDbSnapshotPreparedQuery(
+            SimplePreparedQuery::new_from_query(self.clone(), query, options)?,
+        ))
+    }
+
+    /// Retrieves quads of the snapshot with a filter on each quad component
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn quads_for_pattern<'b>(
+        &'b self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> impl Iterator<Item = Result<Quad>> + 'b
+    where
+        Self: 'b,
+    {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        self.handle()
+            .encoded_quads_for_pattern(subject, predicate, object, graph_name)
+            .map(move |quad| self.decode_quad(&quad?))
+    }
+
+    /// Checks if the snapshot contains a given quad
+    pub fn contains(&self, quad: &Quad) -> Result<bool> {
+        let quad = quad.into();
+        self.handle().contains(&quad)
+    }
+
+    /// Returns the named graphs of the snapshot, including the ones without quads.
+    ///
+    /// They are read from the named graphs index, without going through the quads.
+    pub fn named_graphs(&self) -> impl Iterator<Item = Result<NamedOrBlankNode>> + '_ {
+        self.handle()
+            .named_graphs()
+            .map(move |graph_name| self.decode_named_or_blank_node(graph_name?))
+    }
+
+    /// Checks if the snapshot contains a given named graph, even if it does not contain any quad
+    pub fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        self.handle().contains_named_graph(graph_name.into())
+    }
+
+    /// Returns the number of quads in the snapshot
+    pub fn len(&self) -> Result<usize> {
+        Ok(self.handle().len()? as usize)
+    }
+
+    /// Returns if the snapshot is empty
+    pub fn is_empty(&self) -> Result<bool> {
+        self.handle().is_empty()
+    }
+
+    /// Returns statistics about the content of the snapshot
+    ///
+    /// See `RocksDbStore::stats`.
+    pub fn stats(&self) -> Result<StoreStats> {
+        self.handle().stats()
+    }
+
+    /// Returns the number of quads in a given graph of the snapshot
+    pub fn len_graph(&self, graph_name: &GraphName) -> Result<usize> {
+        Ok(self
+            .handle()
+            .statistic(GRAPH_QUADS_COUNT, graph_name.into())? as usize)
+    }
+
+    /// Dumps a graph of the snapshot into a file.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_graph(
+        &self,
+        writer: impl Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        dump_graph(
+            self.quads_for_pattern(None, None, None, Some(from_graph_name))
+                .map(|quad| Ok(quad?.into())),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Dumps the snapshot dataset into a file.
+    ///
+    /// The writes done to the store during the dump are not visible in it.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_dataset(&self, writer: impl Write, syntax: DatasetSyntax) -> Result<()> {
+        dump_dataset(
+            self.quads_for_pattern(None, None, None, None),
+            writer,
+            syntax,
+        )
+    }
+
+    fn handle(&self) -> RocksDbStoreHandle<'_> {
+        RocksDbStoreHandle {
+            snapshot: Some(&*self.snapshot),
+            snapshot_graph_cfs: Some(&self.graph_cfs),
+            ..self.store.handle()
+        }
+    }
+}
+
+impl sealed::MergeSource for RocksDbSnapshot<'_> {
+    fn encoded_quads_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + '_> {
+        self.encoded_quads_for_pattern(None, None, None, None)
+    }
+
+    fn encoded_named_graphs_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + '_> {
+        self.encoded_named_graphs()
+    }
+}
+
+impl StrLookup for RocksDbSnapshot<'_> {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        self.handle().get_stored_str(id)
+    }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        self.handle().get_stored_shared_str(id)
+    }
+}
+
+impl StoreStatistics for RocksDbSnapshot<'_> {
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        let handle = self.handle();
+        Some(PredicateStatistics {
+            quads_count: handle.statistic(PREDICATE_QUADS_COUNT, predicate).ok()?,
+            distinct_subjects_count: handle
+                .statistic(PREDICATE_DISTINCT_SUBJECTS_COUNT, predicate)
+                .ok()?,
+            distinct_objects_count: handle
+                .statistic(PREDICATE_DISTINCT_OBJECTS_COUNT, predicate)
+                .ok()?,
+        })
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        self.handle().statistic(GRAPH_QUADS_COUNT, graph_name).ok()
+    }
+
+    #[cfg(feature = "full-text")]
+    fn literals_matching_count(&self, query: &str) -> Option<u64> {
+        self.handle().literals_matching_count(query).ok()?
+    }
+}
+
+impl ReadableEncodedStore for RocksDbSnapshot<'_> {
+    fn encoded_quads_for_pattern<'b>(
+        &'b self,
+        subject: Option<EncodedTerm>,
+        predicate: Option<EncodedTerm>,
+        object: Option<EncodedTerm>,
+        graph_name: Option<EncodedTerm>,
+    ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'b> {
+        Box::new(
+            self.handle()
+                .encoded_quads_for_pattern(subject, predicate, object, graph_name),
+        )
+    }
+
+    fn encoded_named_graphs<'b>(&'b self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b> {
+        Box::new(self.handle().named_graphs())
+    }
+
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        self.handle().contains_named_graph(graph_name)
+    }
+
+    #[cfg(feature = "full-text")]
+    fn encoded_literals_matching<'b>(
+        &'b self,
+        query: &str,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'b> {
+        match self.handle().literals_matching(query) {
+            Ok(Some(literals)) => literals,
+            // The stores opened in read-only mode are not always indexed
+            Ok(None) => scan_literals_matching(self, query),
+            Err(error) => Box::new(once(Err(error))),
+        }
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) for a `RocksDbSnapshot`.
+pub struct RocksDbSnapshotPreparedQuery<'a>(SimplePreparedQuery<RocksDbSnapshot<'a>>);
+
+impl RocksDbSnapshotPreparedQuery<'_> {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// A prepared [SPARQL update](https://www.w3.org/TR/sparql11-update/) for the `RocksDbStore`.
+pub struct RocksDbPreparedUpdate {
+    store: RocksDbStore,
+    update: SimplePreparedUpdate,
+}
+
+impl RocksDbPreparedUpdate {
+    /// Executes the update
+    ///
+    /// All the operations of the update are executed in the same transaction:
+    /// the changes are only written if all of them succeed.
+    /// With the snapshot isolation, the update failing because of a conflict with a concurrent write is retried a few times.
+    pub fn exec(&self) -> Result<()> {
+        let mut retries = 0;
+        while !self.store.try_transaction(|transaction| {
+            for operation in self.update.operations() {
+                // The operations see the changes of the previous ones but not their own changes:
+                // the transaction records the changes of the operation apart from the previous ones
+                let previous = take(&mut transaction.changes);
+                if let Some(snapshot) = transaction.snapshot.clone() {
+                    self.update.exec_operation(
+                        operation,
+                        PendingChangesView::new(snapshot, &previous),
+                        transaction,
+                    )?;
+                } else {
+                    self.update.exec_operation(
+                        operation,
+                        PendingChangesView::new(self.store.clone(), &previous),
+                        transaction,
+                    )?;
+                }
+                let changes = replace(&mut transaction.changes, previous);
+                transaction.changes.extend(changes);
+            }
+            Ok(())
+        })? {
+            retries += 1;
+            if retries > MAX_UPDATE_CONFLICT_RETRIES {
+                return Err(StorageError::TransactionConflict.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) evaluated against a `RocksDbStore` during a transaction.
+pub struct RocksDbTransactionPreparedQuery<'a>(
+    SimplePreparedQuery<PendingChangesView<'a, RocksDbSnapshot<'a>>>,
+);
+
+impl RocksDbTransactionPreparedQuery<'_> {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
 }
 
 /// Allows to insert and delete quads during a transaction with the `RocksDbStore`.
 pub struct RocksDbTransaction<'a> {
+    store: &'a RocksDbStore,
     inner: RocksDbInnerTransaction<'a>,
+    /// The changes done by the transaction, in order for its queries to see them
+    changes: PendingChanges,
+    /// The snapshot taken at the beginning of the transaction with the snapshot isolation
+    snapshot: Option<RocksDbSnapshot<'a>>,
+    /// The quads inserted or removed by the transaction, only recorded with the snapshot isolation
+    written: HashSet<EncodedQuad>,
+    /// The named graphs inserted or removed by the transaction, only recorded with the snapshot isolation
+    written_graphs: HashSet<EncodedTerm>,
+    /// The named graphs cleared by dropping their column family, only recorded with the snapshot isolation
+    cleared_graphs: HashSet<EncodedTerm>,
 }
 
 impl StrContainer for RocksDbTransaction<'_> {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
-        self.inner.insert_str(key, value);
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.inner.insert_str(key, value)?;
+        self.changes.insert_str(key, value);
         Ok(())
     }
 }
 
 impl WritableEncodedStore for RocksDbTransaction<'_> {
     fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.record_write(quad);
+        self.changes.insert(quad);
         self.inner.insert(quad)
     }
 
     fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.record_write(quad);
+        self.changes.remove(quad);
         self.inner.remove(quad)
     }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.record_graph_write(graph_name);
+        self.changes.insert_named_graph(graph_name);
+        self.inner.insert_named_graph(graph_name);
+        Ok(())
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        if self.inner.handle.is_in_graph_cf(graph_name) {
+            if self.snapshot.is_some() {
+                self.cleared_graphs.insert(graph_name);
+            }
+            self.changes.clear_graph(graph_name);
+            return self.inner.clear_graph_cf(graph_name);
+        }
+        for quad in self
+            .inner
+            .handle
+            .encoded_quads_for_pattern(None, None, None, Some(graph_name))
+        {
+            self.remove_encoded(&quad?)?;
+        }
+        // The quads inserted by the transaction are not in the store yet
+        for quad in self.inner.pending_quads_in_graph(graph_name) {
+            self.remove_encoded(&quad)?;
+        }
+        Ok(())
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.clear_encoded_graph(graph_name)?;
+        self.record_graph_write(graph_name);
+        self.changes.remove_named_graph(graph_name);
+        self.inner.remove_named_graph(graph_name);
+        Ok(())
+    }
+}
+
+impl CommitLogTarget for RocksDbTransaction<'_> {
+    fn set_encoded_graph_expiration(
+        &mut self,
+        graph_name: EncodedTerm,
+        expiration: Option<SystemTime>,
+    ) -> Result<()> {
+        self.inner.set_graph_expiration(graph_name, expiration)
+    }
 }
 
-impl RocksDbTransaction<'_> {
+impl<'a> RocksDbTransaction<'a> {
+    /// The snapshot of the store taken at the beginning of the transaction.
+    ///
+    /// It is the state the transaction commit is checked against.
+    /// It is `None` if the transaction is not executed with the snapshot isolation.
+    pub fn snapshot(&self) -> Option<&RocksDbSnapshot<'a>> {
+        self.snapshot.as_ref()
+    }
+
     /// Loads a graph file (i.e. triples) into the store during the transaction.
     ///
     /// Warning: Because the load happens during a transaction,
@@ -544,7 +3856,7 @@ impl RocksDbTransaction<'_> {
         load_graph(self, reader, syntax, to_graph_name, base_iri)
     }
 
-    /// Loads a dataset file (i.e. quads) into the store. into the store during the transaction.
+    /// Loads a dataset file (i.e. quads) into the store during the transaction.
     ///
     /// Warning: Because the load happens during a transaction,
     /// the full file content might be temporarily stored in main memory.
@@ -566,14 +3878,102 @@ impl RocksDbTransaction<'_> {
         self.insert_encoded(&quad)
     }
 
+    /// Adds a quad to this store with some provenance metadata during the transaction.
+    ///
+    /// See `RocksDbStore::insert_with_provenance` for a usage example.
+    pub fn insert_with_provenance(
+        &mut self,
+        quad: &Quad,
+        provenance: &QuadProvenance,
+    ) -> Result<()> {
+        let quad = self.encode_quad(quad)?;
+        self.insert_encoded(&quad)?;
+        self.inner.set_provenance(&quad, provenance)
+    }
+
     /// Removes a quad from this store during the transaction.
     pub fn remove(&mut self, quad: &Quad) -> Result<()> {
         let quad = quad.into();
         self.remove_encoded(&quad)
     }
 
-    fn commit(self) -> Result<()> {
-        self.inner.commit()
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) evaluated against the store with the changes of the transaction done so far.
+    ///
+    /// With the snapshot isolation, the store is read from the snapshot of the transaction.
+    /// Otherwise, it is read from a snapshot taken when the query is prepared.
+    ///
+    /// See `MemoryTransaction` for a usage example.
+    pub fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<RocksDbTransactionPreparedQuery<'_>> {
+        let snapshot = match &self.snapshot {
+            Some(snapshot) => snapshot.clone(),
+            None => self.store.snapshot(),
+        };
+        Ok(RocksDbTransactionPreparedQuery(SimplePreparedQuery::new(
+            PendingChangesView::new(snapshot, &self.changes),
+            query,
+            options,
+        )?))
+    }
+
+    /// Adds a named graph to this store during the transaction.
+    pub fn insert_named_graph(&mut self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        let graph_name = self.encode_named_or_blank_node(graph_name)?;
+        self.insert_encoded_named_graph(graph_name)
+    }
+
+    /// Removes a named graph and all its quads from this store during the transaction.
+    pub fn remove_named_graph(&mut self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        self.remove_encoded_named_graph(graph_name.into())
+    }
+
+    fn record_write(&mut self, quad: &EncodedQuad) {
+        if self.snapshot.is_some() {
+            self.written.insert(*quad);
+        }
+    }
+
+    fn record_graph_write(&mut self, graph_name: EncodedTerm) {
+        if self.snapshot.is_some() {
+            self.written_graphs.insert(graph_name);
+        }
+    }
+
+    /// Writes the transaction and returns `false` if a quad it writes has been changed since its snapshot
+    ///
+    /// The graphs cleared by dropping their column family are compared using their quads count.
+    fn commit(mut self) -> Result<bool> {
+        let _lock = self.inner.handle.lock_commits();
+        if let Some(snapshot) = &self.snapshot {
+            let snapshot = snapshot.handle();
+            for quad in &self.written {
+                if snapshot.contains(quad)? != self.inner.handle.contains(quad)? {
+                    return Ok(false);
+                }
+            }
+            for graph_name in &self.written_graphs {
+                if snapshot.contains_named_graph(*graph_name)?
+                    != self.inner.handle.contains_named_graph(*graph_name)?
+                {
+                    return Ok(false);
+                }
+            }
+            for graph_name in &self.cleared_graphs {
+                if snapshot.statistic(GRAPH_QUADS_COUNT, *graph_name)?
+                    != self
+                        .inner
+                        .handle
+                        .statistic(GRAPH_QUADS_COUNT, *graph_name)?
+                {
+                    return Ok(false);
+                }
+            }
+        }
+        self.inner.write_batch_locked()?;
+        Ok(true)
     }
 }
 
@@ -582,9 +3982,8 @@ struct RocksDbAutoTransaction<'a> {
 }
 
 impl StrContainer for RocksDbAutoTransaction<'_> {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
-        self.inner.insert_str(key, value);
-        Ok(())
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        self.inner.insert_str(key, value)
     }
 }
 
@@ -598,6 +3997,33 @@ impl WritableEncodedStore for RocksDbAutoTransaction<'_> {
         self.inner.remove(quad)?;
         self.commit_if_big()
     }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.inner.insert_named_graph(graph_name);
+        self.commit_if_big()
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        if self.inner.handle.is_in_graph_cf(graph_name) {
+            self.inner.clear_graph_cf(graph_name)?;
+            return self.commit_if_big();
+        }
+        for quad in self
+            .inner
+            .handle
+            .encoded_quads_for_pattern(None, None, None, Some(graph_name))
+        {
+            self.inner.remove(&quad?)?;
+            self.commit_if_big()?;
+        }
+        Ok(())
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.clear_encoded_graph(graph_name)?;
+        self.inner.remove_named_graph(graph_name);
+        self.commit_if_big()
+    }
 }
 
 impl RocksDbAutoTransaction<'_> {
@@ -606,93 +4032,735 @@ impl RocksDbAutoTransaction<'_> {
     }
 
     fn commit_if_big(&mut self) -> Result<()> {
-        if self.inner.batch.len() > MAX_TRANSACTION_SIZE {
-            self.inner.handle.db.write(take(&mut self.inner.batch))?;
+        if self.inner.batch.len() + self.inner.graph_writes.len() > MAX_TRANSACTION_SIZE {
+            self.inner.write_batch()?;
         }
         Ok(())
     }
 }
 
-struct RocksDbInnerTransaction<'a> {
+/// Writes the quads in big batches sorted by index without going through the write-ahead log
+///
+/// The statistics are not maintained during the load but rebuilt at the end.
+struct RocksDbBulkLoader<'a> {
     handle: RocksDbStoreHandle<'a>,
-    batch: WriteBatch,
-    buffer: Vec<u8>,
+    id2str: HashMap<StrHash, String>,
+    quads: Vec<EncodedQuad>,
 }
 
-impl RocksDbInnerTransaction<'_> {
-    fn insert_str(&mut self, key: StrHash, value: &str) {
-        self.batch
-            .put_cf(self.handle.id2str_cf, &key.to_be_bytes(), value)
+impl StrContainer for RocksDbBulkLoader<'_> {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if let Some(stored) = self.id2str.get(&key) {
+            return check_str_hash_collision(key, stored.as_bytes(), value);
+        }
+        self.id2str.insert(key, value.to_owned());
+        Ok(())
     }
+}
 
-    fn insert(&mut self, quad: &EncodedQuad) -> Result<()> {
-        write_spog_quad(&mut self.buffer, quad);
-        self.batch.put_cf(self.handle.spog_cf, &self.buffer, &[]);
-        self.buffer.clear();
-
-        write_posg_quad(&mut self.buffer, quad);
-        self.batch.put_cf(self.handle.posg_cf, &self.buffer, &[]);
-        self.buffer.clear();
+impl WritableEncodedStore for RocksDbBulkLoader<'_> {
+    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.quads.push(*quad);
+        if self.quads.len() >= BULK_LOAD_BATCH_SIZE {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
 
-        write_ospg_quad(&mut self.buffer, quad);
-        self.batch.put_cf(self.handle.ospg_cf, &self.buffer, &[]);
-        self.buffer.clear();
+    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        self.write_batch()?;
+        let mut transaction = self.handle.auto_transaction();
+        transaction.remove_encoded(quad)?;
+        transaction.commit()
+    }
 
-        write_gspo_quad(&mut self.buffer, quad);
-        self.batch.put_cf(self.handle.gspo_cf, &self.buffer, &[]);
-        self.buffer.clear();
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_batch()?;
+        let mut transaction = self.handle.auto_transaction();
+        transaction.insert_encoded_named_graph(graph_name)?;
+        transaction.commit()
+    }
 
-        write_gpos_quad(&mut self.buffer, quad);
-        self.batch.put_cf(self.handle.gpos_cf, &self.buffer, &[]);
-        self.buffer.clear();
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_batch()?;
+        let mut transaction = self.handle.auto_transaction();
+        transaction.clear_encoded_graph(graph_name)?;
+        transaction.commit()
+    }
 
-        write_gosp_quad(&mut self.buffer, quad);
-        self.batch.put_cf(self.handle.gosp_cf, &self.buffer, &[]);
-        self.buffer.clear();
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.write_batch()?;
+        let mut transaction = self.handle.auto_transaction();
+        transaction.remove_encoded_named_graph(graph_name)?;
+        transaction.commit()
+    }
+}
+
+impl RocksDbBulkLoader<'_> {
+    fn write_batch(&mut self) -> Result<()> {
+        let mut batch = WriteBatch::default();
+
+        let mut id2str = self.id2str.drain().collect::<Vec<_>>();
+        id2str.sort_unstable_by_key(|(key, _)| key.to_be_bytes());
+        for (key, value) in id2str {
+            if let Some(stored) = self
+                .handle
+                .db
+                .get_pinned_cf(self.handle.id2str_cf, &key.to_be_bytes())?
+            {
+                check_str_hash_collision(key, &stored, &value)?;
+            } else {
+                batch.put_cf(self.handle.id2str_cf, &key.to_be_bytes(), value);
+            }
+        }
+
+        let quads = take(&mut self.quads);
+        let mut graphs = quads
+            .iter()
+            .map(|quad| quad.graph_name)
+            .filter(|graph_name| *graph_name != ENCODED_DEFAULT_GRAPH)
+            .map(|graph_name| {
+                let mut key = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
+                write_term(&mut key, graph_name);
+                key
+            })
+            .collect::<Vec<_>>();
+        graphs.sort_unstable();
+        graphs.dedup();
+        for key in graphs {
+            batch.put_cf(self.handle.graphs_cf, &key, &[]);
+        }
+
+        // The column families of the named graphs are not dropped before the batch is written
+        let mut graph_cfs = write_graph_cfs(self.handle.graph_cfs);
+        let mut cfs = HashMap::new();
+        for index in self.handle.maintained_indexes() {
+            let mut keys = quads
+                .iter()
+                .map(|quad| {
+                    let mut key = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE + 1);
+                    let graph_name = if self.handle.is_in_graph_cf(quad.graph_name) {
+                        key.push(index.tag);
+                        Some(quad.graph_name)
+                    } else {
+                        None
+                    };
+                    (index.write)(&mut key, quad);
+                    (graph_name, key)
+                })
+                .collect::<Vec<_>>();
+            keys.sort_unstable_by(|(_, a), (_, b)| a.cmp(b));
+            keys.dedup_by(|(_, a), (_, b)| a == b);
+            for (graph_name, key) in keys {
+                let cf = match graph_name {
+                    Some(graph_name) => match cfs.entry(graph_name) {
+                        Entry::Occupied(entry) => *entry.get(),
+                        Entry::Vacant(entry) => {
+                            *entry.insert(self.handle.create_graph_cf(&mut graph_cfs, graph_name)?)
+                        }
+                    },
+                    None => index.cf,
+                };
+                batch.put_cf(cf, &key, &[]);
+            }
+        }
+
+        let mut options = WriteOptions::default();
+        options.disable_wal(true);
+        self.handle.db.write_opt(batch, &options)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.write_batch()?;
+        // The written data is only in the memtables because the write-ahead log has been bypassed
+        self.handle.db.flush_cf(self.handle.id2str_cf)?;
+        self.handle.db.flush_cf(self.handle.graphs_cf)?;
+        for index in self.handle.maintained_indexes() {
+            self.handle.db.flush_cf(index.cf)?;
+        }
+        for cf in self.handle.all_graph_cfs() {
+            self.handle.db.flush_cf(cf)?;
+        }
+        self.handle.rebuild_statistics()
+    }
+}
+
+struct RocksDbInnerTransaction<'a> {
+    handle: RocksDbStoreHandle<'a>,
+    batch: WriteBatch,
+    buffer: Vec<u8>,
+    /// The changes to apply to the statistics
+    statistics: HashMap<(u8, EncodedTerm), i64>,
+    /// The quads modified by the not yet written batch and if they are in the store after it
+    pending: HashMap<EncodedQuad, bool>,
+    /// The number of quads of the not yet written batch in the store after it per subject and predicate
+    pending_subject_predicates: HashMap<(EncodedTerm, EncodedTerm), usize>,
+    /// The number of quads of the not yet written batch in the store after it per predicate and object
+    pending_predicate_objects: HashMap<(EncodedTerm, EncodedTerm), usize>,
+    /// The changes of the not yet written batch to append to the commit log, if any
+    log: Option<CommitLogRecord>,
+    /// The changes of the not yet written batch to send to the watchers, if any
+    events: Option<Vec<EncodedQuadEvent>>,
+    /// The strings added by the not yet written batch, to index the words of the new literals
+    #[cfg(feature = "full-text")]
+    strings: HashMap<StrHash, String>,
+    /// The keys to write in the column families of the named graphs with their values, `None` for the removals
+    ///
+    /// They are added to the batch when it is written because the column families may be created or dropped before.
+    graph_writes: Vec<(EncodedTerm, Vec<u8>, Option<Vec<u8>>)>,
+    /// The named graphs whose column family is dropped before the batch is written
+    cleared_graphs: HashSet<EncodedTerm>,
+}
+
+impl<'a> RocksDbInnerTransaction<'a> {
+    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if let Some(stored) = self
+            .handle
+            .db
+            .get_pinned_cf(self.handle.id2str_cf, &key.to_be_bytes())?
+        {
+            return check_str_hash_collision(key, &stored, value);
+        }
+        if let Some(log) = &mut self.log {
+            log.insert_str(key, value);
+        }
+        #[cfg(feature = "full-text")]
+        self.strings.insert(key, value.to_owned());
+        self.batch
+            .put_cf(self.handle.id2str_cf, &key.to_be_bytes(), value);
+        Ok(())
+    }
+
+    fn insert(&mut self, quad: &EncodedQuad) -> Result<()> {
+        if let Some(log) = &mut self.log {
+            log.insert(quad);
+        }
+        if self.update_statistics_for_insert(quad)? {
+            if let Some(events) = &mut self.events {
+                events.push(EncodedQuadEvent::Inserted(*quad));
+            }
+        }
+
+        if quad.graph_name != ENCODED_DEFAULT_GRAPH {
+            self.put_named_graph(quad.graph_name);
+        }
+
+        for index in self.handle.maintained_indexes() {
+            self.write_quad_key(index.cf, index.tag, index.write, quad, Some(&[]));
+        }
 
         Ok(())
     }
 
     fn remove(&mut self, quad: &EncodedQuad) -> Result<()> {
-        write_spog_quad(&mut self.buffer, quad);
-        self.batch.delete_cf(self.handle.spog_cf, &self.buffer);
-        self.buffer.clear();
+        if let Some(log) = &mut self.log {
+            log.remove(quad);
+        }
+        if self.update_statistics_for_remove(quad)? {
+            if let Some(events) = &mut self.events {
+                events.push(EncodedQuadEvent::Removed(*quad));
+            }
+            self.write_quad_key(
+                self.handle.provenance_cf,
+                PROVENANCE_TAG,
+                write_spog_quad,
+                quad,
+                None,
+            );
+        }
 
-        write_posg_quad(&mut self.buffer, quad);
-        self.batch.delete_cf(self.handle.posg_cf, &self.buffer);
-        self.buffer.clear();
+        for index in self.handle.maintained_indexes() {
+            self.write_quad_key(index.cf, index.tag, index.write, quad, None);
+        }
+
+        Ok(())
+    }
+
+    fn set_provenance(&mut self, quad: &EncodedQuad, provenance: &QuadProvenance) -> Result<()> {
+        let mut value = Vec::default();
+        provenance.write(&mut value)?;
+        self.write_quad_key(
+            self.handle.provenance_cf,
+            PROVENANCE_TAG,
+            write_spog_quad,
+            quad,
+            Some(&value),
+        );
+        Ok(())
+    }
 
-        write_ospg_quad(&mut self.buffer, quad);
-        self.batch.delete_cf(self.handle.ospg_cf, &self.buffer);
+    /// Writes the key of a quad written with `write`, or removes it if `value` is `None`
+    ///
+    /// The key is prefixed with `tag` if the quad is stored in the column family of its graph.
+    fn write_quad_key(
+        &mut self,
+        cf: ColumnFamilyRef<'a>,
+        tag: u8,
+        write: fn(&mut Vec<u8>, &EncodedQuad),
+        quad: &EncodedQuad,
+        value: Option<&[u8]>,
+    ) {
+        if self.handle.is_in_graph_cf(quad.graph_name) {
+            let mut key = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE + 1);
+            key.push(tag);
+            write(&mut key, quad);
+            self.graph_writes
+                .push((quad.graph_name, key, value.map(<[u8]>::to_vec)));
+            return;
+        }
+        write(&mut self.buffer, quad);
+        if let Some(value) = value {
+            self.batch.put_cf(cf, &self.buffer, value);
+        } else {
+            self.batch.delete_cf(cf, &self.buffer);
+        }
         self.buffer.clear();
+    }
+
+    /// Removes all the quads of a named graph stored in a column family of its own by dropping it when the batch is written
+    ///
+    /// Only the quads count of the graph is updated in the statistics, not the ones of the predicates.
+    fn clear_graph_cf(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        if let Some(log) = &mut self.log {
+            log.clear_graph(graph_name);
+        }
+        let is_already_cleared = !self.cleared_graphs.insert(graph_name);
+        let mut removed = Vec::new();
+        if self.events.is_some() && !is_already_cleared {
+            for quad in self
+                .handle
+                .encoded_quads_for_pattern(None, None, None, Some(graph_name))
+            {
+                let quad = quad?;
+                if !self.pending.contains_key(&quad) {
+                    removed.push(quad);
+                }
+            }
+        }
+        // The quads inserted by the not yet written batch are removed one by one
+        for quad in self.pending_quads_in_graph(graph_name) {
+            self.update_statistics_for_remove(&quad)?;
+            removed.push(quad);
+        }
+        if let Some(events) = &mut self.events {
+            events.extend(removed.into_iter().map(EncodedQuadEvent::Removed));
+        }
+        self.graph_writes
+            .retain(|(graph, _, _)| *graph != graph_name);
+        self.statistics.insert((GRAPH_QUADS_COUNT, graph_name), 0);
+        Ok(())
+    }
+
+    fn insert_named_graph(&mut self, graph_name: EncodedTerm) {
+        if let Some(log) = &mut self.log {
+            log.insert_named_graph(graph_name);
+        }
+        self.put_named_graph(graph_name);
+    }
 
-        write_gspo_quad(&mut self.buffer, quad);
-        self.batch.delete_cf(self.handle.gspo_cf, &self.buffer);
+    fn put_named_graph(&mut self, graph_name: EncodedTerm) {
+        write_term(&mut self.buffer, graph_name);
+        self.batch.put_cf(self.handle.graphs_cf, &self.buffer, &[]);
         self.buffer.clear();
+    }
 
-        write_gpos_quad(&mut self.buffer, quad);
-        self.batch.delete_cf(self.handle.gpos_cf, &self.buffer);
+    fn remove_named_graph(&mut self, graph_name: EncodedTerm) {
+        if let Some(log) = &mut self.log {
+            log.remove_named_graph(graph_name);
+        }
+        write_term(&mut self.buffer, graph_name);
+        self.batch.delete_cf(self.handle.graphs_cf, &self.buffer);
+        self.batch
+            .delete_cf(self.handle.expirations_cf, &self.buffer);
         self.buffer.clear();
+    }
 
-        write_gosp_quad(&mut self.buffer, quad);
-        self.batch.delete_cf(self.handle.gosp_cf, &self.buffer);
+    fn set_graph_expiration(
+        &mut self,
+        graph_name: EncodedTerm,
+        expiration: Option<SystemTime>,
+    ) -> Result<()> {
+        write_term(&mut self.buffer, graph_name);
+        if let Some(expiration) = expiration {
+            let mut value = Vec::with_capacity(12);
+            write_timestamp(&mut value, expiration)?;
+            self.batch
+                .put_cf(self.handle.expirations_cf, &self.buffer, value);
+        } else {
+            self.batch
+                .delete_cf(self.handle.expirations_cf, &self.buffer);
+        }
         self.buffer.clear();
+        if let Some(log) = &mut self.log {
+            log.set_graph_expiration(graph_name, expiration)?;
+        }
+        Ok(())
+    }
+
+    /// Updates the statistics before inserting a quad and returns if the quad is not already in the store
+    ///
+    /// The statistics take into account the changes of the not yet written batch.
+    fn update_statistics_for_insert(&mut self, quad: &EncodedQuad) -> Result<bool> {
+        if self.contains(quad)? {
+            return Ok(false);
+        }
+        let is_new_subject = !self.contains_subject_predicate(quad.subject, quad.predicate)?;
+        let is_new_object = !self.contains_predicate_object(quad.predicate, quad.object)?;
+        self.set_pending(quad, true);
+        self.add_to_statistic(PREDICATE_QUADS_COUNT, quad.predicate, 1);
+        self.add_to_statistic(GRAPH_QUADS_COUNT, quad.graph_name, 1);
+        if is_new_subject {
+            self.add_to_statistic(PREDICATE_DISTINCT_SUBJECTS_COUNT, quad.predicate, 1);
+        }
+        if is_new_object {
+            self.add_to_statistic(PREDICATE_DISTINCT_OBJECTS_COUNT, quad.predicate, 1);
+        }
+        #[cfg(feature = "full-text")]
+        {
+            if is_string_literal(quad.object) {
+                self.add_to_statistic(LITERAL_QUADS_COUNT, quad.object, 1);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Updates the statistics before removing a quad and returns if the quad is in the store
+    ///
+    /// The statistics take into account the changes of the not yet written batch.
+    fn update_statistics_for_remove(&mut self, quad: &EncodedQuad) -> Result<bool> {
+        if !self.contains(quad)? {
+            return Ok(false);
+        }
+        self.set_pending(quad, false);
+        self.add_to_statistic(PREDICATE_QUADS_COUNT, quad.predicate, -1);
+        self.add_to_statistic(GRAPH_QUADS_COUNT, quad.graph_name, -1);
+        if !self.contains_subject_predicate(quad.subject, quad.predicate)? {
+            self.add_to_statistic(PREDICATE_DISTINCT_SUBJECTS_COUNT, quad.predicate, -1);
+        }
+        if !self.contains_predicate_object(quad.predicate, quad.object)? {
+            self.add_to_statistic(PREDICATE_DISTINCT_OBJECTS_COUNT, quad.predicate, -1);
+        }
+        #[cfg(feature = "full-text")]
+        {
+            if is_string_literal(quad.object) {
+                self.add_to_statistic(LITERAL_QUADS_COUNT, quad.object, -1);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Records if a quad is in the store after the not yet written batch
+    fn set_pending(&mut self, quad: &EncodedQuad, contained: bool) {
+        let subject_predicate = (quad.subject, quad.predicate);
+        let predicate_object = (quad.predicate, quad.object);
+        if self.pending.insert(*quad, contained) == Some(true) {
+            decrement_pending_count(&mut self.pending_subject_predicates, subject_predicate);
+            decrement_pending_count(&mut self.pending_predicate_objects, predicate_object);
+        }
+        if contained {
+            *self
+                .pending_subject_predicates
+                .entry(subject_predicate)
+                .or_default() += 1;
+            *self
+                .pending_predicate_objects
+                .entry(predicate_object)
+                .or_default() += 1;
+        }
+    }
+
+    /// Checks if the store contains a quad with the given subject and predicate after the not yet written batch
+    fn contains_subject_predicate(
+        &self,
+        subject: EncodedTerm,
+        predicate: EncodedTerm,
+    ) -> Result<bool> {
+        if self
+            .pending_subject_predicates
+            .contains_key(&(subject, predicate))
+        {
+            return Ok(true);
+        }
+        self.contains_not_pending_removal(
+            self.handle.quads_for_subject_predicate(subject, predicate),
+        )
+    }
+
+    /// Checks if the store contains a quad with the given predicate and object after the not yet written batch
+    fn contains_predicate_object(
+        &self,
+        predicate: EncodedTerm,
+        object: EncodedTerm,
+    ) -> Result<bool> {
+        if self
+            .pending_predicate_objects
+            .contains_key(&(predicate, object))
+        {
+            return Ok(true);
+        }
+        self.contains_not_pending_removal(self.handle.quads_for_predicate_object(predicate, object))
+    }
+
+    /// Checks if one of the stored quads is not removed by the not yet written batch
+    ///
+    /// At most the quads removed by the batch, one by one or with their graph, are skipped.
+    fn contains_not_pending_removal(
+        &self,
+        quads: impl Iterator<Item = Result<EncodedQuad>>,
+    ) -> Result<bool> {
+        for quad in quads {
+            let quad = quad?;
+            let contained = match self.pending.get(&quad) {
+                Some(contained) => *contained,
+                None => !self.cleared_graphs.contains(&quad.graph_name),
+            };
+            if contained {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The quads of a graph inserted by the not yet written batch
+    fn pending_quads_in_graph(&self, graph_name: EncodedTerm) -> Vec<EncodedQuad> {
+        self.pending
+            .iter()
+            .filter(|(quad, contained)| **contained && quad.graph_name == graph_name)
+            .map(|(quad, _)| *quad)
+            .collect()
+    }
+
+    /// Checks if the store contains a quad after the not yet written batch
+    fn contains(&self, quad: &EncodedQuad) -> Result<bool> {
+        if let Some(contained) = self.pending.get(quad) {
+            Ok(*contained)
+        } else if self.cleared_graphs.contains(&quad.graph_name) {
+            Ok(false)
+        } else {
+            self.handle.contains(quad)
+        }
+    }
+
+    fn add_to_statistic(&mut self, kind: u8, term: EncodedTerm, delta: i64) {
+        *self.statistics.entry((kind, term)).or_default() += delta;
+    }
 
+    /// Adds the new values of the modified statistics to the batch
+    ///
+    /// The string literals used by the first quad or by no quad anymore are added to or removed from the words index.
+    fn write_statistics(&mut self) -> Result<()> {
+        self.pending.clear();
+        self.pending_subject_predicates.clear();
+        self.pending_predicate_objects.clear();
+        #[cfg(feature = "full-text")]
+        let mut indexed_literals = Vec::new();
+        for ((kind, term), delta) in self.statistics.drain() {
+            // The quads count of the cleared graphs starts again from 0
+            let is_cleared = kind == GRAPH_QUADS_COUNT && self.cleared_graphs.contains(&term);
+            if delta != 0 || is_cleared {
+                let old_value = if is_cleared {
+                    0
+                } else {
+                    self.handle.statistic(kind, term)?
+                };
+                let value = (old_value as i64 + delta).max(0) as u64;
+                self.batch.put_cf(
+                    self.handle.stats_cf,
+                    &statistic_key(kind, term),
+                    &value.to_be_bytes(),
+                );
+                #[cfg(feature = "full-text")]
+                {
+                    if kind == LITERAL_QUADS_COUNT && (old_value == 0) != (value == 0) {
+                        indexed_literals.push((term, value > 0));
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "full-text")]
+        {
+            let strings = BatchStrLookup {
+                handle: &self.handle,
+                strings: &self.strings,
+            };
+            let mut words = HashMap::<_, i64>::default();
+            for (literal, insert) in indexed_literals {
+                for word in
+                    self.handle
+                        .write_literal_words(&mut self.batch, &strings, literal, insert)?
+                {
+                    *words.entry(word).or_default() += if insert { 1 } else { -1 };
+                }
+            }
+            for (word, delta) in words {
+                if delta != 0 {
+                    let value = (self.handle.word_statistic(&word)? as i64 + delta).max(0) as u64;
+                    self.batch.put_cf(
+                        self.handle.stats_cf,
+                        &word_statistic_key(&word),
+                        &value.to_be_bytes(),
+                    );
+                }
+            }
+            self.strings.clear();
+        }
         Ok(())
     }
 
-    fn commit(self) -> Result<()> {
-        self.handle.db.write(self.batch)?;
+    /// Writes the batch, appends its changes to the commit log, if any, and sends them to the watchers
+    fn write_batch(&mut self) -> Result<()> {
+        let _lock = self.handle.lock_commits();
+        self.write_batch_locked()
+    }
+
+    /// Same as `write_batch` when the commit lock is already held
+    ///
+    /// The lock makes the statistics updates and the notifications follow the order of the commits.
+    fn write_batch_locked(&mut self) -> Result<()> {
+        self.write_statistics()?;
+        let mut batch = take(&mut self.batch);
+        let graph_cfs = self.write_graph_cfs(&mut batch)?;
+        let handle = &self.handle;
+        match (handle.commit_log, self.log.as_mut().map(take)) {
+            (Some(commit_log), Some(log)) if !log.is_empty() => commit_log.commit(
+                log,
+                handle.durability == RocksDbDurability::Sync,
+                |sequence| {
+                    let mut batch = batch;
+                    batch.put_cf(
+                        handle.stats_cf,
+                        &LOGGED_SEQUENCE_KEY,
+                        &sequence.to_be_bytes(),
+                    );
+                    handle.write(batch)
+                },
+            )?,
+            _ => handle.write(batch)?,
+        }
+        drop(graph_cfs);
+        if let Some(events) = self.events.as_mut().map(take) {
+            handle.watchers.notify(handle, &events)?;
+        }
         Ok(())
     }
+
+    /// Drops the column families of the cleared graphs and adds to the batch the writes to the column families of the named graphs
+    ///
+    /// The returned guard prevents the column families to be created or dropped until the batch is written.
+    fn write_graph_cfs(
+        &mut self,
+        batch: &mut WriteBatch,
+    ) -> Result<Option<RwLockWriteGuard<'a, HashSet<EncodedTerm>>>> {
+        if self.cleared_graphs.is_empty() && self.graph_writes.is_empty() {
+            return Ok(None);
+        }
+        let handle = &self.handle;
+        let mut graph_cfs = write_graph_cfs(handle.graph_cfs);
+        for graph_name in self.cleared_graphs.drain() {
+            handle.drop_graph_cf(&mut graph_cfs, graph_name)?;
+        }
+        let mut cfs = HashMap::new();
+        for (graph_name, key, value) in self.graph_writes.drain(..) {
+            let cf = match cfs.entry(graph_name) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    if value.is_none() && !graph_cfs.contains(&graph_name) {
+                        // There is nothing to remove
+                        continue;
+                    }
+                    *entry.insert(handle.create_graph_cf(&mut graph_cfs, graph_name)?)
+                }
+            };
+            if let Some(value) = value {
+                batch.put_cf(cf, &key, value);
+            } else {
+                batch.delete_cf(cf, &key);
+            }
+        }
+        Ok(Some(graph_cfs))
+    }
+
+    fn commit(mut self) -> Result<()> {
+        self.write_batch()
+    }
+}
+
+/// Returns the smallest key greater than all the keys starting with `prefix` or `None` if there is none
+fn prefix_successor(mut prefix: Vec<u8>) -> Option<Vec<u8>> {
+    while let Some(last) = prefix.pop() {
+        if last < u8::MAX {
+            prefix.push(last + 1);
+            return Some(prefix);
+        }
+    }
+    None
 }
 
 #[allow(clippy::option_expect_used)]
-fn get_cf<'a>(db: &'a DB, name: &str) -> &'a ColumnFamily {
+fn get_cf<'a>(db: &'a DB, name: &str) -> ColumnFamilyRef<'a> {
     db.cf_handle(name)
         .expect("A column family that should exist in RocksDB does not exist")
 }
 
+/// The name of the column family of a named graph in the `ColumnFamilyPerGraph` layout
+fn graph_cf_name(graph_name: EncodedTerm) -> String {
+    format!(
+        "{}{}",
+        GRAPH_CF_PREFIX,
+        hex::encode(encode_term(graph_name))
+    )
+}
+
+/// Returns the named graphs with a column family in the database at `path`, none if the database does not exist yet
+fn graph_column_families(path: &Path) -> HashSet<EncodedTerm> {
+    DB::list_cf(&Options::default(), path)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|name| {
+            let term = hex::decode(name.strip_prefix(GRAPH_CF_PREFIX)?).ok()?;
+            Cursor::new(term).read_term().ok()
+        })
+        .collect()
+}
+
+fn read_graph_cfs(
+    graph_cfs: &RwLock<HashSet<EncodedTerm>>,
+) -> RwLockReadGuard<'_, HashSet<EncodedTerm>> {
+    graph_cfs
+        .read()
+        .expect("the RocksDB graph column families lock has been poisoned because of a panic")
+}
+
+fn write_graph_cfs(
+    graph_cfs: &RwLock<HashSet<EncodedTerm>>,
+) -> RwLockWriteGuard<'_, HashSet<EncodedTerm>> {
+    graph_cfs
+        .write()
+        .expect("the RocksDB graph column families lock has been poisoned because of a panic")
+}
+
+/// Fails if the database could not be read by this version of Oxigraph without being migrated first
+fn check_format_version(path: &Path, version: u64) -> Result<()> {
+    if version < LATEST_FORMAT_VERSION {
+        Err(StorageError::OutdatedFormat {
+            path: path.to_owned(),
+            version,
+            latest: LATEST_FORMAT_VERSION,
+        }
+        .into())
+    } else if version > LATEST_FORMAT_VERSION {
+        Err(StorageError::UnsupportedFormat {
+            path: path.to_owned(),
+            version,
+            latest: LATEST_FORMAT_VERSION,
+        }
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
 fn encode_term(t: EncodedTerm) -> Vec<u8> {
     let mut vec = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
     write_term(&mut vec, t);
@@ -723,30 +4791,185 @@ fn encode_term_quad(t1: EncodedTerm, t2: EncodedTerm, t3: EncodedTerm, t4: Encod
     vec
 }
 
+fn decrement_pending_count(
+    counts: &mut HashMap<(EncodedTerm, EncodedTerm), usize>,
+    key: (EncodedTerm, EncodedTerm),
+) {
+    if let Some(count) = counts.get_mut(&key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(&key);
+        }
+    }
+}
+
+fn statistic_key(kind: u8, term: EncodedTerm) -> Vec<u8> {
+    let mut vec = Vec::with_capacity(1 + WRITTEN_TERM_MAX_SIZE);
+    vec.push(kind);
+    write_term(&mut vec, term);
+    vec
+}
+
+#[cfg(feature = "full-text")]
+fn word_statistic_key(word: &str) -> Vec<u8> {
+    let mut vec = Vec::with_capacity(1 + word.len());
+    vec.push(WORD_LITERALS_COUNT);
+    vec.extend_from_slice(word.as_bytes());
+    vec
+}
+
+/// The key of the words index: the word, a 0 byte and the encoded literal
+#[cfg(feature = "full-text")]
+fn word_literal_key(word: &str, literal: EncodedTerm) -> Vec<u8> {
+    let mut vec = Vec::with_capacity(word.len() + 1 + WRITTEN_TERM_MAX_SIZE);
+    vec.extend_from_slice(word.as_bytes());
+    vec.push(0);
+    write_term(&mut vec, literal);
+    vec
+}
+
+fn decode_statistic(value: &[u8]) -> u64 {
+    let mut buffer = [0; 8];
+    if value.len() == buffer.len() {
+        buffer.copy_from_slice(value);
+    }
+    u64::from_be_bytes(buffer)
+}
+
+/// Returns the quads of an index in the order of its keys, merging the column families it is split between
 struct DecodingIndexIterator<'a> {
-    iter: DBRawIterator<'a>,
-    prefix: Vec<u8>,
+    sources: BinaryHeap<IndexSource<'a>>,
     encoding: QuadEncoding,
+    /// The values the (subject, predicate, object, graph name) components of the returned quads should have, if any
+    filter: [Option<EncodedTerm>; 4],
 }
 
 impl<'a> Iterator for DecodingIndexIterator<'a> {
     type Item = Result<EncodedQuad>;
 
     fn next(&mut self) -> Option<Result<EncodedQuad>> {
-        if let Some(key) = self.iter.key() {
-            if key.starts_with(&self.prefix) {
-                let result = self.encoding.decode(key);
-                self.iter.next();
-                Some(result)
-            } else {
-                None
+        loop {
+            let mut source = self.sources.peek_mut()?;
+            let result = match source.key() {
+                Some(key) => self.encoding.decode(key),
+                None => {
+                    PeekMut::pop(source);
+                    continue;
+                }
+            };
+            source.iter.next();
+            drop(source);
+            if let Ok(quad) = &result {
+                let components = [quad.subject, quad.predicate, quad.object, quad.graph_name];
+                if components
+                    .iter()
+                    .zip(&self.filter)
+                    .any(|(component, filter)| filter.map_or(false, |f| f != *component))
+                {
+                    continue;
+                }
             }
+            return Some(result);
+        }
+    }
+}
+
+/// The keys of an index starting with a prefix in a column family
+struct IndexSource<'a> {
+    iter: DBRawIterator<'a>,
+    /// The prefix of the keys, starting with the tag of the index in the column families of the named graphs
+    prefix: Vec<u8>,
+    /// The length of the tag at the beginning of the keys
+    tag_len: usize,
+}
+
+impl IndexSource<'_> {
+    /// The current key without its tag or `None` if there is no more key starting with the prefix
+    fn key(&self) -> Option<&[u8]> {
+        let key = self.iter.key()?;
+        if key.starts_with(&self.prefix) {
+            Some(&key[self.tag_len..])
         } else {
             None
         }
     }
 }
 
+impl PartialEq for IndexSource<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for IndexSource<'_> {}
+
+impl PartialOrd for IndexSource<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IndexSource<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed in order for the binary heap to return the smallest key first, and the exhausted sources before
+        other.key().cmp(&self.key())
+    }
+}
+
+/// Returns the literals of the words index entries starting with `prefix`
+#[cfg(feature = "full-text")]
+struct DecodingWordLiteralIterator<'a> {
+    iter: DBRawIterator<'a>,
+    prefix: Vec<u8>,
+}
+
+#[cfg(feature = "full-text")]
+impl<'a> Iterator for DecodingWordLiteralIterator<'a> {
+    type Item = Result<EncodedTerm>;
+
+    fn next(&mut self) -> Option<Result<EncodedTerm>> {
+        let key = self.iter.key()?;
+        if !key.starts_with(&self.prefix) {
+            return None;
+        }
+        let result = Cursor::new(&key[self.prefix.len()..]).read_term();
+        self.iter.next();
+        Some(result)
+    }
+}
+
+/// Looks up the strings of the not yet written batch of a transaction before the ones of the store
+#[cfg(feature = "full-text")]
+struct BatchStrLookup<'a, 'b> {
+    handle: &'b RocksDbStoreHandle<'a>,
+    strings: &'b HashMap<StrHash, String>,
+}
+
+#[cfg(feature = "full-text")]
+impl StrLookup for BatchStrLookup<'_, '_> {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
+        if let Some(value) = self.strings.get(&id) {
+            Ok(Some(value.clone()))
+        } else {
+            self.handle.get_stored_str(id)
+        }
+    }
+}
+
+struct DecodingGraphIterator<'a> {
+    iter: DBRawIterator<'a>,
+}
+
+impl<'a> Iterator for DecodingGraphIterator<'a> {
+    type Item = Result<EncodedTerm>;
+
+    fn next(&mut self) -> Option<Result<EncodedTerm>> {
+        let result = Cursor::new(self.iter.key()?).read_term();
+        self.iter.next();
+        Some(result)
+    }
+}
+
 #[test]
 fn store() -> Result<()> {
     use crate::model::*;
@@ -755,7 +4978,7 @@ fn store() -> Result<()> {
     use std::env::temp_dir;
     use std::fs::remove_dir_all;
 
-    let main_s = NamedOrBlankNode::from(BlankNode::default());
+    let main_s = Subject::from(BlankNode::default());
     let main_p = NamedNode::new("http://example.com")?;
     let main_o = Term::from(Literal::from(1));
 
@@ -886,3 +5109,1450 @@ fn store() -> Result<()> {
     remove_dir_all(&repo_path)?;
     Ok(())
 }
+
+#[test]
+fn statistics() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let s1 = NamedNode::new("http://example.com/s1")?;
+    let s2 = NamedNode::new("http://example.com/s2")?;
+    let p = NamedNode::new("http://example.com/p")?;
+    let g = NamedNode::new("http://example.com/g")?;
+    let encoded_p = EncodedTerm::from(&p);
+    let encoded_g = EncodedTerm::from(&g);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&Quad::new(s1.clone(), p.clone(), Literal::from(1), None))?;
+        store.insert(&Quad::new(s1.clone(), p.clone(), Literal::from(1), None))?;
+        store.insert(&Quad::new(s1.clone(), p.clone(), Literal::from(2), None))?;
+        store.insert(&Quad::new(
+            s2.clone(),
+            p.clone(),
+            Literal::from(2),
+            g.clone(),
+        ))?;
+
+        let statistics = store.predicate_statistics(encoded_p).unwrap();
+        assert_eq!(statistics.quads_count, 3);
+        assert_eq!(statistics.distinct_subjects_count, 2);
+        assert_eq!(statistics.distinct_objects_count, 2);
+        assert_eq!(store.graph_quads_count(encoded_g), Some(1));
+        assert_eq!(store.graph_quads_count(ENCODED_DEFAULT_GRAPH), Some(2));
+
+        store.remove(&Quad::new(s1.clone(), p.clone(), Literal::from(1), None))?;
+        store.remove(&Quad::new(s1, p.clone(), Literal::from(1), None))?;
+    }
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        let statistics = store.predicate_statistics(encoded_p).unwrap();
+        assert_eq!(statistics.quads_count, 2);
+        assert_eq!(statistics.distinct_subjects_count, 2);
+        assert_eq!(statistics.distinct_objects_count, 1);
+        assert_eq!(store.graph_quads_count(encoded_g), Some(1));
+        assert_eq!(store.graph_quads_count(ENCODED_DEFAULT_GRAPH), Some(1));
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn statistics_in_same_batch() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let s1 = NamedNode::new("http://example.com/s1")?;
+    let s2 = NamedNode::new("http://example.com/s2")?;
+    let s3 = NamedNode::new("http://example.com/s3")?;
+    let p = NamedNode::new("http://example.com/p")?;
+    let encoded_p = EncodedTerm::from(&p);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.transaction(|transaction| {
+            transaction.insert(&Quad::new(s1.clone(), p.clone(), Literal::from(1), None))?;
+            transaction.insert(&Quad::new(s1.clone(), p.clone(), Literal::from(2), None))?;
+            transaction.insert(&Quad::new(s2.clone(), p.clone(), Literal::from(1), None))
+        })?;
+        let statistics = store.predicate_statistics(encoded_p).unwrap();
+        assert_eq!(statistics.quads_count, 3);
+        assert_eq!(statistics.distinct_subjects_count, 2);
+        assert_eq!(statistics.distinct_objects_count, 2);
+
+        store.transaction(|transaction| {
+            transaction.remove(&Quad::new(s1.clone(), p.clone(), Literal::from(1), None))?;
+            transaction.remove(&Quad::new(s1.clone(), p.clone(), Literal::from(2), None))?;
+            transaction.insert(&Quad::new(s1.clone(), p.clone(), Literal::from(3), None))?;
+            transaction.remove(&Quad::new(s1.clone(), p.clone(), Literal::from(3), None))?;
+            transaction.insert(&Quad::new(s3.clone(), p.clone(), Literal::from(3), None))
+        })?;
+        let statistics = store.predicate_statistics(encoded_p).unwrap();
+        assert_eq!(statistics.quads_count, 2);
+        assert_eq!(statistics.distinct_subjects_count, 2);
+        assert_eq!(statistics.distinct_objects_count, 2);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn len() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g = NamedNode::new("http://example.com/g")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), g.clone());
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        assert!(store.is_empty()?);
+
+        // The same quad inserted twice in a transaction is counted once
+        store.transaction(|transaction| {
+            transaction.insert(&quad1)?;
+            transaction.insert(&quad1)?;
+            transaction.insert(&quad2)?;
+            transaction.remove(&quad2)?;
+            transaction.insert(&quad2)?;
+            Ok(())
+        })?;
+        assert_eq!(store.len()?, 2);
+        assert_eq!(store.len_graph(&GraphName::DefaultGraph)?, 1);
+        assert_eq!(store.len_graph(&g.clone().into())?, 1);
+        assert!(!store.is_empty()?);
+
+        store.remove(&quad1)?;
+        store.remove(&quad1)?;
+    }
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        assert_eq!(store.len()?, 1);
+        assert_eq!(store.len_graph(&GraphName::DefaultGraph)?, 0);
+        assert_eq!(store.len_graph(&g.into())?, 1);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn indexes() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g = NamedNode::new("http://example.com/g")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), g.clone());
+    let graph_name = GraphName::from(g);
+    let object = Term::from(Literal::from(2));
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open_with_indexes(&repo_path, &[])?;
+        store.insert(&quad1)?;
+        store.insert(&quad2)?;
+        // The GSPO index is empty
+        let mut iter = store.db.raw_iterator_cf(get_cf(&store.db, GSPO_CF));
+        iter.seek_to_first();
+        assert!(!iter.valid());
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, Some(&object), Some(&graph_name))
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad2.clone()]
+        );
+        assert_eq!(store.named_graphs().count(), 1);
+    }
+
+    {
+        // The choice is kept
+        let store = RocksDbStore::open(&repo_path)?;
+        assert_eq!(store.indexes, IndexSet::from(&[][..]));
+    }
+
+    {
+        // The missing indexes are built
+        let store = RocksDbStore::open_with_indexes(&repo_path, &RocksDbIndex::ALL)?;
+        assert_eq!(store.indexes, IndexSet::ALL);
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, Some(&object), Some(&graph_name))
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad2]
+        );
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, None, Some(&GraphName::DefaultGraph))
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad1]
+        );
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn transaction() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.transaction(|transaction| {
+            transaction.insert(&quad1)?;
+            transaction.insert(&quad2)
+        })?;
+        assert!(store.contains(&quad1)?);
+        assert!(store.contains(&quad2)?);
+
+        // Nothing is written if the transaction fails
+        assert!(store
+            .transaction(|transaction| {
+                transaction.remove(&quad1)?;
+                transaction.load_graph(
+                    b"<http://example.com> <http://example.com> 3 . foo".as_ref(),
+                    GraphSyntax::NTriples,
+                    &GraphName::DefaultGraph,
+                    None,
+                )
+            })
+            .is_err());
+        assert!(store.contains(&quad1)?);
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, None, None)
+                .collect::<Result<Vec<_>>>()?
+                .len(),
+            2
+        );
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn bulk_load() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let s = NamedNode::new("http://example.com/s")?;
+    let p = NamedNode::new("http://example.com/p")?;
+    let encoded_p = EncodedTerm::from(&p);
+    let data = r#"
+<http://example.com/s> <http://example.com/p> "1" .
+<http://example.com/s> <http://example.com/p> "1" .
+<http://example.com/s> <http://example.com/p> "2" <http://example.com/g> .
+"#;
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&Quad::new(s.clone(), p.clone(), Literal::from(3), None))?;
+        store.bulk_load_dataset(data.as_bytes(), DatasetSyntax::NQuads, None)?;
+        assert!(store.contains(&Quad::new(s.clone(), p.clone(), Literal::from("1"), None))?);
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, None, None)
+                .collect::<Result<Vec<_>>>()?
+                .len(),
+            3
+        );
+        let statistics = store.predicate_statistics(encoded_p).unwrap();
+        assert_eq!(statistics.quads_count, 3);
+        assert_eq!(statistics.distinct_subjects_count, 1);
+        assert_eq!(statistics.distinct_objects_count, 3);
+    }
+
+    {
+        // The loaded data has been persisted even if the write-ahead log has been bypassed
+        let store = RocksDbStore::open(&repo_path)?;
+        assert_eq!(
+            store
+                .quads_for_pattern(Some(&s.into()), None, None, None)
+                .collect::<Result<Vec<_>>>()?
+                .len(),
+            3
+        );
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn backup() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+    let mut backup_path = temp_dir();
+    backup_path.push(random::<u128>().to_string());
+    let mut restore_path = temp_dir();
+    restore_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&quad1)?;
+        store.backup(&backup_path)?;
+
+        // The changes done after the backup are not restored
+        store.insert(&quad2)?;
+        let restored = RocksDbStore::restore(&backup_path, &restore_path)?;
+        assert!(restored.contains(&quad1)?);
+        assert!(!restored.contains(&quad2)?);
+        assert!(store.contains(&quad2)?);
+    }
+
+    remove_dir_all(&repo_path)?;
+    remove_dir_all(&backup_path)?;
+    remove_dir_all(&restore_path)?;
+    Ok(())
+}
+
+#[test]
+fn snapshot() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g = NamedOrBlankNode::from(NamedNode::new("http://example.com/g")?);
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&quad1)?;
+        let snapshot = store.snapshot();
+        store.remove(&quad1)?;
+        store.insert(&quad2)?;
+        store.insert_named_graph(&g)?;
+
+        assert!(snapshot.contains(&quad1)?);
+        assert!(!snapshot.contains(&quad2)?);
+        assert!(!snapshot.contains_named_graph(&g)?);
+        assert_eq!(snapshot.named_graphs().count(), 0);
+        assert_eq!(
+            snapshot
+                .quads_for_pattern(None, None, None, None)
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad1]
+        );
+        let statistics = snapshot
+            .predicate_statistics(EncodedTerm::from(&ex))
+            .unwrap();
+        assert_eq!(statistics.quads_count, 1);
+
+        let query =
+            snapshot.prepare_query("SELECT ?o WHERE { ?s ?p ?o }", QueryOptions::default())?;
+        let result = query.exec()?;
+        if let QueryResult::Solutions(solutions) = result {
+            let solutions = solutions.collect::<Result<Vec<_>>>()?;
+            assert_eq!(solutions.len(), 1);
+            assert_eq!(solutions[0].get("o"), Some(&Literal::from(1).into()));
+        } else {
+            panic!("Expected solutions")
+        }
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn read_only() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&quad1)?;
+
+        // Several read-only stores could be opened while the database is open for writing
+        let read_only1 = RocksDbStore::open_read_only(&repo_path)?;
+        let read_only2 = RocksDbStore::open_read_only(&repo_path)?;
+        assert!(read_only1.contains(&quad1)?);
+        assert!(read_only2.contains(&quad1)?);
+        assert!(read_only1.insert(&quad2).is_err());
+        assert!(!store.contains(&quad2)?);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn named_graphs() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g1 = NamedOrBlankNode::from(NamedNode::new("http://example.com/g1")?);
+    let g2 = NamedOrBlankNode::from(NamedNode::new("http://example.com/g2")?);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&Quad::new(
+            ex.clone(),
+            ex.clone(),
+            ex.clone(),
+            Some(g1.clone().into()),
+        ))?;
+        store.insert_named_graph(&g2)?;
+        assert_eq!(store.named_graphs().count(), 2);
+
+        store.clear_graph(&g1.clone().into())?;
+        assert!(store.contains_named_graph(&g1)?);
+        assert_eq!(store.quads_for_pattern(None, None, None, None).count(), 0);
+    }
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        assert!(store.contains_named_graph(&g1)?);
+        assert!(store.contains_named_graph(&g2)?);
+        store.remove_named_graph(&g1)?;
+        assert_eq!(store.named_graphs().collect::<Result<Vec<_>>>()?, vec![g2]);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn merge_from() -> Result<()> {
+    use crate::model::*;
+    use crate::MemoryStore;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g = NamedNode::new("http://example.com/g")?;
+    let empty = NamedNode::new("http://example.com/empty")?;
+    let quad1 = Quad::new(
+        ex.clone(),
+        ex.clone(),
+        Literal::new_language_tagged_literal("foo", "en")?,
+        None,
+    );
+    let quad2 = Quad::new(
+        BlankNode::new("named")?,
+        ex.clone(),
+        Literal::from(2),
+        g.clone(),
+    );
+
+    let staging = MemoryStore::new();
+    staging.insert(quad1.clone());
+    staging.insert(quad2.clone());
+    staging.insert_named_graph(&empty.clone().into());
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.merge_from(&staging)?;
+        assert_eq!(store.len()?, 2);
+        assert!(store.contains_named_graph(&empty.clone().into())?);
+    }
+
+    {
+        // The strings are stored too
+        let store = RocksDbStore::open(&repo_path)?;
+        let mut quads = store
+            .quads_for_pattern(None, None, None, None)
+            .collect::<Result<Vec<_>>>()?;
+        quads.sort_by_key(|quad| quad.to_string());
+        let mut expected = vec![quad1, quad2];
+        expected.sort_by_key(|quad| quad.to_string());
+        assert_eq!(quads, expected);
+
+        // And back into a memory store
+        let copy = MemoryStore::new();
+        copy.merge_from(&store)?;
+        assert!(copy.is_isomorphic(&staging));
+        assert!(copy.contains_named_graph(&empty.into()));
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn options() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open_with_options(
+            &repo_path,
+            RocksDbOptions::default().with_durability(RocksDbDurability::NoWal),
+        )?;
+        store.insert(&quad)?;
+    }
+
+    {
+        // The options could change between two openings
+        let store = RocksDbStore::open_with_options(
+            &repo_path,
+            RocksDbOptions::default()
+                .with_block_cache_size(1024 * 1024)
+                .with_compression(RocksDbCompression::None)
+                .with_write_buffer_size(1024 * 1024)
+                .with_block_restart_interval(16)
+                .with_bloom_filter_bits_per_key(0)
+                .with_max_background_jobs(1)
+                .with_durability(RocksDbDurability::Sync),
+        )?;
+        assert!(store.contains(&quad)?);
+        store.remove(&quad)?;
+        assert!(store.is_empty()?);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn lock() -> Result<()> {
+    use rand::random;
+    use std::env::temp_dir;
+    use std::error::Error;
+    use std::fs::remove_dir_all;
+    use std::thread::spawn;
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    let store = RocksDbStore::open(&repo_path)?;
+    let error = RocksDbStore::open(&repo_path).err().unwrap();
+    assert!(matches!(
+        error
+            .source()
+            .and_then(|e| e.downcast_ref::<StorageError>()),
+        Some(StorageError::AlreadyLocked { .. })
+    ));
+
+    // The lock is released when the first store is closed
+    let closing = spawn(move || {
+        sleep(Duration::from_millis(200));
+        drop(store);
+    });
+    RocksDbStore::open_with_options(
+        &repo_path,
+        RocksDbOptions::default().with_lock_timeout(Duration::from_secs(10)),
+    )?;
+    closing.join().unwrap();
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn replicate() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::{remove_dir_all, remove_file, OpenOptions};
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g = NamedNode::new("http://example.com/g")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from("foo"), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), ex.clone(), g.clone());
+
+    let mut primary_path = temp_dir();
+    primary_path.push(random::<u128>().to_string());
+    let mut replica_path = temp_dir();
+    replica_path.push(random::<u128>().to_string());
+    let mut new_replica_path = temp_dir();
+    new_replica_path.push(random::<u128>().to_string());
+    let mut log_path = temp_dir();
+    log_path.push(random::<u128>().to_string());
+
+    {
+        let primary = RocksDbStore::open_with_options(
+            &primary_path,
+            RocksDbOptions::default().with_commit_log(&log_path),
+        )?;
+        let replica = RocksDbStore::open(&replica_path)?;
+
+        primary.insert(&quad1)?;
+        primary.transaction(|transaction| {
+            transaction.insert(&quad2)?;
+            transaction.remove(&quad1)
+        })?;
+        assert!(replica.replicate(&log_path)? > 0);
+        assert!(!replica.contains(&quad1)?);
+        assert!(replica.contains(&quad2)?);
+        assert_eq!(replica.replicate(&log_path)?, 0);
+
+        let expiration = SystemTime::now() + Duration::from_secs(60);
+        primary.set_graph_expiration(&g.clone().into(), Some(expiration))?;
+        assert_eq!(replica.replicate(&log_path)?, 1);
+        assert_eq!(
+            replica.graph_expiration(&g.clone().into())?,
+            Some(expiration)
+        );
+
+        primary.remove_named_graph(&g.clone().into())?;
+        assert_eq!(replica.replicate(&log_path)?, 1);
+        assert!(replica.is_empty()?);
+        assert!(!replica.contains_named_graph(&g.clone().into())?);
+        assert!(primary
+            .bulk_load_dataset(b"".as_ref(), DatasetSyntax::NQuads, None)
+            .is_err());
+    }
+
+    // A partially written commit is ignored and then overwritten
+    OpenOptions::new()
+        .append(true)
+        .open(&log_path)?
+        .write_all(&[0, 0, 0])?;
+    {
+        let replica = RocksDbStore::open(&replica_path)?;
+        assert_eq!(replica.replicate(&log_path)?, 0);
+
+        let primary = RocksDbStore::open_with_options(
+            &primary_path,
+            RocksDbOptions::default().with_commit_log(&log_path),
+        )?;
+        primary.insert(&quad1)?;
+        assert_eq!(replica.replicate(&log_path)?, 1);
+        assert!(replica.contains(&quad1)?);
+
+        // The commits applied by the replica are removed from the log
+        primary.truncate_commit_log(replica.replicated_sequence()?)?;
+        primary.insert(&quad2)?;
+        assert_eq!(replica.replicate(&log_path)?, 1);
+        assert!(replica.contains(&quad2)?);
+
+        let new_replica = RocksDbStore::open(&new_replica_path)?;
+        assert!(new_replica.replicate(&log_path).is_err());
+    }
+
+    remove_dir_all(&primary_path)?;
+    remove_dir_all(&replica_path)?;
+    remove_dir_all(&new_replica_path)?;
+    remove_file(&log_path)?;
+    Ok(())
+}
+
+#[test]
+fn watch() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), ex.clone());
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&quad1)?;
+
+        let changes = store.watch();
+        store.transaction(|transaction| {
+            transaction.insert(&quad1)?;
+            transaction.insert(&quad2)?;
+            transaction.remove(&quad1)
+        })?;
+        assert_eq!(
+            changes.try_recv(),
+            Ok(vec![
+                QuadEvent::Inserted(quad2.clone()),
+                QuadEvent::Removed(quad1.clone())
+            ])
+        );
+
+        // Transactions without changes are not reported
+        store.remove(&quad1)?;
+        assert!(changes.try_recv().is_err());
+
+        store.clear_graph(&ex.clone().into())?;
+        assert_eq!(changes.try_recv(), Ok(vec![QuadEvent::Removed(quad2)]));
+
+        drop(changes);
+        store.insert(&quad1)?;
+        assert!(!store.watchers.is_watched());
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn provenance() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+    use std::time::UNIX_EPOCH;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), ex.clone());
+    let provenance = QuadProvenance {
+        source: Some("http://example.com/source".to_owned()),
+        timestamp: Some(UNIX_EPOCH + Duration::new(1_600_000_000, 42)),
+        agent: None,
+    };
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert_with_provenance(&quad1, &QuadProvenance::default())?;
+        store.transaction(|transaction| {
+            transaction.insert_with_provenance(&quad1, &provenance)?;
+            transaction.insert_with_provenance(&quad2, &provenance)
+        })?;
+
+        // A plain insertion keeps the metadata
+        store.insert(&quad2)?;
+    }
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        assert_eq!(store.provenance(&quad1)?, Some(provenance.clone()));
+        assert_eq!(store.provenance(&quad2)?, Some(provenance));
+
+        store.clear_graph(&ex.into())?;
+        assert_eq!(store.provenance(&quad2)?, None);
+        store.insert(&quad2)?;
+        assert_eq!(store.provenance(&quad2)?, None);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn format_version() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::error::Error;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone());
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    // A database written before the format versions, without statistics and named graphs index
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&quad)?;
+        let handle = store.handle();
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(handle.stats_cf, &FORMAT_VERSION_KEY);
+        batch.delete_cf(handle.stats_cf, &STATISTICS_INITIALIZED_KEY);
+        batch.delete_cf(handle.graphs_cf, &encode_term((&ex).into()));
+        handle.write(batch)?;
+    }
+
+    let error = RocksDbStore::open(&repo_path).err().unwrap();
+    assert!(matches!(
+        error
+            .source()
+            .and_then(|e| e.downcast_ref::<StorageError>()),
+        Some(StorageError::OutdatedFormat { version: 0, .. })
+    ));
+    assert!(RocksDbStore::open_read_only(&repo_path).is_err());
+
+    RocksDbStore::migrate(&repo_path)?;
+    RocksDbStore::migrate(&repo_path)?;
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        assert!(store.contains(&quad)?);
+        assert!(store.contains_named_graph(&ex.into())?);
+        assert_eq!(store.len()?, 1);
+
+        store
+            .handle()
+            .set_format_version(LATEST_FORMAT_VERSION + 1)?;
+    }
+
+    let error = RocksDbStore::open(&repo_path).err().unwrap();
+    assert!(matches!(
+        error
+            .source()
+            .and_then(|e| e.downcast_ref::<StorageError>()),
+        Some(StorageError::UnsupportedFormat { .. })
+    ));
+    assert!(RocksDbStore::migrate(&repo_path).is_err());
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn graph_expiration() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g1 = NamedOrBlankNode::from(NamedNode::new("http://example.com/g1")?);
+    let g2 = NamedOrBlankNode::from(NamedNode::new("http://example.com/g2")?);
+    let quad1 = Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(g1.clone().into()));
+    let quad2 = Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(g2.clone().into()));
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&quad1)?;
+        store.insert(&quad2)?;
+        store.set_graph_expiration(&g1, Some(SystemTime::now() - Duration::from_secs(1)))?;
+        store.set_graph_expiration(&g2, Some(SystemTime::now() + Duration::from_secs(3600)))?;
+        assert_eq!(store.remove_expired_graphs()?, 1);
+        assert!(!store.contains(&quad1)?);
+        assert!(!store.contains_named_graph(&g1)?);
+        assert_eq!(store.graph_expiration(&g1)?, None);
+        assert!(store.contains(&quad2)?);
+
+        store.set_graph_expiration(&g2, None)?;
+        assert_eq!(store.graph_expiration(&g2)?, None);
+        store.insert(&quad1)?;
+        store.set_graph_expiration(&g1, Some(SystemTime::now()))?;
+    }
+
+    {
+        // The background sweep removes the graphs expired while the store was closed
+        let store = RocksDbStore::open_with_options(
+            &repo_path,
+            RocksDbOptions::default().with_expiration_sweep_interval(Duration::from_millis(10)),
+        )?;
+        let start = Instant::now();
+        while store.contains(&quad1)? && start.elapsed() < Duration::from_secs(10) {
+            sleep(Duration::from_millis(10));
+        }
+        assert!(!store.contains(&quad1)?);
+        assert!(store.contains(&quad2)?);
+    }
+
+    // The sweep thread does not keep the database open, except during a sweep
+    RocksDbStore::open_with_options(
+        &repo_path,
+        RocksDbOptions::default().with_lock_timeout(Duration::from_secs(1)),
+    )?;
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn inline_short_strings_migration() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("ex:s")?;
+    let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone());
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    // A database of the format version 1 where the short strings are hashed
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        let handle = store.handle();
+        let iri_id = StrHash::from_be_bytes(random::<u128>().to_be_bytes());
+        let term = EncodedTerm::NamedNode { iri_id };
+        let mut transaction = handle.auto_transaction();
+        transaction.insert_stored_str(iri_id, ex.as_str())?;
+        transaction.insert_encoded(&EncodedQuad::new(term, term, term, term))?;
+        transaction.commit()?;
+        handle.set_format_version(1)?;
+    }
+
+    RocksDbStore::migrate(&repo_path)?;
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        assert!(store.contains(&quad)?);
+        assert_eq!(store.len()?, 1);
+        assert_eq!(store.len_graph(&ex.clone().into())?, 1);
+        assert_eq!(
+            store.named_graphs().collect::<Result<Vec<_>>>()?,
+            vec![ex.into()]
+        );
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn validate_and_repair() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com/validate")?;
+    let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone());
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&quad)?;
+        assert!(store.validate()?.is_valid());
+
+        let handle = store.handle();
+        let mut buffer = Vec::new();
+        write_posg_quad(&mut buffer, &(&quad).into());
+        handle.db.delete_cf(handle.posg_cf, &buffer)?;
+        let mut dangling = Vec::new();
+        let missing_id = StrHash::new("http://example.com/missing");
+        let missing = EncodedTerm::NamedNode { iri_id: missing_id };
+        write_spog_quad(
+            &mut dangling,
+            &EncodedQuad::new(missing, missing, missing, ENCODED_DEFAULT_GRAPH),
+        );
+        handle.db.put_cf(handle.spog_cf, &dangling, &[])?;
+
+        let report = store.validate_and_repair()?;
+        assert_eq!(report.incomplete_quads, 1);
+        assert_eq!(report.dangling_quads, 1);
+        assert_eq!(report.missing_named_graphs, 0);
+        assert!(store.validate()?.is_valid());
+        assert_eq!(store.len()?, 1);
+        assert_eq!(
+            store
+                .quads_for_pattern(None, Some(&ex), None, None)
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad]
+        );
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn stats() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let s = NamedNode::new("http://example.com/s")?;
+    let p = NamedNode::new("http://example.com/p")?;
+    let g = NamedNode::new("http://example.com/g")?;
+    let long = Literal::new_simple_literal("a string too long to be inlined");
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    for indexes in &[&[][..], &[RocksDbIndex::OSPG][..]] {
+        let store = RocksDbStore::open_with_indexes(&repo_path, indexes)?;
+        // The datatypes used by the literal encoding are always stored
+        let initial = store.stats()?;
+        assert_eq!(initial.quads_count, 0);
+        assert_eq!(initial.distinct_subjects_count, 0);
+
+        store.insert(&Quad::new(s.clone(), p.clone(), long.clone(), None))?;
+        store.insert(&Quad::new(s.clone(), p.clone(), s.clone(), g.clone()))?;
+        store.insert(&Quad::new(p.clone(), p.clone(), s.clone(), None))?;
+        store.insert_named_graph(&s.clone().into())?;
+
+        let stats = store.stats()?;
+        assert_eq!(stats.quads_count, 3);
+        assert_eq!(stats.distinct_subjects_count, 2);
+        assert_eq!(stats.distinct_predicates_count, 1);
+        assert_eq!(stats.distinct_objects_count, 2);
+        assert_eq!(stats.named_graphs_count, 2);
+        assert_eq!(stats.strings_count, initial.strings_count + 4);
+        drop(store);
+        remove_dir_all(&repo_path)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn transaction_isolation() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let other = Quad::new(ex.clone(), ex.clone(), Literal::from(2), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+
+        // A quad changed by another write since the beginning of the transaction is a conflict
+        assert!(store
+            .transaction(|transaction| {
+                store.insert(&quad)?;
+                transaction.remove(&quad)
+            })
+            .is_err());
+        assert!(store.contains(&quad)?);
+
+        // The other quads could still be written
+        store.transaction(|transaction| {
+            store.insert(&other)?;
+            transaction.remove(&quad)
+        })?;
+        assert!(!store.contains(&quad)?);
+        assert!(store.contains(&other)?);
+    }
+
+    {
+        let store = RocksDbStore::open_with_options(
+            &repo_path,
+            RocksDbOptions::default().with_isolation(RocksDbIsolation::ReadCommitted),
+        )?;
+        store.transaction(|transaction| {
+            store.insert(&quad)?;
+            transaction.remove(&quad)
+        })?;
+        assert!(!store.contains(&quad)?);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn optimistic_transaction() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let graph = NamedOrBlankNode::from(NamedNode::new("http://example.com/g")?);
+    let quad = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open_with_options(
+            &repo_path,
+            RocksDbOptions::default().with_isolation(RocksDbIsolation::ReadCommitted),
+        )?;
+
+        // The conflicting attempts are retried on a new snapshot
+        let mut attempts = 0;
+        store.optimistic_transaction(3, |transaction| {
+            attempts += 1;
+            assert!(transaction.snapshot().is_some());
+            if attempts == 1 {
+                store.insert(&quad)?;
+            }
+            transaction.remove(&quad)
+        })?;
+        assert_eq!(attempts, 2);
+        assert!(!store.contains(&quad)?);
+
+        // The named graphs changes are conflicts too
+        assert!(store
+            .optimistic_transaction(0, |transaction| {
+                store.insert_named_graph(&graph)?;
+                transaction.remove_named_graph(&graph)
+            })
+            .is_err());
+        assert!(store.contains_named_graph(&graph)?);
+
+        // The transactions with the isolation of the options do not retry
+        store.transaction(|transaction| {
+            assert!(transaction.snapshot().is_none());
+            Ok(())
+        })?;
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn fully_bound_pattern() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), ex.clone());
+    let absent = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    for indexes in &[&RocksDbIndex::ALL[..], &[]] {
+        let store = RocksDbStore::open_with_indexes(&repo_path, indexes)?;
+        store.insert(&quad)?;
+        // The quads are read from the files with their bloom filters
+        store.optimize()?;
+
+        let quads_for = |quad: &Quad| {
+            store
+                .quads_for_pattern(
+                    Some(&ex.clone().into()),
+                    Some(&quad.predicate),
+                    Some(&quad.object),
+                    Some(&quad.graph_name),
+                )
+                .collect::<Result<Vec<_>>>()
+        };
+        assert_eq!(quads_for(&quad)?, vec![quad.clone()]);
+        assert!(quads_for(&absent)?.is_empty());
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn transaction_queries() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let graph = NamedNode::new("http://example.com/g")?;
+    let old = Quad::new(ex.clone(), ex.clone(), Literal::from(1), graph.clone());
+    let new = Quad::new(ex.clone(), ex.clone(), Literal::from("a new string"), None);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.insert(&old)?;
+
+        let objects = |transaction: &RocksDbTransaction<'_>| -> Result<Vec<Term>> {
+            let query = transaction.prepare_query(
+                "SELECT ?o WHERE { { ?s ?p ?o } UNION { GRAPH ?g { ?s ?p ?o } } }",
+                QueryOptions::default(),
+            )?;
+            let mut objects = Vec::new();
+            if let QueryResult::Solutions(solutions) = query.exec()? {
+                for solution in solutions {
+                    objects.extend(solution?.get("o").cloned());
+                }
+            }
+            Ok(objects)
+        };
+        store.transaction(|transaction| {
+            // The inserted quads and their new strings are seen by the queries
+            transaction.insert(&new)?;
+            assert_eq!(
+                objects(transaction)?,
+                vec![old.object.clone(), new.object.clone()]
+            );
+
+            // The removed quads are not
+            transaction.remove_named_graph(&graph.clone().into())?;
+            assert_eq!(objects(transaction)?, vec![new.object.clone()]);
+            Ok(())
+        })?;
+        assert!(store.contains(&new)?);
+        assert!(!store.contains(&old)?);
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn update_operations() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let graph = NamedNode::new("http://example.com/g")?;
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store
+            .prepare_update(
+                "INSERT DATA { GRAPH <http://example.com/g> { <http://example.com> <http://example.com> 1 } } ; \
+                 INSERT { <http://example.com> <http://example.com> ?o } WHERE { GRAPH ?g { ?s ?p ?o } } ; \
+                 CLEAR GRAPH <http://example.com/g> ; \
+                 INSERT DATA { GRAPH <http://example.com/g> { <http://example.com> <http://example.com> 2 } }",
+                QueryOptions::default(),
+            )?
+            .exec()?;
+        // Each operation sees the changes of the previous ones
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, None, None)
+                .collect::<Result<HashSet<_>>>()?,
+            vec![
+                Quad::new(ex.clone(), ex.clone(), Literal::from(1), None),
+                Quad::new(ex.clone(), ex.clone(), Literal::from(2), graph),
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "full-text")]
+fn words_index() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let s = NamedNode::new("http://example.com/s")?;
+    let p = NamedNode::new("http://example.com/p")?;
+    let short = Literal::new_simple_literal("foo bar");
+    let long = Literal::new_language_tagged_literal("Foo, baz and a long text", "en")?;
+    let encoded_short = EncodedTerm::from(&short);
+    let encoded_long = EncodedTerm::from(&long);
+    let matching = |store: &RocksDbStore, query: &str| {
+        store
+            .encoded_literals_matching(query)
+            .collect::<Result<HashSet<_>>>()
+    };
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open(&repo_path)?;
+        store.transaction(|transaction| {
+            transaction.insert(&Quad::new(s.clone(), p.clone(), short.clone(), None))?;
+            transaction.insert(&Quad::new(s.clone(), p.clone(), long.clone(), None))?;
+            transaction.insert(&Quad::new(p.clone(), p.clone(), long.clone(), None))
+        })?;
+        assert_eq!(
+            matching(&store, "FOO")?,
+            [encoded_short, encoded_long].iter().copied().collect()
+        );
+        assert_eq!(
+            matching(&store, "baz foo")?,
+            [encoded_long].iter().copied().collect()
+        );
+        assert!(matching(&store, "bar baz")?.is_empty());
+        assert_eq!(store.literals_matching_count("foo"), Some(2));
+        assert_eq!(store.literals_matching_count("bar foo"), Some(1));
+
+        // The literal is indexed until its last quad is removed
+        store.remove(&Quad::new(s.clone(), p.clone(), long.clone(), None))?;
+        assert_eq!(
+            matching(&store, "baz")?,
+            [encoded_long].iter().copied().collect()
+        );
+        store.remove(&Quad::new(p.clone(), p.clone(), long, None))?;
+        assert!(matching(&store, "baz")?.is_empty());
+        assert_eq!(store.literals_matching_count("foo"), Some(1));
+    }
+
+    {
+        // The index is persisted and built again after a bulk load
+        let store = RocksDbStore::open(&repo_path)?;
+        assert_eq!(
+            matching(&store, "foo")?,
+            [encoded_short].iter().copied().collect()
+        );
+        store.bulk_load_dataset(
+            r#"<http://example.com/s> <http://example.com/p> "Foo, baz and a long text"@en ."#
+                .as_bytes(),
+            DatasetSyntax::NQuads,
+            None,
+        )?;
+        assert_eq!(
+            matching(&store, "foo")?,
+            [encoded_short, encoded_long].iter().copied().collect()
+        );
+        assert_eq!(store.literals_matching_count("baz"), Some(1));
+    }
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
+
+#[test]
+fn column_family_per_graph() -> Result<()> {
+    use crate::model::*;
+    use rand::random;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g1 = NamedOrBlankNode::from(NamedNode::new("http://example.com/g1")?);
+    let g2 = NamedOrBlankNode::from(BlankNode::default());
+    let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    let quad1 = Quad::new(ex.clone(), ex.clone(), ex.clone(), Some(g1.clone()));
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), Some(g2.clone()));
+    let options = RocksDbOptions::default().with_layout(RocksDbLayout::ColumnFamilyPerGraph);
+
+    let mut repo_path = temp_dir();
+    repo_path.push(random::<u128>().to_string());
+
+    {
+        let store = RocksDbStore::open_with_options(&repo_path, options.clone())?;
+        store.insert(&quad)?;
+        store.insert(&quad1)?;
+        store.insert(&quad2)?;
+        assert_eq!(store.len()?, 3);
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, None, Some(&g1.clone().into()))
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad1.clone()]
+        );
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, Some(&Literal::from(2).into()), None)
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad2.clone()]
+        );
+        assert_eq!(
+            store
+                .quads_for_pattern(Some(&ex.clone().into()), None, None, None)
+                .count(),
+            3
+        );
+
+        // The column family of the graph is dropped but the snapshot still reads it
+        let snapshot = store.snapshot();
+        store.clear_graph(&g1.clone().into())?;
+        assert!(!store.contains(&quad1)?);
+        assert!(store.contains_named_graph(&g1)?);
+        assert!(store.contains(&quad2)?);
+        assert_eq!(store.len()?, 2);
+        assert!(snapshot.contains(&quad1)?);
+        assert_eq!(snapshot.len()?, 3);
+
+        store.transaction(|transaction| transaction.remove_named_graph(&g2))?;
+        assert!(!store.contains(&quad2)?);
+        assert!(!store.contains_named_graph(&g2)?);
+        store.insert(&quad1)?;
+        assert_eq!(store.len()?, 2);
+        assert!(store.validate()?.is_valid());
+    }
+
+    {
+        // The column families of the graphs are opened again
+        let store = RocksDbStore::open_with_options(&repo_path, options)?;
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, None, None)
+                .collect::<Result<Vec<_>>>()?
+                .len(),
+            2
+        );
+        assert!(store.contains(&quad1)?);
+        assert!(!store.contains(&quad2)?);
+    }
+
+    // The layout could not be changed
+    assert!(RocksDbStore::open_with_options(
+        &repo_path,
+        RocksDbOptions::default().with_layout(RocksDbLayout::SharedColumnFamilies)
+    )
+    .is_err());
+    assert!(RocksDbStore::open(&repo_path)?.contains(&quad1)?);
+
+    remove_dir_all(&repo_path)?;
+    Ok(())
+}
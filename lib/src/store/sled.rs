@@ -1,22 +1,42 @@
 //! Store based on the [Sled](https://sled.rs/) key-value database.
 
 use crate::model::*;
-use crate::sparql::{GraphPattern, QueryOptions, QueryResult, SimplePreparedQuery};
+use crate::sparql::{
+    GraphPattern, Query, QueryExplanation, QueryOptions, QueryResult, QuerySolution, QueryStats,
+    SimplePreparedQuery, SimplePreparedUpdate,
+};
 use crate::store::numeric_encoder::*;
-use crate::store::{load_dataset, load_graph, ReadableEncodedStore, WritableEncodedStore};
-use crate::{DatasetSyntax, GraphSyntax, Result};
-use sled::{Config, Iter, Tree};
-use std::io::BufRead;
+use crate::store::pending::{PendingChanges, PendingChangesView};
+use crate::store::{
+    add_encoded_graph, copy_encoded_graph, dump_dataset, dump_graph, has_stored_strings,
+    load_dataset, load_graph, merge_encoded, move_encoded_graph, scan_predicate_statistics, sealed,
+    EncodedQuadEvent, MemoryStore, MergeSource, PredicateStatistics, QuadEvent,
+    ReadableEncodedStore, StorageError, StoreStatistics, StoreStats, ValidationReport, Watchers,
+    WritableEncodedStore,
+};
+#[cfg(feature = "full-text")]
+use crate::store::{is_string_literal, literal_words, text_words};
+use crate::{DatasetSyntax, Error, GraphSyntax, Result};
+#[cfg(feature = "full-text")]
+use sled::transaction::{ConflictableTransactionResult, TransactionalTree};
+use sled::transaction::{TransactionError, TransactionResult, Transactional};
+use sled::{Batch, Config, Db, Iter, Tree};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{BufRead, Cursor, Write};
+#[cfg(feature = "full-text")]
+use std::iter::{empty, once};
+use std::mem::{replace, take};
 use std::path::Path;
 use std::str;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 
 /// Store based on the [Sled](https://sled.rs/) key-value database.
 /// It encodes a [RDF dataset](https://www.w3.org/TR/rdf11-concepts/#dfn-rdf-dataset) and allows to query and update it using SPARQL.
 ///
 /// To use it, the `"sled"` feature needs to be activated.
 ///
-/// Warning: quad insertions and deletions are not (yet) atomic.
-///
 /// Usage example:
 /// ```
 /// use oxigraph::model::*;
@@ -55,23 +75,52 @@ pub struct SledStore {
     gspo: Tree,
     gpos: Tree,
     gosp: Tree,
+    graphs: Tree,
+    /// The number of quads of each graph, the graphs without quads are omitted
+    graph_lens: Tree,
+    /// The number of quads with each string literal as object, the literals without quads are omitted
+    literal_lens: Tree,
+    /// The words of the string literals followed by the literals, for the full-text search
+    words: Tree,
+    /// The number of literals containing each word, the words without literals are omitted
+    word_lens: Tree,
+    watchers: Arc<Watchers>,
+    /// Held in shared mode by the writes and in exclusive mode while a snapshot is copied
+    snapshot_lock: Arc<RwLock<()>>,
 }
 
 //TODO: indexes for the default graph and indexes for the named graphs (no more Optional and space saving)
 
+// The version of the on-disk format of the database, stored in the default tree
+const FORMAT_VERSION_KEY: &[u8] = b"format_version";
+// Set in the default tree while the words index of the string literals is maintained,
+// i.e. the database is only written with the full-text feature
+const WORDS_INDEX_KEY: &[u8] = b"words_index";
+
+// The versions of the on-disk format:
+// 0: the databases written before the version has been stamped
+// 1: the strings of at most 15 bytes are inlined in the encoded terms
+const LATEST_FORMAT_VERSION: u64 = 1;
+
 impl SledStore {
     /// Opens a temporary `SledStore` that will be deleted after drop.
     pub fn new() -> Result<Self> {
-        Self::do_open(&Config::new().temporary(true))
+        Self::do_open(&Config::new().temporary(true), Path::new(""))
     }
 
     /// Opens a `SledStore`
+    ///
+    /// It fails with a `StorageError::OutdatedFormat` if the database has been written by an older version of Oxigraph
+    /// with a different on-disk format. Such databases should be dumped with the older version and reloaded.
     pub fn open(path: impl AsRef<Path>) -> Result<Self> {
-        Self::do_open(&Config::new().path(path))
+        Self::do_open(&Config::new().path(path.as_ref()), path.as_ref())
     }
 
-    fn do_open(config: &Config) -> Result<Self> {
-        let db = config.open()?;
+    fn do_open(config: &Config, path: &Path) -> Result<Self> {
+        Self::open_db(&config.open()?, path)
+    }
+
+    fn open_db(db: &Db, path: &Path) -> Result<Self> {
         let new = Self {
             id2str: db.open_tree("id2str")?,
             spog: db.open_tree("spog")?,
@@ -80,8 +129,56 @@ impl SledStore {
             gspo: db.open_tree("gspo")?,
             gpos: db.open_tree("gpos")?,
             gosp: db.open_tree("gosp")?,
+            graphs: db.open_tree("graphs")?,
+            graph_lens: db.open_tree("graph_lens")?,
+            literal_lens: db.open_tree("literal_lens")?,
+            words: db.open_tree("words")?,
+            word_lens: db.open_tree("word_lens")?,
+            watchers: Arc::default(),
+            snapshot_lock: Arc::default(),
+        };
+        let version = match db.get(FORMAT_VERSION_KEY)? {
+            Some(value) => u64::from_be_bytes(
+                <[u8; 8]>::try_from(value.as_ref())
+                    .map_err(|_| Error::msg("Invalid format version in the Sled database"))?,
+            ),
+            // The databases without version are the ones written before it has been stamped, except the new empty ones
+            None if new.id2str.is_empty() && new.spog.is_empty() => LATEST_FORMAT_VERSION,
+            None => 0,
         };
+        if version < LATEST_FORMAT_VERSION {
+            return Err(StorageError::OutdatedFormat {
+                path: path.to_owned(),
+                version,
+                latest: LATEST_FORMAT_VERSION,
+            }
+            .into());
+        } else if version > LATEST_FORMAT_VERSION {
+            return Err(StorageError::UnsupportedFormat {
+                path: path.to_owned(),
+                version,
+                latest: LATEST_FORMAT_VERSION,
+            }
+            .into());
+        }
+        db.insert(FORMAT_VERSION_KEY, &LATEST_FORMAT_VERSION.to_be_bytes())?;
         (&new).set_first_strings()?;
+        if new.graphs.is_empty() {
+            new.rebuild_named_graphs()?;
+        }
+        if new.graph_lens.is_empty() && !new.spog.is_empty() {
+            new.rebuild_graph_lens()?;
+        }
+        #[cfg(feature = "full-text")]
+        {
+            if !db.contains_key(WORDS_INDEX_KEY)? {
+                new.rebuild_words_index()?;
+                db.insert(WORDS_INDEX_KEY, &[])?;
+            }
+        }
+        // The next writes are not going to maintain the words index
+        #[cfg(not(feature = "full-text"))]
+        db.remove(WORDS_INDEX_KEY)?;
         Ok(new)
     }
 
@@ -100,6 +197,21 @@ impl SledStore {
         )?))
     }
 
+    /// This is similar to `prepare_query`, but for a `Query` that has already been parsed or that has been built programmatically.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_parsed_query<'a>(
+        &'a self,
+        query: Query,
+        options: QueryOptions<'_>,
+    ) -> Result<SledPreparedQuery> {
+        Ok(SledPreparedQuery(SimplePreparedQuery::new_from_query(
+            (*self).clone(),
+            query,
+            options,
+        )?))
+    }
+
     /// This is similar to `prepare_query`, but useful if a SPARQL query has already been parsed, which is the case when building `ServiceHandler`s for federated queries with `SERVICE` clauses. For examples, look in the tests.
     pub fn prepare_query_from_pattern<'a>(
         &'a self,
@@ -113,12 +225,28 @@ impl SledStore {
         )?))
     }
 
+    /// Prepares a [SPARQL 1.1 update](https://www.w3.org/TR/sparql11-update/) and returns an object that could be used to execute it.
+    ///
+    /// All the operations of the update are executed in a single transaction, each of them seeing the changes of the previous ones.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn prepare_update(
+        &self,
+        update: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<SledPreparedUpdate> {
+        Ok(SledPreparedUpdate {
+            store: self.clone(),
+            update: SimplePreparedUpdate::new(update, options)?,
+        })
+    }
+
     /// Retrieves quads with a filter on each quad component
     ///
     /// See `MemoryStore` for a usage example.
     pub fn quads_for_pattern(
         &self,
-        subject: Option<&NamedOrBlankNode>,
+        subject: Option<&Subject>,
         predicate: Option<&NamedNode>,
         object: Option<&Term>,
         graph_name: Option<&GraphName>,
@@ -138,8 +266,170 @@ impl SledStore {
         self.contains_encoded(&quad)
     }
 
+    /// Returns the number of quads in the store
+    ///
+    /// It is read from the per-graph counters maintained by the store, so the quads are not counted one by one.
+    pub fn len(&self) -> Result<usize> {
+        let mut len = 0;
+        for entry in self.graph_lens.iter() {
+            let (_, value) = entry?;
+            len += decode_len(&value);
+        }
+        Ok(len as usize)
+    }
+
+    /// Returns if the store is empty
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.spog.is_empty())
+    }
+
+    /// Returns the number of quads in a given graph
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn len_graph(&self, graph_name: &GraphName) -> Result<usize> {
+        Ok(self.encoded_graph_len(graph_name.into())? as usize)
+    }
+
+    /// Returns statistics about the content of the store.
+    ///
+    /// The distinct terms are counted by jumping from one term to the next one in the indexes, so one read per distinct term is done.
+    /// The string store is fully read.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn stats(&self) -> Result<StoreStats> {
+        Ok(StoreStats {
+            quads_count: self.len()?,
+            distinct_subjects_count: count_distinct_first_terms(&self.spog)?,
+            distinct_predicates_count: count_distinct_first_terms(&self.posg)?,
+            distinct_objects_count: count_distinct_first_terms(&self.ospg)?,
+            named_graphs_count: self.graphs.len(),
+            strings_count: self.id2str.len(),
+        })
+    }
+
+    /// Returns the named graphs of the store, including the ones without quads.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn named_graphs(&self) -> impl Iterator<Item = Result<NamedOrBlankNode>> {
+        let this = self.clone();
+        self.encoded_named_graphs_inner()
+            .map(move |graph_name| this.decode_named_or_blank_node(graph_name?))
+    }
+
+    /// Checks if the store contains a given named graph, even if it does not contain any quad
+    pub fn contains_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<bool> {
+        self.contains_encoded_named_graph(graph_name.into())
+    }
+
+    /// Adds a named graph to the store, even if it does not contain any quad
+    pub fn insert_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        self.transaction(|transaction| {
+            let graph_name = transaction.encode_named_or_blank_node(graph_name)?;
+            transaction.insert_encoded_named_graph(graph_name)
+        })
+    }
+
+    /// Removes all the quads of a graph. The graph itself stays in the store.
+    pub fn clear_graph(&self, graph_name: &GraphName) -> Result<()> {
+        self.transaction(|transaction| transaction.clear_encoded_graph(graph_name.into()))
+    }
+
+    /// Removes a named graph and all its quads from the store
+    pub fn remove_named_graph(&self, graph_name: &NamedOrBlankNode) -> Result<()> {
+        self.transaction(|transaction| transaction.remove_encoded_named_graph(graph_name.into()))
+    }
+
+    /// Adds the quads of the graph `from` to the graph `to`, like the SPARQL `ADD` operation.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn add_graph(&self, from: &GraphName, to: &GraphName) -> Result<()> {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            add_encoded_graph(self, transaction, from.into(), to)
+        })
+    }
+
+    /// Replaces the content of the graph `to` by the one of the graph `from`, like the SPARQL `COPY` operation.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn copy_graph(&self, from: &GraphName, to: &GraphName) -> Result<()> {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            copy_encoded_graph(self, transaction, from.into(), to)
+        })
+    }
+
+    /// Moves the content of the graph `from` into the graph `to`, like the SPARQL `MOVE` operation.
+    ///
+    /// The graph `from` is removed from the store, or only cleared if it is the default graph.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn move_graph(&self, from: &GraphName, to: &GraphName) -> Result<()> {
+        self.transaction(|transaction| {
+            let to = transaction.encode_graph_name(to)?;
+            move_encoded_graph(self, transaction, from.into(), to)
+        })
+    }
+
+    /// Returns a copy of the store frozen at the time of the call.
+    ///
+    /// Sled does not provide snapshots so the quads are copied into a `MemoryStore`,
+    /// the writes to the store waiting for the copy to be done.
+    /// The writes done after the snapshot creation are not visible from it,
+    /// allowing long-running exports and queries to see a consistent dataset while the store is updated.
+    ///
+    /// Usage example:
+    /// ```
+    /// use oxigraph::model::*;
+    /// use oxigraph::{Result, SledStore};
+    ///
+    /// let store = SledStore::new()?;
+    /// let ex = NamedNode::new("http://example.com")?;
+    /// let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+    ///
+    /// let snapshot = store.snapshot()?;
+    /// store.insert(&quad)?;
+    /// assert!(store.contains(&quad)?);
+    /// assert!(!snapshot.contains(&quad));
+    /// # Result::Ok(())
+    /// ```
+    pub fn snapshot(&self) -> Result<MemoryStore> {
+        let _lock = self
+            .snapshot_lock
+            .write()
+            .expect("the Sled snapshot lock has been poisoned because of a panic");
+        let snapshot = MemoryStore::new();
+        snapshot.merge_from(self)?;
+        Ok(snapshot)
+    }
+
+    /// Prevents a snapshot to be copied until the returned guard is dropped
+    fn lock_writes(&self) -> RwLockReadGuard<'_, ()> {
+        self.snapshot_lock
+            .read()
+            .expect("the Sled snapshot lock has been poisoned because of a panic")
+    }
+
+    /// Executes a transaction.
+    ///
+    /// The transaction is executed if the given closure returns `Ok`.
+    /// Nothing is done if the closure returns `Err`.
+    /// The changes are written atomically at the end of the transaction.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn transaction<'a>(
+        &'a self,
+        f: impl FnOnce(&mut SledTransaction<'a>) -> Result<()>,
+    ) -> Result<()> {
+        let mut transaction = self.transaction_builder();
+        f(&mut transaction)?;
+        transaction.commit()
+    }
+
     /// Loads a graph file (i.e. triples) into the store
     ///
+    /// Each triple is written atomically in all the indexes.
+    ///
     /// Warning: This functions saves the triples in batch. If the parsing fails in the middle of the file,
     /// only a part of it may be written. Use a (memory greedy) transaction if you do not want that.
     ///
@@ -157,6 +447,8 @@ impl SledStore {
 
     /// Loads a dataset file (i.e. quads) into the store.
     ///
+    /// Each quad is written atomically in all the indexes.
+    ///
     /// Warning: This functions saves the quads in batch. If the parsing fails in the middle of the file,
     /// only a part of it may be written. Use a (memory greedy) transaction if you do not want that.
     ///
@@ -171,18 +463,405 @@ impl SledStore {
         load_dataset(&mut store, reader, syntax, base_iri)
     }
 
+    /// Dumps a store graph into a file.
+    ///
+    /// The triples are written while they are read from the store without loading the graph in memory.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_graph(
+        &self,
+        writer: impl Write,
+        syntax: GraphSyntax,
+        from_graph_name: &GraphName,
+    ) -> Result<()> {
+        dump_graph(
+            self.quads_for_pattern(None, None, None, Some(from_graph_name))
+                .map(|quad| Ok(quad?.into())),
+            writer,
+            syntax,
+        )
+    }
+
+    /// Dumps the store dataset into a file.
+    ///
+    /// The quads are written while they are read from the store without loading the dataset in memory.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn dump_dataset(&self, writer: impl Write, syntax: DatasetSyntax) -> Result<()> {
+        dump_dataset(
+            self.quads_for_pattern(None, None, None, None),
+            writer,
+            syntax,
+        )
+    }
+
     /// Adds a quad to this store.
     pub fn insert(&self, quad: &Quad) -> Result<()> {
-        let mut store = self;
-        let quad = store.encode_quad(quad)?;
-        store.insert_encoded(&quad)
+        self.transaction(|transaction| transaction.insert(quad))
+    }
+
+    /// Adds quads to this store.
+    ///
+    /// They are written atomically in a single transaction, so all of them are kept in memory until the end of the insertion.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn insert_all(&self, quads: impl IntoIterator<Item = Quad>) -> Result<()> {
+        self.transaction(|transaction| {
+            for quad in quads {
+                transaction.insert(&quad)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Adds all the quads and named graphs of another store to this store.
+    ///
+    /// The quads are streamed from the other store without decoding their terms, only the strings they use are copied.
+    /// They are written atomically in a single transaction, so all of them are kept in memory until the end of the merge.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn merge_from(&self, other: &impl MergeSource) -> Result<()> {
+        self.transaction(|transaction| merge_encoded(other, transaction))
     }
 
     /// Removes a quad from this store.
     pub fn remove(&self, quad: &Quad) -> Result<()> {
-        let mut store = self;
-        let quad = quad.into();
-        store.remove_encoded(&quad)
+        self.transaction(|transaction| transaction.remove(quad))
+    }
+
+    /// Removes all the quads matching a pattern from this store.
+    ///
+    /// The removal is done in a single transaction: the other readers never see a part of it.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn remove_quads_for_pattern(
+        &self,
+        subject: Option<&Subject>,
+        predicate: Option<&NamedNode>,
+        object: Option<&Term>,
+        graph_name: Option<&GraphName>,
+    ) -> Result<()> {
+        let subject = subject.map(|s| s.into());
+        let predicate = predicate.map(|p| p.into());
+        let object = object.map(|o| o.into());
+        let graph_name = graph_name.map(|g| g.into());
+        self.transaction(|transaction| {
+            for quad in self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name)
+            {
+                transaction.remove_encoded(&quad?)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Subscribes to the changes of the store.
+    ///
+    /// Each message received contains the changes of an atomic write to the store, in the order they have been applied.
+    /// The operations that are not done in a transaction, like `load_graph`, report each change in its own message.
+    /// The subscription ends when the receiver is dropped.
+    ///
+    /// See `MemoryStore` for a usage example.
+    pub fn watch(&self) -> Receiver<Vec<QuadEvent>> {
+        self.watchers.watch()
+    }
+
+    /// Cross-checks the indexes and the string store and returns the inconsistencies found.
+    ///
+    /// All the indexes are read, so it is slow on big databases.
+    ///
+    /// See `RocksDbStore` for a usage example.
+    pub fn validate(&self) -> Result<ValidationReport> {
+        self.validate_inner(false)
+    }
+
+    /// Does the same checks as `validate` and repairs the inconsistencies found.
+    ///
+    /// The quads missing from some indexes are added back to them.
+    /// The quads using strings missing from the string store are removed from all the indexes.
+    /// The missing named graphs are added and the graph sizes are computed again.
+    /// The repairs are not sent to the watchers.
+    ///
+    /// The returned report describes the inconsistencies found before the repair.
+    pub fn validate_and_repair(&self) -> Result<ValidationReport> {
+        let _lock = self.lock_writes();
+        self.validate_inner(true)
+    }
+
+    fn validate_inner(&self, repair: bool) -> Result<ValidationReport> {
+        let indexes: [(&Tree, QuadEncoding, fn(&mut Vec<u8>, &EncodedQuad)); 6] = [
+            (&self.spog, QuadEncoding::SPOG, write_spog_quad),
+            (&self.posg, QuadEncoding::POSG, write_posg_quad),
+            (&self.ospg, QuadEncoding::OSPG, write_ospg_quad),
+            (&self.gspo, QuadEncoding::GSPO, write_gspo_quad),
+            (&self.gpos, QuadEncoding::GPOS, write_gpos_quad),
+            (&self.gosp, QuadEncoding::GOSP, write_gosp_quad),
+        ];
+        let mut report = ValidationReport::default();
+        let mut stored_strings = HashSet::new();
+        let mut checked_graphs = HashSet::new();
+        let mut buffer = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
+        for (position, (tree, encoding, _)) in indexes.iter().enumerate() {
+            // The repairs are done after the scan of each index, so the next indexes see them
+            let mut to_insert = Vec::new();
+            let mut to_remove = Vec::new();
+            for entry in tree.iter() {
+                let (key, _) = entry?;
+                let quad = encoding.decode(&key)?;
+                let mut is_in_all_indexes = true;
+                let mut is_already_checked = false;
+                for (other_position, (other, _, write)) in indexes.iter().enumerate() {
+                    if other_position == position {
+                        continue;
+                    }
+                    write(&mut buffer, &quad);
+                    let is_in_other = other.contains_key(&buffer)?;
+                    buffer.clear();
+                    if is_in_other && other_position < position {
+                        is_already_checked = true;
+                    }
+                    is_in_all_indexes &= is_in_other;
+                }
+                if is_already_checked {
+                    continue;
+                }
+
+                if !has_stored_strings(self, &quad, &mut stored_strings)? {
+                    report.dangling_quads += 1;
+                    to_remove.push(quad);
+                    continue;
+                }
+                if !is_in_all_indexes {
+                    report.incomplete_quads += 1;
+                    to_insert.push(quad);
+                }
+                if quad.graph_name != ENCODED_DEFAULT_GRAPH
+                    && checked_graphs.insert(quad.graph_name)
+                    && !self.contains_encoded_named_graph(quad.graph_name)?
+                {
+                    report.missing_named_graphs += 1;
+                    if repair {
+                        self.graphs.insert(encode_term(quad.graph_name), &[])?;
+                    }
+                }
+            }
+            if repair {
+                for (tree, _, write) in &indexes {
+                    let mut batch = Batch::default();
+                    for quad in &to_insert {
+                        write(&mut buffer, quad);
+                        batch.insert(buffer.as_slice(), &[]);
+                        buffer.clear();
+                    }
+                    for quad in &to_remove {
+                        write(&mut buffer, quad);
+                        batch.remove(buffer.as_slice());
+                        buffer.clear();
+                    }
+                    tree.apply_batch(batch)?;
+                }
+            }
+        }
+        if repair && !report.is_valid() {
+            self.graph_lens.clear()?;
+            self.rebuild_graph_lens()?;
+        }
+        Ok(report)
+    }
+
+    fn transaction_builder(&self) -> SledTransaction<'_> {
+        SledTransaction {
+            store: self,
+            id2str: Batch::default(),
+            spog: Batch::default(),
+            posg: Batch::default(),
+            ospg: Batch::default(),
+            gspo: Batch::default(),
+            gpos: Batch::default(),
+            gosp: Batch::default(),
+            graphs: Batch::default(),
+            graph_lens: HashMap::default(),
+            #[cfg(feature = "full-text")]
+            literal_lens: HashMap::default(),
+            pending: HashMap::default(),
+            changes: PendingChanges::default(),
+            events: if self.watchers.is_watched() {
+                Some(Vec::default())
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Inserts or removes a quad in all the indexes in a single sled transaction,
+    /// so a crash never leaves the indexes inconsistent.
+    ///
+    /// Returns if the store has been changed.
+    fn write_encoded(&self, quad: &EncodedQuad, insert: bool) -> Result<bool> {
+        let _lock = self.lock_writes();
+        let keys = [
+            encode_quad(quad, write_spog_quad),
+            encode_quad(quad, write_posg_quad),
+            encode_quad(quad, write_ospg_quad),
+            encode_quad(quad, write_gspo_quad),
+            encode_quad(quad, write_gpos_quad),
+            encode_quad(quad, write_gosp_quad),
+        ];
+        let graph_key = encode_term(quad.graph_name);
+        #[cfg(feature = "full-text")]
+        let object_words = if is_string_literal(quad.object) {
+            literal_words(self, quad.object)?
+        } else {
+            None
+        };
+        #[cfg_attr(not(feature = "full-text"), allow(unused_variables))]
+        let result: TransactionResult<bool, Error> = (
+            &self.spog,
+            &self.posg,
+            &self.ospg,
+            &self.gspo,
+            &self.gpos,
+            &self.gosp,
+            &self.graphs,
+            &self.graph_lens,
+            &self.literal_lens,
+            &self.words,
+            &self.word_lens,
+        )
+            .transaction(
+                |(
+                    spog,
+                    posg,
+                    ospg,
+                    gspo,
+                    gpos,
+                    gosp,
+                    graphs,
+                    graph_lens,
+                    literal_lens,
+                    words,
+                    word_lens,
+                )| {
+                    let is_present = spog.get(&keys[0])?.is_some();
+                    if insert && quad.graph_name != ENCODED_DEFAULT_GRAPH {
+                        graphs.insert(graph_key.as_slice(), &[])?;
+                    }
+                    if is_present == insert {
+                        return Ok(false);
+                    }
+                    for (tree, key) in [spog, posg, ospg, gspo, gpos, gosp].iter().zip(&keys) {
+                        if insert {
+                            tree.insert(key.as_slice(), &[])?;
+                        } else {
+                            tree.remove(key.as_slice())?;
+                        }
+                    }
+                    let delta = if insert { 1 } else { -1 };
+                    match updated_len(graph_lens.get(&graph_key)?.as_deref(), delta) {
+                        Some(value) => graph_lens.insert(graph_key.as_slice(), value)?,
+                        None => graph_lens.remove(graph_key.as_slice())?,
+                    };
+                    #[cfg(feature = "full-text")]
+                    {
+                        if let Some(object_words) = &object_words {
+                            update_words_index(
+                                (literal_lens, words, word_lens),
+                                quad.object,
+                                object_words,
+                                delta,
+                            )?;
+                        }
+                    }
+                    Ok(true)
+                },
+            );
+        result.map_err(|error| match error {
+            TransactionError::Abort(error) => error,
+            TransactionError::Storage(error) => error.into(),
+        })
+    }
+
+    fn encoded_graph_len(&self, graph_name: EncodedTerm) -> Result<u64> {
+        Ok(self
+            .graph_lens
+            .get(encode_term(graph_name))?
+            .map_or(0, |value| decode_len(&value)))
+    }
+
+    /// Computes the number of quads of each graph from the GSPO index
+    fn rebuild_graph_lens(&self) -> Result<()> {
+        let mut graph_lens = HashMap::<_, u64>::default();
+        for quad in self.gspo_quads(Vec::default()) {
+            *graph_lens.entry(quad?.graph_name).or_default() += 1;
+        }
+        for (graph_name, len) in graph_lens {
+            self.graph_lens
+                .insert(encode_term(graph_name), &len.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the words index of the string literals and builds it again from the SPOG index
+    #[cfg(feature = "full-text")]
+    fn rebuild_words_index(&self) -> Result<()> {
+        self.literal_lens.clear()?;
+        self.words.clear()?;
+        self.word_lens.clear()?;
+        let mut literal_lens = HashMap::<_, u64>::default();
+        for quad in self.quads() {
+            let quad = quad?;
+            if is_string_literal(quad.object) {
+                *literal_lens.entry(quad.object).or_default() += 1;
+            }
+        }
+        let mut word_lens = HashMap::<_, u64>::default();
+        for (literal, len) in literal_lens {
+            self.literal_lens
+                .insert(encode_term(literal), &len.to_be_bytes())?;
+            for word in literal_words(self, literal)?.unwrap_or_default() {
+                self.words.insert(word_literal_key(&word, literal), &[])?;
+                *word_lens.entry(word).or_default() += 1;
+            }
+        }
+        for (word, len) in word_lens {
+            self.word_lens.insert(word.as_bytes(), &len.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "full-text")]
+    fn word_len(&self, word: &str) -> Result<u64> {
+        Ok(self
+            .word_lens
+            .get(word.as_bytes())?
+            .map_or(0, |value| decode_len(&value)))
+    }
+
+    fn encoded_named_graphs_inner(&self) -> impl Iterator<Item = Result<EncodedTerm>> {
+        self.graphs.iter().map(|entry| {
+            let (key, _) = entry?;
+            Cursor::new(key.as_ref()).read_term()
+        })
+    }
+
+    /// Fills the named graphs index from the GSPO index
+    ///
+    /// After each graph the scan jumps directly to the next one, so only one read per graph is done.
+    fn rebuild_named_graphs(&self) -> Result<()> {
+        let mut next = Some(Vec::default());
+        while let Some(start) = next.take() {
+            let (key, _) = match self.gspo.range(start..).next() {
+                Some(entry) => entry?,
+                None => break,
+            };
+            let graph_name = QuadEncoding::GSPO.decode(&key)?.graph_name;
+            let mut prefix = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
+            write_term(&mut prefix, graph_name);
+            if graph_name != ENCODED_DEFAULT_GRAPH {
+                self.graphs.insert(prefix.as_slice(), &[])?;
+            }
+            next = prefix_successor(prefix);
+        }
+        Ok(())
     }
 
     fn contains_encoded(&self, quad: &EncodedQuad) -> Result<bool> {
@@ -394,14 +1073,54 @@ impl SledStore {
     }
 }
 
+impl sealed::MergeSource for SledStore {
+    fn encoded_quads_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + '_> {
+        self.encoded_quads_for_pattern(None, None, None, None)
+    }
+
+    fn encoded_named_graphs_to_merge(&self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + '_> {
+        self.encoded_named_graphs()
+    }
+}
+
 impl StrLookup for SledStore {
-    fn get_str(&self, id: StrHash) -> Result<Option<String>> {
+    fn get_stored_str(&self, id: StrHash) -> Result<Option<String>> {
         Ok(self
             .id2str
             .get(id.to_be_bytes())?
             .map(|v| String::from_utf8(v.to_vec()))
             .transpose()?)
     }
+
+    fn get_stored_shared_str(&self, id: StrHash) -> Result<Option<Arc<str>>> {
+        // The string is copied only once, from the sled buffer to the shared one
+        Ok(self
+            .id2str
+            .get(id.to_be_bytes())?
+            .map(|v| str::from_utf8(&v).map(Arc::from))
+            .transpose()
+            .map_err(Error::wrap)?)
+    }
+}
+
+impl StoreStatistics for SledStore {
+    fn predicate_statistics(&self, predicate: EncodedTerm) -> Option<PredicateStatistics> {
+        scan_predicate_statistics(self, predicate)
+    }
+
+    fn graph_quads_count(&self, graph_name: EncodedTerm) -> Option<u64> {
+        self.encoded_graph_len(graph_name).ok()
+    }
+
+    #[cfg(feature = "full-text")]
+    fn literals_matching_count(&self, query: &str) -> Option<u64> {
+        let mut count = None;
+        for word in text_words(query) {
+            let word_len = self.word_len(&word).ok()?;
+            count = Some(count.map_or(word_len, |count: u64| count.min(word_len)));
+        }
+        Some(count.unwrap_or(0))
+    }
 }
 
 impl ReadableEncodedStore for SledStore {
@@ -414,10 +1133,63 @@ impl ReadableEncodedStore for SledStore {
     ) -> Box<dyn Iterator<Item = Result<EncodedQuad>> + 'a> {
         Box::new(self.encoded_quads_for_pattern_inner(subject, predicate, object, graph_name))
     }
+
+    fn encoded_named_graphs<'a>(&'a self) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        Box::new(self.encoded_named_graphs_inner())
+    }
+
+    fn contains_encoded_named_graph(&self, graph_name: EncodedTerm) -> Result<bool> {
+        Ok(self.graphs.contains_key(encode_term(graph_name))?)
+    }
+
+    /// Reads the literals containing the least frequent word from the words index and filters them with the other words
+    #[cfg(feature = "full-text")]
+    fn encoded_literals_matching<'a>(
+        &'a self,
+        query: &str,
+    ) -> Box<dyn Iterator<Item = Result<EncodedTerm>> + 'a> {
+        let words: HashSet<String> = text_words(query).collect();
+        let mut rarest: Option<(&str, u64)> = None;
+        for word in &words {
+            let len = match self.word_len(word) {
+                Ok(len) => len,
+                Err(error) => return Box::new(once(Err(error))),
+            };
+            if rarest.map_or(true, |(_, rarest_len)| len < rarest_len) {
+                rarest = Some((word, len));
+            }
+        }
+        let mut prefix = match rarest {
+            Some((word, _)) => word.as_bytes().to_vec(),
+            None => return Box::new(empty()),
+        };
+        prefix.push(0);
+        let prefix_len = prefix.len();
+        Box::new(self.words.scan_prefix(prefix).filter_map(move |entry| {
+            let literal = match entry {
+                Ok((key, _)) => match Cursor::new(&key[prefix_len..]).read_term() {
+                    Ok(literal) => literal,
+                    Err(error) => return Some(Err(error)),
+                },
+                Err(error) => return Some(Err(error.into())),
+            };
+            if words.len() == 1 {
+                return Some(Ok(literal));
+            }
+            match literal_words(self, literal) {
+                Ok(Some(literal_words)) if words.is_subset(&literal_words) => Some(Ok(literal)),
+                Ok(_) => None,
+                Err(error) => Some(Err(error)),
+            }
+        }))
+    }
 }
 
 impl<'a> StrContainer for &'a SledStore {
-    fn insert_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if let Some(stored) = self.id2str.get(key.to_be_bytes())? {
+            return check_str_hash_collision(key, &stored, value);
+        }
         self.id2str.insert(key.to_be_bytes(), value)?;
         Ok(())
     }
@@ -425,78 +1197,452 @@ impl<'a> StrContainer for &'a SledStore {
 
 impl<'a> WritableEncodedStore for &'a SledStore {
     fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        //TODO: atomicity
+        if self.write_encoded(quad, true)? {
+            self.watchers
+                .notify(*self, &[EncodedQuadEvent::Inserted(*quad)])?;
+        }
+        Ok(())
+    }
+
+    fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        if self.write_encoded(quad, false)? {
+            self.watchers
+                .notify(*self, &[EncodedQuadEvent::Removed(*quad)])?;
+        }
+        Ok(())
+    }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        let _lock = self.lock_writes();
+        self.graphs.insert(encode_term(graph_name), &[])?;
+        Ok(())
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        for quad in self.quads_for_graph(graph_name) {
+            self.remove_encoded(&quad?)?;
+        }
+        Ok(())
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.clear_encoded_graph(graph_name)?;
+        let _lock = self.lock_writes();
+        self.graphs.remove(encode_term(graph_name))?;
+        Ok(())
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) for the `SledStore`.
+pub struct SledPreparedQuery(SimplePreparedQuery<SledStore>);
+
+impl SledPreparedQuery {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    ///
+    /// It allows to execute the same query with different parameters without parsing it again.
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// A prepared [SPARQL update](https://www.w3.org/TR/sparql11-update/) for the `SledStore`.
+pub struct SledPreparedUpdate {
+    store: SledStore,
+    update: SimplePreparedUpdate,
+}
+
+impl SledPreparedUpdate {
+    /// Executes the update
+    ///
+    /// All the operations of the update are executed in the same transaction:
+    /// the changes are only written if all of them succeed.
+    pub fn exec(&self) -> Result<()> {
+        self.store.transaction(|transaction| {
+            for operation in self.update.operations() {
+                // The operations see the changes of the previous ones but not their own changes:
+                // the transaction records the changes of the operation apart from the previous ones
+                let previous = take(&mut transaction.changes);
+                self.update.exec_operation(
+                    operation,
+                    PendingChangesView::new(self.store.clone(), &previous),
+                    transaction,
+                )?;
+                let changes = replace(&mut transaction.changes, previous);
+                transaction.changes.extend(changes);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) evaluated against a `SledStore` during a transaction.
+pub struct SledTransactionPreparedQuery<'a>(SimplePreparedQuery<PendingChangesView<'a, SledStore>>);
+
+impl SledTransactionPreparedQuery<'_> {
+    /// Evaluates the query and returns its results
+    pub fn exec(&self) -> Result<QueryResult<'_>> {
+        self.0.exec()
+    }
+
+    /// Evaluates the query with some variables already bound to the given values and returns its results
+    pub fn exec_with_bindings(&self, bindings: &QuerySolution) -> Result<QueryResult<'_>> {
+        self.0.exec_with_bindings(bindings)
+    }
+
+    /// Returns a description of the plan used to evaluate the query
+    pub fn explain(&self) -> Result<QueryExplanation> {
+        self.0.explain()
+    }
+
+    /// Returns the metrics of the last execution of the query like its duration or the number of quad pattern lookups
+    pub fn stats(&self) -> QueryStats {
+        self.0.stats()
+    }
+}
+
+/// Allows to insert and delete quads during a transaction with the `SledStore`.
+pub struct SledTransaction<'a> {
+    store: &'a SledStore,
+    id2str: Batch,
+    spog: Batch,
+    posg: Batch,
+    ospg: Batch,
+    gspo: Batch,
+    gpos: Batch,
+    gosp: Batch,
+    graphs: Batch,
+    /// The changes to apply to the number of quads of each graph
+    graph_lens: HashMap<EncodedTerm, i64>,
+    /// The changes to apply to the number of quads with each string literal as object
+    #[cfg(feature = "full-text")]
+    literal_lens: HashMap<EncodedTerm, i64>,
+    /// The quads modified by the transaction and if they are in the store after it
+    pending: HashMap<EncodedQuad, bool>,
+    /// The changes done by the transaction, in order for its queries to see them
+    changes: PendingChanges,
+    /// The changes to send to the watchers, if any
+    events: Option<Vec<EncodedQuadEvent>>,
+}
+
+impl StrContainer for SledTransaction<'_> {
+    fn insert_stored_str(&mut self, key: StrHash, value: &str) -> Result<()> {
+        if let Some(stored) = self.store.id2str.get(key.to_be_bytes())? {
+            return check_str_hash_collision(key, &stored, value);
+        }
+        self.id2str.insert(key.to_be_bytes().as_ref(), value);
+        self.changes.insert_str(key, value);
+        Ok(())
+    }
+}
+
+impl WritableEncodedStore for SledTransaction<'_> {
+    fn insert_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
+        if !self.contains_encoded(quad)? {
+            *self.graph_lens.entry(quad.graph_name).or_default() += 1;
+            #[cfg(feature = "full-text")]
+            {
+                if is_string_literal(quad.object) {
+                    *self.literal_lens.entry(quad.object).or_default() += 1;
+                }
+            }
+            self.pending.insert(*quad, true);
+            if let Some(events) = &mut self.events {
+                events.push(EncodedQuadEvent::Inserted(*quad));
+            }
+        }
+        self.changes.insert(quad);
         let mut buffer = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
 
+        if quad.graph_name != ENCODED_DEFAULT_GRAPH {
+            write_term(&mut buffer, quad.graph_name);
+            self.graphs.insert(buffer.as_slice(), &[]);
+            buffer.clear();
+        }
+
         write_spog_quad(&mut buffer, quad);
-        self.spog.insert(&buffer, &[])?;
+        self.spog.insert(buffer.as_slice(), &[]);
         buffer.clear();
 
         write_posg_quad(&mut buffer, quad);
-        self.posg.insert(&buffer, &[])?;
+        self.posg.insert(buffer.as_slice(), &[]);
         buffer.clear();
 
         write_ospg_quad(&mut buffer, quad);
-        self.ospg.insert(&buffer, &[])?;
+        self.ospg.insert(buffer.as_slice(), &[]);
         buffer.clear();
 
         write_gspo_quad(&mut buffer, quad);
-        self.gspo.insert(&buffer, &[])?;
+        self.gspo.insert(buffer.as_slice(), &[]);
         buffer.clear();
 
         write_gpos_quad(&mut buffer, quad);
-        self.gpos.insert(&buffer, &[])?;
+        self.gpos.insert(buffer.as_slice(), &[]);
         buffer.clear();
 
         write_gosp_quad(&mut buffer, quad);
-        self.gosp.insert(&buffer, &[])?;
+        self.gosp.insert(buffer.as_slice(), &[]);
         buffer.clear();
 
         Ok(())
     }
 
     fn remove_encoded(&mut self, quad: &EncodedQuad) -> Result<()> {
-        //TODO: atomicity
+        if self.contains_encoded(quad)? {
+            *self.graph_lens.entry(quad.graph_name).or_default() -= 1;
+            #[cfg(feature = "full-text")]
+            {
+                if is_string_literal(quad.object) {
+                    *self.literal_lens.entry(quad.object).or_default() -= 1;
+                }
+            }
+            self.pending.insert(*quad, false);
+            if let Some(events) = &mut self.events {
+                events.push(EncodedQuadEvent::Removed(*quad));
+            }
+        }
+        self.changes.remove(quad);
         let mut buffer = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
 
         write_spog_quad(&mut buffer, quad);
-        self.spog.remove(&buffer)?;
+        self.spog.remove(buffer.as_slice());
         buffer.clear();
 
         write_posg_quad(&mut buffer, quad);
-        self.posg.remove(&buffer)?;
+        self.posg.remove(buffer.as_slice());
         buffer.clear();
 
         write_ospg_quad(&mut buffer, quad);
-        self.ospg.remove(&buffer)?;
+        self.ospg.remove(buffer.as_slice());
         buffer.clear();
 
         write_gspo_quad(&mut buffer, quad);
-        self.gspo.remove(&buffer)?;
+        self.gspo.remove(buffer.as_slice());
         buffer.clear();
 
         write_gpos_quad(&mut buffer, quad);
-        self.gpos.remove(&buffer)?;
+        self.gpos.remove(buffer.as_slice());
         buffer.clear();
 
         write_gosp_quad(&mut buffer, quad);
-        self.gosp.remove(&buffer)?;
+        self.gosp.remove(buffer.as_slice());
         buffer.clear();
 
         Ok(())
     }
+
+    fn insert_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.graphs.insert(encode_term(graph_name), &[]);
+        self.changes.insert_named_graph(graph_name);
+        Ok(())
+    }
+
+    fn clear_encoded_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        for quad in self.store.quads_for_graph(graph_name) {
+            self.remove_encoded(&quad?)?;
+        }
+        // The quads inserted by the transaction are not in the store yet
+        let inserted = self
+            .pending
+            .iter()
+            .filter(|(quad, contained)| **contained && quad.graph_name == graph_name)
+            .map(|(quad, _)| *quad)
+            .collect::<Vec<_>>();
+        for quad in inserted {
+            self.remove_encoded(&quad)?;
+        }
+        Ok(())
+    }
+
+    fn remove_encoded_named_graph(&mut self, graph_name: EncodedTerm) -> Result<()> {
+        self.clear_encoded_graph(graph_name)?;
+        self.graphs.remove(encode_term(graph_name));
+        self.changes.remove_named_graph(graph_name);
+        Ok(())
+    }
 }
 
-/// A prepared [SPARQL query](https://www.w3.org/TR/sparql11-query/) for the `SledStore`.
-pub struct SledPreparedQuery(SimplePreparedQuery<SledStore>);
+impl SledTransaction<'_> {
+    /// Loads a graph file (i.e. triples) into the store during the transaction.
+    ///
+    /// Warning: Because the load happens during a transaction,
+    /// the full file content might be temporarily stored in main memory.
+    /// Do not use for big files.
+    ///
+    /// See `MemoryTransaction` for a usage example.
+    pub fn load_graph(
+        &mut self,
+        reader: impl BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        load_graph(self, reader, syntax, to_graph_name, base_iri)
+    }
 
-impl SledPreparedQuery {
-    /// Evaluates the query and returns its results
-    pub fn exec(&self) -> Result<QueryResult<'_>> {
-        self.0.exec()
+    /// Loads a dataset file (i.e. quads) into the store during the transaction.
+    ///
+    /// Warning: Because the load happens during a transaction,
+    /// the full file content might be temporarily stored in main memory.
+    /// Do not use for big files.
+    ///
+    /// See `MemoryTransaction` for a usage example.
+    pub fn load_dataset(
+        &mut self,
+        reader: impl BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> Result<()> {
+        load_dataset(self, reader, syntax, base_iri)
+    }
+
+    /// Adds a quad to this store during the transaction.
+    pub fn insert(&mut self, quad: &Quad) -> Result<()> {
+        let quad = self.encode_quad(quad)?;
+        self.insert_encoded(&quad)
+    }
+
+    /// Removes a quad from this store during the transaction.
+    pub fn remove(&mut self, quad: &Quad) -> Result<()> {
+        let quad = quad.into();
+        self.remove_encoded(&quad)
+    }
+
+    /// Prepares a [SPARQL 1.1 query](https://www.w3.org/TR/sparql11-query/) evaluated against the store with the changes of the transaction done so far.
+    ///
+    /// See `MemoryTransaction` for a usage example.
+    pub fn prepare_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+    ) -> Result<SledTransactionPreparedQuery<'_>> {
+        Ok(SledTransactionPreparedQuery(SimplePreparedQuery::new(
+            PendingChangesView::new(self.store.clone(), &self.changes),
+            query,
+            options,
+        )?))
+    }
+
+    /// Checks if the store contains a quad after the changes already done by the transaction
+    fn contains_encoded(&self, quad: &EncodedQuad) -> Result<bool> {
+        if let Some(contained) = self.pending.get(quad) {
+            Ok(*contained)
+        } else {
+            self.store.contains_encoded(quad)
+        }
+    }
+
+    fn commit(self) -> Result<()> {
+        let store = self.store;
+        let _lock = store.lock_writes();
+        // The words of the literals are read before the sled transaction, with the strings added by this transaction
+        #[cfg(feature = "full-text")]
+        let indexed_literals = {
+            let strings = PendingChangesView::new(store.clone(), &self.changes);
+            self.literal_lens
+                .iter()
+                .filter(|(_, delta)| **delta != 0)
+                .map(|(literal, delta)| {
+                    Ok((
+                        *literal,
+                        *delta,
+                        literal_words(&strings, *literal)?.unwrap_or_default(),
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+        #[cfg_attr(not(feature = "full-text"), allow(unused_variables))]
+        let result: TransactionResult<(), Error> = (
+            &store.id2str,
+            &store.spog,
+            &store.posg,
+            &store.ospg,
+            &store.gspo,
+            &store.gpos,
+            &store.gosp,
+            &store.graphs,
+            &store.graph_lens,
+            &store.literal_lens,
+            &store.words,
+            &store.word_lens,
+        )
+            .transaction(
+                |(
+                    id2str,
+                    spog,
+                    posg,
+                    ospg,
+                    gspo,
+                    gpos,
+                    gosp,
+                    graphs,
+                    graph_lens,
+                    literal_lens,
+                    words,
+                    word_lens,
+                )| {
+                    id2str.apply_batch(&self.id2str)?;
+                    spog.apply_batch(&self.spog)?;
+                    posg.apply_batch(&self.posg)?;
+                    ospg.apply_batch(&self.ospg)?;
+                    gspo.apply_batch(&self.gspo)?;
+                    gpos.apply_batch(&self.gpos)?;
+                    gosp.apply_batch(&self.gosp)?;
+                    graphs.apply_batch(&self.graphs)?;
+                    for (graph_name, delta) in &self.graph_lens {
+                        if *delta != 0 {
+                            let key = encode_term(*graph_name);
+                            match updated_len(graph_lens.get(&key)?.as_deref(), *delta) {
+                                Some(value) => graph_lens.insert(key, value)?,
+                                None => graph_lens.remove(key)?,
+                            };
+                        }
+                    }
+                    #[cfg(feature = "full-text")]
+                    {
+                        for (literal, delta, literal_words) in &indexed_literals {
+                            update_words_index(
+                                (literal_lens, words, word_lens),
+                                *literal,
+                                literal_words,
+                                *delta,
+                            )?;
+                        }
+                    }
+                    Ok(())
+                },
+            );
+        result.map_err(|error| match error {
+            TransactionError::Abort(error) => error,
+            TransactionError::Storage(error) => error.into(),
+        })?;
+        if let Some(events) = &self.events {
+            store.watchers.notify(store, events)?;
+        }
+        Ok(())
     }
 }
 
+fn encode_quad(quad: &EncodedQuad, write: fn(&mut Vec<u8>, &EncodedQuad)) -> Vec<u8> {
+    let mut vec = Vec::with_capacity(4 * WRITTEN_TERM_MAX_SIZE);
+    write(&mut vec, quad);
+    vec
+}
+
 fn encode_term(t: EncodedTerm) -> Vec<u8> {
     let mut vec = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
     write_term(&mut vec, t);
@@ -527,6 +1673,106 @@ fn encode_term_quad(t1: EncodedTerm, t2: EncodedTerm, t3: EncodedTerm, t4: Encod
     vec
 }
 
+fn decode_len(value: &[u8]) -> u64 {
+    let mut buffer = [0; 8];
+    if value.len() == buffer.len() {
+        buffer.copy_from_slice(value);
+    }
+    u64::from_be_bytes(buffer)
+}
+
+/// Adds `delta` to the number of quads of a string literal in a sled transaction on the (literal_lens, words, word_lens) trees
+///
+/// The literal words are added to or removed from the words index when it gets its first quad or loses its last one.
+#[cfg(feature = "full-text")]
+fn update_words_index(
+    (literal_lens, words, word_lens): (&TransactionalTree, &TransactionalTree, &TransactionalTree),
+    literal: EncodedTerm,
+    literal_words: &HashSet<String>,
+    delta: i64,
+) -> ConflictableTransactionResult<(), Error> {
+    let literal_key = encode_term(literal);
+    let previous = literal_lens.get(&literal_key)?;
+    let was_indexed = previous.is_some();
+    let is_indexed = match updated_len(previous.as_deref(), delta) {
+        Some(value) => {
+            literal_lens.insert(literal_key, value)?;
+            true
+        }
+        None => {
+            literal_lens.remove(literal_key)?;
+            false
+        }
+    };
+    if was_indexed == is_indexed {
+        return Ok(());
+    }
+    let word_delta = if is_indexed { 1 } else { -1 };
+    for word in literal_words {
+        let key = word_literal_key(word, literal);
+        if is_indexed {
+            words.insert(key, &[])?;
+        } else {
+            words.remove(key)?;
+        }
+        match updated_len(word_lens.get(word.as_bytes())?.as_deref(), word_delta) {
+            Some(value) => word_lens.insert(word.as_bytes(), value)?,
+            None => word_lens.remove(word.as_bytes())?,
+        };
+    }
+    Ok(())
+}
+
+/// The key of the words index: the word, a 0 byte and the encoded literal
+#[cfg(feature = "full-text")]
+fn word_literal_key(word: &str, literal: EncodedTerm) -> Vec<u8> {
+    let mut vec = Vec::with_capacity(word.len() + 1 + WRITTEN_TERM_MAX_SIZE);
+    vec.extend_from_slice(word.as_bytes());
+    vec.push(0);
+    write_term(&mut vec, literal);
+    vec
+}
+
+/// Returns the encoded quads count after adding `delta` to `value` or `None` if there are no more quads
+fn updated_len(value: Option<&[u8]>, delta: i64) -> Option<Vec<u8>> {
+    let len = (value.map_or(0, decode_len) as i64 + delta).max(0) as u64;
+    if len == 0 {
+        None
+    } else {
+        Some(len.to_be_bytes().to_vec())
+    }
+}
+
+/// Returns the smallest key greater than all the keys starting with `prefix` or `None` if there is none
+/// Counts the distinct terms at the beginning of the keys of an index
+///
+/// After each term the scan jumps directly to the next one, so only one read per term is done.
+fn count_distinct_first_terms(tree: &Tree) -> Result<usize> {
+    let mut count = 0;
+    let mut next = Some(Vec::default());
+    while let Some(start) = next.take() {
+        let (key, _) = match tree.range(start..).next() {
+            Some(entry) => entry?,
+            None => break,
+        };
+        let mut prefix = Vec::with_capacity(WRITTEN_TERM_MAX_SIZE);
+        write_term(&mut prefix, Cursor::new(key.as_ref()).read_term()?);
+        count += 1;
+        next = prefix_successor(prefix);
+    }
+    Ok(count)
+}
+
+fn prefix_successor(mut prefix: Vec<u8>) -> Option<Vec<u8>> {
+    while let Some(last) = prefix.pop() {
+        if last < u8::MAX {
+            prefix.push(last + 1);
+            return Some(prefix);
+        }
+    }
+    None
+}
+
 struct DecodingQuadIterator {
     iter: Iter,
     order: QuadEncoding,
@@ -548,7 +1794,7 @@ fn store() -> Result<()> {
     use crate::model::*;
     use crate::*;
 
-    let main_s = NamedOrBlankNode::from(BlankNode::default());
+    let main_s = Subject::from(BlankNode::default());
     let main_p = NamedNode::new("http://example.com")?;
     let main_o = Term::from(Literal::from(1));
 
@@ -673,3 +1919,206 @@ fn store() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn transaction() -> Result<()> {
+    use crate::model::*;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), None);
+
+    let store = SledStore::new()?;
+    store.transaction(|transaction| {
+        transaction.insert(&quad1)?;
+        transaction.insert(&quad2)
+    })?;
+    assert!(store.contains(&quad1)?);
+    assert!(store.contains(&quad2)?);
+
+    // Nothing is written if the transaction fails
+    assert!(store
+        .transaction(|transaction| {
+            transaction.remove(&quad1)?;
+            transaction.load_graph(
+                b"<http://example.com> <http://example.com> 3 . foo".as_ref(),
+                GraphSyntax::NTriples,
+                &GraphName::DefaultGraph,
+                None,
+            )
+        })
+        .is_err());
+    assert!(store.contains(&quad1)?);
+
+    // SPARQL update
+    store
+        .prepare_update("DELETE WHERE { ?s ?p 1 }", QueryOptions::default())?
+        .exec()?;
+    assert_eq!(
+        store
+            .quads_for_pattern(None, None, None, None)
+            .collect::<Result<Vec<_>>>()?,
+        vec![quad2]
+    );
+    Ok(())
+}
+
+#[test]
+fn len() -> Result<()> {
+    use crate::model::*;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let g = NamedNode::new("http://example.com/g")?;
+    let quad1 = Quad::new(ex.clone(), ex.clone(), Literal::from(1), None);
+    let quad2 = Quad::new(ex.clone(), ex.clone(), Literal::from(2), g.clone());
+
+    let store = SledStore::new()?;
+    assert!(store.is_empty()?);
+
+    // The same quad inserted twice in a transaction is counted once
+    store.transaction(|transaction| {
+        transaction.insert(&quad1)?;
+        transaction.insert(&quad1)?;
+        transaction.insert(&quad2)?;
+        transaction.remove(&quad2)?;
+        transaction.insert(&quad2)
+    })?;
+    assert_eq!(store.len()?, 2);
+    assert_eq!(store.len_graph(&GraphName::DefaultGraph)?, 1);
+    assert_eq!(store.len_graph(&g.clone().into())?, 1);
+
+    store.remove(&quad1)?;
+    store.remove(&quad1)?;
+    store.clear_graph(&g.clone().into())?;
+    assert_eq!(store.len()?, 0);
+    assert_eq!(store.len_graph(&g.into())?, 0);
+    assert!(store.is_empty()?);
+    Ok(())
+}
+
+#[test]
+fn watch() -> Result<()> {
+    use crate::model::*;
+
+    let ex = NamedNode::new("http://example.com")?;
+    let quad = Quad::new(ex.clone(), ex.clone(), ex.clone(), None);
+
+    let store = SledStore::new()?;
+    let changes = store.watch();
+
+    store.transaction(|transaction| {
+        transaction.insert(&quad)?;
+        transaction.insert(&quad)
+    })?;
+    assert_eq!(
+        changes.try_recv(),
+        Ok(vec![QuadEvent::Inserted(quad.clone())])
+    );
+
+    // The writes outside of the transactions are reported one by one
+    store.load_graph(
+        b"<http://example.com> <http://example.com> <http://example.com> .".as_ref(),
+        GraphSyntax::NTriples,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    assert!(changes.try_recv().is_err());
+    store.load_graph(
+        b"<http://example.com> <http://example.com> <http://example.com> , 1 .".as_ref(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    assert_eq!(
+        changes.try_recv(),
+        Ok(vec![QuadEvent::Inserted(Quad::new(
+            ex.clone(),
+            ex,
+            Literal::from(1),
+            None
+        ))])
+    );
+
+    store.remove(&quad)?;
+    assert_eq!(changes.try_recv(), Ok(vec![QuadEvent::Removed(quad)]));
+    Ok(())
+}
+
+#[test]
+fn format_version() -> Result<()> {
+    use std::error::Error;
+
+    // A database written before the format versions
+    let db = Config::new().temporary(true).open()?;
+    db.open_tree("spog")?.insert(&[0], &[])?;
+
+    let error = SledStore::open_db(&db, Path::new("")).err().unwrap();
+    assert!(matches!(
+        error
+            .source()
+            .and_then(|e| e.downcast_ref::<StorageError>()),
+        Some(StorageError::OutdatedFormat { version: 0, .. })
+    ));
+
+    db.insert(
+        FORMAT_VERSION_KEY,
+        &(LATEST_FORMAT_VERSION + 1).to_be_bytes(),
+    )?;
+    let error = SledStore::open_db(&db, Path::new("")).err().unwrap();
+    assert!(matches!(
+        error
+            .source()
+            .and_then(|e| e.downcast_ref::<StorageError>()),
+        Some(StorageError::UnsupportedFormat { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "full-text")]
+fn words_index() -> Result<()> {
+    use crate::model::*;
+
+    let s = NamedNode::new("http://example.com/s")?;
+    let p = NamedNode::new("http://example.com/p")?;
+    let short = Literal::new_simple_literal("foo bar");
+    let long = Literal::new_language_tagged_literal("Foo, baz and a long text", "en")?;
+    let encoded_short = EncodedTerm::from(&short);
+    let encoded_long = EncodedTerm::from(&long);
+    let matching = |store: &SledStore, query: &str| {
+        store
+            .encoded_literals_matching(query)
+            .collect::<Result<HashSet<_>>>()
+    };
+
+    let store = SledStore::new()?;
+    store.transaction(|transaction| {
+        transaction.insert(&Quad::new(s.clone(), p.clone(), short.clone(), None))?;
+        transaction.insert(&Quad::new(s.clone(), p.clone(), long.clone(), None))
+    })?;
+    store.insert(&Quad::new(p.clone(), p.clone(), long.clone(), None))?;
+    assert_eq!(
+        matching(&store, "FOO")?,
+        [encoded_short, encoded_long].iter().copied().collect()
+    );
+    assert_eq!(
+        matching(&store, "baz foo")?,
+        [encoded_long].iter().copied().collect()
+    );
+    assert!(matching(&store, "bar baz")?.is_empty());
+    assert_eq!(store.literals_matching_count("foo"), Some(2));
+    assert_eq!(store.literals_matching_count("bar foo"), Some(1));
+
+    // The literal is indexed until its last quad is removed
+    store.remove(&Quad::new(s, p.clone(), long.clone(), None))?;
+    assert_eq!(
+        matching(&store, "baz")?,
+        [encoded_long].iter().copied().collect()
+    );
+    store.transaction(|transaction| {
+        transaction.remove(&Quad::new(p.clone(), p.clone(), long.clone(), None))
+    })?;
+    assert!(matching(&store, "baz")?.is_empty());
+    assert_eq!(store.literals_matching_count("foo"), Some(1));
+    Ok(())
+}
@@ -0,0 +1,659 @@
+//! A [Turtle-star](https://w3c.github.io/rdf-star/cg-spec/#turtle-star) parser.
+//!
+//! The rio parsers do not support the RDF-star quoted triples so the N-Triples and Turtle documents containing some are parsed here.
+//! [N-Triples-star](https://w3c.github.io/rdf-star/cg-spec/#n-triples-star) is a subset of Turtle-star and is parsed by the same parser
+//! with the Turtle abbreviations disabled.
+
+use crate::model::vocab::{rdf, xsd};
+use crate::model::*;
+use crate::{Error, Result};
+use oxiri::Iri;
+use rand::random;
+use std::char;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Parses a Turtle-star document, or a N-Triples-star one if `is_ntriples` is set, and calls `on_triple` on each of its triples
+///
+/// The blank node labels are mapped to blank node ids using `bnodes_map` like the rio based loaders do.
+pub(crate) fn parse_turtle_star(
+    input: &str,
+    is_ntriples: bool,
+    base_iri: &str,
+    bnodes_map: &mut HashMap<String, u128>,
+    on_triple: &mut dyn FnMut(Triple) -> Result<()>,
+) -> Result<()> {
+    let base_iri = if base_iri.is_empty() {
+        None
+    } else {
+        Some(
+            Iri::parse(base_iri.to_owned())
+                .map_err(|e| Error::msg(format!("Invalid base IRI '{}': {}", base_iri, e)))?,
+        )
+    };
+    TurtleStarParser {
+        input,
+        is_ntriples,
+        position: 0,
+        base_iri,
+        prefixes: HashMap::default(),
+        bnodes_map,
+        on_triple,
+    }
+    .parse_document()
+}
+
+struct TurtleStarParser<'a> {
+    input: &'a str,
+    is_ntriples: bool,
+    position: usize,
+    base_iri: Option<Iri<String>>,
+    prefixes: HashMap<String, String>,
+    bnodes_map: &'a mut HashMap<String, u128>,
+    on_triple: &'a mut dyn FnMut(Triple) -> Result<()>,
+}
+
+impl<'a> TurtleStarParser<'a> {
+    fn parse_document(&mut self) -> Result<()> {
+        loop {
+            self.skip_whitespaces();
+            if self.rest().is_empty() {
+                return Ok(());
+            }
+            self.parse_statement()?;
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<()> {
+        if self.is_ntriples {
+            let subject = self.parse_subject()?;
+            self.skip_whitespaces();
+            let predicate = self.parse_iri()?;
+            self.skip_whitespaces();
+            let object = self.parse_object()?;
+            self.skip_whitespaces();
+            self.expect(".")?;
+            return (self.on_triple)(Triple::new(subject, predicate, object));
+        }
+        if self.consume("@prefix") {
+            self.parse_prefix_declaration()?;
+            self.skip_whitespaces();
+            self.expect(".")
+        } else if self.consume("@base") {
+            self.parse_base_declaration()?;
+            self.skip_whitespaces();
+            self.expect(".")
+        } else if self.consume_keyword("PREFIX") {
+            self.parse_prefix_declaration()
+        } else if self.consume_keyword("BASE") {
+            self.parse_base_declaration()
+        } else {
+            self.parse_triples()?;
+            self.skip_whitespaces();
+            self.expect(".")
+        }
+    }
+
+    fn parse_prefix_declaration(&mut self) -> Result<()> {
+        self.skip_whitespaces();
+        let prefix = self.parse_prefix_name();
+        self.expect(":")?;
+        self.skip_whitespaces();
+        let iri = self.parse_iriref()?;
+        self.prefixes.insert(prefix, iri.into_string());
+        Ok(())
+    }
+
+    fn parse_base_declaration(&mut self) -> Result<()> {
+        self.skip_whitespaces();
+        let iri = self.parse_iriref()?;
+        self.base_iri = Some(Iri::parse(iri.into_string()).map_err(|e| self.error(e))?);
+        Ok(())
+    }
+
+    fn parse_triples(&mut self) -> Result<()> {
+        if self.peek() == Some('[') && !self.is_anon() {
+            let subject = self.parse_blank_node_property_list()?;
+            self.skip_whitespaces();
+            if self.peek() != Some('.') {
+                self.parse_predicate_object_list(&subject.into())?;
+            }
+            Ok(())
+        } else {
+            let subject = self.parse_subject()?;
+            self.skip_whitespaces();
+            self.parse_predicate_object_list(&subject)
+        }
+    }
+
+    fn parse_predicate_object_list(&mut self, subject: &Subject) -> Result<()> {
+        loop {
+            let predicate = self.parse_verb()?;
+            self.skip_whitespaces();
+            self.parse_object_list(subject, &predicate)?;
+            self.skip_whitespaces();
+            if !self.consume(";") {
+                return Ok(());
+            }
+            loop {
+                self.skip_whitespaces();
+                if !self.consume(";") {
+                    break;
+                }
+            }
+            match self.peek() {
+                Some('.') | Some(']') | Some('|') | None => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+
+    fn parse_object_list(&mut self, subject: &Subject, predicate: &NamedNode) -> Result<()> {
+        loop {
+            let object = self.parse_object()?;
+            let triple = Triple::new(subject.clone(), predicate.clone(), object);
+            self.skip_whitespaces();
+            if self.consume("{|") {
+                // The annotation is about the triple that is also asserted
+                self.skip_whitespaces();
+                self.parse_predicate_object_list(&triple.clone().into())?;
+                self.skip_whitespaces();
+                self.expect("|}")?;
+                self.skip_whitespaces();
+            }
+            (self.on_triple)(triple)?;
+            if !self.consume(",") {
+                return Ok(());
+            }
+            self.skip_whitespaces();
+        }
+    }
+
+    fn parse_verb(&mut self) -> Result<NamedNode> {
+        if !self.is_ntriples && self.rest().starts_with('a') && !self.is_prefixed_name_start() {
+            self.position += 1;
+            Ok(rdf::TYPE.clone())
+        } else {
+            self.parse_iri()
+        }
+    }
+
+    fn parse_subject(&mut self) -> Result<Subject> {
+        if self.is_ntriples {
+            return match self.peek() {
+                Some('<') if self.rest().starts_with("<<") => {
+                    Ok(self.parse_quoted_triple()?.into())
+                }
+                Some('_') => Ok(self.parse_blank_node()?.into()),
+                _ => Ok(self.parse_iri()?.into()),
+            };
+        }
+        match self.peek() {
+            Some('<') if self.rest().starts_with("<<") => Ok(self.parse_quoted_triple()?.into()),
+            Some('<') => Ok(self.parse_iri()?.into()),
+            Some('_') | Some('[') => Ok(self.parse_blank_node()?.into()),
+            Some('(') => match self.parse_collection()? {
+                Term::NamedNode(node) => Ok(node.into()),
+                Term::BlankNode(node) => Ok(node.into()),
+                _ => Err(self.error("A collection should be a named node or a blank node")),
+            },
+            _ => Ok(self.parse_iri()?.into()),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Term> {
+        if self.is_ntriples {
+            return self.parse_quoted_triple_object();
+        }
+        match self.peek() {
+            Some('<') if self.rest().starts_with("<<") => Ok(self.parse_quoted_triple()?.into()),
+            Some('[') if !self.is_anon() => Ok(self.parse_blank_node_property_list()?.into()),
+            Some('(') => self.parse_collection(),
+            _ => self.parse_quoted_triple_object(),
+        }
+    }
+
+    fn parse_quoted_triple(&mut self) -> Result<Triple> {
+        self.expect("<<")?;
+        self.skip_whitespaces();
+        let subject: Subject = match self.peek() {
+            Some('<') if self.rest().starts_with("<<") => self.parse_quoted_triple()?.into(),
+            Some('_') | Some('[') => self.parse_blank_node()?.into(),
+            _ => self.parse_iri()?.into(),
+        };
+        self.skip_whitespaces();
+        let predicate = self.parse_verb()?;
+        self.skip_whitespaces();
+        let object = self.parse_quoted_triple_object()?;
+        self.skip_whitespaces();
+        self.expect(">>")?;
+        Ok(Triple::new(subject, predicate, object))
+    }
+
+    /// Parses the objects allowed in quoted triples: named nodes, blank nodes, literals and quoted triples
+    fn parse_quoted_triple_object(&mut self) -> Result<Term> {
+        match self.peek() {
+            Some('<') if self.rest().starts_with("<<") => Ok(self.parse_quoted_triple()?.into()),
+            Some('_') | Some('[') => Ok(self.parse_blank_node()?.into()),
+            Some('"') => Ok(self.parse_rdf_literal()?.into()),
+            _ if self.is_ntriples => Ok(self.parse_iri()?.into()),
+            Some('\'') => Ok(self.parse_rdf_literal()?.into()),
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' || c == '.' => {
+                Ok(self.parse_numeric_literal()?.into())
+            }
+            _ => {
+                if self.rest().starts_with("true") && self.consume_keyword("true") {
+                    Ok(Literal::from(true).into())
+                } else if self.rest().starts_with("false") && self.consume_keyword("false") {
+                    Ok(Literal::from(false).into())
+                } else {
+                    Ok(self.parse_iri()?.into())
+                }
+            }
+        }
+    }
+
+    fn parse_blank_node_property_list(&mut self) -> Result<BlankNode> {
+        self.expect("[")?;
+        self.skip_whitespaces();
+        let node = BlankNode::default();
+        self.parse_predicate_object_list(&node.clone().into())?;
+        self.skip_whitespaces();
+        self.expect("]")?;
+        Ok(node)
+    }
+
+    fn parse_collection(&mut self) -> Result<Term> {
+        self.expect("(")?;
+        let mut elements = Vec::new();
+        loop {
+            self.skip_whitespaces();
+            if self.consume(")") {
+                break;
+            }
+            elements.push(self.parse_object()?);
+        }
+        let mut list: Term = rdf::NIL.clone().into();
+        for element in elements.into_iter().rev() {
+            let node = BlankNode::default();
+            (self.on_triple)(Triple::new(node.clone(), rdf::FIRST.clone(), element))?;
+            (self.on_triple)(Triple::new(node.clone(), rdf::REST.clone(), list))?;
+            list = node.into();
+        }
+        Ok(list)
+    }
+
+    fn parse_blank_node(&mut self) -> Result<BlankNode> {
+        if !self.is_ntriples && self.consume("[") {
+            self.skip_whitespaces();
+            self.expect("]")?;
+            return Ok(BlankNode::default());
+        }
+        self.expect("_:")?;
+        let start = self.position;
+        match self.peek() {
+            Some(c) if is_pn_chars_u(c) || c.is_ascii_digit() => self.position += c.len_utf8(),
+            _ => return Err(self.error("Invalid blank node label")),
+        }
+        self.consume_name_chars();
+        let label = &self.input[start..self.position];
+        let id = *self
+            .bnodes_map
+            .entry(label.to_owned())
+            .or_insert_with(random::<u128>);
+        Ok(BlankNode::new_from_unique_id(id))
+    }
+
+    fn parse_iri(&mut self) -> Result<NamedNode> {
+        if self.is_ntriples || self.peek() == Some('<') {
+            return self.parse_iriref();
+        }
+        let prefix = self.parse_prefix_name();
+        self.expect(":")?;
+        let mut iri = self
+            .prefixes
+            .get(&prefix)
+            .ok_or_else(|| self.error(format!("The prefix {}: has not been declared", prefix)))?
+            .clone();
+        iri.push_str(&self.parse_local_name()?);
+        NamedNode::new(iri).map_err(|e| self.error(e))
+    }
+
+    fn parse_iriref(&mut self) -> Result<NamedNode> {
+        self.expect("<")?;
+        let mut iri = String::new();
+        loop {
+            match self.next_char() {
+                Some('>') => break,
+                Some('\\') => iri.push(self.parse_uchar()?),
+                Some(c) if c <= ' ' || "<\"{}|^`".contains(c) => {
+                    return Err(self.error(format!("Invalid character '{}' in IRI", c)))
+                }
+                Some(c) => iri.push(c),
+                None => return Err(self.error("Unterminated IRI")),
+            }
+        }
+        let iri = if let Some(base_iri) = &self.base_iri {
+            base_iri.resolve(&iri)
+        } else {
+            Iri::parse(iri)
+        }
+        .map_err(|e| self.error(e))?;
+        Ok(NamedNode::new_unchecked(iri.into_inner()))
+    }
+
+    fn parse_prefix_name(&mut self) -> String {
+        let start = self.position;
+        if let Some(c) = self.peek() {
+            if is_pn_chars_base(c) {
+                self.position += c.len_utf8();
+                self.consume_name_chars();
+            }
+        }
+        self.input[start..self.position].to_owned()
+    }
+
+    fn parse_local_name(&mut self) -> Result<String> {
+        let mut local = String::new();
+        let mut is_first = true;
+        loop {
+            match self.peek() {
+                Some('%') => {
+                    let escape = self.rest().get(..3).unwrap_or("");
+                    if escape.len() != 3 || !escape[1..].chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(self.error("Invalid percent encoding in local name"));
+                    }
+                    local.push_str(escape);
+                    self.position += 3;
+                }
+                Some('\\') => {
+                    self.position += 1;
+                    match self.next_char() {
+                        Some(c) if "_~.-!$&'()*+,;=/?#@%".contains(c) => local.push(c),
+                        _ => return Err(self.error("Invalid escape in local name")),
+                    }
+                }
+                Some(c)
+                    if is_pn_chars(c)
+                        || c == ':'
+                        || (is_first && c.is_ascii_digit())
+                        || (!is_first && c == '.' && self.is_name_continued_after_dots()) =>
+                {
+                    local.push(c);
+                    self.position += c.len_utf8();
+                }
+                _ => return Ok(local),
+            }
+            is_first = false;
+        }
+    }
+
+    fn parse_rdf_literal(&mut self) -> Result<Literal> {
+        let value = self.parse_string()?;
+        if self.consume("@") {
+            let start = self.position;
+            while let Some(c) = self.peek() {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    self.position += 1;
+                } else {
+                    break;
+                }
+            }
+            let language = &self.input[start..self.position];
+            Literal::new_language_tagged_literal(value, language).map_err(|e| self.error(e))
+        } else if self.consume("^^") {
+            Ok(Literal::new_typed_literal(value, self.parse_iri()?))
+        } else {
+            Ok(Literal::new_simple_literal(value))
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        let delimiter = if self.is_ntriples {
+            "\""
+        } else if self.rest().starts_with("\"\"\"") {
+            "\"\"\""
+        } else if self.rest().starts_with("'''") {
+            "'''"
+        } else if self.rest().starts_with('"') {
+            "\""
+        } else {
+            "'"
+        };
+        self.position += delimiter.len();
+        let is_long = delimiter.len() == 3;
+        let mut value = String::new();
+        loop {
+            if self.consume(delimiter) {
+                return Ok(value);
+            }
+            match self.next_char() {
+                Some('\\') => value.push(self.parse_echar()?),
+                Some('\n') | Some('\r') if !is_long => {
+                    return Err(self.error("Line break in a short string literal"))
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("Unterminated string literal")),
+            }
+        }
+    }
+
+    fn parse_echar(&mut self) -> Result<char> {
+        let c = match self.peek() {
+            Some('t') => '\t',
+            Some('b') => '\u{8}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('f') => '\u{C}',
+            Some('"') => '"',
+            Some('\'') => '\'',
+            Some('\\') => '\\',
+            _ => return self.parse_uchar(),
+        };
+        self.position += 1;
+        Ok(c)
+    }
+
+    /// Parses an `\uXXXX` or `\UXXXXXXXX` escape, the backslash being already consumed
+    fn parse_uchar(&mut self) -> Result<char> {
+        let length = match self.next_char() {
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => return Err(self.error("Invalid escape sequence")),
+        };
+        let code = self
+            .rest()
+            .get(..length)
+            .and_then(|code| u32::from_str_radix(code, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("Invalid unicode escape sequence"))?;
+        self.position += length;
+        Ok(code)
+    }
+
+    fn parse_numeric_literal(&mut self) -> Result<Literal> {
+        let start = self.position;
+        if let Some('+') | Some('-') = self.peek() {
+            self.position += 1;
+        }
+        let integer_digits = self.consume_digits();
+        let mut datatype = &*xsd::INTEGER;
+        if self.peek() == Some('.')
+            && (self.next_is_digit_after(1)
+                || (integer_digits > 0 && self.rest()[1..].starts_with(|c| c == 'e' || c == 'E')))
+        {
+            self.position += 1;
+            self.consume_digits();
+            datatype = &*xsd::DECIMAL;
+        } else if integer_digits == 0 && self.peek() != Some('e') && self.peek() != Some('E') {
+            return Err(self.error("Invalid numeric literal"));
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            self.position += 1;
+            if let Some('+') | Some('-') = self.peek() {
+                self.position += 1;
+            }
+            if self.consume_digits() == 0 {
+                return Err(self.error("Invalid double literal exponent"));
+            }
+            datatype = &*xsd::DOUBLE;
+        }
+        Ok(Literal::new_typed_literal(
+            &self.input[start..self.position],
+            datatype.clone(),
+        ))
+    }
+
+    fn consume_digits(&mut self) -> usize {
+        let start = self.position;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn next_is_digit_after(&self, offset: usize) -> bool {
+        self.rest()[offset..]
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_digit())
+    }
+
+    /// Consumes the characters of a prefix or a blank node label, that could not end with a dot
+    fn consume_name_chars(&mut self) {
+        while let Some(c) = self.peek() {
+            if is_pn_chars(c) || (c == '.' && self.is_name_continued_after_dots()) {
+                self.position += c.len_utf8();
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Checks if the dots at the current position are followed by a name character and so are part of the name
+    fn is_name_continued_after_dots(&self) -> bool {
+        self.rest()
+            .trim_start_matches('.')
+            .chars()
+            .next()
+            .map_or(false, |c| {
+                is_pn_chars(c) || c == ':' || c == '%' || c == '\\'
+            })
+    }
+
+    fn is_prefixed_name_start(&self) -> bool {
+        let mut chars = self.rest().chars().skip(1);
+        match chars.next() {
+            Some(c) => is_pn_chars(c) || c == '.' || c == ':',
+            None => false,
+        }
+    }
+
+    /// Checks if the current `[` starts an anonymous blank node `[]`
+    fn is_anon(&self) -> bool {
+        self.rest()[1..]
+            .trim_start_matches(|c: char| c.is_whitespace())
+            .starts_with(']')
+    }
+
+    fn skip_whitespaces(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed =
+                rest.trim_start_matches(|c: char| c == ' ' || c == '\t' || c == '\r' || c == '\n');
+            self.position += rest.len() - trimmed.len();
+            if trimmed.starts_with('#') {
+                self.position += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn consume(&mut self, text: &str) -> bool {
+        if self.rest().starts_with(text) {
+            self.position += text.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes a case-insensitive keyword if it is not the start of a longer name
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        let rest = self.rest();
+        if rest.len() >= keyword.len()
+            && rest.is_char_boundary(keyword.len())
+            && rest[..keyword.len()].eq_ignore_ascii_case(keyword)
+            && !rest[keyword.len()..]
+                .chars()
+                .next()
+                .map_or(false, |c| is_pn_chars(c) || c == ':')
+        {
+            self.position += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<()> {
+        if self.consume(text) {
+            Ok(())
+        } else {
+            Err(self.error(format!("'{}' expected", text)))
+        }
+    }
+
+    fn error(&self, message: impl fmt::Display) -> Error {
+        let before = &self.input[..self.position];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Error::msg(format!(
+            "Turtle-star parsing error at line {} column {}: {}",
+            line, column, message
+        ))
+    }
+}
+
+fn is_pn_chars_base(c: char) -> bool {
+    matches!(c,
+        'A'..='Z'
+        | 'a'..='z'
+        | '\u{00C0}'..='\u{00D6}'
+        | '\u{00D8}'..='\u{00F6}'
+        | '\u{00F8}'..='\u{02FF}'
+        | '\u{0370}'..='\u{037D}'
+        | '\u{037F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
+}
+
+fn is_pn_chars_u(c: char) -> bool {
+    c == '_' || is_pn_chars_base(c)
+}
+
+fn is_pn_chars(c: char) -> bool {
+    is_pn_chars_u(c)
+        || matches!(c,
+            '-' | '0'..='9' | '\u{00B7}' | '\u{0300}'..='\u{036F}' | '\u{203F}'..='\u{2040}')
+}
@@ -25,8 +25,12 @@ pub trait FileSyntax: Sized {
 #[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
 pub enum GraphSyntax {
     /// [N-Triples](https://www.w3.org/TR/n-triples/)
+    ///
+    /// The quoted triples are read and written using [N-Triples-star](https://w3c.github.io/rdf-star/cg-spec/#n-triples-star).
     NTriples,
     /// [Turtle](https://www.w3.org/TR/turtle/)
+    ///
+    /// The quoted triples are read using [Turtle-star](https://w3c.github.io/rdf-star/cg-spec/#turtle-star) but could not be written.
     Turtle,
     /// [RDF XML](https://www.w3.org/TR/rdf-syntax-grammar/)
     RdfXml,
@@ -0,0 +1,75 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+#[test]
+fn bnode_function_test() -> Result<()> {
+    let store = MemoryStore::new();
+    let solutions = query_store(
+        &store,
+        r#"SELECT ?i (BNODE("a") AS ?a1) (BNODE("a") AS ?a2) (BNODE("b") AS ?b) WHERE { VALUES ?i { 1 2 } }"#,
+    )?;
+    assert_eq!(solutions.len(), 2);
+    for solution in &solutions {
+        assert!(solution.get("a1").is_some());
+        assert_eq!(solution.get("a1"), solution.get("a2"));
+        assert_ne!(solution.get("a1"), solution.get("b"));
+    }
+    assert_ne!(solutions[0].get("a1"), solutions[1].get("a1"));
+    Ok(())
+}
+
+#[test]
+fn blank_nodes_xml_round_trip_test() -> Result<()> {
+    blank_nodes_round_trip(QueryResultSyntax::Xml)
+}
+
+#[test]
+fn blank_nodes_json_round_trip_test() -> Result<()> {
+    blank_nodes_round_trip(QueryResultSyntax::Json)
+}
+
+fn blank_nodes_round_trip(syntax: QueryResultSyntax) -> Result<()> {
+    let store = MemoryStore::new();
+    let p = NamedNode::new("http://example.com/p")?;
+    let b1 = BlankNode::default();
+    let b2 = BlankNode::new("b2")?;
+    store.insert(Quad::new(
+        b1.clone(),
+        p.clone(),
+        b2.clone(),
+        GraphName::DefaultGraph,
+    ));
+    store.insert(Quad::new(b2, p, b1, GraphName::DefaultGraph));
+
+    let query = "SELECT ?s ?o WHERE { ?s ?p ?o }";
+    let expected = query_store(&store, query)?;
+    let mut serialization = Vec::default();
+    store
+        .prepare_query(query, QueryOptions::default())?
+        .exec()?
+        .write(&mut serialization, syntax)?;
+    let result = QueryResult::read(serialization.as_slice(), syntax)?;
+    if let QueryResult::Solutions(solutions) = result {
+        let actual = solutions.collect::<Result<Vec<_>>>()?;
+        assert_eq!(actual.len(), expected.len());
+        for actual in &actual {
+            assert!(expected.iter().any(|expected| {
+                actual.get("s") == expected.get("s") && actual.get("o") == expected.get("o")
+            }));
+        }
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+fn query_store(store: &MemoryStore, query: &str) -> Result<Vec<QuerySolution>> {
+    match store
+        .prepare_query(query, QueryOptions::default())?
+        .exec()?
+    {
+        QueryResult::Solutions(solutions) => solutions.collect(),
+        _ => Err(Error::msg("Expected solutions")),
+    }
+}
@@ -0,0 +1,78 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+const DATA: &str = r#"
+@prefix : <http://www.example.org/> .
+:s1 :p "a", "b", "c" .
+:s2 :p "a" ; :q "a" .
+"#;
+
+#[test]
+fn custom_aggregate_with_group_by_test() -> Result<()> {
+    let solutions = query_store(
+        "PREFIX : <http://www.example.org/> SELECT ?s (:concat(?o) AS ?c) (:concat(DISTINCT ?o) AS ?d) WHERE { ?s ?p ?o } GROUP BY ?s ORDER BY ?s",
+    )?;
+    assert_eq!(solutions.len(), 2);
+    assert_eq!(
+        solutions[0].get("c"),
+        Some(&Literal::new_simple_literal("abc").into())
+    );
+    assert_eq!(
+        solutions[1].get("c"),
+        Some(&Literal::new_simple_literal("aa").into())
+    );
+    assert_eq!(
+        solutions[1].get("d"),
+        Some(&Literal::new_simple_literal("a").into())
+    );
+    Ok(())
+}
+
+#[test]
+fn custom_aggregate_in_having_test() -> Result<()> {
+    let solutions = query_store(
+        "PREFIX : <http://www.example.org/> SELECT ?s WHERE { ?s ?p ?o } GROUP BY ?s HAVING(STRLEN(:concat(?o)) > 2)",
+    )?;
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(
+        solutions[0].get("s"),
+        Some(&NamedNode::new("http://www.example.org/s1")?.into())
+    );
+    Ok(())
+}
+
+#[test]
+fn custom_aggregate_arity_test() {
+    assert!(query_store(
+        "PREFIX : <http://www.example.org/> SELECT (:concat(?s, ?o) AS ?c) WHERE { ?s ?p ?o }"
+    )
+    .is_err());
+}
+
+fn query_store(query: &str) -> Result<Vec<QuerySolution>> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    let options = QueryOptions::default().with_custom_aggregate(
+        NamedNode::new("http://www.example.org/concat")?,
+        Vec::new,
+        |values: &mut Vec<String>, value| {
+            if let Term::Literal(value) = value {
+                values.push(value.value().to_owned());
+            }
+        },
+        |mut values| {
+            values.sort();
+            Some(Literal::new_simple_literal(values.concat()).into())
+        },
+    );
+    match store.prepare_query(query, options)?.exec()? {
+        QueryResult::Solutions(solutions) => solutions.collect(),
+        _ => Err(Error::msg("Expected solutions")),
+    }
+}
@@ -0,0 +1,70 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+const DATA: &str = r#"
+@prefix : <http://www.example.org/> .
+:s :p :o .
+:o :p [ :q _:b ] .
+_:b :q :s .
+"#;
+
+#[test]
+fn custom_describe_handler_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    let query = store.prepare_query(
+        "PREFIX : <http://www.example.org/> DESCRIBE ?s ?o WHERE { ?s :p ?o . FILTER(isIRI(?o)) }",
+        QueryOptions::default().with_describe_handler(
+            |resource: &Term, _: &dyn QueryDataset| -> Result<Vec<Triple>> {
+                Ok(vec![Triple::new(
+                    NamedNode::new("http://www.example.org/described")?,
+                    NamedNode::new("http://www.example.org/value")?,
+                    resource.clone(),
+                )])
+            },
+        ),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Graph(triples) = result {
+        let mut objects = triples
+            .map(|triple| Ok(triple?.object.to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        objects.sort();
+        assert_eq!(
+            objects,
+            vec!["<http://www.example.org/o>", "<http://www.example.org/s>"]
+        );
+        Ok(())
+    } else {
+        Err(Error::msg("Expected triples"))
+    }
+}
+
+#[test]
+fn symmetric_concise_bounded_description_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    let query = store.prepare_query(
+        "DESCRIBE <http://www.example.org/s>",
+        QueryOptions::default().with_describe_handler(SymmetricConciseBoundedDescription),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Graph(triples) = result {
+        // :s :p :o and, through the blank nodes linked to :s, _:b :q :s, [] :q _:b and :o :p []
+        assert_eq!(triples.collect::<Result<Vec<_>>>()?.len(), 4);
+        Ok(())
+    } else {
+        Err(Error::msg("Expected triples"))
+    }
+}
@@ -0,0 +1,146 @@
+use oxigraph::model::*;
+use oxigraph::sparql::{QueryOptions, QueryResult};
+use oxigraph::store::FrozenStore;
+use oxigraph::*;
+use rand::random;
+use std::collections::HashSet;
+use std::env::temp_dir;
+use std::fs::remove_file;
+
+fn node(id: usize) -> Result<NamedNode> {
+    Ok(NamedNode::new(format!("http://www.example.org/{}", id))?)
+}
+
+#[test]
+fn frozen_store_test() -> Result<()> {
+    let store = MemoryStore::new();
+    let graph = node(1000)?;
+    for i in 0..100 {
+        // More strings than a dictionary block
+        store.insert(Quad::new(
+            node(i)?,
+            node(1001)?,
+            Literal::new_language_tagged_literal(format!("long value number {}", i), "en")?,
+            graph.clone(),
+        ));
+        store.insert(Quad::new(
+            node(i)?,
+            node(1002)?,
+            Literal::from(i as i64),
+            None,
+        ));
+    }
+    store.insert(Quad::new(
+        BlankNode::default(),
+        node(1002)?,
+        Literal::new_simple_literal("a long enough simple literal"),
+        None,
+    ));
+    let empty_graph = NamedOrBlankNode::from(node(1003)?);
+    store.insert_named_graph(&empty_graph);
+
+    let mut file = temp_dir();
+    file.push(random::<u128>().to_string());
+    FrozenStore::create(&file, &store)?;
+    {
+        let frozen = FrozenStore::open(&file)?;
+        assert_eq!(frozen.len(), store.len());
+        assert_eq!(
+            frozen
+                .quads_for_pattern(None, None, None, None)
+                .collect::<Result<HashSet<_>>>()?,
+            store.quads_for_pattern(None, None, None, None).collect()
+        );
+        assert_eq!(
+            frozen.named_graphs().collect::<Result<HashSet<_>>>()?,
+            store.named_graphs().collect()
+        );
+        assert!(frozen.contains_named_graph(&empty_graph)?);
+        assert!(!frozen.contains_named_graph(&node(1004)?.into())?);
+
+        // Each pattern shape uses an index
+        let quad = Quad::new(
+            node(1)?,
+            node(1001)?,
+            Literal::new_language_tagged_literal("long value number 1", "en")?,
+            graph.clone(),
+        );
+        assert!(frozen.contains(&quad)?);
+        assert!(!frozen.contains(&Quad::new(
+            node(1)?,
+            node(1001)?,
+            Literal::from(1),
+            graph.clone()
+        ))?);
+        assert_eq!(
+            frozen
+                .quads_for_pattern(Some(&node(1)?.into()), None, None, None)
+                .count(),
+            2
+        );
+        assert_eq!(
+            frozen
+                .quads_for_pattern(None, Some(&node(1002)?), None, None)
+                .count(),
+            101
+        );
+        assert_eq!(
+            frozen
+                .quads_for_pattern(None, None, Some(&Literal::from(5).into()), None)
+                .count(),
+            1
+        );
+        assert_eq!(
+            frozen
+                .quads_for_pattern(None, Some(&node(1001)?), None, Some(&graph.into()))
+                .count(),
+            100
+        );
+        assert_eq!(
+            frozen
+                .quads_for_pattern(None, None, None, Some(&node(1005)?.into()))
+                .count(),
+            0
+        );
+
+        let prepared_query = frozen.prepare_query(
+            "SELECT (COUNT(?o) AS ?c) WHERE { ?s <http://www.example.org/1002> ?o FILTER(isLiteral(?o) && STRLEN(STR(?o)) > 10) }",
+            QueryOptions::default(),
+        )?;
+        let result = prepared_query.exec()?;
+        if let QueryResult::Solutions(mut solutions) = result {
+            assert_eq!(
+                solutions.next().unwrap()?.get("c"),
+                Some(&Literal::from(1).into())
+            );
+        } else {
+            panic!("A SELECT query should return solutions")
+        }
+    }
+    remove_file(&file)?;
+    Ok(())
+}
+
+#[cfg(feature = "memmap")]
+#[test]
+fn frozen_store_mmap_test() -> Result<()> {
+    let store = MemoryStore::new();
+    let quad = Quad::new(node(0)?, node(1)?, node(2)?, None);
+    store.insert(quad.clone());
+
+    let mut file = temp_dir();
+    file.push(random::<u128>().to_string());
+    FrozenStore::create(&file, &store)?;
+    {
+        // The file is not modified while it is mapped
+        let frozen = unsafe { FrozenStore::open_mmap(&file)? };
+        assert_eq!(
+            frozen
+                .quads_for_pattern(None, None, None, None)
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad]
+        );
+    }
+    remove_file(&file)?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use oxigraph::model::*;
+use oxigraph::sparql::{QueryOptions, QueryResult};
+use oxigraph::store::HdtStore;
+use oxigraph::*;
+use rand::random;
+use std::collections::HashSet;
+use std::env::temp_dir;
+use std::fs::remove_file;
+
+fn node(id: usize) -> Result<NamedNode> {
+    Ok(NamedNode::new(format!("http://www.example.org/{}", id))?)
+}
+
+#[test]
+fn hdt_store_test() -> Result<()> {
+    let store = MemoryStore::new();
+    for i in 0..100 {
+        // More strings than a dictionary block
+        store.insert(Quad::new(
+            node(i)?,
+            node(1001)?,
+            Literal::new_language_tagged_literal(format!("long value number {}", i), "en")?,
+            None,
+        ));
+        store.insert(Quad::new(
+            node(i)?,
+            node(1002)?,
+            Literal::from(i as i64),
+            None,
+        ));
+        // Shared subjects and objects
+        store.insert(Quad::new(node(i)?, node(1003)?, node(i + 1)?, None));
+    }
+    store.insert(Quad::new(
+        BlankNode::default(),
+        node(1002)?,
+        Literal::new_simple_literal("a long enough simple literal"),
+        None,
+    ));
+    // Named graphs are not exported
+    store.insert(Quad::new(node(1)?, node(1001)?, node(1)?, node(1004)?));
+
+    let mut file = temp_dir();
+    file.push(random::<u128>().to_string());
+    HdtStore::write(&file, &store)?;
+    {
+        let hdt = HdtStore::open(&file)?;
+        assert_eq!(hdt.len(), store.len() - 1);
+        assert_eq!(
+            hdt.quads_for_pattern(None, None, None, None)
+                .collect::<Result<HashSet<_>>>()?,
+            store
+                .quads_for_pattern(None, None, None, Some(&GraphName::DefaultGraph))
+                .collect()
+        );
+
+        let quad = Quad::new(
+            node(1)?,
+            node(1001)?,
+            Literal::new_language_tagged_literal("long value number 1", "en")?,
+            None,
+        );
+        assert!(hdt.contains(&quad)?);
+        assert!(!hdt.contains(&Quad::new(node(1)?, node(1001)?, Literal::from(1), None))?);
+        assert!(!hdt.contains(&Quad::new(
+            node(1)?,
+            node(1001)?,
+            Literal::new_language_tagged_literal("long value number 1", "en")?,
+            node(1004)?
+        ))?);
+        assert_eq!(
+            hdt.quads_for_pattern(Some(&node(1)?.into()), None, None, None)
+                .count(),
+            3
+        );
+        assert_eq!(
+            hdt.quads_for_pattern(Some(&node(100)?.into()), None, None, None)
+                .count(),
+            0
+        );
+        assert_eq!(
+            hdt.quads_for_pattern(Some(&node(99)?.into()), Some(&node(1003)?), None, None)
+                .count(),
+            1
+        );
+        assert_eq!(
+            hdt.quads_for_pattern(None, Some(&node(1002)?), None, None)
+                .count(),
+            101
+        );
+        assert_eq!(
+            hdt.quads_for_pattern(None, None, Some(&node(50)?.into()), None)
+                .count(),
+            1
+        );
+        assert_eq!(
+            hdt.quads_for_pattern(None, None, None, Some(&node(1004)?.into()))
+                .count(),
+            0
+        );
+
+        let prepared_query = hdt.prepare_query(
+            "SELECT (COUNT(?o) AS ?c) WHERE { ?s <http://www.example.org/1002> ?o FILTER(isLiteral(?o) && STRLEN(STR(?o)) > 10) }",
+            QueryOptions::default(),
+        )?;
+        let result = prepared_query.exec()?;
+        if let QueryResult::Solutions(mut solutions) = result {
+            assert_eq!(
+                solutions.next().unwrap()?.get("c"),
+                Some(&Literal::from(1).into())
+            );
+        } else {
+            panic!("A SELECT query should return solutions")
+        }
+    }
+    remove_file(&file)?;
+    Ok(())
+}
+
+#[cfg(feature = "memmap")]
+#[test]
+fn hdt_store_mmap_test() -> Result<()> {
+    let store = MemoryStore::new();
+    let quad = Quad::new(node(0)?, node(1)?, node(2)?, None);
+    store.insert(quad.clone());
+
+    let mut file = temp_dir();
+    file.push(random::<u128>().to_string());
+    HdtStore::write(&file, &store)?;
+    {
+        // The file is not modified while it is mapped
+        let hdt = unsafe { HdtStore::open_mmap(&file)? };
+        assert_eq!(
+            hdt.quads_for_pattern(None, None, None, None)
+                .collect::<Result<Vec<_>>>()?,
+            vec![quad]
+        );
+    }
+    remove_file(&file)?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use oxigraph::model::*;
+use oxigraph::store::hybrid::HybridStoreOptions;
+use oxigraph::*;
+use rand::random;
+use std::env::temp_dir;
+use std::fs::remove_dir_all;
+
+fn node(id: usize) -> Result<NamedNode> {
+    Ok(NamedNode::new(format!("http://www.example.org/{}", id))?)
+}
+
+#[test]
+fn spilled_shards_test() -> Result<()> {
+    let mut directory = temp_dir();
+    directory.push(random::<u128>().to_string());
+    {
+        let store = HybridStore::new_with_options(
+            &directory,
+            HybridStoreOptions::default().with_max_hot_shards(1),
+        )?;
+        let graph = node(0)?;
+        for i in 0..200 {
+            let subject = node(i)?;
+            store.insert(&Quad::new(
+                subject.clone(),
+                subject,
+                Literal::from(i as i64),
+                graph.clone(),
+            ))?;
+        }
+
+        assert_eq!(store.len(), 200);
+        assert_eq!(store.len_graph(&graph.clone().into()), 200);
+        assert!(store.contains(&Quad::new(
+            node(1)?,
+            node(1)?,
+            Literal::from(1),
+            graph.clone()
+        ))?);
+        assert_eq!(
+            store
+                .quads_for_pattern(None, None, Some(&Literal::from(2).into()), None)
+                .collect::<Result<Vec<_>>>()?
+                .len(),
+            1
+        );
+
+        // The removals and graph operations load back the spilled shards
+        store.remove(&Quad::new(
+            node(1)?,
+            node(1)?,
+            Literal::from(1),
+            graph.clone(),
+        ))?;
+        assert_eq!(store.len(), 199);
+        store.clear_graph(&graph.clone().into())?;
+        assert!(store.is_empty());
+        assert!(store.contains_named_graph(&graph.into()));
+    }
+    remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn spilled_shards_lookup_test() -> Result<()> {
+    let mut directory = temp_dir();
+    directory.push(random::<u128>().to_string());
+    {
+        let store = HybridStore::new_with_options(
+            &directory,
+            HybridStoreOptions::default().with_max_hot_shards(1),
+        )?;
+        // Each subject has enough quads to fill several blocks of its spilled shard
+        for s in 0..8 {
+            for i in 0..3000 {
+                store.insert(&Quad::new(
+                    node(s)?,
+                    node(100 + i % 3)?,
+                    Literal::from(i as i64),
+                    None,
+                ))?;
+            }
+        }
+
+        assert_eq!(store.len(), 24000);
+        let count =
+            |s: Option<&Subject>, p: Option<&NamedNode>, o: Option<&Term>| -> Result<usize> {
+                Ok(store
+                    .quads_for_pattern(s, p, o, None)
+                    .collect::<Result<Vec<_>>>()?
+                    .len())
+            };
+        assert_eq!(count(None, None, None)?, 24000);
+        assert_eq!(count(Some(&node(3)?.into()), None, None)?, 3000);
+        assert_eq!(
+            count(Some(&node(3)?.into()), Some(&node(101)?), None)?,
+            1000
+        );
+        assert_eq!(count(None, Some(&node(102)?), None)?, 8000);
+        assert_eq!(
+            count(None, Some(&node(102)?), Some(&Literal::from(2).into()))?,
+            8
+        );
+        assert_eq!(count(None, None, Some(&Literal::from(2999).into()))?, 8);
+        assert_eq!(count(Some(&node(8)?.into()), None, None)?, 0);
+        assert!(store.contains(&Quad::new(node(5)?, node(100)?, Literal::from(2997), None))?);
+        assert!(!store.contains(&Quad::new(node(5)?, node(101)?, Literal::from(2997), None))?);
+    }
+    remove_dir_all(&directory)?;
+    Ok(())
+}
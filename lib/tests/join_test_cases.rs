@@ -0,0 +1,112 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+const DATA: &str = r#"
+@prefix : <http://www.example.org/> .
+:s1 :p :o1 ; :r 1, 2 .
+:s2 :p :o1 ; :r 3, 4 .
+:s3 :p :o3 ; :r 5, 6 .
+:o1 :q "a", "b", "c" .
+"#;
+
+#[test]
+fn hash_join_is_used_for_unselective_patterns_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    let query = store.prepare_query(
+        "PREFIX : <http://www.example.org/> SELECT ?s ?y ?x WHERE { ?s :p ?o . ?s :r ?y . ?o :q ?x }",
+        QueryOptions::default(),
+    )?;
+    assert!(contains_operation(&query.explain()?, "HashJoin"));
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        assert_eq!(solutions.collect::<Result<Vec<_>>>()?.len(), 12);
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn nested_loop_join_is_used_for_selective_patterns_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    let query = store.prepare_query(
+        "PREFIX : <http://www.example.org/> SELECT ?y WHERE { :s1 :p ?o . ?o :q ?x . :s1 :r ?y }",
+        QueryOptions::default(),
+    )?;
+    assert!(!contains_operation(&query.explain()?, "HashJoin"));
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        assert_eq!(solutions.collect::<Result<Vec<_>>>()?.len(), 6);
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn statistics_respect_dataset_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::from(NamedNode::new("http://www.example.org/g1")?),
+        None,
+    )?;
+    store.insert(Quad::new(
+        NamedNode::new("http://www.example.org/s4")?,
+        NamedNode::new("http://www.example.org/r")?,
+        Literal::from(7),
+        NamedNode::new("http://www.example.org/g2")?,
+    ));
+    let query = store.prepare_query(
+        "PREFIX : <http://www.example.org/> SELECT ?s ?y FROM :g2 WHERE { ?s :r ?y }",
+        QueryOptions::default(),
+    )?;
+    assert_eq!(
+        estimated_cardinalities(&query.explain()?),
+        vec!["1".to_string()]
+    );
+    let query = store.prepare_query(
+        "PREFIX : <http://www.example.org/> SELECT ?s ?y WHERE { ?s :r ?y }",
+        QueryOptions::default().with_default_graph_as_union(),
+    )?;
+    assert_eq!(
+        estimated_cardinalities(&query.explain()?),
+        vec!["7".to_string()]
+    );
+    Ok(())
+}
+
+fn estimated_cardinalities(explanation: &QueryExplanation) -> Vec<String> {
+    let mut cardinalities: Vec<_> = explanation
+        .attributes()
+        .iter()
+        .filter(|(name, _)| *name == "estimated cardinality")
+        .map(|(_, value)| value.clone())
+        .collect();
+    for child in explanation.children() {
+        cardinalities.extend(estimated_cardinalities(child));
+    }
+    cardinalities
+}
+
+fn contains_operation(explanation: &QueryExplanation, name: &str) -> bool {
+    explanation.name() == name
+        || explanation
+            .children()
+            .iter()
+            .any(|child| contains_operation(child, name))
+}
@@ -0,0 +1,689 @@
+use oxigraph::model::*;
+use oxigraph::*;
+use std::thread::spawn;
+
+fn node(id: usize) -> Result<NamedNode> {
+    Ok(NamedNode::new(format!("http://www.example.org/{}", id))?)
+}
+
+#[test]
+fn concurrent_writes_test() -> Result<()> {
+    let store = MemoryStore::new();
+    let graph = node(0)?;
+    let threads = (0..8)
+        .map(|thread| {
+            let store = store.clone();
+            let graph = graph.clone();
+            spawn(move || -> Result<()> {
+                for i in 0..100 {
+                    let subject = node(thread * 100 + i)?;
+                    store.insert(Quad::new(
+                        subject.clone(),
+                        subject.clone(),
+                        Literal::from(i as i64),
+                        None,
+                    ));
+                    store.insert(Quad::new(
+                        subject.clone(),
+                        subject,
+                        Literal::from(i as i64),
+                        graph.clone(),
+                    ));
+                }
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+
+    assert_eq!(store.len(), 1600);
+    assert_eq!(store.len_graph(&GraphName::DefaultGraph), 800);
+    assert_eq!(store.len_graph(&graph.clone().into()), 800);
+    assert_eq!(
+        store
+            .quads_for_pattern(None, None, Some(&Literal::from(1).into()), None)
+            .count(),
+        16
+    );
+    assert_eq!(
+        store.named_graphs().collect::<Vec<_>>(),
+        vec![graph.clone().into()]
+    );
+
+    // Graph operations see the quads of all the shards
+    store.clear_graph(&graph.clone().into());
+    assert_eq!(store.len(), 800);
+    assert!(store.contains_named_graph(&graph.into()));
+    Ok(())
+}
+
+#[test]
+fn atomic_insert_all_test() -> Result<()> {
+    let store = MemoryStore::new();
+    let writer = {
+        let store = store.clone();
+        spawn(move || -> Result<()> {
+            for batch in 0..100 {
+                // The subjects are spread over all the shards
+                store.insert_all(
+                    (0..32)
+                        .map(|i| {
+                            let subject = node(batch * 32 + i)?;
+                            Ok(Quad::new(subject.clone(), subject, Literal::from(1), None))
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                );
+            }
+            Ok(())
+        })
+    };
+    while store.len() < 3200 {
+        // The readers never see a part of a batch
+        assert_eq!(store.len() % 32, 0);
+        assert_eq!(
+            store.quads_for_pattern(None, None, None, None).count() % 32,
+            0
+        );
+        assert_eq!(store.stats().quads_count % 32, 0);
+    }
+    writer.join().unwrap()
+}
+
+#[test]
+fn parallel_nquads_load_test() -> Result<()> {
+    // Big enough to be split into multiple chunks
+    let mut file = String::new();
+    file.push_str("_:b <http://www.example.org/p> \"first\" .\n");
+    for i in 0..50_000 {
+        file.push_str(&format!(
+            "<http://www.example.org/{}> <http://www.example.org/p> \"{}\" <http://www.example.org/g> .\n",
+            i, i
+        ));
+    }
+    file.push_str("_:b <http://www.example.org/p> \"last\" .\n");
+
+    let store = MemoryStore::new();
+    store.load_dataset(file.as_bytes(), DatasetSyntax::NQuads, None)?;
+    assert_eq!(store.len(), 50_002);
+    assert_eq!(
+        store.len_graph(&NamedNode::new("http://www.example.org/g")?.into()),
+        50_000
+    );
+
+    // The blank node label is mapped to the same blank node in all the chunks
+    let subjects = store
+        .quads_for_pattern(None, None, None, Some(&GraphName::DefaultGraph))
+        .map(|q| q.subject)
+        .collect::<Vec<_>>();
+    assert_eq!(subjects.len(), 2);
+    assert_eq!(subjects[0], subjects[1]);
+
+    // The syntax errors give the line in the file and not in the chunk
+    file.push_str("<http://www.example.org/s> \"p\" \"o\" .");
+    let error = MemoryStore::new()
+        .load_dataset(file.as_bytes(), DatasetSyntax::NQuads, None)
+        .unwrap_err();
+    assert!(error.to_string().contains("line 50003"));
+    Ok(())
+}
+
+#[test]
+fn fork_test() -> Result<()> {
+    let store = MemoryStore::new();
+    for i in 0..100 {
+        store.insert(Quad::new(node(i)?, node(i)?, node(i)?, None));
+    }
+    let graph = NamedOrBlankNode::from(node(1000)?);
+    store.insert_named_graph(&graph);
+
+    let fork = store.fork();
+    assert!(fork == store);
+
+    // The writes to the fork are not visible from the store
+    fork.remove(&Quad::new(node(0)?, node(0)?, node(0)?, None));
+    fork.insert(Quad::new(node(100)?, node(100)?, node(100)?, None));
+    fork.remove_named_graph(&graph);
+    assert_eq!(fork.len(), 100);
+    assert!(!fork.contains(&Quad::new(node(0)?, node(0)?, node(0)?, None)));
+    assert!(!fork.contains_named_graph(&graph));
+    assert_eq!(store.len(), 100);
+    assert!(store.contains(&Quad::new(node(0)?, node(0)?, node(0)?, None)));
+    assert!(store.contains_named_graph(&graph));
+
+    // The writes to the store are not visible from the fork
+    store.clear_graph(&GraphName::DefaultGraph);
+    assert!(store.is_empty());
+    assert_eq!(fork.len(), 100);
+    assert_eq!(
+        fork.quads_for_pattern(Some(&node(100)?.into()), None, None, None)
+            .count(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn quoted_triple_test() -> Result<()> {
+    let store = MemoryStore::new();
+    let quoted = Triple::new(node(0)?, node(1)?, Literal::from(1));
+    let quad = Quad::new(quoted, node(2)?, node(3)?, None);
+    store.insert(quad.clone());
+    assert!(store.contains(&quad));
+    assert_eq!(
+        store
+            .quads_for_pattern(None, None, None, None)
+            .collect::<Vec<_>>(),
+        vec![quad]
+    );
+
+    let mut buffer = Vec::new();
+    store.dump_graph(&mut buffer, GraphSyntax::NTriples, &GraphName::DefaultGraph)?;
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "<< <http://www.example.org/0> <http://www.example.org/1> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> >> <http://www.example.org/2> <http://www.example.org/3> .\n"
+    );
+    assert!(store
+        .dump_graph(Vec::new(), GraphSyntax::Turtle, &GraphName::DefaultGraph)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn quoted_triple_load_test() -> Result<()> {
+    let quoted = Triple::new(node(0)?, node(1)?, Literal::from(1));
+    let asserted = Quad::new(node(0)?, node(1)?, Literal::from(1), None);
+    let annotation = Quad::new(quoted.clone(), node(2)?, node(3)?, None);
+    let nested = Quad::new(
+        node(4)?,
+        node(5)?,
+        Triple::new(quoted.clone(), node(2)?, Literal::new_simple_literal("a")),
+        None,
+    );
+
+    // N-Triples-star
+    let store = MemoryStore::new();
+    let data = "<< <http://www.example.org/0> <http://www.example.org/1> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> >> <http://www.example.org/2> <http://www.example.org/3> .\n\
+        <http://www.example.org/4> <http://www.example.org/5> << << <http://www.example.org/0> <http://www.example.org/1> \"1\"^^<http://www.w3.org/2001/XMLSchema#integer> >> <http://www.example.org/2> \"a\" >> .\n";
+    store.load_graph(
+        data.as_bytes(),
+        GraphSyntax::NTriples,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    assert_eq!(store.len(), 2);
+    assert!(store.contains(&annotation));
+    assert!(store.contains(&nested));
+
+    // The N-Triples serialization could be loaded back
+    let mut buffer = Vec::new();
+    store.dump_graph(&mut buffer, GraphSyntax::NTriples, &GraphName::DefaultGraph)?;
+    let copy = MemoryStore::new();
+    copy.load_graph(
+        buffer.as_slice(),
+        GraphSyntax::NTriples,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    assert!(store.is_isomorphic(&copy));
+
+    // Turtle-star with an annotation
+    let store = MemoryStore::new();
+    let data = "@prefix ex: <http://www.example.org/> .\n\
+        ex:0 ex:1 1 {| ex:2 ex:3 |} .\n\
+        ex:4 ex:5 << << ex:0 ex:1 1 >> ex:2 \"a\" >> .\n";
+    store.load_graph(
+        data.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    assert_eq!(store.len(), 3);
+    assert!(store.contains(&asserted));
+    assert!(store.contains(&annotation));
+    assert!(store.contains(&nested));
+
+    // The Turtle abbreviations are not allowed in N-Triples-star
+    assert!(MemoryStore::new()
+        .load_graph(
+            "@prefix ex: <http://www.example.org/> .\n<< ex:0 ex:1 ex:2 >> ex:3 ex:4 .\n"
+                .as_bytes(),
+            GraphSyntax::NTriples,
+            &GraphName::DefaultGraph,
+            None,
+        )
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn skolemized_load_test() -> Result<()> {
+    use oxigraph::model::skolemization::Skolemizer;
+    use oxigraph::store::Store;
+
+    let store = MemoryStore::new();
+    let skolemizer = Skolemizer::new("http://www.example.org")?;
+    let data = "_:a <http://www.example.org/1> _:b .\n_:b <http://www.example.org/1> _:a .\n";
+    store.load_skolemized_graph(
+        &mut data.as_bytes(),
+        GraphSyntax::NTriples,
+        &GraphName::DefaultGraph,
+        None,
+        &skolemizer,
+    )?;
+    let quads = store
+        .quads_for_pattern(None, None, None, None)
+        .collect::<Vec<_>>();
+    assert_eq!(quads.len(), 2);
+    for quad in &quads {
+        assert!(quad
+            .subject
+            .to_string()
+            .starts_with(&format!("<{}", skolemizer.prefix())));
+        assert!(skolemizer
+            .deskolemize_quad(quad)
+            .subject
+            .to_string()
+            .starts_with("_:"));
+    }
+    Ok(())
+}
+
+#[test]
+fn big_numeric_evaluation_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    let query = store.prepare_query(
+        "SELECT (9223372036854775807 + 1 AS ?i) (0.1234567890123456789 * 10 AS ?d) WHERE {}",
+        QueryOptions::default(),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(mut solutions) = result {
+        let solution = solutions.next().unwrap()?;
+        assert_eq!(
+            solution.get("i"),
+            Some(
+                &Literal::new_typed_literal("9223372036854775808", vocab::xsd::INTEGER.clone())
+                    .into()
+            )
+        );
+        assert_eq!(
+            solution.get("d"),
+            Some(
+                &Literal::new_typed_literal("1.234567890123456789", vocab::xsd::DECIMAL.clone())
+                    .into()
+            )
+        );
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn duration_arithmetic_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    let query = store.prepare_query(
+        "PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+        SELECT
+            (2.3 * \"P2Y11M\"^^xsd:yearMonthDuration AS ?m)
+            (\"P1DT2H30M10.5S\"^^xsd:dayTimeDuration / 1.5 AS ?d)
+            (\"P1D\"^^xsd:dayTimeDuration / \"PT16H\"^^xsd:dayTimeDuration AS ?r)
+        WHERE {}",
+        QueryOptions::default(),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(mut solutions) = result {
+        let solution = solutions.next().unwrap()?;
+        assert_eq!(
+            solution.get("m"),
+            Some(
+                &Literal::new_typed_literal("P6Y9M", vocab::xsd::YEAR_MONTH_DURATION.clone())
+                    .into()
+            )
+        );
+        assert_eq!(
+            solution.get("d"),
+            Some(
+                &Literal::new_typed_literal("PT17H40M7S", vocab::xsd::DAY_TIME_DURATION.clone())
+                    .into()
+            )
+        );
+        assert_eq!(
+            solution.get("r"),
+            Some(&Literal::new_typed_literal("1.5", vocab::xsd::DECIMAL.clone()).into())
+        );
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn full_text_search_graph_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    store.insert(Quad::new(
+        node(0)?,
+        node(1)?,
+        Literal::from("Foo bar"),
+        None,
+    ));
+    store.insert(Quad::new(
+        node(0)?,
+        node(1)?,
+        Literal::from("bar baz"),
+        node(2)?,
+    ));
+    store.insert(Quad::new(
+        node(0)?,
+        node(1)?,
+        Literal::from("foo baz"),
+        node(3)?,
+    ));
+    let search = |query: &str| -> Result<Vec<(Option<Term>, Option<Term>)>> {
+        let query = store.prepare_query(
+            &format!(
+                "PREFIX ox: <http://oxigraph.org/ns#> PREFIX : <http://www.example.org/> {}",
+                query
+            ),
+            QueryOptions::default(),
+        )?;
+        let result = query.exec()?;
+        if let QueryResult::Solutions(solutions) = result {
+            let mut results = solutions
+                .map(|solution| {
+                    let solution = solution?;
+                    Ok((solution.get("l").cloned(), solution.get("g").cloned()))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            results.sort_by_key(|(l, g)| (format!("{:?}", l), format!("{:?}", g)));
+            Ok(results)
+        } else {
+            Err(Error::msg("Expected solutions"))
+        }
+    };
+
+    assert_eq!(
+        search("SELECT ?l WHERE { ?l ox:matches \"foo\" }")?,
+        vec![(Some(Literal::from("Foo bar").into()), None)]
+    );
+    assert_eq!(
+        search("SELECT ?l ?g WHERE { GRAPH ?g { ?l ox:matches \"baz\" } }")?,
+        vec![
+            (Some(Literal::from("bar baz").into()), Some(node(2)?.into())),
+            (Some(Literal::from("foo baz").into()), Some(node(3)?.into()))
+        ]
+    );
+    assert_eq!(
+        search("SELECT ?l WHERE { GRAPH :3 { ?l ox:matches \"foo\" } }")?,
+        vec![(Some(Literal::from("foo baz").into()), None)]
+    );
+    assert_eq!(
+        search("SELECT ?l FROM :2 WHERE { ?l ox:matches \"bar\" }")?,
+        vec![(Some(Literal::from("bar baz").into()), None)]
+    );
+    assert_eq!(
+        search("SELECT ?l ?g FROM NAMED :3 WHERE { GRAPH ?g { ?l ox:matches \"baz\" } }")?,
+        vec![(Some(Literal::from("foo baz").into()), Some(node(3)?.into()))]
+    );
+    Ok(())
+}
+
+#[test]
+fn unicode_collation_test() -> Result<()> {
+    use oxigraph::sparql::{Collation, QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    for name in &["côte", "coter", "Cote"] {
+        store.insert(Quad::new(node(0)?, node(1)?, Literal::from(*name), None));
+    }
+    let query = store.prepare_query(
+        "SELECT ?o WHERE { ?s ?p ?o } ORDER BY ?o",
+        QueryOptions::default().with_collation(Collation::unicode("und")),
+    );
+    if !cfg!(feature = "collation") {
+        assert!(query.is_err());
+        return Ok(());
+    }
+    let query = query?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        let names = solutions
+            .map(|solution| Ok(solution?.get("o").cloned()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            names,
+            vec![
+                Some(Literal::from("Cote").into()),
+                Some(Literal::from("côte").into()),
+                Some(Literal::from("coter").into())
+            ]
+        );
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn query_stats_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    store.insert(Quad::new(node(0)?, node(1)?, node(2)?, None));
+    for (options, lookups) in vec![
+        (QueryOptions::default(), 0),
+        (QueryOptions::default().with_profiling(), 0),
+        (QueryOptions::default().with_stats(), 1),
+    ] {
+        let query = store.prepare_query("SELECT ?s WHERE { ?s ?p ?o }", options)?;
+        let result = query.exec()?;
+        if let QueryResult::Solutions(solutions) = result {
+            assert_eq!(solutions.count(), 1);
+        }
+        assert_eq!(query.stats().quad_pattern_lookups(), lookups);
+    }
+    Ok(())
+}
+
+#[test]
+fn union_default_graph_in_transaction_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    store.insert(Quad::new(node(0)?, node(1)?, node(2)?, node(10)?));
+    store.insert(Quad::new(node(3)?, node(1)?, node(4)?, node(10)?));
+    store.transaction(|transaction| {
+        transaction.insert(Quad::new(node(0)?, node(1)?, node(2)?, node(11)?));
+        transaction.insert(Quad::new(node(0)?, node(1)?, node(2)?, None));
+        transaction.insert(Quad::new(node(5)?, node(1)?, node(6)?, node(11)?));
+        transaction.insert(Quad::new(node(5)?, node(1)?, node(6)?, node(12)?));
+        let query = transaction.prepare_query(
+            "SELECT ?s ?o WHERE { ?s ?p ?o }",
+            QueryOptions::default().with_default_graph_as_union(),
+        )?;
+        if let QueryResult::Solutions(solutions) = query.exec()? {
+            assert_eq!(solutions.collect::<Result<Vec<_>>>()?.len(), 3);
+        } else {
+            return Err(Error::msg("Expected solutions"));
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn sort_memory_limit_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    for i in 0..1000 {
+        store.insert(Quad::new(
+            node(0)?,
+            node(1)?,
+            Literal::from((i * 7919) % 1000),
+            None,
+        ));
+    }
+    // 500 runs are spilled, more than can be merged at once
+    let query = store.prepare_query(
+        "SELECT ?o WHERE { ?s ?p ?o } ORDER BY ?o",
+        QueryOptions::default().with_sort_memory_limit(2),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        let values = solutions
+            .map(|solution| Ok(solution?.get("o").cloned()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            values,
+            (0..1000)
+                .map(|i| Some(Literal::from(i).into()))
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn exec_with_unknown_binding_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult, QuerySolution, Variable};
+
+    let store = MemoryStore::new();
+    store.insert(Quad::new(node(0)?, node(1)?, node(2)?, None));
+    let query = store.prepare_query("SELECT ?o WHERE { ?s ?p ?o }", QueryOptions::default())?;
+    let bindings: QuerySolution = vec![(Variable::new("s"), node(0)?.into())]
+        .into_iter()
+        .collect();
+    let result = query.exec_with_bindings(&bindings)?;
+    if let QueryResult::Solutions(solutions) = result {
+        assert_eq!(solutions.count(), 1);
+    }
+    let bindings: QuerySolution = vec![(Variable::new("x"), node(0)?.into())]
+        .into_iter()
+        .collect();
+    assert!(query.exec_with_bindings(&bindings).is_err());
+    Ok(())
+}
+
+#[test]
+fn query_variables_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult, QueryVariables};
+
+    let store = MemoryStore::new();
+    store.insert(Quad::new(node(0)?, node(1)?, node(2)?, None));
+    // The variables of the WHERE clause can be bound even if they are not projected
+    let query = store.prepare_query(
+        "SELECT ?o WHERE { ?s ?p ?o }",
+        QueryOptions::default().with_variables(QueryVariables::new().bind("s", node(0)?)),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        assert_eq!(solutions.count(), 1);
+    }
+    assert!(store
+        .prepare_query(
+            "SELECT ?o WHERE { ?s ?p ?o }",
+            QueryOptions::default().with_variables(QueryVariables::new().bind("x", node(0)?)),
+        )
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn query_profiling_reset_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    store.insert(Quad::new(node(0)?, node(1)?, node(2)?, None));
+    let query = store.prepare_query(
+        "SELECT ?s WHERE { ?s ?p ?o }",
+        QueryOptions::default().with_profiling(),
+    )?;
+    for _ in 0..2 {
+        let result = query.exec()?;
+        if let QueryResult::Solutions(solutions) = result {
+            assert_eq!(solutions.count(), 1);
+        }
+        let explanation = query.explain()?;
+        assert_eq!(explanation.rows(), Some(1));
+    }
+    Ok(())
+}
+
+#[test]
+fn spatial_filter_test() -> Result<()> {
+    use oxigraph::sparql::{QueryOptions, QueryResult};
+
+    let store = MemoryStore::new();
+    for (city, point) in &[
+        ("paris", "POINT(2.3522 48.8566)"),
+        ("lyon", "POINT(4.8357 45.764)"),
+        ("london", "POINT(-0.1276 51.5072)"),
+        ("berlin", "POINT(13.405 52.52)"),
+    ] {
+        store.insert(Quad::new(
+            NamedNode::new(format!("http://example.com/{}", city))?,
+            NamedNode::new("http://example.com/location")?,
+            Literal::new_typed_literal(
+                *point,
+                NamedNode::new("http://www.opengis.net/ont/geosparql#wktLiteral")?,
+            ),
+            None,
+        ));
+    }
+    for (filter, expected) in &[
+        (
+            "geof:sfWithin(?l, \"POLYGON((-5 42, 8 42, 8 51, -5 51, -5 42))\"^^geo:wktLiteral)",
+            vec!["http://example.com/lyon", "http://example.com/paris"],
+        ),
+        (
+            "geof:distance(?l, \"POINT(2.3522 48.8566)\"^^geo:wktLiteral, uom:metre) < 360000",
+            vec!["http://example.com/london", "http://example.com/paris"],
+        ),
+        (
+            "1000 >= geof:distance(\"POINT(13.4 52.52)\"^^geo:wktLiteral, ?l, uom:metre)",
+            vec!["http://example.com/berlin"],
+        ),
+    ] {
+        let query = store.prepare_query(
+            &format!(
+                "PREFIX geo: <http://www.opengis.net/ont/geosparql#>
+                PREFIX geof: <http://www.opengis.net/def/function/geosparql/>
+                PREFIX uom: <http://www.opengis.net/def/uom/OGC/1.0/>
+                SELECT ?s WHERE {{ ?s <http://example.com/location> ?l FILTER({}) }} ORDER BY ?s",
+                filter
+            ),
+            QueryOptions::default(),
+        )?;
+        #[cfg(feature = "spatial-index")]
+        assert!(contains_operation(&query.explain()?, "SpatialSearch"));
+        let result = query.exec()?;
+        if let QueryResult::Solutions(solutions) = result {
+            let cities = solutions
+                .map(|solution| Ok(solution?.get("s").unwrap().to_string()))
+                .collect::<Result<Vec<_>>>()?;
+            let expected: Vec<_> = expected.iter().map(|c| format!("<{}>", c)).collect();
+            assert_eq!(cities, expected);
+        } else {
+            return Err(Error::msg("Expected solutions"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "spatial-index")]
+fn contains_operation(explanation: &oxigraph::sparql::QueryExplanation, name: &str) -> bool {
+    explanation.name() == name
+        || explanation
+            .children()
+            .iter()
+            .any(|child| contains_operation(child, name))
+}
@@ -0,0 +1,174 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+const DATA: &str = r#"
+<http://www.example.org/s> <http://www.example.org/p> "default" .
+<http://www.example.org/s> <http://www.example.org/p> "1" <http://www.example.org/g1> .
+<http://www.example.org/s> <http://www.example.org/p> "2" <http://www.example.org/g2> .
+"#;
+
+fn graph(name: &str) -> Result<NamedOrBlankNode> {
+    Ok(NamedNode::new(format!("http://www.example.org/{}", name))?.into())
+}
+
+fn sorted_named_graphs(store: &MemoryStore) -> Vec<String> {
+    let mut graphs = store
+        .named_graphs()
+        .map(|graph| graph.to_string())
+        .collect::<Vec<_>>();
+    graphs.sort();
+    graphs
+}
+
+#[test]
+fn named_graph_management_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_dataset(DATA.as_bytes(), DatasetSyntax::NQuads, None)?;
+    assert_eq!(
+        sorted_named_graphs(&store),
+        vec!["<http://www.example.org/g1>", "<http://www.example.org/g2>"]
+    );
+
+    // Empty graphs exist
+    store.insert_named_graph(&graph("g3")?);
+    assert!(store.contains_named_graph(&graph("g3")?));
+    assert_eq!(store.len(), 3);
+
+    // Clearing keeps the graph
+    store.clear_graph(&graph("g1")?.into());
+    assert!(store.contains_named_graph(&graph("g1")?));
+    assert_eq!(store.len(), 2);
+
+    // Removing drops the graph and its quads
+    store.remove_named_graph(&graph("g2")?);
+    assert!(!store.contains_named_graph(&graph("g2")?));
+    assert_eq!(store.len(), 1);
+    assert_eq!(
+        sorted_named_graphs(&store),
+        vec!["<http://www.example.org/g1>", "<http://www.example.org/g3>"]
+    );
+    Ok(())
+}
+
+#[test]
+fn sparql_graph_management_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_dataset(DATA.as_bytes(), DatasetSyntax::NQuads, None)?;
+
+    store
+        .prepare_update(
+            "CREATE GRAPH <http://www.example.org/g3>",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert!(store.contains_named_graph(&graph("g3")?));
+    assert!(store
+        .prepare_update(
+            "CREATE GRAPH <http://www.example.org/g3>",
+            QueryOptions::default(),
+        )?
+        .exec()
+        .is_err());
+    store
+        .prepare_update(
+            "CREATE SILENT GRAPH <http://www.example.org/g3>",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+
+    store
+        .prepare_update("CLEAR NAMED", QueryOptions::default())?
+        .exec()?;
+    assert_eq!(store.len(), 1);
+    assert_eq!(sorted_named_graphs(&store).len(), 3);
+
+    store
+        .prepare_update(
+            "DROP GRAPH <http://www.example.org/g1>",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert!(!store.contains_named_graph(&graph("g1")?));
+
+    store
+        .prepare_update("DROP ALL", QueryOptions::default())?
+        .exec()?;
+    assert!(store.is_empty());
+    assert_eq!(store.named_graphs().count(), 0);
+    Ok(())
+}
+
+#[test]
+fn graph_transfer_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_dataset(DATA.as_bytes(), DatasetSyntax::NQuads, None)?;
+
+    // ADD keeps the content of both graphs
+    store.add_graph(&graph("g1")?.into(), &graph("g2")?.into());
+    assert_eq!(store.len_graph(&graph("g2")?.into()), 2);
+    assert_eq!(store.len_graph(&graph("g1")?.into()), 1);
+
+    // COPY replaces the content of the target graph
+    store.copy_graph(&GraphName::DefaultGraph, &graph("g2")?.into());
+    assert_eq!(store.len_graph(&graph("g2")?.into()), 1);
+    assert_eq!(store.len(), 3);
+
+    // MOVE removes the source graph
+    store.move_graph(&graph("g1")?.into(), &graph("g3")?.into());
+    assert!(!store.contains_named_graph(&graph("g1")?));
+    assert_eq!(store.len_graph(&graph("g3")?.into()), 1);
+    assert_eq!(
+        sorted_named_graphs(&store),
+        vec!["<http://www.example.org/g2>", "<http://www.example.org/g3>"]
+    );
+    Ok(())
+}
+
+#[test]
+fn sparql_graph_transfer_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_dataset(DATA.as_bytes(), DatasetSyntax::NQuads, None)?;
+
+    store
+        .prepare_update(
+            "ADD <http://www.example.org/g1> TO DEFAULT",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert_eq!(store.len_graph(&GraphName::DefaultGraph), 2);
+
+    store
+        .prepare_update(
+            "COPY DEFAULT TO GRAPH <http://www.example.org/g2>",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert_eq!(store.len_graph(&graph("g2")?.into()), 2);
+
+    store
+        .prepare_update(
+            "MOVE DEFAULT TO <http://www.example.org/g3>",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert_eq!(store.len_graph(&GraphName::DefaultGraph), 0);
+    assert_eq!(store.len_graph(&graph("g3")?.into()), 2);
+
+    // The source graph should exist unless SILENT is used
+    assert!(store
+        .prepare_update(
+            "ADD <http://www.example.org/g4> TO DEFAULT",
+            QueryOptions::default(),
+        )?
+        .exec()
+        .is_err());
+    store
+        .prepare_update(
+            "ADD SILENT <http://www.example.org/g4> TO DEFAULT",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert!(!store.contains_named_graph(&graph("g4")?));
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+const DATA: &str = r#"
+@prefix : <http://www.example.org/> .
+:s :p ( "a" "b" "c" ) ; :q "foo" .
+"#;
+
+#[test]
+fn list_member_test() -> Result<()> {
+    let solutions = query_store(
+        "PREFIX : <http://www.example.org/> PREFIX list: <http://jena.apache.org/ARQ/list#> SELECT ?m WHERE { ?m list:member ?l . :s :p ?l }",
+    );
+    // The list should be the subject of list:member
+    assert!(solutions.is_err());
+    let solutions = query_store(
+        "PREFIX : <http://www.example.org/> PREFIX list: <http://jena.apache.org/ARQ/list#> SELECT ?m WHERE { ?l list:member ?m . :s :p ?l }",
+    )?;
+    let mut members = solutions
+        .iter()
+        .map(|solution| solution.get("m").map(|m| m.to_string()))
+        .collect::<Vec<_>>();
+    members.sort();
+    assert_eq!(
+        members,
+        vec![
+            Some("\"a\"".to_owned()),
+            Some("\"b\"".to_owned()),
+            Some("\"c\"".to_owned())
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn list_member_in_named_graph_test() -> Result<()> {
+    let store = store()?;
+    let graph = NamedNode::new("http://www.example.org/g")?;
+    store.load_graph(
+        r#"@prefix : <http://www.example.org/> . :s :p ( "d" "e" ) ."#.as_bytes(),
+        GraphSyntax::Turtle,
+        &graph.into(),
+        None,
+    )?;
+    for (query, count) in &[
+        ("SELECT ?m WHERE { :s :p ?l . ?l list:member ?m }", 3),
+        (
+            "SELECT ?m WHERE { GRAPH :g { :s :p ?l . ?l list:member ?m } }",
+            2,
+        ),
+        (
+            "SELECT ?m ?g WHERE { GRAPH ?g { :s :p ?l . ?l list:member ?m } }",
+            2,
+        ),
+        // The list of the named graph is not in the default graph
+        (
+            "SELECT ?m WHERE { GRAPH :g { :s :p ?l } ?l list:member ?m }",
+            0,
+        ),
+    ] {
+        let query = format!(
+            "PREFIX : <http://www.example.org/> PREFIX list: <http://jena.apache.org/ARQ/list#> {}",
+            query
+        );
+        let query = store.prepare_query(&query, options()?)?;
+        let result = query.exec()?;
+        if let QueryResult::Solutions(solutions) = result {
+            assert_eq!(solutions.collect::<Result<Vec<_>>>()?.len(), *count);
+        } else {
+            panic!("A SELECT query should return solutions")
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn closure_property_function_test() -> Result<()> {
+    let solutions = query_store(
+        "PREFIX : <http://www.example.org/> SELECT ?length WHERE { :s :q ?o . ?o :length ?length }",
+    )?;
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0].get("length"), Some(&Literal::from(3).into()));
+    Ok(())
+}
+
+#[test]
+fn property_function_with_bound_object_test() -> Result<()> {
+    let solutions = query_store(
+        "PREFIX : <http://www.example.org/> SELECT ?o WHERE { :s :q ?o . ?o :length 4 }",
+    )?;
+    assert!(solutions.is_empty());
+    Ok(())
+}
+
+#[test]
+fn property_function_in_explanation_test() -> Result<()> {
+    let query = store()?.prepare_query(
+        "PREFIX : <http://www.example.org/> SELECT ?length WHERE { :s :q ?o . ?o :length ?length }",
+        options()?,
+    )?;
+    assert!(contains_operation(&query.explain()?, "PropertyFunction"));
+    Ok(())
+}
+
+fn store() -> Result<MemoryStore> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    Ok(store)
+}
+
+fn options() -> Result<QueryOptions<'static>> {
+    Ok(QueryOptions::default()
+        .with_property_function(
+            NamedNode::new("http://jena.apache.org/ARQ/list#member")?,
+            ListMember,
+        )
+        .with_property_function(
+            NamedNode::new("http://www.example.org/length")?,
+            |subject: Option<&Term>,
+             _: Option<&Term>,
+             _: &GraphName,
+             _: &dyn QueryDataset|
+             -> Result<Vec<(Term, Term)>> {
+                Ok(match subject {
+                    Some(Term::Literal(literal)) => vec![(
+                        literal.clone().into(),
+                        Literal::from(literal.value().chars().count() as i64).into(),
+                    )],
+                    _ => Vec::new(),
+                })
+            },
+        ))
+}
+
+fn query_store(query: &str) -> Result<Vec<QuerySolution>> {
+    match store()?.prepare_query(query, options()?)?.exec()? {
+        QueryResult::Solutions(solutions) => solutions.collect(),
+        _ => Err(Error::msg("Expected solutions")),
+    }
+}
+
+fn contains_operation(explanation: &QueryExplanation, name: &str) -> bool {
+    explanation.name() == name
+        || explanation
+            .children()
+            .iter()
+            .any(|child| contains_operation(child, name))
+}
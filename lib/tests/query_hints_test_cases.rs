@@ -0,0 +1,83 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+const DATA: &str = r#"
+@prefix : <http://www.example.org/> .
+:s1 :p :o1 ; :r 1, 2 .
+:s2 :p :o1 ; :r 3, 4 .
+:s3 :p :o3 ; :r 5, 6 .
+:o1 :q "a", "b", "c" .
+"#;
+
+#[test]
+fn pragma_parsing_test() -> Result<()> {
+    let query = Query::parse(
+        "#pragma join-order fixed\n# a comment\nSELECT * WHERE { ?s ?p ?o } #pragma hash-join off",
+        None,
+    )?;
+    assert!(query.hints().fixed_join_order);
+    assert!(query.hints().hash_join_disabled);
+    // The hints are kept when the query is serialized
+    assert_eq!(
+        Query::parse(&query.to_string(), None)?.hints(),
+        query.hints()
+    );
+
+    let query = Query::parse("#pragma unknown\nSELECT * WHERE { ?s ?p ?o }", None)?;
+    assert_eq!(query.hints(), &algebra::QueryHints::default());
+    Ok(())
+}
+
+#[test]
+fn hash_join_off_test() -> Result<()> {
+    let (explanation, count) = query_store(
+        "#pragma hash-join off\nPREFIX : <http://www.example.org/> SELECT ?s ?y ?x WHERE { ?s :p ?o . ?s :r ?y . ?o :q ?x }",
+    )?;
+    assert!(!contains_operation(&explanation, "HashJoin"));
+    assert_eq!(count, 12);
+    Ok(())
+}
+
+#[test]
+fn fixed_join_order_test() -> Result<()> {
+    let (explanation, count) = query_store(
+        "#pragma join-order fixed\n#pragma hash-join off\nPREFIX : <http://www.example.org/> SELECT ?y WHERE { ?s :r ?y . ?s :p :o3 }",
+    )?;
+    // The last pattern of the query is evaluated last
+    let last_pattern = &explanation.children()[0];
+    assert_eq!(last_pattern.name(), "QuadPatternJoin");
+    assert!(last_pattern
+        .attributes()
+        .iter()
+        .any(|(key, value)| *key == "pattern" && value.contains("<http://www.example.org/o3>")));
+    assert_eq!(count, 2);
+    Ok(())
+}
+
+/// Returns the explanation of the query and its number of solutions
+fn query_store(query: &str) -> Result<(QueryExplanation, usize)> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    let query = store.prepare_query(query, QueryOptions::default())?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        let count = solutions.collect::<Result<Vec<_>>>()?.len();
+        Ok((query.explain()?, count))
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+fn contains_operation(explanation: &QueryExplanation, name: &str) -> bool {
+    explanation.name() == name
+        || explanation
+            .children()
+            .iter()
+            .any(|child| contains_operation(child, name))
+}
@@ -0,0 +1,186 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+use std::iter::once;
+use std::str;
+
+#[test]
+fn csv_results_test() -> Result<()> {
+    assert_eq!(
+        write_results(test_solutions(), QueryResultSyntax::Csv)?,
+        "s,o\r\nhttp://example.com/s,\"foo, \"\"bar\"\"\"\r\n_:b,1\r\nhttp://example.com/s,\r\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn tsv_results_test() -> Result<()> {
+    assert_eq!(
+        write_results(test_solutions(), QueryResultSyntax::Tsv)?,
+        "?s\t?o\n<http://example.com/s>\t\"foo, \\\"bar\\\"\"\n_:b\t\"1\"^^<http://www.w3.org/2001/XMLSchema#integer>\n<http://example.com/s>\t\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn json_results_test() -> Result<()> {
+    assert_eq!(
+        write_results(test_solutions(), QueryResultSyntax::Json)?,
+        r#"{"head":{"vars":["s","o"]},"results":{"bindings":[{"s":{"type":"uri","value":"http://example.com/s"},"o":{"type":"literal","value":"foo, \"bar\""}},{"s":{"type":"bnode","value":"b"},"o":{"type":"literal","value":"1","datatype":"http://www.w3.org/2001/XMLSchema#integer"}},{"s":{"type":"uri","value":"http://example.com/s"}}]}}"#
+    );
+    Ok(())
+}
+
+#[test]
+fn json_control_characters_test() -> Result<()> {
+    let results = QueryResult::Solutions(QuerySolutionsIterator::new(
+        vec![Variable::new("o")],
+        Box::new(once(Ok(vec![Some(
+            Literal::new_simple_literal("a\u{1F}\u{0}\n").into(),
+        )]))),
+    ));
+    assert_eq!(
+        write_results(results, QueryResultSyntax::Json)?,
+        r#"{"head":{"vars":["o"]},"results":{"bindings":[{"o":{"type":"literal","value":"a\u001F\u0000\n"}}]}}"#
+    );
+    Ok(())
+}
+
+#[test]
+fn json_boolean_test() -> Result<()> {
+    assert_eq!(
+        write_results(QueryResult::Boolean(true), QueryResultSyntax::Json)?,
+        r#"{"head":{},"boolean":true}"#
+    );
+    Ok(())
+}
+
+#[test]
+fn json_round_trip_test() -> Result<()> {
+    let json = write_results(test_solutions(), QueryResultSyntax::Json)?;
+    assert_eq!(
+        write_results(
+            QueryResult::read(json.as_bytes(), QueryResultSyntax::Json)?,
+            QueryResultSyntax::Json
+        )?,
+        json
+    );
+    Ok(())
+}
+
+#[test]
+fn json_read_test() -> Result<()> {
+    let json = r#"{
+        "results": { "bindings": [
+            { "o": { "type": "literal", "value": "caf\u00e9 \ud83d\ude00", "xml:lang": "fr" } },
+            { "s": { "type": "bnode", "value": "b" }, "o": { "type": "typed-literal", "value": "1.5e0", "datatype": "http://www.w3.org/2001/XMLSchema#double" } }
+        ] },
+        "head": { "link": [], "vars": [ "s", "o" ] }
+    }"#;
+    if let QueryResult::Solutions(solutions) =
+        QueryResult::read(json.as_bytes(), QueryResultSyntax::Json)?
+    {
+        assert_eq!(
+            solutions.variables(),
+            &[Variable::new("s"), Variable::new("o")]
+        );
+        let solutions = solutions.collect::<Result<Vec<_>>>()?;
+        assert_eq!(solutions.len(), 2);
+        assert_eq!(solutions[0].get("s"), None);
+        assert_eq!(
+            solutions[0].get("o"),
+            Some(&Literal::new_language_tagged_literal("caf\u{e9} \u{1f600}", "fr")?.into())
+        );
+        assert_eq!(solutions[1].get("s"), Some(&BlankNode::new("b")?.into()));
+        assert_eq!(
+            solutions[1].get("o"),
+            Some(
+                &Literal::new_typed_literal(
+                    "1.5e0",
+                    NamedNode::new("http://www.w3.org/2001/XMLSchema#double")?
+                )
+                .into()
+            )
+        );
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn json_read_boolean_test() -> Result<()> {
+    if let QueryResult::Boolean(value) = QueryResult::read(
+        r#"{"head":{},"boolean":false}"#.as_bytes(),
+        QueryResultSyntax::Json,
+    )? {
+        assert!(!value);
+        Ok(())
+    } else {
+        Err(Error::msg("Expected a boolean"))
+    }
+}
+
+#[test]
+fn json_read_undeclared_variable_test() -> Result<()> {
+    let json = r#"{"head":{"vars":["s"]},"results":{"bindings":[{"o":{"type":"uri","value":"http://example.com/o"}}]}}"#;
+    if let QueryResult::Solutions(mut solutions) =
+        QueryResult::read(json.as_bytes(), QueryResultSyntax::Json)?
+    {
+        assert!(solutions.next().unwrap().is_err());
+        // The iterator is fused after an error
+        assert!(solutions.next().is_none());
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+#[test]
+fn json_read_deeply_nested_value_test() -> Result<()> {
+    let depth = 1_000_000;
+    let json = format!(
+        r#"{{"head":{{"link":{}{}}},"boolean":true}}"#,
+        "[".repeat(depth),
+        "]".repeat(depth)
+    );
+    let result = QueryResult::read(json.as_bytes(), QueryResultSyntax::Json)?;
+    if let QueryResult::Boolean(value) = result {
+        assert!(value);
+        Ok(())
+    } else {
+        Err(Error::msg("Expected a boolean"))
+    }
+}
+
+#[test]
+fn csv_boolean_test() {
+    assert!(write_results(QueryResult::Boolean(true), QueryResultSyntax::Csv).is_err());
+}
+
+fn test_solutions() -> QueryResult<'static> {
+    let s = Term::from(NamedNode::new_unchecked("http://example.com/s"));
+    QueryResult::Solutions(QuerySolutionsIterator::new(
+        vec![Variable::new("s"), Variable::new("o")],
+        Box::new(
+            vec![
+                Ok(vec![
+                    Some(s.clone()),
+                    Some(Literal::new_simple_literal("foo, \"bar\"").into()),
+                ]),
+                Ok(vec![
+                    Some(BlankNode::new_unchecked("b").into()),
+                    Some(Literal::from(1).into()),
+                ]),
+                Ok(vec![Some(s), None]),
+            ]
+            .into_iter(),
+        ),
+    ))
+}
+
+fn write_results(results: QueryResult<'_>, syntax: QueryResultSyntax) -> Result<String> {
+    Ok(str::from_utf8(&results.write(Vec::default(), syntax)?)
+        .map_err(Error::wrap)?
+        .to_owned())
+}
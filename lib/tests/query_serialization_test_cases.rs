@@ -0,0 +1,87 @@
+use oxigraph::model::*;
+use oxigraph::sparql::algebra::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+#[test]
+fn serialization_roundtrip_test() -> Result<()> {
+    for query in &[
+        "SELECT ?s WHERE { ?s ?p ?o FILTER(?o = (1 + 2) * 3 && -(?o) < 4) }",
+        "SELECT ?s WHERE { ?s ?p ?o FILTER(STRSTARTS(STR(?o), \"a\") || ?o IN (1, 2 - 1)) }",
+        "SELECT ?s WHERE { ?s ?p ?o FILTER(!(?o NOT IN (1)) && NOT EXISTS { ?o ?p ?s }) }",
+        "SELECT ?s WHERE { ?s ?p ?o OPTIONAL { { ?o ?p ?v FILTER(?v = ?s) } } }",
+        "SELECT ?s WHERE { { ?s ?p ?o FILTER(BOUND(?v)) } BIND(1 AS ?v) }",
+        "SELECT ?s WHERE { ?s (<http://example.com/p>?)+/^(<http://example.com/q>*) ?o }",
+        "SELECT DISTINCT ?s WHERE { ?s ?p ?o } ORDER BY DESC(?o) OFFSET 1 LIMIT 2",
+        "ASK { GRAPH ?g { ?s ?p ?o } MINUS { ?s ?p 1 } }",
+        "CONSTRUCT { ?s ?p ?o } WHERE { ?s ?p ?o VALUES ?o { 1 UNDEF } }",
+        "CONSTRUCT { ?s ?p ?o } WHERE { ?s ?p ?o } ORDER BY ?o LIMIT 2",
+        "DESCRIBE * WHERE { ?s ?p ?o } OFFSET 1",
+    ] {
+        let query = Query::parse(query, None)?;
+        assert_eq!(
+            Query::parse(&query.to_string(), None)?,
+            query,
+            "Serialization of {} changed the query",
+            query
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn aggregate_serialization_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        "<http://example.com/s1> <http://example.com/p> 1, 2 . <http://example.com/s2> <http://example.com/p> 1 .".as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    let query = Query::parse(
+        "SELECT ?s (COUNT(?o) AS ?c) WHERE { ?s ?p ?o } GROUP BY ?s HAVING(COUNT(?o) > 1)",
+        None,
+    )?;
+    // The aggregates are written as sub-queries
+    let serialized_query = Query::parse(&query.to_string(), None)?;
+    for query in vec![query, serialized_query] {
+        if let QueryResult::Solutions(solutions) = store
+            .prepare_parsed_query(query, QueryOptions::default())?
+            .exec()?
+        {
+            let solutions = solutions.collect::<Result<Vec<_>>>()?;
+            assert_eq!(solutions.len(), 1);
+            assert_eq!(solutions[0].get("c"), Some(&Literal::from(2).into()));
+        } else {
+            return Err(Error::msg("Expected solutions"));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn solution_modifiers_order_test() -> Result<()> {
+    // The DISTINCT is evaluated after the LIMIT so it should be in a sub-query
+    let pattern = GraphPattern::BGP(vec![TriplePattern::new(
+        Variable::new("s"),
+        Variable::new("p"),
+        Variable::new("o"),
+    )
+    .into()]);
+    let query = Query::from(QueryVariants::Select {
+        dataset: DatasetSpec::default(),
+        algebra: GraphPattern::Distinct(Box::new(GraphPattern::Slice(
+            Box::new(GraphPattern::Project(
+                Box::new(pattern),
+                vec![Variable::new("s")],
+            )),
+            0,
+            Some(10),
+        ))),
+        base_iri: None,
+    });
+    let serialization = query.to_string();
+    assert!(serialization.starts_with("SELECT DISTINCT *"));
+    assert!(serialization.contains("{ SELECT ?s "));
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+const DATA: &str = r#"
+@prefix : <http://www.example.org/> .
+@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+:Cat rdfs:subClassOf :Mammal .
+:Mammal rdfs:subClassOf :Animal .
+:hasMother rdfs:subPropertyOf :hasParent .
+:hasParent rdfs:domain :Animal ; rdfs:range :Animal .
+:owns rdfs:range :Pet .
+:tom a :Cat ; :hasMother :kitty .
+:alice :owns :tom .
+"#;
+
+#[test]
+fn sub_class_of_test() -> Result<()> {
+    assert_eq!(
+        subjects("SELECT ?s WHERE { ?s a :Mammal }")?,
+        vec!["<http://www.example.org/tom>"]
+    );
+    assert_eq!(
+        subjects("SELECT ?s WHERE { ?s rdfs:subClassOf :Animal }")?,
+        vec![
+            "<http://www.example.org/Cat>",
+            "<http://www.example.org/Mammal>"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn sub_property_of_test() -> Result<()> {
+    assert_eq!(
+        subjects("SELECT ?s WHERE { ?s :hasParent [] }")?,
+        vec!["<http://www.example.org/tom>"]
+    );
+    Ok(())
+}
+
+#[test]
+fn domain_and_range_test() -> Result<()> {
+    assert_eq!(
+        subjects("SELECT ?s WHERE { ?s a :Animal }")?,
+        vec![
+            "<http://www.example.org/kitty>",
+            "<http://www.example.org/tom>"
+        ]
+    );
+    assert_eq!(
+        subjects("SELECT ?s WHERE { ?s a :Pet }")?,
+        vec!["<http://www.example.org/tom>"]
+    );
+    Ok(())
+}
+
+#[test]
+fn without_entailment_test() -> Result<()> {
+    let store = store()?;
+    let query = store.prepare_query(
+        "PREFIX : <http://www.example.org/> SELECT ?s WHERE { ?s a :Animal }",
+        QueryOptions::default(),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        assert_eq!(solutions.count(), 0);
+        Ok(())
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
+
+fn store() -> Result<MemoryStore> {
+    let store = MemoryStore::new();
+    store.load_graph(
+        DATA.as_bytes(),
+        GraphSyntax::Turtle,
+        &GraphName::DefaultGraph,
+        None,
+    )?;
+    Ok(store)
+}
+
+/// Returns the sorted values of ?s
+fn subjects(query: &str) -> Result<Vec<String>> {
+    let query = store()?.prepare_query(
+        &format!(
+            "PREFIX : <http://www.example.org/> PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#> {}",
+            query
+        ),
+        QueryOptions::default().with_rdfs_entailment(),
+    )?;
+    let result = query.exec()?;
+    if let QueryResult::Solutions(solutions) = result {
+        let mut subjects = solutions
+            .map(|solution| {
+                Ok(solution?
+                    .get("s")
+                    .map_or_else(String::new, |s| s.to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        subjects.sort();
+        Ok(subjects)
+    } else {
+        Err(Error::msg("Expected solutions"))
+    }
+}
@@ -179,6 +179,51 @@ fn non_silent_service_test() {
     }
 }
 
+#[test]
+fn disabled_service_test() {
+    let query = r#"
+  SELECT ?s ?p ?o
+  WHERE
+    {
+      SERVICE <http://service1.org>
+      { ?s ?p ?o
+      }
+    }
+  "#
+    .to_string();
+
+    let triples = b"".as_ref();
+    let options = QueryOptions::default()
+        .with_simple_service_handler()
+        .without_service_handler();
+    let mut solutions = do_query(triples, query, options).unwrap();
+    if let Some(Err(_)) = solutions.next() {
+    } else {
+        panic!("This should have been an error since the SERVICE calls are disabled")
+    }
+}
+
+#[test]
+fn default_disabled_service_test() {
+    let query = r#"
+  SELECT ?s ?p ?o
+  WHERE
+    {
+      SERVICE <http://service1.org>
+      { ?s ?p ?o
+      }
+    }
+  "#
+    .to_string();
+
+    let triples = b"".as_ref();
+    let mut solutions = do_query(triples, query, QueryOptions::default()).unwrap();
+    if let Some(Err(_)) = solutions.next() {
+    } else {
+        panic!("This should have been an error since the SERVICE calls are disabled by default")
+    }
+}
+
 fn ex(id: &str) -> Term {
     Term::NamedNode(NamedNode::new(format!("http://example.com/{}", id)).unwrap())
 }
@@ -0,0 +1,137 @@
+use oxigraph::model::*;
+use oxigraph::sparql::*;
+use oxigraph::*;
+
+fn node(id: usize) -> Result<NamedNode> {
+    Ok(NamedNode::new(format!("http://www.example.org/{}", id))?)
+}
+
+/// The store with `<< :0 :1 1 >> :2 :3`, `:0 :1 1` and `<< << :0 :1 1 >> :4 :5 >> :2 :6`
+fn store() -> Result<MemoryStore> {
+    let store = MemoryStore::new();
+    let quoted = Triple::new(node(0)?, node(1)?, Literal::from(1));
+    store.insert(Quad::new(quoted.clone(), node(2)?, node(3)?, None));
+    store.insert(Quad::new(node(0)?, node(1)?, Literal::from(1), None));
+    store.insert(Quad::new(
+        Triple::new(quoted, node(4)?, node(5)?),
+        node(2)?,
+        node(6)?,
+        None,
+    ));
+    Ok(store)
+}
+
+fn query_store(store: &MemoryStore, query: &str) -> Result<Vec<QuerySolution>> {
+    match store
+        .prepare_query(query, QueryOptions::default())?
+        .exec()?
+    {
+        QueryResult::Solutions(solutions) => solutions.collect(),
+        _ => Err(Error::msg("Expected solutions")),
+    }
+}
+
+#[test]
+fn quoted_triple_pattern_test() -> Result<()> {
+    let solutions = query_store(
+        &store()?,
+        "PREFIX : <http://www.example.org/> SELECT ?s ?o ?v WHERE { << ?s :1 ?o >> :2 ?v }",
+    )?;
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0].get("s"), Some(&node(0)?.into()));
+    assert_eq!(solutions[0].get("o"), Some(&Literal::from(1).into()));
+    assert_eq!(solutions[0].get("v"), Some(&node(3)?.into()));
+
+    let solutions = query_store(
+        &store()?,
+        "PREFIX : <http://www.example.org/> SELECT ?v WHERE { << :0 :1 2 >> :2 ?v }",
+    )?;
+    assert!(solutions.is_empty());
+    Ok(())
+}
+
+#[test]
+fn quoted_triple_with_bound_terms_test() -> Result<()> {
+    let store = store()?;
+    let query =
+        "PREFIX : <http://www.example.org/> SELECT ?v WHERE { ?s :1 ?o . << ?s :1 ?o >> :2 ?v }";
+    let solutions = query_store(&store, query)?;
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0].get("v"), Some(&node(3)?.into()));
+
+    let explanation = store
+        .prepare_query(query, QueryOptions::default())?
+        .explain()?;
+    assert!(contains_operation(&explanation, "QuotedTriplePattern"));
+    Ok(())
+}
+
+#[test]
+fn nested_quoted_triple_pattern_test() -> Result<()> {
+    let solutions = query_store(
+        &store()?,
+        "PREFIX : <http://www.example.org/> SELECT ?s ?q ?v WHERE { << << ?s :1 1 >> ?q :5 >> :2 ?v }",
+    )?;
+    assert_eq!(solutions.len(), 1);
+    assert_eq!(solutions[0].get("s"), Some(&node(0)?.into()));
+    assert_eq!(solutions[0].get("q"), Some(&node(4)?.into()));
+    assert_eq!(solutions[0].get("v"), Some(&node(6)?.into()));
+    Ok(())
+}
+
+#[test]
+fn construct_quoted_triple_test() -> Result<()> {
+    let query = store()?.prepare_query(
+        "PREFIX : <http://www.example.org/> CONSTRUCT { << ?s :1 ?o >> :7 ?v } WHERE { << ?s :1 ?o >> :2 ?v }",
+        QueryOptions::default(),
+    )?;
+    let triples = if let QueryResult::Graph(triples) = query.exec()? {
+        triples.collect::<Result<Vec<_>>>()?
+    } else {
+        return Err(Error::msg("Expected triples"));
+    };
+    assert_eq!(
+        triples,
+        vec![Triple::new(
+            Triple::new(node(0)?, node(1)?, Literal::from(1)),
+            node(7)?,
+            node(3)?
+        )]
+    );
+    Ok(())
+}
+
+#[test]
+fn update_quoted_triple_test() -> Result<()> {
+    let store = MemoryStore::new();
+    store
+        .prepare_update(
+            "PREFIX : <http://www.example.org/> INSERT DATA { << :0 :1 1 >> :2 :3 }",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    let quoted = Triple::new(node(0)?, node(1)?, Literal::from(1));
+    assert!(store.contains(&Quad::new(quoted.clone(), node(2)?, node(3)?, None)));
+
+    store
+        .prepare_update(
+            "PREFIX : <http://www.example.org/> DELETE { << ?s :1 ?o >> :2 ?v } INSERT { << ?s :1 ?o >> :4 ?v } WHERE { << ?s :1 ?o >> :2 ?v }",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert_eq!(
+        store
+            .quads_for_pattern(None, None, None, None)
+            .collect::<Vec<_>>(),
+        vec![Quad::new(quoted, node(4)?, node(3)?, None)]
+    );
+    Ok(())
+}
+
+fn contains_operation(explanation: &QueryExplanation, name: &str) -> bool {
+    explanation.name() == name
+        || explanation
+            .children()
+            .iter()
+            .any(|child| contains_operation(child, name))
+}
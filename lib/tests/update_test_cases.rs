@@ -0,0 +1,136 @@
+use oxigraph::sparql::*;
+use oxigraph::*;
+use std::env::temp_dir;
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::path::Path;
+
+const DATA: &str = "<http://example.com/s> <http://example.com/p> <http://example.com/o> .";
+
+fn load(store: &MemoryStore, file: &Path, options: QueryOptions<'_>) -> Result<()> {
+    let iri = format!(
+        "file://{}",
+        file.to_str()
+            .unwrap()
+            .replace('%', "%25")
+            .replace(' ', "%20")
+    );
+    store
+        .prepare_update(&format!("LOAD <{}>", iri), options)?
+        .exec()
+}
+
+#[test]
+fn load_disabled_by_default_test() -> Result<()> {
+    let directory = temp_dir().join("oxigraph_load_disabled_test");
+    create_dir_all(&directory)?;
+    let file = directory.join("data.nt");
+    write(&file, DATA)?;
+
+    let store = MemoryStore::new();
+    assert!(load(&store, &file, QueryOptions::default()).is_err());
+    assert_eq!(store.len(), 0);
+    remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn load_from_directory_test() -> Result<()> {
+    let directory = temp_dir().join("oxigraph_load_test");
+    create_dir_all(&directory)?;
+    let file = directory.join("data with spaces.nt");
+    write(&file, DATA)?;
+
+    let store = MemoryStore::new();
+    load(
+        &store,
+        &file,
+        QueryOptions::default().with_load_directory(&directory),
+    )?;
+    assert_eq!(store.len(), 1);
+    remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn load_outside_of_directory_test() -> Result<()> {
+    let directory = temp_dir().join("oxigraph_load_outside_test");
+    let allowed = directory.join("allowed");
+    create_dir_all(&allowed)?;
+    let file = directory.join("data.nt");
+    write(&file, DATA)?;
+
+    let store = MemoryStore::new();
+    let options = || QueryOptions::default().with_load_directory(&allowed);
+    assert!(load(&store, &file, options()).is_err());
+    assert!(load(&store, &allowed.join("..").join("data.nt"), options()).is_err());
+    assert!(store
+        .prepare_update("LOAD <http://example.com/data.nt>", options())?
+        .exec()
+        .is_err());
+    assert_eq!(store.len(), 0);
+    remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[test]
+fn update_atomicity_test() -> Result<()> {
+    let store = MemoryStore::new();
+    // The second operation fails so the first one should not be applied
+    assert!(store
+        .prepare_update(
+            "INSERT DATA { <http://example.com/s> <http://example.com/p> 1 } ; LOAD <file:///data.nt>",
+            QueryOptions::default(),
+        )?
+        .exec()
+        .is_err());
+    assert_eq!(store.len(), 0);
+
+    // The operations see the changes of the previous ones
+    store
+        .prepare_update(
+            "INSERT DATA { <http://example.com/s> <http://example.com/p> 1 } ; INSERT { ?s <http://example.com/p> 2 } WHERE { ?s <http://example.com/p> 1 }",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    assert_eq!(store.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn clear_and_drop_missing_graph_test() -> Result<()> {
+    let store = MemoryStore::new();
+    for operation in &["CLEAR", "DROP"] {
+        // The graph does not exist so the operation fails unless it is SILENT
+        assert!(store
+            .prepare_update(
+                &format!("{} GRAPH <http://example.com/g>", operation),
+                QueryOptions::default(),
+            )?
+            .exec()
+            .is_err());
+        store
+            .prepare_update(
+                &format!("{} SILENT GRAPH <http://example.com/g>", operation),
+                QueryOptions::default(),
+            )?
+            .exec()?;
+    }
+
+    // The graph exists even if it is empty
+    store
+        .prepare_update(
+            "CREATE GRAPH <http://example.com/g>",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    store
+        .prepare_update(
+            "CLEAR GRAPH <http://example.com/g>",
+            QueryOptions::default(),
+        )?
+        .exec()?;
+    store
+        .prepare_update("DROP GRAPH <http://example.com/g>", QueryOptions::default())?
+        .exec()?;
+    Ok(())
+}
@@ -10,21 +10,26 @@
 )]
 
 use argh::FromArgs;
-use async_std::future::Future;
+use async_std::future::{poll_fn, Future};
 use async_std::io::{BufRead, Read};
 use async_std::net::{TcpListener, TcpStream};
 use async_std::prelude::*;
-use async_std::task::{block_on, spawn, spawn_blocking};
+use async_std::task::{block_on, sleep, spawn, spawn_blocking};
 use http_types::{headers, Body, Error, Method, Mime, Request, Response, Result, StatusCode};
 use oxigraph::model::GraphName;
 use oxigraph::sparql::{QueryOptions, QueryResult, QueryResultSyntax};
-use oxigraph::{DatasetSyntax, FileSyntax, GraphSyntax, RocksDbStore};
+use oxigraph::store::rocksdb::RocksDbOptions;
+use oxigraph::{DatasetSyntax, FileSyntax, GraphSyntax, RocksDbStore, SledStore};
+use std::pin::Pin;
 use std::str::FromStr;
+use std::task::Poll;
+use std::time::Duration;
 use url::form_urlencoded;
 
 const MAX_SPARQL_BODY_SIZE: u64 = 1_048_576;
 const HTML_ROOT_PAGE: &str = include_str!("../templates/query.html");
 const SERVER: &str = concat!("Oxigraph/", env!("CARGO_PKG_VERSION"));
+const REPLICATION_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(FromArgs)]
 /// Oxigraph SPARQL server
@@ -36,21 +41,218 @@ struct Args {
     /// directory in which persist the data
     #[argh(option, short = 'f')]
     file: String,
+
+    /// storage backend to use: "rocksdb" (the default) or "sled"
+    #[argh(option, default = "Backend::RocksDb")]
+    backend: Backend,
+
+    /// maximal number of solutions or triples returned by a query
+    #[argh(option)]
+    max_results: Option<usize>,
+
+    /// maximal number of solutions sorted in memory by ORDER BY before spilling them to temporary files
+    #[argh(option)]
+    sort_memory_limit: Option<usize>,
+
+    /// allow the queries to call other SPARQL endpoints over HTTP using SERVICE
+    #[argh(switch)]
+    enable_service: bool,
+
+    /// file to which append the commits in order to replicate them (rocksdb backend only)
+    #[argh(option)]
+    commit_log: Option<String>,
+
+    /// commit log of a primary server to replicate, the server is then read-only (rocksdb backend only)
+    #[argh(option)]
+    replicate_from: Option<String>,
+}
+
+enum Backend {
+    RocksDb,
+    Sled,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        match s {
+            "rocksdb" => Ok(Self::RocksDb),
+            "sled" => Ok(Self::Sled),
+            _ => Err(format!(
+                "Unknown backend {}, the supported ones are rocksdb and sled",
+                s
+            )),
+        }
+    }
+}
+
+/// The operations of the storage backends used by the server
+trait ServerStore: Clone + Send + Sync + 'static {
+    fn load_graph(
+        &self,
+        reader: impl std::io::BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> oxigraph::Result<()>;
+
+    fn load_dataset(
+        &self,
+        reader: impl std::io::BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> oxigraph::Result<()>;
+
+    /// Evaluates the query and serializes its results in the format requested by `request`
+    fn evaluate_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+        request: Request,
+    ) -> Result<Response>;
+}
+
+impl ServerStore for RocksDbStore {
+    fn load_graph(
+        &self,
+        reader: impl std::io::BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> oxigraph::Result<()> {
+        RocksDbStore::load_graph(self, reader, syntax, to_graph_name, base_iri)
+    }
+
+    fn load_dataset(
+        &self,
+        reader: impl std::io::BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> oxigraph::Result<()> {
+        RocksDbStore::load_dataset(self, reader, syntax, base_iri)
+    }
+
+    fn evaluate_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+        request: Request,
+    ) -> Result<Response> {
+        let query = self.prepare_query(query, options).map_err(bad_request)?;
+        let results = query.exec()?;
+        query_results_response(results, request)
+    }
+}
+
+impl ServerStore for SledStore {
+    fn load_graph(
+        &self,
+        reader: impl std::io::BufRead,
+        syntax: GraphSyntax,
+        to_graph_name: &GraphName,
+        base_iri: Option<&str>,
+    ) -> oxigraph::Result<()> {
+        SledStore::load_graph(self, reader, syntax, to_graph_name, base_iri)
+    }
+
+    fn load_dataset(
+        &self,
+        reader: impl std::io::BufRead,
+        syntax: DatasetSyntax,
+        base_iri: Option<&str>,
+    ) -> oxigraph::Result<()> {
+        SledStore::load_dataset(self, reader, syntax, base_iri)
+    }
+
+    fn evaluate_query(
+        &self,
+        query: &str,
+        options: QueryOptions<'_>,
+        request: Request,
+    ) -> Result<Response> {
+        let query = self.prepare_query(query, options).map_err(bad_request)?;
+        let results = query.exec()?;
+        query_results_response(results, request)
+    }
 }
 
 #[async_std::main]
 pub async fn main() -> Result<()> {
     let args: Args = argh::from_env();
-    let store = RocksDbStore::open(args.file)?;
+    match args.backend {
+        Backend::RocksDb => {
+            let mut options = RocksDbOptions::default();
+            if let Some(commit_log) = &args.commit_log {
+                options = options.with_commit_log(commit_log);
+            }
+            let store = RocksDbStore::open_with_options(&args.file, options)?;
+            if let Some(commit_log) = args.replicate_from.clone() {
+                let mut replication = spawn(replicate(store.clone(), commit_log));
+                let mut server = spawn(serve(store, args));
+                // The server stops if the replication fails
+                poll_fn(|cx| match Pin::new(&mut replication).poll(cx) {
+                    Poll::Ready(result) => Poll::Ready(result),
+                    Poll::Pending => Pin::new(&mut server).poll(cx),
+                })
+                .await
+            } else {
+                serve(store, args).await
+            }
+        }
+        Backend::Sled => {
+            if args.commit_log.is_some() || args.replicate_from.is_some() {
+                return Err(Error::from_str(
+                    StatusCode::InternalServerError,
+                    "The replication is only supported by the rocksdb backend",
+                ));
+            }
+            serve(SledStore::open(&args.file)?, args).await
+        }
+    }
+}
+
+/// Applies periodically the new commits of the log of the primary server
+///
+/// It only returns if the replication fails.
+async fn replicate(store: RocksDbStore, commit_log: String) -> Result<()> {
+    loop {
+        let store = store.clone();
+        let commit_log = commit_log.clone();
+        spawn_blocking(move || store.replicate(commit_log)).await?;
+        sleep(REPLICATION_INTERVAL).await;
+    }
+}
+
+async fn serve(store: impl ServerStore, args: Args) -> Result<()> {
+    let settings = QuerySettings {
+        max_results: args.max_results,
+        sort_memory_limit: args.sort_memory_limit,
+        enable_service: args.enable_service,
+    };
+    let read_only = args.replicate_from.is_some();
 
     println!("Listening for requests at http://{}", &args.bind);
     http_server(&args.bind, move |request| {
-        handle_request(request, store.clone())
+        handle_request(request, store.clone(), settings, read_only)
     })
     .await
 }
 
-async fn handle_request(request: Request, store: RocksDbStore) -> Result<Response> {
+/// The query evaluation settings given on the command line
+#[derive(Clone, Copy, Default)]
+struct QuerySettings {
+    max_results: Option<usize>,
+    sort_memory_limit: Option<usize>,
+    enable_service: bool,
+}
+
+async fn handle_request(
+    request: Request,
+    store: impl ServerStore,
+    settings: QuerySettings,
+    read_only: bool,
+) -> Result<Response> {
     let mut response = match (request.url().path(), request.method()) {
         ("/", Method::Get) => {
             let mut response = Response::new(StatusCode::Ok);
@@ -58,6 +260,9 @@ async fn handle_request(request: Request, store: RocksDbStore) -> Result<Respons
             response.set_body(HTML_ROOT_PAGE);
             response
         }
+        _ if read_only && !is_read_request(&request) => {
+            simple_response(StatusCode::Forbidden, "This server is a read-only replica")
+        }
         ("/", Method::Post) => {
             if let Some(content_type) = request.content_type() {
                 match if let Some(format) = GraphSyntax::from_mime_type(content_type.essence()) {
@@ -97,6 +302,7 @@ async fn handle_request(request: Request, store: RocksDbStore) -> Result<Respons
                 store,
                 request.url().query().unwrap_or("").as_bytes().to_vec(),
                 request,
+                settings,
             )
             .await?
         }
@@ -110,7 +316,7 @@ async fn handle_request(request: Request, store: RocksDbStore) -> Result<Respons
                         .take(MAX_SPARQL_BODY_SIZE)
                         .read_to_string(&mut buffer)
                         .await?;
-                    evaluate_sparql_query(store, buffer, request).await?
+                    evaluate_sparql_query(store, buffer, request, settings).await?
                 } else if content_type.essence() == "application/x-www-form-urlencoded" {
                     let mut buffer = Vec::new();
                     let mut request = request;
@@ -119,7 +325,7 @@ async fn handle_request(request: Request, store: RocksDbStore) -> Result<Respons
                         .take(MAX_SPARQL_BODY_SIZE)
                         .read_to_end(&mut buffer)
                         .await?;
-                    evaluate_urlencoded_sparql_query(store, buffer, request).await?
+                    evaluate_urlencoded_sparql_query(store, buffer, request, settings).await?
                 } else {
                     simple_response(
                         StatusCode::UnsupportedMediaType,
@@ -136,6 +342,14 @@ async fn handle_request(request: Request, store: RocksDbStore) -> Result<Respons
     Ok(response)
 }
 
+/// Checks if the request does not change the store, the only requests allowed by the read-only replicas
+fn is_read_request(request: &Request) -> bool {
+    matches!(
+        (request.url().path(), request.method()),
+        (_, Method::Get) | (_, Method::Head) | ("/query", Method::Post)
+    )
+}
+
 fn simple_response(status: StatusCode, body: impl Into<Body>) -> Response {
     let mut response = Response::new(status);
     response.set_body(body);
@@ -143,12 +357,13 @@ fn simple_response(status: StatusCode, body: impl Into<Body>) -> Response {
 }
 
 async fn evaluate_urlencoded_sparql_query(
-    store: RocksDbStore,
+    store: impl ServerStore,
     encoded: Vec<u8>,
     request: Request,
+    settings: QuerySettings,
 ) -> Result<Response> {
     if let Some((_, query)) = form_urlencoded::parse(&encoded).find(|(k, _)| k == "query") {
-        evaluate_sparql_query(store, query.to_string(), request).await
+        evaluate_sparql_query(store, query.to_string(), request, settings).await
     } else {
         Ok(simple_response(
             StatusCode::BadRequest,
@@ -158,49 +373,64 @@ async fn evaluate_urlencoded_sparql_query(
 }
 
 async fn evaluate_sparql_query(
-    store: RocksDbStore,
+    store: impl ServerStore,
     query: String,
     request: Request,
+    settings: QuerySettings,
 ) -> Result<Response> {
     spawn_blocking(move || {
         //TODO: stream
-        let query = store
-            .prepare_query(&query, QueryOptions::default())
-            .map_err(|e| {
-                let mut e = Error::from(e);
-                e.set_status(StatusCode::BadRequest);
-                e
-            })?;
-        let results = query.exec()?;
-        if let QueryResult::Graph(_) = results {
-            let format = content_negotiation(
-                request,
-                &[
-                    GraphSyntax::NTriples.media_type(),
-                    GraphSyntax::Turtle.media_type(),
-                    GraphSyntax::RdfXml.media_type(),
-                ],
-            )?;
-
-            let mut response = Response::from(results.write_graph(Vec::default(), format)?);
-            response.insert_header(headers::CONTENT_TYPE, format.media_type());
-            Ok(response)
-        } else {
-            let format = content_negotiation(
-                request,
-                &[
-                    QueryResultSyntax::Xml.media_type(),
-                    QueryResultSyntax::Json.media_type(),
-                ],
-            )?;
-            let mut response = Response::from(results.write(Vec::default(), format)?);
-            response.insert_header(headers::CONTENT_TYPE, format.media_type());
-            Ok(response)
+        let mut options = QueryOptions::default();
+        if let Some(max_results) = settings.max_results {
+            options = options.with_max_results(max_results);
+        }
+        if let Some(sort_memory_limit) = settings.sort_memory_limit {
+            options = options.with_sort_memory_limit(sort_memory_limit);
         }
+        if settings.enable_service {
+            options = options.with_simple_service_handler();
+        }
+        store.evaluate_query(&query, options, request)
     })
     .await
 }
 
+fn bad_request(error: oxigraph::Error) -> Error {
+    let mut error = Error::from(error);
+    error.set_status(StatusCode::BadRequest);
+    error
+}
+
+fn query_results_response(results: QueryResult<'_>, request: Request) -> Result<Response> {
+    if let QueryResult::Graph(_) = results {
+        let format = content_negotiation(
+            request,
+            &[
+                GraphSyntax::NTriples.media_type(),
+                GraphSyntax::Turtle.media_type(),
+                GraphSyntax::RdfXml.media_type(),
+            ],
+        )?;
+
+        let mut response = Response::from(results.write_graph(Vec::default(), format)?);
+        response.insert_header(headers::CONTENT_TYPE, format.media_type());
+        Ok(response)
+    } else {
+        let format = content_negotiation(
+            request,
+            &[
+                QueryResultSyntax::Xml.media_type(),
+                QueryResultSyntax::Json.media_type(),
+                QueryResultSyntax::Csv.media_type(),
+                QueryResultSyntax::Tsv.media_type(),
+            ],
+        )?;
+        let mut response = Response::from(results.write(Vec::default(), format)?);
+        response.insert_header(headers::CONTENT_TYPE, format.media_type());
+        Ok(response)
+    }
+}
+
 async fn http_server<
     F: Clone + Send + Sync + 'static + Fn(Request) -> Fut,
     Fut: Send + Future<Output = Result<Response>>,
@@ -313,10 +543,10 @@ impl<R: BufRead + Unpin> std::io::BufRead for SyncAsyncBufReader<R> {
 
 #[cfg(test)]
 mod tests {
-    use crate::handle_request;
+    use crate::{handle_request, QuerySettings};
     use async_std::task::block_on;
     use http_types::{Method, Request, StatusCode, Url};
-    use oxigraph::RocksDbStore;
+    use oxigraph::{RocksDbStore, SledStore};
     use std::collections::hash_map::DefaultHasher;
     use std::env::temp_dir;
     use std::fs::remove_dir_all;
@@ -338,6 +568,53 @@ mod tests {
         exec(request, StatusCode::NoContent)
     }
 
+    #[test]
+    fn post_file_and_query_sled() {
+        let store = SledStore::new().unwrap();
+
+        let mut request = Request::new(Method::Post, Url::parse("http://localhost/").unwrap());
+        request.insert_header("Content-Type", "text/turtle");
+        request.set_body("<http://example.com> <http://example.com> <http://example.com> .");
+        exec_sled(request, store.clone(), StatusCode::NoContent);
+
+        let mut request = Request::new(Method::Post, Url::parse("http://localhost/query").unwrap());
+        request.insert_header("Content-Type", "application/sparql-query");
+        request.set_body("SELECT * WHERE { ?s ?p ?o }");
+        exec_sled(request, store, StatusCode::Ok)
+    }
+
+    #[test]
+    fn post_file_read_only() {
+        let mut request = Request::new(Method::Post, Url::parse("http://localhost/").unwrap());
+        request.insert_header("Content-Type", "text/turtle");
+        request.set_body("<http://example.com> <http://example.com> <http://example.com> .");
+        exec_read_only(request, StatusCode::Forbidden)
+    }
+
+    #[test]
+    fn put_file_read_only() {
+        let mut request = Request::new(Method::Put, Url::parse("http://localhost/").unwrap());
+        request.insert_header("Content-Type", "text/turtle");
+        request.set_body("<http://example.com> <http://example.com> <http://example.com> .");
+        exec_read_only(request, StatusCode::Forbidden)
+    }
+
+    #[test]
+    fn delete_read_only() {
+        exec_read_only(
+            Request::new(Method::Delete, Url::parse("http://localhost/").unwrap()),
+            StatusCode::Forbidden,
+        )
+    }
+
+    #[test]
+    fn post_query_read_only() {
+        let mut request = Request::new(Method::Post, Url::parse("http://localhost/query").unwrap());
+        request.insert_header("Content-Type", "application/sparql-query");
+        request.set_body("SELECT * WHERE { ?s ?p ?o }");
+        exec_read_only(request, StatusCode::Ok)
+    }
+
     #[test]
     fn post_wrong_file() {
         let mut request = Request::new(Method::Post, Url::parse("http://localhost/").unwrap());
@@ -419,7 +696,12 @@ mod tests {
 
         let store = RocksDbStore::open(&path).unwrap();
         assert_eq!(
-            match block_on(handle_request(request, store)) {
+            match block_on(handle_request(
+                request,
+                store,
+                QuerySettings::default(),
+                false
+            )) {
                 Ok(r) => r.status(),
                 Err(e) => e.status(),
             },
@@ -427,4 +709,34 @@ mod tests {
         );
         remove_dir_all(&path).unwrap()
     }
+
+    fn exec_read_only(request: Request, expected_status: StatusCode) {
+        assert_eq!(
+            match block_on(handle_request(
+                request,
+                SledStore::new().unwrap(),
+                QuerySettings::default(),
+                true
+            )) {
+                Ok(r) => r.status(),
+                Err(e) => e.status(),
+            },
+            expected_status
+        );
+    }
+
+    fn exec_sled(request: Request, store: SledStore, expected_status: StatusCode) {
+        assert_eq!(
+            match block_on(handle_request(
+                request,
+                store,
+                QuerySettings::default(),
+                false
+            )) {
+                Ok(r) => r.status(),
+                Err(e) => e.status(),
+            },
+            expected_status
+        );
+    }
 }
@@ -0,0 +1,61 @@
+use crate::files::{load_store, read_file_to_string};
+use crate::manifest::Test;
+use crate::report::TestResult;
+use chrono::Utc;
+use oxigraph::model::canonicalization::to_canonical_n_quads;
+use oxigraph::model::Dataset;
+use oxigraph::{Error, Result};
+
+pub fn evaluate_canonicalization_tests(
+    manifest: impl Iterator<Item = Result<Test>>,
+) -> Result<Vec<TestResult>> {
+    manifest
+        .map(|test| {
+            let test = test?;
+            let outcome = evaluate_canonicalization_test(&test);
+            Ok(TestResult {
+                test: test.id,
+                outcome,
+                date: Utc::now(),
+            })
+        })
+        .collect()
+}
+
+fn evaluate_canonicalization_test(test: &Test) -> Result<()> {
+    let action = test
+        .action
+        .as_deref()
+        .ok_or_else(|| Error::msg(format!("No action found for test {}", test)))?;
+    if test.kind == "https://w3c.github.io/rdf-canon/tests/vocab#RDFC10EvalTest" {
+        let result = test
+            .result
+            .as_deref()
+            .ok_or_else(|| Error::msg(format!("No tests result found for test {}", test)))?;
+        let expected = read_file_to_string(result)?;
+        let actual = to_canonical_n_quads(&load_dataset(action)?)?;
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(Error::msg(format!(
+                "The canonical forms are not the same. Expected:\n{}\nActual:\n{}",
+                expected, actual
+            )))
+        }
+    } else if test.kind == "https://w3c.github.io/rdf-canon/tests/vocab#RDFC10NegativeEvalTest" {
+        match to_canonical_n_quads(&load_dataset(action)?) {
+            Ok(_) => Err(Error::msg(
+                "The dataset has been canonicalized even if it should not",
+            )),
+            Err(_) => Ok(()),
+        }
+    } else {
+        Err(Error::msg(format!("Unsupported test type: {}", test.kind)))
+    }
+}
+
+fn load_dataset(url: &str) -> Result<Dataset> {
+    Ok(load_store(url)?
+        .quads_for_pattern(None, None, None, None)
+        .collect())
+}
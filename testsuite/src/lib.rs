@@ -10,6 +10,7 @@
     unused_qualifications
 )]
 
+pub mod canonicalization_evaluator;
 pub mod files;
 pub mod manifest;
 pub mod parser_evaluator;
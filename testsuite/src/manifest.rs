@@ -12,10 +12,12 @@ pub struct Test {
     pub comment: Option<String>,
     pub action: Option<String>,
     pub query: Option<String>,
+    pub update: Option<String>,
     pub data: Option<String>,
-    pub graph_data: Vec<String>,
+    pub graph_data: Vec<(NamedNode, String)>,
     pub service_data: Vec<(String, String)>,
     pub result: Option<String>,
+    pub result_graph_data: Vec<(NamedNode, String)>,
 }
 
 impl fmt::Display for Test {
@@ -33,15 +35,21 @@ impl fmt::Display for Test {
         if let Some(query) = &self.query {
             write!(f, " on query {}", &query)?;
         }
+        if let Some(update) = &self.update {
+            write!(f, " on update {}", &update)?;
+        }
         for data in &self.data {
             write!(f, " with data {}", data)?;
         }
-        for data in &self.graph_data {
+        for (_, data) in &self.graph_data {
             write!(f, " and graph data {}", data)?;
         }
         for result in &self.result {
             write!(f, " and expected result {}", result)?;
         }
+        for (_, data) in &self.result_graph_data {
+            write!(f, " and expected graph data {}", data)?;
+        }
         Ok(())
     }
 }
@@ -71,7 +79,7 @@ impl Iterator for TestManifest {
     fn next(&mut self) -> Option<Result<Test>> {
         match self.tests_to_do.pop() {
             Some(Term::NamedNode(test_node)) => {
-                let test_subject = NamedOrBlankNode::from(test_node.clone());
+                let test_subject = Subject::from(test_node.clone());
                 let kind =
                     match object_for_subject_predicate(&self.graph, &test_subject, &rdf::TYPE) {
                         Some(Term::NamedNode(c)) => c,
@@ -90,10 +98,10 @@ impl Iterator for TestManifest {
                     Some(Term::Literal(c)) => Some(c.value().to_string()),
                     _ => None,
                 };
-                let (action, query, data, graph_data, service_data) =
+                let (action, query, update, data, graph_data, service_data) =
                     match object_for_subject_predicate(&self.graph, &test_subject, &*mf::ACTION) {
                         Some(Term::NamedNode(n)) => {
-                            (Some(n.into_string()), None, None, vec![], vec![])
+                            (Some(n.into_string()), None, None, None, vec![], vec![])
                         }
                         Some(Term::BlankNode(n)) => {
                             let n = n.into();
@@ -102,18 +110,27 @@ impl Iterator for TestManifest {
                                     Some(Term::NamedNode(q)) => Some(q.into_string()),
                                     _ => None,
                                 };
+                            let update =
+                                match object_for_subject_predicate(&self.graph, &n, &ut::REQUEST) {
+                                    Some(Term::NamedNode(q)) => Some(q.into_string()),
+                                    _ => None,
+                                };
                             let data =
-                                match object_for_subject_predicate(&self.graph, &n, &qt::DATA) {
+                                match object_for_subject_predicate(&self.graph, &n, &qt::DATA)
+                                    .or_else(|| {
+                                        object_for_subject_predicate(&self.graph, &n, &ut::DATA)
+                                    }) {
                                     Some(Term::NamedNode(q)) => Some(q.into_string()),
                                     _ => None,
                                 };
-                            let graph_data =
+                            let mut graph_data: Vec<_> =
                                 objects_for_subject_predicate(&self.graph, &n, &qt::GRAPH_DATA)
                                     .filter_map(|g| match g {
-                                        Term::NamedNode(q) => Some(q.into_string()),
+                                        Term::NamedNode(q) => Some((q.clone(), q.into_string())),
                                         _ => None,
                                     })
                                     .collect();
+                            graph_data.extend(update_graph_data(&self.graph, &n));
                             let service_data =
                                 objects_for_subject_predicate(&self.graph, &n, &qt::SERVICE_DATA)
                                     .filter_map(|g| match g {
@@ -143,7 +160,7 @@ impl Iterator for TestManifest {
                                         }
                                     })
                                     .collect();
-                            (None, query, data, graph_data, service_data)
+                            (None, query, update, data, graph_data, service_data)
                         }
                         Some(_) => return Some(Err(Error::msg("invalid action"))),
                         None => {
@@ -153,11 +170,21 @@ impl Iterator for TestManifest {
                             ))));
                         }
                     };
-                let result =
+                let (result, result_graph_data) =
                     match object_for_subject_predicate(&self.graph, &test_subject, &*mf::RESULT) {
-                        Some(Term::NamedNode(n)) => Some(n.into_string()),
+                        Some(Term::NamedNode(n)) => (Some(n.into_string()), vec![]),
+                        Some(Term::BlankNode(n)) => {
+                            // The expected dataset of an update evaluation test
+                            let n = n.into();
+                            let data =
+                                match object_for_subject_predicate(&self.graph, &n, &ut::DATA) {
+                                    Some(Term::NamedNode(q)) => Some(q.into_string()),
+                                    _ => None,
+                                };
+                            (data, update_graph_data(&self.graph, &n))
+                        }
                         Some(_) => return Some(Err(Error::msg("invalid result"))),
-                        None => None,
+                        None => (None, vec![]),
                     };
                 Some(Ok(Test {
                     id: test_node,
@@ -166,17 +193,19 @@ impl Iterator for TestManifest {
                     comment,
                     action,
                     query,
+                    update,
                     data,
                     graph_data,
                     service_data,
                     result,
+                    result_graph_data,
                 }))
             }
             Some(_) => self.next(),
             None => {
                 match self.manifests_to_do.pop() {
                     Some(url) => {
-                        let manifest = NamedOrBlankNode::from(NamedNode::new(url.clone()).unwrap());
+                        let manifest = Subject::from(NamedNode::new(url.clone()).unwrap());
                         if let Err(error) =
                             load_to_store(&url, &self.graph, &&GraphName::DefaultGraph)
                         {
@@ -224,11 +253,11 @@ impl Iterator for TestManifest {
 
 struct RdfListIterator<'a> {
     graph: &'a MemoryStore,
-    current_node: Option<NamedOrBlankNode>,
+    current_node: Option<Subject>,
 }
 
 impl<'a> RdfListIterator<'a> {
-    fn iter(graph: &'a MemoryStore, root: NamedOrBlankNode) -> RdfListIterator<'a> {
+    fn iter(graph: &'a MemoryStore, root: Subject) -> RdfListIterator<'a> {
         RdfListIterator {
             graph,
             current_node: Some(root),
@@ -257,9 +286,30 @@ impl<'a> Iterator for RdfListIterator<'a> {
     }
 }
 
+/// The named graphs of an update test, given as `ut:graphData [ ut:graph <file> ; rdfs:label "name" ]`
+fn update_graph_data(store: &MemoryStore, node: &Subject) -> Vec<(NamedNode, String)> {
+    objects_for_subject_predicate(store, node, &ut::GRAPH_DATA)
+        .filter_map(|g| {
+            let g = match g {
+                Term::NamedNode(g) => g.into(),
+                Term::BlankNode(g) => g.into(),
+                _ => return None,
+            };
+            if let (Some(Term::NamedNode(data)), Some(Term::Literal(name))) = (
+                object_for_subject_predicate(store, &g, &ut::GRAPH),
+                object_for_subject_predicate(store, &g, &rdfs::LABEL),
+            ) {
+                Some((NamedNode::new(name.value()).ok()?, data.into_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
 fn object_for_subject_predicate(
     store: &MemoryStore,
-    subject: &NamedOrBlankNode,
+    subject: &Subject,
     predicate: &NamedNode,
 ) -> Option<Term> {
     objects_for_subject_predicate(store, subject, predicate).next()
@@ -267,7 +317,7 @@ fn object_for_subject_predicate(
 
 fn objects_for_subject_predicate(
     store: &MemoryStore,
-    subject: &NamedOrBlankNode,
+    subject: &Subject,
     predicate: &NamedNode,
 ) -> impl Iterator<Item = Term> {
     store
@@ -68,6 +68,88 @@ fn evaluate_sparql_test(test: &Test) -> Result<()> {
             ))),
             Err(_) => Ok(()),
         }
+    } else if test.kind
+        == "http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#PositiveUpdateSyntaxTest11"
+    {
+        let update_file = test
+            .action
+            .as_deref()
+            .ok_or_else(|| Error::msg(format!("No action found for test {}", test)))?;
+        match Update::parse(&read_file_to_string(&update_file)?, Some(&update_file)) {
+            Err(error) => Err(Error::msg(format!(
+                "Not able to parse {} with error: {}",
+                test, error
+            ))),
+            Ok(update) => match Update::parse(&update.to_string(), None) {
+                Ok(_) => Ok(()),
+                Err(error) => Err(Error::msg(format!(
+                    "Failure to deserialize \"{}\" of {} with error: {}",
+                    update.to_string(),
+                    test,
+                    error
+                ))),
+            },
+        }
+    } else if test.kind
+        == "http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#NegativeUpdateSyntaxTest11"
+    {
+        let update_file = test
+            .action
+            .as_deref()
+            .ok_or_else(|| Error::msg(format!("No action found for test {}", test)))?;
+        match Update::parse(&read_file_to_string(update_file)?, Some(update_file)) {
+            Ok(result) => Err(Error::msg(format!(
+                "Oxigraph parses even if it should not {}. The output tree is: {}",
+                test, result
+            ))),
+            Err(_) => Ok(()),
+        }
+    } else if test.kind
+        == "http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#UpdateEvaluationTest"
+    {
+        let actual_store = MemoryStore::new();
+        if let Some(data) = &test.data {
+            load_to_store(data, &actual_store, &GraphName::DefaultGraph)?;
+        }
+        for (name, data) in &test.graph_data {
+            load_to_store(data, &actual_store, &name.clone().into())?;
+        }
+        let expected_store = MemoryStore::new();
+        if let Some(data) = &test.result {
+            load_to_store(data, &expected_store, &GraphName::DefaultGraph)?;
+        }
+        for (name, data) in &test.result_graph_data {
+            load_to_store(data, &expected_store, &name.clone().into())?;
+        }
+        let update_file = test
+            .update
+            .as_deref()
+            .ok_or_else(|| Error::msg(format!("No action found for test {}", test)))?;
+        match actual_store.prepare_update(
+            &read_file_to_string(update_file)?,
+            QueryOptions::default().with_base_iri(update_file),
+        ) {
+            Err(error) => Err(Error::msg(format!(
+                "Failure to parse update of {} with error: {}",
+                test, error
+            ))),
+            Ok(update) => match update.exec() {
+                Err(error) => Err(Error::msg(format!(
+                    "Failure to execute update of {} with error: {}",
+                    test, error
+                ))),
+                Ok(()) => {
+                    if expected_store.is_isomorphic(&actual_store) {
+                        Ok(())
+                    } else {
+                        Err(Error::msg(format!(
+                            "Failure on {}.\nExpected file:\n{}\nOutput file:\n{}\n",
+                            test, expected_store, actual_store
+                        )))
+                    }
+                }
+            },
+        }
     } else if test.kind
         == "http://www.w3.org/2001/sw/DataAccess/tests/test-manifest#QueryEvaluationTest"
     {
@@ -75,8 +157,8 @@ fn evaluate_sparql_test(test: &Test) -> Result<()> {
         if let Some(data) = &test.data {
             load_to_store(data, &store, &GraphName::DefaultGraph)?;
         }
-        for graph_data in &test.graph_data {
-            load_to_store(&graph_data, &store, &NamedNode::new(graph_data)?.into())?;
+        for (name, data) in &test.graph_data {
+            load_to_store(data, &store, &name.clone().into())?;
         }
         let query_file = test
             .query
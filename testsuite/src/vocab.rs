@@ -51,6 +51,22 @@ pub mod mf {
     }
 }
 
+pub mod ut {
+    use lazy_static::lazy_static;
+    use oxigraph::model::NamedNode;
+
+    lazy_static! {
+        pub static ref REQUEST: NamedNode =
+            NamedNode::new("http://www.w3.org/2009/sparql/tests/test-update#request").unwrap();
+        pub static ref DATA: NamedNode =
+            NamedNode::new("http://www.w3.org/2009/sparql/tests/test-update#data").unwrap();
+        pub static ref GRAPH_DATA: NamedNode =
+            NamedNode::new("http://www.w3.org/2009/sparql/tests/test-update#graphData").unwrap();
+        pub static ref GRAPH: NamedNode =
+            NamedNode::new("http://www.w3.org/2009/sparql/tests/test-update#graph").unwrap();
+    }
+}
+
 pub mod qt {
     use lazy_static::lazy_static;
     use oxigraph::model::NamedNode;
@@ -0,0 +1,25 @@
+use oxigraph::Result;
+use oxigraph_testsuite::canonicalization_evaluator::evaluate_canonicalization_tests;
+use oxigraph_testsuite::manifest::TestManifest;
+
+#[test]
+fn rdf_canon_w3c_testsuite() -> Result<()> {
+    // The map tests use the blank node labels of the input files that are not kept by the parsers
+    let manifest = TestManifest::new(vec!["https://w3c.github.io/rdf-canon/tests/manifest.ttl"])
+        .filter(|test| {
+            test.as_ref().map_or(true, |test| {
+                test.kind != "https://w3c.github.io/rdf-canon/tests/vocab#RDFC10MapTest"
+            })
+        });
+    let results = evaluate_canonicalization_tests(manifest)?;
+
+    let mut errors = Vec::default();
+    for result in results {
+        if let Err(error) = &result.outcome {
+            errors.push(format!("{}: failed with error {}", result.test, error))
+        }
+    }
+
+    assert!(errors.is_empty(), "\n{}\n", errors.join("\n"));
+    Ok(())
+}
@@ -115,11 +115,17 @@ fn sparql11_query_w3c_evaluation_testsuite() -> Result<()> {
         "http://www.w3.org/2009/sparql/docs/tests/data-sparql11/construct/manifest#constructwhere04",
         //BNODE() scope is currently wrong
         "http://www.w3.org/2009/sparql/docs/tests/data-sparql11/functions/manifest#bnode01",
-        //Property path with unbound graph name are not supported yet
-        "http://www.w3.org/2009/sparql/docs/tests/data-sparql11/property-path/manifest#pp35",
         //SERVICE name from a BGP
         "http://www.w3.org/2009/sparql/docs/tests/data-sparql11/service/manifest#service5"
     ];
 
     run_testsuite(manifest_urls, test_blacklist)
 }
+
+#[test]
+fn sparql11_update_w3c_evaluation_testsuite() -> Result<()> {
+    let manifest_urls =
+        vec!["http://www.w3.org/2009/sparql/docs/tests/data-sparql11/manifest-sparql11-update.ttl"];
+
+    run_testsuite(manifest_urls, vec![])
+}
@@ -201,6 +201,8 @@ async fn evaluate_sparql_query(
                 &[
                     QueryResultSyntax::Xml.media_type(),
                     QueryResultSyntax::Json.media_type(),
+                    QueryResultSyntax::Csv.media_type(),
+                    QueryResultSyntax::Tsv.media_type(),
                 ],
             )?;
             let mut response = Response::from(results.write(Vec::default(), format)?);